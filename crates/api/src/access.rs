@@ -0,0 +1,292 @@
+//! Caller roles and what they may see of the read models.
+//!
+//! The API doesn't authenticate callers itself: a gateway in front of it
+//! does, and passes the caller's role in `x-principal-role` and, for
+//! customers, their ID in `x-principal-customer-id`. Each role is a
+//! [`RowPolicy`] over the views' rows:
+//!
+//! - `customer` sees only their own orders
+//! - `operations` sees every order
+//! - `finance` sees every order, with customer IDs replaced by stable
+//!   pseudonyms so totals can still be grouped per customer
+//!
+//! Requests without a role are refused with 401. Pseudonyms are an HMAC of
+//! the customer ID under a server secret, a [`PseudonymKey`], so they can't
+//! be linked back to customers without it.
+//!
+//! Staff routes are wrapped in [`require_role`], which refuses callers of
+//! other roles with 403.
+//!
+//! When OIDC is configured the headers are ignored: callers present a
+//! bearer token instead, and [`crate::oidc`] maps its claims to a
+//! [`Principal`] before the handler runs.
+
+use std::str::FromStr;
+use std::sync::Arc;
+
+use axum::extract::{FromRequestParts, Request, State};
+use axum::http::request::Parts;
+use axum::middleware::Next;
+use axum::response::Response;
+use domain::CustomerId;
+use event_store::Audience;
+use hmac::{Hmac, Mac};
+use projections::RowPolicy;
+use projections::views::current_orders::CurrentOrderSummary;
+use projections::views::order_history::OrderHistorySummary;
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::error::ApiError;
+
+/// Header carrying the caller's role, set by the authenticating gateway.
+pub const ROLE_HEADER: &str = "x-principal-role";
+
+/// Header carrying a customer caller's ID, set by the authenticating
+/// gateway.
+pub const CUSTOMER_HEADER: &str = "x-principal-customer-id";
+
+/// Secret keying the pseudonyms finance sees in place of customer IDs.
+///
+/// Every replica should share one key, so a customer's pseudonym is the same
+/// wherever and whenever it is read.
+#[derive(Clone)]
+pub struct PseudonymKey(Arc<[u8]>);
+
+impl PseudonymKey {
+    /// Creates a key from a server secret.
+    pub fn new(secret: impl AsRef<[u8]>) -> Self {
+        Self(secret.as_ref().into())
+    }
+
+    /// Creates a random key; pseudonyms then change when the process
+    /// restarts.
+    pub fn random() -> Self {
+        let mut secret = Uuid::new_v4().as_bytes().to_vec();
+        secret.extend_from_slice(Uuid::new_v4().as_bytes());
+        Self::new(secret)
+    }
+
+    /// Returns the pseudonym for `customer_id`.
+    pub fn pseudonym(&self, customer_id: CustomerId) -> CustomerId {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.0).expect("HMAC accepts keys of any length");
+        mac.update(customer_id.as_uuid().as_bytes());
+        let digest = mac.finalize().into_bytes();
+        let mut bytes = [0; 16];
+        bytes.copy_from_slice(&digest[..16]);
+        CustomerId::from_uuid(uuid::Builder::from_custom_bytes(bytes).into_uuid())
+    }
+}
+
+impl std::fmt::Debug for PseudonymKey {
+    // Keeps the secret out of logs
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("PseudonymKey(<redacted>)")
+    }
+}
+
+/// A caller's role, as named in `x-principal-role` and in OIDC role
+/// mappings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Role {
+    Customer,
+    Finance,
+    Operations,
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "customer" => Ok(Role::Customer),
+            "operations" => Ok(Role::Operations),
+            "finance" => Ok(Role::Finance),
+            other => Err(format!("Unknown role {other:?}")),
+        }
+    }
+}
+
+/// The caller a read is made for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Principal {
+    /// A customer, limited to their own orders.
+    Customer(CustomerId),
+    /// Operations staff, who see everything.
+    Operations,
+    /// Finance staff, who see every order but not who placed it.
+    Finance,
+}
+
+impl Principal {
+    /// Returns this caller's policy over read model rows, masking customer
+    /// IDs with `pseudonyms` for finance.
+    pub fn rows(self, pseudonyms: &PseudonymKey) -> RowAccess<'_> {
+        RowAccess {
+            principal: self,
+            pseudonyms,
+        }
+    }
+
+    /// Returns the caller's role.
+    pub fn role(&self) -> Role {
+        match self {
+            Principal::Customer(_) => Role::Customer,
+            Principal::Operations => Role::Operations,
+            Principal::Finance => Role::Finance,
+        }
+    }
+
+    /// Returns which streams this caller may read events of: customers
+    /// only `normal` ones, staff `internal` ones too.
+    pub fn audience(&self) -> Audience {
+        match self {
+            Principal::Customer(_) => Audience::Consumer,
+            Principal::Operations | Principal::Finance => Audience::Internal,
+        }
+    }
+
+    /// Returns true if the caller may see `customer_id`'s data: staff may
+    /// see anyone's, customers only their own.
+    pub fn permits_customer(&self, customer_id: CustomerId) -> bool {
+        match self {
+            Principal::Customer(caller) => *caller == customer_id,
+            Principal::Operations | Principal::Finance => true,
+        }
+    }
+
+    /// Refuses callers other than operations and the customer themselves
+    /// with 403, e.g. for reading or changing a customer's contact info.
+    pub fn check_acts_for(&self, customer_id: CustomerId) -> Result<(), ApiError> {
+        match self {
+            Principal::Operations => Ok(()),
+            Principal::Customer(caller) if *caller == customer_id => Ok(()),
+            _ => Err(ApiError::Forbidden(format!(
+                "Only operations or customer {customer_id} may do this"
+            ))),
+        }
+    }
+
+    /// Refuses a customer acting on another customer's data with 403.
+    pub fn check_customer(&self, customer_id: CustomerId) -> Result<(), ApiError> {
+        if self.permits_customer(customer_id) {
+            Ok(())
+        } else {
+            Err(ApiError::Forbidden(format!(
+                "Customer {customer_id} is not the caller"
+            )))
+        }
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for Principal {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        // Set from a verified bearer token when OIDC is configured
+        if let Some(principal) = parts.extensions.get::<Principal>() {
+            return Ok(*principal);
+        }
+        let header = |name| {
+            parts
+                .headers
+                .get(name)
+                .map(|value| {
+                    value
+                        .to_str()
+                        .map_err(|_| ApiError::Unauthorized(format!("Invalid {name} header")))
+                })
+                .transpose()
+        };
+        let role = header(ROLE_HEADER)?
+            .ok_or_else(|| ApiError::Unauthorized(format!("Missing {ROLE_HEADER} header")))?
+            .parse()
+            .map_err(ApiError::Unauthorized)?;
+        match role {
+            Role::Operations => Ok(Principal::Operations),
+            Role::Finance => Ok(Principal::Finance),
+            Role::Customer => {
+                let id = header(CUSTOMER_HEADER)?.ok_or_else(|| {
+                    ApiError::Unauthorized(format!("Missing {CUSTOMER_HEADER} header"))
+                })?;
+                let uuid = Uuid::parse_str(id).map_err(|_| {
+                    ApiError::Unauthorized(format!("Invalid {CUSTOMER_HEADER} header"))
+                })?;
+                Ok(Principal::Customer(CustomerId::from_uuid(uuid)))
+            }
+        }
+    }
+}
+
+/// Middleware refusing callers whose role isn't one of `roles` with `403
+/// Forbidden`.
+pub async fn require_role(
+    State(roles): State<&'static [Role]>,
+    principal: Principal,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    if !roles.contains(&principal.role()) {
+        metrics::counter!("requests_forbidden").increment(1);
+        return Err(ApiError::Forbidden(format!(
+            "{:?} callers may not use this route",
+            principal.role()
+        )));
+    }
+    Ok(next.run(request).await)
+}
+
+/// A caller's [`RowPolicy`] over the views' rows.
+pub struct RowAccess<'a> {
+    principal: Principal,
+    pseudonyms: &'a PseudonymKey,
+}
+
+impl RowAccess<'_> {
+    fn mask_customer(&self, customer_id: &mut CustomerId) {
+        if self.principal == Principal::Finance {
+            *customer_id = self.pseudonyms.pseudonym(*customer_id);
+        }
+    }
+}
+
+impl RowPolicy<CurrentOrderSummary> for RowAccess<'_> {
+    fn permits(&self, row: &CurrentOrderSummary) -> bool {
+        self.principal.permits_customer(row.customer_id)
+    }
+
+    fn mask(&self, row: &mut CurrentOrderSummary) {
+        self.mask_customer(&mut row.customer_id);
+    }
+}
+
+impl RowPolicy<OrderHistorySummary> for RowAccess<'_> {
+    fn permits(&self, row: &OrderHistorySummary) -> bool {
+        self.principal.permits_customer(row.customer_id)
+    }
+
+    fn mask(&self, row: &mut OrderHistorySummary) {
+        self.mask_customer(&mut row.customer_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pseudonyms_depend_on_the_key() {
+        let customer_id = CustomerId::new();
+        let key = PseudonymKey::new("server-secret");
+
+        assert_eq!(key.pseudonym(customer_id), key.pseudonym(customer_id));
+        assert_ne!(key.pseudonym(customer_id), customer_id);
+        assert_ne!(
+            key.pseudonym(customer_id),
+            PseudonymKey::new("other-secret").pseudonym(customer_id)
+        );
+        assert_ne!(key.pseudonym(customer_id), key.pseudonym(CustomerId::new()));
+        assert!(!format!("{key:?}").contains("server-secret"));
+    }
+}
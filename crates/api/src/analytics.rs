@@ -0,0 +1,309 @@
+//! Parquet export of order history for offline analytics.
+//!
+//! Each export replays the event log into a fresh [`OrderHistoryView`], so it
+//! covers every finished order regardless of which views the server runs or
+//! how many entries they keep in memory, and writes one row per order.
+//!
+//! Files are laid out as `<dir>/order_history/v<N>/order_history-<time>.parquet`
+//! where `N` is [`ORDER_HISTORY_SCHEMA_VERSION`], also stored in the file's
+//! key-value metadata. Within a version, columns are only ever appended and
+//! new ones are nullable, so a warehouse table can load every file of that
+//! version by column name. Renaming, removing, or retyping a column bumps the
+//! version, which starts a new directory. Files are written under a temporary
+//! name and renamed into place, so loaders never see a partial file.
+//!
+//! Events moved to an archive by compaction are not part of the live log and
+//! are not included.
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use arrow_array::builder::{ListBuilder, StringBuilder};
+use arrow_array::{
+    ArrayRef, Int64Array, RecordBatch, StringArray, TimestampMicrosecondArray, UInt64Array,
+};
+use arrow_schema::{ArrowError, DataType, Field, Schema, TimeUnit};
+use chrono::{DateTime, Utc};
+use event_store::EventStore;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::errors::ParquetError;
+use parquet::file::properties::WriterProperties;
+use parquet::format::KeyValue;
+use projections::views::order_history::OrderHistorySummary;
+use projections::{OrderHistoryView, ProjectionError, ProjectionProcessor};
+use serde::Serialize;
+use thiserror::Error;
+
+/// Version of the order history file schema.
+///
+/// Bump it when a column is renamed, removed, or changes type; appending a
+/// nullable column doesn't need a bump.
+pub const ORDER_HISTORY_SCHEMA_VERSION: u32 = 1;
+
+/// Key under which the schema version is stored in each file's metadata.
+pub const SCHEMA_VERSION_KEY: &str = "schema_version";
+
+/// Errors from writing an export.
+#[derive(Debug, Error)]
+pub enum ParquetExportError {
+    #[error("replaying order history failed: {0}")]
+    Projection(#[from] ProjectionError),
+
+    #[error("building record batch failed: {0}")]
+    Arrow(#[from] ArrowError),
+
+    #[error("writing parquet failed: {0}")]
+    Parquet(#[from] ParquetError),
+
+    #[error("export file I/O failed: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// A written export file.
+#[derive(Debug, Clone, Serialize)]
+pub struct ParquetExport {
+    pub path: PathBuf,
+    pub rows: usize,
+    pub schema_version: u32,
+    pub exported_at: DateTime<Utc>,
+}
+
+/// Writes order history exports into a directory.
+#[derive(Debug, Clone)]
+pub struct ParquetExporter {
+    dir: PathBuf,
+}
+
+impl ParquetExporter {
+    /// Creates an exporter writing under `dir`.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Returns the directory exports are written under.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Replays `store` and writes every finished order to a new file.
+    #[tracing::instrument(skip(self, store))]
+    pub async fn export_order_history<S: EventStore + Clone + 'static>(
+        &self,
+        store: &S,
+    ) -> Result<ParquetExport, ParquetExportError> {
+        let history = OrderHistoryView::new();
+        let mut processor = ProjectionProcessor::new(store.clone());
+        processor.register(Box::new(history.clone()));
+        processor.run_catch_up().await?;
+
+        let mut orders = history.get_all_history().await;
+        orders.sort_by_key(|order| (order.finished_at(), order.order_id.as_uuid()));
+
+        let exported_at = Utc::now();
+        let dir = self
+            .dir
+            .join("order_history")
+            .join(format!("v{ORDER_HISTORY_SCHEMA_VERSION}"));
+        let path = dir.join(format!(
+            "order_history-{}.parquet",
+            exported_at.format("%Y%m%dT%H%M%S%.6fZ")
+        ));
+        let rows = orders.len();
+
+        let target = path.clone();
+        tokio::task::spawn_blocking(move || write_order_history(&dir, &target, &orders))
+            .await
+            .map_err(std::io::Error::other)??;
+
+        metrics::counter!("parquet_exports").increment(1);
+        metrics::counter!("parquet_export_rows").increment(rows as u64);
+        tracing::info!(path = %path.display(), rows, "order history exported");
+        Ok(ParquetExport {
+            path,
+            rows,
+            schema_version: ORDER_HISTORY_SCHEMA_VERSION,
+            exported_at,
+        })
+    }
+}
+
+/// Returns the Arrow schema of order history files.
+pub fn order_history_schema() -> Schema {
+    let timestamp = DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into()));
+    Schema::new(vec![
+        Field::new("order_id", DataType::Utf8, false),
+        Field::new("customer_id", DataType::Utf8, false),
+        Field::new("state", DataType::Utf8, false),
+        Field::new("item_count", DataType::UInt64, false),
+        Field::new("total_cents", DataType::Int64, false),
+        Field::new("created_at", timestamp.clone(), false),
+        Field::new("completed_at", timestamp.clone(), true),
+        Field::new("cancelled_at", timestamp.clone(), true),
+        Field::new("delivered_at", timestamp, true),
+        Field::new("carrier", DataType::Utf8, true),
+        Field::new("tracking_number", DataType::Utf8, true),
+        Field::new("last_location", DataType::Utf8, true),
+        Field::new("cancellation_reason", DataType::Utf8, true),
+        Field::new("delivery_failure_reason", DataType::Utf8, true),
+        Field::new(
+            "tags",
+            DataType::List(Arc::new(Field::new("item", DataType::Utf8, true))),
+            false,
+        ),
+        // JSON object of the order's metadata attachments
+        Field::new("metadata", DataType::Utf8, false),
+    ])
+}
+
+/// Writes `orders` to `path` through a temporary file in `dir`.
+fn write_order_history(
+    dir: &Path,
+    path: &Path,
+    orders: &[OrderHistorySummary],
+) -> Result<(), ParquetExportError> {
+    let schema = Arc::new(order_history_schema());
+    let batch = RecordBatch::try_new(schema.clone(), order_history_columns(orders))?;
+
+    std::fs::create_dir_all(dir)?;
+    let partial = path.with_extension("parquet.tmp");
+    let props = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .set_key_value_metadata(Some(vec![KeyValue::new(
+            SCHEMA_VERSION_KEY.to_string(),
+            ORDER_HISTORY_SCHEMA_VERSION.to_string(),
+        )]))
+        .build();
+    let mut writer = ArrowWriter::try_new(File::create(&partial)?, schema, Some(props))?;
+    writer.write(&batch)?;
+    writer.close()?;
+    std::fs::rename(&partial, path)?;
+    Ok(())
+}
+
+/// Builds the columns of [`order_history_schema`], in order.
+fn order_history_columns(orders: &[OrderHistorySummary]) -> Vec<ArrayRef> {
+    let strings = |f: fn(&OrderHistorySummary) -> Option<String>| -> ArrayRef {
+        Arc::new(orders.iter().map(f).collect::<StringArray>())
+    };
+    let times = |f: fn(&OrderHistorySummary) -> Option<DateTime<Utc>>| -> ArrayRef {
+        Arc::new(
+            orders
+                .iter()
+                .map(|o| f(o).map(|t| t.timestamp_micros()))
+                .collect::<TimestampMicrosecondArray>()
+                .with_timezone("UTC"),
+        )
+    };
+
+    let mut tags = ListBuilder::new(StringBuilder::new());
+    for order in orders {
+        for tag in &order.tags {
+            tags.values().append_value(tag);
+        }
+        tags.append(true);
+    }
+
+    vec![
+        strings(|o| Some(o.order_id.to_string())),
+        strings(|o| Some(o.customer_id.to_string())),
+        strings(|o| Some(o.state.to_string())),
+        Arc::new(
+            orders
+                .iter()
+                .map(|o| o.item_count as u64)
+                .collect::<UInt64Array>(),
+        ),
+        Arc::new(
+            orders
+                .iter()
+                .map(|o| o.total_amount.cents())
+                .collect::<Int64Array>(),
+        ),
+        times(|o| Some(o.created_at)),
+        times(|o| o.completed_at),
+        times(|o| o.cancelled_at),
+        times(|o| o.delivered_at),
+        strings(|o| o.carrier.clone()),
+        strings(|o| o.tracking_number.clone()),
+        strings(|o| o.last_location.clone()),
+        strings(|o| o.cancellation_reason.clone()),
+        strings(|o| o.delivery_failure_reason.clone()),
+        Arc::new(tags.finish()),
+        strings(|o| Some(serde_json::to_string(&o.metadata).unwrap_or_default())),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use domain::{CancelOrder, CreateOrder, CustomerId, OrderService, SetOrderMetadata};
+    use event_store::InMemoryEventStore;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    #[tokio::test]
+    async fn test_export_writes_versioned_file() {
+        let store = InMemoryEventStore::new();
+        let service = OrderService::new(store.clone());
+        let cmd = CreateOrder::for_customer(CustomerId::new());
+        let order_id = cmd.order_id;
+        service.create_order(cmd).await.unwrap();
+        service
+            .set_metadata(SetOrderMetadata::new(order_id, "po_number", "PO-7"))
+            .await
+            .unwrap();
+        service
+            .cancel_order(CancelOrder::new(order_id, "Customer request", None))
+            .await
+            .unwrap();
+        // Still a draft, so not part of history
+        service
+            .create_order(CreateOrder::for_customer(CustomerId::new()))
+            .await
+            .unwrap();
+
+        let dir = std::env::temp_dir().join(format!("parquet-export-{}", uuid::Uuid::new_v4()));
+        let export = ParquetExporter::new(&dir)
+            .export_order_history(&store)
+            .await
+            .unwrap();
+        assert_eq!(export.rows, 1);
+        assert!(
+            export
+                .path
+                .starts_with(dir.join("order_history").join("v1"))
+        );
+
+        let builder =
+            ParquetRecordBatchReaderBuilder::try_new(File::open(&export.path).unwrap()).unwrap();
+        let version = builder
+            .metadata()
+            .file_metadata()
+            .key_value_metadata()
+            .unwrap()
+            .iter()
+            .find(|kv| kv.key == SCHEMA_VERSION_KEY)
+            .and_then(|kv| kv.value.clone());
+        assert_eq!(version.as_deref(), Some("1"));
+
+        let batch = builder.build().unwrap().next().unwrap().unwrap();
+        assert_eq!(batch.num_rows(), 1);
+        let column = |name: &str| {
+            batch
+                .column_by_name(name)
+                .unwrap()
+                .as_any()
+                .downcast_ref::<StringArray>()
+                .unwrap()
+                .value(0)
+                .to_string()
+        };
+        assert_eq!(column("order_id"), order_id.to_string());
+        assert_eq!(column("state"), "Cancelled");
+        assert_eq!(column("cancellation_reason"), "Customer request");
+        assert_eq!(column("metadata"), r#"{"po_number":"PO-7"}"#);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
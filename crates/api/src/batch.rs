@@ -0,0 +1,244 @@
+//! Batched saga fulfillment for campaigns.
+//!
+//! A batch fulfills many orders at once, e.g. every draft order tagged for a
+//! flash sale, in place of one fulfill call per order. Its orders are queued
+//! and fulfilled in the background, at most `concurrency` at a time; each
+//! order's saga runs exactly as `POST /orders/:id/fulfill` would run it, so
+//! one order failing doesn't stop the others.
+//!
+//! Batches and their progress are held in memory until the process
+//! restarts. Finished batches are dropped after [`DEFAULT_BATCH_RETENTION`],
+//! and only the newest [`DEFAULT_FINISHED_BATCHES`] of them are kept. The
+//! sagas themselves are stored as usual and outlive the batch.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use common::AggregateId;
+use saga::SagaState;
+use uuid::Uuid;
+
+/// Sagas a batch runs at once unless the request asks otherwise.
+pub const DEFAULT_BATCH_CONCURRENCY: usize = 8;
+
+/// Most sagas a batch may run at once.
+pub const MAX_BATCH_CONCURRENCY: usize = 64;
+
+/// Most orders a single batch may hold.
+pub const MAX_BATCH_ORDERS: usize = 10_000;
+
+/// How long a finished batch stays available by default.
+pub const DEFAULT_BATCH_RETENTION: Duration = Duration::from_secs(60 * 60);
+
+/// How many finished batches are kept by default.
+pub const DEFAULT_FINISHED_BATCHES: usize = 100;
+
+/// Progress of one order in a batch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BatchOrderStatus {
+    /// Waiting for a free slot.
+    Queued,
+    /// Its saga is being started or run.
+    Running,
+    /// Its saga was started; see the saga for how it ended.
+    Started { saga_id: AggregateId },
+    /// Its saga reached `state`, a terminal state, so it needn't be looked
+    /// up again.
+    Ended {
+        saga_id: AggregateId,
+        state: SagaState,
+    },
+    /// No saga could be started, e.g. because the order isn't a draft.
+    Failed(String),
+}
+
+/// An order in a batch.
+#[derive(Debug, Clone)]
+pub struct BatchOrder {
+    pub order_id: AggregateId,
+    pub status: BatchOrderStatus,
+}
+
+/// A requested fulfillment batch.
+#[derive(Debug, Clone)]
+pub struct FulfillmentBatch {
+    pub batch_id: Uuid,
+    pub requested_at: DateTime<Utc>,
+    /// Sagas run at once.
+    pub concurrency: usize,
+    /// Orders in the order they were requested.
+    pub orders: Vec<BatchOrder>,
+    /// When the last order left the queue and finished.
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// In-process registry of fulfillment batches.
+#[derive(Clone)]
+pub struct FulfillmentBatches {
+    batches: Arc<Mutex<HashMap<Uuid, FulfillmentBatch>>>,
+    retention: Duration,
+    finished_capacity: usize,
+}
+
+impl Default for FulfillmentBatches {
+    fn default() -> Self {
+        Self {
+            batches: Arc::default(),
+            retention: DEFAULT_BATCH_RETENTION,
+            finished_capacity: DEFAULT_FINISHED_BATCHES,
+        }
+    }
+}
+
+impl FulfillmentBatches {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Drops finished batches `retention` after they finished.
+    pub fn with_retention(mut self, retention: Duration) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    /// Keeps at most `capacity` finished batches, dropping the oldest first.
+    /// Running batches are always kept.
+    pub fn with_finished_capacity(mut self, capacity: usize) -> Self {
+        self.finished_capacity = capacity;
+        self
+    }
+
+    /// Registers a batch of `order_ids`, all queued.
+    pub fn start(&self, order_ids: Vec<AggregateId>, concurrency: usize) -> FulfillmentBatch {
+        let batch = FulfillmentBatch {
+            batch_id: Uuid::new_v4(),
+            requested_at: Utc::now(),
+            concurrency,
+            orders: order_ids
+                .into_iter()
+                .map(|order_id| BatchOrder {
+                    order_id,
+                    status: BatchOrderStatus::Queued,
+                })
+                .collect(),
+            finished_at: None,
+        };
+        self.batches
+            .lock()
+            .expect("fulfillment batches poisoned")
+            .insert(batch.batch_id, batch.clone());
+        batch
+    }
+
+    /// Records the progress of the order at `index` in a batch.
+    pub fn update(&self, batch_id: Uuid, index: usize, status: BatchOrderStatus) {
+        if let Some(order) = self
+            .batches
+            .lock()
+            .expect("fulfillment batches poisoned")
+            .get_mut(&batch_id)
+            .and_then(|batch| batch.orders.get_mut(index))
+        {
+            order.status = status;
+        }
+    }
+
+    /// Marks a batch finished, and drops finished batches that are past
+    /// their retention or over capacity.
+    pub fn finish(&self, batch_id: Uuid) {
+        let now = Utc::now();
+        let mut batches = self.batches.lock().expect("fulfillment batches poisoned");
+        if let Some(batch) = batches.get_mut(&batch_id) {
+            batch.finished_at = Some(now);
+        }
+
+        let cutoff =
+            now - chrono::Duration::from_std(self.retention).unwrap_or(chrono::Duration::MAX);
+        batches.retain(|_, batch| batch.finished_at.is_none_or(|at| at > cutoff));
+        let mut finished: Vec<_> = batches
+            .values()
+            .filter_map(|batch| batch.finished_at.map(|at| (at, batch.batch_id)))
+            .collect();
+        if finished.len() > self.finished_capacity {
+            finished.sort_unstable();
+            let excess = finished.len() - self.finished_capacity;
+            for (_, batch_id) in &finished[..excess] {
+                batches.remove(batch_id);
+            }
+        }
+    }
+
+    /// Returns a batch by ID.
+    pub fn get(&self, batch_id: Uuid) -> Option<FulfillmentBatch> {
+        self.batches
+            .lock()
+            .expect("fulfillment batches poisoned")
+            .get(&batch_id)
+            .cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch_tracks_each_order() {
+        let batches = FulfillmentBatches::new();
+        let (first, second) = (AggregateId::new(), AggregateId::new());
+        let batch = batches.start(vec![first, second], 2);
+        assert!(
+            batch
+                .orders
+                .iter()
+                .all(|o| o.status == BatchOrderStatus::Queued)
+        );
+
+        let saga_id = AggregateId::new();
+        batches.update(batch.batch_id, 0, BatchOrderStatus::Started { saga_id });
+        batches.update(
+            batch.batch_id,
+            1,
+            BatchOrderStatus::Failed("not a draft".to_string()),
+        );
+        // Out of range indexes and unknown batches are ignored
+        batches.update(batch.batch_id, 2, BatchOrderStatus::Running);
+        batches.update(Uuid::new_v4(), 0, BatchOrderStatus::Running);
+        batches.finish(batch.batch_id);
+
+        let batch = batches.get(batch.batch_id).unwrap();
+        assert_eq!(batch.orders[0].order_id, first);
+        assert_eq!(
+            batch.orders[0].status,
+            BatchOrderStatus::Started { saga_id }
+        );
+        assert!(matches!(
+            batch.orders[1].status,
+            BatchOrderStatus::Failed(_)
+        ));
+        assert!(batch.finished_at.is_some());
+        assert!(batches.get(Uuid::new_v4()).is_none());
+    }
+
+    #[test]
+    fn test_finished_batches_are_evicted() {
+        let batches = FulfillmentBatches::new().with_finished_capacity(1);
+        let running = batches.start(vec![AggregateId::new()], 1).batch_id;
+        let first = batches.start(vec![AggregateId::new()], 1).batch_id;
+        let second = batches.start(vec![AggregateId::new()], 1).batch_id;
+        batches.finish(first);
+        batches.finish(second);
+        // The oldest finished batch makes room; running ones stay
+        assert!(batches.get(first).is_none());
+        assert!(batches.get(second).is_some());
+        assert!(batches.get(running).is_some());
+
+        let batches = FulfillmentBatches::new().with_retention(Duration::ZERO);
+        let batch_id = batches.start(vec![AggregateId::new()], 1).batch_id;
+        batches.finish(batch_id);
+        assert!(batches.get(batch_id).is_none());
+    }
+}
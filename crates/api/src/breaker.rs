@@ -0,0 +1,386 @@
+//! Degraded mode for the command side.
+//!
+//! Every command's outcome is counted over a fixed window: whether it
+//! failed with a server error or timeout, or with a concurrency conflict.
+//! Once a window holds enough commands and either share passes its
+//! threshold, the breaker trips and non-critical commands get `503` with
+//! `Retry-After` for the cooldown, taking load off the database during an
+//! incident. Reads are still served, as are critical commands: fulfillment,
+//! which finishes sagas already under way, and the admin controls operators
+//! need to recover. Heavier admin commands, such as reservation reconciles
+//! and exports, are shed like any other. After the cooldown the breaker
+//! closes and counting starts over.
+//!
+//! `PUT /admin/breaker` overrides the breaker, forcing degraded mode on or
+//! off until it is set back to `auto`.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::extract::{MatchedPath, Request, State};
+use axum::http::header::RETRY_AFTER;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::config::BreakerThresholds;
+use crate::error::ApiError;
+
+/// Command routes served in degraded mode: fulfillment, and the controls
+/// operators recover with.
+pub const CRITICAL_ROUTES: &[&str] = &[
+    "/orders/{id}/fulfill",
+    "/admin/breaker",
+    "/admin/settings/reload",
+    "/admin/flags/{name}",
+    "/admin/sampling",
+    "/admin/quotas",
+];
+
+/// Response extension marking a command refused by a concurrency conflict.
+#[derive(Debug, Clone, Copy)]
+pub struct ConcurrencyConflict;
+
+/// Who decides whether commands are shed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerMode {
+    /// The failure rates decide.
+    #[default]
+    Auto,
+    /// Shed non-critical commands whatever the rates.
+    Degraded,
+    /// Serve every command whatever the rates.
+    Normal,
+}
+
+/// What a command's response says about the command side's health.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Ok,
+    Failed,
+    Conflict,
+}
+
+impl Outcome {
+    fn of(response: &Response) -> Self {
+        let status = response.status();
+        if response.extensions().get::<ConcurrencyConflict>().is_some() {
+            Outcome::Conflict
+        } else if status.is_server_error() {
+            Outcome::Failed
+        } else {
+            Outcome::Ok
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Outcome::Ok => "ok",
+            Outcome::Failed => "failed",
+            Outcome::Conflict => "conflict",
+        }
+    }
+}
+
+/// Why and until when the breaker is tripped.
+struct Trip {
+    reason: String,
+    at: DateTime<Utc>,
+    until: Instant,
+}
+
+struct BreakerState {
+    mode: BreakerMode,
+    window_started: Instant,
+    commands: u64,
+    failures: u64,
+    conflicts: u64,
+    trip: Option<Trip>,
+}
+
+impl BreakerState {
+    fn new() -> Self {
+        Self {
+            mode: BreakerMode::Auto,
+            window_started: Instant::now(),
+            commands: 0,
+            failures: 0,
+            conflicts: 0,
+            trip: None,
+        }
+    }
+
+    fn reset_window(&mut self, now: Instant) {
+        self.window_started = now;
+        self.commands = 0;
+        self.failures = 0;
+        self.conflicts = 0;
+    }
+
+    /// Drops a trip whose cooldown has passed.
+    fn expire_trip(&mut self, now: Instant) {
+        if self.trip.as_ref().is_some_and(|trip| trip.until <= now) {
+            self.trip = None;
+            self.reset_window(now);
+            metrics::gauge!("command_breaker_degraded").set(0.0);
+            tracing::info!("command breaker closed");
+        }
+    }
+}
+
+/// The breaker's current state, as reported at `/admin/breaker`.
+#[derive(Debug, Clone, Serialize)]
+pub struct BreakerStatus {
+    pub mode: BreakerMode,
+    /// Whether non-critical commands are being shed.
+    pub degraded: bool,
+    /// Why the breaker tripped, while it is tripped.
+    pub reason: Option<String>,
+    pub tripped_at: Option<DateTime<Utc>>,
+    /// Seconds until a trip's cooldown ends.
+    pub retry_after_secs: Option<u64>,
+    /// Counts in the current window.
+    pub commands: u64,
+    pub failures: u64,
+    pub conflicts: u64,
+}
+
+/// Tracks command outcomes and decides when to shed commands.
+#[derive(Clone)]
+pub struct CommandBreaker {
+    thresholds: Option<BreakerThresholds>,
+    state: Arc<Mutex<BreakerState>>,
+}
+
+impl Default for CommandBreaker {
+    /// A breaker that only degrades when overridden.
+    fn default() -> Self {
+        Self {
+            thresholds: None,
+            state: Arc::new(Mutex::new(BreakerState::new())),
+        }
+    }
+}
+
+impl CommandBreaker {
+    /// Creates a breaker that trips at `thresholds`.
+    pub fn new(thresholds: BreakerThresholds) -> Self {
+        Self {
+            thresholds: Some(thresholds),
+            ..Self::default()
+        }
+    }
+
+    /// Sets who decides whether commands are shed. Returning to `auto`
+    /// closes the breaker and starts a new window.
+    pub fn set_mode(&self, mode: BreakerMode) {
+        let mut state = self.state.lock().unwrap();
+        if mode == BreakerMode::Auto {
+            state.trip = None;
+            state.reset_window(Instant::now());
+        }
+        state.mode = mode;
+        let degraded = mode == BreakerMode::Degraded;
+        metrics::gauge!("command_breaker_degraded").set(if degraded { 1.0 } else { 0.0 });
+        tracing::warn!(?mode, "command breaker overridden");
+    }
+
+    /// Returns how long callers should wait before retrying, or None when
+    /// commands are being served.
+    pub fn retry_after(&self) -> Option<Duration> {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        state.expire_trip(now);
+        match state.mode {
+            BreakerMode::Normal => None,
+            BreakerMode::Degraded => Some(self.cooldown()),
+            BreakerMode::Auto => state.trip.as_ref().map(|trip| trip.until - now),
+        }
+    }
+
+    /// Returns the breaker's current state.
+    pub fn status(&self) -> BreakerStatus {
+        let retry_after = self.retry_after();
+        let state = self.state.lock().unwrap();
+        BreakerStatus {
+            mode: state.mode,
+            degraded: retry_after.is_some(),
+            reason: state.trip.as_ref().map(|trip| trip.reason.clone()),
+            tripped_at: state.trip.as_ref().map(|trip| trip.at),
+            retry_after_secs: retry_after.map(retry_secs),
+            commands: state.commands,
+            failures: state.failures,
+            conflicts: state.conflicts,
+        }
+    }
+
+    fn cooldown(&self) -> Duration {
+        self.thresholds
+            .map_or(BreakerThresholds::default().cooldown, |t| t.cooldown)
+    }
+
+    /// Counts a command's outcome, tripping the breaker when a threshold
+    /// is passed.
+    fn record(&self, outcome: Outcome) {
+        metrics::counter!("commands_total", "outcome" => outcome.label()).increment(1);
+        let Some(thresholds) = self.thresholds else {
+            return;
+        };
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        if now.duration_since(state.window_started) >= thresholds.window {
+            state.reset_window(now);
+        }
+        state.commands += 1;
+        match outcome {
+            Outcome::Ok => {}
+            Outcome::Failed => state.failures += 1,
+            Outcome::Conflict => state.conflicts += 1,
+        }
+        if state.mode != BreakerMode::Auto
+            || state.trip.is_some()
+            || state.commands < thresholds.min_commands
+        {
+            return;
+        }
+
+        let rate = |count: u64| count as f64 / state.commands as f64;
+        let reason = if rate(state.failures) > thresholds.failure_rate {
+            format!("{} of {} commands failed", state.failures, state.commands)
+        } else if rate(state.conflicts) > thresholds.conflict_rate {
+            format!(
+                "{} of {} commands hit concurrency conflicts",
+                state.conflicts, state.commands
+            )
+        } else {
+            return;
+        };
+        tracing::warn!(%reason, cooldown_secs = thresholds.cooldown.as_secs(), "command breaker tripped");
+        metrics::counter!("command_breaker_trips").increment(1);
+        metrics::gauge!("command_breaker_degraded").set(1.0);
+        state.trip = Some(Trip {
+            reason,
+            at: Utc::now(),
+            until: now + thresholds.cooldown,
+        });
+        state.reset_window(now);
+    }
+}
+
+/// Rounds up, so callers never retry before the cooldown ends.
+fn retry_secs(wait: Duration) -> u64 {
+    wait.as_secs() + u64::from(wait.subsec_nanos() > 0)
+}
+
+fn is_critical(path: &str) -> bool {
+    CRITICAL_ROUTES.contains(&path)
+}
+
+/// Middleware shedding non-critical commands in degraded mode and counting
+/// the outcome of the rest.
+pub async fn shed_when_degraded(
+    State(breaker): State<CommandBreaker>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let critical = request
+        .extensions()
+        .get::<MatchedPath>()
+        .is_some_and(|path| is_critical(path.as_str()));
+    if !critical && let Some(wait) = breaker.retry_after() {
+        metrics::counter!("commands_shed").increment(1);
+        let mut response = ApiError::Unavailable(
+            "Commands are temporarily refused while the service recovers".to_string(),
+        )
+        .into_response();
+        response
+            .headers_mut()
+            .insert(RETRY_AFTER, retry_secs(wait).into());
+        return response;
+    }
+
+    let response = next.run(request).await;
+    breaker.record(Outcome::of(&response));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_control_routes_are_critical() {
+        assert!(is_critical("/admin/breaker"));
+        assert!(is_critical("/orders/{id}/fulfill"));
+        assert!(!is_critical("/admin/reservations/reconcile"));
+        assert!(!is_critical("/admin/export/parquet"));
+        assert!(!is_critical("/orders"));
+    }
+
+    fn thresholds() -> BreakerThresholds {
+        BreakerThresholds {
+            min_commands: 4,
+            window: Duration::from_secs(60),
+            cooldown: Duration::from_secs(60),
+            ..BreakerThresholds::default()
+        }
+    }
+
+    #[test]
+    fn test_trips_when_failure_rate_exceeded() {
+        let breaker = CommandBreaker::new(thresholds());
+        for outcome in [Outcome::Failed, Outcome::Failed, Outcome::Failed] {
+            breaker.record(outcome);
+        }
+        // Too few commands to judge yet
+        assert!(breaker.retry_after().is_none());
+
+        breaker.record(Outcome::Ok);
+        let status = breaker.status();
+        assert!(status.degraded);
+        assert_eq!(status.reason.as_deref(), Some("3 of 4 commands failed"));
+        assert_eq!(status.retry_after_secs, Some(60));
+
+        // Overrides win over the trip, and auto starts afresh
+        breaker.set_mode(BreakerMode::Normal);
+        assert!(breaker.retry_after().is_none());
+        breaker.set_mode(BreakerMode::Auto);
+        assert!(breaker.retry_after().is_none());
+        assert_eq!(breaker.status().commands, 0);
+    }
+
+    #[test]
+    fn test_trips_on_conflicts_and_closes_after_cooldown() {
+        let breaker = CommandBreaker::new(BreakerThresholds {
+            cooldown: Duration::ZERO,
+            ..thresholds()
+        });
+        for outcome in [Outcome::Conflict, Outcome::Conflict, Outcome::Ok] {
+            breaker.record(outcome);
+        }
+        breaker.record(Outcome::Conflict);
+        assert_eq!(
+            breaker
+                .state
+                .lock()
+                .unwrap()
+                .trip
+                .as_ref()
+                .map(|t| t.reason.clone()),
+            Some("3 of 4 commands hit concurrency conflicts".to_string())
+        );
+        assert!(breaker.retry_after().is_none());
+        assert!(breaker.state.lock().unwrap().trip.is_none());
+
+        // Without thresholds only an override degrades
+        let manual = CommandBreaker::default();
+        for _ in 0..10 {
+            manual.record(Outcome::Failed);
+        }
+        assert!(manual.retry_after().is_none());
+        manual.set_mode(BreakerMode::Degraded);
+        assert_eq!(manual.retry_after(), Some(Duration::from_secs(30)));
+    }
+}
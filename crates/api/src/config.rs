@@ -1,5 +1,22 @@
 //! Application configuration loaded from environment variables.
 
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use domain::{Money, OrderLimits, PriceDriftPolicy};
+use event_store::{QuotaLimits, SchemaDeprecations, StreamVisibility, VisibilityPolicy};
+use projections::ViewSpec;
+use projections::nats::DEFAULT_SUBJECT_PREFIX;
+use projections::shadow::DEFAULT_SHADOW_SAMPLE_RATE;
+use saga::BusinessHours;
+use saga::coordinator::DEFAULT_MAX_STEP_ATTEMPTS;
+use thiserror::Error;
+
+use crate::access::{PseudonymKey, Role};
+use crate::idempotency::{DEFAULT_IDEMPOTENCY_LEASE, DEFAULT_IDEMPOTENCY_TTL};
+use crate::maintenance::JobSchedule;
+
 /// Server configuration with sensible defaults.
 ///
 /// Reads from environment variables:
@@ -8,6 +25,116 @@
 /// - `RUST_LOG` — tracing filter directive (default: `"info"`)
 /// - `DATABASE_URL` — PostgreSQL connection string (default: `None`, uses in-memory store)
 /// - `DB_MAX_CONNECTIONS` — max database pool connections (default: `10`)
+/// - `MIGRATE_TO_DATABASE_URL` — PostgreSQL database the `store_migration`
+///   job copies the in-memory store into (default: `None`, no job)
+/// - `QUERY_TIMEOUT_MS` — timeout for read endpoints (default: `5000`)
+/// - `COMMAND_TIMEOUT_MS` — timeout for write endpoints (default: `10000`)
+/// - `FULFILL_TIMEOUT_MS` — timeout for saga fulfillment (default: `30000`)
+/// - `COMMAND_BREAKER_FAILURE_RATE`, `COMMAND_BREAKER_CONFLICT_RATE` —
+///   shares of commands failing with server errors or timeouts, and with
+///   concurrency conflicts, above which the API sheds non-critical commands;
+///   `1` never trips (default: `0.5` each)
+/// - `COMMAND_BREAKER_MIN_COMMANDS` — commands a window needs before its
+///   rates count (default: `20`)
+/// - `COMMAND_BREAKER_WINDOW_SECS` — window the rates are measured over
+///   (default: `30`)
+/// - `COMMAND_BREAKER_COOLDOWN_SECS` — how long commands are shed once the
+///   breaker trips (default: `30`)
+/// - `CONTENTION_REPORT_SECS` — hot aggregate report window (default: `60`)
+/// - `PRICE_DRIFT_POLICY` — `adjust` or `reject` to reconcile item prices at
+///   submit (default: unset, no reconciliation)
+/// - `REJECTION_BUFFER_SIZE` — recent command rejections kept in memory for
+///   the admin API (default: unset, rejections are only logged)
+/// - `PROJECTION_POLL_MS` — how often read models poll for new events when no
+///   append notification arrives (default: `1000`)
+/// - `QUOTA_MAX_EVENTS_PER_AGGREGATE`, `QUOTA_MAX_AGGREGATES_PER_TENANT`,
+///   `QUOTA_MAX_PAYLOAD_BYTES` — append quotas (default: unset, unlimited)
+/// - `ADD_ITEM_DEDUPE_SECS` — refuse an item addition identical to one made
+///   to the same order this many seconds earlier (default: unset, no dedupe)
+/// - `DRAFT_ORDERS_PER_MINUTE` — orders a single customer may create in any
+///   minute; more get `429 Too Many Requests` (default: unset, unlimited)
+/// - `MAX_ORDER_ITEMS`, `MAX_LINE_QUANTITY`, `MAX_ORDER_TOTAL_CENTS` — caps on
+///   distinct products per order, quantity per product, and order total in
+///   USD cents, which other currencies' orders aren't held to; item changes
+///   past them get `400` (default: unset, unlimited)
+/// - `MIGRATION_MODE` — `apply`, `require`, or `warn`; how startup handles a
+///   PostgreSQL schema that differs from this build's (default: `apply`)
+/// - `PROJECTIONS` — comma-separated read models to run, each `name` or
+///   `name:backend`, e.g. `current_orders,order_history:overflow=10000`
+///   (default: unset, current orders and stock levels in memory)
+/// - `SHADOW_PROJECTIONS` — read models to run in shadow mode beside the view
+///   of the same name, in the same format as `PROJECTIONS`; they are compared
+///   against it but never queried (default: unset, no shadows)
+/// - `SHADOW_SAMPLE_RATE` — fraction of events after which shadows are
+///   compared, between `0.0` and `1.0` (default: `0.1`)
+/// - `SNAPSHOT_IDLE_SECS` — snapshot orders far past their last snapshot once
+///   no event has been appended for this many seconds (default: unset, no
+///   background snapshots)
+/// - `MAINTENANCE_SCHEDULES` — `;`-separated `job=schedule` entries, each
+///   schedule a five-field cron expression (UTC), `@hourly`, `@daily`, or
+///   `@every 10m`, e.g. `snapshots=*/15 * * * *` (default: unset, jobs only
+///   run when triggered)
+/// - `SAGA_MAX_STEP_ATTEMPTS` — attempts at a saga step or compensation that
+///   fails with a retryable service error (default: `3`)
+/// - `SETTINGS_FILE` — file of `KEY=value` overrides for `RUST_LOG`,
+///   `DRAFT_ORDERS_PER_MINUTE`, `SAGA_MAX_STEP_ATTEMPTS`, and
+///   `PROJECTION_POLL_MS`, applied at startup and again on `SIGHUP` (default:
+///   unset, settings only change with a restart)
+/// - `PARQUET_EXPORT_DIR` — directory for Parquet exports of order history,
+///   written by `POST /admin/export/parquet` or the `parquet_export`
+///   maintenance job (default: unset, exporting is off)
+/// - `IDEMPOTENCY_TTL_SECS` — how long responses to commands sent with an
+///   `Idempotency-Key` are kept for replay; expired keys are deleted by the
+///   `idempotency_cleanup` maintenance job (default: `86400`)
+/// - `IDEMPOTENCY_LEASE_SECS` — how long a request may run before a retry
+///   with its `Idempotency-Key` runs in its place (default: `120`)
+/// - `METRICS_PORT` — serve `/metrics` on its own listener on this port
+///   instead of the API port (default: unset, served with the API)
+/// - `METRICS_BEARER_TOKEN` — token scrapers must send as
+///   `Authorization: Bearer <token>` (default: unset, no authentication)
+/// - `METRICS_RUNTIME_COLLECTORS` — `false` to stop reporting process memory,
+///   open file descriptors, and tokio runtime gauges (default: `true`)
+/// - `NATS_URL` — NATS server to share events through JetStream (default:
+///   unset, events stay in this process)
+/// - `NATS_MODE` — `publish` to send this store's events to the stream, or
+///   `subscribe` to feed the stream's events to this server's read models
+///   (default: `publish`)
+/// - `NATS_STREAM` — JetStream stream name (default: `EVENTS`)
+/// - `NATS_SUBJECT_PREFIX` — first token of event subjects (default: `events`)
+/// - `DEPRECATED_EVENT_VERSIONS` — comma-separated `EventType:version` schema
+///   versions whose reads are counted and reported at
+///   `/admin/schemas/deprecations`, e.g. `ItemAdded:1` (default: unset)
+/// - `TRACK_DEPRECATED_AGGREGATES` — `true` to also log and report each
+///   aggregate still holding a deprecated version (default: `false`)
+/// - `ASYNC_SAGAS` — `true` to run sagas in a background runner, with
+///   fulfill returning 202 Accepted once the saga is started (default: `false`)
+/// - `SHIPMENT_BUSINESS_HOURS` — `HH:MM-HH:MM` UTC hours, Monday to Friday,
+///   outside which saga shipment creation is deferred (default: unset)
+/// - `OIDC_ISSUER_URL` — OpenID Connect issuer whose bearer tokens callers
+///   must present; its keys are found through discovery (default: unset,
+///   callers are identified by gateway headers)
+/// - `OIDC_AUDIENCE` — audience tokens must be issued for (default: unset,
+///   any audience)
+/// - `OIDC_ROLE_CLAIM` — claim holding the caller's roles, a name or a JSON
+///   pointer such as `/realm_access/roles` (default: `roles`)
+/// - `OIDC_CUSTOMER_CLAIM` — claim holding a customer caller's ID, in the
+///   same form (default: `sub`)
+/// - `OIDC_ROLE_MAP` — comma-separated `idp_role=role` entries naming which
+///   provider roles are `customer`, `operations`, or `finance`, e.g.
+///   `order-admin=operations` (default: unset, roles match by name)
+/// - `OIDC_JWKS_REFRESH_SECS` — how long the provider's signing keys are
+///   cached before being fetched again (default: `3600`)
+/// - `STREAM_VISIBILITY` — comma-separated `AggregateType=visibility` entries
+///   setting which streams the customer export and published events include:
+///   `normal`, `internal` (internal readers only), or `hidden`, e.g.
+///   `TestOrder=hidden`; saga streams are `internal` unless listed (default:
+///   unset)
+/// - `PSEUDONYM_SECRET` — secret keying the customer pseudonyms finance sees;
+///   every replica should share it (default: unset, a random secret, so
+///   pseudonyms change on restart)
+///
+/// A variable that is set but can't be parsed is a [`ConfigError`] naming
+/// it, rather than falling back to its default.
 #[derive(Debug, Clone)]
 pub struct Config {
     pub host: String,
@@ -15,30 +142,457 @@ pub struct Config {
     pub log_level: String,
     pub database_url: Option<String>,
     pub db_max_connections: u32,
+    /// Postgres target for copying the in-memory store at runtime.
+    pub migrate_to_database_url: Option<String>,
+    pub timeouts: RouteTimeouts,
+    /// When the command side is switched into degraded mode.
+    pub command_breaker: BreakerThresholds,
+    /// How often the hot aggregate report is logged and its window reset.
+    pub contention_report_interval: Duration,
+    /// How submit handles item prices that drifted from the catalog.
+    pub price_drift_policy: Option<PriceDriftPolicy>,
+    /// How many recent command rejections to keep in memory.
+    pub rejection_buffer: Option<usize>,
+    /// Longest read models wait between catch-ups without a notification.
+    pub projection_poll_interval: Duration,
+    /// Append quotas enforced by the event store.
+    pub quotas: QuotaLimits,
+    /// Window in which identical item additions to an order are refused.
+    pub add_item_dedupe_window: Option<Duration>,
+    /// Orders each customer may create per minute.
+    pub draft_orders_per_minute: Option<u32>,
+    /// Caps on the size of a single order.
+    pub order_limits: OrderLimits,
+    /// How startup handles schema migrations.
+    pub migration_mode: MigrationMode,
+    /// Read models to run, in place of the default set.
+    pub projections: Option<Vec<ViewSpec>>,
+    /// Read models to run in shadow mode against the served ones.
+    pub shadow_projections: Vec<ViewSpec>,
+    /// Fraction of events after which shadow views are compared.
+    pub shadow_sample_rate: f64,
+    /// How long the event log must be idle before background snapshots.
+    pub snapshot_idle_period: Option<Duration>,
+    /// Schedules for maintenance jobs; unlisted jobs only run on demand.
+    pub maintenance_schedules: Vec<JobSchedule>,
+    /// Attempts at a saga step that fails with a retryable error.
+    pub saga_max_step_attempts: u32,
+    /// File of setting overrides re-read on `SIGHUP`.
+    pub settings_file: Option<PathBuf>,
+    /// Directory for Parquet exports of order history.
+    pub parquet_export_dir: Option<PathBuf>,
+    /// How long idempotency keys are kept.
+    pub idempotency_ttl: Duration,
+    /// How long a request holds its idempotency key before a retry may
+    /// take it over.
+    pub idempotency_lease: Duration,
+    /// Where and how `/metrics` is served.
+    pub metrics: MetricsConfig,
+    /// Event sharing over NATS JetStream, if enabled.
+    pub nats: Option<NatsConfig>,
+    /// Event schema versions whose reads are counted.
+    pub deprecated_event_versions: Vec<(String, u32)>,
+    /// Whether aggregates holding deprecated versions are listed.
+    pub track_deprecated_aggregates: bool,
+    /// Whether sagas run in the background rather than within fulfill.
+    pub async_sagas: bool,
+    /// Hours the saga's shipment step may run in; any time when unset.
+    pub shipment_business_hours: Option<BusinessHours>,
+    /// Bearer token authentication against an OIDC provider, if enabled.
+    pub oidc: Option<OidcConfig>,
+    /// Default visibility of streams by aggregate type.
+    pub stream_visibility: Vec<(String, StreamVisibility)>,
+    /// Key for the customer pseudonyms finance sees.
+    pub pseudonym_key: Option<PseudonymKey>,
 }
 
-impl Config {
-    /// Loads configuration from environment variables, falling back to defaults.
-    pub fn from_env() -> Self {
+/// An environment variable that is set to a value it can't hold.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("invalid {var}: {reason}")]
+pub struct ConfigError {
+    /// Name of the variable.
+    pub var: &'static str,
+    /// Why its value was refused.
+    pub reason: String,
+}
+
+/// Which side of a JetStream stream this server is on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NatsMode {
+    /// Publish the events appended to this server's store.
+    #[default]
+    Publish,
+    /// Apply another service's published events to this server's read models.
+    Subscribe,
+}
+
+impl FromStr for NatsMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "publish" => Ok(NatsMode::Publish),
+            "subscribe" => Ok(NatsMode::Subscribe),
+            other => Err(format!("unknown NATS mode: {other}")),
+        }
+    }
+}
+
+/// Connection to a NATS JetStream stream of events.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NatsConfig {
+    pub url: String,
+    pub mode: NatsMode,
+    pub stream: String,
+    pub subject_prefix: String,
+}
+
+impl NatsConfig {
+    /// Stream name used when `NATS_STREAM` is unset.
+    pub const DEFAULT_STREAM: &str = "EVENTS";
+
+    /// Loads NATS options from environment variables, or None when
+    /// `NATS_URL` is unset.
+    pub fn from_env() -> Option<Self> {
+        let url = std::env::var("NATS_URL").ok().filter(|v| !v.is_empty())?;
+        Some(Self {
+            url,
+            mode: std::env::var("NATS_MODE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or_default(),
+            stream: std::env::var("NATS_STREAM")
+                .ok()
+                .filter(|v| !v.is_empty())
+                .unwrap_or_else(|| Self::DEFAULT_STREAM.to_string()),
+            subject_prefix: std::env::var("NATS_SUBJECT_PREFIX")
+                .ok()
+                .filter(|v| !v.is_empty())
+                .unwrap_or_else(|| DEFAULT_SUBJECT_PREFIX.to_string()),
+        })
+    }
+}
+
+/// An OpenID Connect provider whose tokens authenticate callers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OidcConfig {
+    /// Issuer URL; tokens must carry it as `iss`.
+    pub issuer_url: String,
+    /// Audience tokens must carry in `aud`; not checked when unset.
+    pub audience: Option<String>,
+    /// Claim holding the caller's roles, as a string or array.
+    pub role_claim: String,
+    /// Claim holding a customer caller's ID.
+    pub customer_claim: String,
+    /// Provider role names mapped to internal roles; roles named
+    /// `customer`, `operations`, or `finance` map to themselves.
+    pub role_map: Vec<(String, Role)>,
+    /// How long signing keys are cached.
+    pub jwks_refresh: Duration,
+}
+
+impl OidcConfig {
+    /// Claim read for roles when `OIDC_ROLE_CLAIM` is unset.
+    pub const DEFAULT_ROLE_CLAIM: &str = "roles";
+
+    /// Claim read for customer IDs when `OIDC_CUSTOMER_CLAIM` is unset.
+    pub const DEFAULT_CUSTOMER_CLAIM: &str = "sub";
+
+    /// Creates a configuration for `issuer_url` with the default claims and
+    /// no audience check.
+    pub fn new(issuer_url: impl Into<String>) -> Self {
+        Self {
+            issuer_url: issuer_url.into(),
+            audience: None,
+            role_claim: Self::DEFAULT_ROLE_CLAIM.to_string(),
+            customer_claim: Self::DEFAULT_CUSTOMER_CLAIM.to_string(),
+            role_map: Vec::new(),
+            jwks_refresh: Duration::from_secs(3600),
+        }
+    }
+
+    /// Loads OIDC options from environment variables, or None when
+    /// `OIDC_ISSUER_URL` is unset.
+    pub fn from_env() -> Result<Option<Self>, ConfigError> {
+        let Some(issuer_url) = env_var("OIDC_ISSUER_URL") else {
+            return Ok(None);
+        };
+        let defaults = Self::new(issuer_url);
+        Ok(Some(Self {
+            audience: env_var("OIDC_AUDIENCE"),
+            role_claim: env_var("OIDC_ROLE_CLAIM").unwrap_or(defaults.role_claim.clone()),
+            customer_claim: env_var("OIDC_CUSTOMER_CLAIM")
+                .unwrap_or(defaults.customer_claim.clone()),
+            role_map: env_with("OIDC_ROLE_MAP", Self::parse_role_map)?.unwrap_or_default(),
+            jwks_refresh: env_limit("OIDC_JWKS_REFRESH_SECS")?
+                .map_or(defaults.jwks_refresh, Duration::from_secs),
+            ..defaults
+        }))
+    }
+
+    /// Parses comma-separated `idp_role=role` entries.
+    pub fn parse_role_map(s: &str) -> Result<Vec<(String, Role)>, String> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let (name, role) = entry
+                    .split_once('=')
+                    .ok_or_else(|| format!("expected idp_role=role, got {entry:?}"))?;
+                Ok((name.trim().to_string(), role.trim().parse()?))
+            })
+            .collect()
+    }
+}
+
+/// How the Prometheus endpoint is exposed.
+#[derive(Clone, PartialEq, Eq)]
+pub struct MetricsConfig {
+    /// Port for a separate metrics listener on the same host; `/metrics`
+    /// is served with the API when unset.
+    pub port: Option<u16>,
+    /// Bearer token required to scrape; the endpoint is open when unset.
+    pub bearer_token: Option<String>,
+    /// Report process and tokio runtime gauges on each scrape.
+    pub runtime_collectors: bool,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            port: None,
+            bearer_token: None,
+            runtime_collectors: true,
+        }
+    }
+}
+
+impl std::fmt::Debug for MetricsConfig {
+    // Keeps the token out of the startup configuration log
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MetricsConfig")
+            .field("port", &self.port)
+            .field(
+                "bearer_token",
+                &self.bearer_token.as_ref().map(|_| "<redacted>"),
+            )
+            .field("runtime_collectors", &self.runtime_collectors)
+            .finish()
+    }
+}
+
+impl MetricsConfig {
+    /// Loads metrics options from environment variables, falling back to
+    /// defaults.
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Ok(Self {
+            port: env_limit("METRICS_PORT")?,
+            bearer_token: env_var("METRICS_BEARER_TOKEN"),
+            runtime_collectors: std::env::var("METRICS_RUNTIME_COLLECTORS")
+                .map(|v| !matches!(v.to_ascii_lowercase().as_str(), "false" | "0" | "off"))
+                .unwrap_or(true),
+        })
+    }
+}
+
+/// How startup handles a database schema that differs from the one this
+/// build expects.
+///
+/// A schema ahead of the build (migrated by a newer release) is refused in
+/// every mode but [`MigrationMode::Warn`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MigrationMode {
+    /// Run pending migrations before serving.
+    #[default]
+    Apply,
+    /// Refuse to start unless migrations were already applied, for
+    /// deployments that migrate in a separate step.
+    Require,
+    /// Log the drift and start anyway.
+    Warn,
+}
+
+impl FromStr for MigrationMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "apply" => Ok(MigrationMode::Apply),
+            "require" => Ok(MigrationMode::Require),
+            "warn" => Ok(MigrationMode::Warn),
+            other => Err(format!("unknown migration mode: {other}")),
+        }
+    }
+}
+
+/// Request timeouts per route class.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RouteTimeouts {
+    /// Read-only endpoints.
+    pub query: Duration,
+    /// Endpoints that execute commands.
+    pub command: Duration,
+    /// Saga fulfillment, which calls external services.
+    pub fulfill: Duration,
+}
+
+impl Default for RouteTimeouts {
+    fn default() -> Self {
+        Self {
+            query: Duration::from_secs(5),
+            command: Duration::from_secs(10),
+            fulfill: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RouteTimeouts {
+    /// Loads timeouts from environment variables, falling back to defaults.
+    pub fn from_env() -> Result<Self, ConfigError> {
+        let defaults = Self::default();
+        let millis = |key, default| {
+            Ok::<_, ConfigError>(env_limit(key)?.map_or(default, Duration::from_millis))
+        };
+        Ok(Self {
+            query: millis("QUERY_TIMEOUT_MS", defaults.query)?,
+            command: millis("COMMAND_TIMEOUT_MS", defaults.command)?,
+            fulfill: millis("FULFILL_TIMEOUT_MS", defaults.fulfill)?,
+        })
+    }
+}
+
+/// Failure rates at which the command breaker sheds non-critical commands.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BreakerThresholds {
+    /// Share of commands failing with a server error or timeout.
+    pub failure_rate: f64,
+    /// Share of commands failing with a concurrency conflict.
+    pub conflict_rate: f64,
+    /// Commands a window needs before its rates count.
+    pub min_commands: u64,
+    /// Window the rates are measured over.
+    pub window: Duration,
+    /// How long commands are shed once the breaker trips.
+    pub cooldown: Duration,
+}
+
+impl Default for BreakerThresholds {
+    fn default() -> Self {
         Self {
+            failure_rate: 0.5,
+            conflict_rate: 0.5,
+            min_commands: 20,
+            window: Duration::from_secs(30),
+            cooldown: Duration::from_secs(30),
+        }
+    }
+}
+
+impl BreakerThresholds {
+    /// Loads thresholds from environment variables, falling back to
+    /// defaults.
+    pub fn from_env() -> Result<Self, ConfigError> {
+        let defaults = Self::default();
+        Ok(Self {
+            failure_rate: env_with("COMMAND_BREAKER_FAILURE_RATE", parse_rate)?
+                .unwrap_or(defaults.failure_rate),
+            conflict_rate: env_with("COMMAND_BREAKER_CONFLICT_RATE", parse_rate)?
+                .unwrap_or(defaults.conflict_rate),
+            min_commands: env_limit("COMMAND_BREAKER_MIN_COMMANDS")?
+                .unwrap_or(defaults.min_commands),
+            window: env_limit("COMMAND_BREAKER_WINDOW_SECS")?
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.window),
+            cooldown: env_limit("COMMAND_BREAKER_COOLDOWN_SECS")?
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.cooldown),
+        })
+    }
+}
+
+impl Config {
+    /// Loads configuration from environment variables, falling back to
+    /// defaults for unset ones.
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Ok(Self {
             host: std::env::var("HOST").unwrap_or_else(|_| "0.0.0.0".to_string()),
-            port: std::env::var("PORT")
-                .ok()
-                .and_then(|p| p.parse().ok())
-                .unwrap_or(3000),
+            port: env_parse("PORT")?.unwrap_or(3000),
             log_level: std::env::var("RUST_LOG").unwrap_or_else(|_| "info".to_string()),
             database_url: std::env::var("DATABASE_URL").ok(),
-            db_max_connections: std::env::var("DB_MAX_CONNECTIONS")
+            db_max_connections: env_limit("DB_MAX_CONNECTIONS")?.unwrap_or(10),
+            migrate_to_database_url: env_var("MIGRATE_TO_DATABASE_URL"),
+            timeouts: RouteTimeouts::from_env()?,
+            command_breaker: BreakerThresholds::from_env()?,
+            contention_report_interval: env_limit("CONTENTION_REPORT_SECS")?
+                .map_or(Duration::from_secs(60), Duration::from_secs),
+            price_drift_policy: env_parse("PRICE_DRIFT_POLICY")?,
+            rejection_buffer: env_limit("REJECTION_BUFFER_SIZE")?,
+            projection_poll_interval: env_limit("PROJECTION_POLL_MS")?
+                .map_or(Duration::from_secs(1), Duration::from_millis),
+            quotas: QuotaLimits {
+                max_events_per_aggregate: env_limit("QUOTA_MAX_EVENTS_PER_AGGREGATE")?,
+                max_aggregates_per_tenant: env_limit("QUOTA_MAX_AGGREGATES_PER_TENANT")?,
+                max_payload_bytes: env_limit("QUOTA_MAX_PAYLOAD_BYTES")?,
+            },
+            add_item_dedupe_window: env_limit("ADD_ITEM_DEDUPE_SECS")?.map(Duration::from_secs),
+            draft_orders_per_minute: env_limit("DRAFT_ORDERS_PER_MINUTE")?,
+            order_limits: OrderLimits {
+                max_items: env_limit("MAX_ORDER_ITEMS")?,
+                max_line_quantity: env_limit("MAX_LINE_QUANTITY")?,
+                max_total: env_limit("MAX_ORDER_TOTAL_CENTS")?.map(Money::from_cents),
+            },
+            migration_mode: env_parse("MIGRATION_MODE")?.unwrap_or_default(),
+            projections: env_with("PROJECTIONS", ViewSpec::parse_list)?,
+            shadow_projections: env_with("SHADOW_PROJECTIONS", ViewSpec::parse_list)?
+                .unwrap_or_default(),
+            shadow_sample_rate: env_with("SHADOW_SAMPLE_RATE", parse_rate)?
+                .unwrap_or(DEFAULT_SHADOW_SAMPLE_RATE),
+            snapshot_idle_period: env_limit("SNAPSHOT_IDLE_SECS")?.map(Duration::from_secs),
+            maintenance_schedules: std::env::var("MAINTENANCE_SCHEDULES")
+                .ok()
+                .and_then(|v| JobSchedule::parse_list(&v).ok())
+                .unwrap_or_default(),
+            saga_max_step_attempts: env_limit("SAGA_MAX_STEP_ATTEMPTS")?
+                .unwrap_or(DEFAULT_MAX_STEP_ATTEMPTS),
+            settings_file: env_var("SETTINGS_FILE").map(PathBuf::from),
+            parquet_export_dir: env_var("PARQUET_EXPORT_DIR").map(PathBuf::from),
+            idempotency_ttl: env_limit("IDEMPOTENCY_TTL_SECS")?
+                .map_or(DEFAULT_IDEMPOTENCY_TTL, Duration::from_secs),
+            idempotency_lease: env_limit("IDEMPOTENCY_LEASE_SECS")?
+                .map_or(DEFAULT_IDEMPOTENCY_LEASE, Duration::from_secs),
+            metrics: MetricsConfig::from_env()?,
+            nats: NatsConfig::from_env(),
+            deprecated_event_versions: env_with(
+                "DEPRECATED_EVENT_VERSIONS",
+                SchemaDeprecations::parse_list,
+            )?
+            .unwrap_or_default(),
+            track_deprecated_aggregates: env_parse("TRACK_DEPRECATED_AGGREGATES")?.unwrap_or(false),
+            async_sagas: std::env::var("ASYNC_SAGAS")
                 .ok()
                 .and_then(|v| v.parse().ok())
-                .unwrap_or(10),
-        }
+                .unwrap_or(false),
+            shipment_business_hours: std::env::var("SHIPMENT_BUSINESS_HOURS")
+                .ok()
+                .and_then(|v| v.parse().ok()),
+            oidc: OidcConfig::from_env()?,
+            stream_visibility: env_with("STREAM_VISIBILITY", VisibilityPolicy::parse_list)?
+                .unwrap_or_default(),
+            pseudonym_key: env_var("PSEUDONYM_SECRET").map(PseudonymKey::new),
+        })
     }
 
     /// Returns the `"host:port"` bind address string.
     pub fn addr(&self) -> String {
         format!("{}:{}", self.host, self.port)
     }
+
+    /// Returns the bind address of the separate metrics listener, if any.
+    pub fn metrics_addr(&self) -> Option<String> {
+        self.metrics
+            .port
+            .map(|port| format!("{}:{}", self.host, port))
+    }
 }
 
 impl Default for Config {
@@ -49,10 +603,85 @@ impl Default for Config {
             log_level: "info".to_string(),
             database_url: None,
             db_max_connections: 10,
+            migrate_to_database_url: None,
+            timeouts: RouteTimeouts::default(),
+            command_breaker: BreakerThresholds::default(),
+            contention_report_interval: Duration::from_secs(60),
+            price_drift_policy: None,
+            rejection_buffer: None,
+            projection_poll_interval: Duration::from_secs(1),
+            quotas: QuotaLimits::unlimited(),
+            add_item_dedupe_window: None,
+            draft_orders_per_minute: None,
+            order_limits: OrderLimits::unlimited(),
+            migration_mode: MigrationMode::Apply,
+            projections: None,
+            shadow_projections: Vec::new(),
+            shadow_sample_rate: DEFAULT_SHADOW_SAMPLE_RATE,
+            snapshot_idle_period: None,
+            maintenance_schedules: Vec::new(),
+            saga_max_step_attempts: DEFAULT_MAX_STEP_ATTEMPTS,
+            settings_file: None,
+            parquet_export_dir: None,
+            idempotency_ttl: DEFAULT_IDEMPOTENCY_TTL,
+            idempotency_lease: DEFAULT_IDEMPOTENCY_LEASE,
+            metrics: MetricsConfig::default(),
+            nats: None,
+            deprecated_event_versions: Vec::new(),
+            track_deprecated_aggregates: false,
+            async_sagas: false,
+            shipment_business_hours: None,
+            oidc: None,
+            stream_visibility: Vec::new(),
+            pseudonym_key: None,
         }
     }
 }
 
+/// Reads `key`, or None when it is unset or empty.
+fn env_var(key: &str) -> Option<String> {
+    std::env::var(key).ok().filter(|v| !v.is_empty())
+}
+
+/// Parses `key` with `parse`; unset or empty means None.
+fn env_with<T, E: std::fmt::Display>(
+    key: &'static str,
+    parse: impl FnOnce(&str) -> Result<T, E>,
+) -> Result<Option<T>, ConfigError> {
+    env_var(key)
+        .map(|v| {
+            parse(&v).map_err(|e| ConfigError {
+                var: key,
+                reason: e.to_string(),
+            })
+        })
+        .transpose()
+}
+
+/// Parses `key` as a `T`; unset or empty means None.
+fn env_parse<T: FromStr>(key: &'static str) -> Result<Option<T>, ConfigError>
+where
+    T::Err: std::fmt::Display,
+{
+    env_with(key, str::parse)
+}
+
+/// Parses a fraction between `0.0` and `1.0`.
+fn parse_rate(s: &str) -> Result<f64, String> {
+    s.parse()
+        .ok()
+        .filter(|rate| (0.0..=1.0).contains(rate))
+        .ok_or_else(|| format!("expected a number between 0 and 1, got {s:?}"))
+}
+
+/// Reads a positive limit from `key`; unset, empty, or zero means none.
+fn env_limit<T: FromStr + Default + PartialEq>(key: &'static str) -> Result<Option<T>, ConfigError>
+where
+    T::Err: std::fmt::Display,
+{
+    Ok(env_parse(key)?.filter(|limit| *limit != T::default()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -73,8 +702,43 @@ mod tests {
             log_level: "debug".to_string(),
             database_url: None,
             db_max_connections: 10,
+            migrate_to_database_url: None,
+            timeouts: RouteTimeouts::default(),
+            command_breaker: BreakerThresholds::default(),
+            contention_report_interval: Duration::from_secs(60),
+            price_drift_policy: None,
+            rejection_buffer: None,
+            projection_poll_interval: Duration::from_secs(1),
+            quotas: QuotaLimits::unlimited(),
+            add_item_dedupe_window: None,
+            draft_orders_per_minute: None,
+            order_limits: OrderLimits::unlimited(),
+            migration_mode: MigrationMode::Apply,
+            projections: None,
+            shadow_projections: Vec::new(),
+            shadow_sample_rate: DEFAULT_SHADOW_SAMPLE_RATE,
+            snapshot_idle_period: None,
+            maintenance_schedules: Vec::new(),
+            saga_max_step_attempts: DEFAULT_MAX_STEP_ATTEMPTS,
+            settings_file: None,
+            parquet_export_dir: None,
+            idempotency_ttl: DEFAULT_IDEMPOTENCY_TTL,
+            idempotency_lease: DEFAULT_IDEMPOTENCY_LEASE,
+            metrics: MetricsConfig {
+                port: Some(9090),
+                ..MetricsConfig::default()
+            },
+            nats: None,
+            deprecated_event_versions: Vec::new(),
+            track_deprecated_aggregates: false,
+            async_sagas: false,
+            shipment_business_hours: None,
+            oidc: None,
+            stream_visibility: Vec::new(),
+            pseudonym_key: None,
         };
         assert_eq!(config.addr(), "127.0.0.1:8080");
+        assert_eq!(config.metrics_addr().as_deref(), Some("127.0.0.1:9090"));
     }
 
     #[test]
@@ -83,10 +747,85 @@ mod tests {
         assert_eq!(config.addr(), "0.0.0.0:3000");
     }
 
+    #[test]
+    fn test_metrics_token_is_redacted_in_debug_output() {
+        let metrics = MetricsConfig {
+            bearer_token: Some("s3cret".to_string()),
+            ..MetricsConfig::default()
+        };
+        let rendered = format!("{metrics:?}");
+        assert!(!rendered.contains("s3cret"));
+        assert!(rendered.contains("<redacted>"));
+        assert!(Config::default().metrics_addr().is_none());
+        assert!(Config::default().metrics.runtime_collectors);
+    }
+
     #[test]
     fn test_default_database_fields() {
         let config = Config::default();
         assert!(config.database_url.is_none());
         assert_eq!(config.db_max_connections, 10);
     }
+
+    #[test]
+    fn test_default_timeouts() {
+        let timeouts = Config::default().timeouts;
+        assert_eq!(timeouts.query, Duration::from_secs(5));
+        assert_eq!(timeouts.command, Duration::from_secs(10));
+        assert_eq!(timeouts.fulfill, Duration::from_secs(30));
+    }
+
+    #[test]
+    fn test_migration_mode_parsing() {
+        assert_eq!("apply".parse(), Ok(MigrationMode::Apply));
+        assert_eq!("REQUIRE".parse(), Ok(MigrationMode::Require));
+        assert_eq!("warn".parse(), Ok(MigrationMode::Warn));
+        assert!("skip".parse::<MigrationMode>().is_err());
+        assert_eq!(Config::default().migration_mode, MigrationMode::Apply);
+    }
+
+    #[test]
+    fn test_nats_mode_parsing() {
+        assert_eq!("publish".parse(), Ok(NatsMode::Publish));
+        assert_eq!("Subscribe".parse(), Ok(NatsMode::Subscribe));
+        assert!("mirror".parse::<NatsMode>().is_err());
+        assert_eq!(NatsMode::default(), NatsMode::Publish);
+        assert!(Config::default().nats.is_none());
+    }
+
+    #[test]
+    fn test_invalid_variable_is_an_error_naming_it() {
+        // SAFETY: no other test reads or writes these variables
+        unsafe {
+            std::env::set_var("CONFIG_TEST_LIMIT", "ten");
+            std::env::set_var("CONFIG_TEST_ZERO", "0");
+        }
+        let err = env_limit::<u64>("CONFIG_TEST_LIMIT").unwrap_err();
+        assert_eq!(err.var, "CONFIG_TEST_LIMIT");
+        assert!(err.to_string().starts_with("invalid CONFIG_TEST_LIMIT: "));
+        assert_eq!(env_limit::<u64>("CONFIG_TEST_ZERO"), Ok(None));
+        assert_eq!(env_limit::<u64>("CONFIG_TEST_UNSET"), Ok(None));
+    }
+
+    #[test]
+    fn test_rate_parsing() {
+        assert_eq!(parse_rate("0.25"), Ok(0.25));
+        assert!(parse_rate("1.5").is_err());
+        assert!(parse_rate("-0.1").is_err());
+        assert!(parse_rate("often").is_err());
+    }
+
+    #[test]
+    fn test_oidc_role_map_parsing() {
+        assert_eq!(
+            OidcConfig::parse_role_map("order-admin=operations, billing = finance"),
+            Ok(vec![
+                ("order-admin".to_string(), Role::Operations),
+                ("billing".to_string(), Role::Finance),
+            ])
+        );
+        assert!(OidcConfig::parse_role_map("admin").is_err());
+        assert!(OidcConfig::parse_role_map("admin=root").is_err());
+        assert!(Config::default().oidc.is_none());
+    }
 }
@@ -0,0 +1,553 @@
+//! Recorded request/response contracts, for checking that refactors of
+//! handlers and DTOs keep the API's behaviour.
+//!
+//! In record mode, [`record_contracts`] stores every exchange a router
+//! handles in golden files, one JSON file per route under a directory,
+//! numbered in the order the requests arrived. In verify mode,
+//! [`verify_contracts`] replays the recorded requests in that order against
+//! a fresh router and compares each response with the recorded one: the
+//! status, the content type, and the body. JSON bodies are compared field by
+//! field, with two allowances for values that change from run to run:
+//!
+//! - UUIDs need only correspond: the first time a recorded ID is seen its
+//!   replayed counterpart is noted, later requests use the replayed ID in
+//!   its place, and later responses must use it consistently
+//! - RFC 3339 timestamps need only still be timestamps
+//!
+//! Record mode is meant for tests; it is not wired into the server.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use axum::Router;
+use axum::body::{Body, Bytes, to_bytes};
+use axum::extract::{MatchedPath, Request, State};
+use axum::http::{HeaderMap, header};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tower::ServiceExt;
+
+use crate::error::ApiError;
+
+/// Largest request or response body recorded.
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+/// Request headers left out of recordings, since the replaying client sets
+/// them itself.
+const SKIPPED_HEADERS: &[&str] = &["host", "content-length"];
+
+/// Errors reading or writing golden files.
+#[derive(Debug, thiserror::Error)]
+pub enum ContractError {
+    #[error("Failed to access {}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("Invalid golden file {}: {source}", path.display())]
+    Json {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+
+    #[error("Invalid recorded request {sequence}: {reason}")]
+    Request { sequence: u64, reason: String },
+}
+
+/// A recorded message body: JSON when it parses as such, text otherwise.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordedBody {
+    Empty,
+    Json(Value),
+    Text(String),
+}
+
+impl RecordedBody {
+    fn from_bytes(bytes: &[u8]) -> Self {
+        if bytes.is_empty() {
+            RecordedBody::Empty
+        } else if let Ok(json) = serde_json::from_slice(bytes) {
+            RecordedBody::Json(json)
+        } else {
+            RecordedBody::Text(String::from_utf8_lossy(bytes).into_owned())
+        }
+    }
+
+    fn to_text(&self) -> String {
+        match self {
+            RecordedBody::Empty => String::new(),
+            RecordedBody::Json(json) => json.to_string(),
+            RecordedBody::Text(text) => text.clone(),
+        }
+    }
+}
+
+/// A request as it was sent.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    pub method: String,
+    /// Path and query.
+    pub uri: String,
+    pub headers: BTreeMap<String, String>,
+    pub body: RecordedBody,
+}
+
+/// A response as it was returned.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub body: RecordedBody,
+}
+
+/// One request and the response it got.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Exchange {
+    /// Position among all the exchanges recorded in the run.
+    pub sequence: u64,
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+/// The golden file of one route.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RouteContract {
+    /// Method and matched path, e.g. `POST /orders/{id}/submit`.
+    pub route: String,
+    pub exchanges: Vec<Exchange>,
+}
+
+struct Recording {
+    next_sequence: u64,
+    routes: BTreeMap<String, Vec<Exchange>>,
+}
+
+/// Writes the exchanges a router handles to golden files under a
+/// directory.
+///
+/// Each route's file is rewritten as its exchanges arrive; files of routes
+/// the run doesn't reach are left as they were.
+#[derive(Clone)]
+pub struct ContractRecorder {
+    dir: PathBuf,
+    recording: Arc<Mutex<Recording>>,
+}
+
+impl ContractRecorder {
+    /// Records into `dir`, which is created if missing.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            recording: Arc::new(Mutex::new(Recording {
+                next_sequence: 1,
+                routes: BTreeMap::new(),
+            })),
+        }
+    }
+
+    /// Adds the recording middleware to every route of `router`.
+    pub fn record(&self, router: Router) -> Router {
+        router.route_layer(axum::middleware::from_fn_with_state(
+            self.clone(),
+            record_contracts,
+        ))
+    }
+
+    fn store(
+        &self,
+        route: String,
+        request: RecordedRequest,
+        response: RecordedResponse,
+    ) -> Result<(), ContractError> {
+        let mut recording = self.recording.lock().unwrap();
+        let sequence = recording.next_sequence;
+        recording.next_sequence += 1;
+        let exchanges = recording.routes.entry(route.clone()).or_default();
+        exchanges.push(Exchange {
+            sequence,
+            request,
+            response,
+        });
+
+        let contract = RouteContract {
+            route: route.clone(),
+            exchanges: exchanges.clone(),
+        };
+        let path = self.dir.join(golden_file_name(&route));
+        let io = |source| ContractError::Io {
+            path: path.clone(),
+            source,
+        };
+        std::fs::create_dir_all(&self.dir).map_err(io)?;
+        let json =
+            serde_json::to_string_pretty(&contract).map_err(|source| ContractError::Json {
+                path: path.clone(),
+                source,
+            })?;
+        std::fs::write(&path, json + "\n").map_err(io)
+    }
+}
+
+/// Names a route's golden file after its method and path, e.g.
+/// `post_orders_id_submit.json`.
+fn golden_file_name(route: &str) -> String {
+    let mut name = String::new();
+    for part in route
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+    {
+        if !name.is_empty() {
+            name.push('_');
+        }
+        name.push_str(&part.to_ascii_lowercase());
+    }
+    name + ".json"
+}
+
+/// Middleware recording each request and its response for the route it
+/// matched.
+pub async fn record_contracts(
+    State(recorder): State<ContractRecorder>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let route = match request.extensions().get::<MatchedPath>() {
+        Some(path) => format!("{} {}", request.method(), path.as_str()),
+        None => return next.run(request).await,
+    };
+
+    let (parts, body) = request.into_parts();
+    let bytes = match to_bytes(body, MAX_BODY_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(_) => {
+            return ApiError::BadRequest("request body too large to record".into()).into_response();
+        }
+    };
+    let recorded_request = RecordedRequest {
+        method: parts.method.to_string(),
+        uri: parts
+            .uri
+            .path_and_query()
+            .map_or_else(|| parts.uri.path().to_string(), ToString::to_string),
+        headers: recorded_headers(&parts.headers),
+        body: RecordedBody::from_bytes(&bytes),
+    };
+
+    let response = next
+        .run(Request::from_parts(parts, Body::from(bytes)))
+        .await;
+    let (parts, body) = response.into_parts();
+    let bytes = match to_bytes(body, MAX_BODY_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            return ApiError::Internal(format!("failed to read response body: {e}"))
+                .into_response();
+        }
+    };
+    let recorded_response = RecordedResponse {
+        status: parts.status.as_u16(),
+        content_type: content_type(&parts.headers),
+        body: RecordedBody::from_bytes(&bytes),
+    };
+
+    if let Err(e) = recorder.store(route, recorded_request, recorded_response) {
+        tracing::warn!(error = %e, "failed to record contract");
+    }
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+fn recorded_headers(headers: &HeaderMap) -> BTreeMap<String, String> {
+    headers
+        .iter()
+        .filter(|(name, _)| !SKIPPED_HEADERS.contains(&name.as_str()))
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect()
+}
+
+fn content_type(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(String::from)
+}
+
+/// A replayed exchange whose response no longer matches the recording.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContractMismatch {
+    pub route: String,
+    pub sequence: u64,
+    /// What differs, one entry per field, e.g.
+    /// `$.items[0].quantity: expected 2, got 3`.
+    pub differences: Vec<String>,
+}
+
+/// Reads every golden file in `dir`.
+pub fn load_contracts(dir: &Path) -> Result<Vec<RouteContract>, ContractError> {
+    let io = |source| ContractError::Io {
+        path: dir.to_path_buf(),
+        source,
+    };
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map_err(io)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()
+        .map_err(io)?;
+    paths.retain(|path| path.extension().is_some_and(|ext| ext == "json"));
+    paths.sort();
+
+    paths
+        .into_iter()
+        .map(|path| {
+            let contents = std::fs::read_to_string(&path).map_err(|source| ContractError::Io {
+                path: path.clone(),
+                source,
+            })?;
+            serde_json::from_str(&contents).map_err(|source| ContractError::Json { path, source })
+        })
+        .collect()
+}
+
+/// Replays the contracts recorded in `dir` against `router`, in the order
+/// they were recorded, and returns the exchanges whose responses differ.
+///
+/// The router should start from the same state the recording run did.
+pub async fn verify_contracts(
+    router: Router,
+    dir: &Path,
+) -> Result<Vec<ContractMismatch>, ContractError> {
+    let mut exchanges: Vec<(String, Exchange)> = load_contracts(dir)?
+        .into_iter()
+        .flat_map(|contract| {
+            let route = contract.route;
+            contract
+                .exchanges
+                .into_iter()
+                .map(move |exchange| (route.clone(), exchange))
+        })
+        .collect();
+    exchanges.sort_by_key(|(_, exchange)| exchange.sequence);
+
+    let mut ids = HashMap::new();
+    let mut mismatches = Vec::new();
+    for (route, exchange) in exchanges {
+        let request = replay_request(&exchange, &ids)?;
+        let response = router
+            .clone()
+            .oneshot(request)
+            .await
+            .unwrap_or_else(|never| match never {});
+        let (parts, body) = response.into_parts();
+        let bytes = to_bytes(body, MAX_BODY_BYTES).await.unwrap_or_default();
+        let actual = RecordedResponse {
+            status: parts.status.as_u16(),
+            content_type: content_type(&parts.headers),
+            body: RecordedBody::from_bytes(&bytes),
+        };
+
+        let differences = compare_responses(&exchange.response, &actual, &mut ids);
+        if !differences.is_empty() {
+            mismatches.push(ContractMismatch {
+                route,
+                sequence: exchange.sequence,
+                differences,
+            });
+        }
+    }
+    Ok(mismatches)
+}
+
+/// Rebuilds a recorded request, with recorded IDs swapped for their
+/// replayed counterparts.
+fn replay_request(
+    exchange: &Exchange,
+    ids: &HashMap<String, String>,
+) -> Result<Request, ContractError> {
+    let substitute = |text: &str| {
+        ids.iter()
+            .fold(text.to_string(), |text, (recorded, replayed)| {
+                text.replace(recorded, replayed)
+            })
+    };
+    let recorded = &exchange.request;
+    let mut builder = Request::builder()
+        .method(recorded.method.as_str())
+        .uri(substitute(&recorded.uri));
+    for (name, value) in &recorded.headers {
+        builder = builder.header(name, substitute(value));
+    }
+    builder
+        .body(Body::from(Bytes::from(substitute(
+            &recorded.body.to_text(),
+        ))))
+        .map_err(|e| ContractError::Request {
+            sequence: exchange.sequence,
+            reason: e.to_string(),
+        })
+}
+
+fn compare_responses(
+    expected: &RecordedResponse,
+    actual: &RecordedResponse,
+    ids: &mut HashMap<String, String>,
+) -> Vec<String> {
+    let mut differences = Vec::new();
+    if expected.status != actual.status {
+        differences.push(format!(
+            "status: expected {}, got {}",
+            expected.status, actual.status
+        ));
+    }
+    if expected.content_type != actual.content_type {
+        differences.push(format!(
+            "content type: expected {:?}, got {:?}",
+            expected.content_type, actual.content_type
+        ));
+    }
+    match (&expected.body, &actual.body) {
+        (RecordedBody::Json(expected), RecordedBody::Json(actual)) => {
+            compare_json("$", expected, actual, ids, &mut differences);
+        }
+        (expected, actual) if expected != actual => {
+            differences.push(format!(
+                "body: expected {:?}, got {:?}",
+                expected.to_text(),
+                actual.to_text()
+            ));
+        }
+        _ => {}
+    }
+    differences
+}
+
+/// Compares two JSON values field by field, noting corresponding IDs in
+/// `ids`.
+fn compare_json(
+    path: &str,
+    expected: &Value,
+    actual: &Value,
+    ids: &mut HashMap<String, String>,
+    differences: &mut Vec<String>,
+) {
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) => {
+            for (key, expected) in expected {
+                let field = format!("{path}.{key}");
+                match actual.get(key) {
+                    Some(actual) => compare_json(&field, expected, actual, ids, differences),
+                    None => differences.push(format!("{field}: missing")),
+                }
+            }
+            for key in actual.keys().filter(|key| !expected.contains_key(*key)) {
+                differences.push(format!("{path}.{key}: unexpected field"));
+            }
+        }
+        (Value::Array(expected), Value::Array(actual)) => {
+            if expected.len() != actual.len() {
+                differences.push(format!(
+                    "{path}: expected {} elements, got {}",
+                    expected.len(),
+                    actual.len()
+                ));
+            }
+            for (i, (expected, actual)) in expected.iter().zip(actual).enumerate() {
+                compare_json(&format!("{path}[{i}]"), expected, actual, ids, differences);
+            }
+        }
+        (Value::String(expected), Value::String(actual))
+            if uuid::Uuid::parse_str(expected).is_ok() =>
+        {
+            if uuid::Uuid::parse_str(actual).is_err() {
+                differences.push(format!("{path}: expected an ID, got {actual:?}"));
+                return;
+            }
+            let replayed = ids
+                .entry(expected.clone())
+                .or_insert_with(|| actual.clone());
+            if replayed != actual {
+                differences.push(format!(
+                    "{path}: expected the ID replayed as {replayed}, got {actual}"
+                ));
+            }
+        }
+        (Value::String(expected), Value::String(actual)) if is_timestamp(expected) => {
+            differences.extend(
+                (!is_timestamp(actual))
+                    .then(|| format!("{path}: expected a timestamp, got {actual:?}")),
+            );
+        }
+        (expected, actual) if expected != actual => {
+            differences.push(format!("{path}: expected {expected}, got {actual}"));
+        }
+        _ => {}
+    }
+}
+
+fn is_timestamp(value: &str) -> bool {
+    chrono::DateTime::parse_from_rfc3339(value).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_compare_json_allows_new_ids_and_timestamps() {
+        let mut ids = HashMap::new();
+        let mut differences = Vec::new();
+        compare_json(
+            "$",
+            &json!({
+                "id": "7f2c4d1e-8a3b-4c5d-9e6f-0a1b2c3d4e5f",
+                "at": "2026-01-01T00:00:00Z",
+                "items": [{ "quantity": 2 }],
+                "state": "Draft"
+            }),
+            &json!({
+                "id": "11111111-2222-4333-8444-555555555555",
+                "at": "2026-10-17T12:30:00+00:00",
+                "items": [{ "quantity": 3 }],
+                "total": 0
+            }),
+            &mut ids,
+            &mut differences,
+        );
+        assert_eq!(
+            differences,
+            [
+                "$.items[0].quantity: expected 2, got 3",
+                "$.state: missing",
+                "$.total: unexpected field",
+            ]
+        );
+        assert_eq!(
+            ids["7f2c4d1e-8a3b-4c5d-9e6f-0a1b2c3d4e5f"],
+            "11111111-2222-4333-8444-555555555555"
+        );
+
+        // A recorded ID must keep mapping to the same replayed one
+        differences.clear();
+        compare_json(
+            "$",
+            &json!("7f2c4d1e-8a3b-4c5d-9e6f-0a1b2c3d4e5f"),
+            &json!("99999999-2222-4333-8444-555555555555"),
+            &mut ids,
+            &mut differences,
+        );
+        assert_eq!(differences.len(), 1);
+    }
+
+    #[test]
+    fn test_golden_file_name() {
+        assert_eq!(
+            golden_file_name("POST /orders/{id}/submit"),
+            "post_orders_id_submit.json"
+        );
+        assert_eq!(golden_file_name("GET /health"), "get_health.json");
+    }
+}
@@ -2,9 +2,12 @@
 
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
-use domain::{DomainError, OrderError};
-use event_store::EventStoreError;
-use saga::SagaError;
+use domain::{CustomerError, DomainError, OrderError};
+use event_store::{EventStoreError, QuotaExceeded};
+use projections::ProjectionError;
+use saga::{InventoryError, SagaError};
+
+use crate::breaker::ConcurrencyConflict;
 
 /// API-level error type that maps to HTTP responses.
 #[derive(Debug)]
@@ -13,56 +16,161 @@ pub enum ApiError {
     NotFound(String),
     /// Bad request from the client.
     BadRequest(String),
+    /// Request could not be authenticated.
+    Unauthorized(String),
+    /// Capability not enabled for the caller.
+    Forbidden(String),
+    /// Request conflicts with the resource's current state.
+    Conflict(String),
+    /// Request is well-formed but can't be processed as sent.
+    Unprocessable(String),
     /// Domain logic error.
     Domain(DomainError),
     /// Saga execution error.
     Saga(SagaError),
+    /// A dependency can't serve the request right now; retrying may succeed.
+    Unavailable(String),
     /// Internal server error.
     Internal(String),
 }
 
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
+        let retry_after = match &self {
+            ApiError::Domain(DomainError::Order(OrderError::DraftLimitExceeded {
+                retry_after_secs,
+                ..
+            })) => Some(*retry_after_secs),
+            _ => None,
+        };
+        let conflict = match &self {
+            ApiError::Domain(DomainError::EventStore(EventStoreError::ConcurrencyConflict {
+                aggregate_id,
+                expected,
+                actual,
+                conflicting,
+            })) => Some(serde_json::json!({
+                "aggregate_id": aggregate_id.to_string(),
+                "expected_version": expected,
+                "actual_version": actual,
+                "conflicting_events": conflicting,
+            })),
+            _ => None,
+        };
         let (status, message) = match self {
             ApiError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
             ApiError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
+            ApiError::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, msg),
+            ApiError::Forbidden(msg) => (StatusCode::FORBIDDEN, msg),
+            ApiError::Conflict(msg) => (StatusCode::CONFLICT, msg),
+            ApiError::Unprocessable(msg) => (StatusCode::UNPROCESSABLE_ENTITY, msg),
             ApiError::Domain(err) => domain_error_to_response(err),
             ApiError::Saga(err) => saga_error_to_response(err),
+            ApiError::Unavailable(msg) => {
+                tracing::warn!(error = %msg, "service unavailable");
+                (StatusCode::SERVICE_UNAVAILABLE, msg)
+            }
             ApiError::Internal(msg) => {
                 tracing::error!(error = %msg, "internal server error");
                 (StatusCode::INTERNAL_SERVER_ERROR, msg)
             }
         };
 
-        let body = serde_json::json!({ "error": message });
-        (status, axum::Json(body)).into_response()
+        let mut body = serde_json::json!({ "error": message });
+        let concurrency_conflict = conflict.is_some();
+        if let Some(conflict) = conflict {
+            body["conflict"] = conflict;
+        }
+        let mut response = (status, axum::Json(body)).into_response();
+        if concurrency_conflict {
+            response.extensions_mut().insert(ConcurrencyConflict);
+        }
+        if let Some(secs) = retry_after {
+            response
+                .headers_mut()
+                .insert(axum::http::header::RETRY_AFTER, secs.into());
+        }
+        response
     }
 }
 
 fn domain_error_to_response(err: DomainError) -> (StatusCode, String) {
     match &err {
         DomainError::Order(order_err) => match order_err {
-            OrderError::InvalidStateTransition { .. } => (StatusCode::CONFLICT, err.to_string()),
+            OrderError::InvalidStateTransition { .. }
+            | OrderError::PriceDrift { .. }
+            | OrderError::DuplicateCommand { .. }
+            | OrderError::ShippingAddressAlreadySet
+            | OrderError::NoShippingAddress => (StatusCode::CONFLICT, err.to_string()),
+            OrderError::DraftLimitExceeded { .. } => {
+                (StatusCode::TOO_MANY_REQUESTS, err.to_string())
+            }
             OrderError::ItemNotFound { .. } => (StatusCode::NOT_FOUND, err.to_string()),
             OrderError::InvalidQuantity { .. }
             | OrderError::InvalidPrice { .. }
             | OrderError::NoItems
             | OrderError::CustomerIdRequired
-            | OrderError::AlreadyCreated => (StatusCode::BAD_REQUEST, err.to_string()),
+            | OrderError::AlreadyCreated
+            | OrderError::DisallowedContent { .. }
+            | OrderError::InvalidCurrency { .. }
+            | OrderError::CurrencyMismatch { .. }
+            | OrderError::InvalidShippedItem { .. }
+            | OrderError::InvalidSubstitution { .. }
+            | OrderError::InvalidTag { .. }
+            | OrderError::InvalidMetadata { .. }
+            | OrderError::InvalidAddress { .. }
+            | OrderError::TooManyItems { .. }
+            | OrderError::LineQuantityExceeded { .. }
+            | OrderError::OrderTotalExceeded { .. }
+            | OrderError::ConflictingItemEvents { .. } => {
+                (StatusCode::BAD_REQUEST, err.to_string())
+            }
         },
+        DomainError::Customer(customer_err) => match customer_err {
+            CustomerError::SelfMerge { .. } => (StatusCode::BAD_REQUEST, err.to_string()),
+            CustomerError::AlreadyMerged { .. }
+            | CustomerError::AlreadyRegistered { .. }
+            | CustomerError::Deactivated { .. } => (StatusCode::CONFLICT, err.to_string()),
+            CustomerError::NotRegistered { .. } => (StatusCode::NOT_FOUND, err.to_string()),
+            CustomerError::InvalidContactInfo { .. } => (StatusCode::BAD_REQUEST, err.to_string()),
+        },
+        DomainError::FeatureFlag(_) => (StatusCode::BAD_REQUEST, err.to_string()),
+        DomainError::Inventory(domain::InventoryError::InsufficientStock { .. }) => {
+            (StatusCode::CONFLICT, err.to_string())
+        }
+        DomainError::Inventory(_) => (StatusCode::BAD_REQUEST, err.to_string()),
+        DomainError::Payment(_) => (StatusCode::BAD_REQUEST, err.to_string()),
+        DomainError::Rejected { .. } => (StatusCode::BAD_REQUEST, err.to_string()),
         DomainError::AggregateNotFound { .. } => (StatusCode::NOT_FOUND, err.to_string()),
+        DomainError::Cancelled => (StatusCode::SERVICE_UNAVAILABLE, err.to_string()),
+        DomainError::MissingMetadata { .. } => (StatusCode::BAD_REQUEST, err.to_string()),
+        DomainError::CommandIdReused { .. } => (StatusCode::UNPROCESSABLE_ENTITY, err.to_string()),
         DomainError::EventStore(EventStoreError::ConcurrencyConflict { .. }) => {
             (StatusCode::CONFLICT, err.to_string())
         }
+        DomainError::EventStore(EventStoreError::QuotaExceeded(exceeded)) => match exceeded {
+            QuotaExceeded::PayloadSize { .. } => (StatusCode::PAYLOAD_TOO_LARGE, err.to_string()),
+            QuotaExceeded::EventsPerAggregate { .. }
+            | QuotaExceeded::AggregatesPerTenant { .. } => {
+                (StatusCode::TOO_MANY_REQUESTS, err.to_string())
+            }
+        },
         _ => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()),
     }
 }
 
 fn saga_error_to_response(err: SagaError) -> (StatusCode, String) {
+    if let SagaError::Domain(err) = err {
+        return domain_error_to_response(err);
+    }
     match &err {
         SagaError::OrderNotFound(_) => (StatusCode::NOT_FOUND, err.to_string()),
         SagaError::OrderNotReady(_) => (StatusCode::BAD_REQUEST, err.to_string()),
         SagaError::InvalidState { .. } => (StatusCode::CONFLICT, err.to_string()),
+        SagaError::Cancelled => (StatusCode::SERVICE_UNAVAILABLE, err.to_string()),
+        SagaError::Inventory(InventoryError::OutOfStock { .. }) => {
+            (StatusCode::CONFLICT, err.to_string())
+        }
         _ => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()),
     }
 }
@@ -73,6 +181,16 @@ impl From<DomainError> for ApiError {
     }
 }
 
+impl From<ProjectionError> for ApiError {
+    fn from(err: ProjectionError) -> Self {
+        if err.is_unavailable() {
+            ApiError::Unavailable(err.to_string())
+        } else {
+            ApiError::Internal(err.to_string())
+        }
+    }
+}
+
 impl From<SagaError> for ApiError {
     fn from(err: SagaError) -> Self {
         ApiError::Saga(err)
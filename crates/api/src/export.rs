@@ -0,0 +1,504 @@
+//! Customer data export for data portability requests.
+//!
+//! An export gathers every order placed by a customer — including orders
+//! placed under customer IDs later merged into it — together with the raw
+//! events for those orders and a few derived statistics. The event log is
+//! streamed once to find the customer's orders and once more to collect
+//! their events, so memory use is bounded by the export itself.
+//!
+//! Every exported event passes through the configured [`ExportRedactor`]s
+//! before it leaves the service. Streams the exporter's [`VisibilityPolicy`]
+//! doesn't show to consumers are left out entirely, orders included.
+//!
+//! Events moved to an archive by compaction are not part of the live log and
+//! are not included; the exported orders still reflect them because they are
+//! loaded from their snapshots.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use common::AggregateId;
+use domain::{
+    Aggregate, Customer, CustomerEvent, CustomerId, DomainError, Order, OrderEvent, OrderQueries,
+    OrderState,
+};
+use event_store::{Audience, EventEnvelope, EventStore, VisibilityPolicy};
+use futures_util::StreamExt;
+use serde::Serialize;
+use uuid::Uuid;
+
+/// Replacement value for redacted fields.
+pub const REDACTED: &str = "[redacted]";
+
+/// Hook applied to every event before it is included in an export.
+pub trait ExportRedactor: Send + Sync {
+    /// Removes or masks data that must not leave the service.
+    fn redact(&self, event: &mut EventEnvelope);
+}
+
+/// Masks the given metadata keys, e.g. operator identities recorded by the
+/// audit metadata policy.
+pub struct MetadataRedactor {
+    keys: Vec<String>,
+}
+
+impl MetadataRedactor {
+    /// Creates a redactor for the given metadata keys.
+    pub fn new<I, K>(keys: I) -> Self
+    where
+        I: IntoIterator<Item = K>,
+        K: Into<String>,
+    {
+        Self {
+            keys: keys.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl ExportRedactor for MetadataRedactor {
+    fn redact(&self, event: &mut EventEnvelope) {
+        for key in &self.keys {
+            if let Some(value) = event.metadata.get_mut(key) {
+                *value = serde_json::Value::String(REDACTED.to_string());
+            }
+        }
+    }
+}
+
+/// A customer's complete export.
+#[derive(Debug, Serialize)]
+pub struct CustomerExport {
+    pub customer_id: String,
+    /// Customer IDs that were merged into this customer.
+    pub merged_customer_ids: Vec<String>,
+    pub exported_at: DateTime<Utc>,
+    pub orders: Vec<ExportedOrder>,
+    pub events: Vec<EventEnvelope>,
+    pub stats: CustomerExportStats,
+}
+
+/// An order as of the time of export.
+#[derive(Debug, Serialize)]
+pub struct ExportedOrder {
+    pub order_id: String,
+    /// The customer ID the order was placed under.
+    pub customer_id: String,
+    pub state: String,
+    pub currency: String,
+    pub total_cents: i64,
+    pub items: Vec<ExportedOrderItem>,
+    pub carrier: Option<String>,
+    pub tracking_number: Option<String>,
+}
+
+/// A line item in an exported order.
+#[derive(Debug, Serialize)]
+pub struct ExportedOrderItem {
+    pub product_id: String,
+    pub product_name: String,
+    pub quantity: u32,
+    pub unit_price_cents: i64,
+}
+
+/// Statistics derived from a customer's orders.
+#[derive(Debug, Default, Serialize)]
+pub struct CustomerExportStats {
+    pub order_count: usize,
+    pub active_orders: usize,
+    pub fulfilled_orders: usize,
+    pub cancelled_orders: usize,
+    /// Total of fulfilled orders, in cents, keyed by currency code.
+    pub total_spent_cents: BTreeMap<String, i64>,
+    pub event_count: usize,
+}
+
+/// One line of an NDJSON export.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ExportRecord<'a> {
+    Customer {
+        customer_id: &'a str,
+        merged_customer_ids: &'a [String],
+        exported_at: DateTime<Utc>,
+    },
+    Order(&'a ExportedOrder),
+    Event(&'a EventEnvelope),
+    Stats(&'a CustomerExportStats),
+}
+
+impl CustomerExport {
+    /// Renders the export as newline-delimited JSON: a customer header, then
+    /// one line per order and per event, then the stats.
+    pub fn to_ndjson(&self) -> Result<Vec<u8>, serde_json::Error> {
+        let mut out = Vec::new();
+        let mut write = |record: ExportRecord<'_>| -> Result<(), serde_json::Error> {
+            serde_json::to_writer(&mut out, &record)?;
+            out.push(b'\n');
+            Ok(())
+        };
+
+        write(ExportRecord::Customer {
+            customer_id: &self.customer_id,
+            merged_customer_ids: &self.merged_customer_ids,
+            exported_at: self.exported_at,
+        })?;
+        for order in &self.orders {
+            write(ExportRecord::Order(order))?;
+        }
+        for event in &self.events {
+            write(ExportRecord::Event(event))?;
+        }
+        write(ExportRecord::Stats(&self.stats))?;
+        Ok(out)
+    }
+}
+
+/// Builds customer exports, applying redaction hooks to every event.
+#[derive(Clone, Default)]
+pub struct CustomerExporter {
+    redactors: Vec<Arc<dyn ExportRedactor>>,
+    visibility: VisibilityPolicy,
+}
+
+impl CustomerExporter {
+    /// Creates an exporter with no redaction hooks that includes every
+    /// stream.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a redaction hook. Hooks run in the order they were added.
+    pub fn with_redactor(mut self, redactor: impl ExportRedactor + 'static) -> Self {
+        self.redactors.push(Arc::new(redactor));
+        self
+    }
+
+    /// Leaves out streams `visibility` doesn't show to consumers.
+    pub fn with_visibility(mut self, visibility: VisibilityPolicy) -> Self {
+        self.visibility = visibility;
+        self
+    }
+
+    /// Collects the export for `customer_id`.
+    #[tracing::instrument(skip(self, store, orders))]
+    pub async fn export<S: EventStore>(
+        &self,
+        store: &S,
+        orders: &impl OrderQueries,
+        customer_id: CustomerId,
+    ) -> Result<CustomerExport, DomainError> {
+        // Pass 1: find merges and the customer each order was placed under
+        let mut merged_into: HashMap<CustomerId, CustomerId> = HashMap::new();
+        let mut placed_by: Vec<(AggregateId, CustomerId)> = Vec::new();
+        let mut stream = self
+            .visibility
+            .stream_all_events(store, Audience::Consumer)
+            .await?;
+        while let Some(envelope) = stream.next().await {
+            let envelope = envelope?;
+            if envelope.aggregate_type == Customer::aggregate_type() {
+                if let CustomerEvent::CustomerMerged(data) =
+                    serde_json::from_value(envelope.payload)?
+                {
+                    merged_into.insert(data.customer_id, data.merged_into);
+                }
+            } else if envelope.aggregate_type == Order::aggregate_type()
+                && envelope.event_type == "OrderCreated"
+                && let OrderEvent::OrderCreated(data) = serde_json::from_value(envelope.payload)?
+            {
+                placed_by.push((envelope.aggregate_id, data.customer_id));
+            }
+        }
+
+        let resolves_to_customer = |mut id: CustomerId| {
+            let mut seen = HashSet::new();
+            while id != customer_id {
+                match merged_into.get(&id) {
+                    Some(next) if seen.insert(id) => id = *next,
+                    _ => return false,
+                }
+            }
+            true
+        };
+        let mut merged_customer_ids: Vec<String> = merged_into
+            .keys()
+            .filter(|id| **id != customer_id && resolves_to_customer(**id))
+            .map(ToString::to_string)
+            .collect();
+        merged_customer_ids.sort();
+        let order_ids: HashSet<AggregateId> = placed_by
+            .into_iter()
+            .filter(|(_, placed)| resolves_to_customer(*placed))
+            .map(|(order_id, _)| order_id)
+            .collect();
+
+        // Pass 2: collect the events of those orders
+        let mut events = Vec::new();
+        let mut stream = self
+            .visibility
+            .stream_all_events(store, Audience::Consumer)
+            .await?;
+        while let Some(envelope) = stream.next().await {
+            let mut envelope = envelope?;
+            if !order_ids.contains(&envelope.aggregate_id) {
+                continue;
+            }
+            for redactor in &self.redactors {
+                redactor.redact(&mut envelope);
+            }
+            events.push(envelope);
+        }
+
+        let mut exported = Vec::with_capacity(order_ids.len());
+        let mut stats = CustomerExportStats {
+            event_count: events.len(),
+            ..Default::default()
+        };
+        for order_id in order_ids {
+            let Some(order) = orders.get_order(order_id).await? else {
+                continue;
+            };
+            stats.record(&order);
+            exported.push(ExportedOrder::new(order_id, &order));
+        }
+        exported.sort_by(|a, b| a.order_id.cmp(&b.order_id));
+
+        Ok(CustomerExport {
+            customer_id: customer_id.to_string(),
+            merged_customer_ids,
+            exported_at: Utc::now(),
+            orders: exported,
+            events,
+            stats,
+        })
+    }
+}
+
+impl ExportedOrder {
+    fn new(order_id: AggregateId, order: &Order) -> Self {
+        let mut items: Vec<ExportedOrderItem> = order
+            .items()
+            .map(|item| ExportedOrderItem {
+                product_id: item.product_id.to_string(),
+                product_name: item.product_name.clone(),
+                quantity: item.quantity,
+                unit_price_cents: item.unit_price.cents(),
+            })
+            .collect();
+        items.sort_by(|a, b| a.product_id.cmp(&b.product_id));
+
+        Self {
+            order_id: order_id.to_string(),
+            customer_id: order
+                .customer_id()
+                .map(|c| c.to_string())
+                .unwrap_or_default(),
+            state: order.state().to_string(),
+            currency: order.currency().to_string(),
+            total_cents: order.total_amount().cents(),
+            items,
+            carrier: order.carrier().map(String::from),
+            tracking_number: order.tracking_number().map(String::from),
+        }
+    }
+}
+
+impl CustomerExportStats {
+    fn record(&mut self, order: &Order) {
+        self.order_count += 1;
+        match order.state() {
+            OrderState::Cancelled => self.cancelled_orders += 1,
+            OrderState::Completed
+            | OrderState::Shipped
+            | OrderState::InTransit
+            | OrderState::Delivered
+            | OrderState::DeliveryFailed => {
+                self.fulfilled_orders += 1;
+                *self
+                    .total_spent_cents
+                    .entry(order.currency().to_string())
+                    .or_default() += order.total_amount().cents();
+            }
+            OrderState::Draft | OrderState::Reserved | OrderState::Processing => {
+                self.active_orders += 1
+            }
+        }
+    }
+}
+
+/// Progress of an asynchronous export.
+#[derive(Debug, Clone)]
+pub enum ExportStatus {
+    Pending,
+    /// The rendered NDJSON archive.
+    Ready(Arc<Vec<u8>>),
+    Failed(String),
+}
+
+/// A requested asynchronous export.
+#[derive(Debug, Clone)]
+pub struct ExportJob {
+    pub export_id: Uuid,
+    pub customer_id: CustomerId,
+    pub requested_at: DateTime<Utc>,
+    pub status: ExportStatus,
+}
+
+/// In-process registry of asynchronous exports.
+///
+/// Archives are held in memory until the process restarts.
+#[derive(Clone, Default)]
+pub struct ExportJobs {
+    jobs: Arc<Mutex<HashMap<Uuid, ExportJob>>>,
+}
+
+impl ExportJobs {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a pending export for `customer_id`.
+    pub fn start(&self, customer_id: CustomerId) -> ExportJob {
+        let job = ExportJob {
+            export_id: Uuid::new_v4(),
+            customer_id,
+            requested_at: Utc::now(),
+            status: ExportStatus::Pending,
+        };
+        self.jobs
+            .lock()
+            .expect("export jobs poisoned")
+            .insert(job.export_id, job.clone());
+        job
+    }
+
+    /// Records the outcome of an export.
+    pub fn finish(&self, export_id: Uuid, status: ExportStatus) {
+        if let Some(job) = self
+            .jobs
+            .lock()
+            .expect("export jobs poisoned")
+            .get_mut(&export_id)
+        {
+            job.status = status;
+        }
+    }
+
+    /// Returns an export by ID.
+    pub fn get(&self, export_id: Uuid) -> Option<ExportJob> {
+        self.jobs
+            .lock()
+            .expect("export jobs poisoned")
+            .get(&export_id)
+            .cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use domain::{CustomerService, MergeCustomers, Money, OrderItem, OrderService};
+    use event_store::InMemoryEventStore;
+
+    async fn place_order(
+        orders: &OrderService<InMemoryEventStore>,
+        customer: CustomerId,
+    ) -> AggregateId {
+        orders
+            .create_order_with_items(
+                customer,
+                vec![OrderItem::new("SKU-1", "Widget", 2, Money::from_cents(500))],
+            )
+            .await
+            .unwrap()
+            .aggregate
+            .id()
+            .unwrap()
+    }
+
+    struct TagRedactor;
+
+    impl ExportRedactor for TagRedactor {
+        fn redact(&self, event: &mut EventEnvelope) {
+            event
+                .metadata
+                .insert("redacted".to_string(), serde_json::json!(true));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_export_includes_merged_customers_orders() {
+        let store = InMemoryEventStore::new();
+        let orders = OrderService::new(store.clone());
+        let customers = CustomerService::new(store.clone());
+
+        let survivor = CustomerId::new();
+        let duplicate = CustomerId::new();
+        let own = place_order(&orders, survivor).await;
+        let merged = place_order(&orders, duplicate).await;
+        place_order(&orders, CustomerId::new()).await;
+        customers
+            .merge_customers(MergeCustomers::new(duplicate, survivor, None))
+            .await
+            .unwrap();
+
+        let export = CustomerExporter::new()
+            .with_redactor(TagRedactor)
+            .export(&store, &orders, survivor)
+            .await
+            .unwrap();
+
+        assert_eq!(export.merged_customer_ids, [duplicate.to_string()]);
+        let mut expected = vec![own.to_string(), merged.to_string()];
+        expected.sort();
+        let order_ids: Vec<_> = export.orders.iter().map(|o| o.order_id.clone()).collect();
+        assert_eq!(order_ids, expected);
+        assert_eq!(export.stats.order_count, 2);
+        assert_eq!(export.stats.active_orders, 2);
+        assert_eq!(export.stats.event_count, 4);
+        assert!(export.events.iter().all(|e| {
+            (e.aggregate_id == own || e.aggregate_id == merged) && e.metadata["redacted"] == true
+        }));
+    }
+
+    #[test]
+    fn test_metadata_redactor_masks_listed_keys() {
+        let mut event = EventEnvelope::builder()
+            .aggregate_id(AggregateId::new())
+            .aggregate_type("Order")
+            .event_type("OrderCreated")
+            .version(event_store::Version::first())
+            .payload_raw(serde_json::json!({}))
+            .metadata("actor", serde_json::json!("ops@example.com"))
+            .metadata("source", serde_json::json!("web"))
+            .build();
+
+        MetadataRedactor::new(["actor"]).redact(&mut event);
+        assert_eq!(event.metadata["actor"], REDACTED);
+        assert_eq!(event.metadata["source"], "web");
+    }
+
+    #[tokio::test]
+    async fn test_ndjson_has_one_record_per_line() {
+        let store = InMemoryEventStore::new();
+        let orders = OrderService::new(store.clone());
+        let customer = CustomerId::new();
+        place_order(&orders, customer).await;
+
+        let export = CustomerExporter::new()
+            .export(&store, &orders, customer)
+            .await
+            .unwrap();
+        let ndjson = String::from_utf8(export.to_ndjson().unwrap()).unwrap();
+        let types: Vec<String> = ndjson
+            .lines()
+            .map(|line| {
+                let value: serde_json::Value = serde_json::from_str(line).unwrap();
+                value["type"].as_str().unwrap().to_string()
+            })
+            .collect();
+        assert_eq!(types, ["customer", "order", "event", "event", "stats"]);
+    }
+}
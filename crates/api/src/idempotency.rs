@@ -0,0 +1,236 @@
+//! `Idempotency-Key` handling for command endpoints.
+//!
+//! A command sent with an `Idempotency-Key` header is hashed over its method,
+//! path, and body. The first request under a key runs and its response is
+//! stored with its headers; retries with the same key and request get that
+//! response back, marked with `Idempotent-Replayed: true`, without running
+//! the command again. Reusing a key for a different request gets `422`, and retrying
+//! while the first request is still running gets `409`.
+//!
+//! Responses that don't settle the command's outcome — server errors,
+//! timeouts, and rate limiting — free the key so the request can be retried.
+//! A request that never answers, e.g. because the process stopped during a
+//! deploy, holds its key only for a short lease, after which a retry runs.
+//!
+//! Keys are scoped to the caller's [`Principal`]: each customer has keys of
+//! their own, so two callers choosing the same key don't collide.
+//!
+//! The commands a keyed request executes are numbered under the key (see
+//! [`domain::processed`]), so a retry after a response was lost, e.g. to a
+//! timeout, replays the commands that went through instead of repeating them.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::{Body, to_bytes};
+use axum::extract::{FromRequestParts, Request, State};
+use axum::http::{HeaderName, HeaderValue, StatusCode, header};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use domain::CommandIds;
+use domain::processed;
+use event_store::{IdempotencyClaim, IdempotencyStore, StoredResponse};
+use sha2::{Digest, Sha256};
+
+use crate::access::Principal;
+use crate::error::ApiError;
+
+/// Request header carrying the client's idempotency key.
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Response header set on replayed responses.
+pub const REPLAYED_HEADER: &str = "idempotent-replayed";
+
+/// How long keys are kept by default.
+pub const DEFAULT_IDEMPOTENCY_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How long a request may run before a retry can take its key over. Longer
+/// than any route's default timeout, so only abandoned requests lose theirs.
+pub const DEFAULT_IDEMPOTENCY_LEASE: Duration = Duration::from_secs(2 * 60);
+
+/// Longest accepted key.
+pub const MAX_KEY_LENGTH: usize = 255;
+
+/// Largest request or response body stored for a key.
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+/// Response headers describing the body's framing rather than the
+/// response, set afresh for a replayed body instead of being stored.
+const NOT_REPLAYED: [HeaderName; 2] = [header::CONTENT_LENGTH, header::TRANSFER_ENCODING];
+
+/// Idempotency key storage and how long keys are kept.
+#[derive(Clone)]
+pub struct IdempotencyKeys {
+    store: Arc<dyn IdempotencyStore>,
+    ttl: Duration,
+    lease: Duration,
+}
+
+impl IdempotencyKeys {
+    /// Keeps keys in `store` for `ttl`, with the default lease.
+    pub fn new(store: Arc<dyn IdempotencyStore>, ttl: Duration) -> Self {
+        Self {
+            store,
+            ttl,
+            lease: DEFAULT_IDEMPOTENCY_LEASE,
+        }
+    }
+
+    /// Lets a retry take over a key whose request hasn't answered within
+    /// `lease`.
+    pub fn with_lease(mut self, lease: Duration) -> Self {
+        self.lease = lease;
+        self
+    }
+
+    /// Returns the key storage.
+    pub fn store(&self) -> &Arc<dyn IdempotencyStore> {
+        &self.store
+    }
+}
+
+/// Middleware replaying responses for requests with a known idempotency
+/// key. Requests without the header pass straight through.
+pub async fn replay_by_key(
+    State(keys): State<IdempotencyKeys>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(key) = request.headers().get(IDEMPOTENCY_KEY_HEADER) else {
+        return next.run(request).await;
+    };
+    let key = match key.to_str() {
+        Ok(key)
+            if !key.is_empty()
+                && key.len() <= MAX_KEY_LENGTH
+                && key.bytes().all(|b| b.is_ascii_graphic()) =>
+        {
+            key.to_string()
+        }
+        _ => {
+            return ApiError::BadRequest(format!(
+                "Idempotency-Key must be 1 to {MAX_KEY_LENGTH} visible ASCII characters"
+            ))
+            .into_response();
+        }
+    };
+
+    let (mut parts, body) = request.into_parts();
+    let principal = match Principal::from_request_parts(&mut parts, &()).await {
+        Ok(principal) => principal,
+        Err(e) => return e.into_response(),
+    };
+    let body = match to_bytes(body, MAX_BODY_BYTES).await {
+        Ok(body) => body,
+        Err(_) => {
+            return ApiError::BadRequest("request body too large to make idempotent".into())
+                .into_response();
+        }
+    };
+    let request_hash = {
+        let mut hasher = Sha256::new();
+        hasher.update(parts.method.as_str());
+        hasher.update(b" ");
+        hasher.update(parts.uri.to_string());
+        hasher.update(b"\n");
+        hasher.update(&body);
+        hex::encode(hasher.finalize())
+    };
+
+    let expires_at =
+        chrono::Utc::now() + chrono::Duration::from_std(keys.ttl).unwrap_or(chrono::Duration::MAX);
+    let stored_key = scoped_key(principal, &key);
+    let claim = match keys
+        .store
+        .claim(&stored_key, &request_hash, keys.lease, expires_at)
+        .await
+    {
+        Ok(claim) => claim,
+        Err(e) => return ApiError::Internal(e.to_string()).into_response(),
+    };
+    match claim {
+        IdempotencyClaim::New => {}
+        IdempotencyClaim::Replay(stored) => return replayed(stored),
+        IdempotencyClaim::Mismatch => {
+            return ApiError::Unprocessable(format!(
+                "Idempotency-Key {key} was already used for a different request"
+            ))
+            .into_response();
+        }
+        IdempotencyClaim::InProgress => {
+            return ApiError::Conflict(format!(
+                "a request with Idempotency-Key {key} is still being processed"
+            ))
+            .into_response();
+        }
+    }
+
+    let command_ids = CommandIds::new(format!("idempotency:{stored_key}"));
+    let response = processed::with_command_ids(
+        command_ids,
+        next.run(Request::from_parts(parts, Body::from(body))),
+    )
+    .await;
+    let status = response.status();
+    if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+        release(&keys, &stored_key).await;
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let body = match to_bytes(body, MAX_BODY_BYTES).await {
+        Ok(body) => body,
+        Err(e) => {
+            release(&keys, &stored_key).await;
+            return ApiError::Internal(format!("failed to read response body: {e}"))
+                .into_response();
+        }
+    };
+    let stored = StoredResponse {
+        status: parts.status.as_u16(),
+        headers: parts
+            .headers
+            .iter()
+            .filter(|(name, _)| !NOT_REPLAYED.contains(*name))
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect(),
+        body: body.to_vec(),
+    };
+    if let Err(e) = keys.store.complete(&stored_key, stored).await {
+        // The command ran; the client still gets its response
+        tracing::warn!(error = %e, key = stored_key, "failed to store idempotent response");
+    }
+    Response::from_parts(parts, Body::from(body))
+}
+
+/// Returns the key `principal`'s `key` is stored under.
+fn scoped_key(principal: Principal, key: &str) -> String {
+    match principal {
+        Principal::Customer(customer_id) => format!("customer:{customer_id}:{key}"),
+        Principal::Operations => format!("operations:{key}"),
+        Principal::Finance => format!("finance:{key}"),
+    }
+}
+
+async fn release(keys: &IdempotencyKeys, key: &str) {
+    if let Err(e) = keys.store.release(key).await {
+        tracing::warn!(error = %e, key, "failed to release idempotency key");
+    }
+}
+
+fn replayed(stored: StoredResponse) -> Response {
+    let status = StatusCode::from_u16(stored.status).unwrap_or(StatusCode::OK);
+    let mut response = (status, stored.body).into_response();
+    let headers = response.headers_mut();
+    headers.remove(header::CONTENT_TYPE);
+    for (name, value) in stored.headers {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) {
+            headers.append(name, value);
+        }
+    }
+    headers.insert(REPLAYED_HEADER, HeaderValue::from_static("true"));
+    response
+}
@@ -1,46 +1,413 @@
 //! HTTP API server with observability for the event-sourcing system.
 //!
-//! Provides REST endpoints for order management and saga execution,
+//! Provides REST endpoints for order management, inventory, and saga execution,
 //! with structured logging (tracing) and Prometheus metrics.
 
+pub mod access;
+pub mod analytics;
+pub mod batch;
+pub mod breaker;
 pub mod config;
+pub mod contracts;
 pub mod error;
+pub mod export;
+pub mod idempotency;
+pub mod maintenance;
+pub mod oidc;
 pub mod routes;
+pub mod settings;
+pub mod streaming;
+pub mod webhooks;
 
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
 use axum::Router;
-use axum::routing::{get, post};
-use event_store::EventStore;
+use axum::extract::Request;
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get, post, put};
+use domain::{OrderCommands, OrderLimits, OrderQueries, PriceDriftPolicy};
+use event_store::{
+    AnnotationStore, ConsumerOffsetStore, EnvelopeFactory, EventStore, IdempotencyStore,
+    InMemoryAnnotationStore, InMemoryIdempotencyStore, InMemoryStreamVisibilityStore,
+    QueryAnalyzer, QuotaEnforcer, SchemaDeprecations, SchemaVersionSource, StreamVisibility,
+    StreamVisibilityStore, VisibilityPolicy,
+};
 use metrics_exporter_prometheus::PrometheusHandle;
-use projections::{CurrentOrdersView, ProjectionProcessor};
+use projections::registry::{CURRENT_ORDERS, STOCK_LEVELS};
+use projections::{
+    CurrentOrdersView, ProjectionProcessor, ProjectionRegistry, ProjectionSet, ViewSpec,
+};
+use saga::{
+    CustomerNotifier, InMemoryInventoryService, InMemoryPaymentService, InMemoryShippingService,
+    SagaCoordinator, SagaRunner, StepSchedulingPolicy,
+};
+use tokio_util::sync::CancellationToken;
 use tower_http::cors::{Any, CorsLayer};
+use tower_http::timeout::TimeoutLayer;
 use tower_http::trace::TraceLayer;
 
+use access::{PseudonymKey, Role};
+use analytics::ParquetExporter;
+use batch::FulfillmentBatches;
+use breaker::CommandBreaker;
+use config::{BreakerThresholds, MetricsConfig, RouteTimeouts};
+use error::ApiError;
+use export::{CustomerExporter, ExportJobs, MetadataRedactor};
+use idempotency::{DEFAULT_IDEMPOTENCY_LEASE, DEFAULT_IDEMPOTENCY_TTL, IdempotencyKeys};
+use maintenance::MaintenanceScheduler;
+use oidc::OidcAuthenticator;
 use routes::orders::AppState;
+use settings::{LiveSettings, Tunables};
+use webhooks::WebhookVerifier;
 
 /// Creates the Axum application router with all routes and shared state.
-pub fn create_app<S: EventStore + Clone + 'static>(
-    state: Arc<AppState<S>>,
+///
+/// Uses the default per-route timeouts.
+pub fn create_app<S, O>(
+    state: Arc<AppState<S, O>>,
     metrics_handle: PrometheusHandle,
     projection_processor: Arc<ProjectionProcessor<S>>,
-) -> Router {
+) -> Router
+where
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+{
+    create_app_with_timeouts(
+        state,
+        metrics_handle,
+        projection_processor,
+        RouteTimeouts::default(),
+    )
+}
+
+/// Creates the application router with explicit per-route-class timeouts.
+///
+/// Requests that exceed their timeout get `504 Gateway Timeout` and their
+/// handler future is dropped; commands are cancelled before they append. `/metrics` is served unauthenticated.
+pub fn create_app_with_timeouts<S, O>(
+    state: Arc<AppState<S, O>>,
+    metrics_handle: PrometheusHandle,
+    projection_processor: Arc<ProjectionProcessor<S>>,
+    timeouts: RouteTimeouts,
+) -> Router
+where
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+{
+    create_app_with_metrics(
+        state,
+        metrics_handle,
+        projection_processor,
+        timeouts,
+        &MetricsConfig::default(),
+    )
+}
+
+/// Creates the application router, exposing `/metrics` as configured.
+///
+/// When `metrics.port` is set the API router leaves `/metrics` out; serve
+/// [`create_metrics_app`] on that port instead.
+pub fn create_app_with_metrics<S, O>(
+    state: Arc<AppState<S, O>>,
+    metrics_handle: PrometheusHandle,
+    projection_processor: Arc<ProjectionProcessor<S>>,
+    timeouts: RouteTimeouts,
+    metrics: &MetricsConfig,
+) -> Router
+where
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+{
     let _ = &projection_processor;
 
-    let metrics_router = Router::new()
-        .route("/metrics", get(routes::metrics::get))
-        .with_state(metrics_handle);
+    let metrics_router = if metrics.port.is_none() {
+        metrics_routes(metrics_handle, metrics)
+    } else {
+        Router::new()
+    };
 
-    Router::new()
+    // Open to every caller; handlers narrow what customers see
+    let queries = Router::new()
         .route("/health", get(routes::health::check))
-        .route("/orders", post(routes::orders::create::<S>))
-        .route("/orders", get(routes::orders::list::<S>))
-        .route("/orders/{id}", get(routes::orders::get::<S>))
-        .route("/orders/{id}/submit", post(routes::orders::submit::<S>))
-        .route("/orders/{id}/fulfill", post(routes::orders::fulfill::<S>))
-        .route("/orders/{id}/saga", get(routes::orders::saga_status::<S>))
-        .route("/orders/{id}/events", get(routes::orders::events::<S>))
-        .with_state(state)
+        .route("/health/ready", get(routes::health::ready::<S, O>))
+        .route("/orders", get(routes::orders::list::<S, O>))
+        .route("/orders/{id}", get(routes::orders::get::<S, O>))
+        .route(
+            "/orders/{id}/saga",
+            get(routes::orders::saga_status::<S, O>),
+        )
+        .route("/orders/{id}/full", get(routes::orders::full::<S, O>))
+        .route("/orders/{id}/events", get(routes::orders::events::<S, O>))
+        .route("/customers/{id}", get(routes::customers::get::<S, O>))
+        .route(
+            "/customers/{id}/resolve",
+            get(routes::customers::resolve::<S, O>),
+        )
+        .route(
+            "/inventory/{product_id}",
+            get(routes::inventory::get::<S, O>),
+        )
+        .route(
+            "/customers/{id}/history",
+            get(routes::customers::history::<S, O>),
+        )
+        .route(
+            "/customers/{id}/export",
+            get(routes::customers::export::<S, O>),
+        )
+        .route(
+            "/exports/{export_id}",
+            get(routes::customers::export_status::<S, O>),
+        )
+        .route(
+            "/exports/{export_id}/download",
+            get(routes::customers::download_export::<S, O>),
+        )
+        .route_layer(timeout(timeouts.query));
+
+    let staff_queries = Router::new()
+        .route("/dashboard", get(routes::dashboard::get::<S, O>))
+        .route(
+            "/projections/status",
+            get(routes::projections::status::<S, O>),
+        )
+        .route(
+            "/inventory/oversold",
+            get(routes::inventory::oversold::<S, O>),
+        )
+        .route_layer(timeout(timeouts.query))
+        .route_layer(axum::middleware::from_fn_with_state(
+            STAFF,
+            access::require_role,
+        ));
+
+    let operations_queries = Router::new()
+        .route("/sagas/{id}/graph", get(routes::sagas::graph::<S, O>))
+        .route(
+            "/sagas/batches/{id}",
+            get(routes::sagas::batch_status::<S, O>),
+        )
+        .route("/admin/sampling", get(routes::admin::get_sampling::<S, O>))
+        .route("/admin/flags", get(routes::admin::list_flags::<S, O>))
+        .route(
+            "/admin/projections",
+            get(routes::admin::list_projections::<S, O>),
+        )
+        .route(
+            "/admin/read-models",
+            get(routes::admin::read_model_stats::<S, O>),
+        )
+        .route("/admin/contention", get(routes::admin::contention::<S, O>))
+        .route(
+            "/admin/decisions/rejections",
+            get(routes::admin::rejections::<S, O>),
+        )
+        .route(
+            "/admin/schemas/deprecations",
+            get(routes::admin::schema_deprecations::<S, O>),
+        )
+        .route("/admin/quotas", get(routes::admin::get_quotas::<S, O>))
+        .route("/admin/breaker", get(routes::admin::get_breaker::<S, O>))
+        .route("/admin/settings", get(routes::admin::get_settings::<S, O>))
+        .route(
+            "/admin/maintenance",
+            get(routes::admin::list_maintenance::<S, O>),
+        )
+        .route(
+            "/admin/orders/{id}/diff",
+            get(routes::admin::order_diff::<S, O>),
+        )
+        .route(
+            "/admin/orders/{id}/timeline",
+            get(routes::admin::order_timeline::<S, O>),
+        )
+        .route(
+            "/admin/orders/{id}/lineage",
+            get(routes::admin::order_lineage::<S, O>),
+        )
+        .route(
+            "/admin/events/{event_id}/annotations",
+            get(routes::admin::event_annotations::<S, O>),
+        )
+        .route(
+            "/admin/streams/visibility",
+            get(routes::admin::stream_visibilities::<S, O>),
+        )
+        .route(
+            "/admin/imports/validate",
+            post(routes::admin::validate_import),
+        )
+        .route("/consumers", get(routes::consumers::list::<S, O>))
+        .route(
+            "/consumers/{name}/offset",
+            get(routes::consumers::get_offset::<S, O>),
+        )
+        .route_layer(timeout(timeouts.query))
+        .route_layer(axum::middleware::from_fn_with_state(
+            OPERATIONS,
+            access::require_role,
+        ));
+
+    // Commands share their timeout, idempotency keys, and the breaker; the
+    // role check runs first so refused callers claim no key
+    let command_layers = |router: Router<Arc<AppState<S, O>>>, limit: Duration| {
+        router
+            .route_layer(timeout(limit))
+            .route_layer(axum::middleware::from_fn_with_state(
+                state.idempotency.clone(),
+                idempotency::replay_by_key,
+            ))
+            .route_layer(axum::middleware::from_fn_with_state(
+                state.command_breaker.clone(),
+                breaker::shed_when_degraded,
+            ))
+    };
+
+    let commands = Router::new()
+        .route("/orders", post(routes::orders::create::<S, O>))
+        .route("/orders/{id}/submit", post(routes::orders::submit::<S, O>))
+        .route("/orders/{id}/tags", post(routes::orders::add_tag::<S, O>))
+        .route(
+            "/orders/{id}/tags/{tag}",
+            delete(routes::orders::remove_tag::<S, O>),
+        )
+        .route(
+            "/orders/{id}/metadata/{key}",
+            put(routes::orders::set_metadata::<S, O>)
+                .delete(routes::orders::clear_metadata::<S, O>),
+        )
+        .route(
+            "/orders/{id}/shipping-address",
+            post(routes::orders::set_shipping_address::<S, O>)
+                .put(routes::orders::update_shipping_address::<S, O>),
+        )
+        .route(
+            "/integrations/shipping/webhook",
+            post(routes::integrations::shipping_webhook::<S, O>),
+        )
+        .route("/customers", post(routes::customers::register::<S, O>))
+        .route(
+            "/customers/{id}/contact",
+            put(routes::customers::update_contact::<S, O>),
+        )
+        .route(
+            "/customers/{id}/deactivate",
+            post(routes::customers::deactivate::<S, O>),
+        )
+        .route(
+            "/customers/{id}/export",
+            post(routes::customers::request_export::<S, O>),
+        )
+        .route_layer(axum::middleware::from_fn(cancel_when_dropped));
+    let commands = command_layers(commands, timeouts.command);
+
+    let staff_commands = Router::new()
+        .route(
+            "/admin/export/parquet",
+            post(routes::admin::export_parquet::<S, O>),
+        )
+        .route_layer(axum::middleware::from_fn(cancel_when_dropped));
+    let staff_commands = command_layers(staff_commands, timeouts.command).route_layer(
+        axum::middleware::from_fn_with_state(STAFF, access::require_role),
+    );
+
+    let operations_commands = Router::new()
+        .route(
+            "/orders/{id}/items/{product_id}/substitute",
+            post(routes::orders::substitute_item::<S, O>),
+        )
+        .route(
+            "/inventory/{product_id}/restock",
+            post(routes::inventory::restock::<S, O>),
+        )
+        .route("/admin/sampling", put(routes::admin::set_sampling::<S, O>))
+        .route(
+            "/admin/customers/merge",
+            post(routes::customers::merge::<S, O>),
+        )
+        .route("/admin/flags/{name}", put(routes::admin::set_flag::<S, O>))
+        .route("/admin/quotas", put(routes::admin::set_quotas::<S, O>))
+        .route("/admin/breaker", put(routes::admin::set_breaker::<S, O>))
+        .route(
+            "/admin/maintenance/{job}/run",
+            post(routes::admin::run_maintenance::<S, O>),
+        )
+        .route(
+            "/admin/prices/{product_id}",
+            put(routes::admin::set_price::<S, O>),
+        )
+        .route(
+            "/admin/projections/{name}",
+            delete(routes::admin::deregister_projection::<S, O>),
+        )
+        .route(
+            "/consumers/{name}/offset",
+            put(routes::consumers::set_offset::<S, O>),
+        )
+        .route(
+            "/admin/settings/reload",
+            post(routes::admin::reload_settings::<S, O>),
+        )
+        .route(
+            "/admin/queries/analyze",
+            post(routes::admin::analyze_query::<S, O>),
+        )
+        .route(
+            "/admin/events/{event_id}/annotations",
+            post(routes::admin::annotate_event::<S, O>),
+        )
+        .route(
+            "/admin/streams/{id}/visibility",
+            put(routes::admin::set_stream_visibility::<S, O>),
+        )
+        .route(
+            "/sagas/fulfill-batch",
+            post(routes::sagas::fulfill_batch::<S, O>),
+        )
+        .route(
+            "/admin/reservations/reconcile",
+            post(routes::admin::reconcile_reservations::<S, O>),
+        )
+        .route(
+            "/admin/orders/{id}/reservations/reconcile",
+            post(routes::admin::reconcile_order_reservations::<S, O>),
+        )
+        .route_layer(axum::middleware::from_fn(cancel_when_dropped));
+    let operations_commands = command_layers(operations_commands, timeouts.command).route_layer(
+        axum::middleware::from_fn_with_state(OPERATIONS, access::require_role),
+    );
+
+    // Fulfillment runs the saga, which outlives a timed-out request on its
+    // own task, so it gets the longer timeout and isn't cancelled. It is a
+    // warehouse action, so only operations may trigger it
+    let fulfill = Router::new().route(
+        "/orders/{id}/fulfill",
+        post(routes::orders::fulfill::<S, O>),
+    );
+    let fulfill = command_layers(fulfill, timeouts.fulfill).route_layer(
+        axum::middleware::from_fn_with_state(OPERATIONS, access::require_role),
+    );
+
+    let mut api = Router::new()
+        .merge(queries)
+        .merge(staff_queries)
+        .merge(operations_queries)
+        .merge(commands)
+        .merge(staff_commands)
+        .merge(operations_commands)
+        .merge(fulfill);
+    if let Some(authenticator) = &state.oidc {
+        api = api.route_layer(axum::middleware::from_fn_with_state(
+            authenticator.clone(),
+            oidc::require_token,
+        ));
+    }
+
+    api.with_state(state)
         .merge(metrics_router)
         .layer(
             CorsLayer::new()
@@ -51,39 +418,477 @@ pub fn create_app<S: EventStore + Clone + 'static>(
         .layer(TraceLayer::new_for_http())
 }
 
-/// Creates the default application state with stores and mock services.
-pub fn create_default_state<S: EventStore + Clone + 'static>(
-    event_store: S,
-) -> (
+/// Creates the router for a separate metrics listener.
+pub fn create_metrics_app(metrics_handle: PrometheusHandle, metrics: &MetricsConfig) -> Router {
+    metrics_routes(metrics_handle, metrics).layer(TraceLayer::new_for_http())
+}
+
+fn metrics_routes(metrics_handle: PrometheusHandle, metrics: &MetricsConfig) -> Router {
+    Router::new()
+        .route("/metrics", get(routes::metrics::get))
+        .with_state(routes::metrics::MetricsState {
+            handle: metrics_handle,
+            bearer_token: metrics.bearer_token.clone(),
+            runtime_collectors: metrics.runtime_collectors,
+        })
+}
+
+/// Roles that may run operations' routes.
+const OPERATIONS: &[Role] = &[Role::Operations];
+
+/// Roles that may run staff routes: operations and finance.
+const STAFF: &[Role] = &[Role::Operations, Role::Finance];
+
+fn timeout(duration: Duration) -> TimeoutLayer {
+    TimeoutLayer::with_status_code(StatusCode::GATEWAY_TIMEOUT, duration)
+}
+
+/// Runs a command route on its own task, cancelling the commands it
+/// executes when the request is dropped, e.g. by its timeout. A command is
+/// then stopped before it appends rather than dropped partway through.
+async fn cancel_when_dropped(request: Request, next: Next) -> Response {
+    let cancel = CancellationToken::new();
+    let _cancel_on_drop = cancel.clone().drop_guard();
+    // Task-locals don't cross the spawn; carry the request's command IDs over
+    let command_ids = domain::CommandIds::current();
+    let handler = tokio::spawn(async move {
+        let run = domain::cancellation::with_cancellation(cancel, next.run(request));
+        match command_ids {
+            Some(ids) => domain::processed::with_command_ids(ids, run).await,
+            None => run.await,
+        }
+    });
+    match handler.await {
+        Ok(response) => response,
+        Err(e) => ApiError::Internal(format!("Command task failed: {e}")).into_response(),
+    }
+}
+
+/// Application state, its projection processor, and the current orders view
+/// if it is enabled.
+pub type DefaultState<S> = (
     Arc<AppState<S>>,
     Arc<ProjectionProcessor<S>>,
-    Arc<CurrentOrdersView>,
-) {
-    use domain::OrderService;
-    use projections::Projection;
-    use saga::{
-        InMemoryInventoryService, InMemoryPaymentService, InMemoryShippingService, SagaCoordinator,
+    Option<Arc<CurrentOrdersView>>,
+);
+
+/// Creates the default application state with stores and mock services.
+///
+/// Shipping webhooks are disabled.
+pub fn create_default_state<S: EventStore + ConsumerOffsetStore + Clone + 'static>(
+    event_store: S,
+) -> DefaultState<S> {
+    create_default_state_with_webhooks(event_store, None)
+}
+
+/// Creates the default application state, accepting shipping webhooks
+/// signed for `shipping_webhooks`.
+pub fn create_default_state_with_webhooks<S: EventStore + ConsumerOffsetStore + Clone + 'static>(
+    event_store: S,
+    shipping_webhooks: Option<WebhookVerifier>,
+) -> DefaultState<S> {
+    create_default_state_with_options(
+        event_store,
+        StateOptions {
+            shipping_webhooks,
+            ..StateOptions::default()
+        },
+    )
+}
+
+/// Per-deployment options for the default application state.
+#[derive(Default)]
+pub struct StateOptions {
+    /// Verifier for carrier callbacks; webhooks are refused when unset.
+    pub shipping_webhooks: Option<WebhookVerifier>,
+    /// How submit handles prices that drifted from the catalog; prices are
+    /// not reconciled when unset.
+    pub price_drift_policy: Option<PriceDriftPolicy>,
+    /// Number of recent command rejections kept for `/admin/decisions`;
+    /// rejections are only logged when unset.
+    pub rejection_buffer: Option<usize>,
+    /// Quotas enforced by the event store, exposed at `/admin/quotas`;
+    /// the quota endpoints return 404 when unset.
+    pub quotas: Option<QuotaEnforcer>,
+    /// Window in which identical item additions to an order are refused;
+    /// repeats are accepted when unset.
+    pub add_item_dedupe_window: Option<Duration>,
+    /// Orders each customer may create per minute; unlimited when unset.
+    /// Ignored when `settings` is set.
+    pub draft_orders_per_minute: Option<u32>,
+    /// Caps on the size of a single order; unlimited by default.
+    pub order_limits: OrderLimits,
+    /// Reloadable settings; built from `draft_orders_per_minute` and the
+    /// defaults, with no settings file, when unset.
+    pub settings: Option<LiveSettings>,
+    /// Schema version source for the readiness check; the schema is
+    /// assumed current when unset.
+    pub schema: Option<Arc<dyn SchemaVersionSource>>,
+    /// Plans event queries for `/admin/queries/analyze`, which returns 404
+    /// when unset.
+    pub query_analyzer: Option<Arc<dyn QueryAnalyzer>>,
+    /// Read models to run; [`default_projections`] when unset.
+    pub projections: Option<ProjectionSet>,
+    /// Directory for Parquet exports of order history; exporting is off
+    /// when unset.
+    pub parquet_export_dir: Option<PathBuf>,
+    /// Storage for idempotency keys; keys are kept in memory, and lost on
+    /// restart, when unset.
+    pub idempotency_store: Option<Arc<dyn IdempotencyStore>>,
+    /// How long idempotency keys are kept; 24 hours when unset.
+    pub idempotency_ttl: Option<Duration>,
+    /// How long a request holds its idempotency key before a retry may
+    /// take it over; two minutes when unset.
+    pub idempotency_lease: Option<Duration>,
+    /// Hooks told about fulfillment changes customers should hear about,
+    /// such as item substitutions; none by default.
+    pub customer_notifiers: Vec<Arc<dyn CustomerNotifier>>,
+    /// Deprecated event schema versions whose reads are counted by loads
+    /// and projections; none by default.
+    pub schema_deprecations: SchemaDeprecations,
+    /// Runner driving sagas in the background, which the caller spawns;
+    /// fulfill runs the whole saga before responding when unset.
+    pub saga_runner: Option<SagaRunner>,
+    /// Conventions every appended event follows, shared by the command
+    /// handlers and the saga coordinator; system clock, random IDs, and no
+    /// enrichers by default.
+    pub envelopes: EnvelopeFactory,
+    /// Policies deciding when saga steps may run, by step name; steps run
+    /// immediately by default.
+    pub step_policies: Vec<(String, Arc<dyn StepSchedulingPolicy>)>,
+    /// Verifies callers' bearer tokens; callers are identified by gateway
+    /// headers when unset.
+    pub oidc: Option<OidcAuthenticator>,
+    /// Failure rates at which non-critical commands are shed; commands are
+    /// only shed when overridden at `/admin/breaker` when unset.
+    pub command_breaker: Option<BreakerThresholds>,
+    /// Storage for event annotations; notes are kept in memory, and lost
+    /// on restart, when unset.
+    pub annotation_store: Option<Arc<dyn AnnotationStore>>,
+    /// Which streams the customer export includes; saga streams are
+    /// internal, and overrides are kept in memory, when unset.
+    pub stream_visibility: Option<VisibilityPolicy>,
+    /// Secret keying the customer pseudonyms finance sees; a random key,
+    /// so pseudonyms change on restart, when unset.
+    pub pseudonym_key: Option<PseudonymKey>,
+}
+
+/// Builds the read models the API serves by default: current orders and
+/// stock levels.
+pub fn default_projections() -> ProjectionSet {
+    ProjectionRegistry::with_builtin_views()
+        .build(&[
+            ViewSpec::memory(CURRENT_ORDERS),
+            ViewSpec::memory(STOCK_LEVELS),
+        ])
+        .expect("built-in views build in memory")
+}
+
+/// Builds the stream visibility policy keeping overrides in `store`: saga
+/// streams are internal, then each `(aggregate_type, visibility)` in `types`
+/// applies in order.
+pub fn stream_visibility_policy(
+    store: Arc<dyn StreamVisibilityStore>,
+    types: &[(String, StreamVisibility)],
+) -> VisibilityPolicy {
+    use domain::Aggregate;
+
+    types.iter().fold(
+        VisibilityPolicy::new(store).with_type_visibility(
+            saga::SagaInstance::aggregate_type(),
+            StreamVisibility::Internal,
+        ),
+        |policy, (aggregate_type, visibility)| {
+            policy.with_type_visibility(aggregate_type.clone(), *visibility)
+        },
+    )
+}
+
+/// Creates the default application state with the given deployment options.
+pub fn create_default_state_with_options<S: EventStore + ConsumerOffsetStore + Clone + 'static>(
+    event_store: S,
+    options: StateOptions,
+) -> DefaultState<S> {
+    use domain::{
+        ContentionTracker, CustomerService, DecisionLog, FeatureFlagService, InMemoryPriceCatalog,
+        InventoryItemService, OrderService, PiiMasker, WhitespaceNormalizer,
     };
+    use saga::OrderDetailsQuery;
+
+    let StateOptions {
+        shipping_webhooks,
+        price_drift_policy,
+        rejection_buffer,
+        quotas,
+        add_item_dedupe_window,
+        draft_orders_per_minute,
+        order_limits,
+        settings,
+        schema,
+        query_analyzer,
+        projections,
+        parquet_export_dir,
+        idempotency_store,
+        idempotency_ttl,
+        idempotency_lease,
+        customer_notifiers,
+        schema_deprecations,
+        saga_runner,
+        envelopes,
+        step_policies,
+        oidc,
+        command_breaker,
+        annotation_store,
+        stream_visibility,
+        pseudonym_key,
+    } = options;
+    let settings = settings.unwrap_or_else(|| {
+        LiveSettings::new(
+            Tunables {
+                draft_orders_per_minute,
+                ..Tunables::default()
+            },
+            None,
+            None,
+        )
+    });
 
-    let order_service = OrderService::new(event_store.clone());
+    let contention = ContentionTracker::new();
+    let decisions = rejection_buffer
+        .map(DecisionLog::with_rejection_buffer)
+        .unwrap_or_default();
+    let price_catalog = Arc::new(InMemoryPriceCatalog::new());
+    let mut order_service = OrderService::new(event_store.clone())
+        .with_middleware(WhitespaceNormalizer)
+        .with_middleware(PiiMasker::new())
+        .with_contention_tracker(contention.clone())
+        .with_decision_log(decisions.clone())
+        .with_schema_deprecations(schema_deprecations.clone())
+        .with_envelope_factory(envelopes.clone());
+    if let Some(policy) = price_drift_policy {
+        order_service = order_service.with_price_reconciliation(price_catalog.clone(), policy);
+    }
+    if let Some(window) = add_item_dedupe_window {
+        order_service = order_service.with_dedupe_window(window);
+    }
+    let order_service = order_service
+        .with_draft_throttle(settings.draft_throttle().clone())
+        .with_limits(order_limits);
+    let customer_service = CustomerService::new(event_store.clone())
+        .with_decision_log(decisions.clone())
+        .with_schema_deprecations(schema_deprecations.clone())
+        .with_envelope_factory(envelopes.clone());
+    let feature_flags = FeatureFlagService::new(event_store.clone())
+        .with_decision_log(decisions.clone())
+        .with_schema_deprecations(schema_deprecations.clone())
+        .with_envelope_factory(envelopes.clone());
+    let inventory_service = InventoryItemService::new(event_store.clone())
+        .with_contention_tracker(contention.clone())
+        .with_decision_log(decisions.clone())
+        .with_schema_deprecations(schema_deprecations.clone())
+        .with_envelope_factory(envelopes.clone());
     let inventory = InMemoryInventoryService::new();
     let payment = InMemoryPaymentService::new();
     let shipping = InMemoryShippingService::new();
-    let saga_coordinator = SagaCoordinator::new(event_store.clone(), inventory, payment, shipping);
+    let mut saga_coordinator = customer_notifiers.into_iter().fold(
+        SagaCoordinator::new(event_store.clone(), inventory, payment, shipping)
+            .with_max_step_attempts(settings.saga_max_step_attempts())
+            .with_envelope_factory(envelopes),
+        SagaCoordinator::with_notifier,
+    );
+    for (step, policy) in step_policies {
+        saga_coordinator = saga_coordinator.with_step_policy(step, policy);
+    }
+    if saga_runner.is_some() {
+        saga_coordinator = saga_coordinator.with_resumable_sagas();
+    }
+    let saga_coordinator = Arc::new(saga_coordinator);
 
-    let current_orders = Arc::new(CurrentOrdersView::new());
+    let maintenance = default_maintenance(&event_store);
+    let parquet_export = parquet_export_dir.map(ParquetExporter::new);
+    if let Some(exporter) = &parquet_export {
+        register_parquet_export(&maintenance, &event_store, exporter.clone());
+    }
+    let idempotency = IdempotencyKeys::new(
+        idempotency_store.unwrap_or_else(|| Arc::new(InMemoryIdempotencyStore::new())),
+        idempotency_ttl.unwrap_or(DEFAULT_IDEMPOTENCY_TTL),
+    )
+    .with_lease(idempotency_lease.unwrap_or(DEFAULT_IDEMPOTENCY_LEASE));
+    register_idempotency_cleanup(&maintenance, &idempotency);
+    register_reservation_reconcile(&maintenance, &saga_coordinator);
 
-    let mut processor = ProjectionProcessor::new(event_store.clone());
-    processor.register(Box::new(current_orders.as_ref().clone()) as Box<dyn Projection>);
+    let projections = projections.unwrap_or_else(default_projections);
+    let current_orders = projections.views.get::<CurrentOrdersView>();
+
+    let visibility = stream_visibility.unwrap_or_else(|| {
+        stream_visibility_policy(Arc::new(InMemoryStreamVisibilityStore::new()), &[])
+    });
+
+    let mut processor = ProjectionProcessor::new(event_store.clone())
+        .with_schema_deprecations(schema_deprecations.clone());
+    for projection in projections.projections {
+        processor.register(projection);
+    }
     let processor = Arc::new(processor);
 
     let state = Arc::new(AppState {
         order_service,
+        customer_service,
+        feature_flags,
+        inventory_service,
         saga_coordinator,
-        current_orders: current_orders.clone(),
+        views: projections.views,
+        consumer_offsets: Arc::new(event_store.clone()),
+        // Operator identities recorded on events are not the customer's data
+        customer_exporter: CustomerExporter::new()
+            .with_redactor(MetadataRedactor::new(["actor"]))
+            .with_visibility(visibility.clone()),
+        exports: ExportJobs::new(),
+        fulfillment_batches: FulfillmentBatches::new(),
+        contention,
+        price_catalog,
+        decisions,
+        order_details: OrderDetailsQuery::new(event_store.clone()),
         event_store,
         projection_processor: processor.clone(),
+        shipping_webhooks,
+        quotas,
+        schema,
+        query_analyzer,
+        maintenance,
+        parquet_export,
+        settings,
+        idempotency,
+        schema_deprecations,
+        saga_runner,
+        oidc: oidc.map(Arc::new),
+        command_breaker: command_breaker.map(CommandBreaker::new).unwrap_or_default(),
+        annotations: annotation_store.unwrap_or_else(|| Arc::new(InMemoryAnnotationStore::new())),
+        visibility,
+        pseudonyms: pseudonym_key.unwrap_or_else(PseudonymKey::random),
     });
 
     (state, processor, current_orders)
 }
+
+/// Registers the built-in maintenance jobs, all unscheduled:
+///
+/// - `snapshots` — snapshots orders far past their last snapshot
+pub fn default_maintenance<S: EventStore + Clone + 'static>(
+    event_store: &S,
+) -> MaintenanceScheduler {
+    use domain::{Order, Snapshotter};
+
+    let maintenance = MaintenanceScheduler::new();
+    let snapshotter = Arc::new(Snapshotter::<_, Order>::new(event_store.clone()));
+    maintenance.register("snapshots", move || {
+        let snapshotter = snapshotter.clone();
+        async move { snapshotter.snapshot_due().await.map_err(|e| e.to_string()) }
+    });
+    maintenance
+}
+
+/// Registers `parquet_export`, which writes order history to a new Parquet
+/// file each run.
+fn register_parquet_export<S: EventStore + Clone + 'static>(
+    maintenance: &MaintenanceScheduler,
+    event_store: &S,
+    exporter: ParquetExporter,
+) {
+    let event_store = event_store.clone();
+    maintenance.register("parquet_export", move || {
+        let event_store = event_store.clone();
+        let exporter = exporter.clone();
+        async move {
+            exporter
+                .export_order_history(&event_store)
+                .await
+                .map(|export| export.rows)
+                .map_err(|e| e.to_string())
+        }
+    });
+}
+
+/// Registers `store_migration`, which copies the events in `from` into `to`
+/// from where the last run left off.
+///
+/// A run fails if an aggregate's version differs between the stores
+/// afterwards, as it can when events arrive during the copy; running it
+/// again once writes stop brings the target level.
+pub fn register_store_migration<S, T>(maintenance: &MaintenanceScheduler, from: &S, to: T)
+where
+    S: EventStore + Clone + 'static,
+    T: EventStore + ConsumerOffsetStore + Clone + 'static,
+{
+    use event_store::StoreMigration;
+
+    let from = from.clone();
+    maintenance.register("store_migration", move || {
+        let (from, to) = (from.clone(), to.clone());
+        async move {
+            let report = StoreMigration::new(&from, &to)
+                .with_progress(1000, |progress| {
+                    tracing::info!(
+                        copied = progress.copied,
+                        checkpoint = progress.checkpoint,
+                        source_head = progress.source_head,
+                        "store migration progress"
+                    );
+                })
+                .run()
+                .await
+                .map_err(|e| e.to_string())?;
+            match report.mismatches.first() {
+                None => Ok(report.progress.copied as usize),
+                Some(mismatch) => Err(format!(
+                    "{} aggregates differ after copying, e.g. {} at {} in the source and {:?} in the target",
+                    report.mismatches.len(),
+                    mismatch.aggregate_id,
+                    mismatch.source,
+                    mismatch.target
+                )),
+            }
+        }
+    });
+}
+
+/// Registers `reservation_reconcile`, which renews reserved orders'
+/// lapsed inventory reservations or cancels the orders when the stock is
+/// gone.
+fn register_reservation_reconcile<S: EventStore + Clone + 'static>(
+    maintenance: &MaintenanceScheduler,
+    coordinator: &Arc<
+        SagaCoordinator<
+            S,
+            InMemoryInventoryService,
+            InMemoryPaymentService,
+            InMemoryShippingService,
+        >,
+    >,
+) {
+    let coordinator = Arc::clone(coordinator);
+    maintenance.register("reservation_reconcile", move || {
+        let coordinator = coordinator.clone();
+        async move {
+            coordinator
+                .reconcile_all_reservations()
+                .await
+                .map(|reports| reports.len())
+                .map_err(|e| e.to_string())
+        }
+    });
+}
+
+/// Registers `idempotency_cleanup`, which deletes expired idempotency keys.
+fn register_idempotency_cleanup(maintenance: &MaintenanceScheduler, keys: &IdempotencyKeys) {
+    let store = keys.store().clone();
+    maintenance.register("idempotency_cleanup", move || {
+        let store = store.clone();
+        async move {
+            store
+                .purge_expired(chrono::Utc::now())
+                .await
+                .map_err(|e| e.to_string())
+        }
+    });
+}
@@ -1,11 +1,28 @@
 //! API server entry point.
 
-use api::config::Config;
-use event_store::{InMemoryEventStore, PostgresEventStore};
+use std::sync::Arc;
+
+use api::StateOptions;
+use api::config::{Config, MigrationMode, NatsMode};
+use api::oidc::OidcAuthenticator;
+use api::routes::orders::AppState;
+use api::settings::{LiveSettings, LogLevelReload, Tunables};
+use api::webhooks::WebhookVerifier;
+use domain::{Order, Snapshotter};
+use event_store::{
+    AnnotationStore, Audience, EnvelopeFactory, EventStore, IdempotencyStore, InMemoryEventStore,
+    InMemoryStreamVisibilityStore, PostgresEventStore, QueryAnalyzer, QuotaEnforcer,
+    QuotaEventStore, SchemaDeprecations, SchemaDrift, SchemaVersionSource, VisibilityPolicy,
+};
+use projections::{
+    JetStreamPublisher, JetStreamSubscriber, ProjectionProcessor, ProjectionRegistry, ProjectionSet,
+};
+use saga::{SagaRunner, StepSchedulingPolicy, order_fulfillment};
 use tokio::signal;
-use tracing_subscriber::EnvFilter;
+use tokio_util::sync::CancellationToken;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, reload};
 
 /// Waits for a shutdown signal (SIGINT or SIGTERM).
 async fn shutdown_signal() {
@@ -36,20 +53,346 @@ async fn shutdown_signal() {
     }
 }
 
+/// Deployment options for the application state.
+fn state_options(
+    config: &Config,
+    settings: LiveSettings,
+    quotas: QuotaEnforcer,
+    schema: Option<Arc<dyn SchemaVersionSource>>,
+) -> StateOptions {
+    StateOptions {
+        shipping_webhooks: WebhookVerifier::shipping_from_env(),
+        price_drift_policy: config.price_drift_policy,
+        rejection_buffer: config.rejection_buffer,
+        quotas: Some(quotas),
+        add_item_dedupe_window: config.add_item_dedupe_window,
+        draft_orders_per_minute: config.draft_orders_per_minute,
+        order_limits: config.order_limits,
+        settings: Some(settings),
+        schema,
+        query_analyzer: None,
+        projections: projection_set(config),
+        parquet_export_dir: config.parquet_export_dir.clone(),
+        idempotency_store: None,
+        idempotency_ttl: Some(config.idempotency_ttl),
+        idempotency_lease: Some(config.idempotency_lease),
+        customer_notifiers: Vec::new(),
+        schema_deprecations: schema_deprecations(config),
+        saga_runner: config.async_sagas.then(SagaRunner::new),
+        envelopes: EnvelopeFactory::new(),
+        step_policies: step_policies(config),
+        oidc: config.oidc.clone().map(OidcAuthenticator::new),
+        command_breaker: Some(config.command_breaker),
+        annotation_store: None,
+        stream_visibility: Some(api::stream_visibility_policy(
+            Arc::new(InMemoryStreamVisibilityStore::new()),
+            &config.stream_visibility,
+        )),
+        pseudonym_key: config.pseudonym_key.clone().or_else(|| {
+            tracing::warn!("PSEUDONYM_SECRET is unset, pseudonyms will change on restart");
+            None
+        }),
+    }
+}
+
+/// Scheduling policies for the configured saga steps.
+fn step_policies(config: &Config) -> Vec<(String, Arc<dyn StepSchedulingPolicy>)> {
+    config
+        .shipment_business_hours
+        .iter()
+        .map(|hours| {
+            let policy: Arc<dyn StepSchedulingPolicy> = Arc::new(hours.clone());
+            (order_fulfillment::STEP_CREATE_SHIPMENT.to_string(), policy)
+        })
+        .collect()
+}
+
+/// Registry of the configured deprecated event schema versions.
+fn schema_deprecations(config: &Config) -> SchemaDeprecations {
+    let deprecations = if config.track_deprecated_aggregates {
+        SchemaDeprecations::new().with_aggregate_tracking()
+    } else {
+        SchemaDeprecations::new()
+    };
+    for (event_type, version) in &config.deprecated_event_versions {
+        deprecations.deprecate(event_type.clone(), *version);
+    }
+    deprecations
+}
+
+/// Builds the reloadable settings, applying the settings file if one is
+/// configured.
+///
+/// Exits the process when the settings file can't be read or is invalid.
+fn live_settings(config: &Config, log_level: LogLevelReload) -> LiveSettings {
+    let settings = LiveSettings::new(
+        Tunables::from_config(config),
+        config.settings_file.clone(),
+        Some(log_level),
+    );
+    if settings.file().is_some() && settings.reload().is_err() {
+        tracing::error!("invalid settings file, refusing to start");
+        std::process::exit(1);
+    }
+    settings
+}
+
+/// Reloads the settings file on each SIGHUP, if one is configured.
+#[cfg(unix)]
+fn spawn_settings_reload<S: event_store::EventStore>(state: &AppState<S>) {
+    if state.settings.file().is_none() {
+        return;
+    }
+    let settings = state.settings.clone();
+    let mut hangup = signal::unix::signal(signal::unix::SignalKind::hangup())
+        .expect("failed to install SIGHUP handler");
+    tokio::spawn(async move {
+        while hangup.recv().await.is_some() {
+            tracing::info!("received SIGHUP, reloading settings");
+            // A rejected file is logged and the current settings kept
+            let _ = settings.reload();
+        }
+    });
+}
+
+#[cfg(not(unix))]
+fn spawn_settings_reload<S: event_store::EventStore>(_state: &AppState<S>) {}
+
+/// Builds the configured read models and their shadows, or None for the
+/// default set without shadows.
+///
+/// Exits the process when the configuration names an unknown view, a
+/// backend the view doesn't support, or a shadow with nothing to shadow.
+fn projection_set(config: &Config) -> Option<ProjectionSet> {
+    if config.projections.is_none() && config.shadow_projections.is_empty() {
+        return None;
+    }
+    let registry = ProjectionRegistry::with_builtin_views();
+    let mut set = match &config.projections {
+        Some(specs) => registry.build(specs),
+        None => Ok(api::default_projections()),
+    }
+    .unwrap_or_else(|e| {
+        let known: Vec<_> = registry.names().collect();
+        tracing::error!(error = %e, ?known, "invalid projection set, refusing to start");
+        std::process::exit(1);
+    });
+    for spec in &config.shadow_projections {
+        if let Err(e) = registry.build_shadow(spec, &mut set, config.shadow_sample_rate) {
+            tracing::error!(error = %e, view = %spec.name, "invalid shadow view, refusing to start");
+            std::process::exit(1);
+        }
+        tracing::info!(view = %spec.name, backend = ?spec.backend, "running view in shadow mode");
+    }
+    Some(set)
+}
+
+/// Brings the schema up to date or checks it, per the migration mode.
+///
+/// Exits the process when the schema cannot be used and the mode does not
+/// allow starting anyway.
+async fn prepare_schema(store: &PostgresEventStore, mode: MigrationMode) {
+    let version = store
+        .schema_version()
+        .await
+        .expect("failed to read schema version");
+    let drift = version.drift();
+    match (drift, mode) {
+        (SchemaDrift::UpToDate, _) => {
+            tracing::info!(?version, "database schema up to date");
+        }
+        (SchemaDrift::Behind, MigrationMode::Apply) => {
+            tracing::info!(?version, "running database migrations");
+            store
+                .run_migrations()
+                .await
+                .expect("failed to run migrations");
+        }
+        (_, MigrationMode::Warn) => {
+            tracing::warn!(?version, ?drift, "database schema drift, starting anyway");
+        }
+        (_, MigrationMode::Apply | MigrationMode::Require) => {
+            tracing::error!(
+                ?version,
+                ?drift,
+                ?mode,
+                "database schema drift, refusing to start"
+            );
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Wraps `store` so appends are checked against the configured quotas.
+async fn with_quotas<S: EventStore>(store: S, config: &Config) -> QuotaEventStore<S> {
+    let store = QuotaEventStore::new(store, QuotaEnforcer::new(config.quotas));
+    store
+        .load_tenant_usage()
+        .await
+        .expect("failed to load tenant quota usage");
+    store
+}
+
+/// Number of aggregates logged in each hot aggregate report.
+const HOT_AGGREGATE_REPORT_SIZE: usize = 10;
+
+/// Logs the hottest aggregates once per report window.
+fn spawn_contention_report<S: event_store::EventStore>(state: &AppState<S>, config: &Config) {
+    tokio::spawn(
+        state
+            .contention
+            .clone()
+            .report_periodically(config.contention_report_interval, HOT_AGGREGATE_REPORT_SIZE),
+    );
+}
+
+/// Converges sagas and orders left disagreeing by a crash mid-saga.
+async fn reconcile_sagas<S: event_store::EventStore + Clone>(state: &AppState<S>) {
+    match state.saga_coordinator.reconcile_all().await {
+        Ok(reports) => tracing::info!(unsettled = reports.len(), "sagas reconciled"),
+        Err(e) => tracing::error!(error = %e, "saga reconciliation failed"),
+    }
+}
+
+/// Drives sagas in the background until `shutdown`, if configured. Sagas
+/// left running by the previous process are resumed on the first pass.
+fn spawn_saga_runner<S: event_store::EventStore + Clone + 'static>(
+    state: &Arc<AppState<S>>,
+    shutdown: &CancellationToken,
+) {
+    let Some(runner) = state.saga_runner.clone() else {
+        return;
+    };
+    let state = Arc::clone(state);
+    let shutdown = shutdown.clone();
+    tokio::spawn(async move { runner.run(&state.saga_coordinator, shutdown).await });
+}
+
+/// Snapshots orders in idle windows until `shutdown`, if configured.
+fn spawn_background_snapshots<S: event_store::EventStore + 'static>(
+    store: S,
+    config: &Config,
+    shutdown: &CancellationToken,
+) {
+    let Some(quiet_period) = config.snapshot_idle_period else {
+        return;
+    };
+    let snapshotter = Snapshotter::<_, Order>::new(store);
+    let shutdown = shutdown.clone();
+    tokio::spawn(async move { snapshotter.run_when_idle(quiet_period, shutdown).await });
+}
+
+/// Applies the configured maintenance schedules and runs due jobs until
+/// `shutdown`.
+///
+/// Exits the process when a schedule names a job that isn't registered.
+fn spawn_maintenance<S: event_store::EventStore>(
+    state: &AppState<S>,
+    config: &Config,
+    shutdown: &CancellationToken,
+) {
+    let maintenance = state.maintenance.clone();
+    for entry in &config.maintenance_schedules {
+        if let Err(e) = maintenance.set_schedule(&entry.job, Some(entry.schedule.clone())) {
+            let known: Vec<_> = maintenance.statuses().into_iter().map(|s| s.name).collect();
+            tracing::error!(error = %e, ?known, "invalid maintenance schedule, refusing to start");
+            std::process::exit(1);
+        }
+    }
+    let shutdown = shutdown.clone();
+    tokio::spawn(async move { maintenance.run_scheduled(shutdown).await });
+}
+
+/// Keeps read models caught up in the background until `shutdown`.
+fn spawn_projection_updates<S: event_store::EventStore + 'static>(
+    processor: &Arc<ProjectionProcessor<S>>,
+    settings: &LiveSettings,
+    shutdown: &CancellationToken,
+) {
+    let processor = processor.clone();
+    let poll_interval = settings.projection_poll_interval();
+    let shutdown = shutdown.clone();
+    tokio::spawn(async move { processor.run_continuous(poll_interval, shutdown).await });
+}
+
+/// Connects the read side to NATS JetStream, if configured: publishes this
+/// store's events, leaving out hidden streams, or feeds the stream's events
+/// to the read models until `shutdown`.
+///
+/// Exits the process when the stream can't be set up.
+async fn start_event_bus<S: event_store::EventStore + 'static>(
+    processor: &Arc<ProjectionProcessor<S>>,
+    visibility: &VisibilityPolicy,
+    config: &Config,
+    shutdown: &CancellationToken,
+) {
+    let Some(nats) = &config.nats else {
+        return;
+    };
+    let context = match projections::nats::connect(&nats.url).await {
+        Ok(context) => context,
+        Err(e) => {
+            tracing::error!(error = %e, "can't reach NATS, refusing to start");
+            std::process::exit(1);
+        }
+    };
+
+    match nats.mode {
+        NatsMode::Publish => {
+            // Subscribers are this system's own services
+            let publisher = JetStreamPublisher::new(context, nats.subject_prefix.clone())
+                .with_visibility(visibility.clone(), Audience::Internal);
+            let registered = match publisher.ensure_stream(&nats.stream).await {
+                Ok(()) => {
+                    processor
+                        .register_dynamic("jetstream_publisher", Box::new(publisher))
+                        .await
+                }
+                Err(e) => Err(e),
+            };
+            if let Err(e) = registered {
+                tracing::error!(error = %e, stream = %nats.stream, "can't publish to JetStream, refusing to start");
+                std::process::exit(1);
+            }
+            tracing::info!(stream = %nats.stream, "publishing events to JetStream");
+        }
+        NatsMode::Subscribe => {
+            let subscriber =
+                JetStreamSubscriber::new(context, nats.stream.clone(), nats.subject_prefix.clone());
+            let processor = processor.clone();
+            let shutdown = shutdown.clone();
+            tokio::spawn(async move {
+                if let Err(e) = subscriber.run(&processor, shutdown).await {
+                    tracing::error!(error = %e, "JetStream subscriber stopped");
+                }
+            });
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() {
-    // 1. Load configuration
-    let config = Config::from_env();
+    // 1. Load configuration; tracing isn't set up yet, so errors go to stderr
+    let config = Config::from_env().unwrap_or_else(|e| {
+        eprintln!("{e}, refusing to start");
+        std::process::exit(1);
+    });
 
-    // 2. Initialize tracing
+    // 2. Initialize tracing, with a filter the settings file can replace
+    let (filter, filter_handle) = reload::Layer::new(
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(&config.log_level)),
+    );
     tracing_subscriber::registry()
-        .with(
-            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(&config.log_level)),
-        )
+        .with(filter)
         .with(tracing_subscriber::fmt::layer())
         .init();
+    let log_level: LogLevelReload = Arc::new(move |directive| {
+        let filter = EnvFilter::try_new(directive).map_err(|e| e.to_string())?;
+        filter_handle.reload(filter).map_err(|e| e.to_string())
+    });
 
     tracing::info!(?config, "loaded configuration");
+    let settings = live_settings(&config, log_level);
 
     // 3. Install Prometheus metrics recorder
     let prometheus_builder = metrics_exporter_prometheus::PrometheusBuilder::new();
@@ -57,23 +400,110 @@ async fn main() {
         .install_recorder()
         .expect("failed to install Prometheus recorder");
 
+    let projection_shutdown = CancellationToken::new();
+
     // 4. Create event store and application state (Postgres if DATABASE_URL set, else in-memory)
     let app = if let Some(ref database_url) = config.database_url {
         tracing::info!("connecting to PostgreSQL");
-        let store = PostgresEventStore::connect(database_url, config.db_max_connections)
+        let store =
+            PostgresEventStore::connect_without_migrations(database_url, config.db_max_connections)
+                .await
+                .expect("failed to connect to PostgreSQL");
+        prepare_schema(&store, config.migration_mode).await;
+        let schema: Arc<dyn SchemaVersionSource> = Arc::new(store.clone());
+        let query_analyzer: Arc<dyn QueryAnalyzer> = Arc::new(store.clone());
+        let idempotency_store: Arc<dyn IdempotencyStore> = Arc::new(store.clone());
+        let annotation_store: Arc<dyn AnnotationStore> = Arc::new(store.clone());
+        let stream_visibility =
+            api::stream_visibility_policy(Arc::new(store.clone()), &config.stream_visibility);
+        stream_visibility
+            .load()
             .await
-            .expect("failed to connect to PostgreSQL");
-        let (state, processor, _) = api::create_default_state(store);
+            .expect("failed to load stream visibility overrides");
+        let store = match store.clone().with_append_listener().await {
+            Ok(store) => store,
+            Err(e) => {
+                tracing::warn!(error = %e, "append notifications unavailable, polling only");
+                store
+            }
+        };
+        let store = with_quotas(store, &config).await;
+        let options = StateOptions {
+            query_analyzer: Some(query_analyzer),
+            idempotency_store: Some(idempotency_store),
+            annotation_store: Some(annotation_store),
+            stream_visibility: Some(stream_visibility),
+            ..state_options(
+                &config,
+                settings.clone(),
+                store.enforcer().clone(),
+                Some(schema),
+            )
+        };
+        spawn_background_snapshots(store.clone(), &config, &projection_shutdown);
+        let (state, processor, _) = api::create_default_state_with_options(store, options);
         processor.run_catch_up().await.expect("catch-up failed");
-        api::create_app(state, metrics_handle, processor)
+        reconcile_sagas(&state).await;
+        spawn_saga_runner(&state, &projection_shutdown);
+        spawn_contention_report(&state, &config);
+        spawn_projection_updates(&processor, &settings, &projection_shutdown);
+        start_event_bus(&processor, &state.visibility, &config, &projection_shutdown).await;
+        spawn_maintenance(&state, &config, &projection_shutdown);
+        spawn_settings_reload(&state);
+        api::create_app_with_metrics(
+            state,
+            metrics_handle.clone(),
+            processor,
+            config.timeouts,
+            &config.metrics,
+        )
     } else {
         tracing::info!("using in-memory event store");
-        let store = InMemoryEventStore::new();
-        let (state, processor, _) = api::create_default_state(store);
+        let store = with_quotas(InMemoryEventStore::new(), &config).await;
+        let options = state_options(&config, settings.clone(), store.enforcer().clone(), None);
+        spawn_background_snapshots(store.clone(), &config, &projection_shutdown);
+        let (state, processor, _) = api::create_default_state_with_options(store, options);
         processor.run_catch_up().await.expect("catch-up failed");
-        api::create_app(state, metrics_handle, processor)
+        if let Some(ref target_url) = config.migrate_to_database_url {
+            let target = PostgresEventStore::connect(target_url, config.db_max_connections)
+                .await
+                .expect("failed to connect to the migration target");
+            api::register_store_migration(&state.maintenance, &state.event_store, target);
+            tracing::info!("store_migration job copies the in-memory store to PostgreSQL");
+        }
+        reconcile_sagas(&state).await;
+        spawn_saga_runner(&state, &projection_shutdown);
+        spawn_contention_report(&state, &config);
+        spawn_projection_updates(&processor, &settings, &projection_shutdown);
+        start_event_bus(&processor, &state.visibility, &config, &projection_shutdown).await;
+        spawn_maintenance(&state, &config, &projection_shutdown);
+        spawn_settings_reload(&state);
+        api::create_app_with_metrics(
+            state,
+            metrics_handle.clone(),
+            processor,
+            config.timeouts,
+            &config.metrics,
+        )
     };
 
+    // 5. Start the metrics listener, if metrics are served on their own port
+    if let Some(metrics_addr) = config.metrics_addr() {
+        tracing::info!(%metrics_addr, "starting metrics listener");
+        let listener = tokio::net::TcpListener::bind(&metrics_addr)
+            .await
+            .expect("failed to bind metrics address");
+        let metrics_app = api::create_metrics_app(metrics_handle, &config.metrics);
+        tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, metrics_app)
+                .with_graceful_shutdown(shutdown_signal())
+                .await
+            {
+                tracing::error!(error = %e, "metrics listener failed");
+            }
+        });
+    }
+
     // 7. Start server
     let addr = config.addr();
     tracing::info!(%addr, "starting API server");
@@ -85,6 +515,7 @@ async fn main() {
         .with_graceful_shutdown(shutdown_signal())
         .await
         .expect("server error");
+    projection_shutdown.cancel();
 
     tracing::info!("server shut down gracefully");
 }
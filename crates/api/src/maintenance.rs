@@ -0,0 +1,727 @@
+//! Scheduled maintenance jobs.
+//!
+//! Housekeeping such as background snapshots, compaction into an archive, or
+//! pruning runs as named jobs on a [`MaintenanceScheduler`]. Each job has an
+//! optional [`Schedule`]; unscheduled jobs only run when triggered through
+//! `POST /admin/maintenance/{job}/run`. A job never overlaps itself: a run
+//! that comes due while the previous one is still going is skipped.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Datelike, DurationRound, TimeDelta, Timelike, Utc};
+use common::{Clock, SystemClock, TimerDriver, TokioTimer};
+use futures_util::FutureExt;
+use futures_util::future::BoxFuture;
+use serde::Serialize;
+use thiserror::Error;
+use tokio_util::sync::CancellationToken;
+
+/// What a job run returns: how many items it processed, or why it failed.
+pub type JobResult = Result<usize, String>;
+
+type JobFn = Arc<dyn Fn() -> BoxFuture<'static, JobResult> + Send + Sync>;
+
+/// Errors from scheduling or triggering maintenance jobs.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum MaintenanceError {
+    /// No job is registered under the name.
+    #[error("no maintenance job named {0}")]
+    UnknownJob(String),
+
+    /// The job is running and can't be started again until it finishes.
+    #[error("maintenance job {0} is already running")]
+    AlreadyRunning(String),
+
+    /// A schedule expression couldn't be parsed.
+    #[error("invalid schedule {expression:?}: {reason}")]
+    InvalidSchedule { expression: String, reason: String },
+}
+
+/// When a job runs.
+///
+/// Parsed from `@every <n>s|m|h`, `@hourly`, `@daily`, or a five-field cron
+/// expression (`minute hour day-of-month month day-of-week`, evaluated in
+/// UTC). Cron fields accept `*`, numbers, ranges (`1-5`), lists (`1,15`),
+/// and steps (`*/10`, `0-30/5`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Schedule {
+    /// A fixed interval after each time the job comes due.
+    Every(Duration),
+    /// Matching minutes of a cron expression.
+    Cron(CronSchedule),
+}
+
+impl Schedule {
+    /// Returns the first time after `after` the job comes due.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Schedule::Every(interval) => TimeDelta::from_std(*interval)
+                .ok()
+                .map(|delta| after + delta),
+            Schedule::Cron(cron) => cron.next_after(after),
+        }
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Schedule::Every(interval) => write!(f, "@every {}s", interval.as_secs()),
+            Schedule::Cron(cron) => f.write_str(&cron.expression),
+        }
+    }
+}
+
+impl FromStr for Schedule {
+    type Err = MaintenanceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let invalid = |reason: &str| MaintenanceError::InvalidSchedule {
+            expression: s.to_string(),
+            reason: reason.to_string(),
+        };
+        match s {
+            "@hourly" => return Schedule::from_str("0 * * * *"),
+            "@daily" => return Schedule::from_str("0 0 * * *"),
+            _ => {}
+        }
+        if let Some(interval) = s.strip_prefix("@every ") {
+            let interval = interval.trim();
+            let (count, unit) = interval.split_at(interval.len().saturating_sub(1));
+            let count: u64 = count
+                .parse()
+                .map_err(|_| invalid("expected a count like 30s, 15m, or 6h"))?;
+            let secs = match unit {
+                "s" => count,
+                "m" => count * 60,
+                "h" => count * 3600,
+                _ => return Err(invalid("interval unit must be s, m, or h")),
+            };
+            if secs == 0 {
+                return Err(invalid("interval must be positive"));
+            }
+            return Ok(Schedule::Every(Duration::from_secs(secs)));
+        }
+        CronSchedule::parse(s)
+            .map(Schedule::Cron)
+            .map_err(|reason| MaintenanceError::InvalidSchedule {
+                expression: s.to_string(),
+                reason,
+            })
+    }
+}
+
+/// A five-field cron expression, each field held as a bitmask of the
+/// values it matches.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    expression: String,
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    /// Whether day-of-month and day-of-week were both restricted, in which
+    /// case a day matching either one is due, as in standard cron.
+    either_day: bool,
+}
+
+impl CronSchedule {
+    /// Latest minute searched for a match, to bound impossible expressions
+    /// such as `0 0 31 2 *`.
+    const SEARCH_LIMIT: TimeDelta = TimeDelta::days(366 * 4);
+
+    fn parse(expression: &str) -> Result<Self, String> {
+        let fields: Vec<_> = expression.split_whitespace().collect();
+        let [minute, hour, dom, month, dow] = fields[..] else {
+            return Err(format!("expected 5 fields, found {}", fields.len()));
+        };
+        // Sunday is both 0 and 7
+        let mut days_of_week = parse_field(dow, 0, 7)?;
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week |= 1;
+        }
+        Ok(Self {
+            expression: fields.join(" "),
+            minutes: parse_field(minute, 0, 59)?,
+            hours: parse_field(hour, 0, 23)?,
+            days_of_month: parse_field(dom, 1, 31)?,
+            months: parse_field(month, 1, 12)?,
+            days_of_week,
+            either_day: dom != "*" && dow != "*",
+        })
+    }
+
+    fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let limit = after + Self::SEARCH_LIMIT;
+        let mut t = after.duration_trunc(TimeDelta::minutes(1)).ok()? + TimeDelta::minutes(1);
+        while t <= limit {
+            if !self.day_matches(t) {
+                t = (t + TimeDelta::days(1))
+                    .duration_trunc(TimeDelta::days(1))
+                    .ok()?;
+            } else if !in_mask(self.hours, t.hour()) {
+                t = (t + TimeDelta::hours(1))
+                    .duration_trunc(TimeDelta::hours(1))
+                    .ok()?;
+            } else if !in_mask(self.minutes, t.minute()) {
+                t += TimeDelta::minutes(1);
+            } else {
+                return Some(t);
+            }
+        }
+        None
+    }
+
+    fn day_matches(&self, t: DateTime<Utc>) -> bool {
+        if !in_mask(self.months, t.month()) {
+            return false;
+        }
+        let dom = in_mask(self.days_of_month, t.day());
+        let dow = in_mask(self.days_of_week, t.weekday().num_days_from_sunday());
+        if self.either_day {
+            dom || dow
+        } else {
+            dom && dow
+        }
+    }
+}
+
+fn in_mask(mask: u64, value: u32) -> bool {
+    mask & (1 << value) != 0
+}
+
+/// Parses one cron field into a bitmask of the values in `min..=max` it
+/// matches.
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64, String> {
+    let mut mask = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (
+                range,
+                step.parse::<u32>()
+                    .ok()
+                    .filter(|step| *step > 0)
+                    .ok_or_else(|| format!("invalid step in {part:?}"))?,
+            ),
+            None => (part, 1),
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (parse_value(start, min, max)?, parse_value(end, min, max)?)
+        } else {
+            let value = parse_value(range, min, max)?;
+            // `5/15` means every 15 starting at 5
+            (value, if step > 1 { max } else { value })
+        };
+        if start > end {
+            return Err(format!("range {range:?} runs backwards"));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            mask |= 1 << value;
+        }
+    }
+    Ok(mask)
+}
+
+fn parse_value(value: &str, min: u32, max: u32) -> Result<u32, String> {
+    value
+        .parse()
+        .ok()
+        .filter(|v| (min..=max).contains(v))
+        .ok_or_else(|| format!("{value:?} is not in {min}-{max}"))
+}
+
+/// A job and the schedule it runs on, as configured.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JobSchedule {
+    pub job: String,
+    pub schedule: Schedule,
+}
+
+impl JobSchedule {
+    /// Parses a `;`-separated list of `job=schedule` entries, e.g.
+    /// `snapshots=*/10 * * * *;compaction=@daily`.
+    pub fn parse_list(list: &str) -> Result<Vec<Self>, MaintenanceError> {
+        list.split(';')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let (job, schedule) =
+                    entry
+                        .split_once('=')
+                        .ok_or_else(|| MaintenanceError::InvalidSchedule {
+                            expression: entry.to_string(),
+                            reason: "expected job=schedule".to_string(),
+                        })?;
+                Ok(Self {
+                    job: job.trim().to_string(),
+                    schedule: schedule.parse()?,
+                })
+            })
+            .collect()
+    }
+}
+
+/// How a job run ended.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum RunOutcome {
+    Succeeded { items: usize },
+    Failed { error: String },
+}
+
+/// One completed run of a job.
+#[derive(Debug, Clone, Serialize)]
+pub struct JobRun {
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub duration_ms: u64,
+    #[serde(flatten)]
+    pub outcome: RunOutcome,
+}
+
+/// A job's schedule and run history, as served at `/admin/maintenance`.
+#[derive(Debug, Clone, Serialize)]
+pub struct JobStatus {
+    pub name: String,
+    /// The schedule expression; unscheduled jobs only run when triggered.
+    pub schedule: Option<String>,
+    pub running: bool,
+    pub next_run_at: Option<DateTime<Utc>>,
+    pub last_run: Option<JobRun>,
+    pub runs: u64,
+    pub failures: u64,
+    /// Due runs skipped because the previous run hadn't finished.
+    pub skipped: u64,
+}
+
+#[derive(Default)]
+struct JobState {
+    schedule: Option<Schedule>,
+    next_run_at: Option<DateTime<Utc>>,
+    last_run: Option<JobRun>,
+    runs: u64,
+    failures: u64,
+    skipped: u64,
+}
+
+struct Job {
+    name: String,
+    run: JobFn,
+    /// Held for the duration of a run, so a job never overlaps itself.
+    lock: tokio::sync::Mutex<()>,
+    running: AtomicBool,
+    state: Mutex<JobState>,
+}
+
+impl Job {
+    fn status(&self) -> JobStatus {
+        let state = self.state.lock().expect("maintenance job state poisoned");
+        JobStatus {
+            name: self.name.clone(),
+            schedule: state.schedule.as_ref().map(ToString::to_string),
+            running: self.running.load(Ordering::SeqCst),
+            next_run_at: state.next_run_at,
+            last_run: state.last_run.clone(),
+            runs: state.runs,
+            failures: state.failures,
+            skipped: state.skipped,
+        }
+    }
+
+    async fn run(&self, clock: &dyn Clock) -> Result<JobRun, MaintenanceError> {
+        let Ok(_guard) = self.lock.try_lock() else {
+            self.state
+                .lock()
+                .expect("maintenance job state poisoned")
+                .skipped += 1;
+            metrics::counter!("maintenance_job_runs", "job" => self.name.clone(), "outcome" => "skipped")
+                .increment(1);
+            return Err(MaintenanceError::AlreadyRunning(self.name.clone()));
+        };
+
+        self.running.store(true, Ordering::SeqCst);
+        let started_at = clock.now();
+        let start = Instant::now();
+        // A panicking job is recorded as a failure instead of poisoning the
+        // scheduler
+        let result = std::panic::AssertUnwindSafe((self.run)())
+            .catch_unwind()
+            .await
+            .unwrap_or_else(|_| Err("job panicked".to_string()));
+        let elapsed = start.elapsed();
+        self.running.store(false, Ordering::SeqCst);
+
+        let outcome = match result {
+            Ok(items) => {
+                tracing::info!(job = %self.name, items, duration_ms = elapsed.as_millis() as u64, "maintenance job finished");
+                RunOutcome::Succeeded { items }
+            }
+            Err(error) => {
+                tracing::warn!(job = %self.name, %error, "maintenance job failed");
+                RunOutcome::Failed { error }
+            }
+        };
+        let outcome_label = match outcome {
+            RunOutcome::Succeeded { .. } => "succeeded",
+            RunOutcome::Failed { .. } => "failed",
+        };
+        metrics::counter!("maintenance_job_runs", "job" => self.name.clone(), "outcome" => outcome_label)
+            .increment(1);
+        metrics::histogram!("maintenance_job_duration_seconds", "job" => self.name.clone())
+            .record(elapsed.as_secs_f64());
+
+        let run = JobRun {
+            started_at,
+            finished_at: clock.now(),
+            duration_ms: elapsed.as_millis() as u64,
+            outcome,
+        };
+        let mut state = self.state.lock().expect("maintenance job state poisoned");
+        state.runs += 1;
+        if matches!(run.outcome, RunOutcome::Failed { .. }) {
+            state.failures += 1;
+        }
+        state.last_run = Some(run.clone());
+        Ok(run)
+    }
+}
+
+/// Registry and runner of maintenance jobs.
+///
+/// Clones share the registry, so jobs registered or rescheduled through one
+/// handle are seen by the running scheduler.
+#[derive(Clone)]
+pub struct MaintenanceScheduler {
+    jobs: Arc<RwLock<BTreeMap<String, Arc<Job>>>>,
+    clock: Arc<dyn Clock>,
+    timer: Arc<dyn TimerDriver>,
+}
+
+impl Default for MaintenanceScheduler {
+    fn default() -> Self {
+        Self {
+            jobs: Arc::default(),
+            clock: Arc::new(SystemClock),
+            timer: Arc::new(TokioTimer),
+        }
+    }
+}
+
+impl MaintenanceScheduler {
+    /// How often the scheduler checks for due jobs.
+    const TICK: Duration = Duration::from_secs(1);
+
+    /// Creates a scheduler with no jobs.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads the time from `clock` and waits between checks on `timer`
+    /// instead of the system clock, so tests can drive the schedule with a
+    /// [`common::VirtualClock`].
+    pub fn with_time(mut self, clock: Arc<dyn Clock>, timer: Arc<dyn TimerDriver>) -> Self {
+        self.clock = clock;
+        self.timer = timer;
+        self
+    }
+
+    /// Registers a job, unscheduled. Registering a name again replaces the
+    /// job and resets its history.
+    pub fn register<F, Fut>(&self, name: impl Into<String>, job: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = JobResult> + Send + 'static,
+    {
+        let name = name.into();
+        let job = Job {
+            name: name.clone(),
+            run: Arc::new(move || job().boxed()),
+            lock: tokio::sync::Mutex::new(()),
+            running: AtomicBool::new(false),
+            state: Mutex::new(JobState::default()),
+        };
+        self.jobs
+            .write()
+            .expect("maintenance jobs poisoned")
+            .insert(name, Arc::new(job));
+    }
+
+    /// Sets or clears a job's schedule. The next run is computed from now.
+    pub fn set_schedule(
+        &self,
+        name: &str,
+        schedule: Option<Schedule>,
+    ) -> Result<(), MaintenanceError> {
+        let job = self.job(name)?;
+        let mut state = job.state.lock().expect("maintenance job state poisoned");
+        state.next_run_at = schedule
+            .as_ref()
+            .and_then(|s| s.next_after(self.clock.now()));
+        state.schedule = schedule;
+        Ok(())
+    }
+
+    /// Returns the status of every job, ordered by name.
+    pub fn statuses(&self) -> Vec<JobStatus> {
+        let jobs = self.jobs.read().expect("maintenance jobs poisoned");
+        jobs.values().map(|job| job.status()).collect()
+    }
+
+    /// Runs a job now, outside its schedule.
+    pub async fn run_now(&self, name: &str) -> Result<JobRun, MaintenanceError> {
+        self.job(name)?.run(self.clock.as_ref()).await
+    }
+
+    /// Starts due jobs until `shutdown` is cancelled.
+    ///
+    /// Each run is spawned, so a slow job doesn't hold back the others.
+    /// Runs in flight at shutdown are left to finish on their own.
+    pub async fn run_scheduled(&self, shutdown: CancellationToken) {
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => return,
+                _ = self.timer.sleep(Self::TICK) => {}
+            }
+            for job in self.take_due(self.clock.now()) {
+                let clock = self.clock.clone();
+                tokio::spawn(async move {
+                    // Overlaps are counted as skipped by the job itself
+                    let _ = job.run(clock.as_ref()).await;
+                });
+            }
+        }
+    }
+
+    /// Returns the jobs due at `now` and moves each one's next run forward.
+    fn take_due(&self, now: DateTime<Utc>) -> Vec<Arc<Job>> {
+        let jobs = self.jobs.read().expect("maintenance jobs poisoned");
+        jobs.values()
+            .filter(|job| {
+                let mut state = job.state.lock().expect("maintenance job state poisoned");
+                if state.next_run_at.is_none_or(|at| at > now) {
+                    return false;
+                }
+                state.next_run_at = state.schedule.as_ref().and_then(|s| s.next_after(now));
+                true
+            })
+            .cloned()
+            .collect()
+    }
+
+    fn job(&self, name: &str) -> Result<Arc<Job>, MaintenanceError> {
+        self.jobs
+            .read()
+            .expect("maintenance jobs poisoned")
+            .get(name)
+            .cloned()
+            .ok_or_else(|| MaintenanceError::UnknownJob(name.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use common::VirtualClock;
+
+    fn at(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, mo, d, h, mi, 0).unwrap()
+    }
+
+    #[test]
+    fn test_cron_next_run() {
+        let every_15: Schedule = "*/15 * * * *".parse().unwrap();
+        assert_eq!(
+            every_15.next_after(at(2025, 3, 1, 10, 7)),
+            Some(at(2025, 3, 1, 10, 15))
+        );
+        assert_eq!(
+            every_15.next_after(at(2025, 3, 1, 10, 45)),
+            Some(at(2025, 3, 1, 11, 0))
+        );
+
+        // 03:30 on weekdays; 2025-03-01 is a Saturday
+        let nightly: Schedule = "30 3 * * 1-5".parse().unwrap();
+        assert_eq!(
+            nightly.next_after(at(2025, 3, 1, 0, 0)),
+            Some(at(2025, 3, 3, 3, 30))
+        );
+
+        // Day-of-month or day-of-week, as in standard cron
+        let either: Schedule = "0 0 15 * 0".parse().unwrap();
+        assert_eq!(
+            either.next_after(at(2025, 3, 1, 0, 0)),
+            Some(at(2025, 3, 2, 0, 0))
+        );
+
+        assert_eq!(
+            "@daily"
+                .parse::<Schedule>()
+                .unwrap()
+                .next_after(at(2025, 3, 1, 0, 0)),
+            Some(at(2025, 3, 2, 0, 0))
+        );
+        assert_eq!(
+            "@every 10m".parse::<Schedule>().unwrap(),
+            Schedule::Every(Duration::from_secs(600))
+        );
+        assert_eq!(
+            "0 0 31 2 *"
+                .parse::<Schedule>()
+                .unwrap()
+                .next_after(at(2025, 1, 1, 0, 0)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_job_schedule_list_parsing() {
+        let list = JobSchedule::parse_list("snapshots=*/10 * * * *; compaction=@daily;").unwrap();
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[0].job, "snapshots");
+        assert_eq!(list[0].schedule.to_string(), "*/10 * * * *");
+        assert_eq!(list[1].job, "compaction");
+        assert!(JobSchedule::parse_list("snapshots").is_err());
+        assert!(JobSchedule::parse_list("snapshots=@weekly").is_err());
+    }
+
+    #[test]
+    fn test_invalid_schedules_are_rejected() {
+        for expression in [
+            "",
+            "* * * *",
+            "60 * * * *",
+            "*/0 * * * *",
+            "5-1 * * * *",
+            "@every 0s",
+            "@every 5d",
+        ] {
+            assert!(
+                matches!(
+                    expression.parse::<Schedule>(),
+                    Err(MaintenanceError::InvalidSchedule { .. })
+                ),
+                "{expression:?} should be rejected"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_job_runs_are_recorded_and_never_overlap() {
+        let scheduler = MaintenanceScheduler::new();
+        let release = Arc::new(tokio::sync::Notify::new());
+        let gate = release.clone();
+        scheduler.register("compaction", move || {
+            let gate = gate.clone();
+            async move {
+                gate.notified().await;
+                Ok(3)
+            }
+        });
+        scheduler.register("broken", || async { Err("disk full".to_string()) });
+
+        let running = {
+            let scheduler = scheduler.clone();
+            tokio::spawn(async move { scheduler.run_now("compaction").await })
+        };
+        while !scheduler.statuses()[1].running {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(
+            scheduler.run_now("compaction").await.unwrap_err(),
+            MaintenanceError::AlreadyRunning("compaction".to_string())
+        );
+        release.notify_one();
+        let run = running.await.unwrap().unwrap();
+        assert_eq!(run.outcome, RunOutcome::Succeeded { items: 3 });
+
+        let run = scheduler.run_now("broken").await.unwrap();
+        assert_eq!(
+            run.outcome,
+            RunOutcome::Failed {
+                error: "disk full".to_string()
+            }
+        );
+        assert_eq!(
+            scheduler.run_now("vacuum").await.unwrap_err(),
+            MaintenanceError::UnknownJob("vacuum".to_string())
+        );
+
+        let statuses = scheduler.statuses();
+        assert_eq!(statuses[0].name, "broken");
+        assert_eq!((statuses[0].runs, statuses[0].failures), (1, 1));
+        assert_eq!(statuses[1].name, "compaction");
+        assert_eq!((statuses[1].runs, statuses[1].skipped), (1, 1));
+        assert!(!statuses[1].running);
+    }
+
+    #[test]
+    fn test_due_jobs_move_to_their_next_run() {
+        let scheduler = MaintenanceScheduler::new();
+        scheduler.register("snapshots", || async { Ok(0) });
+        scheduler.register("manual", || async { Ok(0) });
+        scheduler
+            .set_schedule("snapshots", Some("@every 60s".parse().unwrap()))
+            .unwrap();
+        assert!(scheduler.set_schedule("vacuum", None).is_err());
+
+        let next = scheduler.statuses()[1].next_run_at.unwrap();
+        assert!(scheduler.take_due(next - TimeDelta::seconds(1)).is_empty());
+        let due = scheduler.take_due(next);
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].name, "snapshots");
+        assert_eq!(
+            scheduler.statuses()[1].next_run_at,
+            Some(next + TimeDelta::seconds(60))
+        );
+        assert!(scheduler.statuses()[0].next_run_at.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_scheduled_runs_follow_virtual_time() {
+        let time = VirtualClock::new(at(2025, 3, 1, 10, 0));
+        let scheduler =
+            MaintenanceScheduler::new().with_time(Arc::new(time.clone()), Arc::new(time.clone()));
+        scheduler.register("snapshots", || async { Ok(2) });
+        scheduler
+            .set_schedule("snapshots", Some("@every 60s".parse().unwrap()))
+            .unwrap();
+
+        let shutdown = CancellationToken::new();
+        let worker = {
+            let scheduler = scheduler.clone();
+            let shutdown = shutdown.clone();
+            tokio::spawn(async move { scheduler.run_scheduled(shutdown).await })
+        };
+        time.wait_for_sleepers(1).await;
+
+        time.advance(Duration::from_secs(59));
+        time.wait_for_sleepers(1).await;
+        assert_eq!(scheduler.statuses()[0].runs, 0);
+
+        time.advance(Duration::from_secs(1));
+        time.wait_for_sleepers(1).await;
+        while scheduler.statuses()[0].runs == 0 {
+            tokio::task::yield_now().await;
+        }
+        let status = &scheduler.statuses()[0];
+        assert_eq!(
+            status.last_run.as_ref().unwrap().started_at,
+            at(2025, 3, 1, 10, 1)
+        );
+        assert_eq!(status.next_run_at, Some(at(2025, 3, 1, 10, 2)));
+
+        shutdown.cancel();
+        worker.await.unwrap();
+    }
+}
@@ -0,0 +1,1174 @@
+//! Administrative endpoints for runtime diagnostics.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use axum::Json;
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
+use common::AggregateId;
+use domain::{
+    Change, ContentionReport, Decision, FeatureFlag, FlagScope, ImportReport, ImportValidator,
+    Money, OrderCommands, OrderItem, OrderQueries, ProductId, SetFeatureFlag,
+};
+use event_store::{
+    DeprecationReport, EventAnnotation, EventEnvelope, EventId, EventQuery, EventStore,
+    QueryAnalysis, QuotaEnforcer, QuotaLimits, QuotaUsage, StreamVisibility, Version,
+};
+use projections::{ProjectionError, SamplingConfig};
+use saga::causation::{CAUSATION_ID_KEY, SAGA_STEP_KEY};
+use saga::trace::TRACE_ID_KEY;
+use saga::{ReservationCheck, ReservationReport};
+use serde::{Deserialize, Serialize};
+
+use crate::analytics::ParquetExport;
+use crate::breaker::{BreakerMode, BreakerStatus};
+use crate::error::ApiError;
+use crate::maintenance::{JobRun, JobStatus, MaintenanceError};
+use crate::routes::integrations::WEBHOOK_DELIVERY_ID_KEY;
+use crate::routes::orders::AppState;
+use crate::settings::{SettingsError, SettingsReload, Tunables};
+
+#[derive(Serialize)]
+pub struct SettingsResponse {
+    /// Settings file re-read on reload, if any.
+    pub file: Option<String>,
+    pub settings: Tunables,
+}
+
+#[derive(Serialize)]
+pub struct ProjectionStatusResponse {
+    pub name: String,
+    pub events_processed: u64,
+}
+
+#[derive(Serialize)]
+pub struct ReadModelStatsResponse {
+    pub name: &'static str,
+    pub count: usize,
+    /// Lower-bound estimate of the bytes the view holds.
+    pub memory_bytes: usize,
+}
+
+#[derive(Deserialize)]
+pub struct SetFlagRequest {
+    #[serde(default = "global_scope")]
+    pub scope: FlagScope,
+    pub enabled: bool,
+    pub changed_by: Option<String>,
+}
+
+fn global_scope() -> FlagScope {
+    FlagScope::Global
+}
+
+#[derive(Serialize)]
+pub struct FlagResponse {
+    pub name: String,
+    pub global: Option<bool>,
+    pub segments: std::collections::BTreeMap<String, bool>,
+    pub customers: std::collections::BTreeMap<String, bool>,
+}
+
+impl From<FeatureFlag> for FlagResponse {
+    fn from(flag: FeatureFlag) -> Self {
+        Self {
+            name: flag.name().to_string(),
+            global: flag.global(),
+            segments: flag
+                .segments()
+                .iter()
+                .map(|(k, v)| (k.clone(), *v))
+                .collect(),
+            customers: flag
+                .customers()
+                .iter()
+                .map(|(k, v)| (k.to_string(), *v))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SamplingRequest {
+    pub enabled: bool,
+    /// Fraction of events to sample (0.0–1.0). Keeps the current rate if omitted.
+    pub rate: Option<f64>,
+}
+
+#[derive(Serialize)]
+pub struct SamplingResponse {
+    pub enabled: bool,
+    pub rate: f64,
+}
+
+impl From<SamplingConfig> for SamplingResponse {
+    fn from(config: SamplingConfig) -> Self {
+        Self {
+            enabled: config.enabled,
+            rate: config.rate,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ContentionQuery {
+    /// Number of hot aggregates to return.
+    #[serde(default = "default_top")]
+    pub top: usize,
+}
+
+fn default_top() -> usize {
+    10
+}
+
+#[derive(Deserialize)]
+pub struct RejectionsQuery {
+    /// Number of rejections to return, newest first.
+    #[serde(default = "default_rejection_limit")]
+    pub limit: usize,
+}
+
+fn default_rejection_limit() -> usize {
+    50
+}
+
+#[derive(Deserialize)]
+pub struct SetPriceRequest {
+    pub unit_price_cents: i64,
+}
+
+#[derive(Serialize)]
+pub struct PriceResponse {
+    pub product_id: String,
+    pub unit_price_cents: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DiffQuery {
+    pub from: i64,
+    pub to: i64,
+}
+
+#[derive(Serialize)]
+pub struct ValueChange<T> {
+    pub from: T,
+    pub to: T,
+}
+
+impl<T, U: Into<T>> From<Change<U>> for ValueChange<T> {
+    fn from(change: Change<U>) -> Self {
+        Self {
+            from: change.from.into(),
+            to: change.to.into(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct DiffItemResponse {
+    pub product_id: String,
+    pub product_name: String,
+    pub quantity: u32,
+    pub unit_price_cents: i64,
+}
+
+impl From<OrderItem> for DiffItemResponse {
+    fn from(item: OrderItem) -> Self {
+        Self {
+            product_id: item.product_id.to_string(),
+            product_name: item.product_name,
+            quantity: item.quantity,
+            unit_price_cents: item.unit_price.cents(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ItemChangeResponse {
+    pub product_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quantity: Option<ValueChange<u32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unit_price_cents: Option<ValueChange<i64>>,
+}
+
+#[derive(Serialize)]
+pub struct OrderDiffResponse {
+    pub order_id: String,
+    pub from_version: i64,
+    pub to_version: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state: Option<ValueChange<String>>,
+    pub items_added: Vec<DiffItemResponse>,
+    pub items_removed: Vec<DiffItemResponse>,
+    pub items_changed: Vec<ItemChangeResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_amount_cents: Option<ValueChange<i64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub carrier: Option<ValueChange<Option<String>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tracking_number: Option<ValueChange<Option<String>>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TimelineQuery {
+    /// First version to include; defaults to the first event.
+    pub from: Option<i64>,
+    /// Last version to include; defaults to the latest event.
+    pub to: Option<i64>,
+}
+
+/// An order's state after one event.
+#[derive(Serialize)]
+pub struct TimelineEntryResponse {
+    pub version: i64,
+    pub state: String,
+    pub item_count: usize,
+    pub total_amount_cents: i64,
+}
+
+#[derive(Serialize)]
+pub struct OrderTimelineResponse {
+    pub order_id: String,
+    pub entries: Vec<TimelineEntryResponse>,
+}
+
+/// An order or saga event in a lineage report, with the metadata linking it
+/// to the others.
+#[derive(Serialize)]
+pub struct LineageEventResponse {
+    pub event_id: String,
+    pub event_type: String,
+    pub aggregate_type: String,
+    pub aggregate_id: String,
+    pub version: i64,
+    /// Position in the global log.
+    pub sequence: Option<u64>,
+    pub timestamp: DateTime<Utc>,
+    /// The saga event that decided this one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub causation_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub saga_step: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub annotations: Vec<AnnotationResponse>,
+}
+
+/// A saga started for the order, with the external references it holds.
+#[derive(Serialize)]
+pub struct LineageSagaResponse {
+    pub saga_id: String,
+    pub state: String,
+    /// Reservation ID per product.
+    pub reservations: BTreeMap<String, String>,
+    pub payment_id: Option<String>,
+    pub tracking_number: Option<String>,
+}
+
+/// How far a projection has read into the order's events.
+#[derive(Serialize)]
+pub struct LineageProjectionResponse {
+    pub name: String,
+    pub last_sequence: u64,
+    /// The order's events at or before `last_sequence`.
+    pub consumed_events: usize,
+    /// The order's events the projection hasn't reached yet.
+    pub pending_events: usize,
+}
+
+/// A carrier webhook delivery applied to the order.
+#[derive(Serialize)]
+pub struct LineageWebhookResponse {
+    pub delivery_id: String,
+    /// The order event the delivery produced.
+    pub event_id: String,
+    pub event_type: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+pub struct OrderLineageResponse {
+    pub order_id: String,
+    /// Order and saga events, in log order.
+    pub events: Vec<LineageEventResponse>,
+    pub sagas: Vec<LineageSagaResponse>,
+    pub projections: Vec<LineageProjectionResponse>,
+    pub webhook_deliveries: Vec<LineageWebhookResponse>,
+}
+
+#[derive(Deserialize)]
+pub struct AnnotateEventRequest {
+    /// Who is writing the note.
+    pub author: String,
+    pub note: String,
+}
+
+/// An operator's note on an event.
+#[derive(Serialize)]
+pub struct AnnotationResponse {
+    pub annotation_id: String,
+    pub event_id: String,
+    pub author: String,
+    pub note: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<EventAnnotation> for AnnotationResponse {
+    fn from(annotation: EventAnnotation) -> Self {
+        Self {
+            annotation_id: annotation.annotation_id.to_string(),
+            event_id: annotation.event_id.to_string(),
+            author: annotation.author,
+            note: annotation.note,
+            created_at: annotation.created_at,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SetStreamVisibilityRequest {
+    /// `normal`, `internal`, or `hidden`; null returns the stream to its
+    /// aggregate type's default.
+    pub visibility: Option<StreamVisibility>,
+}
+
+#[derive(Serialize)]
+pub struct StreamVisibilityResponse {
+    pub aggregate_id: String,
+    /// The stream's override, if it has one.
+    pub visibility: Option<StreamVisibility>,
+}
+
+#[derive(Serialize)]
+pub struct StreamVisibilitiesResponse {
+    /// Default visibility by aggregate type; unlisted types are normal.
+    pub aggregate_types: BTreeMap<String, StreamVisibility>,
+    /// Streams overriding their type's default.
+    pub streams: BTreeMap<String, StreamVisibility>,
+}
+
+/// How a reserved order's reservations compared with the inventory
+/// service's.
+#[derive(Serialize)]
+pub struct ReservationReportResponse {
+    pub order_id: String,
+    /// `held`, `renewed`, or `cancelled`.
+    pub outcome: &'static str,
+    /// Line items whose lapsed reservations were replaced.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub renewed: Vec<String>,
+    /// The lapsed line item that couldn't be reserved again.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub out_of_stock: Option<String>,
+}
+
+impl From<ReservationReport> for ReservationReportResponse {
+    fn from(report: ReservationReport) -> Self {
+        let outcome = report.check.as_str();
+        let (renewed, out_of_stock) = match report.check {
+            ReservationCheck::Held => (Vec::new(), None),
+            ReservationCheck::Renewed { products } => {
+                (products.iter().map(ToString::to_string).collect(), None)
+            }
+            ReservationCheck::Cancelled { product_id } => {
+                (Vec::new(), Some(product_id.to_string()))
+            }
+        };
+        Self {
+            order_id: report.order_id.to_string(),
+            outcome,
+            renewed,
+            out_of_stock,
+        }
+    }
+}
+
+/// Longest note an event annotation may carry.
+const MAX_ANNOTATION_LEN: usize = 2000;
+
+/// GET /admin/sampling — current projection event sampling configuration.
+pub async fn get_sampling<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+) -> Json<SamplingResponse> {
+    Json(state.projection_processor.sampler().config().into())
+}
+
+/// PUT /admin/sampling — enable/disable projection event sampling at runtime.
+#[tracing::instrument(skip(state, req))]
+pub async fn set_sampling<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+    Json(req): Json<SamplingRequest>,
+) -> Result<Json<SamplingResponse>, ApiError> {
+    let sampler = state.projection_processor.sampler();
+    let rate = req.rate.unwrap_or(sampler.config().rate);
+    if !(0.0..=1.0).contains(&rate) {
+        return Err(ApiError::BadRequest(format!(
+            "Invalid rate: {rate} (must be between 0.0 and 1.0)"
+        )));
+    }
+
+    sampler.configure(req.enabled, rate);
+    tracing::info!(enabled = req.enabled, rate, "projection sampling updated");
+
+    Ok(Json(sampler.config().into()))
+}
+
+/// GET /admin/projections — registered projections and their positions.
+pub async fn list_projections<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+) -> Json<Vec<ProjectionStatusResponse>> {
+    let registrations = state.projection_processor.registrations().await;
+    Json(
+        registrations
+            .into_iter()
+            .map(|(name, position)| ProjectionStatusResponse {
+                name,
+                events_processed: position.events_processed,
+            })
+            .collect(),
+    )
+}
+
+/// GET /admin/read-models — entry counts and memory estimates of the
+/// enabled views. 503 if a view can't be read in time.
+pub async fn read_model_stats<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+) -> Result<Json<Vec<ReadModelStatsResponse>>, ApiError> {
+    let views = state.views.read_models();
+    let mut stats = Vec::with_capacity(views.len());
+    for view in views {
+        stats.push(ReadModelStatsResponse {
+            name: view.name(),
+            count: view.count().await?,
+            memory_bytes: view.memory_usage().await?,
+        });
+    }
+    Ok(Json(stats))
+}
+
+/// DELETE /admin/projections/:name — stop delivering events to a projection.
+#[tracing::instrument(skip(state))]
+pub async fn deregister_projection<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    match state.projection_processor.deregister(&name).await {
+        Ok(_) => Ok(StatusCode::NO_CONTENT),
+        Err(ProjectionError::NotRegistered(_)) => Err(ApiError::NotFound(format!(
+            "Projection {name} not registered"
+        ))),
+        Err(e) => Err(ApiError::Internal(e.to_string())),
+    }
+}
+
+/// GET /admin/flags — all feature flags and their settings.
+pub async fn list_flags<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+) -> Result<Json<Vec<FlagResponse>>, ApiError> {
+    let flags = state.feature_flags.list_flags().await?;
+    Ok(Json(flags.into_iter().map(FlagResponse::from).collect()))
+}
+
+/// PUT /admin/flags/:name — turn a feature flag on or off for a scope.
+#[tracing::instrument(skip(state, req))]
+pub async fn set_flag<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+    Path(name): Path<String>,
+    Json(req): Json<SetFlagRequest>,
+) -> Result<Json<FlagResponse>, ApiError> {
+    let mut cmd = SetFeatureFlag::new(name, req.scope, req.enabled);
+    cmd.changed_by = req.changed_by;
+
+    let result = state.feature_flags.set_flag(cmd).await?;
+    Ok(Json(result.aggregate.into()))
+}
+
+/// GET /admin/contention — concurrency conflicts in the current report window
+/// and the aggregates with the most of them.
+pub async fn contention<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+    Query(query): Query<ContentionQuery>,
+) -> Json<ContentionReport> {
+    Json(state.contention.report(query.top))
+}
+
+/// GET /admin/decisions/rejections — the most recent command rejections.
+///
+/// Empty unless the server keeps a rejection buffer.
+pub async fn rejections<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+    Query(query): Query<RejectionsQuery>,
+) -> Json<Vec<Decision>> {
+    Json(state.decisions.recent_rejections(query.limit))
+}
+
+/// GET /admin/schemas/deprecations — reads of each deprecated event schema
+/// version since startup.
+pub async fn schema_deprecations<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+) -> Json<DeprecationReport> {
+    Json(state.schema_deprecations.report())
+}
+
+#[derive(Deserialize)]
+pub struct SetBreakerRequest {
+    pub mode: BreakerMode,
+}
+
+/// GET /admin/breaker — whether commands are being shed, and the failure
+/// counts of the current window.
+pub async fn get_breaker<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+) -> Json<BreakerStatus> {
+    Json(state.command_breaker.status())
+}
+
+/// PUT /admin/breaker — force degraded mode on (`degraded`) or off
+/// (`normal`), or hand control back to the failure rates (`auto`).
+#[tracing::instrument(skip(state, req))]
+pub async fn set_breaker<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+    Json(req): Json<SetBreakerRequest>,
+) -> Json<BreakerStatus> {
+    state.command_breaker.set_mode(req.mode);
+    Json(state.command_breaker.status())
+}
+
+#[derive(Serialize)]
+pub struct MaintenanceResponse {
+    pub jobs: Vec<JobStatus>,
+}
+
+/// GET /admin/maintenance — every maintenance job with its schedule, next
+/// run, and how its last run went.
+pub async fn list_maintenance<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+) -> Json<MaintenanceResponse> {
+    Json(MaintenanceResponse {
+        jobs: state.maintenance.statuses(),
+    })
+}
+
+/// POST /admin/maintenance/:job/run — run a maintenance job now.
+///
+/// The run is spawned, so it finishes even if the request times out.
+/// Returns 409 if the job is already running.
+#[tracing::instrument(skip(state))]
+pub async fn run_maintenance<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+    Path(job): Path<String>,
+) -> Result<Json<JobRun>, ApiError> {
+    let maintenance = state.maintenance.clone();
+    let run = tokio::spawn(async move { maintenance.run_now(&job).await })
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+    match run {
+        Ok(run) => Ok(Json(run)),
+        Err(e @ MaintenanceError::UnknownJob(_)) => Err(ApiError::NotFound(e.to_string())),
+        Err(e @ MaintenanceError::AlreadyRunning(_)) => Err(ApiError::Conflict(e.to_string())),
+        Err(e) => Err(ApiError::BadRequest(e.to_string())),
+    }
+}
+
+/// POST /admin/export/parquet — write order history to a new Parquet file
+/// now. Returns 404 when no export directory is configured.
+#[tracing::instrument(skip(state))]
+pub async fn export_parquet<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+) -> Result<Json<ParquetExport>, ApiError> {
+    let exporter = state
+        .parquet_export
+        .as_ref()
+        .ok_or_else(|| ApiError::NotFound("Parquet export is not enabled".to_string()))?;
+    let export = exporter
+        .export_order_history(&state.event_store)
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+    Ok(Json(export))
+}
+
+/// GET /admin/settings — the reloadable settings in effect.
+pub async fn get_settings<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+) -> Json<SettingsResponse> {
+    Json(SettingsResponse {
+        file: state.settings.file().map(|p| p.display().to_string()),
+        settings: (*state.settings.current()).clone(),
+    })
+}
+
+/// POST /admin/settings/reload — re-read the settings file, as `SIGHUP`
+/// does. Returns 404 when no settings file is configured, and 400 without
+/// changing anything when the file is invalid.
+#[tracing::instrument(skip(state))]
+pub async fn reload_settings<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+) -> Result<Json<SettingsReload>, ApiError> {
+    let reload = state.settings.reload().map_err(|e| match e {
+        SettingsError::NoFile => ApiError::NotFound(e.to_string()),
+        SettingsError::Invalid { .. } | SettingsError::LogLevel(_) => {
+            ApiError::BadRequest(e.to_string())
+        }
+        SettingsError::Read { .. } => ApiError::Internal(e.to_string()),
+    })?;
+    Ok(Json(reload))
+}
+
+/// POST /admin/queries/analyze — how the database would run an event
+/// query, with index advice for its shape. The body is an event query, e.g.
+/// `{"event_types": ["OrderCreated"], "from_timestamp": "..."}`; it is
+/// planned but not run. Returns 404 when the store can't plan queries.
+#[tracing::instrument(skip(state))]
+pub async fn analyze_query<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+    Json(query): Json<EventQuery>,
+) -> Result<Json<QueryAnalysis>, ApiError> {
+    let analyzer = state.query_analyzer.as_ref().ok_or_else(|| {
+        ApiError::NotFound("query analysis needs the PostgreSQL event store".to_string())
+    })?;
+    let analysis = analyzer
+        .analyze_query(&query)
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+    Ok(Json(analysis))
+}
+
+/// GET /admin/quotas — the enforced append quotas and per-tenant usage.
+pub async fn get_quotas<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+) -> Result<Json<QuotaUsage>, ApiError> {
+    Ok(Json(quota_enforcer(&state)?.usage()))
+}
+
+/// PUT /admin/quotas — replace the append quotas. Omitted limits are
+/// removed; the new limits apply to later appends only.
+pub async fn set_quotas<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+    Json(limits): Json<QuotaLimits>,
+) -> Result<Json<QuotaUsage>, ApiError> {
+    let enforcer = quota_enforcer(&state)?;
+    enforcer.set_limits(limits);
+    tracing::info!(?limits, "quotas updated");
+    Ok(Json(enforcer.usage()))
+}
+
+fn quota_enforcer<S: EventStore, O>(state: &AppState<S, O>) -> Result<&QuotaEnforcer, ApiError> {
+    state
+        .quotas
+        .as_ref()
+        .ok_or_else(|| ApiError::NotFound("Quotas are not enabled".to_string()))
+}
+
+/// GET /admin/orders/:id/diff?from=&to= — what changed on an order between
+/// two versions, from replaying its events to each.
+#[tracing::instrument(skip(state))]
+pub async fn order_diff<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+    Path(id): Path<String>,
+    Query(query): Query<DiffQuery>,
+) -> Result<Json<OrderDiffResponse>, ApiError> {
+    let uuid = uuid::Uuid::parse_str(&id)
+        .map_err(|e| ApiError::BadRequest(format!("Invalid ID format: {e}")))?;
+    let order_id = AggregateId::from(uuid);
+
+    if query.from < 0 || query.from > query.to {
+        return Err(ApiError::BadRequest(format!(
+            "Invalid version range: {}..{}",
+            query.from, query.to
+        )));
+    }
+
+    let diff = state
+        .order_service
+        .diff_versions(order_id, Version::new(query.from), Version::new(query.to))
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Order {id} not found")))?;
+    if diff.to_version.as_i64() < query.to {
+        return Err(ApiError::BadRequest(format!(
+            "Version {} is beyond the order's latest version ({})",
+            query.to, diff.to_version
+        )));
+    }
+
+    let money = |change: Change<domain::Money>| ValueChange {
+        from: change.from.cents(),
+        to: change.to.cents(),
+    };
+    Ok(Json(OrderDiffResponse {
+        order_id: order_id.to_string(),
+        from_version: diff.from_version.as_i64(),
+        to_version: diff.to_version.as_i64(),
+        state: diff.state.map(|change| ValueChange {
+            from: change.from.to_string(),
+            to: change.to.to_string(),
+        }),
+        items_added: diff.items_added.into_iter().map(Into::into).collect(),
+        items_removed: diff.items_removed.into_iter().map(Into::into).collect(),
+        items_changed: diff
+            .items_changed
+            .into_iter()
+            .map(|change| ItemChangeResponse {
+                product_id: change.product_id.to_string(),
+                quantity: change.quantity.map(Into::into),
+                unit_price_cents: change.unit_price.map(money),
+            })
+            .collect(),
+        total_amount_cents: diff.total_amount.map(money),
+        carrier: diff.carrier.map(Into::into),
+        tracking_number: diff.tracking_number.map(Into::into),
+    }))
+}
+
+/// GET /admin/orders/:id/timeline?from=&to= — an order's state, item count,
+/// and total after each event in a version range, from replaying its events.
+#[tracing::instrument(skip(state))]
+pub async fn order_timeline<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+    Path(id): Path<String>,
+    Query(query): Query<TimelineQuery>,
+) -> Result<Json<OrderTimelineResponse>, ApiError> {
+    let uuid = uuid::Uuid::parse_str(&id)
+        .map_err(|e| ApiError::BadRequest(format!("Invalid ID format: {e}")))?;
+    let order_id = AggregateId::from(uuid);
+
+    let from = query.from.unwrap_or(1);
+    let to = query.to.unwrap_or(i64::MAX);
+    if from < 0 || from > to {
+        return Err(ApiError::BadRequest(format!(
+            "Invalid version range: {from}..{to}"
+        )));
+    }
+
+    let states = state
+        .order_service
+        .replay_range(order_id, Version::new(from), Version::new(to))
+        .await?;
+    // An empty range on an existing order is still a valid answer
+    if states.is_empty() && state.order_service.get_order(order_id).await?.is_none() {
+        return Err(ApiError::NotFound(format!("Order {id} not found")));
+    }
+
+    Ok(Json(OrderTimelineResponse {
+        order_id: order_id.to_string(),
+        entries: states
+            .into_iter()
+            .map(|(version, order)| TimelineEntryResponse {
+                version: version.as_i64(),
+                state: order.state().to_string(),
+                item_count: order.item_count(),
+                total_amount_cents: order.total_amount().cents(),
+            })
+            .collect(),
+    }))
+}
+
+/// GET /admin/orders/:id/lineage — every record tied to an order: its
+/// events and those of its sagas, the payment, reservation, and shipment
+/// references the sagas hold, how far each projection has consumed them, and
+/// the carrier webhooks applied to it.
+#[tracing::instrument(skip(state))]
+pub async fn order_lineage<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+    Path(id): Path<String>,
+) -> Result<Json<OrderLineageResponse>, ApiError> {
+    let uuid = uuid::Uuid::parse_str(&id)
+        .map_err(|e| ApiError::BadRequest(format!("Invalid ID format: {e}")))?;
+    let order_id = AggregateId::from(uuid);
+
+    let mut envelopes = state
+        .event_store
+        .get_events_for_aggregate(order_id)
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+    if envelopes.is_empty() {
+        return Err(ApiError::NotFound(format!("Order {id} not found")));
+    }
+
+    let webhook_deliveries = envelopes
+        .iter()
+        .filter_map(|envelope| {
+            let delivery_id = metadata_str(envelope, WEBHOOK_DELIVERY_ID_KEY)?;
+            Some(LineageWebhookResponse {
+                delivery_id,
+                event_id: envelope.event_id.to_string(),
+                event_type: envelope.event_type.clone(),
+                timestamp: envelope.timestamp,
+            })
+        })
+        .collect();
+
+    let mut sagas = Vec::new();
+    for saga_id in state.order_details.saga_ids(order_id).await? {
+        envelopes.extend(
+            state
+                .event_store
+                .get_events_for_aggregate(saga_id)
+                .await
+                .map_err(|e| ApiError::Internal(e.to_string()))?,
+        );
+        if let Some(saga) = state.saga_coordinator.get_saga(saga_id).await? {
+            sagas.push(LineageSagaResponse {
+                saga_id: saga_id.to_string(),
+                state: format!("{:?}", saga.state()),
+                reservations: saga
+                    .reservations()
+                    .iter()
+                    .map(|(product_id, rid)| (product_id.to_string(), rid.clone()))
+                    .collect(),
+                payment_id: saga.payment_id().map(String::from),
+                tracking_number: saga.tracking_number().map(String::from),
+            });
+        }
+    }
+    envelopes.sort_by_key(|envelope| (envelope.sequence, envelope.timestamp));
+
+    let mut annotations = annotations_by_event(&state, &envelopes).await?;
+
+    let sequences: Vec<u64> = envelopes.iter().filter_map(|e| e.sequence).collect();
+    let projections = state
+        .projection_processor
+        .registrations()
+        .await
+        .into_iter()
+        .map(|(name, position)| {
+            let consumed_events = sequences
+                .iter()
+                .filter(|&&sequence| sequence <= position.last_sequence)
+                .count();
+            LineageProjectionResponse {
+                name,
+                last_sequence: position.last_sequence,
+                consumed_events,
+                pending_events: sequences.len() - consumed_events,
+            }
+        })
+        .collect();
+
+    Ok(Json(OrderLineageResponse {
+        order_id: order_id.to_string(),
+        events: envelopes
+            .iter()
+            .map(|envelope| LineageEventResponse {
+                event_id: envelope.event_id.to_string(),
+                event_type: envelope.event_type.clone(),
+                aggregate_type: envelope.aggregate_type.clone(),
+                aggregate_id: envelope.aggregate_id.to_string(),
+                version: envelope.version.as_i64(),
+                sequence: envelope.sequence,
+                timestamp: envelope.timestamp,
+                causation_id: metadata_str(envelope, CAUSATION_ID_KEY),
+                saga_step: metadata_str(envelope, SAGA_STEP_KEY),
+                trace_id: metadata_str(envelope, TRACE_ID_KEY),
+                annotations: annotations.remove(&envelope.event_id).unwrap_or_default(),
+            })
+            .collect(),
+        sagas,
+        projections,
+        webhook_deliveries,
+    }))
+}
+
+/// Looks up the annotations on `events`, grouped by event.
+pub(crate) async fn annotations_by_event<S: EventStore, O>(
+    state: &AppState<S, O>,
+    events: &[EventEnvelope],
+) -> Result<HashMap<EventId, Vec<AnnotationResponse>>, ApiError> {
+    let event_ids: Vec<EventId> = events.iter().map(|e| e.event_id).collect();
+    let mut grouped: HashMap<EventId, Vec<AnnotationResponse>> = HashMap::new();
+    for annotation in state
+        .annotations
+        .get_annotations(&event_ids)
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?
+    {
+        grouped
+            .entry(annotation.event_id)
+            .or_default()
+            .push(annotation.into());
+    }
+    Ok(grouped)
+}
+
+fn parse_event_id(id: &str) -> Result<EventId, ApiError> {
+    uuid::Uuid::parse_str(id)
+        .map(EventId::from_uuid)
+        .map_err(|e| ApiError::BadRequest(format!("Invalid event ID format: {e}")))
+}
+
+/// POST /admin/events/:event_id/annotations — attach an operator's note to
+/// a past event, e.g. to mark where an incident began. The event itself is
+/// left untouched; the note is stored beside it.
+#[tracing::instrument(skip(state, req))]
+pub async fn annotate_event<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+    Path(event_id): Path<String>,
+    Json(req): Json<AnnotateEventRequest>,
+) -> Result<(StatusCode, Json<AnnotationResponse>), ApiError> {
+    let event_id = parse_event_id(&event_id)?;
+    let author = req.author.trim();
+    let note = req.note.trim();
+    if author.is_empty() || note.is_empty() {
+        return Err(ApiError::BadRequest(
+            "An annotation needs an author and a note".to_string(),
+        ));
+    }
+    if note.chars().count() > MAX_ANNOTATION_LEN {
+        return Err(ApiError::BadRequest(format!(
+            "Annotations are limited to {MAX_ANNOTATION_LEN} characters"
+        )));
+    }
+    let exists = state
+        .event_store
+        .get_event(event_id)
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?
+        .is_some();
+    if !exists {
+        return Err(ApiError::NotFound(format!("Event {event_id} not found")));
+    }
+
+    let annotation = EventAnnotation::new(event_id, author, note);
+    state
+        .annotations
+        .add_annotation(annotation.clone())
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+    tracing::info!(%event_id, author, "event annotated");
+    Ok((StatusCode::CREATED, Json(annotation.into())))
+}
+
+/// GET /admin/events/:event_id/annotations — the notes on an event, oldest
+/// first.
+#[tracing::instrument(skip(state))]
+pub async fn event_annotations<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+    Path(event_id): Path<String>,
+) -> Result<Json<Vec<AnnotationResponse>>, ApiError> {
+    let event_id = parse_event_id(&event_id)?;
+    let annotations = state
+        .annotations
+        .get_annotations(&[event_id])
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+    Ok(Json(annotations.into_iter().map(Into::into).collect()))
+}
+
+/// POST /admin/reservations/reconcile — check every reserved order's
+/// reservations with the inventory service, renewing lapsed ones or
+/// cancelling orders whose stock is gone. Lists only the orders changed.
+#[tracing::instrument(skip(state))]
+pub async fn reconcile_reservations<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+) -> Result<Json<Vec<ReservationReportResponse>>, ApiError> {
+    let reports = state.saga_coordinator.reconcile_all_reservations().await?;
+    Ok(Json(reports.into_iter().map(Into::into).collect()))
+}
+
+/// POST /admin/orders/:id/reservations/reconcile — check one reserved
+/// order's reservations, e.g. when the warehouse reports that one lapsed.
+/// Returns 400 if the order isn't reserved.
+#[tracing::instrument(skip(state))]
+pub async fn reconcile_order_reservations<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+    Path(id): Path<String>,
+) -> Result<Json<ReservationReportResponse>, ApiError> {
+    let uuid = uuid::Uuid::parse_str(&id)
+        .map_err(|e| ApiError::BadRequest(format!("Invalid ID format: {e}")))?;
+    let report = state
+        .saga_coordinator
+        .reconcile_reservations(AggregateId::from(uuid))
+        .await?;
+    Ok(Json(report.into()))
+}
+
+/// GET /admin/streams/visibility — list the default visibility of each
+/// configured aggregate type and every stream overriding it.
+pub async fn stream_visibilities<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+) -> Json<StreamVisibilitiesResponse> {
+    Json(StreamVisibilitiesResponse {
+        aggregate_types: state.visibility.type_visibilities().clone(),
+        streams: state
+            .visibility
+            .stream_visibilities()
+            .into_iter()
+            .map(|(id, visibility)| (id.to_string(), visibility))
+            .collect(),
+    })
+}
+
+/// PUT /admin/streams/:id/visibility — hide a stream from, or show it to,
+/// consumer-facing feeds and exports. Events stay in the store either way.
+#[tracing::instrument(skip(state, req))]
+pub async fn set_stream_visibility<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+    Path(id): Path<String>,
+    Json(req): Json<SetStreamVisibilityRequest>,
+) -> Result<Json<StreamVisibilityResponse>, ApiError> {
+    let uuid = uuid::Uuid::parse_str(&id)
+        .map_err(|e| ApiError::BadRequest(format!("Invalid ID format: {e}")))?;
+    let aggregate_id = AggregateId::from(uuid);
+    state
+        .visibility
+        .set_stream(aggregate_id, req.visibility)
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+    tracing::info!(%aggregate_id, visibility = ?req.visibility, "stream visibility changed");
+    Ok(Json(StreamVisibilityResponse {
+        aggregate_id: aggregate_id.to_string(),
+        visibility: req.visibility,
+    }))
+}
+
+fn metadata_str(envelope: &EventEnvelope, key: &str) -> Option<String> {
+    envelope
+        .metadata
+        .get(key)
+        .and_then(|value| value.as_str())
+        .map(String::from)
+}
+
+#[derive(Serialize)]
+pub struct ImportValidationResponse {
+    pub valid: bool,
+    pub violation_count: usize,
+    #[serde(flatten)]
+    pub report: ImportReport,
+}
+
+/// POST /admin/imports/validate — replay a JSON Lines order import in memory
+/// and report per-stream rule violations by line. Nothing is written.
+#[tracing::instrument(skip(body), fields(bytes = body.len()))]
+pub async fn validate_import(body: String) -> Json<ImportValidationResponse> {
+    let report = ImportValidator::validate_jsonl(&body);
+    tracing::info!(
+        streams = report.streams.len(),
+        violations = report.violation_count(),
+        "import validated"
+    );
+    Json(ImportValidationResponse {
+        valid: report.is_valid(),
+        violation_count: report.violation_count(),
+        report,
+    })
+}
+
+/// PUT /admin/prices/:product_id — set a product's current catalog price.
+#[tracing::instrument(skip(state, req))]
+pub async fn set_price<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+    Path(product_id): Path<String>,
+    Json(req): Json<SetPriceRequest>,
+) -> Result<Json<PriceResponse>, ApiError> {
+    if req.unit_price_cents <= 0 {
+        return Err(ApiError::BadRequest(format!(
+            "Invalid price: {} (must be greater than 0)",
+            req.unit_price_cents
+        )));
+    }
+
+    let product_id = ProductId::new(product_id);
+    state
+        .price_catalog
+        .set_price(product_id.clone(), Money::from_cents(req.unit_price_cents));
+    tracing::info!(%product_id, unit_price_cents = req.unit_price_cents, "catalog price updated");
+
+    Ok(Json(PriceResponse {
+        product_id: product_id.to_string(),
+        unit_price_cents: req.unit_price_cents,
+    }))
+}
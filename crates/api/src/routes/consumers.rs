@@ -0,0 +1,143 @@
+//! Named offsets for downstream consumers of the event log.
+
+use std::sync::Arc;
+
+use axum::Json;
+use axum::extract::{Path, State};
+use chrono::{DateTime, Utc};
+use domain::{OrderCommands, OrderQueries};
+use event_store::{ConsumerOffset, EventStore};
+use serde::{Deserialize, Serialize};
+
+use crate::error::ApiError;
+use crate::routes::orders::AppState;
+
+// -- Request types --
+
+#[derive(Deserialize)]
+pub struct SetOffsetRequest {
+    /// Last global sequence the consumer has processed.
+    pub sequence: u64,
+}
+
+// -- Response types --
+
+#[derive(Serialize)]
+pub struct ConsumerOffsetResponse {
+    pub consumer: String,
+    pub sequence: u64,
+    pub head_sequence: u64,
+    /// Number of log positions between the consumer and the head.
+    pub lag: u64,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ConsumerOffsetResponse {
+    fn new(offset: ConsumerOffset, head_sequence: u64) -> Self {
+        let lag = offset.lag(head_sequence);
+        metrics::gauge!("consumer_lag", "consumer" => offset.consumer.clone()).set(lag as f64);
+        Self {
+            consumer: offset.consumer,
+            sequence: offset.sequence,
+            head_sequence,
+            lag,
+            updated_at: offset.updated_at,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ConsumersResponse {
+    pub head_sequence: u64,
+    pub consumers: Vec<ConsumerOffsetResponse>,
+}
+
+// -- Handlers --
+
+/// GET /consumers — every consumer's offset and lag behind the head.
+#[tracing::instrument(skip(state))]
+pub async fn list<S: EventStore + Clone + 'static, O: OrderCommands + OrderQueries + 'static>(
+    State(state): State<Arc<AppState<S, O>>>,
+) -> Result<Json<ConsumersResponse>, ApiError> {
+    let head = head_sequence(&state).await?;
+    let consumers = state
+        .consumer_offsets
+        .list_offsets()
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?
+        .into_iter()
+        .map(|offset| ConsumerOffsetResponse::new(offset, head))
+        .collect();
+
+    Ok(Json(ConsumersResponse {
+        head_sequence: head,
+        consumers,
+    }))
+}
+
+/// GET /consumers/:name/offset — a consumer's offset and lag behind the head.
+#[tracing::instrument(skip(state))]
+pub async fn get_offset<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+    Path(name): Path<String>,
+) -> Result<Json<ConsumerOffsetResponse>, ApiError> {
+    let offset = state
+        .consumer_offsets
+        .get_offset(&name)
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?
+        .ok_or_else(|| ApiError::NotFound(format!("Consumer '{name}' has no offset")))?;
+
+    let head = head_sequence(&state).await?;
+    Ok(Json(ConsumerOffsetResponse::new(offset, head)))
+}
+
+/// PUT /consumers/:name/offset — record the last sequence a consumer processed.
+#[tracing::instrument(skip(state, req))]
+pub async fn set_offset<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+    Path(name): Path<String>,
+    Json(req): Json<SetOffsetRequest>,
+) -> Result<Json<ConsumerOffsetResponse>, ApiError> {
+    if name.trim().is_empty() {
+        return Err(ApiError::BadRequest(
+            "Consumer name must not be empty".to_string(),
+        ));
+    }
+
+    let head = head_sequence(&state).await?;
+    if req.sequence > head {
+        return Err(ApiError::BadRequest(format!(
+            "Sequence {} is beyond the head of the log ({head})",
+            req.sequence
+        )));
+    }
+
+    let offset = state
+        .consumer_offsets
+        .set_offset(&name, req.sequence)
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+
+    tracing::info!(consumer = %name, sequence = req.sequence, "consumer offset updated");
+    Ok(Json(ConsumerOffsetResponse::new(offset, head)))
+}
+
+async fn head_sequence<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    state: &AppState<S, O>,
+) -> Result<u64, ApiError> {
+    state
+        .event_store
+        .head_sequence()
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))
+}
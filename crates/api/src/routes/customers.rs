@@ -0,0 +1,549 @@
+//! Customer lifecycle, identity, and data export endpoints.
+
+use std::sync::Arc;
+
+use axum::Json;
+use axum::extract::{Path, Query, State};
+use axum::http::{StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Utc};
+use domain::{
+    Address, Aggregate, ContactInfo, Customer, CustomerId, DeactivateCustomer, MergeCustomers,
+    OrderCommands, OrderQueries, OrderState, RegisterCustomer, UpdateContactInfo,
+};
+use event_store::EventStore;
+use projections::registry::ORDER_HISTORY;
+use projections::views::order_history::OrderHistorySummary;
+use projections::{HistoryQuery, OrderHistoryView};
+use serde::{Deserialize, Serialize};
+
+use crate::access::Principal;
+use crate::error::ApiError;
+use crate::export::{CustomerExport, ExportJob, ExportStatus};
+use crate::routes::orders::AppState;
+use crate::streaming::StreamedJson;
+
+/// Content type of NDJSON exports.
+const NDJSON: &str = "application/x-ndjson";
+
+// -- Request types --
+
+#[derive(Deserialize)]
+pub struct RegisterCustomerRequest {
+    /// ID to register under, e.g. one orders were already placed with;
+    /// generated when omitted.
+    pub customer_id: Option<String>,
+    #[serde(flatten)]
+    pub contact: ContactInfoRequest,
+}
+
+#[derive(Deserialize)]
+pub struct ContactInfoRequest {
+    pub name: String,
+    pub email: String,
+    pub phone: Option<String>,
+}
+
+impl From<ContactInfoRequest> for ContactInfo {
+    fn from(req: ContactInfoRequest) -> Self {
+        Self {
+            name: req.name,
+            email: req.email,
+            phone: req.phone,
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+pub struct DeactivateCustomerRequest {
+    pub reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct MergeCustomersRequest {
+    pub source_customer_id: String,
+    pub target_customer_id: String,
+    pub merged_by: Option<String>,
+}
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Json,
+    Ndjson,
+}
+
+#[derive(Deserialize)]
+pub struct ExportParams {
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+/// Query parameters for GET /customers/:id/history.
+#[derive(Deserialize, Default)]
+pub struct HistoryParams {
+    /// Earliest completion or cancellation time to include (RFC 3339).
+    pub from: Option<DateTime<Utc>>,
+    /// Completion or cancellation time to stop before (RFC 3339).
+    pub to: Option<DateTime<Utc>>,
+    /// Only orders currently in this state, e.g. `Completed`.
+    pub state: Option<OrderState>,
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub offset: usize,
+}
+
+// -- Response types --
+
+#[derive(Serialize)]
+pub struct CustomerResponse {
+    pub customer_id: String,
+    /// `"active"`, `"deactivated"`, or `"merged"`.
+    pub status: &'static str,
+    pub name: Option<String>,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub merged_into: Option<String>,
+    pub version: i64,
+}
+
+impl CustomerResponse {
+    fn new(customer_id: CustomerId, customer: &Customer) -> Self {
+        let status = if customer.is_merged() {
+            "merged"
+        } else if customer.is_deactivated() {
+            "deactivated"
+        } else {
+            "active"
+        };
+        let contact = customer.contact().cloned();
+        Self {
+            customer_id: customer_id.to_string(),
+            status,
+            name: contact.as_ref().map(|c| c.name.clone()),
+            email: contact.as_ref().map(|c| c.email.clone()),
+            phone: contact.and_then(|c| c.phone),
+            merged_into: customer.merged_into().map(|id| id.to_string()),
+            version: customer.version().as_i64(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct CustomerResolutionResponse {
+    pub customer_id: String,
+    pub resolved_customer_id: String,
+    pub merged: bool,
+}
+
+#[derive(Serialize)]
+pub struct ExportJobResponse {
+    pub export_id: String,
+    pub customer_id: String,
+    /// `"pending"`, `"ready"`, or `"failed"`.
+    pub status: &'static str,
+    pub requested_at: DateTime<Utc>,
+    pub error: Option<String>,
+    /// Where to fetch the archive once ready.
+    pub download_url: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct HistoryOrderResponse {
+    pub order_id: String,
+    pub state: String,
+    pub item_count: usize,
+    pub total_cents: i64,
+    pub currency: String,
+    pub shipping_address: Option<Address>,
+    pub created_at: DateTime<Utc>,
+    /// Completion or cancellation time; the list is sorted by it.
+    pub finished_at: DateTime<Utc>,
+    pub cancellation_reason: Option<String>,
+    pub tracking_number: Option<String>,
+    pub tags: Vec<String>,
+}
+
+impl From<OrderHistorySummary> for HistoryOrderResponse {
+    fn from(summary: OrderHistorySummary) -> Self {
+        Self {
+            order_id: summary.order_id.to_string(),
+            state: summary.state.to_string(),
+            item_count: summary.item_count,
+            total_cents: summary.total_amount.cents(),
+            currency: summary.currency.to_string(),
+            finished_at: summary.finished_at(),
+            shipping_address: summary.shipping_address,
+            created_at: summary.created_at,
+            cancellation_reason: summary.cancellation_reason,
+            tracking_number: summary.tracking_number,
+            tags: summary.tags.into_iter().collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct CustomerHistoryResponse {
+    pub customer_id: String,
+    pub orders: Vec<HistoryOrderResponse>,
+    /// Matching orders across all pages.
+    pub total: usize,
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+impl From<ExportJob> for ExportJobResponse {
+    fn from(job: ExportJob) -> Self {
+        let (status, error) = match job.status {
+            ExportStatus::Pending => ("pending", None),
+            ExportStatus::Ready(_) => ("ready", None),
+            ExportStatus::Failed(e) => ("failed", Some(e)),
+        };
+        Self {
+            download_url: (status == "ready")
+                .then(|| format!("/exports/{}/download", job.export_id)),
+            export_id: job.export_id.to_string(),
+            customer_id: job.customer_id.to_string(),
+            status,
+            requested_at: job.requested_at,
+            error,
+        }
+    }
+}
+
+// -- Handlers --
+
+/// POST /customers — register a customer with their contact info. The
+/// customer's URL is returned in `Location`.
+#[tracing::instrument(skip(state, req))]
+pub async fn register<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+    Json(req): Json<RegisterCustomerRequest>,
+) -> Result<Response, ApiError> {
+    let customer_id = match &req.customer_id {
+        Some(id) => parse_customer_id(id)?,
+        // Derived from the Idempotency-Key, if any, so a retry registers
+        // the customer the first attempt did
+        None => domain::processed::scoped_uuid("customer")
+            .map(CustomerId::from_uuid)
+            .unwrap_or_default(),
+    };
+    let result = state
+        .customer_service
+        .register_customer(RegisterCustomer::new(customer_id, req.contact.into()))
+        .await?;
+
+    Ok((
+        StatusCode::CREATED,
+        [(header::LOCATION, format!("/customers/{customer_id}"))],
+        Json(CustomerResponse::new(customer_id, &result.aggregate)),
+    )
+        .into_response())
+}
+
+/// GET /customers/:id — a customer's contact info and status, for
+/// operations or the customer themselves.
+#[tracing::instrument(skip(state))]
+pub async fn get<S: EventStore + Clone + 'static, O: OrderCommands + OrderQueries + 'static>(
+    State(state): State<Arc<AppState<S, O>>>,
+    principal: Principal,
+    Path(id): Path<String>,
+) -> Result<Json<CustomerResponse>, ApiError> {
+    let customer_id = parse_customer_id(&id)?;
+    principal.check_acts_for(customer_id)?;
+    let customer = state
+        .customer_service
+        .get_customer(customer_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Customer {customer_id} not found")))?;
+
+    Ok(Json(CustomerResponse::new(customer_id, &customer)))
+}
+
+/// PUT /customers/:id/contact — replace a customer's contact info, for
+/// operations or the customer themselves.
+#[tracing::instrument(skip(state, req))]
+pub async fn update_contact<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+    principal: Principal,
+    Path(id): Path<String>,
+    Json(req): Json<ContactInfoRequest>,
+) -> Result<Json<CustomerResponse>, ApiError> {
+    let customer_id = parse_customer_id(&id)?;
+    principal.check_acts_for(customer_id)?;
+    let result = state
+        .customer_service
+        .update_contact_info(UpdateContactInfo::new(customer_id, req.into()))
+        .await?;
+
+    Ok(Json(CustomerResponse::new(customer_id, &result.aggregate)))
+}
+
+/// POST /customers/:id/deactivate — deactivate a customer, for operations
+/// or the customer themselves.
+#[tracing::instrument(skip(state, req))]
+pub async fn deactivate<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+    principal: Principal,
+    Path(id): Path<String>,
+    Json(req): Json<DeactivateCustomerRequest>,
+) -> Result<Json<CustomerResponse>, ApiError> {
+    let customer_id = parse_customer_id(&id)?;
+    principal.check_acts_for(customer_id)?;
+    let result = state
+        .customer_service
+        .deactivate_customer(DeactivateCustomer::new(customer_id, req.reason))
+        .await?;
+
+    Ok(Json(CustomerResponse::new(customer_id, &result.aggregate)))
+}
+
+/// POST /admin/customers/merge — merge a duplicate customer into a survivor.
+#[tracing::instrument(skip(state, req))]
+pub async fn merge<S: EventStore + Clone + 'static, O: OrderCommands + OrderQueries + 'static>(
+    State(state): State<Arc<AppState<S, O>>>,
+    Json(req): Json<MergeCustomersRequest>,
+) -> Result<Json<CustomerResolutionResponse>, ApiError> {
+    let source = parse_customer_id(&req.source_customer_id)?;
+    let target = parse_customer_id(&req.target_customer_id)?;
+
+    let result = state
+        .customer_service
+        .merge_customers(MergeCustomers::new(source, target, req.merged_by))
+        .await?;
+
+    let resolved = result.aggregate.merged_into().unwrap_or(target);
+    Ok(Json(CustomerResolutionResponse {
+        customer_id: source.to_string(),
+        resolved_customer_id: resolved.to_string(),
+        merged: true,
+    }))
+}
+
+/// GET /customers/:id/resolve — resolve a customer ID to the surviving customer.
+#[tracing::instrument(skip(state))]
+pub async fn resolve<S: EventStore + Clone + 'static, O: OrderCommands + OrderQueries + 'static>(
+    State(state): State<Arc<AppState<S, O>>>,
+    Path(id): Path<String>,
+) -> Result<Json<CustomerResolutionResponse>, ApiError> {
+    let customer_id = parse_customer_id(&id)?;
+    let resolved = state.customer_service.resolve(customer_id).await?;
+
+    Ok(Json(CustomerResolutionResponse {
+        customer_id: customer_id.to_string(),
+        resolved_customer_id: resolved.to_string(),
+        merged: resolved != customer_id,
+    }))
+}
+
+/// GET /customers/:id/history — a customer's completed and cancelled orders.
+///
+/// Sorted by completion (or cancellation) date, oldest first, and narrowed
+/// with `from`/`to`, `state`, and `limit`/`offset`. Served from the order
+/// history view, so it returns 404 unless that view is enabled. Customers
+/// only see their own history; anyone else's comes back empty.
+#[tracing::instrument(skip(state, params))]
+pub async fn history<S: EventStore + Clone + 'static, O: OrderCommands + OrderQueries + 'static>(
+    State(state): State<Arc<AppState<S, O>>>,
+    principal: Principal,
+    Path(id): Path<String>,
+    Query(params): Query<HistoryParams>,
+) -> Result<Json<CustomerHistoryResponse>, ApiError> {
+    let customer_id = parse_customer_id(&id)?;
+    let order_history = state
+        .views
+        .get::<OrderHistoryView>()
+        .ok_or_else(|| ApiError::NotFound(format!("Read model {ORDER_HISTORY} is not enabled")))?;
+
+    let query = HistoryQuery {
+        from: params.from,
+        to: params.to,
+        state: params.state,
+        offset: params.offset,
+        limit: params.limit,
+    };
+    let page = order_history
+        .query_customer_history_for(customer_id, &query, &principal.rows(&state.pseudonyms))
+        .await;
+
+    Ok(Json(CustomerHistoryResponse {
+        customer_id: customer_id.to_string(),
+        orders: page.orders.into_iter().map(Into::into).collect(),
+        total: page.total,
+        offset: params.offset,
+        limit: params.limit,
+    }))
+}
+
+/// GET /customers/:id/export — export a customer's orders, events, and stats.
+///
+/// Returns a single JSON document, or NDJSON with `?format=ndjson`.
+/// Customers may only export their own data.
+#[tracing::instrument(skip(state, params))]
+pub async fn export<S: EventStore + Clone + 'static, O: OrderCommands + OrderQueries + 'static>(
+    State(state): State<Arc<AppState<S, O>>>,
+    principal: Principal,
+    Path(id): Path<String>,
+    Query(params): Query<ExportParams>,
+) -> Result<Response, ApiError> {
+    let customer_id = parse_customer_id(&id)?;
+    principal.check_customer(customer_id)?;
+    let export = state
+        .customer_exporter
+        .export(&state.event_store, &state.order_service, customer_id)
+        .await?;
+
+    match params.format {
+        ExportFormat::Json => Ok(stream_export(export)?.into_response()),
+        ExportFormat::Ndjson => {
+            let body = render_ndjson(&export)?;
+            Ok(([(header::CONTENT_TYPE, NDJSON)], body).into_response())
+        }
+    }
+}
+
+/// POST /customers/:id/export — prepare a downloadable export in the background.
+#[tracing::instrument(skip(state))]
+pub async fn request_export<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+    principal: Principal,
+    Path(id): Path<String>,
+) -> Result<(StatusCode, Json<ExportJobResponse>), ApiError> {
+    let customer_id = parse_customer_id(&id)?;
+    principal.check_customer(customer_id)?;
+    let job = state.exports.start(customer_id);
+    let export_id = job.export_id;
+
+    let task_state = state.clone();
+    tokio::spawn(async move {
+        let result = task_state
+            .customer_exporter
+            .export(
+                &task_state.event_store,
+                &task_state.order_service,
+                customer_id,
+            )
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|export| export.to_ndjson().map_err(|e| e.to_string()));
+        let status = match result {
+            Ok(archive) => ExportStatus::Ready(Arc::new(archive)),
+            Err(e) => {
+                tracing::warn!(%export_id, error = %e, "customer export failed");
+                ExportStatus::Failed(e)
+            }
+        };
+        task_state.exports.finish(export_id, status);
+    });
+
+    Ok((StatusCode::ACCEPTED, Json(job.into())))
+}
+
+/// GET /exports/:export_id — status of a background export.
+#[tracing::instrument(skip(state))]
+pub async fn export_status<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+    principal: Principal,
+    Path(export_id): Path<String>,
+) -> Result<Json<ExportJobResponse>, ApiError> {
+    find_export(&state, &principal, &export_id).map(|job| Json(job.into()))
+}
+
+/// GET /exports/:export_id/download — download a finished export as NDJSON.
+#[tracing::instrument(skip(state))]
+pub async fn download_export<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+    principal: Principal,
+    Path(export_id): Path<String>,
+) -> Result<Response, ApiError> {
+    let job = find_export(&state, &principal, &export_id)?;
+    match job.status {
+        ExportStatus::Ready(archive) => {
+            let disposition = format!(
+                "attachment; filename=\"customer-{}-export.ndjson\"",
+                job.customer_id
+            );
+            Ok((
+                [
+                    (header::CONTENT_TYPE, NDJSON.to_string()),
+                    (header::CONTENT_DISPOSITION, disposition),
+                ],
+                archive.as_ref().clone(),
+            )
+                .into_response())
+        }
+        ExportStatus::Pending => Err(ApiError::Conflict(format!(
+            "Export {export_id} is not ready yet"
+        ))),
+        ExportStatus::Failed(e) => Err(ApiError::Internal(format!(
+            "Export {export_id} failed: {e}"
+        ))),
+    }
+}
+
+/// Finds an export the caller may see; other customers' exports are
+/// reported as missing.
+fn find_export<S: EventStore, O>(
+    state: &AppState<S, O>,
+    principal: &Principal,
+    export_id: &str,
+) -> Result<ExportJob, ApiError> {
+    uuid::Uuid::parse_str(export_id)
+        .ok()
+        .and_then(|id| state.exports.get(id))
+        .filter(|job| principal.permits_customer(job.customer_id))
+        .ok_or_else(|| ApiError::NotFound(format!("Export {export_id} not found")))
+}
+
+/// Streams a JSON export, serializing orders and events as they are sent.
+fn stream_export(export: CustomerExport) -> Result<StreamedJson, ApiError> {
+    let CustomerExport {
+        customer_id,
+        merged_customer_ids,
+        exported_at,
+        orders,
+        events,
+        stats,
+    } = export;
+    let fields = serde_json::json!({
+        "customer_id": customer_id,
+        "merged_customer_ids": merged_customer_ids,
+        "exported_at": exported_at,
+        "stats": stats,
+    });
+    Ok(StreamedJson::new(&fields)
+        .map_err(|e| ApiError::Internal(format!("Failed to render export: {e}")))?
+        .array("orders", orders)
+        .array("events", events))
+}
+
+fn render_ndjson(export: &CustomerExport) -> Result<Vec<u8>, ApiError> {
+    export
+        .to_ndjson()
+        .map_err(|e| ApiError::Internal(format!("Failed to render export: {e}")))
+}
+
+fn parse_customer_id(id: &str) -> Result<CustomerId, ApiError> {
+    let uuid = uuid::Uuid::parse_str(id)
+        .map_err(|e| ApiError::BadRequest(format!("Invalid customer ID format: {e}")))?;
+    Ok(CustomerId::from_uuid(uuid))
+}
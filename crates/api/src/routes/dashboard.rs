@@ -0,0 +1,139 @@
+//! Operations dashboard endpoint.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use axum::Json;
+use axum::extract::State;
+use chrono::{DateTime, Duration, Utc};
+use domain::{Aggregate, OrderCommands, OrderQueries};
+use event_store::{EventQuery, EventStore};
+use projections::{CurrentOrdersView, InventoryView, OrderHistoryView, ReadModel};
+use saga::SagaInstance;
+use serde::Serialize;
+
+use crate::error::ApiError;
+use crate::routes::orders::AppState;
+
+/// Number of products listed in [`DashboardResponse::top_products`].
+pub const TOP_PRODUCTS: usize = 5;
+
+#[derive(Serialize)]
+pub struct DashboardResponse {
+    pub generated_at: DateTime<Utc>,
+    /// Orders not yet completed or cancelled; null without the current
+    /// orders view.
+    pub active_orders: Option<usize>,
+    /// Totals of orders completed since midnight UTC, in cents per
+    /// currency; null without the order history view.
+    pub completed_revenue_today: Option<BTreeMap<String, i64>>,
+    /// Sagas that failed in the last 24 hours.
+    pub failed_sagas_24h: usize,
+    /// Most-ordered products by quantity; null without the inventory view.
+    pub top_products: Option<Vec<TopProduct>>,
+    pub projection_lag: ProjectionLag,
+}
+
+#[derive(Serialize)]
+pub struct TopProduct {
+    pub product_id: String,
+    pub product_name: String,
+    pub total_quantity_ordered: u64,
+    pub order_count: u64,
+}
+
+#[derive(Serialize)]
+pub struct ProjectionLag {
+    /// Global sequence of the newest event in the store.
+    pub head_sequence: u64,
+    /// Largest lag of any projection.
+    pub max_lag: u64,
+    /// Each projection's lag, by name.
+    pub projections: BTreeMap<String, u64>,
+}
+
+/// GET /dashboard — headline numbers for the operations homepage.
+///
+/// Each view is read once and projections are not caught up first, so the
+/// figures are as of the reported projection lag. 503 if a view can't be
+/// read in time.
+#[tracing::instrument(skip(state))]
+pub async fn get<S: EventStore + Clone + 'static, O: OrderCommands + OrderQueries + 'static>(
+    State(state): State<Arc<AppState<S, O>>>,
+) -> Result<Json<DashboardResponse>, ApiError> {
+    let now = Utc::now();
+
+    let active_orders = match state.views.get::<CurrentOrdersView>() {
+        Some(view) => Some(view.count().await?),
+        None => None,
+    };
+
+    let midnight = now
+        .date_naive()
+        .and_hms_opt(0, 0, 0)
+        .expect("midnight is a valid time")
+        .and_utc();
+    let completed_revenue_today = match state.views.get::<OrderHistoryView>() {
+        Some(view) => Some(
+            view.completed_revenue_since(midnight)
+                .await?
+                .into_iter()
+                .map(|(currency, amount)| (currency.to_string(), amount.cents()))
+                .collect(),
+        ),
+        None => None,
+    };
+
+    let top_products = match state.views.get::<InventoryView>() {
+        Some(view) => Some(
+            view.get_top_products_by_demand(TOP_PRODUCTS)
+                .await
+                .into_iter()
+                .map(|product| TopProduct {
+                    product_id: product.product_id.to_string(),
+                    product_name: product.product_name,
+                    total_quantity_ordered: product.total_quantity_ordered,
+                    order_count: product.order_count,
+                })
+                .collect(),
+        ),
+        None => None,
+    };
+
+    let failed_sagas_24h = state
+        .event_store
+        .query_events(
+            EventQuery::new()
+                .aggregate_type(SagaInstance::aggregate_type())
+                .event_type("SagaFailed")
+                .from_timestamp(now - Duration::hours(24)),
+        )
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?
+        .len();
+
+    let processor = &state.projection_processor;
+    let head_sequence = processor
+        .head_sequence()
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+    let projections: BTreeMap<_, _> = processor
+        .registrations()
+        .await
+        .into_iter()
+        .map(|(name, position)| (name, position.lag(head_sequence)))
+        .collect();
+
+    Ok(Json(DashboardResponse {
+        generated_at: now,
+        active_orders,
+        completed_revenue_today,
+        failed_sagas_24h,
+        top_products,
+        projection_lag: ProjectionLag {
+            head_sequence,
+            max_lag: projections.values().copied().max().unwrap_or(0),
+            projections,
+        },
+    }))
+}
@@ -1,14 +1,84 @@
-//! Health check endpoint.
+//! Health check endpoints.
+
+use std::sync::Arc;
 
 use axum::Json;
+use axum::extract::State;
+use axum::http::StatusCode;
+use domain::{OrderCommands, OrderQueries};
+use event_store::{EventStore, SchemaDrift, SchemaVersion};
 use serde::Serialize;
 
+use super::orders::AppState;
+
 #[derive(Serialize)]
 pub struct HealthResponse {
     pub status: &'static str,
 }
 
+#[derive(Serialize)]
+pub struct ReadinessResponse {
+    pub status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schema: Option<SchemaVersion>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub drift: Option<SchemaDrift>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
 /// GET /health — returns system health status.
 pub async fn check() -> Json<HealthResponse> {
     Json(HealthResponse { status: "ok" })
 }
+
+/// GET /health/ready — 200 once the database schema matches this build,
+/// 503 while it is behind, ahead, or unreadable.
+pub async fn ready<S: EventStore + Clone + 'static, O: OrderCommands + OrderQueries + 'static>(
+    State(state): State<Arc<AppState<S, O>>>,
+) -> (StatusCode, Json<ReadinessResponse>) {
+    let Some(source) = &state.schema else {
+        return (
+            StatusCode::OK,
+            Json(ReadinessResponse {
+                status: "ready",
+                schema: None,
+                drift: None,
+                error: None,
+            }),
+        );
+    };
+
+    match source.schema_version().await {
+        Ok(version) => {
+            let drift = version.drift();
+            let (status_code, status) = match drift {
+                SchemaDrift::UpToDate => (StatusCode::OK, "ready"),
+                SchemaDrift::Behind | SchemaDrift::Ahead => {
+                    (StatusCode::SERVICE_UNAVAILABLE, "schema_drift")
+                }
+            };
+            (
+                status_code,
+                Json(ReadinessResponse {
+                    status,
+                    schema: Some(version),
+                    drift: Some(drift),
+                    error: None,
+                }),
+            )
+        }
+        Err(e) => {
+            tracing::warn!(error = %e, "schema version check failed");
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(ReadinessResponse {
+                    status: "unavailable",
+                    schema: None,
+                    drift: None,
+                    error: Some(e.to_string()),
+                }),
+            )
+        }
+    }
+}
@@ -0,0 +1,201 @@
+//! Inbound webhooks from external fulfillment partners.
+
+use std::sync::Arc;
+
+use axum::Json;
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::HeaderMap;
+use chrono::{DateTime, Utc};
+use common::AggregateId;
+use domain::{
+    COMMAND_ID_KEY, CommandMetadata, MarkDelivered, MarkInTransit, MarkShipped, OrderCommands,
+    OrderQueries, RecordDeliveryFailure,
+};
+use event_store::EventStore;
+use serde::{Deserialize, Serialize};
+
+use crate::error::ApiError;
+use crate::routes::orders::AppState;
+
+/// Header carrying the `sha256=<hex>` request signature.
+pub const SIGNATURE_HEADER: &str = "x-shipping-signature";
+
+/// Header carrying the Unix timestamp (seconds) the request was signed at.
+pub const TIMESTAMP_HEADER: &str = "x-shipping-timestamp";
+
+/// Metadata key holding the delivery ID of the webhook that caused an event.
+pub const WEBHOOK_DELIVERY_ID_KEY: &str = "webhook_delivery_id";
+
+// -- Request types --
+
+#[derive(Deserialize)]
+pub struct ShippingWebhookRequest {
+    /// Carrier's unique delivery ID, used for replay protection.
+    pub id: String,
+    pub order_id: String,
+    /// When the carrier observed the event; defaults to receipt time.
+    pub occurred_at: Option<DateTime<Utc>>,
+    #[serde(flatten)]
+    pub event: CarrierEvent,
+}
+
+/// Carrier callback types.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CarrierEvent {
+    Shipped {
+        carrier: String,
+        tracking_number: Option<String>,
+    },
+    InTransit {
+        location: Option<String>,
+    },
+    Delivered {
+        received_by: Option<String>,
+    },
+    DeliveryFailed {
+        reason: String,
+    },
+}
+
+// -- Response types --
+
+#[derive(Serialize)]
+pub struct ShippingWebhookResponse {
+    /// `"applied"`, or `"duplicate"` for an already-processed delivery ID.
+    pub status: &'static str,
+    pub order_id: String,
+    pub order_state: Option<String>,
+}
+
+// -- Handlers --
+
+/// POST /integrations/shipping/webhook — apply a signed carrier callback to an order.
+#[tracing::instrument(skip(state, headers, body))]
+pub async fn shipping_webhook<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<ShippingWebhookResponse>, ApiError> {
+    let verifier = state
+        .shipping_webhooks
+        .as_ref()
+        .ok_or_else(|| ApiError::Forbidden("Shipping webhooks are not configured".to_string()))?;
+
+    let signature = header(&headers, SIGNATURE_HEADER)?;
+    let timestamp: i64 = header(&headers, TIMESTAMP_HEADER)?
+        .parse()
+        .map_err(|_| ApiError::Unauthorized(format!("Invalid {TIMESTAMP_HEADER} header")))?;
+    let now = Utc::now().timestamp();
+    verifier
+        .verify(signature, timestamp, &body, now)
+        .map_err(|e| ApiError::Unauthorized(e.to_string()))?;
+
+    let req: ShippingWebhookRequest = serde_json::from_slice(&body)
+        .map_err(|e| ApiError::BadRequest(format!("Invalid webhook payload: {e}")))?;
+    let order_id = uuid::Uuid::parse_str(&req.order_id)
+        .map(AggregateId::from)
+        .map_err(|e| ApiError::BadRequest(format!("Invalid order_id: {e}")))?;
+
+    if !verifier.claim(&req.id, timestamp, now) {
+        tracing::info!(delivery_id = %req.id, "ignoring replayed shipping webhook");
+        return Ok(Json(ShippingWebhookResponse {
+            status: "duplicate",
+            order_id: req.order_id,
+            order_state: None,
+        }));
+    }
+
+    match apply_carrier_event(&state, order_id, &req.id, req.occurred_at, req.event).await {
+        Ok(order_state) => Ok(Json(ShippingWebhookResponse {
+            status: "applied",
+            order_id: req.order_id,
+            order_state: Some(order_state),
+        })),
+        Err(e) => {
+            // Let the carrier's retry through
+            verifier.release(&req.id);
+            Err(e)
+        }
+    }
+}
+
+async fn apply_carrier_event<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    state: &AppState<S, O>,
+    order_id: AggregateId,
+    delivery_id: &str,
+    occurred_at: Option<DateTime<Utc>>,
+    event: CarrierEvent,
+) -> Result<String, ApiError> {
+    state
+        .order_service
+        .get_order(order_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Order {order_id} not found")))?;
+
+    let at = occurred_at.unwrap_or_else(Utc::now);
+    // The delivery doubles as the command ID, so a retry the verifier no
+    // longer remembers is still applied only once
+    let metadata = CommandMetadata::from([
+        (
+            WEBHOOK_DELIVERY_ID_KEY.to_string(),
+            serde_json::json!(delivery_id),
+        ),
+        (
+            COMMAND_ID_KEY.to_string(),
+            serde_json::json!(format!("shipping-webhook:{delivery_id}")),
+        ),
+    ]);
+    let result = match event {
+        CarrierEvent::Shipped {
+            carrier,
+            tracking_number,
+        } => {
+            state
+                .order_service
+                .mark_shipped(
+                    MarkShipped::new(order_id, carrier, tracking_number, at)
+                        .with_metadata(metadata),
+                )
+                .await?
+        }
+        CarrierEvent::InTransit { location } => {
+            state
+                .order_service
+                .mark_in_transit(MarkInTransit::new(order_id, location, at).with_metadata(metadata))
+                .await?
+        }
+        CarrierEvent::Delivered { received_by } => {
+            state
+                .order_service
+                .mark_delivered(
+                    MarkDelivered::new(order_id, received_by, at).with_metadata(metadata),
+                )
+                .await?
+        }
+        CarrierEvent::DeliveryFailed { reason } => {
+            state
+                .order_service
+                .record_delivery_failure(
+                    RecordDeliveryFailure::new(order_id, reason, at).with_metadata(metadata),
+                )
+                .await?
+        }
+    };
+
+    Ok(result.aggregate.state().to_string())
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Result<&'a str, ApiError> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .ok_or_else(|| ApiError::Unauthorized(format!("Missing {name} header")))
+}
@@ -0,0 +1,142 @@
+//! Inventory restock, stock level, and oversell endpoints.
+
+use std::sync::Arc;
+
+use axum::Json;
+use axum::extract::{Path, State};
+use chrono::{DateTime, Utc};
+use domain::{OrderCommands, OrderQueries, ProductId, RestockItem};
+use event_store::EventStore;
+use projections::registry::{OVERSELL, STOCK_LEVELS};
+use projections::{Oversell, OversellDetectionView, StockLevel, StockLevelsView};
+use serde::{Deserialize, Serialize};
+
+use crate::error::ApiError;
+use crate::routes::orders::AppState;
+
+// -- Request types --
+
+#[derive(Deserialize)]
+pub struct RestockRequest {
+    pub quantity: u32,
+    pub reference: Option<String>,
+}
+
+// -- Response types --
+
+#[derive(Serialize)]
+pub struct StockLevelResponse {
+    pub product_id: String,
+    pub on_hand: i64,
+    pub reserved: u64,
+    pub available: i64,
+}
+
+impl From<StockLevel> for StockLevelResponse {
+    fn from(level: StockLevel) -> Self {
+        Self {
+            available: level.available(),
+            product_id: level.product_id.to_string(),
+            on_hand: level.on_hand,
+            reserved: level.reserved,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct OversellResponse {
+    pub product_id: String,
+    pub on_hand: i64,
+    pub reserved: u64,
+    pub shortfall: u64,
+    pub detected_at: DateTime<Utc>,
+}
+
+impl From<Oversell> for OversellResponse {
+    fn from(oversell: Oversell) -> Self {
+        Self {
+            shortfall: oversell.shortfall(),
+            product_id: oversell.product_id.to_string(),
+            on_hand: oversell.on_hand,
+            reserved: oversell.reserved,
+            detected_at: oversell.detected_at,
+        }
+    }
+}
+
+// -- Handlers --
+
+/// POST /inventory/:product_id/restock — record stock received for a product.
+#[tracing::instrument(skip(state, req))]
+pub async fn restock<S: EventStore + Clone + 'static, O: OrderCommands + OrderQueries + 'static>(
+    State(state): State<Arc<AppState<S, O>>>,
+    Path(product_id): Path<String>,
+    Json(req): Json<RestockRequest>,
+) -> Result<Json<StockLevelResponse>, ApiError> {
+    let product_id = ProductId::new(product_id);
+    state
+        .inventory_service
+        .restock(RestockItem::new(
+            product_id.clone(),
+            req.quantity,
+            req.reference,
+        ))
+        .await?;
+
+    stock_level(&state, &product_id).await.map(Json)
+}
+
+/// GET /inventory/:product_id — get on-hand, reserved, and available stock.
+#[tracing::instrument(skip(state))]
+pub async fn get<S: EventStore + Clone + 'static, O: OrderCommands + OrderQueries + 'static>(
+    State(state): State<Arc<AppState<S, O>>>,
+    Path(product_id): Path<String>,
+) -> Result<Json<StockLevelResponse>, ApiError> {
+    stock_level(&state, &ProductId::new(product_id))
+        .await
+        .map(Json)
+}
+
+/// GET /inventory/oversold — list products with more reserved than in stock.
+#[tracing::instrument(skip(state))]
+pub async fn oversold<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+) -> Result<Json<Vec<OversellResponse>>, ApiError> {
+    state
+        .projection_processor
+        .run_catch_up()
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+
+    let oversell = state
+        .views
+        .get::<OversellDetectionView>()
+        .ok_or_else(|| ApiError::NotFound(format!("Read model {OVERSELL} is not enabled")))?;
+    let oversold = oversell.get_all().await;
+    Ok(Json(oversold.into_iter().map(Into::into).collect()))
+}
+
+async fn stock_level<S: EventStore + Clone + 'static, O: OrderCommands + OrderQueries + 'static>(
+    state: &AppState<S, O>,
+    product_id: &ProductId,
+) -> Result<StockLevelResponse, ApiError> {
+    // Run catch-up to ensure the read model includes latest events
+    state
+        .projection_processor
+        .run_catch_up()
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+
+    let stock_levels = state
+        .views
+        .get::<StockLevelsView>()
+        .ok_or_else(|| ApiError::NotFound(format!("Read model {STOCK_LEVELS} is not enabled")))?;
+    stock_levels
+        .get_product(product_id)
+        .await
+        .map(StockLevelResponse::from)
+        .ok_or_else(|| ApiError::NotFound(format!("Product {product_id} not found")))
+}
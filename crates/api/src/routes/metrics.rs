@@ -1,18 +1,100 @@
 //! Prometheus metrics endpoint.
 
 use axum::extract::State;
-use axum::http::StatusCode;
-use axum::response::IntoResponse;
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::{IntoResponse, Response};
 use metrics_exporter_prometheus::PrometheusHandle;
 
+/// Shared state for the metrics endpoint.
+#[derive(Clone)]
+pub struct MetricsState {
+    pub handle: PrometheusHandle,
+    /// Token scrapers must present as `Authorization: Bearer <token>`;
+    /// the endpoint is open when unset.
+    pub bearer_token: Option<String>,
+    /// Whether process and tokio runtime gauges are refreshed on scrape.
+    pub runtime_collectors: bool,
+}
+
 /// GET /metrics — returns Prometheus-formatted metrics.
-pub async fn get(State(handle): State<PrometheusHandle>) -> impl IntoResponse {
+pub async fn get(State(state): State<MetricsState>, headers: HeaderMap) -> Response {
+    if let Some(expected) = &state.bearer_token {
+        let presented = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "));
+        if !presented.is_some_and(|token| constant_time_eq(token, expected)) {
+            return (
+                StatusCode::UNAUTHORIZED,
+                [(header::WWW_AUTHENTICATE, "Bearer")],
+            )
+                .into_response();
+        }
+    }
+
+    if state.runtime_collectors {
+        collect_runtime_metrics();
+    }
+
     (
         StatusCode::OK,
         [(
-            axum::http::header::CONTENT_TYPE,
+            header::CONTENT_TYPE,
             "text/plain; version=0.0.4; charset=utf-8",
         )],
-        handle.render(),
+        state.handle.render(),
     )
+        .into_response()
+}
+
+/// Compares tokens without short-circuiting on the first differing byte.
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0u8, |diff, (x, y)| diff | (x ^ y))
+            == 0
+}
+
+/// Refreshes the process and tokio runtime gauges.
+///
+/// Uses the standard Prometheus process metric names so existing dashboards
+/// pick them up. Process memory and file descriptors are read from `/proc`
+/// and are only reported on Linux.
+pub fn collect_runtime_metrics() {
+    if let Ok(handle) = tokio::runtime::Handle::try_current() {
+        let runtime = handle.metrics();
+        metrics::gauge!("tokio_workers").set(runtime.num_workers() as f64);
+        metrics::gauge!("tokio_alive_tasks").set(runtime.num_alive_tasks() as f64);
+        metrics::gauge!("tokio_global_queue_depth").set(runtime.global_queue_depth() as f64);
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        if let Ok(status) = std::fs::read_to_string("/proc/self/status") {
+            if let Some(bytes) = status_kib(&status, "VmRSS:") {
+                metrics::gauge!("process_resident_memory_bytes").set(bytes as f64);
+            }
+            if let Some(bytes) = status_kib(&status, "VmSize:") {
+                metrics::gauge!("process_virtual_memory_bytes").set(bytes as f64);
+            }
+        }
+        if let Ok(fds) = std::fs::read_dir("/proc/self/fd") {
+            metrics::gauge!("process_open_fds").set(fds.count() as f64);
+        }
+    }
+}
+
+/// Reads a `/proc/self/status` field reported in KiB, as bytes.
+#[cfg(target_os = "linux")]
+fn status_kib(status: &str, field: &str) -> Option<u64> {
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix(field))?
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .map(|kib| kib * 1024)
 }
@@ -1,3 +1,11 @@
+pub mod admin;
+pub mod consumers;
+pub mod customers;
+pub mod dashboard;
 pub mod health;
+pub mod integrations;
+pub mod inventory;
 pub mod metrics;
 pub mod orders;
+pub mod projections;
+pub mod sagas;
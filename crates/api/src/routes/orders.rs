@@ -1,32 +1,157 @@
 //! Order CRUD and saga trigger endpoints.
 
+use std::collections::BTreeMap;
 use std::sync::Arc;
 
 use axum::Json;
-use axum::extract::{Path, State};
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, header};
+use axum::response::{IntoResponse, Response};
 use common::AggregateId;
-use domain::{AddItem, CreateOrder, CustomerId, Money, OrderItem, OrderService, SubmitOrder};
-use event_store::EventStore;
-use projections::{CurrentOrdersView, ProjectionProcessor};
+use domain::feature_flag::flags;
+use domain::{
+    AddTag, Address, Aggregate, ContentionTracker, CreateOrder, Currency, CustomerId,
+    CustomerService, DecisionLog, FeatureFlagService, FlagContext, InMemoryPriceCatalog,
+    InventoryItemService, Money, Order, OrderCommands, OrderItem, OrderQueries, OrderService,
+    OrderState, RemoveTag, SetOrderMetadata, SetShippingAddress, SubmitOrder, SubstituteItem,
+    UpdateShippingAddress,
+};
+use event_store::cloudevents::{CLOUDEVENTS_BATCH_JSON, CLOUDEVENTS_JSON};
+use event_store::{
+    AnnotationStore, CloudEvent, ConsumerOffsetStore, EventQuery, EventStore, QueryAnalyzer,
+    QuotaEnforcer, SchemaDeprecations, SchemaVersionSource, Version, VisibilityPolicy,
+};
+use projections::registry::CURRENT_ORDERS;
+use projections::{CurrentOrdersView, Projection, ProjectionProcessor, ViewHandles};
 use saga::{
-    InMemoryInventoryService, InMemoryPaymentService, InMemoryShippingService, SagaCoordinator,
+    DryRunStep, FailureKind, InMemoryInventoryService, InMemoryPaymentService,
+    InMemoryShippingService, OrderDetailsQuery, SagaCoordinator, SagaInstance, SagaRunner,
+    SagaState, SagaTrace, StepAttempt, TraceContext,
 };
 use serde::{Deserialize, Serialize};
+use tokio_util::sync::CancellationToken;
 
+use crate::access::{Principal, PseudonymKey};
+use crate::analytics::ParquetExporter;
+use crate::batch::FulfillmentBatches;
+use crate::breaker::CommandBreaker;
 use crate::error::ApiError;
+use crate::export::{CustomerExporter, ExportJobs};
+use crate::idempotency::IdempotencyKeys;
+use crate::maintenance::MaintenanceScheduler;
+use crate::oidc::OidcAuthenticator;
+use crate::routes::admin::{AnnotationResponse, annotations_by_event};
+use crate::settings::LiveSettings;
+use crate::streaming::StreamedJson;
+use crate::webhooks::WebhookVerifier;
 
 /// Shared application state accessible from all handlers.
-pub struct AppState<S: EventStore> {
-    pub order_service: OrderService<S>,
-    pub saga_coordinator: SagaCoordinator<
-        S,
-        InMemoryInventoryService,
-        InMemoryPaymentService,
-        InMemoryShippingService,
+///
+/// Handlers reach orders only through [`OrderCommands`] and [`OrderQueries`],
+/// so tests can swap `O` for a mock instead of a real [`OrderService`].
+pub struct AppState<S: EventStore, O = OrderService<S>> {
+    pub order_service: O,
+    pub customer_service: CustomerService<S>,
+    pub feature_flags: FeatureFlagService<S>,
+    pub inventory_service: InventoryItemService<S>,
+    pub saga_coordinator: Arc<
+        SagaCoordinator<
+            S,
+            InMemoryInventoryService,
+            InMemoryPaymentService,
+            InMemoryShippingService,
+        >,
     >,
-    pub current_orders: Arc<CurrentOrdersView>,
+    /// Read models enabled for this deployment.
+    pub views: ViewHandles,
     pub event_store: S,
     pub projection_processor: Arc<ProjectionProcessor<S>>,
+    /// Verifier for carrier callbacks; webhooks are refused when unset.
+    pub shipping_webhooks: Option<WebhookVerifier>,
+    /// Named read positions of downstream consumers.
+    pub consumer_offsets: Arc<dyn ConsumerOffsetStore>,
+    /// Builds customer data exports.
+    pub customer_exporter: CustomerExporter,
+    /// Background exports awaiting download.
+    pub exports: ExportJobs,
+    /// Fulfillment batches and their progress.
+    pub fulfillment_batches: FulfillmentBatches,
+    /// Concurrency conflicts per aggregate in the current report window.
+    pub contention: ContentionTracker,
+    /// Current product prices, used when submit-time reconciliation is on.
+    pub price_catalog: Arc<InMemoryPriceCatalog>,
+    /// Command decisions, with recent rejections when buffering is on.
+    pub decisions: DecisionLog,
+    /// Loads orders together with their saga, payment, and shipment.
+    pub order_details: OrderDetailsQuery<S>,
+    /// Append quotas, when the event store enforces them.
+    pub quotas: Option<QuotaEnforcer>,
+    /// Schema version of the backing database, checked by `/health/ready`.
+    pub schema: Option<Arc<dyn SchemaVersionSource>>,
+    /// Plans event queries for `/admin/queries/analyze`, when the store can.
+    pub query_analyzer: Option<Arc<dyn QueryAnalyzer>>,
+    /// Housekeeping jobs, listed at `/admin/maintenance`.
+    pub maintenance: MaintenanceScheduler,
+    /// Writes order history to Parquet, when an export directory is set.
+    pub parquet_export: Option<ParquetExporter>,
+    /// Settings reloaded on `SIGHUP`, listed at `/admin/settings`.
+    pub settings: LiveSettings,
+    /// Stored responses of commands sent with an `Idempotency-Key`.
+    pub idempotency: IdempotencyKeys,
+    /// Reads of deprecated event schema versions, reported at
+    /// `/admin/schemas/deprecations`.
+    pub schema_deprecations: SchemaDeprecations,
+    /// Drives sagas in the background, when fulfillment is asynchronous.
+    pub saga_runner: Option<SagaRunner>,
+    /// Verifies callers' bearer tokens, when an OIDC provider is configured.
+    pub oidc: Option<Arc<OidcAuthenticator>>,
+    /// Sheds non-critical commands while the command side is degraded.
+    pub command_breaker: CommandBreaker,
+    /// Operator notes on past events, shown alongside them.
+    pub annotations: Arc<dyn AnnotationStore>,
+    /// Which streams consumer-facing feeds and exports include.
+    pub visibility: VisibilityPolicy,
+    /// Keys the customer pseudonyms finance sees.
+    pub pseudonyms: PseudonymKey,
+}
+
+impl<S: EventStore, O> AppState<S, O> {
+    /// Replaces the order service, keeping the rest of the state.
+    pub fn with_order_service<O2>(self, order_service: O2) -> AppState<S, O2> {
+        AppState {
+            order_service,
+            customer_service: self.customer_service,
+            feature_flags: self.feature_flags,
+            inventory_service: self.inventory_service,
+            saga_coordinator: self.saga_coordinator,
+            views: self.views,
+            event_store: self.event_store,
+            projection_processor: self.projection_processor,
+            shipping_webhooks: self.shipping_webhooks,
+            consumer_offsets: self.consumer_offsets,
+            customer_exporter: self.customer_exporter,
+            exports: self.exports,
+            fulfillment_batches: self.fulfillment_batches,
+            contention: self.contention,
+            price_catalog: self.price_catalog,
+            decisions: self.decisions,
+            order_details: self.order_details,
+            quotas: self.quotas,
+            schema: self.schema,
+            query_analyzer: self.query_analyzer,
+            maintenance: self.maintenance,
+            parquet_export: self.parquet_export,
+            settings: self.settings,
+            idempotency: self.idempotency,
+            schema_deprecations: self.schema_deprecations,
+            saga_runner: self.saga_runner,
+            oidc: self.oidc,
+            command_breaker: self.command_breaker,
+            annotations: self.annotations,
+            visibility: self.visibility,
+            pseudonyms: self.pseudonyms,
+        }
+    }
 }
 
 // -- Request types --
@@ -34,6 +159,7 @@ pub struct AppState<S: EventStore> {
 #[derive(Deserialize)]
 pub struct CreateOrderRequest {
     pub customer_id: Option<String>,
+    pub currency: Option<String>,
     pub items: Vec<OrderItemRequest>,
 }
 
@@ -45,6 +171,42 @@ pub struct OrderItemRequest {
     pub unit_price_cents: i64,
 }
 
+/// Body of POST /orders/:id/items/:product_id/substitute. The substitute
+/// keeps the original line's quantity.
+#[derive(Deserialize)]
+pub struct SubstituteItemRequest {
+    pub product_id: String,
+    pub product_name: String,
+    pub unit_price_cents: i64,
+}
+
+#[derive(Deserialize)]
+pub struct TagRequest {
+    pub tag: String,
+}
+
+#[derive(Deserialize)]
+pub struct MetadataRequest {
+    /// New value; null or blank clears the key.
+    pub value: Option<String>,
+}
+
+/// Query parameters for GET /orders.
+#[derive(Deserialize, Default)]
+pub struct ListParams {
+    /// Only list orders carrying this tag.
+    pub tag: Option<String>,
+}
+
+/// Query parameters for GET /orders/:id.
+#[derive(Deserialize, Default)]
+pub struct ItemsParams {
+    /// Items to skip, for paging through a large order.
+    pub items_offset: Option<usize>,
+    /// Most items to return.
+    pub items_limit: Option<usize>,
+}
+
 // -- Response types --
 
 #[derive(Serialize)]
@@ -54,6 +216,20 @@ pub struct OrderResponse {
     pub state: String,
     pub items: Vec<OrderItemResponse>,
     pub total_cents: i64,
+    /// Currency the total and every item price are in.
+    pub currency: String,
+    pub shipping_address: Option<Address>,
+    pub delivery: Option<DeliveryResponse>,
+    pub tags: Vec<String>,
+    pub metadata: BTreeMap<String, String>,
+}
+
+/// Shipment details, present once the order has been completed.
+#[derive(Serialize)]
+pub struct DeliveryResponse {
+    pub carrier: Option<String>,
+    pub tracking_number: Option<String>,
+    pub last_location: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -62,6 +238,12 @@ pub struct OrderItemResponse {
     pub product_name: String,
     pub quantity: u32,
     pub unit_price_cents: i64,
+    /// Serial numbers of the units shipped, once the order is completed.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub serial_numbers: Vec<String>,
+    /// Lot the units shipped from, once the order is completed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lot_number: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -76,10 +258,51 @@ pub struct SagaStatusResponse {
     pub order_id: String,
     pub state: String,
     pub completed_steps: Vec<String>,
+    /// Inventory reservation ID per line item, keyed by product ID.
+    pub reservations: BTreeMap<String, String>,
+    /// Whole-order reservation ID of sagas started before per-line tracking.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub reservation_id: Option<String>,
     pub payment_id: Option<String>,
     pub tracking_number: Option<String>,
     pub failure_reason: Option<String>,
+    /// Classification of the step failure, if a step failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failure_code: Option<FailureKind>,
+    /// Message to show the customer when a step failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failure_message: Option<&'static str>,
+    /// Every step attempt with its timing, in the order they started.
+    pub steps: Vec<StepAttempt>,
+    /// Trace the saga ran under and the span of each step attempt; null
+    /// for sagas started before tracing.
+    pub trace: Option<SagaTrace>,
+}
+
+/// Payment taken by the order's saga.
+#[derive(Serialize)]
+pub struct PaymentResponse {
+    pub payment_id: String,
+    pub amount_cents: i64,
+    pub currency: String,
+    pub refunded: bool,
+}
+
+/// Shipment created for the order, from the order and its saga.
+#[derive(Serialize)]
+pub struct ShipmentResponse {
+    pub carrier: Option<String>,
+    pub tracking_number: Option<String>,
+    pub last_location: Option<String>,
+    pub cancelled: bool,
+}
+
+#[derive(Serialize)]
+pub struct OrderDetailsResponse {
+    pub order: OrderResponse,
+    pub saga: Option<SagaStatusResponse>,
+    pub payment: Option<PaymentResponse>,
+    pub shipment: Option<ShipmentResponse>,
 }
 
 #[derive(Serialize)]
@@ -88,87 +311,239 @@ pub struct FulfillResponse {
     pub saga_state: String,
 }
 
+/// Query parameters for POST /orders/:id/fulfill.
+#[derive(Debug, Deserialize, Default)]
+pub struct FulfillParams {
+    /// Check the order and each step's service without running the saga.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Serialize)]
+pub struct DryRunResponse {
+    pub order_id: String,
+    pub dry_run: bool,
+    pub would_succeed: bool,
+    pub definition_version: u32,
+    pub steps: Vec<DryRunStep>,
+}
+
 // -- Handlers --
 
-/// POST /orders — create a new order with optional items.
-#[tracing::instrument(skip(state, req))]
-pub async fn create<S: EventStore + Clone + 'static>(
-    State(state): State<Arc<AppState<S>>>,
+/// Header carrying the caller's customer segment, used for feature flags.
+pub const SEGMENT_HEADER: &str = "x-customer-segment";
+
+/// Header carrying the global sequence a read model has processed through.
+///
+/// Clients compare it with the sequence of an event they wrote, or with a
+/// consumer offset, to tell whether a list already reflects that event.
+pub const PROJECTION_POSITION_HEADER: &str = "x-projection-position";
+
+/// POST /orders — create a new order with optional items, stored together.
+/// The new order's URL is returned in `Location`.
+///
+/// Orders for a named customer, or placed by a customer, need that customer
+/// to be registered and active; customers may only order for themselves.
+/// Without either, the order is a guest order under a new customer ID.
+#[tracing::instrument(skip(state, headers, req))]
+pub async fn create<S: EventStore + Clone + 'static, O: OrderCommands + OrderQueries + 'static>(
+    State(state): State<Arc<AppState<S, O>>>,
+    principal: Principal,
+    headers: HeaderMap,
     Json(req): Json<CreateOrderRequest>,
-) -> Result<(axum::http::StatusCode, Json<OrderCreatedResponse>), ApiError> {
-    let customer_id = if let Some(ref id_str) = req.customer_id {
-        let uuid = uuid::Uuid::parse_str(id_str)
-            .map_err(|e| ApiError::BadRequest(format!("Invalid customer_id: {e}")))?;
-        CustomerId::from_uuid(uuid)
-    } else {
-        CustomerId::new()
+) -> Result<Response, ApiError> {
+    let named = match req.customer_id {
+        Some(ref id_str) => {
+            let uuid = uuid::Uuid::parse_str(id_str)
+                .map_err(|e| ApiError::BadRequest(format!("Invalid customer_id: {e}")))?;
+            Some(CustomerId::from_uuid(uuid))
+        }
+        None => None,
+    };
+    let customer_id = match (principal, named) {
+        (Principal::Customer(caller), named) => {
+            if named.is_some_and(|named| named != caller) {
+                return Err(ApiError::Forbidden(
+                    "Customers may only order for themselves".to_string(),
+                ));
+            }
+            Some(caller)
+        }
+        (_, named) => named,
+    };
+    let customer_id = match customer_id {
+        Some(customer_id) => {
+            state
+                .customer_service
+                .get_customer(customer_id)
+                .await?
+                .unwrap_or_default()
+                .ensure_active(customer_id)
+                .map_err(domain::DomainError::from)?;
+            customer_id
+        }
+        None => CustomerId::new(),
     };
 
-    let cmd = CreateOrder::for_customer(customer_id);
-    let order_id = cmd.order_id;
-    state.order_service.create_order(cmd).await?;
+    let currency = match req.currency {
+        Some(ref code) => code
+            .parse::<Currency>()
+            .map_err(|e| ApiError::BadRequest(e.to_string()))?,
+        None => Currency::default(),
+    };
 
-    for item_req in &req.items {
-        let item = OrderItem::new(
-            item_req.product_id.as_str(),
-            item_req.product_name.as_str(),
-            item_req.quantity,
-            Money::from_cents(item_req.unit_price_cents),
-        );
-        state
-            .order_service
-            .add_item(AddItem::new(order_id, item))
-            .await?;
+    if currency != Currency::default() {
+        let mut ctx = FlagContext::for_customer(customer_id);
+        if let Some(segment) = headers.get(SEGMENT_HEADER).and_then(|v| v.to_str().ok()) {
+            ctx = ctx.with_segment(segment);
+        }
+        if !state
+            .feature_flags
+            .is_enabled(flags::MULTI_CURRENCY, &ctx)
+            .await?
+        {
+            return Err(ApiError::Forbidden(format!(
+                "Currency {currency} is not enabled for this customer"
+            )));
+        }
     }
 
+    let items = req
+        .items
+        .iter()
+        .map(|item_req| {
+            OrderItem::new(
+                item_req.product_id.as_str(),
+                item_req.product_name.as_str(),
+                item_req.quantity,
+                Money::from_cents_in(item_req.unit_price_cents, currency),
+            )
+        })
+        .collect();
+    // Created with its items in one append, so a refused item leaves no
+    // half-filled draft behind
+    let cmd = CreateOrder::for_customer(customer_id)
+        .with_currency(currency)
+        .with_items(items);
+    let order_id = cmd.order_id;
+    state.order_service.create_order(cmd).await?;
+
     let response = OrderCreatedResponse {
         order_id: order_id.to_string(),
         state: "Draft".to_string(),
     };
 
-    Ok((axum::http::StatusCode::CREATED, Json(response)))
+    Ok((
+        axum::http::StatusCode::CREATED,
+        [(header::LOCATION, format!("/orders/{order_id}"))],
+        Json(response),
+    )
+        .into_response())
+}
+
+/// Returns 404 for an order the caller may not see, so customers can't
+/// probe which order IDs exist.
+fn check_order_owner(principal: &Principal, order: &Order, id: &str) -> Result<(), ApiError> {
+    let permitted = match order.customer_id() {
+        Some(customer_id) => principal.permits_customer(customer_id),
+        None => !matches!(principal, Principal::Customer(_)),
+    };
+    if permitted {
+        Ok(())
+    } else {
+        Err(ApiError::NotFound(format!("Order {id} not found")))
+    }
 }
 
 /// GET /orders/:id — load an order aggregate by ID.
-#[tracing::instrument(skip(state))]
-pub async fn get<S: EventStore + Clone + 'static>(
-    State(state): State<Arc<AppState<S>>>,
+///
+/// Items are streamed into the body, and `items_offset`/`items_limit` page
+/// through them; `items_total` counts every item in the order. Customers
+/// only see their own orders.
+#[tracing::instrument(skip(state, params))]
+pub async fn get<S: EventStore + Clone + 'static, O: OrderCommands + OrderQueries + 'static>(
+    State(state): State<Arc<AppState<S, O>>>,
+    principal: Principal,
     Path(id): Path<String>,
-) -> Result<Json<OrderResponse>, ApiError> {
+    Query(params): Query<ItemsParams>,
+) -> Result<StreamedJson, ApiError> {
     let aggregate_id = parse_aggregate_id(&id)?;
     let order = state
         .order_service
         .get_order(aggregate_id)
         .await?
         .ok_or_else(|| ApiError::NotFound(format!("Order {id} not found")))?;
+    check_order_owner(&principal, &order, &id)?;
 
-    let items: Vec<OrderItemResponse> = order
+    let items = order
         .items()
-        .map(|item| OrderItemResponse {
-            product_id: item.product_id.to_string(),
-            product_name: item.product_name.clone(),
-            quantity: item.quantity,
-            unit_price_cents: item.unit_price.cents(),
-        })
-        .collect();
+        .skip(params.items_offset.unwrap_or(0))
+        .take(params.items_limit.unwrap_or(usize::MAX));
+    let mut response = order_response_with_items(aggregate_id, &order, items);
+    let items = std::mem::take(&mut response.items);
 
-    Ok(Json(OrderResponse {
-        id: aggregate_id.to_string(),
-        customer_id: order
-            .customer_id()
-            .map(|c| c.to_string())
-            .unwrap_or_default(),
-        state: order.state().to_string(),
-        items,
-        total_cents: order.total_amount().cents(),
+    Ok(StreamedJson::new(&response)
+        .map_err(|e| ApiError::Internal(e.to_string()))?
+        .field("items_total", order.items().count())
+        .array("items", items))
+}
+
+/// GET /orders/:id/full — an order with its latest saga, payment, and
+/// shipment. Customers only see their own orders.
+#[tracing::instrument(skip(state))]
+pub async fn full<S: EventStore + Clone + 'static, O: OrderCommands + OrderQueries + 'static>(
+    State(state): State<Arc<AppState<S, O>>>,
+    principal: Principal,
+    Path(id): Path<String>,
+) -> Result<Json<OrderDetailsResponse>, ApiError> {
+    let aggregate_id = parse_aggregate_id(&id)?;
+    let details = state
+        .order_details
+        .get(aggregate_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Order {id} not found")))?;
+    check_order_owner(&principal, &details.order, &id)?;
+    let saga = match &details.saga {
+        Some(saga) => {
+            let trace = match saga.id() {
+                Some(saga_id) => state.saga_coordinator.saga_trace(saga_id).await?,
+                None => None,
+            };
+            Some(saga_response(saga, trace))
+        }
+        None => None,
+    };
+
+    Ok(Json(OrderDetailsResponse {
+        order: order_response(aggregate_id, &details.order),
+        saga,
+        payment: details.payment.map(|p| PaymentResponse {
+            payment_id: p.payment_id,
+            amount_cents: p.amount.cents(),
+            currency: p.amount.currency().to_string(),
+            refunded: p.refunded,
+        }),
+        shipment: details.shipment.map(|s| ShipmentResponse {
+            carrier: s.carrier,
+            tracking_number: s.tracking_number,
+            last_location: s.last_location,
+            cancelled: s.cancelled,
+        }),
     }))
 }
 
 /// GET /orders — list current (active) orders from projection.
-#[tracing::instrument(skip(state))]
-pub async fn list<S: EventStore + Clone + 'static>(
-    State(state): State<Arc<AppState<S>>>,
-) -> Result<Json<Vec<OrderResponse>>, ApiError> {
+///
+/// `?tag=` narrows the list to orders carrying that tag. Only the orders
+/// the caller's [`Principal`] may see are listed. The
+/// `x-projection-position` header holds the view's position, read before
+/// the orders, so the list includes at least every event up to it.
+#[tracing::instrument(skip(state, params))]
+pub async fn list<S: EventStore + Clone + 'static, O: OrderCommands + OrderQueries + 'static>(
+    State(state): State<Arc<AppState<S, O>>>,
+    principal: Principal,
+    Query(params): Query<ListParams>,
+) -> Result<Response, ApiError> {
     // Run catch-up to ensure the read model includes latest events
     state
         .projection_processor
@@ -176,7 +551,23 @@ pub async fn list<S: EventStore + Clone + 'static>(
         .await
         .map_err(|e| ApiError::Internal(e.to_string()))?;
 
-    let orders = state.current_orders.get_all_orders().await;
+    let current_orders = state
+        .views
+        .get::<CurrentOrdersView>()
+        .ok_or_else(|| ApiError::NotFound(format!("Read model {CURRENT_ORDERS} is not enabled")))?;
+    let position = current_orders.position().await.last_sequence;
+    let orders = match params.tag.as_deref() {
+        Some(tag) => {
+            current_orders
+                .get_orders_by_tag_for(tag, &principal.rows(&state.pseudonyms))
+                .await
+        }
+        None => {
+            current_orders
+                .get_all_orders_for(&principal.rows(&state.pseudonyms))
+                .await
+        }
+    };
 
     let responses: Vec<OrderResponse> = orders
         .into_iter()
@@ -189,6 +580,8 @@ pub async fn list<S: EventStore + Clone + 'static>(
                     product_name: item.product_name.clone(),
                     quantity: item.quantity,
                     unit_price_cents: item.unit_price.cents(),
+                    serial_numbers: Vec::new(),
+                    lot_number: None,
                 })
                 .collect();
             OrderResponse {
@@ -197,20 +590,32 @@ pub async fn list<S: EventStore + Clone + 'static>(
                 state: o.state.to_string(),
                 items,
                 total_cents: o.total_amount.cents(),
+                currency: o.currency.to_string(),
+                shipping_address: None,
+                // Current orders haven't been completed yet
+                delivery: None,
+                tags: o.tags.into_iter().collect(),
+                metadata: o.metadata,
             }
         })
         .collect();
 
-    Ok(Json(responses))
+    Ok((
+        [(PROJECTION_POSITION_HEADER, position.to_string())],
+        Json(responses),
+    )
+        .into_response())
 }
 
 /// POST /orders/:id/submit — submit an order for fulfillment.
 #[tracing::instrument(skip(state))]
-pub async fn submit<S: EventStore + Clone + 'static>(
-    State(state): State<Arc<AppState<S>>>,
+pub async fn submit<S: EventStore + Clone + 'static, O: OrderCommands + OrderQueries + 'static>(
+    State(state): State<Arc<AppState<S, O>>>,
+    principal: Principal,
     Path(id): Path<String>,
 ) -> Result<Json<OrderResponse>, ApiError> {
     let aggregate_id = parse_aggregate_id(&id)?;
+    ensure_order_owner(&state, &principal, aggregate_id).await?;
 
     state
         .order_service
@@ -226,12 +631,7 @@ pub async fn submit<S: EventStore + Clone + 'static>(
 
     let items: Vec<OrderItemResponse> = order
         .items()
-        .map(|item| OrderItemResponse {
-            product_id: item.product_id.to_string(),
-            product_name: item.product_name.clone(),
-            quantity: item.quantity,
-            unit_price_cents: item.unit_price.cents(),
-        })
+        .map(|item| item_response(&order, item))
         .collect();
 
     Ok(Json(OrderResponse {
@@ -243,18 +643,265 @@ pub async fn submit<S: EventStore + Clone + 'static>(
         state: order.state().to_string(),
         items,
         total_cents: order.total_amount().cents(),
+        currency: order.currency().to_string(),
+        shipping_address: order.shipping_address().cloned(),
+        delivery: delivery_response(&order),
+        tags: order.tags().map(String::from).collect(),
+        metadata: order.metadata().clone(),
     }))
 }
 
-/// POST /orders/:id/fulfill — trigger saga execution for the order.
+/// POST /orders/:id/tags — attach a tag to an order.
+#[tracing::instrument(skip(state, req))]
+pub async fn add_tag<S: EventStore + Clone + 'static, O: OrderCommands + OrderQueries + 'static>(
+    State(state): State<Arc<AppState<S, O>>>,
+    principal: Principal,
+    Path(id): Path<String>,
+    Json(req): Json<TagRequest>,
+) -> Result<Json<OrderResponse>, ApiError> {
+    let aggregate_id = parse_aggregate_id(&id)?;
+    ensure_order_owner(&state, &principal, aggregate_id).await?;
+
+    let result = state
+        .order_service
+        .add_tag(AddTag::new(aggregate_id, req.tag))
+        .await?;
+
+    Ok(Json(order_response(aggregate_id, &result.aggregate)))
+}
+
+/// DELETE /orders/:id/tags/:tag — remove a tag from an order.
+#[tracing::instrument(skip(state))]
+pub async fn remove_tag<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+    principal: Principal,
+    Path((id, tag)): Path<(String, String)>,
+) -> Result<Json<OrderResponse>, ApiError> {
+    let aggregate_id = parse_aggregate_id(&id)?;
+    ensure_order_owner(&state, &principal, aggregate_id).await?;
+
+    let result = state
+        .order_service
+        .remove_tag(RemoveTag::new(aggregate_id, tag))
+        .await?;
+
+    Ok(Json(order_response(aggregate_id, &result.aggregate)))
+}
+
+/// PUT /orders/:id/metadata/:key — set a metadata attachment on a draft order.
+#[tracing::instrument(skip(state, req))]
+pub async fn set_metadata<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+    principal: Principal,
+    Path((id, key)): Path<(String, String)>,
+    Json(req): Json<MetadataRequest>,
+) -> Result<Json<OrderResponse>, ApiError> {
+    let aggregate_id = parse_aggregate_id(&id)?;
+    ensure_order_owner(&state, &principal, aggregate_id).await?;
+
+    let result = state
+        .order_service
+        .set_metadata(SetOrderMetadata {
+            order_id: aggregate_id,
+            key,
+            value: req.value,
+        })
+        .await?;
+
+    Ok(Json(order_response(aggregate_id, &result.aggregate)))
+}
+
+/// POST /orders/:id/shipping-address — give an order its shipping address.
+#[tracing::instrument(skip(state, address))]
+pub async fn set_shipping_address<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+    principal: Principal,
+    Path(id): Path<String>,
+    Json(address): Json<Address>,
+) -> Result<Json<OrderResponse>, ApiError> {
+    let aggregate_id = parse_aggregate_id(&id)?;
+    ensure_order_owner(&state, &principal, aggregate_id).await?;
+
+    let result = state
+        .order_service
+        .set_shipping_address(SetShippingAddress::new(aggregate_id, address))
+        .await?;
+
+    Ok(Json(order_response(aggregate_id, &result.aggregate)))
+}
+
+/// PUT /orders/:id/shipping-address — change an order's shipping address.
+#[tracing::instrument(skip(state, address))]
+pub async fn update_shipping_address<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+    principal: Principal,
+    Path(id): Path<String>,
+    Json(address): Json<Address>,
+) -> Result<Json<OrderResponse>, ApiError> {
+    let aggregate_id = parse_aggregate_id(&id)?;
+    ensure_order_owner(&state, &principal, aggregate_id).await?;
+
+    let result = state
+        .order_service
+        .update_shipping_address(UpdateShippingAddress::new(aggregate_id, address))
+        .await?;
+
+    Ok(Json(order_response(aggregate_id, &result.aggregate)))
+}
+
+/// DELETE /orders/:id/metadata/:key — clear a metadata attachment.
 #[tracing::instrument(skip(state))]
-pub async fn fulfill<S: EventStore + Clone + 'static>(
-    State(state): State<Arc<AppState<S>>>,
+pub async fn clear_metadata<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+    principal: Principal,
+    Path((id, key)): Path<(String, String)>,
+) -> Result<Json<OrderResponse>, ApiError> {
+    let aggregate_id = parse_aggregate_id(&id)?;
+    ensure_order_owner(&state, &principal, aggregate_id).await?;
+
+    let result = state
+        .order_service
+        .set_metadata(SetOrderMetadata::clear(aggregate_id, key))
+        .await?;
+
+    Ok(Json(order_response(aggregate_id, &result.aggregate)))
+}
+
+/// POST /orders/:id/items/:product_id/substitute — replace an item of a
+/// reserved or processing order with a substitute product.
+///
+/// The substitute is reserved in place of the original and the customer's
+/// notification hooks are told. Returns 409 if the order isn't reserved or
+/// processing, or the substitute is out of stock.
+#[tracing::instrument(skip(state, req))]
+pub async fn substitute_item<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+    Path((id, product_id)): Path<(String, String)>,
+    Json(req): Json<SubstituteItemRequest>,
+) -> Result<Json<OrderResponse>, ApiError> {
+    let aggregate_id = parse_aggregate_id(&id)?;
+
+    // The substitute is priced in the order's currency
+    let currency = state
+        .order_service
+        .get_order(aggregate_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Order {id} not found")))?
+        .currency();
+    let order = state
+        .saga_coordinator
+        .substitute_item(SubstituteItem::new(
+            aggregate_id,
+            product_id,
+            req.product_id,
+            req.product_name,
+            Money::from_cents_in(req.unit_price_cents, currency),
+        ))
+        .await?;
+
+    Ok(Json(order_response(aggregate_id, &order)))
+}
+
+/// Returns 404 for an unknown order, so a command on it doesn't start a
+/// stream, and for an order the caller may not see, as [`get`] does.
+async fn ensure_order_owner<S: EventStore, O: OrderQueries>(
+    state: &AppState<S, O>,
+    principal: &Principal,
+    order_id: AggregateId,
+) -> Result<(), ApiError> {
+    let order = state
+        .order_service
+        .get_order(order_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Order {order_id} not found")))?;
+    check_order_owner(principal, &order, &order_id.to_string())
+}
+
+/// Header carrying the caller's W3C trace context.
+pub const TRACEPARENT_HEADER: &str = "traceparent";
+
+/// POST /orders/:id/fulfill — trigger saga execution for the order.
+///
+/// With `?dry_run=true`, validates the order and asks each step's service
+/// whether it would succeed, without reserving, charging, or recording a
+/// saga. The saga continues the trace of a valid `traceparent` header and
+/// starts a new one otherwise. When a saga runner is configured, the saga
+/// is only started here and the response is 202 Accepted; poll
+/// `/orders/:saga_id/saga` for its outcome.
+#[tracing::instrument(skip(state, headers))]
+pub async fn fulfill<S: EventStore + Clone + 'static, O: OrderCommands + OrderQueries + 'static>(
+    State(state): State<Arc<AppState<S, O>>>,
     Path(id): Path<String>,
-) -> Result<Json<FulfillResponse>, ApiError> {
+    headers: HeaderMap,
+    Query(params): Query<FulfillParams>,
+) -> Result<Response, ApiError> {
     let aggregate_id = parse_aggregate_id(&id)?;
 
-    let saga_id = state.saga_coordinator.execute_saga(aggregate_id).await?;
+    if params.dry_run {
+        let report = state.saga_coordinator.dry_run(aggregate_id).await?;
+        return Ok(Json(DryRunResponse {
+            order_id: report.order_id.to_string(),
+            dry_run: true,
+            would_succeed: report.would_succeed(),
+            definition_version: report.definition_version,
+            steps: report.steps,
+        })
+        .into_response());
+    }
+
+    // The saga runs on its own task so a timed-out request can't drop it
+    // mid-step; dropping this handler cancels it at the next step boundary.
+    let trace = headers
+        .get(TRACEPARENT_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(TraceContext::from_traceparent)
+        .unwrap_or_else(TraceContext::new_root);
+
+    if let Some(runner) = &state.saga_runner {
+        let saga_id = state
+            .saga_coordinator
+            .start_saga(aggregate_id, trace)
+            .await?;
+        runner.wake();
+        return Ok((
+            axum::http::StatusCode::ACCEPTED,
+            Json(FulfillResponse {
+                saga_id: saga_id.to_string(),
+                saga_state: format!("{:?}", SagaState::Running),
+            }),
+        )
+            .into_response());
+    }
+
+    let cancel = CancellationToken::new();
+    let _cancel_on_drop = cancel.clone().drop_guard();
+    let saga_state = Arc::clone(&state);
+    let saga_id = tokio::spawn(async move {
+        saga_state
+            .saga_coordinator
+            .execute_saga_traced(aggregate_id, cancel, trace)
+            .await
+    })
+    .await
+    .map_err(|e| ApiError::Internal(format!("Saga task failed: {e}")))??;
 
     let saga = state
         .saga_coordinator
@@ -265,35 +912,79 @@ pub async fn fulfill<S: EventStore + Clone + 'static>(
     Ok(Json(FulfillResponse {
         saga_id: saga_id.to_string(),
         saga_state: format!("{:?}", saga.state()),
-    }))
+    })
+    .into_response())
 }
 
 /// GET /orders/:id/saga — get saga state for an order.
+///
+/// Customers only see the sagas of their own orders.
 #[tracing::instrument(skip(state))]
-pub async fn saga_status<S: EventStore + Clone + 'static>(
-    State(state): State<Arc<AppState<S>>>,
+pub async fn saga_status<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+    principal: Principal,
     Path(id): Path<String>,
 ) -> Result<Json<SagaStatusResponse>, ApiError> {
     let saga_id = parse_aggregate_id(&id)?;
+    let not_found = || ApiError::NotFound(format!("Saga {id} not found"));
 
     let saga = state
         .saga_coordinator
         .get_saga(saga_id)
         .await?
-        .ok_or_else(|| ApiError::NotFound(format!("Saga {id} not found")))?;
+        .ok_or_else(not_found)?;
+    let order = match saga.order_id() {
+        Some(order_id) => state.order_service.get_order(order_id).await?,
+        None => None,
+    };
+    match order {
+        Some(order) => check_order_owner(&principal, &order, &id).map_err(|_| not_found())?,
+        None if matches!(principal, Principal::Customer(_)) => return Err(not_found()),
+        None => {}
+    }
+    let trace = state.saga_coordinator.saga_trace(saga_id).await?;
 
-    Ok(Json(SagaStatusResponse {
-        saga_id: saga_id.to_string(),
-        order_id: saga.order_id().map(|id| id.to_string()).unwrap_or_default(),
-        state: format!("{:?}", saga.state()),
-        completed_steps: saga.completed_steps().to_vec(),
-        reservation_id: saga.reservation_id().map(String::from),
-        payment_id: saga.payment_id().map(String::from),
-        tracking_number: saga.tracking_number().map(String::from),
-        failure_reason: saga.failure_reason().map(String::from),
-    }))
+    Ok(Json(saga_response(&saga, trace)))
+}
+
+/// Sort order for listed events.
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EventOrder {
+    #[default]
+    Asc,
+    Desc,
 }
 
+/// Query parameters for GET /orders/:id/events.
+#[derive(Deserialize, Default)]
+pub struct EventsParams {
+    /// Comma-separated event types to include, e.g. `ItemAdded,OrderSubmitted`.
+    pub types: Option<String>,
+    pub from_version: Option<i64>,
+    pub to_version: Option<i64>,
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
+    #[serde(default)]
+    pub order: EventOrder,
+    /// Comma-separated response fields to keep, e.g. `event_type,version`.
+    pub fields: Option<String>,
+}
+
+/// Fields of [`EventEnvelopeResponse`] selectable with `?fields=`.
+const EVENT_FIELDS: &[&str] = &[
+    "event_id",
+    "event_type",
+    "aggregate_id",
+    "version",
+    "timestamp",
+    "payload",
+    "annotations",
+];
+
 /// Response type for event envelope data.
 #[derive(Serialize)]
 pub struct EventEnvelopeResponse {
@@ -303,35 +994,222 @@ pub struct EventEnvelopeResponse {
     pub version: i64,
     pub timestamp: String,
     pub payload: serde_json::Value,
+    /// Operator notes on the event, if any.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub annotations: Vec<AnnotationResponse>,
 }
 
-/// GET /orders/:id/events — list all events for an order aggregate.
-#[tracing::instrument(skip(state))]
-pub async fn events<S: EventStore + Clone + 'static>(
-    State(state): State<Arc<AppState<S>>>,
+/// CloudEvents `source` of order events.
+pub const ORDER_EVENT_SOURCE: &str = "/orders";
+
+/// GET /orders/:id/events — list events for an order aggregate.
+///
+/// Supports filtering by `types` and version range, paging with `limit` and
+/// `offset`, `order=desc` for newest first, and `fields` to return only
+/// some fields of each event. With `Accept: application/cloudevents+json`
+/// the events are returned as a CloudEvents batch instead. Streams the
+/// caller's audience may not see list no events.
+#[tracing::instrument(skip(state, headers, params))]
+pub async fn events<S: EventStore + Clone + 'static, O: OrderCommands + OrderQueries + 'static>(
+    State(state): State<Arc<AppState<S, O>>>,
     Path(id): Path<String>,
-) -> Result<Json<Vec<EventEnvelopeResponse>>, ApiError> {
+    principal: Principal,
+    headers: HeaderMap,
+    Query(params): Query<EventsParams>,
+) -> Result<Response, ApiError> {
     let aggregate_id = parse_aggregate_id(&id)?;
+    let cloudevents = accepts_cloudevents(&headers);
+    let fields = params.fields.as_deref().map(split_list);
+    if cloudevents && fields.is_some() {
+        return Err(ApiError::BadRequest(
+            "fields can't be selected for CloudEvents output".to_string(),
+        ));
+    }
+    if let Some(unknown) = fields
+        .iter()
+        .flatten()
+        .find(|f| !EVENT_FIELDS.contains(&f.as_str()))
+    {
+        return Err(ApiError::BadRequest(format!(
+            "Unknown field '{unknown}'; expected one of {}",
+            EVENT_FIELDS.join(", ")
+        )));
+    }
+    if let Principal::Customer(_) = principal {
+        let order = state
+            .order_service
+            .get_order(aggregate_id)
+            .await?
+            .ok_or_else(|| ApiError::NotFound(format!("Order {id} not found")))?;
+        check_order_owner(&principal, &order, &id)?;
+    }
+
+    let mut query = EventQuery::for_aggregate(aggregate_id);
+    if let Some(types) = params.types.as_deref() {
+        query = query.event_types(split_list(types));
+    }
+    if let Some(from) = params.from_version {
+        query = query.from_version(Version::new(from));
+    }
+    if let Some(to) = params.to_version {
+        query = query.to_version(Version::new(to));
+    }
+    if let Some(limit) = params.limit {
+        query = query.limit(limit);
+    }
+    if let Some(offset) = params.offset {
+        query = query.offset(offset);
+    }
+    if params.order == EventOrder::Desc {
+        query = query.descending();
+    }
 
-    let envelopes = state
+    let mut envelopes = state
         .event_store
-        .get_events_for_aggregate(aggregate_id)
+        .query_events(query)
         .await
         .map_err(|e| ApiError::Internal(e.to_string()))?;
+    envelopes.retain(|e| state.visibility.is_visible(e, principal.audience()));
 
-    let responses: Vec<EventEnvelopeResponse> = envelopes
+    if cloudevents {
+        let events: Vec<_> = envelopes
+            .iter()
+            .map(|e| CloudEvent::from_envelope(e, ORDER_EVENT_SOURCE))
+            .collect();
+        return Ok((
+            [(header::CONTENT_TYPE, CLOUDEVENTS_BATCH_JSON)],
+            Json(events),
+        )
+            .into_response());
+    }
+
+    let mut annotations = annotations_by_event(&state, &envelopes).await?;
+    let responses = envelopes
         .into_iter()
-        .map(|e| EventEnvelopeResponse {
-            event_id: e.event_id.to_string(),
-            event_type: e.event_type,
-            aggregate_id: e.aggregate_id.to_string(),
-            version: e.version.as_i64(),
-            timestamp: e.timestamp.to_rfc3339(),
-            payload: e.payload,
+        .map(|e| {
+            let response = EventEnvelopeResponse {
+                annotations: annotations.remove(&e.event_id).unwrap_or_default(),
+                event_id: e.event_id.to_string(),
+                event_type: e.event_type,
+                aggregate_id: e.aggregate_id.to_string(),
+                version: e.version.as_i64(),
+                timestamp: e.timestamp.to_rfc3339(),
+                payload: e.payload,
+            };
+            let mut value =
+                serde_json::to_value(response).map_err(|e| ApiError::Internal(e.to_string()))?;
+            if let (Some(fields), Some(object)) = (&fields, value.as_object_mut()) {
+                object.retain(|key, _| fields.contains(key));
+            }
+            Ok(value)
         })
-        .collect();
+        .collect::<Result<Vec<_>, ApiError>>()?;
+
+    Ok(Json(responses).into_response())
+}
+
+/// Returns true if the request accepts CloudEvents JSON, as single events
+/// or a batch.
+fn accepts_cloudevents(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|range| range.split(';').next().unwrap_or_default().trim())
+        .any(|media_type| {
+            media_type.eq_ignore_ascii_case(CLOUDEVENTS_JSON)
+                || media_type.eq_ignore_ascii_case(CLOUDEVENTS_BATCH_JSON)
+        })
+}
+
+/// Splits a comma-separated query parameter, dropping empty entries.
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+fn order_response(id: AggregateId, order: &Order) -> OrderResponse {
+    order_response_with_items(id, order, order.items())
+}
+
+/// Builds an order response listing only `items`.
+fn order_response_with_items<'a>(
+    id: AggregateId,
+    order: &Order,
+    items: impl Iterator<Item = &'a OrderItem>,
+) -> OrderResponse {
+    OrderResponse {
+        id: id.to_string(),
+        customer_id: order
+            .customer_id()
+            .map(|c| c.to_string())
+            .unwrap_or_default(),
+        state: order.state().to_string(),
+        items: items.map(|item| item_response(order, item)).collect(),
+        total_cents: order.total_amount().cents(),
+        currency: order.currency().to_string(),
+        shipping_address: order.shipping_address().cloned(),
+        delivery: delivery_response(order),
+        tags: order.tags().map(String::from).collect(),
+        metadata: order.metadata().clone(),
+    }
+}
+
+fn saga_response(saga: &SagaInstance, trace: Option<SagaTrace>) -> SagaStatusResponse {
+    SagaStatusResponse {
+        saga_id: saga.id().map(|id| id.to_string()).unwrap_or_default(),
+        order_id: saga.order_id().map(|id| id.to_string()).unwrap_or_default(),
+        state: format!("{:?}", saga.state()),
+        completed_steps: saga.completed_steps().to_vec(),
+        reservations: saga
+            .reservations()
+            .iter()
+            .map(|(product_id, rid)| (product_id.to_string(), rid.clone()))
+            .collect(),
+        reservation_id: saga.reservation_id().map(String::from),
+        payment_id: saga.payment_id().map(String::from),
+        tracking_number: saga.tracking_number().map(String::from),
+        failure_reason: saga.failure_reason().map(String::from),
+        failure_code: saga.failure_kind(),
+        failure_message: saga.failure_kind().map(|kind| kind.customer_message()),
+        steps: saga.step_attempts().to_vec(),
+        trace,
+    }
+}
+
+fn item_response(order: &Order, item: &OrderItem) -> OrderItemResponse {
+    let shipped = order.shipped_item(&item.product_id);
+    OrderItemResponse {
+        product_id: item.product_id.to_string(),
+        product_name: item.product_name.clone(),
+        quantity: item.quantity,
+        unit_price_cents: item.unit_price.cents(),
+        serial_numbers: shipped
+            .map(|s| s.serial_numbers.clone())
+            .unwrap_or_default(),
+        lot_number: shipped.and_then(|s| s.lot_number.clone()),
+    }
+}
 
-    Ok(Json(responses))
+fn delivery_response(order: &Order) -> Option<DeliveryResponse> {
+    matches!(
+        order.state(),
+        OrderState::Completed
+            | OrderState::Shipped
+            | OrderState::InTransit
+            | OrderState::Delivered
+            | OrderState::DeliveryFailed
+    )
+    .then(|| DeliveryResponse {
+        carrier: order.carrier().map(String::from),
+        tracking_number: order.tracking_number().map(String::from),
+        last_location: order.last_location().map(String::from),
+    })
 }
 
 fn parse_aggregate_id(id: &str) -> Result<AggregateId, ApiError> {
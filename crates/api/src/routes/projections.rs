@@ -0,0 +1,64 @@
+//! Projection progress endpoints.
+
+use std::sync::Arc;
+
+use axum::Json;
+use axum::extract::State;
+use domain::{OrderCommands, OrderQueries};
+use event_store::EventStore;
+use serde::Serialize;
+
+use crate::error::ApiError;
+use crate::routes::orders::AppState;
+
+#[derive(Serialize)]
+pub struct ProjectionsStatusResponse {
+    /// Global sequence of the newest event in the store.
+    pub head_sequence: u64,
+    pub projections: Vec<ProjectionProgress>,
+}
+
+#[derive(Serialize)]
+pub struct ProjectionProgress {
+    pub name: String,
+    pub events_processed: u64,
+    pub last_sequence: u64,
+    pub last_event_id: Option<String>,
+    /// Number of log positions between this projection and the head.
+    pub lag: u64,
+    pub behind: bool,
+    /// Difference between the processed counter and the sequence position.
+    pub counter_drift: i64,
+}
+
+/// GET /projections/status — each projection's log position relative to the head.
+#[tracing::instrument(skip(state))]
+pub async fn status<S: EventStore + Clone + 'static, O: OrderCommands + OrderQueries + 'static>(
+    State(state): State<Arc<AppState<S, O>>>,
+) -> Result<Json<ProjectionsStatusResponse>, ApiError> {
+    let processor = &state.projection_processor;
+    let head = processor
+        .head_sequence()
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+
+    let projections = processor
+        .registrations()
+        .await
+        .into_iter()
+        .map(|(name, position)| ProjectionProgress {
+            name,
+            events_processed: position.events_processed,
+            last_sequence: position.last_sequence,
+            last_event_id: position.last_event_id.map(|id| id.to_string()),
+            lag: position.lag(head),
+            behind: position.is_behind(head),
+            counter_drift: position.counter_drift(),
+        })
+        .collect();
+
+    Ok(Json(ProjectionsStatusResponse {
+        head_sequence: head,
+        projections,
+    }))
+}
@@ -0,0 +1,352 @@
+//! Saga visualization and batch fulfillment endpoints.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use axum::Json;
+use axum::extract::{Path, Query, State};
+use axum::http::{StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Utc};
+use common::AggregateId;
+use domain::{OrderCommands, OrderQueries, OrderState};
+use event_store::EventStore;
+use futures_util::StreamExt;
+use projections::CurrentOrdersView;
+use projections::registry::CURRENT_ORDERS;
+use saga::{SagaError, SagaState, TraceContext};
+use serde::{Deserialize, Serialize};
+use tokio_util::sync::CancellationToken;
+
+use crate::batch::{
+    BatchOrderStatus, DEFAULT_BATCH_CONCURRENCY, FulfillmentBatch, MAX_BATCH_CONCURRENCY,
+    MAX_BATCH_ORDERS,
+};
+use crate::error::ApiError;
+use crate::routes::orders::AppState;
+
+// -- Request types --
+
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum GraphFormat {
+    #[default]
+    Json,
+    Dot,
+}
+
+#[derive(Deserialize)]
+pub struct GraphParams {
+    #[serde(default)]
+    pub format: GraphFormat,
+}
+
+#[derive(Deserialize)]
+pub struct FulfillBatchRequest {
+    /// Orders to fulfill.
+    #[serde(default)]
+    pub order_ids: Vec<String>,
+    /// Also fulfill every draft order tagged with this tag that has items.
+    pub tag: Option<String>,
+    /// Sagas to run at once (default 8, at most 64).
+    pub concurrency: Option<usize>,
+}
+
+// -- Response types --
+
+#[derive(Serialize)]
+pub struct FulfillmentBatchResponse {
+    pub batch_id: String,
+    pub requested_at: DateTime<Utc>,
+    pub concurrency: usize,
+    /// `"running"` until every order's saga has been started or refused,
+    /// then `"finished"`.
+    pub status: &'static str,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub summary: BatchSummaryResponse,
+    pub orders: Vec<BatchOrderResponse>,
+}
+
+/// How many of a batch's orders are in each status.
+#[derive(Serialize, Default)]
+pub struct BatchSummaryResponse {
+    pub total: usize,
+    pub queued: usize,
+    pub running: usize,
+    pub completed: usize,
+    pub failed: usize,
+}
+
+#[derive(Serialize)]
+pub struct BatchOrderResponse {
+    pub order_id: String,
+    /// `"queued"`, `"running"`, `"completed"`, or `"failed"`; an order is
+    /// running until its saga completes or fails.
+    pub status: &'static str,
+    pub saga_id: Option<String>,
+    pub saga_state: Option<String>,
+    /// Why no saga could be started.
+    pub error: Option<String>,
+}
+
+// -- Handlers --
+
+/// POST /sagas/fulfill-batch — fulfill many orders in the background, at
+/// most `concurrency` at a time. Returns 202 Accepted with the batch; poll
+/// `/sagas/batches/:id` for each order's progress.
+#[tracing::instrument(skip(state, req))]
+pub async fn fulfill_batch<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+    Json(req): Json<FulfillBatchRequest>,
+) -> Result<(StatusCode, Json<FulfillmentBatchResponse>), ApiError> {
+    if req.order_ids.is_empty() && req.tag.is_none() {
+        return Err(ApiError::BadRequest(
+            "A batch needs order_ids, a tag, or both".to_string(),
+        ));
+    }
+    let mut order_ids = req
+        .order_ids
+        .iter()
+        .map(|id| {
+            uuid::Uuid::parse_str(id)
+                .map(AggregateId::from)
+                .map_err(|e| ApiError::BadRequest(format!("Invalid order ID {id:?}: {e}")))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if let Some(tag) = &req.tag {
+        order_ids.extend(tagged_drafts(&state, tag).await?);
+    }
+    let mut seen = HashSet::new();
+    order_ids.retain(|id| seen.insert(*id));
+    if order_ids.len() > MAX_BATCH_ORDERS {
+        return Err(ApiError::BadRequest(format!(
+            "A batch holds at most {MAX_BATCH_ORDERS} orders, got {}",
+            order_ids.len()
+        )));
+    }
+
+    let concurrency = req
+        .concurrency
+        .unwrap_or(DEFAULT_BATCH_CONCURRENCY)
+        .clamp(1, MAX_BATCH_CONCURRENCY);
+    let batch = state
+        .fulfillment_batches
+        .start(order_ids.clone(), concurrency);
+    let batch_id = batch.batch_id;
+    tracing::info!(%batch_id, orders = order_ids.len(), concurrency, "fulfillment batch started");
+
+    let task_state = state.clone();
+    tokio::spawn(async move {
+        futures_util::stream::iter(order_ids.into_iter().enumerate())
+            .for_each_concurrent(concurrency, |(index, order_id)| {
+                let state = &task_state;
+                async move {
+                    let batches = &state.fulfillment_batches;
+                    batches.update(batch_id, index, BatchOrderStatus::Running);
+                    let status = match fulfill_order(state, order_id).await {
+                        Ok(saga_id) => BatchOrderStatus::Started { saga_id },
+                        Err(e) => {
+                            tracing::warn!(%batch_id, %order_id, error = %e, "batched fulfillment refused");
+                            BatchOrderStatus::Failed(e.to_string())
+                        }
+                    };
+                    batches.update(batch_id, index, status);
+                }
+            })
+            .await;
+        task_state.fulfillment_batches.finish(batch_id);
+        tracing::info!(%batch_id, "fulfillment batch finished");
+    });
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(batch_response(&state, batch).await?),
+    ))
+}
+
+/// GET /sagas/batches/:id — a fulfillment batch's progress, per order.
+#[tracing::instrument(skip(state))]
+pub async fn batch_status<
+    S: EventStore + Clone + 'static,
+    O: OrderCommands + OrderQueries + 'static,
+>(
+    State(state): State<Arc<AppState<S, O>>>,
+    Path(id): Path<String>,
+) -> Result<Json<FulfillmentBatchResponse>, ApiError> {
+    let batch = uuid::Uuid::parse_str(&id)
+        .ok()
+        .and_then(|id| state.fulfillment_batches.get(id))
+        .ok_or_else(|| ApiError::NotFound(format!("Batch {id} not found")))?;
+    Ok(Json(batch_response(&state, batch).await?))
+}
+
+/// GET /sagas/:id/graph — the saga's step graph with statuses and timings.
+///
+/// Returns JSON nodes and edges, or Graphviz DOT with `?format=dot`.
+#[tracing::instrument(skip(state, params))]
+pub async fn graph<S: EventStore + Clone + 'static, O: OrderCommands + OrderQueries + 'static>(
+    State(state): State<Arc<AppState<S, O>>>,
+    Path(id): Path<String>,
+    Query(params): Query<GraphParams>,
+) -> Result<Response, ApiError> {
+    let saga_id = uuid::Uuid::parse_str(&id)
+        .map(AggregateId::from)
+        .map_err(|e| ApiError::BadRequest(format!("Invalid ID format: {e}")))?;
+
+    let graph = state
+        .saga_coordinator
+        .saga_graph(saga_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Saga {id} not found")))?;
+
+    Ok(match params.format {
+        GraphFormat::Json => Json(graph).into_response(),
+        GraphFormat::Dot => (
+            [(header::CONTENT_TYPE, "text/vnd.graphviz")],
+            graph.to_dot(),
+        )
+            .into_response(),
+    })
+}
+
+// -- Helpers --
+
+/// Returns the draft orders tagged with `tag` that have items, the ones a
+/// saga accepts, oldest first.
+async fn tagged_drafts<S: EventStore + Clone + 'static, O>(
+    state: &AppState<S, O>,
+    tag: &str,
+) -> Result<Vec<AggregateId>, ApiError> {
+    state
+        .projection_processor
+        .run_catch_up()
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+    let current_orders = state
+        .views
+        .get::<CurrentOrdersView>()
+        .ok_or_else(|| ApiError::NotFound(format!("Read model {CURRENT_ORDERS} is not enabled")))?;
+
+    let mut drafts: Vec<_> = current_orders
+        .get_orders_by_tag(tag)
+        .await
+        .into_iter()
+        .filter(|o| o.state == OrderState::Draft && o.item_count > 0)
+        .collect();
+    drafts.sort_by_key(|o| o.created_at);
+    Ok(drafts.into_iter().map(|o| o.order_id).collect())
+}
+
+/// Starts fulfilling one order the way `POST /orders/:id/fulfill` would:
+/// handed to the saga runner when there is one, run to its end otherwise.
+async fn fulfill_order<S: EventStore + Clone + 'static, O: OrderCommands + OrderQueries>(
+    state: &AppState<S, O>,
+    order_id: AggregateId,
+) -> Result<AggregateId, SagaError> {
+    let trace = TraceContext::new_root();
+    match &state.saga_runner {
+        Some(runner) => {
+            let saga_id = state.saga_coordinator.start_saga(order_id, trace).await?;
+            runner.wake();
+            Ok(saga_id)
+        }
+        None => {
+            state
+                .saga_coordinator
+                .execute_saga_traced(order_id, CancellationToken::new(), trace)
+                .await
+        }
+    }
+}
+
+/// Describes `batch`, looking up the current state of each started saga.
+///
+/// Sagas found in a terminal state are recorded on the batch, so later polls
+/// don't look them up again.
+async fn batch_response<S: EventStore + Clone + 'static, O>(
+    state: &AppState<S, O>,
+    batch: FulfillmentBatch,
+) -> Result<FulfillmentBatchResponse, ApiError> {
+    let mut summary = BatchSummaryResponse {
+        total: batch.orders.len(),
+        ..Default::default()
+    };
+    let mut orders = Vec::with_capacity(batch.orders.len());
+    for (index, order) in batch.orders.into_iter().enumerate() {
+        let (status, saga_id, saga_state, error) = match order.status {
+            BatchOrderStatus::Queued => ("queued", None, None, None),
+            BatchOrderStatus::Running => ("running", None, None, None),
+            BatchOrderStatus::Failed(e) => ("failed", None, None, Some(e)),
+            BatchOrderStatus::Ended {
+                saga_id,
+                state: ended,
+            } => saga_status(saga_id, Some(ended)),
+            BatchOrderStatus::Started { saga_id } => {
+                let saga_state = state
+                    .saga_coordinator
+                    .get_saga(saga_id)
+                    .await?
+                    .map(|saga| saga.state());
+                if let Some(ended) = saga_state.filter(SagaState::is_terminal) {
+                    state.fulfillment_batches.update(
+                        batch.batch_id,
+                        index,
+                        BatchOrderStatus::Ended {
+                            saga_id,
+                            state: ended,
+                        },
+                    );
+                }
+                saga_status(saga_id, saga_state)
+            }
+        };
+        match status {
+            "queued" => summary.queued += 1,
+            "running" => summary.running += 1,
+            "completed" => summary.completed += 1,
+            _ => summary.failed += 1,
+        }
+        orders.push(BatchOrderResponse {
+            order_id: order.order_id.to_string(),
+            status,
+            saga_id,
+            saga_state,
+            error,
+        });
+    }
+
+    Ok(FulfillmentBatchResponse {
+        batch_id: batch.batch_id.to_string(),
+        requested_at: batch.requested_at,
+        concurrency: batch.concurrency,
+        status: if batch.finished_at.is_some() {
+            "finished"
+        } else {
+            "running"
+        },
+        finished_at: batch.finished_at,
+        summary,
+        orders,
+    })
+}
+
+/// Describes a batched order whose saga was started and is in `saga_state`.
+fn saga_status(
+    saga_id: AggregateId,
+    saga_state: Option<SagaState>,
+) -> (&'static str, Option<String>, Option<String>, Option<String>) {
+    let status = match saga_state {
+        Some(SagaState::Completed) => "completed",
+        Some(SagaState::Failed) => "failed",
+        _ => "running",
+    };
+    (
+        status,
+        Some(saga_id.to_string()),
+        saga_state.map(|s| format!("{s:?}")),
+        None,
+    )
+}
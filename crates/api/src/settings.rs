@@ -0,0 +1,367 @@
+//! Operational settings that can change without a restart.
+//!
+//! The settings start from the environment, like the rest of [`Config`].
+//! When a settings file is configured, it is read at startup and again on
+//! `SIGHUP` or `POST /admin/settings/reload`. It holds `KEY=value` lines
+//! using the same names as the environment variables; `#` starts a comment.
+//! Keys left out of the file fall back to their environment value, so
+//! deleting a line undoes it. Other settings only change with a restart;
+//! setting them in the file is reported and otherwise ignored.
+//!
+//! Each subsystem reads its setting through a shared [`Tunable`] handle on
+//! every use, so a reload takes effect on the next order, saga step, or
+//! projection wait. A file that fails to parse or validate changes nothing.
+//!
+//! [`Config`]: crate::config::Config
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use common::Tunable;
+use domain::DraftThrottle;
+use saga::coordinator::DEFAULT_MAX_STEP_ATTEMPTS;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::config::Config;
+
+/// Applies a new tracing filter directive, e.g. `info,api=debug`.
+pub type LogLevelReload = Arc<dyn Fn(&str) -> Result<(), String> + Send + Sync>;
+
+/// Errors from reloading settings.
+#[derive(Debug, Error)]
+pub enum SettingsError {
+    #[error("no settings file is configured")]
+    NoFile,
+
+    #[error("reading {path}: {source}")]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("line {line}: {reason}")]
+    Invalid { line: usize, reason: String },
+
+    #[error("invalid log level: {0}")]
+    LogLevel(String),
+}
+
+/// The values of the reloadable settings.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Tunables {
+    /// Tracing filter directive (`RUST_LOG`).
+    pub log_level: String,
+    /// Orders each customer may create per minute
+    /// (`DRAFT_ORDERS_PER_MINUTE`); unlimited when unset.
+    pub draft_orders_per_minute: Option<u32>,
+    /// Attempts at a saga step or compensation that fails with a retryable
+    /// error (`SAGA_MAX_STEP_ATTEMPTS`).
+    pub saga_max_step_attempts: u32,
+    /// Longest read models wait between catch-ups (`PROJECTION_POLL_MS`).
+    #[serde(rename = "projection_poll_ms", serialize_with = "as_millis")]
+    pub projection_poll_interval: Duration,
+}
+
+impl Default for Tunables {
+    fn default() -> Self {
+        Self {
+            log_level: "info".to_string(),
+            draft_orders_per_minute: None,
+            saga_max_step_attempts: DEFAULT_MAX_STEP_ATTEMPTS,
+            projection_poll_interval: Duration::from_secs(1),
+        }
+    }
+}
+
+impl Tunables {
+    /// Takes the settings' startup values from `config`.
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            log_level: config.log_level.clone(),
+            draft_orders_per_minute: config.draft_orders_per_minute,
+            saga_max_step_attempts: config.saga_max_step_attempts,
+            projection_poll_interval: config.projection_poll_interval,
+        }
+    }
+
+    /// Returns these settings overridden by the `KEY=value` lines in
+    /// `contents`, and the keys in it that can't be reloaded.
+    pub fn overridden_by(&self, contents: &str) -> Result<(Self, Vec<String>), SettingsError> {
+        let mut next = self.clone();
+        let mut ignored = Vec::new();
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = |reason: String| SettingsError::Invalid {
+                line: i + 1,
+                reason,
+            };
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| invalid("expected KEY=value".to_string()))?;
+            let (key, value) = (key.trim(), value.trim());
+            let number = |min: u64| {
+                value
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|n| *n >= min)
+                    .ok_or_else(|| {
+                        invalid(format!("{key} must be a whole number of at least {min}"))
+                    })
+            };
+            match key {
+                "RUST_LOG" => next.log_level = value.to_string(),
+                "DRAFT_ORDERS_PER_MINUTE" => {
+                    // Empty or zero lifts the limit, as in the environment
+                    next.draft_orders_per_minute = if value.is_empty() {
+                        None
+                    } else {
+                        Some(number(0)? as u32).filter(|limit| *limit > 0)
+                    };
+                }
+                "SAGA_MAX_STEP_ATTEMPTS" => next.saga_max_step_attempts = number(1)? as u32,
+                "PROJECTION_POLL_MS" => {
+                    next.projection_poll_interval = Duration::from_millis(number(1)?)
+                }
+                key => ignored.push(key.to_string()),
+            }
+        }
+        Ok((next, ignored))
+    }
+}
+
+fn as_millis<S: serde::Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(duration.as_millis() as u64)
+}
+
+/// What a reload changed.
+#[derive(Debug, Clone, Serialize)]
+pub struct SettingsReload {
+    /// Names of the settings whose value changed.
+    pub changed: Vec<&'static str>,
+    /// Keys in the file that can't be reloaded, left as they were.
+    pub ignored: Vec<String>,
+    /// The settings now in effect.
+    pub settings: Tunables,
+}
+
+/// Shared handles to the reloadable settings.
+///
+/// Clones share the handles. The subsystems hold the individual
+/// [`Tunable`]s, this holds the other end.
+#[derive(Clone)]
+pub struct LiveSettings {
+    startup: Arc<Tunables>,
+    current: Tunable<Tunables>,
+    file: Option<PathBuf>,
+    log_level: Option<LogLevelReload>,
+    draft_throttle: DraftThrottle,
+    saga_max_step_attempts: Tunable<u32>,
+    projection_poll_interval: Tunable<Duration>,
+}
+
+impl LiveSettings {
+    /// Starts from `startup`, reloading from `file` if set. The log level
+    /// is only reloaded when `log_level` is set.
+    pub fn new(
+        startup: Tunables,
+        file: Option<PathBuf>,
+        log_level: Option<LogLevelReload>,
+    ) -> Self {
+        let draft_throttle = DraftThrottle::unlimited(Duration::from_secs(60));
+        draft_throttle.set_limit(startup.draft_orders_per_minute);
+        Self {
+            current: Tunable::new(startup.clone()),
+            saga_max_step_attempts: Tunable::new(startup.saga_max_step_attempts),
+            projection_poll_interval: Tunable::new(startup.projection_poll_interval),
+            startup: Arc::new(startup),
+            file,
+            log_level,
+            draft_throttle,
+        }
+    }
+
+    /// Returns the settings in effect.
+    pub fn current(&self) -> Arc<Tunables> {
+        self.current.get()
+    }
+
+    /// Returns the settings file, if one is configured.
+    pub fn file(&self) -> Option<&Path> {
+        self.file.as_deref()
+    }
+
+    /// Returns the per-customer order throttle, limited as configured.
+    pub fn draft_throttle(&self) -> &DraftThrottle {
+        &self.draft_throttle
+    }
+
+    /// Returns the handle to the saga step attempt limit.
+    pub fn saga_max_step_attempts(&self) -> Tunable<u32> {
+        self.saga_max_step_attempts.clone()
+    }
+
+    /// Returns the handle to the projection poll interval.
+    pub fn projection_poll_interval(&self) -> Tunable<Duration> {
+        self.projection_poll_interval.clone()
+    }
+
+    /// Re-reads the settings file and applies it over the startup values.
+    pub fn reload(&self) -> Result<SettingsReload, SettingsError> {
+        let result = self.read_file().and_then(|(next, ignored)| {
+            let changed = self.apply(next)?;
+            Ok(SettingsReload {
+                changed,
+                ignored,
+                settings: (*self.current()).clone(),
+            })
+        });
+        match &result {
+            Ok(reload) => {
+                metrics::counter!("settings_reloads", "outcome" => "applied").increment(1);
+                tracing::info!(changed = ?reload.changed, ignored = ?reload.ignored, "settings reloaded");
+                if !reload.ignored.is_empty() {
+                    tracing::warn!(keys = ?reload.ignored, "settings need a restart to change");
+                }
+            }
+            Err(e) => {
+                metrics::counter!("settings_reloads", "outcome" => "rejected").increment(1);
+                tracing::error!(error = %e, "settings reload rejected, keeping current settings");
+            }
+        }
+        result
+    }
+
+    fn read_file(&self) -> Result<(Tunables, Vec<String>), SettingsError> {
+        let path = self.file.as_ref().ok_or(SettingsError::NoFile)?;
+        let contents = std::fs::read_to_string(path).map_err(|source| SettingsError::Read {
+            path: path.clone(),
+            source,
+        })?;
+        self.startup.overridden_by(&contents)
+    }
+
+    /// Applies `next`, returning the names of the settings that changed.
+    ///
+    /// The log level is applied first, so an invalid filter changes
+    /// nothing.
+    pub fn apply(&self, next: Tunables) -> Result<Vec<&'static str>, SettingsError> {
+        let current = self.current();
+        let mut changed = Vec::new();
+        if next.log_level != current.log_level {
+            if let Some(reload) = &self.log_level {
+                reload(&next.log_level).map_err(SettingsError::LogLevel)?;
+            }
+            changed.push("log_level");
+        }
+        if next.draft_orders_per_minute != current.draft_orders_per_minute {
+            self.draft_throttle.set_limit(next.draft_orders_per_minute);
+            changed.push("draft_orders_per_minute");
+        }
+        if next.saga_max_step_attempts != current.saga_max_step_attempts {
+            self.saga_max_step_attempts.set(next.saga_max_step_attempts);
+            changed.push("saga_max_step_attempts");
+        }
+        if next.projection_poll_interval != current.projection_poll_interval {
+            self.projection_poll_interval
+                .set(next.projection_poll_interval);
+            changed.push("projection_poll_interval");
+        }
+        self.current.set(next);
+        Ok(changed)
+    }
+}
+
+impl Default for LiveSettings {
+    fn default() -> Self {
+        Self::new(Tunables::default(), None, None)
+    }
+}
+
+impl std::fmt::Debug for LiveSettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LiveSettings")
+            .field("current", &self.current)
+            .field("file", &self.file)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_overrides_startup_values() {
+        let startup = Tunables {
+            draft_orders_per_minute: Some(5),
+            ..Tunables::default()
+        };
+        let (next, ignored) = startup
+            .overridden_by(
+                "# tuning\nRUST_LOG = debug\nSAGA_MAX_STEP_ATTEMPTS=5\n\nPORT=8080\nDRAFT_ORDERS_PER_MINUTE=0\n",
+            )
+            .unwrap();
+
+        assert_eq!(next.log_level, "debug");
+        assert_eq!(next.saga_max_step_attempts, 5);
+        assert_eq!(next.draft_orders_per_minute, None);
+        assert_eq!(next.projection_poll_interval, Duration::from_secs(1));
+        assert_eq!(ignored, ["PORT"]);
+
+        assert!(matches!(
+            startup.overridden_by("SAGA_MAX_STEP_ATTEMPTS=0"),
+            Err(SettingsError::Invalid { line: 1, .. })
+        ));
+        assert!(matches!(
+            startup.overridden_by("\nPROJECTION_POLL_MS"),
+            Err(SettingsError::Invalid { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_apply_updates_shared_handles() {
+        let settings = LiveSettings::default();
+        let attempts = settings.saga_max_step_attempts();
+        let throttle = settings.draft_throttle().clone();
+
+        let changed = settings
+            .apply(Tunables {
+                draft_orders_per_minute: Some(3),
+                saga_max_step_attempts: 1,
+                ..Tunables::default()
+            })
+            .unwrap();
+
+        assert_eq!(
+            changed,
+            ["draft_orders_per_minute", "saga_max_step_attempts"]
+        );
+        assert_eq!(attempts.value(), 1);
+        assert_eq!(throttle.limit(), Some(3));
+        assert_eq!(settings.current().saga_max_step_attempts, 1);
+    }
+
+    #[test]
+    fn test_invalid_log_level_changes_nothing() {
+        let reload: LogLevelReload = Arc::new(|_| Err("bad directive".to_string()));
+        let settings = LiveSettings::new(Tunables::default(), None, Some(reload));
+
+        let result = settings.apply(Tunables {
+            log_level: "???".to_string(),
+            saga_max_step_attempts: 9,
+            ..Tunables::default()
+        });
+
+        assert!(matches!(result, Err(SettingsError::LogLevel(_))));
+        assert_eq!(
+            settings.saga_max_step_attempts().value(),
+            DEFAULT_MAX_STEP_ATTEMPTS
+        );
+        assert_eq!(*settings.current(), Tunables::default());
+    }
+}
@@ -0,0 +1,157 @@
+//! Chunked JSON response bodies for large arrays.
+//!
+//! A [`StreamedJson`] response writes its small fields up front and then
+//! serializes each array a chunk at a time, as the body is sent. An order
+//! with thousands of items no longer has to be rendered into one buffer
+//! before the first byte goes out.
+
+use axum::body::{Body, Bytes};
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// Array elements serialized per body chunk.
+pub const ITEMS_PER_CHUNK: usize = 256;
+
+type Chunks = Box<dyn Iterator<Item = Result<Vec<u8>, serde_json::Error>> + Send>;
+
+/// A JSON object response whose array fields are streamed.
+pub struct StreamedJson {
+    fields: Map<String, Value>,
+    arrays: Vec<(String, Chunks)>,
+}
+
+impl StreamedJson {
+    /// Starts a response from `fields`, which must serialize to an object.
+    pub fn new(fields: &impl Serialize) -> Result<Self, serde_json::Error> {
+        let fields = match serde_json::to_value(fields)? {
+            Value::Object(fields) => fields,
+            other => {
+                return Err(serde::ser::Error::custom(format!(
+                    "streamed response fields must be an object, got {other}"
+                )));
+            }
+        };
+        Ok(Self {
+            fields,
+            arrays: Vec::new(),
+        })
+    }
+
+    /// Adds a plain field, replacing any field of the same name.
+    pub fn field(mut self, name: &str, value: impl Into<Value>) -> Self {
+        self.fields.insert(name.to_string(), value.into());
+        self
+    }
+
+    /// Streams `items` as the array field `name`, replacing any plain field
+    /// of the same name.
+    pub fn array<T>(mut self, name: &str, items: Vec<T>) -> Self
+    where
+        T: Serialize + Send + 'static,
+    {
+        self.fields.remove(name);
+        self.arrays.push((name.to_string(), array_chunks(items)));
+        self
+    }
+}
+
+impl IntoResponse for StreamedJson {
+    fn into_response(self) -> Response {
+        let mut head = Vec::from(&b"{"[..]);
+        for (i, (name, value)) in self.fields.iter().enumerate() {
+            if i > 0 {
+                head.push(b',');
+            }
+            // Serializing a string and an already-built value can't fail
+            serde_json::to_writer(&mut head, name).expect("key serializes");
+            head.push(b':');
+            serde_json::to_writer(&mut head, value).expect("value serializes");
+        }
+
+        let mut separate = !self.fields.is_empty();
+        let arrays = self.arrays.into_iter().flat_map(move |(name, chunks)| {
+            let mut open = Vec::new();
+            if std::mem::replace(&mut separate, true) {
+                open.push(b',');
+            }
+            serde_json::to_writer(&mut open, &name).expect("key serializes");
+            open.extend_from_slice(b":[");
+            std::iter::once(Ok(open))
+                .chain(chunks)
+                .chain(std::iter::once(Ok(b"]".to_vec())))
+        });
+        let body = std::iter::once(Ok(head))
+            .chain(arrays)
+            .chain(std::iter::once(Ok(b"}".to_vec())))
+            .map(|chunk| chunk.map(Bytes::from));
+
+        (
+            [(header::CONTENT_TYPE, "application/json")],
+            Body::from_stream(futures_util::stream::iter(body)),
+        )
+            .into_response()
+    }
+}
+
+/// Serializes `items` lazily, [`ITEMS_PER_CHUNK`] elements per chunk, with
+/// the commas between them.
+fn array_chunks<T>(items: Vec<T>) -> Chunks
+where
+    T: Serialize + Send + 'static,
+{
+    let mut items = items.into_iter().peekable();
+    let mut first = true;
+    Box::new(std::iter::from_fn(move || {
+        items.peek()?;
+        let mut chunk = Vec::new();
+        for item in items.by_ref().take(ITEMS_PER_CHUNK) {
+            if !std::mem::take(&mut first) {
+                chunk.push(b',');
+            }
+            if let Err(e) = serde_json::to_writer(&mut chunk, &item) {
+                return Some(Err(e));
+            }
+        }
+        Some(Ok(chunk))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn render(response: StreamedJson) -> Value {
+        let body = axum::body::to_bytes(response.into_response().into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_streams_arrays_across_chunks() {
+        let items: Vec<u32> = (0..(ITEMS_PER_CHUNK as u32 * 2 + 3)).collect();
+        let response = StreamedJson::new(&serde_json::json!({ "id": "o-1", "items": [] }))
+            .unwrap()
+            .field("items_total", items.len())
+            .array("items", items.clone())
+            .array("tags", vec!["a", "b"]);
+
+        let json = render(response).await;
+        assert_eq!(json["id"], "o-1");
+        assert_eq!(json["items_total"], items.len());
+        assert_eq!(json["items"], serde_json::json!(items));
+        assert_eq!(json["tags"], serde_json::json!(["a", "b"]));
+    }
+
+    #[tokio::test]
+    async fn test_empty_fields_and_arrays() {
+        let response = StreamedJson::new(&serde_json::json!({}))
+            .unwrap()
+            .array("items", Vec::<u32>::new());
+
+        assert_eq!(render(response).await, serde_json::json!({ "items": [] }));
+        assert!(StreamedJson::new(&[1, 2]).is_err());
+    }
+}
@@ -0,0 +1,209 @@
+//! Signature verification and replay protection for inbound webhooks.
+//!
+//! Senders sign `"{timestamp}.{body}"` with HMAC-SHA256 using a shared secret
+//! and send the result as `sha256=<hex>`. Requests are rejected when the
+//! signature doesn't match or the timestamp is outside the tolerance window.
+//! Delivery IDs are remembered for the same window so a captured request
+//! can't be replayed while its timestamp is still accepted.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use thiserror::Error;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Prefix of the signature header value.
+const SIGNATURE_PREFIX: &str = "sha256=";
+
+/// Reasons a webhook request is rejected.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum WebhookError {
+    /// Signature header is missing or not in `sha256=<hex>` form.
+    #[error("Malformed webhook signature")]
+    MalformedSignature,
+
+    /// Signature does not match the request body.
+    #[error("Invalid webhook signature")]
+    InvalidSignature,
+
+    /// Timestamp is too far from the current time.
+    #[error("Webhook timestamp {timestamp} is outside the allowed window")]
+    StaleTimestamp { timestamp: i64 },
+}
+
+/// Verifies signed webhook requests from a single sender.
+pub struct WebhookVerifier {
+    secret: Vec<u8>,
+    tolerance: Duration,
+    /// Delivery ID → timestamp of deliveries seen within the tolerance window.
+    seen: Mutex<HashMap<String, i64>>,
+}
+
+impl WebhookVerifier {
+    /// Default allowed clock skew between sender and receiver.
+    pub const DEFAULT_TOLERANCE: Duration = Duration::from_secs(300);
+
+    /// Creates a verifier for the given shared secret.
+    pub fn new(secret: impl Into<Vec<u8>>) -> Self {
+        Self {
+            secret: secret.into(),
+            tolerance: Self::DEFAULT_TOLERANCE,
+            seen: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Sets the allowed clock skew.
+    pub fn with_tolerance(mut self, tolerance: Duration) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Creates a verifier from `SHIPPING_WEBHOOK_SECRET`, if set.
+    pub fn shipping_from_env() -> Option<Self> {
+        std::env::var("SHIPPING_WEBHOOK_SECRET")
+            .ok()
+            .filter(|s| !s.is_empty())
+            .map(Self::new)
+    }
+
+    /// Computes the signature header value for a request.
+    pub fn sign(&self, timestamp: i64, body: &[u8]) -> String {
+        let mac = self.mac(timestamp, body);
+        format!(
+            "{SIGNATURE_PREFIX}{}",
+            hex::encode(mac.finalize().into_bytes())
+        )
+    }
+
+    /// Verifies a request's signature and timestamp against `now`.
+    pub fn verify(
+        &self,
+        signature: &str,
+        timestamp: i64,
+        body: &[u8],
+        now: i64,
+    ) -> Result<(), WebhookError> {
+        let expected = signature
+            .strip_prefix(SIGNATURE_PREFIX)
+            .and_then(|h| hex::decode(h).ok())
+            .ok_or(WebhookError::MalformedSignature)?;
+
+        // Constant-time comparison
+        self.mac(timestamp, body)
+            .verify_slice(&expected)
+            .map_err(|_| WebhookError::InvalidSignature)?;
+
+        if timestamp.abs_diff(now) > self.tolerance.as_secs() {
+            return Err(WebhookError::StaleTimestamp { timestamp });
+        }
+        Ok(())
+    }
+
+    /// Claims a delivery ID, returning false if it was already seen.
+    ///
+    /// Call [`release`](Self::release) if processing fails so the sender's
+    /// retry is not treated as a replay.
+    pub fn claim(&self, delivery_id: &str, timestamp: i64, now: i64) -> bool {
+        let window = self.tolerance.as_secs() as i64;
+        let mut seen = self.seen.lock().expect("replay cache poisoned");
+        // Anything older than the window is rejected by timestamp anyway
+        seen.retain(|_, ts| now - *ts <= window);
+        if seen.contains_key(delivery_id) {
+            return false;
+        }
+        seen.insert(delivery_id.to_string(), timestamp);
+        true
+    }
+
+    /// Forgets a claimed delivery ID.
+    pub fn release(&self, delivery_id: &str) {
+        self.seen
+            .lock()
+            .expect("replay cache poisoned")
+            .remove(delivery_id);
+    }
+
+    fn mac(&self, timestamp: i64, body: &[u8]) -> HmacSha256 {
+        let mut mac =
+            HmacSha256::new_from_slice(&self.secret).expect("HMAC accepts keys of any length");
+        mac.update(timestamp.to_string().as_bytes());
+        mac.update(b".");
+        mac.update(body);
+        mac
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_700_000_000;
+
+    #[test]
+    fn test_valid_signature_accepted() {
+        let verifier = WebhookVerifier::new("secret");
+        let signature = verifier.sign(NOW, b"{}");
+        assert_eq!(verifier.verify(&signature, NOW, b"{}", NOW + 10), Ok(()));
+    }
+
+    #[test]
+    fn test_tampered_body_rejected() {
+        let verifier = WebhookVerifier::new("secret");
+        let signature = verifier.sign(NOW, b"{}");
+        assert_eq!(
+            verifier.verify(&signature, NOW, b"{\"x\":1}", NOW),
+            Err(WebhookError::InvalidSignature)
+        );
+
+        let other = WebhookVerifier::new("other").sign(NOW, b"{}");
+        assert_eq!(
+            verifier.verify(&other, NOW, b"{}", NOW),
+            Err(WebhookError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn test_malformed_signature_rejected() {
+        let verifier = WebhookVerifier::new("secret");
+        assert_eq!(
+            verifier.verify("md5=abc", NOW, b"{}", NOW),
+            Err(WebhookError::MalformedSignature)
+        );
+        assert_eq!(
+            verifier.verify("sha256=zz", NOW, b"{}", NOW),
+            Err(WebhookError::MalformedSignature)
+        );
+    }
+
+    #[test]
+    fn test_stale_timestamp_rejected() {
+        let verifier = WebhookVerifier::new("secret").with_tolerance(Duration::from_secs(60));
+        let signature = verifier.sign(NOW, b"{}");
+        assert_eq!(
+            verifier.verify(&signature, NOW, b"{}", NOW + 61),
+            Err(WebhookError::StaleTimestamp { timestamp: NOW })
+        );
+    }
+
+    #[test]
+    fn test_claim_rejects_replays_until_released() {
+        let verifier = WebhookVerifier::new("secret");
+        assert!(verifier.claim("dlv-1", NOW, NOW));
+        assert!(!verifier.claim("dlv-1", NOW, NOW + 1));
+
+        verifier.release("dlv-1");
+        assert!(verifier.claim("dlv-1", NOW, NOW + 2));
+    }
+
+    #[test]
+    fn test_claims_expire_with_the_window() {
+        let verifier = WebhookVerifier::new("secret").with_tolerance(Duration::from_secs(60));
+        assert!(verifier.claim("dlv-1", NOW, NOW));
+        assert!(verifier.claim("dlv-2", NOW + 120, NOW + 120));
+        assert_eq!(verifier.seen.lock().unwrap().len(), 1);
+    }
+}
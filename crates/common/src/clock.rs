@@ -0,0 +1,182 @@
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use tokio::sync::oneshot;
+
+/// A future returned by [`TimerDriver::sleep`].
+pub type Sleep = Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
+
+/// Source of the current time.
+///
+/// Background workers read the time through a clock instead of calling
+/// `Utc::now()`, so tests can substitute a [`VirtualClock`].
+pub trait Clock: Send + Sync {
+    /// Returns the current time.
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// Waits for time to pass.
+///
+/// Paired with a [`Clock`] so a worker that sleeps between passes wakes when
+/// that clock says the time is up.
+pub trait TimerDriver: Send + Sync {
+    /// Returns a future that completes once `duration` has elapsed.
+    fn sleep(&self, duration: Duration) -> Sleep;
+}
+
+/// The system wall clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// Sleeps on the Tokio timer.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokioTimer;
+
+impl TimerDriver for TokioTimer {
+    fn sleep(&self, duration: Duration) -> Sleep {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+/// A clock and timer that only move when told to, for tests.
+///
+/// Sleeps resolve when [`advance`](Self::advance) moves the time past their
+/// deadline, never on their own. A worker looping on a sleep wakes once per
+/// advance however far it goes, like a process that was suspended, and sleeps
+/// again from the new time. Clones share the time.
+#[derive(Clone)]
+pub struct VirtualClock {
+    state: Arc<Mutex<VirtualState>>,
+}
+
+struct VirtualState {
+    now: DateTime<Utc>,
+    sleepers: Vec<(DateTime<Utc>, oneshot::Sender<()>)>,
+}
+
+impl VirtualClock {
+    /// Creates a clock stopped at `start`.
+    pub fn new(start: DateTime<Utc>) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(VirtualState {
+                now: start,
+                sleepers: Vec::new(),
+            })),
+        }
+    }
+
+    /// Moves the time forward and wakes every sleep that is now due.
+    pub fn advance(&self, by: Duration) {
+        let mut state = self.state.lock().expect("virtual clock poisoned");
+        state.now += TimeDelta::from_std(by).expect("advance out of range");
+        let now = state.now;
+        let (due, waiting) = std::mem::take(&mut state.sleepers)
+            .into_iter()
+            .partition(|(deadline, _)| *deadline <= now);
+        state.sleepers = waiting;
+        drop(state);
+        for (_, wake) in due {
+            let _ = wake.send(());
+        }
+    }
+
+    /// Returns how many sleeps are waiting on this clock.
+    pub fn sleepers(&self) -> usize {
+        let mut state = self.state.lock().expect("virtual clock poisoned");
+        state.sleepers.retain(|(_, wake)| !wake.is_closed());
+        state.sleepers.len()
+    }
+
+    /// Yields until at least `count` sleeps are waiting, i.e. until the
+    /// workers under test have finished their pass and gone back to sleep.
+    pub async fn wait_for_sleepers(&self, count: usize) {
+        while self.sleepers() < count {
+            tokio::task::yield_now().await;
+        }
+    }
+}
+
+impl fmt::Debug for VirtualClock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VirtualClock")
+            .field("now", &self.now())
+            .field("sleepers", &self.sleepers())
+            .finish()
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.state.lock().expect("virtual clock poisoned").now
+    }
+}
+
+impl TimerDriver for VirtualClock {
+    fn sleep(&self, duration: Duration) -> Sleep {
+        let (wake, woken) = oneshot::channel();
+        {
+            let mut state = self.state.lock().expect("virtual clock poisoned");
+            let deadline = state.now + TimeDelta::from_std(duration).expect("sleep out of range");
+            if deadline <= state.now {
+                return Box::pin(std::future::ready(()));
+            }
+            state.sleepers.push((deadline, wake));
+        }
+        Box::pin(async move {
+            // A dropped clock can never wake the sleep, so let it go
+            let _ = woken.await;
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn start() -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000, 0).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_virtual_sleep_waits_for_advance() {
+        let clock = VirtualClock::new(start());
+        let sleeper = tokio::spawn({
+            let clock = clock.clone();
+            async move {
+                clock.sleep(Duration::from_secs(60)).await;
+                clock.now()
+            }
+        });
+        clock.wait_for_sleepers(1).await;
+
+        clock.advance(Duration::from_secs(59));
+        assert_eq!(clock.sleepers(), 1);
+        assert!(!sleeper.is_finished());
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(clock.sleepers(), 0);
+        assert_eq!(sleeper.await.unwrap(), start() + TimeDelta::seconds(60));
+    }
+
+    #[tokio::test]
+    async fn test_zero_sleep_and_dropped_sleeps() {
+        let clock = VirtualClock::new(start());
+        clock.sleep(Duration::ZERO).await;
+        assert_eq!(clock.now(), start());
+
+        let sleep = clock.sleep(Duration::from_secs(1));
+        assert_eq!(clock.sleepers(), 1);
+        drop(sleep);
+        assert_eq!(clock.sleepers(), 0);
+    }
+}
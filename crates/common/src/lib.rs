@@ -1,3 +1,7 @@
+mod clock;
+mod tunable;
 mod types;
 
+pub use clock::{Clock, Sleep, SystemClock, TimerDriver, TokioTimer, VirtualClock};
+pub use tunable::Tunable;
 pub use types::AggregateId;
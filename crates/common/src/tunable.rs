@@ -0,0 +1,74 @@
+use std::fmt;
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+
+/// A setting that can be changed while the code reading it keeps running.
+///
+/// Clones share the value, so a subsystem holds one clone and reads it on
+/// each use while configuration reload holds another and sets it. Reads
+/// never block.
+pub struct Tunable<T>(Arc<ArcSwap<T>>);
+
+impl<T> Tunable<T> {
+    /// Creates a handle holding `value`.
+    pub fn new(value: T) -> Self {
+        Self(Arc::new(ArcSwap::from_pointee(value)))
+    }
+
+    /// Returns the current value.
+    pub fn get(&self) -> Arc<T> {
+        self.0.load_full()
+    }
+
+    /// Replaces the value for every clone of this handle.
+    pub fn set(&self, value: T) {
+        self.0.store(Arc::new(value));
+    }
+}
+
+impl<T: Copy> Tunable<T> {
+    /// Returns a copy of the current value.
+    pub fn value(&self) -> T {
+        **self.0.load()
+    }
+}
+
+impl<T> Clone for Tunable<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> From<T> for Tunable<T> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T: Default> Default for Tunable<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Tunable<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Tunable").field(&**self.0.load()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clones_share_the_value() {
+        let limit = Tunable::new(5u32);
+        let reader = limit.clone();
+
+        limit.set(10);
+        assert_eq!(reader.value(), 10);
+        assert_eq!(*reader.get(), 10);
+    }
+}
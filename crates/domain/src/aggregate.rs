@@ -59,6 +59,17 @@ pub trait Aggregate: Default + Send + Sync + Sized {
     /// - It must not fail (events represent facts that have happened)
     fn apply(&mut self, event: Self::Event);
 
+    /// Checks the events a single command emitted before they are persisted.
+    ///
+    /// Override to enforce invariants that span several events, which
+    /// per-command checks can't see. Called by
+    /// [`CommandHandler`](crate::CommandHandler) on the state the command
+    /// was decided against; the default accepts everything.
+    fn validate_emitted(&self, events: &[Self::Event]) -> Result<(), Self::Error> {
+        let _ = events;
+        Ok(())
+    }
+
     /// Applies multiple events in sequence.
     fn apply_events(&mut self, events: impl IntoIterator<Item = Self::Event>) {
         for event in events {
@@ -0,0 +1,45 @@
+//! Cancellation of commands by the request that issued them.
+//!
+//! Commands run through a [`CommandHandler`](crate::CommandHandler) pick up
+//! the token of the scope they run in, so a caller several layers up, such
+//! as an HTTP route whose deadline passed, can cancel them without every
+//! service method taking a token. The handler checks the token after loading
+//! and again just before appending; once events are appended the command
+//! completes regardless. Commands outside any scope are never cancelled.
+
+use std::future::Future;
+
+use tokio_util::sync::CancellationToken;
+
+tokio::task_local! {
+    static CANCEL: CancellationToken;
+}
+
+/// Runs `future` with `cancel` as the token of the commands it executes.
+pub async fn with_cancellation<F: Future>(cancel: CancellationToken, future: F) -> F::Output {
+    CANCEL.scope(cancel, future).await
+}
+
+/// Returns the token of the enclosing scope, or a token that is never
+/// cancelled outside one.
+pub fn current() -> CancellationToken {
+    CANCEL
+        .try_with(CancellationToken::clone)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_scope_sets_current_token() {
+        assert!(!current().is_cancelled());
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let inside = with_cancellation(cancel, async { current().is_cancelled() }).await;
+        assert!(inside);
+        assert!(!current().is_cancelled());
+    }
+}
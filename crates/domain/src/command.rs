@@ -3,11 +3,20 @@
 use std::marker::PhantomData;
 
 use common::AggregateId;
-use event_store::{AppendOptions, EventEnvelope, EventStore, EventStoreExt, Snapshot, Version};
+use event_store::{
+    AppendOptions, EnvelopeFactory, EventEnvelope, EventStore, EventStoreError, EventStoreExt,
+    ReadSource, SchemaDeprecations, Snapshot, Version,
+};
 use serde::Serialize;
+use tokio_util::sync::CancellationToken;
 
 use crate::aggregate::{Aggregate, DomainEvent, SnapshotCapable};
+use crate::cancellation;
+use crate::contention::ContentionTracker;
+use crate::decision::{Decision, DecisionLog, DecisionOutcome};
 use crate::error::DomainError;
+use crate::metadata::{CommandMetadata, MetadataPolicy};
+use crate::processed::{self, ProcessedCommand, ProcessedCommands};
 
 /// Result of command execution.
 #[derive(Debug)]
@@ -20,8 +29,18 @@ pub struct CommandResult<A: Aggregate> {
 
     /// The new version of the aggregate after the command.
     pub new_version: Version,
+
+    /// Whether the command had already been processed under its command ID,
+    /// so this is the original result rather than a new decision.
+    pub replayed: bool,
 }
 
+/// A command function boxed so that different commands can be batched; see
+/// [`CommandHandler::execute_many`].
+pub type BatchedCommand<'a, A> = Box<
+    dyn FnOnce(&A) -> Result<Vec<<A as Aggregate>::Event>, <A as Aggregate>::Error> + Send + 'a,
+>;
+
 /// Trait for commands that can be executed against an aggregate.
 ///
 /// Commands represent an intention to perform an action. They may be rejected
@@ -32,6 +51,14 @@ pub trait Command: Send + Sync {
 
     /// Returns the ID of the aggregate this command targets.
     fn aggregate_id(&self) -> AggregateId;
+
+    /// Returns the command's name, used in decision records.
+    ///
+    /// Defaults to the type's name without its module path.
+    fn name(&self) -> &'static str {
+        let path = std::any::type_name::<Self>();
+        path.rsplit("::").next().unwrap_or(path)
+    }
 }
 
 /// Handler for executing commands against aggregates.
@@ -41,12 +68,27 @@ pub trait Command: Send + Sync {
 /// 2. Executing the command to produce events
 /// 3. Persisting the events to the event store
 /// 4. Optionally saving a snapshot
+///
+/// A [`MetadataPolicy`] can be configured to reject appends that lack
+/// required audit metadata, and a [`ContentionTracker`] attached to record
+/// which aggregates hit concurrency conflicts. Every execution is recorded
+/// in a [`DecisionLog`], and with [`SchemaDeprecations`] attached, loads
+/// count the events they read at deprecated schema versions.
+///
+/// Commands whose metadata carries a command ID, or that run in a
+/// command-ID scope, are processed once; see [`processed`](crate::processed).
 pub struct CommandHandler<S, A>
 where
     S: EventStore,
     A: Aggregate,
 {
     store: S,
+    metadata_policy: MetadataPolicy,
+    contention: Option<ContentionTracker>,
+    decisions: DecisionLog,
+    schema_deprecations: Option<SchemaDeprecations>,
+    envelopes: EnvelopeFactory,
+    processed: ProcessedCommands,
     _phantom: PhantomData<A>,
 }
 
@@ -59,10 +101,78 @@ where
     pub fn new(store: S) -> Self {
         Self {
             store,
+            metadata_policy: MetadataPolicy::none(),
+            contention: None,
+            decisions: DecisionLog::new(),
+            schema_deprecations: None,
+            envelopes: EnvelopeFactory::new().for_aggregate(A::aggregate_type()),
+            processed: ProcessedCommands::new(),
             _phantom: PhantomData,
         }
     }
 
+    /// Requires the policy's metadata keys on every append.
+    pub fn with_metadata_policy(mut self, policy: MetadataPolicy) -> Self {
+        self.metadata_policy = policy;
+        self
+    }
+
+    /// Returns the metadata policy enforced on appends.
+    pub fn metadata_policy(&self) -> &MetadataPolicy {
+        &self.metadata_policy
+    }
+
+    /// Records concurrency conflicts per aggregate in `tracker`.
+    pub fn with_contention_tracker(mut self, tracker: ContentionTracker) -> Self {
+        self.contention = Some(tracker);
+        self
+    }
+
+    /// Returns the attached contention tracker, if any.
+    pub fn contention_tracker(&self) -> Option<&ContentionTracker> {
+        self.contention.as_ref()
+    }
+
+    /// Records decisions in `log` instead of a tracing-only log.
+    pub fn with_decision_log(mut self, log: DecisionLog) -> Self {
+        self.decisions = log;
+        self
+    }
+
+    /// Returns the log decisions are recorded in.
+    pub fn decision_log(&self) -> &DecisionLog {
+        &self.decisions
+    }
+
+    /// Counts reads of deprecated event schema versions in `deprecations`.
+    pub fn with_schema_deprecations(mut self, deprecations: SchemaDeprecations) -> Self {
+        self.schema_deprecations = Some(deprecations);
+        self
+    }
+
+    /// Builds envelopes with `factory`, under this handler's aggregate type.
+    pub fn with_envelope_factory(mut self, factory: EnvelopeFactory) -> Self {
+        self.envelopes = factory.for_aggregate(A::aggregate_type());
+        self
+    }
+
+    /// Returns the factory envelopes are built with.
+    pub fn envelope_factory(&self) -> &EnvelopeFactory {
+        &self.envelopes
+    }
+
+    /// Indexes processed command IDs in `index`, e.g. to share it between
+    /// handlers of the same aggregate type.
+    pub fn with_processed_commands(mut self, index: ProcessedCommands) -> Self {
+        self.processed = index;
+        self
+    }
+
+    /// Returns the index of processed command IDs.
+    pub fn processed_commands(&self) -> &ProcessedCommands {
+        &self.processed
+    }
+
     /// Returns a reference to the underlying event store.
     pub fn store(&self) -> &S {
         &self.store
@@ -86,6 +196,7 @@ where
 
         // Apply events after snapshot
         for envelope in events {
+            self.record_read(&envelope);
             let event: A::Event = serde_json::from_value(envelope.payload)?;
             aggregate.apply(event);
             aggregate.set_version(envelope.version);
@@ -94,6 +205,66 @@ where
         Ok(aggregate)
     }
 
+    /// Replays an aggregate's events up to and including `version`.
+    ///
+    /// Snapshots are ignored so any historical version can be rebuilt. The
+    /// returned aggregate's version is the last one applied, which is lower
+    /// than `version` when the aggregate has fewer events.
+    pub async fn load_at_version(
+        &self,
+        aggregate_id: AggregateId,
+        version: Version,
+    ) -> Result<A, DomainError>
+    where
+        A: for<'de> serde::Deserialize<'de>,
+        A::Event: for<'de> serde::Deserialize<'de>,
+    {
+        let events = self.store.get_events_for_aggregate(aggregate_id).await?;
+
+        let mut aggregate = A::default();
+        for envelope in events.into_iter().take_while(|e| e.version <= version) {
+            self.record_read(&envelope);
+            let event: A::Event = serde_json::from_value(envelope.payload)?;
+            aggregate.apply(event);
+            aggregate.set_version(envelope.version);
+        }
+
+        Ok(aggregate)
+    }
+
+    /// Replays an aggregate and returns its state after each event whose
+    /// version falls in `from..=to`.
+    ///
+    /// Like [`load_at_version`](Self::load_at_version), snapshots are
+    /// ignored. Events before `from` are applied but not returned, and the
+    /// result is empty when the aggregate has no events in the range.
+    pub async fn replay_range(
+        &self,
+        aggregate_id: AggregateId,
+        from: Version,
+        to: Version,
+    ) -> Result<Vec<(Version, A)>, DomainError>
+    where
+        A: Clone + for<'de> serde::Deserialize<'de>,
+        A::Event: for<'de> serde::Deserialize<'de>,
+    {
+        let events = self.store.get_events_for_aggregate(aggregate_id).await?;
+
+        let mut aggregate = A::default();
+        let mut states = Vec::new();
+        for envelope in events.into_iter().take_while(|e| e.version <= to) {
+            self.record_read(&envelope);
+            let event: A::Event = serde_json::from_value(envelope.payload)?;
+            aggregate.apply(event);
+            aggregate.set_version(envelope.version);
+            if envelope.version >= from {
+                states.push((envelope.version, aggregate.clone()));
+            }
+        }
+
+        Ok(states)
+    }
+
     /// Loads an aggregate, returning None if it doesn't exist.
     pub async fn load_existing(&self, aggregate_id: AggregateId) -> Result<Option<A>, DomainError>
     where
@@ -111,8 +282,9 @@ where
     /// Executes a command and persists the resulting events.
     ///
     /// The command function receives the current aggregate state and returns
-    /// either a list of events to apply, or an error.
-    #[tracing::instrument(skip(self, command_fn), fields(aggregate_type = A::aggregate_type()))]
+    /// either a list of events to apply, or an error. Like every method
+    /// without a token of its own, it is cancelled by the enclosing
+    /// [`cancellation`] scope, if any.
     pub async fn execute<F>(
         &self,
         aggregate_id: AggregateId,
@@ -124,12 +296,355 @@ where
         F: FnOnce(&A) -> Result<Vec<A::Event>, A::Error>,
         DomainError: From<A::Error>,
     {
+        self.execute_cancellable(aggregate_id, &cancellation::current(), command_fn)
+            .await
+    }
+
+    /// Executes a command unless the caller cancels it first.
+    ///
+    /// The token is checked after loading and again just before appending;
+    /// once events are appended the command is no longer cancellable.
+    pub async fn execute_cancellable<F>(
+        &self,
+        aggregate_id: AggregateId,
+        cancel: &CancellationToken,
+        command_fn: F,
+    ) -> Result<CommandResult<A>, DomainError>
+    where
+        A: for<'de> serde::Deserialize<'de>,
+        A::Event: for<'de> serde::Deserialize<'de> + Serialize,
+        F: FnOnce(&A) -> Result<Vec<A::Event>, A::Error>,
+        DomainError: From<A::Error>,
+    {
+        self.run(
+            None,
+            aggregate_id,
+            cancel,
+            &CommandMetadata::new(),
+            command_fn,
+        )
+        .await
+    }
+
+    /// Executes a command, naming it in the decision record.
+    pub async fn execute_named<F>(
+        &self,
+        command: &'static str,
+        aggregate_id: AggregateId,
+        command_fn: F,
+    ) -> Result<CommandResult<A>, DomainError>
+    where
+        A: for<'de> serde::Deserialize<'de>,
+        A::Event: for<'de> serde::Deserialize<'de> + Serialize,
+        F: FnOnce(&A) -> Result<Vec<A::Event>, A::Error>,
+        DomainError: From<A::Error>,
+    {
+        self.run(
+            Some(command),
+            aggregate_id,
+            &cancellation::current(),
+            &CommandMetadata::new(),
+            command_fn,
+        )
+        .await
+    }
+
+    /// Executes a command, attaching `metadata` to every appended event.
+    pub async fn execute_with_metadata<F>(
+        &self,
+        aggregate_id: AggregateId,
+        metadata: CommandMetadata,
+        command_fn: F,
+    ) -> Result<CommandResult<A>, DomainError>
+    where
+        A: for<'de> serde::Deserialize<'de>,
+        A::Event: for<'de> serde::Deserialize<'de> + Serialize,
+        F: FnOnce(&A) -> Result<Vec<A::Event>, A::Error>,
+        DomainError: From<A::Error>,
+    {
+        self.run(
+            None,
+            aggregate_id,
+            &cancellation::current(),
+            &metadata,
+            command_fn,
+        )
+        .await
+    }
+
+    /// Executes a named command, attaching `metadata` to every appended
+    /// event.
+    pub async fn execute_named_with_metadata<F>(
+        &self,
+        command: &'static str,
+        aggregate_id: AggregateId,
+        metadata: CommandMetadata,
+        command_fn: F,
+    ) -> Result<CommandResult<A>, DomainError>
+    where
+        A: for<'de> serde::Deserialize<'de>,
+        A::Event: for<'de> serde::Deserialize<'de> + Serialize,
+        F: FnOnce(&A) -> Result<Vec<A::Event>, A::Error>,
+        DomainError: From<A::Error>,
+    {
+        self.run(
+            Some(command),
+            aggregate_id,
+            &cancellation::current(),
+            &metadata,
+            command_fn,
+        )
+        .await
+    }
+
+    /// Executes several commands against one aggregate and persists all
+    /// their events in a single append.
+    ///
+    /// Each command sees the aggregate with the events of the commands
+    /// before it applied, and its events are validated on their own. If any
+    /// command fails nothing is appended, and the append checks the version
+    /// the aggregate was loaded at once for the whole batch. Commands of
+    /// different types can be passed as [`BatchedCommand`]s.
+    pub async fn execute_many<F>(
+        &self,
+        aggregate_id: AggregateId,
+        commands: impl IntoIterator<Item = F>,
+    ) -> Result<CommandResult<A>, DomainError>
+    where
+        A: Clone + for<'de> serde::Deserialize<'de>,
+        A::Event: for<'de> serde::Deserialize<'de> + Serialize,
+        F: FnOnce(&A) -> Result<Vec<A::Event>, A::Error>,
+        DomainError: From<A::Error>,
+    {
+        let commands = commands.into_iter().map(|f| (CommandMetadata::new(), f));
+        self.run_many(None, aggregate_id, commands).await
+    }
+
+    /// Executes several commands in a single append, naming the batch in
+    /// the decision record.
+    pub async fn execute_many_named<F>(
+        &self,
+        command: &'static str,
+        aggregate_id: AggregateId,
+        commands: impl IntoIterator<Item = F>,
+    ) -> Result<CommandResult<A>, DomainError>
+    where
+        A: Clone + for<'de> serde::Deserialize<'de>,
+        A::Event: for<'de> serde::Deserialize<'de> + Serialize,
+        F: FnOnce(&A) -> Result<Vec<A::Event>, A::Error>,
+        DomainError: From<A::Error>,
+    {
+        let commands = commands.into_iter().map(|f| (CommandMetadata::new(), f));
+        self.run_many(Some(command), aggregate_id, commands).await
+    }
+
+    /// Executes several commands in a single append, attaching each
+    /// command's `metadata` to the events it emits.
+    pub async fn execute_many_named_with_metadata<F>(
+        &self,
+        command: &'static str,
+        aggregate_id: AggregateId,
+        commands: impl IntoIterator<Item = (CommandMetadata, F)>,
+    ) -> Result<CommandResult<A>, DomainError>
+    where
+        A: Clone + for<'de> serde::Deserialize<'de>,
+        A::Event: for<'de> serde::Deserialize<'de> + Serialize,
+        F: FnOnce(&A) -> Result<Vec<A::Event>, A::Error>,
+        DomainError: From<A::Error>,
+    {
+        self.run_many(Some(command), aggregate_id, commands).await
+    }
+
+    #[tracing::instrument(skip(self, commands), fields(aggregate_type = A::aggregate_type()))]
+    async fn run_many<F>(
+        &self,
+        command: Option<&'static str>,
+        aggregate_id: AggregateId,
+        commands: impl IntoIterator<Item = (CommandMetadata, F)>,
+    ) -> Result<CommandResult<A>, DomainError>
+    where
+        A: Clone + for<'de> serde::Deserialize<'de>,
+        A::Event: for<'de> serde::Deserialize<'de> + Serialize,
+        F: FnOnce(&A) -> Result<Vec<A::Event>, A::Error>,
+        DomainError: From<A::Error>,
+    {
+        let batch = |aggregate: &A| {
+            let mut state = aggregate.clone();
+            let mut events = Vec::new();
+            let mut event_metadata = Vec::new();
+            for (metadata, command_fn) in commands {
+                let emitted = command_fn(&state)?;
+                state.validate_emitted(&emitted)?;
+                state.apply_events(emitted.iter().cloned());
+                event_metadata.extend(std::iter::repeat_n(metadata, emitted.len()));
+                events.extend(emitted);
+            }
+            Ok((events, event_metadata))
+        };
+        let result = self
+            .decide(
+                aggregate_id,
+                &cancellation::current(),
+                &processed::scoped_metadata(
+                    Self::scope_name(command),
+                    aggregate_id,
+                    &CommandMetadata::new(),
+                ),
+                batch,
+            )
+            .await;
+        self.record_decision(command, aggregate_id, &result);
+        result
+    }
+
+    /// Executes a command, re-running it against a freshly loaded aggregate
+    /// when the append hits a concurrency conflict.
+    ///
+    /// Only suitable for commands that remain valid when re-decided against
+    /// newer state. Gives up after `max_attempts` (at least one) and returns
+    /// the last conflict. The number of attempts used is recorded in the
+    /// `command_attempts` histogram.
+    pub async fn execute_with_retry<F>(
+        &self,
+        aggregate_id: AggregateId,
+        max_attempts: u32,
+        command_fn: F,
+    ) -> Result<CommandResult<A>, DomainError>
+    where
+        A: for<'de> serde::Deserialize<'de>,
+        A::Event: for<'de> serde::Deserialize<'de> + Serialize,
+        F: Fn(&A) -> Result<Vec<A::Event>, A::Error>,
+        DomainError: From<A::Error>,
+    {
+        self.retry(None, aggregate_id, max_attempts, command_fn)
+            .await
+    }
+
+    /// Executes a command with retries on conflict, naming it in the
+    /// decision record of each attempt.
+    pub async fn execute_named_with_retry<F>(
+        &self,
+        command: &'static str,
+        aggregate_id: AggregateId,
+        max_attempts: u32,
+        command_fn: F,
+    ) -> Result<CommandResult<A>, DomainError>
+    where
+        A: for<'de> serde::Deserialize<'de>,
+        A::Event: for<'de> serde::Deserialize<'de> + Serialize,
+        F: Fn(&A) -> Result<Vec<A::Event>, A::Error>,
+        DomainError: From<A::Error>,
+    {
+        self.retry(Some(command), aggregate_id, max_attempts, command_fn)
+            .await
+    }
+
+    async fn retry<F>(
+        &self,
+        command: Option<&'static str>,
+        aggregate_id: AggregateId,
+        max_attempts: u32,
+        command_fn: F,
+    ) -> Result<CommandResult<A>, DomainError>
+    where
+        A: for<'de> serde::Deserialize<'de>,
+        A::Event: for<'de> serde::Deserialize<'de> + Serialize,
+        F: Fn(&A) -> Result<Vec<A::Event>, A::Error>,
+        DomainError: From<A::Error>,
+    {
+        let cancel = cancellation::current();
+        // Numbered once, so every attempt is the same command
+        let metadata = processed::scoped_metadata(
+            Self::scope_name(command),
+            aggregate_id,
+            &CommandMetadata::new(),
+        )
+        .into_owned();
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let result = self
+                .run(command, aggregate_id, &cancel, &metadata, &command_fn)
+                .await;
+            let conflicted = matches!(
+                result,
+                Err(DomainError::EventStore(
+                    EventStoreError::ConcurrencyConflict { .. }
+                ))
+            );
+            if !conflicted || attempts >= max_attempts.max(1) {
+                metrics::histogram!("command_attempts", "aggregate_type" => A::aggregate_type())
+                    .record(f64::from(attempts));
+                return result;
+            }
+            tracing::debug!(attempts, "retrying command after concurrency conflict");
+        }
+    }
+
+    #[tracing::instrument(skip(self, cancel, metadata, command_fn), fields(aggregate_type = A::aggregate_type()))]
+    async fn run<F>(
+        &self,
+        command: Option<&'static str>,
+        aggregate_id: AggregateId,
+        cancel: &CancellationToken,
+        metadata: &CommandMetadata,
+        command_fn: F,
+    ) -> Result<CommandResult<A>, DomainError>
+    where
+        A: for<'de> serde::Deserialize<'de>,
+        A::Event: for<'de> serde::Deserialize<'de> + Serialize,
+        F: FnOnce(&A) -> Result<Vec<A::Event>, A::Error>,
+        DomainError: From<A::Error>,
+    {
+        let validated = |aggregate: &A| {
+            let events = command_fn(aggregate)?;
+            aggregate.validate_emitted(&events)?;
+            Ok((events, Vec::new()))
+        };
+        let metadata =
+            processed::scoped_metadata(Self::scope_name(command), aggregate_id, metadata);
+        let result = self
+            .decide(aggregate_id, cancel, &metadata, validated)
+            .await;
+        self.record_decision(command, aggregate_id, &result);
+        result
+    }
+
+    /// Loads the aggregate, decides its events with `command_fn`, which
+    /// validates them, and appends them.
+    ///
+    /// Alongside the events, `command_fn` returns metadata of each event's
+    /// own, added to `metadata` on that event; it is empty when the events
+    /// have none.
+    async fn decide<F>(
+        &self,
+        aggregate_id: AggregateId,
+        cancel: &CancellationToken,
+        metadata: &CommandMetadata,
+        command_fn: F,
+    ) -> Result<CommandResult<A>, DomainError>
+    where
+        A: for<'de> serde::Deserialize<'de>,
+        A::Event: for<'de> serde::Deserialize<'de> + Serialize,
+        F: FnOnce(&A) -> Result<(Vec<A::Event>, Vec<CommandMetadata>), A::Error>,
+        DomainError: From<A::Error>,
+    {
+        let command_id = processed::command_id(metadata);
+        if let Some(command_id) = command_id
+            && let Some(result) = self.replay(aggregate_id, command_id).await?
+        {
+            return Ok(result);
+        }
+
         let mut aggregate = self.load(aggregate_id).await?;
         let current_version = aggregate.version();
+        if cancel.is_cancelled() {
+            return Err(DomainError::Cancelled);
+        }
 
         // Execute command to get events
-        let events = match command_fn(&aggregate) {
-            Ok(events) => events,
+        let (events, event_metadata) = match command_fn(&aggregate) {
+            Ok(decided) => decided,
             Err(e) => {
                 metrics::counter!("commands_failed", "aggregate_type" => A::aggregate_type())
                     .increment(1);
@@ -138,15 +653,30 @@ where
         };
 
         if events.is_empty() {
+            self.record_processed(command_id, aggregate_id, current_version, current_version);
             return Ok(CommandResult {
                 aggregate,
                 events: vec![],
                 new_version: current_version,
+                replayed: false,
             });
         }
 
+        let missing = self.metadata_policy.missing(metadata);
+        if !missing.is_empty() {
+            metrics::counter!("commands_failed", "aggregate_type" => A::aggregate_type())
+                .increment(1);
+            return Err(DomainError::MissingMetadata { keys: missing });
+        }
+
         // Build envelopes for persistence
-        let envelopes = self.build_envelopes(aggregate_id, current_version, &events)?;
+        let envelopes = self.build_envelopes(
+            aggregate_id,
+            current_version,
+            &events,
+            metadata,
+            &event_metadata,
+        )?;
 
         // Persist events with optimistic concurrency
         let options = if current_version == Version::initial() {
@@ -155,32 +685,229 @@ where
             AppendOptions::expect_version(current_version)
         };
 
-        let new_version = self.store.append(envelopes, options).await?;
+        if cancel.is_cancelled() {
+            return Err(DomainError::Cancelled);
+        }
+        let new_version = match self.store.append(envelopes, options).await {
+            Ok(version) => version,
+            Err(e @ EventStoreError::ConcurrencyConflict { .. }) => {
+                self.record_conflict(aggregate_id);
+                return Err(self.store.describe_conflict(e).await.into());
+            }
+            Err(e) => return Err(e.into()),
+        };
 
         // Apply events to aggregate
         for event in &events {
             aggregate.apply(event.clone());
         }
         aggregate.set_version(new_version);
+        self.record_processed(command_id, aggregate_id, current_version, new_version);
 
-        let events_count = events.len();
         metrics::counter!("commands_executed", "aggregate_type" => A::aggregate_type())
             .increment(1);
-        tracing::info!(events_count, "command executed");
 
         Ok(CommandResult {
             aggregate,
             events,
             new_version,
+            replayed: false,
         })
     }
 
+    /// Returns the original result of the command with `command_id`, or None
+    /// if it hasn't been processed.
+    ///
+    /// IDs missing from the index are looked up in the aggregate's events;
+    /// IDs it holds are replayed without scanning the stream. The aggregate
+    /// is rebuilt from its snapshot when the snapshot predates the command.
+    /// Reuse of an ID on another aggregate is only caught while the index
+    /// still holds it.
+    async fn replay(
+        &self,
+        aggregate_id: AggregateId,
+        command_id: &str,
+    ) -> Result<Option<CommandResult<A>>, DomainError>
+    where
+        A: for<'de> serde::Deserialize<'de>,
+        A::Event: for<'de> serde::Deserialize<'de>,
+    {
+        let indexed = self.processed.get(command_id);
+        if let Some(processed) = indexed
+            && processed.aggregate_id != aggregate_id
+        {
+            return Err(DomainError::CommandIdReused {
+                command_id: command_id.to_string(),
+                aggregate_id: processed.aggregate_id.to_string(),
+            });
+        }
+
+        let (processed, scanned) = match indexed {
+            Some(processed) => (processed, None),
+            None => {
+                let envelopes = self.store.get_events_for_aggregate(aggregate_id).await?;
+                let mut stamped = envelopes
+                    .iter()
+                    .filter(|e| processed::command_id(&e.metadata) == Some(command_id))
+                    .map(|e| e.version);
+                let Some(first) = stamped.next() else {
+                    return Ok(None);
+                };
+                let processed = ProcessedCommand {
+                    aggregate_id,
+                    from: Version::new(first.as_i64() - 1),
+                    to: stamped.next_back().unwrap_or(first),
+                };
+                self.processed.record(command_id, processed);
+                (processed, Some(envelopes))
+            }
+        };
+
+        let snapshot = self
+            .store
+            .get_snapshot(aggregate_id)
+            .await?
+            .filter(|snapshot| snapshot.version <= processed.from);
+        let (mut aggregate, after) = match snapshot {
+            Some(snapshot) => {
+                let version = snapshot.version;
+                (self.restore_from_snapshot(snapshot)?, version)
+            }
+            None => (A::default(), Version::initial()),
+        };
+        let envelopes = match scanned {
+            Some(envelopes) => envelopes,
+            None => {
+                self.store
+                    .get_events_for_aggregate_from_version(aggregate_id, after.next())
+                    .await?
+            }
+        };
+
+        let mut events = Vec::new();
+        for envelope in envelopes
+            .into_iter()
+            .skip_while(|e| e.version <= after)
+            .take_while(|e| e.version <= processed.to)
+        {
+            self.record_read(&envelope);
+            let event: A::Event = serde_json::from_value(envelope.payload)?;
+            if envelope.version > processed.from {
+                events.push(event.clone());
+            }
+            aggregate.apply(event);
+            aggregate.set_version(envelope.version);
+        }
+
+        metrics::counter!("commands_replayed", "aggregate_type" => A::aggregate_type())
+            .increment(1);
+        tracing::debug!(%aggregate_id, command_id, "replaying processed command");
+        Ok(Some(CommandResult {
+            aggregate,
+            events,
+            new_version: processed.to,
+            replayed: true,
+        }))
+    }
+
+    /// Names a command in its scoped command ID; unnamed commands go by
+    /// their aggregate type.
+    fn scope_name(command: Option<&'static str>) -> &'static str {
+        command.unwrap_or_else(A::aggregate_type)
+    }
+
+    fn record_processed(
+        &self,
+        command_id: Option<&str>,
+        aggregate_id: AggregateId,
+        from: Version,
+        to: Version,
+    ) {
+        if let Some(command_id) = command_id {
+            self.processed.record(
+                command_id,
+                ProcessedCommand {
+                    aggregate_id,
+                    from,
+                    to,
+                },
+            );
+        }
+    }
+
+    fn record_decision(
+        &self,
+        command: Option<&'static str>,
+        aggregate_id: AggregateId,
+        result: &Result<CommandResult<A>, DomainError>,
+    ) {
+        let (outcome, invariant, reason, event_types) = match result {
+            Ok(result) if result.replayed => (
+                DecisionOutcome::Replayed,
+                None,
+                None,
+                result.events.iter().map(|e| e.event_type()).collect(),
+            ),
+            Ok(result) if result.events.is_empty() => {
+                (DecisionOutcome::Unchanged, None, None, Vec::new())
+            }
+            Ok(result) => (
+                DecisionOutcome::Accepted,
+                None,
+                None,
+                result.events.iter().map(|e| e.event_type()).collect(),
+            ),
+            Err(e) => match e.invariant() {
+                Some(code) => (
+                    DecisionOutcome::Rejected,
+                    Some(code),
+                    Some(e.to_string()),
+                    Vec::new(),
+                ),
+                None => (
+                    DecisionOutcome::Failed,
+                    None,
+                    Some(e.to_string()),
+                    Vec::new(),
+                ),
+            },
+        };
+
+        self.decisions.record(Decision {
+            recorded_at: chrono::Utc::now(),
+            command,
+            aggregate_type: A::aggregate_type(),
+            aggregate_id,
+            outcome,
+            invariant,
+            reason,
+            event_types,
+        });
+    }
+
+    fn record_read(&self, envelope: &EventEnvelope) {
+        if let Some(deprecations) = &self.schema_deprecations {
+            deprecations.record_read(envelope, ReadSource::Load);
+        }
+    }
+
+    fn record_conflict(&self, aggregate_id: AggregateId) {
+        metrics::counter!("concurrency_conflicts", "aggregate_type" => A::aggregate_type())
+            .increment(1);
+        if let Some(tracker) = &self.contention {
+            tracker.record_conflict(A::aggregate_type(), aggregate_id);
+        }
+        tracing::debug!(%aggregate_id, "concurrency conflict");
+    }
+
     /// Builds event envelopes from domain events.
     fn build_envelopes(
         &self,
         aggregate_id: AggregateId,
         current_version: Version,
         events: &[A::Event],
+        metadata: &CommandMetadata,
+        event_metadata: &[CommandMetadata],
     ) -> Result<Vec<EventEnvelope>, DomainError>
     where
         A::Event: Serialize,
@@ -188,16 +915,19 @@ where
         let mut envelopes = Vec::with_capacity(events.len());
         let mut version = current_version;
 
-        for event in events {
+        for (i, event) in events.iter().enumerate() {
             version = version.next();
-            let envelope = EventEnvelope::builder()
-                .aggregate_id(aggregate_id)
-                .aggregate_type(A::aggregate_type())
-                .event_type(event.event_type())
-                .version(version)
-                .payload(event)?
-                .build();
-            envelopes.push(envelope);
+            let mut metadata = metadata.clone();
+            if let Some(own) = event_metadata.get(i) {
+                metadata.extend(own.clone());
+            }
+            envelopes.push(self.envelopes.build(
+                aggregate_id,
+                version,
+                event.event_type(),
+                event,
+                metadata,
+            )?);
         }
 
         Ok(envelopes)
@@ -249,8 +979,9 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
-    use event_store::InMemoryEventStore;
+    use event_store::{FaultyEventStore, InMemoryEventStore, StoreOperation};
     use serde::{Deserialize, Serialize};
+    use std::sync::Arc;
 
     #[derive(Debug, Clone, Serialize, Deserialize)]
     enum TestEvent {
@@ -279,6 +1010,8 @@ mod tests {
     enum TestError {
         #[error("invalid value: {0}")]
         InvalidValue(i32),
+        #[error("more than one update in a command")]
+        ConflictingUpdates,
     }
 
     impl Aggregate for TestAggregate {
@@ -301,6 +1034,17 @@ mod tests {
             self.version = version;
         }
 
+        fn validate_emitted(&self, events: &[Self::Event]) -> Result<(), Self::Error> {
+            let updates = events
+                .iter()
+                .filter(|e| matches!(e, TestEvent::Updated { .. }))
+                .count();
+            if updates > 1 {
+                return Err(TestError::ConflictingUpdates);
+            }
+            Ok(())
+        }
+
         fn apply(&mut self, event: Self::Event) {
             match event {
                 TestEvent::Created { name } => {
@@ -373,6 +1117,36 @@ mod tests {
         assert_eq!(result.aggregate.value, 42);
     }
 
+    #[tokio::test]
+    async fn test_execute_surfaces_store_faults_without_writing() {
+        let store = FaultyEventStore::new(InMemoryEventStore::new());
+        let handler: CommandHandler<_, TestAggregate> = CommandHandler::new(store.clone());
+        let aggregate_id = AggregateId::new();
+        let create = |_: &TestAggregate| {
+            Ok(vec![TestEvent::Created {
+                name: "Test".to_string(),
+            }])
+        };
+
+        store.fail_next(StoreOperation::Append, 1);
+        let result = handler.execute(aggregate_id, create).await;
+        assert!(matches!(
+            result,
+            Err(DomainError::EventStore(EventStoreError::Injected(_)))
+        ));
+        assert_eq!(
+            store.get_aggregate_version(aggregate_id).await.unwrap(),
+            None
+        );
+
+        store.fail_next(StoreOperation::Load, 1);
+        assert!(handler.execute(aggregate_id, create).await.is_err());
+
+        // Once the store recovers the same command goes through
+        let result = handler.execute(aggregate_id, create).await.unwrap();
+        assert_eq!(result.new_version, Version::new(1));
+    }
+
     #[tokio::test]
     async fn test_execute_returns_error_on_invalid_command() {
         let store = InMemoryEventStore::new();
@@ -386,6 +1160,353 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[tokio::test]
+    async fn test_validate_emitted_rejects_before_append() {
+        let store = InMemoryEventStore::new();
+        let handler: CommandHandler<_, TestAggregate> = CommandHandler::new(store.clone());
+        let aggregate_id = AggregateId::new();
+
+        let result = handler
+            .execute(aggregate_id, |_| {
+                Ok(vec![
+                    TestEvent::Created {
+                        name: "Test".to_string(),
+                    },
+                    TestEvent::Updated { value: 1 },
+                    TestEvent::Updated { value: 2 },
+                ])
+            })
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(store.event_count().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_execute_many_appends_batch_at_once() {
+        let store = InMemoryEventStore::new();
+        let handler: CommandHandler<_, TestAggregate> = CommandHandler::new(store.clone());
+        let aggregate_id = AggregateId::new();
+
+        // Two updates across commands are fine; each command sees the last
+        let commands: [BatchedCommand<'_, TestAggregate>; 3] = [
+            Box::new(|_| {
+                Ok(vec![TestEvent::Created {
+                    name: "Test".to_string(),
+                }])
+            }),
+            Box::new(|agg| {
+                Ok(vec![TestEvent::Updated {
+                    value: agg.value + 1,
+                }])
+            }),
+            Box::new(|agg| {
+                Ok(vec![TestEvent::Updated {
+                    value: agg.value * 10,
+                }])
+            }),
+        ];
+        let result = handler.execute_many(aggregate_id, commands).await.unwrap();
+        assert_eq!(result.events.len(), 3);
+        assert_eq!(result.new_version, Version::new(3));
+        assert_eq!(result.aggregate.value, 10);
+
+        // A refused command leaves the whole batch unwritten
+        let commands: [BatchedCommand<'_, TestAggregate>; 2] = [
+            Box::new(|_| Ok(vec![TestEvent::Updated { value: 5 }])),
+            Box::new(|_| Err(TestError::InvalidValue(-1))),
+        ];
+        let err = handler.execute_many(aggregate_id, commands).await;
+        assert!(err.is_err());
+        // Per-command validation still applies within the batch
+        let err = handler
+            .execute_many(
+                aggregate_id,
+                [|_: &TestAggregate| {
+                    Ok(vec![
+                        TestEvent::Updated { value: 1 },
+                        TestEvent::Updated { value: 2 },
+                    ])
+                }],
+            )
+            .await;
+        assert!(err.is_err());
+
+        let events = store.get_events_for_aggregate(aggregate_id).await.unwrap();
+        assert_eq!(events.len(), 3);
+        assert_eq!(handler.load(aggregate_id).await.unwrap().value, 10);
+    }
+
+    #[tokio::test]
+    async fn test_execute_cancellable_skips_append_when_cancelled() {
+        let store = InMemoryEventStore::new();
+        let handler: CommandHandler<_, TestAggregate> = CommandHandler::new(store.clone());
+        let aggregate_id = AggregateId::new();
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let result = handler
+            .execute_cancellable(aggregate_id, &cancel, |_| {
+                Ok(vec![TestEvent::Created {
+                    name: "Test".to_string(),
+                }])
+            })
+            .await;
+
+        assert!(matches!(result, Err(DomainError::Cancelled)));
+        assert_eq!(store.event_count().await, 0);
+
+        // Commands without a token of their own take the scope's
+        let result = cancellation::with_cancellation(cancel, async {
+            handler
+                .execute_named("Create", aggregate_id, |_| {
+                    Ok(vec![TestEvent::Created {
+                        name: "Test".to_string(),
+                    }])
+                })
+                .await
+        })
+        .await;
+        assert!(matches!(result, Err(DomainError::Cancelled)));
+        assert_eq!(store.event_count().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_metadata_policy_rejects_missing_keys() {
+        let store = InMemoryEventStore::new();
+        let handler: CommandHandler<_, TestAggregate> = CommandHandler::new(store.clone())
+            .with_metadata_policy(MetadataPolicy::require(["actor", "correlation_id"]));
+        let aggregate_id = AggregateId::new();
+        let created = || {
+            Ok(vec![TestEvent::Created {
+                name: "Test".to_string(),
+            }])
+        };
+
+        let result = handler.execute(aggregate_id, |_| created()).await;
+        match result {
+            Err(DomainError::MissingMetadata { keys }) => {
+                assert_eq!(keys, ["actor", "correlation_id"]);
+            }
+            other => panic!("expected MissingMetadata, got {other:?}"),
+        }
+        assert_eq!(store.event_count().await, 0);
+
+        let mut metadata = CommandMetadata::new();
+        metadata.insert("actor".to_string(), serde_json::json!("ops@example.com"));
+        metadata.insert("correlation_id".to_string(), serde_json::json!("req-1"));
+        handler
+            .execute_with_metadata(aggregate_id, metadata, |_| created())
+            .await
+            .unwrap();
+
+        let events = store.get_events_for_aggregate(aggregate_id).await.unwrap();
+        assert_eq!(
+            events[0].metadata.get("actor"),
+            Some(&serde_json::json!("ops@example.com"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_command_with_processed_id_returns_original_result() {
+        let store = InMemoryEventStore::new();
+        let handler: CommandHandler<_, TestAggregate> = CommandHandler::new(store.clone());
+        let aggregate_id = AggregateId::new();
+        let metadata = CommandMetadata::from([(
+            processed::COMMAND_ID_KEY.to_string(),
+            serde_json::json!("cmd-1"),
+        )]);
+        handler
+            .execute(aggregate_id, |_| {
+                Ok(vec![TestEvent::Created {
+                    name: "Test".to_string(),
+                }])
+            })
+            .await
+            .unwrap();
+
+        let first = handler
+            .execute_with_metadata(aggregate_id, metadata.clone(), |_| {
+                Ok(vec![TestEvent::Updated { value: 1 }])
+            })
+            .await
+            .unwrap();
+        assert!(!first.replayed);
+        handler
+            .execute(aggregate_id, |_| Ok(vec![TestEvent::Updated { value: 2 }]))
+            .await
+            .unwrap();
+
+        // The retry gets the state the first attempt left, not the latest
+        let retry = handler
+            .execute_with_metadata(aggregate_id, metadata.clone(), |_| {
+                panic!("a processed command is not decided again")
+            })
+            .await
+            .unwrap();
+        assert!(retry.replayed);
+        assert_eq!(retry.new_version, Version::new(2));
+        assert_eq!(retry.aggregate.value, 1);
+        assert!(matches!(
+            retry.events[..],
+            [TestEvent::Updated { value: 1 }]
+        ));
+        assert_eq!(store.event_count().await, 3);
+
+        // After a restart the ID is found on the stored events
+        let restarted: CommandHandler<_, TestAggregate> = CommandHandler::new(store.clone());
+        let retry = restarted
+            .execute_with_metadata(aggregate_id, metadata.clone(), |_| {
+                panic!("a processed command is not decided again")
+            })
+            .await
+            .unwrap();
+        assert!(retry.replayed);
+        assert_eq!(retry.new_version, Version::new(2));
+        assert_eq!(retry.events.len(), 1);
+
+        // Reusing the ID on another aggregate is refused
+        let result = handler
+            .execute_with_metadata(AggregateId::new(), metadata, |_| {
+                Ok(vec![TestEvent::Created {
+                    name: "Other".to_string(),
+                }])
+            })
+            .await;
+        assert!(matches!(result, Err(DomainError::CommandIdReused { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_replay_rebuilds_from_a_snapshot_before_the_command() {
+        let store = InMemoryEventStore::new();
+        let handler: CommandHandler<_, TestAggregate> = CommandHandler::new(store.clone());
+        let aggregate_id = AggregateId::new();
+        let metadata = CommandMetadata::from([(
+            processed::COMMAND_ID_KEY.to_string(),
+            serde_json::json!("cmd-1"),
+        )]);
+        let snapshot = |name: &str, aggregate: &TestAggregate| {
+            let mut state = aggregate.clone();
+            state.name = name.to_string();
+            event_store::Snapshot::new(
+                aggregate_id,
+                "TestAggregate",
+                state.version,
+                serde_json::to_value(state).unwrap(),
+            )
+        };
+        let created = handler
+            .execute(aggregate_id, |_| {
+                Ok(vec![TestEvent::Created {
+                    name: "Test".to_string(),
+                }])
+            })
+            .await
+            .unwrap();
+        // The snapshot's name shows whether the replay started from it
+        store
+            .save_snapshot(snapshot("From snapshot", &created.aggregate))
+            .await
+            .unwrap();
+        let first = handler
+            .execute_with_metadata(aggregate_id, metadata.clone(), |_| {
+                Ok(vec![TestEvent::Updated { value: 1 }])
+            })
+            .await
+            .unwrap();
+
+        let retry = handler
+            .execute_with_metadata(aggregate_id, metadata.clone(), |_| {
+                panic!("a processed command is not decided again")
+            })
+            .await
+            .unwrap();
+        assert_eq!(retry.aggregate.name, "From snapshot");
+        assert_eq!(retry.aggregate.value, 1);
+        assert_eq!(retry.events.len(), 1);
+
+        // A snapshot taken after the command would hide its events
+        store
+            .save_snapshot(snapshot("Too late", &first.aggregate))
+            .await
+            .unwrap();
+        let restarted: CommandHandler<_, TestAggregate> = CommandHandler::new(store.clone());
+        let retry = restarted
+            .execute_with_metadata(aggregate_id, metadata, |_| {
+                panic!("a processed command is not decided again")
+            })
+            .await
+            .unwrap();
+        assert_eq!(retry.aggregate.name, "Test");
+        assert_eq!(retry.new_version, Version::new(2));
+        assert_eq!(retry.events.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_envelopes_follow_the_factory_conventions() {
+        let store = InMemoryEventStore::new();
+        let factory = EnvelopeFactory::new()
+            .with_schema_version("TestUpdated", 2)
+            .with_enricher(Arc::new(|envelope: &mut EventEnvelope| {
+                envelope
+                    .metadata
+                    .insert("service".to_string(), serde_json::json!("tests"));
+            }));
+        let handler: CommandHandler<_, TestAggregate> =
+            CommandHandler::new(store.clone()).with_envelope_factory(factory);
+        assert_eq!(
+            handler.envelope_factory().aggregate_type(),
+            Some("TestAggregate")
+        );
+
+        let aggregate_id = AggregateId::new();
+        handler
+            .execute(aggregate_id, |_| {
+                Ok(vec![
+                    TestEvent::Created {
+                        name: "Test".to_string(),
+                    },
+                    TestEvent::Updated { value: 1 },
+                ])
+            })
+            .await
+            .unwrap();
+
+        let events = store.get_events_for_aggregate(aggregate_id).await.unwrap();
+        assert!(events.iter().all(|e| e.aggregate_type == "TestAggregate"
+            && e.metadata.get("service") == Some(&serde_json::json!("tests"))));
+        assert_eq!(event_store::deprecation::event_version(&events[0]), 1);
+        assert_eq!(event_store::deprecation::event_version(&events[1]), 2);
+    }
+
+    #[tokio::test]
+    async fn test_rejections_are_recorded_in_decision_log() {
+        let log = DecisionLog::with_rejection_buffer(10);
+        let handler: CommandHandler<_, TestAggregate> =
+            CommandHandler::new(InMemoryEventStore::new())
+                .with_metadata_policy(MetadataPolicy::require(["actor"]))
+                .with_decision_log(log.clone());
+        let aggregate_id = AggregateId::new();
+
+        let result = handler
+            .execute_named("CreateTest", aggregate_id, |_| {
+                Ok(vec![TestEvent::Created {
+                    name: "Test".to_string(),
+                }])
+            })
+            .await;
+        assert!(result.is_err());
+
+        let rejections = log.recent_rejections(10);
+        assert_eq!(rejections.len(), 1);
+        assert_eq!(rejections[0].command, Some("CreateTest"));
+        assert_eq!(rejections[0].aggregate_type, "TestAggregate");
+        assert_eq!(rejections[0].aggregate_id, aggregate_id);
+        assert_eq!(rejections[0].outcome, DecisionOutcome::Rejected);
+        assert_eq!(rejections[0].invariant, Some("metadata.missing"));
+        assert!(rejections[0].event_types.is_empty());
+    }
+
     #[tokio::test]
     async fn test_load_existing_returns_none_for_new() {
         let store = InMemoryEventStore::new();
@@ -429,4 +1550,173 @@ mod tests {
         assert_eq!(result.new_version, Version::initial());
         assert_eq!(store.event_count().await, 0);
     }
+
+    #[tokio::test]
+    async fn test_load_at_version_replays_prefix() {
+        let store = InMemoryEventStore::new();
+        let handler: CommandHandler<_, TestAggregate> = CommandHandler::new(store);
+        let aggregate_id = AggregateId::new();
+        handler
+            .execute(aggregate_id, |_| {
+                Ok(vec![TestEvent::Created {
+                    name: "Test".to_string(),
+                }])
+            })
+            .await
+            .unwrap();
+        for value in [1, 2] {
+            handler
+                .execute(aggregate_id, |_| Ok(vec![TestEvent::Updated { value }]))
+                .await
+                .unwrap();
+        }
+
+        let at_two = handler
+            .load_at_version(aggregate_id, Version::new(2))
+            .await
+            .unwrap();
+        assert_eq!(at_two.version(), Version::new(2));
+        assert_eq!(at_two.value, 1);
+
+        let beyond = handler
+            .load_at_version(aggregate_id, Version::new(10))
+            .await
+            .unwrap();
+        assert_eq!(beyond.version(), Version::new(3));
+        assert_eq!(beyond.value, 2);
+    }
+
+    #[tokio::test]
+    async fn test_replay_range_returns_state_after_each_event() {
+        let store = InMemoryEventStore::new();
+        let handler: CommandHandler<_, TestAggregate> = CommandHandler::new(store);
+        let aggregate_id = AggregateId::new();
+        handler
+            .execute(aggregate_id, |_| {
+                Ok(vec![TestEvent::Created {
+                    name: "Test".to_string(),
+                }])
+            })
+            .await
+            .unwrap();
+        for value in [1, 2, 3] {
+            handler
+                .execute(aggregate_id, |_| Ok(vec![TestEvent::Updated { value }]))
+                .await
+                .unwrap();
+        }
+
+        let states = handler
+            .replay_range(aggregate_id, Version::new(2), Version::new(3))
+            .await
+            .unwrap();
+        let values: Vec<_> = states.iter().map(|(v, a)| (v.as_i64(), a.value)).collect();
+        assert_eq!(values, vec![(2, 1), (3, 2)]);
+        assert_eq!(states[1].1.version(), Version::new(3));
+
+        // The range is clamped to the events that exist
+        let states = handler
+            .replay_range(aggregate_id, Version::new(4), Version::new(10))
+            .await
+            .unwrap();
+        assert_eq!(states.len(), 1);
+        assert!(
+            handler
+                .replay_range(AggregateId::new(), Version::first(), Version::new(10))
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    /// Appends an update from a second writer, as if it raced the command.
+    fn rival_update(store: &InMemoryEventStore, aggregate_id: AggregateId) {
+        let store = store.clone();
+        std::thread::spawn(move || {
+            let rival: CommandHandler<_, TestAggregate> = CommandHandler::new(store);
+            tokio::runtime::Runtime::new()
+                .unwrap()
+                .block_on(
+                    rival.execute(aggregate_id, |_| Ok(vec![TestEvent::Updated { value: -1 }])),
+                )
+                .unwrap();
+        })
+        .join()
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_execute_with_retry_recovers_from_conflict() {
+        let store = InMemoryEventStore::new();
+        let tracker = ContentionTracker::new();
+        let handler: CommandHandler<_, TestAggregate> =
+            CommandHandler::new(store.clone()).with_contention_tracker(tracker.clone());
+        let aggregate_id = AggregateId::new();
+        handler
+            .execute(aggregate_id, |_| {
+                Ok(vec![TestEvent::Created {
+                    name: "Test".to_string(),
+                }])
+            })
+            .await
+            .unwrap();
+
+        let calls = std::sync::atomic::AtomicU32::new(0);
+        let result = handler
+            .execute_with_retry(aggregate_id, 3, |_| {
+                if calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst) == 0 {
+                    rival_update(&store, aggregate_id);
+                }
+                Ok(vec![TestEvent::Updated { value: 42 }])
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(calls.into_inner(), 2);
+        assert_eq!(result.new_version, Version::new(3));
+        assert_eq!(result.aggregate.value, 42);
+
+        let report = tracker.report(10);
+        assert_eq!(report.conflicts_by_type["TestAggregate"], 1);
+        assert_eq!(report.hot_aggregates[0].aggregate_id, aggregate_id);
+    }
+
+    #[tokio::test]
+    async fn test_execute_with_retry_gives_up_after_max_attempts() {
+        let store = InMemoryEventStore::new();
+        let tracker = ContentionTracker::new();
+        let handler: CommandHandler<_, TestAggregate> =
+            CommandHandler::new(store.clone()).with_contention_tracker(tracker.clone());
+        let aggregate_id = AggregateId::new();
+        handler
+            .execute(aggregate_id, |_| {
+                Ok(vec![TestEvent::Created {
+                    name: "Test".to_string(),
+                }])
+            })
+            .await
+            .unwrap();
+
+        let result = handler
+            .execute_with_retry(aggregate_id, 2, |_| {
+                rival_update(&store, aggregate_id);
+                Ok(vec![TestEvent::Updated { value: 42 }])
+            })
+            .await;
+
+        // The rival's update on the last attempt is reported
+        match result {
+            Err(DomainError::EventStore(EventStoreError::ConcurrencyConflict {
+                expected,
+                conflicting,
+                ..
+            })) => {
+                assert_eq!(expected, Version::new(2));
+                assert_eq!(conflicting.len(), 1);
+                assert_eq!(conflicting[0].version, Version::new(3));
+            }
+            other => panic!("expected ConcurrencyConflict, got {other:?}"),
+        }
+        assert_eq!(tracker.report(10).hot_aggregates[0].conflicts, 2);
+    }
 }
@@ -0,0 +1,203 @@
+//! Optimistic concurrency contention tracking.
+//!
+//! The command handler records every concurrency conflict it sees. Conflicts
+//! are counted per aggregate type in the `concurrency_conflicts` counter and,
+//! when a [`ContentionTracker`] is attached, per aggregate for the current
+//! reporting window. The window's hottest aggregates show whether a stream
+//! needs single-writer handling rather than optimistic retries.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use common::AggregateId;
+use serde::Serialize;
+
+/// An aggregate's conflict count within a reporting window.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HotAggregate {
+    pub aggregate_type: String,
+    pub aggregate_id: AggregateId,
+    pub conflicts: u64,
+}
+
+/// Conflicts recorded since the window started.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ContentionReport {
+    pub window_started_at: DateTime<Utc>,
+    pub generated_at: DateTime<Utc>,
+    /// Total conflicts per aggregate type.
+    pub conflicts_by_type: BTreeMap<String, u64>,
+    /// Aggregates with the most conflicts, highest first.
+    pub hot_aggregates: Vec<HotAggregate>,
+}
+
+#[derive(Debug)]
+struct Window {
+    started_at: DateTime<Utc>,
+    conflicts: HashMap<(String, AggregateId), u64>,
+}
+
+impl Window {
+    fn new() -> Self {
+        Self {
+            started_at: Utc::now(),
+            conflicts: HashMap::new(),
+        }
+    }
+
+    fn report(&self, top_n: usize) -> ContentionReport {
+        let mut conflicts_by_type = BTreeMap::new();
+        for ((aggregate_type, _), count) in &self.conflicts {
+            *conflicts_by_type.entry(aggregate_type.clone()).or_insert(0) += count;
+        }
+
+        let mut hot_aggregates: Vec<HotAggregate> = self
+            .conflicts
+            .iter()
+            .map(|((aggregate_type, aggregate_id), conflicts)| HotAggregate {
+                aggregate_type: aggregate_type.clone(),
+                aggregate_id: *aggregate_id,
+                conflicts: *conflicts,
+            })
+            .collect();
+        hot_aggregates.sort_by(|a, b| {
+            b.conflicts
+                .cmp(&a.conflicts)
+                .then_with(|| a.aggregate_type.cmp(&b.aggregate_type))
+                .then_with(|| a.aggregate_id.as_uuid().cmp(&b.aggregate_id.as_uuid()))
+        });
+        hot_aggregates.truncate(top_n);
+
+        ContentionReport {
+            window_started_at: self.started_at,
+            generated_at: Utc::now(),
+            conflicts_by_type,
+            hot_aggregates,
+        }
+    }
+}
+
+/// Per-aggregate conflict counts for the current reporting window.
+///
+/// Cloning shares the same window, so one tracker can be attached to several
+/// command handlers and read from elsewhere.
+#[derive(Debug, Clone)]
+pub struct ContentionTracker {
+    window: Arc<Mutex<Window>>,
+}
+
+impl Default for ContentionTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ContentionTracker {
+    /// Creates a tracker with an empty window starting now.
+    pub fn new() -> Self {
+        Self {
+            window: Arc::new(Mutex::new(Window::new())),
+        }
+    }
+
+    /// Records a concurrency conflict on an aggregate.
+    pub fn record_conflict(&self, aggregate_type: &str, aggregate_id: AggregateId) {
+        let mut window = self.window.lock().unwrap();
+        *window
+            .conflicts
+            .entry((aggregate_type.to_string(), aggregate_id))
+            .or_insert(0) += 1;
+    }
+
+    /// Returns the current window's totals and its `top_n` hottest aggregates.
+    pub fn report(&self, top_n: usize) -> ContentionReport {
+        self.window.lock().unwrap().report(top_n)
+    }
+
+    /// Closes the current window, returning its report, and starts a new one.
+    ///
+    /// The closed window's hottest aggregates are published as the
+    /// `hot_aggregate_conflicts` gauge.
+    pub fn rotate(&self, top_n: usize) -> ContentionReport {
+        let report = {
+            let mut window = self.window.lock().unwrap();
+            let report = window.report(top_n);
+            *window = Window::new();
+            report
+        };
+
+        for hot in &report.hot_aggregates {
+            metrics::gauge!(
+                "hot_aggregate_conflicts",
+                "aggregate_type" => hot.aggregate_type.clone(),
+                "aggregate_id" => hot.aggregate_id.to_string()
+            )
+            .set(hot.conflicts as f64);
+        }
+        report
+    }
+
+    /// Rotates the window every `period`, logging each closed window's
+    /// hottest aggregates. Runs until the task is dropped.
+    pub async fn report_periodically(self, period: Duration, top_n: usize) {
+        let mut interval = tokio::time::interval(period);
+        // The first tick completes immediately
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let report = self.rotate(top_n);
+            for hot in &report.hot_aggregates {
+                tracing::warn!(
+                    aggregate_type = %hot.aggregate_type,
+                    aggregate_id = %hot.aggregate_id,
+                    conflicts = hot.conflicts,
+                    "hot aggregate"
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_ranks_hot_aggregates() {
+        let tracker = ContentionTracker::new();
+        let hot = AggregateId::new();
+        let warm = AggregateId::new();
+        let item = AggregateId::new();
+
+        for _ in 0..3 {
+            tracker.record_conflict("Order", hot);
+        }
+        tracker.record_conflict("Order", warm);
+        tracker.record_conflict("InventoryItem", item);
+        tracker.record_conflict("InventoryItem", item);
+
+        let report = tracker.report(2);
+        assert_eq!(report.conflicts_by_type["Order"], 4);
+        assert_eq!(report.conflicts_by_type["InventoryItem"], 2);
+        assert_eq!(report.hot_aggregates.len(), 2);
+        assert_eq!(report.hot_aggregates[0].aggregate_id, hot);
+        assert_eq!(report.hot_aggregates[0].conflicts, 3);
+        assert_eq!(report.hot_aggregates[1].aggregate_id, item);
+    }
+
+    #[test]
+    fn test_rotate_starts_new_window() {
+        let tracker = ContentionTracker::new();
+        tracker.record_conflict("Order", AggregateId::new());
+
+        let closed = tracker.rotate(10);
+        assert_eq!(closed.hot_aggregates.len(), 1);
+
+        let current = tracker.report(10);
+        assert!(current.hot_aggregates.is_empty());
+        assert!(current.conflicts_by_type.is_empty());
+        assert!(current.window_started_at >= closed.window_started_at);
+    }
+}
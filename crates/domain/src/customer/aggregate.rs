@@ -0,0 +1,366 @@
+//! Customer aggregate implementation.
+
+use common::AggregateId;
+use event_store::Version;
+use serde::{Deserialize, Serialize};
+
+use crate::aggregate::Aggregate;
+use crate::order::CustomerId;
+
+use super::events::{
+    ContactInfo, ContactInfoUpdatedData, CustomerDeactivatedData, CustomerMergedData,
+    CustomerRegisteredData,
+};
+use super::{CustomerError, CustomerEvent};
+
+/// Customer aggregate root.
+///
+/// The customer stream shares its ID with the `CustomerId` it describes.
+/// Customers are registered with their contact info, which can be updated
+/// until they're deactivated. Customers from before registration existed
+/// have no stream until they're merged.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Customer {
+    /// Unique stream identifier.
+    id: Option<AggregateId>,
+
+    /// Current version for optimistic concurrency.
+    #[serde(default)]
+    version: Version,
+
+    /// The customer this stream describes.
+    customer_id: Option<CustomerId>,
+
+    /// Contact info, once registered.
+    #[serde(default)]
+    contact: Option<ContactInfo>,
+
+    /// Whether the customer was deactivated.
+    #[serde(default)]
+    deactivated: bool,
+
+    /// The surviving customer, if this one was merged away.
+    merged_into: Option<CustomerId>,
+}
+
+impl Aggregate for Customer {
+    type Event = CustomerEvent;
+    type Error = CustomerError;
+
+    fn aggregate_type() -> &'static str {
+        "Customer"
+    }
+
+    fn id(&self) -> Option<AggregateId> {
+        self.id
+    }
+
+    fn version(&self) -> Version {
+        self.version
+    }
+
+    fn set_version(&mut self, version: Version) {
+        self.version = version;
+    }
+
+    fn apply(&mut self, event: Self::Event) {
+        match event {
+            CustomerEvent::CustomerRegistered(data) => self.apply_customer_registered(data),
+            CustomerEvent::ContactInfoUpdated(data) => self.apply_contact_info_updated(data),
+            CustomerEvent::CustomerDeactivated(data) => self.apply_customer_deactivated(data),
+            CustomerEvent::CustomerMerged(data) => self.apply_customer_merged(data),
+        }
+    }
+}
+
+// Query methods
+impl Customer {
+    /// Returns the stream ID for a customer.
+    pub fn stream_id(customer_id: CustomerId) -> AggregateId {
+        AggregateId::from_uuid(customer_id.as_uuid())
+    }
+
+    /// Returns the customer ID.
+    pub fn customer_id(&self) -> Option<CustomerId> {
+        self.customer_id
+    }
+
+    /// Returns the customer's contact info, if registered.
+    pub fn contact(&self) -> Option<&ContactInfo> {
+        self.contact.as_ref()
+    }
+
+    /// Returns true if the customer was registered.
+    pub fn is_registered(&self) -> bool {
+        self.contact.is_some()
+    }
+
+    /// Returns true if the customer is registered and neither deactivated
+    /// nor merged away.
+    pub fn is_active(&self) -> bool {
+        self.is_registered() && !self.deactivated && !self.is_merged()
+    }
+
+    /// Returns true if the customer was deactivated.
+    pub fn is_deactivated(&self) -> bool {
+        self.deactivated
+    }
+
+    /// Returns the surviving customer if this one was merged away.
+    pub fn merged_into(&self) -> Option<CustomerId> {
+        self.merged_into
+    }
+
+    /// Returns true if this customer was merged into another.
+    pub fn is_merged(&self) -> bool {
+        self.merged_into.is_some()
+    }
+}
+
+// Command methods (return events)
+impl Customer {
+    /// Registers the customer with their contact info.
+    pub fn register(
+        &self,
+        customer_id: CustomerId,
+        contact: ContactInfo,
+    ) -> Result<Vec<CustomerEvent>, CustomerError> {
+        self.ensure_not_merged(customer_id)?;
+        if self.is_registered() {
+            return Err(CustomerError::AlreadyRegistered { customer_id });
+        }
+        validate_contact(&contact)?;
+
+        Ok(vec![CustomerEvent::customer_registered(
+            customer_id,
+            contact,
+        )])
+    }
+
+    /// Replaces the customer's contact info.
+    pub fn update_contact(
+        &self,
+        customer_id: CustomerId,
+        contact: ContactInfo,
+    ) -> Result<Vec<CustomerEvent>, CustomerError> {
+        self.ensure_active(customer_id)?;
+        validate_contact(&contact)?;
+
+        Ok(vec![CustomerEvent::contact_info_updated(
+            customer_id,
+            contact,
+        )])
+    }
+
+    /// Deactivates the customer.
+    pub fn deactivate(
+        &self,
+        customer_id: CustomerId,
+        reason: Option<String>,
+    ) -> Result<Vec<CustomerEvent>, CustomerError> {
+        self.ensure_active(customer_id)?;
+
+        Ok(vec![CustomerEvent::customer_deactivated(
+            customer_id,
+            reason,
+        )])
+    }
+
+    /// Merges this customer into a surviving customer.
+    pub fn merge_into(
+        &self,
+        customer_id: CustomerId,
+        target: CustomerId,
+        merged_by: Option<String>,
+    ) -> Result<Vec<CustomerEvent>, CustomerError> {
+        if customer_id == target {
+            return Err(CustomerError::SelfMerge { customer_id });
+        }
+
+        if let Some(merged_into) = self.merged_into {
+            return Err(CustomerError::AlreadyMerged {
+                customer_id,
+                merged_into,
+            });
+        }
+
+        Ok(vec![CustomerEvent::customer_merged(
+            customer_id,
+            target,
+            merged_by,
+        )])
+    }
+}
+
+// Validation helpers
+impl Customer {
+    fn ensure_not_merged(&self, customer_id: CustomerId) -> Result<(), CustomerError> {
+        match self.merged_into {
+            Some(merged_into) => Err(CustomerError::AlreadyMerged {
+                customer_id,
+                merged_into,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Refuses a customer that isn't registered, was deactivated, or was
+    /// merged away, e.g. before taking an order for them.
+    pub fn ensure_active(&self, customer_id: CustomerId) -> Result<(), CustomerError> {
+        self.ensure_not_merged(customer_id)?;
+        if !self.is_registered() {
+            return Err(CustomerError::NotRegistered { customer_id });
+        }
+        if self.deactivated {
+            return Err(CustomerError::Deactivated { customer_id });
+        }
+        Ok(())
+    }
+}
+
+fn validate_contact(contact: &ContactInfo) -> Result<(), CustomerError> {
+    let invalid = |reason: &str| {
+        Err(CustomerError::InvalidContactInfo {
+            reason: reason.to_string(),
+        })
+    };
+    if contact.name.trim().is_empty() {
+        return invalid("name is required");
+    }
+    match contact.email.split_once('@') {
+        Some((local, domain)) if !local.is_empty() && domain.contains('.') => Ok(()),
+        _ => invalid("email must look like name@example.com"),
+    }
+}
+
+// Apply event helpers
+impl Customer {
+    fn apply_customer_registered(&mut self, data: CustomerRegisteredData) {
+        self.id = Some(Self::stream_id(data.customer_id));
+        self.customer_id = Some(data.customer_id);
+        self.contact = Some(data.contact);
+    }
+
+    fn apply_contact_info_updated(&mut self, data: ContactInfoUpdatedData) {
+        self.contact = Some(data.contact);
+    }
+
+    fn apply_customer_deactivated(&mut self, _data: CustomerDeactivatedData) {
+        self.deactivated = true;
+    }
+
+    fn apply_customer_merged(&mut self, data: CustomerMergedData) {
+        self.id = Some(Self::stream_id(data.customer_id));
+        self.customer_id = Some(data.customer_id);
+        self.merged_into = Some(data.merged_into);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_into() {
+        let mut customer = Customer::default();
+        let source = CustomerId::new();
+        let target = CustomerId::new();
+
+        let events = customer.merge_into(source, target, None).unwrap();
+        customer.apply_events(events);
+
+        assert_eq!(customer.id(), Some(Customer::stream_id(source)));
+        assert_eq!(customer.customer_id(), Some(source));
+        assert_eq!(customer.merged_into(), Some(target));
+        assert!(customer.is_merged());
+    }
+
+    #[test]
+    fn test_customer_lifecycle() {
+        let mut customer = Customer::default();
+        let id = CustomerId::new();
+
+        let events = customer
+            .register(id, ContactInfo::new("Ada", "ada@example.com"))
+            .unwrap();
+        customer.apply_events(events);
+        assert_eq!(customer.id(), Some(Customer::stream_id(id)));
+        assert!(customer.is_active());
+        assert!(matches!(
+            customer.register(id, ContactInfo::new("Ada", "ada@example.com")),
+            Err(CustomerError::AlreadyRegistered { .. })
+        ));
+
+        let updated = ContactInfo::new("Ada L.", "ada@example.org").with_phone("+1 555 0100");
+        let events = customer.update_contact(id, updated.clone()).unwrap();
+        customer.apply_events(events);
+        assert_eq!(customer.contact(), Some(&updated));
+
+        let events = customer
+            .deactivate(id, Some("closed account".to_string()))
+            .unwrap();
+        customer.apply_events(events);
+        assert!(customer.is_deactivated());
+        assert!(!customer.is_active());
+        assert!(matches!(
+            customer.update_contact(id, updated),
+            Err(CustomerError::Deactivated { .. })
+        ));
+        assert!(matches!(
+            customer.deactivate(id, None),
+            Err(CustomerError::Deactivated { .. })
+        ));
+    }
+
+    #[test]
+    fn test_unregistered_customer_cannot_change() {
+        let customer = Customer::default();
+        let id = CustomerId::new();
+        assert!(matches!(
+            customer.update_contact(id, ContactInfo::new("Ada", "ada@example.com")),
+            Err(CustomerError::NotRegistered { .. })
+        ));
+        assert!(matches!(
+            customer.deactivate(id, None),
+            Err(CustomerError::NotRegistered { .. })
+        ));
+    }
+
+    #[test]
+    fn test_register_validates_contact_info() {
+        let customer = Customer::default();
+        let id = CustomerId::new();
+        for contact in [
+            ContactInfo::new(" ", "ada@example.com"),
+            ContactInfo::new("Ada", "ada"),
+            ContactInfo::new("Ada", "@example.com"),
+        ] {
+            assert!(matches!(
+                customer.register(id, contact),
+                Err(CustomerError::InvalidContactInfo { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_merge_into_self_fails() {
+        let customer = Customer::default();
+        let id = CustomerId::new();
+        let result = customer.merge_into(id, id, None);
+        assert!(matches!(result, Err(CustomerError::SelfMerge { .. })));
+    }
+
+    #[test]
+    fn test_merge_twice_fails() {
+        let mut customer = Customer::default();
+        let source = CustomerId::new();
+
+        let events = customer
+            .merge_into(source, CustomerId::new(), None)
+            .unwrap();
+        customer.apply_events(events);
+
+        let result = customer.merge_into(source, CustomerId::new(), None);
+        assert!(matches!(result, Err(CustomerError::AlreadyMerged { .. })));
+    }
+}
@@ -0,0 +1,120 @@
+//! Customer commands.
+
+use common::AggregateId;
+
+use crate::command::Command;
+use crate::order::CustomerId;
+
+use super::{ContactInfo, Customer};
+
+/// Command to register a customer.
+#[derive(Debug, Clone)]
+pub struct RegisterCustomer {
+    pub customer_id: CustomerId,
+    pub contact: ContactInfo,
+}
+
+impl RegisterCustomer {
+    /// Creates a new RegisterCustomer command.
+    pub fn new(customer_id: CustomerId, contact: ContactInfo) -> Self {
+        Self {
+            customer_id,
+            contact,
+        }
+    }
+
+    /// Creates a command registering a new customer with a generated ID.
+    pub fn with_contact(contact: ContactInfo) -> Self {
+        Self::new(CustomerId::new(), contact)
+    }
+}
+
+impl Command for RegisterCustomer {
+    type Aggregate = Customer;
+
+    fn aggregate_id(&self) -> AggregateId {
+        Customer::stream_id(self.customer_id)
+    }
+}
+
+/// Command to replace a customer's contact info.
+#[derive(Debug, Clone)]
+pub struct UpdateContactInfo {
+    pub customer_id: CustomerId,
+    pub contact: ContactInfo,
+}
+
+impl UpdateContactInfo {
+    /// Creates a new UpdateContactInfo command.
+    pub fn new(customer_id: CustomerId, contact: ContactInfo) -> Self {
+        Self {
+            customer_id,
+            contact,
+        }
+    }
+}
+
+impl Command for UpdateContactInfo {
+    type Aggregate = Customer;
+
+    fn aggregate_id(&self) -> AggregateId {
+        Customer::stream_id(self.customer_id)
+    }
+}
+
+/// Command to deactivate a customer.
+#[derive(Debug, Clone)]
+pub struct DeactivateCustomer {
+    pub customer_id: CustomerId,
+    pub reason: Option<String>,
+}
+
+impl DeactivateCustomer {
+    /// Creates a new DeactivateCustomer command.
+    pub fn new(customer_id: CustomerId, reason: Option<String>) -> Self {
+        Self {
+            customer_id,
+            reason,
+        }
+    }
+}
+
+impl Command for DeactivateCustomer {
+    type Aggregate = Customer;
+
+    fn aggregate_id(&self) -> AggregateId {
+        Customer::stream_id(self.customer_id)
+    }
+}
+
+/// Command to merge a duplicate customer into a surviving customer.
+#[derive(Debug, Clone)]
+pub struct MergeCustomers {
+    /// The duplicate customer to merge away.
+    pub source: CustomerId,
+
+    /// The customer that survives the merge.
+    pub target: CustomerId,
+
+    /// Who requested the merge.
+    pub merged_by: Option<String>,
+}
+
+impl MergeCustomers {
+    /// Creates a new MergeCustomers command.
+    pub fn new(source: CustomerId, target: CustomerId, merged_by: Option<String>) -> Self {
+        Self {
+            source,
+            target,
+            merged_by,
+        }
+    }
+}
+
+impl Command for MergeCustomers {
+    type Aggregate = Customer;
+
+    fn aggregate_id(&self) -> AggregateId {
+        Customer::stream_id(self.source)
+    }
+}
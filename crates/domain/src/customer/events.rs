@@ -0,0 +1,200 @@
+//! Customer domain events.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::aggregate::DomainEvent;
+use crate::order::CustomerId;
+
+/// Events that can occur on a customer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data")]
+pub enum CustomerEvent {
+    /// Customer was registered with their contact info.
+    CustomerRegistered(CustomerRegisteredData),
+
+    /// Customer's contact info was replaced.
+    ContactInfoUpdated(ContactInfoUpdatedData),
+
+    /// Customer was deactivated.
+    CustomerDeactivated(CustomerDeactivatedData),
+
+    /// Customer was merged into a surviving customer.
+    CustomerMerged(CustomerMergedData),
+}
+
+impl DomainEvent for CustomerEvent {
+    fn event_type(&self) -> &'static str {
+        match self {
+            CustomerEvent::CustomerRegistered(_) => "CustomerRegistered",
+            CustomerEvent::ContactInfoUpdated(_) => "ContactInfoUpdated",
+            CustomerEvent::CustomerDeactivated(_) => "CustomerDeactivated",
+            CustomerEvent::CustomerMerged(_) => "CustomerMerged",
+        }
+    }
+}
+
+/// How to reach a customer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContactInfo {
+    pub name: String,
+    pub email: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phone: Option<String>,
+}
+
+impl ContactInfo {
+    /// Creates contact info without a phone number.
+    pub fn new(name: impl Into<String>, email: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            email: email.into(),
+            phone: None,
+        }
+    }
+
+    /// Adds a phone number.
+    pub fn with_phone(mut self, phone: impl Into<String>) -> Self {
+        self.phone = Some(phone.into());
+        self
+    }
+}
+
+/// Data for CustomerRegistered event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomerRegisteredData {
+    /// The customer registered.
+    pub customer_id: CustomerId,
+
+    /// Contact info given at registration.
+    pub contact: ContactInfo,
+
+    /// When the customer registered.
+    pub registered_at: DateTime<Utc>,
+}
+
+/// Data for ContactInfoUpdated event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContactInfoUpdatedData {
+    /// The customer updated.
+    pub customer_id: CustomerId,
+
+    /// The new contact info, replacing the old.
+    pub contact: ContactInfo,
+
+    /// When the contact info changed.
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Data for CustomerDeactivated event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomerDeactivatedData {
+    /// The customer deactivated.
+    pub customer_id: CustomerId,
+
+    /// Why the customer was deactivated.
+    pub reason: Option<String>,
+
+    /// When the customer was deactivated.
+    pub deactivated_at: DateTime<Utc>,
+}
+
+/// Data for CustomerMerged event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomerMergedData {
+    /// The duplicate customer being merged away.
+    pub customer_id: CustomerId,
+
+    /// The surviving customer.
+    pub merged_into: CustomerId,
+
+    /// Who performed the merge.
+    pub merged_by: Option<String>,
+
+    /// When the merge happened.
+    pub merged_at: DateTime<Utc>,
+}
+
+// Convenience constructors for events
+impl CustomerEvent {
+    /// Creates a CustomerRegistered event.
+    pub fn customer_registered(customer_id: CustomerId, contact: ContactInfo) -> Self {
+        CustomerEvent::CustomerRegistered(CustomerRegisteredData {
+            customer_id,
+            contact,
+            registered_at: Utc::now(),
+        })
+    }
+
+    /// Creates a ContactInfoUpdated event.
+    pub fn contact_info_updated(customer_id: CustomerId, contact: ContactInfo) -> Self {
+        CustomerEvent::ContactInfoUpdated(ContactInfoUpdatedData {
+            customer_id,
+            contact,
+            updated_at: Utc::now(),
+        })
+    }
+
+    /// Creates a CustomerDeactivated event.
+    pub fn customer_deactivated(customer_id: CustomerId, reason: Option<String>) -> Self {
+        CustomerEvent::CustomerDeactivated(CustomerDeactivatedData {
+            customer_id,
+            reason,
+            deactivated_at: Utc::now(),
+        })
+    }
+
+    /// Creates a CustomerMerged event.
+    pub fn customer_merged(
+        customer_id: CustomerId,
+        merged_into: CustomerId,
+        merged_by: Option<String>,
+    ) -> Self {
+        CustomerEvent::CustomerMerged(CustomerMergedData {
+            customer_id,
+            merged_into,
+            merged_by,
+            merged_at: Utc::now(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_type() {
+        let event = CustomerEvent::customer_merged(CustomerId::new(), CustomerId::new(), None);
+        assert_eq!(event.event_type(), "CustomerMerged");
+
+        let contact = ContactInfo::new("Ada", "ada@example.com");
+        let id = CustomerId::new();
+        assert_eq!(
+            CustomerEvent::customer_registered(id, contact.clone()).event_type(),
+            "CustomerRegistered"
+        );
+        assert_eq!(
+            CustomerEvent::contact_info_updated(id, contact).event_type(),
+            "ContactInfoUpdated"
+        );
+        assert_eq!(
+            CustomerEvent::customer_deactivated(id, None).event_type(),
+            "CustomerDeactivated"
+        );
+    }
+
+    #[test]
+    fn test_serialization_roundtrip() {
+        let event = CustomerEvent::customer_merged(
+            CustomerId::new(),
+            CustomerId::new(),
+            Some("admin".to_string()),
+        );
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["type"], "CustomerMerged");
+
+        let parsed: CustomerEvent = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.event_type(), "CustomerMerged");
+    }
+}
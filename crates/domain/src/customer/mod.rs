@@ -0,0 +1,63 @@
+//! Customer aggregate and related types.
+
+mod aggregate;
+mod commands;
+mod events;
+mod service;
+
+pub use aggregate::Customer;
+pub use commands::*;
+pub use events::{
+    ContactInfo, ContactInfoUpdatedData, CustomerDeactivatedData, CustomerEvent,
+    CustomerMergedData, CustomerRegisteredData,
+};
+pub use service::CustomerService;
+
+use thiserror::Error;
+
+use crate::order::CustomerId;
+
+/// Errors that can occur during customer operations.
+#[derive(Debug, Error)]
+pub enum CustomerError {
+    /// A customer cannot be merged into itself.
+    #[error("Cannot merge customer {customer_id} into itself")]
+    SelfMerge { customer_id: CustomerId },
+
+    /// The customer was already registered.
+    #[error("Customer {customer_id} is already registered")]
+    AlreadyRegistered { customer_id: CustomerId },
+
+    /// The customer was never registered.
+    #[error("Customer {customer_id} is not registered")]
+    NotRegistered { customer_id: CustomerId },
+
+    /// The customer was deactivated.
+    #[error("Customer {customer_id} is deactivated")]
+    Deactivated { customer_id: CustomerId },
+
+    /// Contact info is missing a name or has a malformed email.
+    #[error("Invalid contact info: {reason}")]
+    InvalidContactInfo { reason: String },
+
+    /// The customer was already merged into another customer.
+    #[error("Customer {customer_id} was already merged into {merged_into}")]
+    AlreadyMerged {
+        customer_id: CustomerId,
+        merged_into: CustomerId,
+    },
+}
+
+impl CustomerError {
+    /// Returns a stable code naming the violated invariant.
+    pub fn code(&self) -> &'static str {
+        match self {
+            CustomerError::SelfMerge { .. } => "customer.self_merge",
+            CustomerError::AlreadyMerged { .. } => "customer.already_merged",
+            CustomerError::AlreadyRegistered { .. } => "customer.already_registered",
+            CustomerError::NotRegistered { .. } => "customer.not_registered",
+            CustomerError::Deactivated { .. } => "customer.deactivated",
+            CustomerError::InvalidContactInfo { .. } => "customer.invalid_contact_info",
+        }
+    }
+}
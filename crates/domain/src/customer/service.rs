@@ -0,0 +1,240 @@
+//! Customer service providing a simplified API for customer operations.
+
+use event_store::{EnvelopeFactory, EventStore, SchemaDeprecations};
+
+use crate::command::{Command, CommandHandler, CommandResult};
+use crate::decision::DecisionLog;
+use crate::error::DomainError;
+use crate::order::CustomerId;
+
+use super::{
+    Customer, CustomerError, DeactivateCustomer, MergeCustomers, RegisterCustomer,
+    UpdateContactInfo,
+};
+
+/// Maximum number of merge links followed when resolving a customer.
+const MAX_MERGE_DEPTH: usize = 32;
+
+impl From<CustomerError> for DomainError {
+    fn from(e: CustomerError) -> Self {
+        DomainError::Customer(e)
+    }
+}
+
+/// Service for managing customers.
+pub struct CustomerService<S: EventStore> {
+    handler: CommandHandler<S, Customer>,
+}
+
+impl<S: EventStore> CustomerService<S> {
+    /// Creates a new customer service with the given event store.
+    pub fn new(store: S) -> Self {
+        Self {
+            handler: CommandHandler::new(store),
+        }
+    }
+
+    /// Records command decisions in `log`.
+    pub fn with_decision_log(mut self, log: DecisionLog) -> Self {
+        self.handler = self.handler.with_decision_log(log);
+        self
+    }
+
+    /// Counts reads of deprecated event schema versions in `deprecations`.
+    pub fn with_schema_deprecations(mut self, deprecations: SchemaDeprecations) -> Self {
+        self.handler = self.handler.with_schema_deprecations(deprecations);
+        self
+    }
+
+    /// Builds this service's event envelopes with `factory`.
+    pub fn with_envelope_factory(mut self, factory: EnvelopeFactory) -> Self {
+        self.handler = self.handler.with_envelope_factory(factory);
+        self
+    }
+
+    /// Returns a reference to the underlying command handler.
+    pub fn handler(&self) -> &CommandHandler<S, Customer> {
+        &self.handler
+    }
+
+    /// Registers a customer.
+    #[tracing::instrument(skip(self, cmd), fields(customer_id = %cmd.customer_id))]
+    pub async fn register_customer(
+        &self,
+        cmd: RegisterCustomer,
+    ) -> Result<CommandResult<Customer>, DomainError> {
+        let customer_id = cmd.customer_id;
+        self.handler
+            .execute_named(cmd.name(), cmd.aggregate_id(), |customer| {
+                customer.register(customer_id, cmd.contact)
+            })
+            .await
+    }
+
+    /// Replaces a registered customer's contact info.
+    #[tracing::instrument(skip(self, cmd), fields(customer_id = %cmd.customer_id))]
+    pub async fn update_contact_info(
+        &self,
+        cmd: UpdateContactInfo,
+    ) -> Result<CommandResult<Customer>, DomainError> {
+        let customer_id = cmd.customer_id;
+        self.handler
+            .execute_named(cmd.name(), cmd.aggregate_id(), |customer| {
+                customer.update_contact(customer_id, cmd.contact)
+            })
+            .await
+    }
+
+    /// Deactivates a registered customer.
+    #[tracing::instrument(skip(self))]
+    pub async fn deactivate_customer(
+        &self,
+        cmd: DeactivateCustomer,
+    ) -> Result<CommandResult<Customer>, DomainError> {
+        let customer_id = cmd.customer_id;
+        self.handler
+            .execute_named(cmd.name(), cmd.aggregate_id(), |customer| {
+                customer.deactivate(customer_id, cmd.reason)
+            })
+            .await
+    }
+
+    /// Merges a duplicate customer into a surviving customer.
+    ///
+    /// If the target was itself merged, the source is linked directly to the
+    /// final surviving customer.
+    #[tracing::instrument(skip(self))]
+    pub async fn merge_customers(
+        &self,
+        cmd: MergeCustomers,
+    ) -> Result<CommandResult<Customer>, DomainError> {
+        let source = cmd.source;
+        let target = self.resolve(cmd.target).await?;
+        let merged_by = cmd.merged_by.clone();
+
+        self.handler
+            .execute_named(cmd.name(), Customer::stream_id(source), |customer| {
+                customer.merge_into(source, target, merged_by)
+            })
+            .await
+    }
+
+    /// Resolves a customer ID to the surviving customer after any merges.
+    ///
+    /// Returns the ID unchanged if the customer was never merged.
+    #[tracing::instrument(skip(self))]
+    pub async fn resolve(&self, customer_id: CustomerId) -> Result<CustomerId, DomainError> {
+        let mut current = customer_id;
+        for _ in 0..MAX_MERGE_DEPTH {
+            match self
+                .get_customer(current)
+                .await?
+                .and_then(|c| c.merged_into())
+            {
+                Some(next) => current = next,
+                None => return Ok(current),
+            }
+        }
+        Ok(current)
+    }
+
+    /// Loads a customer by ID.
+    ///
+    /// Returns None if the customer has no stream.
+    #[tracing::instrument(skip(self))]
+    pub async fn get_customer(
+        &self,
+        customer_id: CustomerId,
+    ) -> Result<Option<Customer>, DomainError> {
+        self.handler
+            .load_existing(Customer::stream_id(customer_id))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aggregate::Aggregate;
+    use crate::customer::ContactInfo;
+    use event_store::InMemoryEventStore;
+
+    #[tokio::test]
+    async fn test_register_update_and_deactivate() {
+        let service = CustomerService::new(InMemoryEventStore::new());
+        let cmd = RegisterCustomer::with_contact(ContactInfo::new("Ada", "ada@example.com"));
+        let id = cmd.customer_id;
+        service.register_customer(cmd).await.unwrap();
+
+        let contact = ContactInfo::new("Ada", "ada@example.org");
+        service
+            .update_contact_info(UpdateContactInfo::new(id, contact.clone()))
+            .await
+            .unwrap();
+        service
+            .deactivate_customer(DeactivateCustomer::new(id, None))
+            .await
+            .unwrap();
+
+        let customer = service.get_customer(id).await.unwrap().unwrap();
+        assert_eq!(customer.contact(), Some(&contact));
+        assert!(customer.is_deactivated());
+        assert_eq!(customer.version(), event_store::Version::new(3));
+    }
+
+    #[tokio::test]
+    async fn test_merge_and_resolve() {
+        let service = CustomerService::new(InMemoryEventStore::new());
+        let source = CustomerId::new();
+        let target = CustomerId::new();
+
+        service
+            .merge_customers(MergeCustomers::new(source, target, None))
+            .await
+            .unwrap();
+
+        assert_eq!(service.resolve(source).await.unwrap(), target);
+        assert_eq!(service.resolve(target).await.unwrap(), target);
+    }
+
+    #[tokio::test]
+    async fn test_merge_into_merged_customer_links_to_survivor() {
+        let service = CustomerService::new(InMemoryEventStore::new());
+        let a = CustomerId::new();
+        let b = CustomerId::new();
+        let c = CustomerId::new();
+
+        service
+            .merge_customers(MergeCustomers::new(b, c, None))
+            .await
+            .unwrap();
+        let result = service
+            .merge_customers(MergeCustomers::new(a, b, None))
+            .await
+            .unwrap();
+
+        assert_eq!(result.aggregate.merged_into(), Some(c));
+        assert_eq!(service.resolve(a).await.unwrap(), c);
+    }
+
+    #[tokio::test]
+    async fn test_merge_cycle_rejected() {
+        let service = CustomerService::new(InMemoryEventStore::new());
+        let a = CustomerId::new();
+        let b = CustomerId::new();
+
+        service
+            .merge_customers(MergeCustomers::new(a, b, None))
+            .await
+            .unwrap();
+
+        // b -> a resolves to b -> b
+        let result = service
+            .merge_customers(MergeCustomers::new(b, a, None))
+            .await;
+        assert!(matches!(
+            result,
+            Err(DomainError::Customer(CustomerError::SelfMerge { .. }))
+        ));
+    }
+}
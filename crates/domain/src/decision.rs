@@ -0,0 +1,225 @@
+//! Structured records of command decisions.
+//!
+//! The command handler records every command it executes: which command ran
+//! against which aggregate, how it was decided, and either the events it
+//! emitted or the code of the invariant it violated. Records are logged with
+//! the same field names every time so they can be filtered and aggregated.
+//! A [`DecisionLog`] with a rejection buffer also keeps the most recent
+//! rejections in memory as diagnostics; they are never persisted.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use common::AggregateId;
+use serde::Serialize;
+
+/// How a command was decided.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DecisionOutcome {
+    /// The command emitted events and they were persisted.
+    Accepted,
+    /// The command was valid but had nothing to change.
+    Unchanged,
+    /// The command had already been processed under its command ID, and
+    /// its original result was returned.
+    Replayed,
+    /// The command violated a domain invariant or policy.
+    Rejected,
+    /// The command could not be decided or persisted, e.g. on a concurrency
+    /// conflict or store error.
+    Failed,
+}
+
+impl DecisionOutcome {
+    /// Returns the outcome's name as logged.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DecisionOutcome::Accepted => "accepted",
+            DecisionOutcome::Unchanged => "unchanged",
+            DecisionOutcome::Replayed => "replayed",
+            DecisionOutcome::Rejected => "rejected",
+            DecisionOutcome::Failed => "failed",
+        }
+    }
+}
+
+/// One command execution and its outcome.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Decision {
+    pub recorded_at: DateTime<Utc>,
+    /// Name of the command, if the caller gave one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command: Option<&'static str>,
+    pub aggregate_type: &'static str,
+    pub aggregate_id: AggregateId,
+    pub outcome: DecisionOutcome,
+    /// Code of the violated invariant, for rejections.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub invariant: Option<&'static str>,
+    /// Error message, for rejections and failures.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Types of the events emitted, in order, for accepted commands.
+    pub event_types: Vec<&'static str>,
+}
+
+#[derive(Debug)]
+struct RejectionBuffer {
+    capacity: usize,
+    entries: VecDeque<Decision>,
+}
+
+/// Logs command decisions, optionally keeping recent rejections.
+///
+/// Cloning shares the same buffer, so one log can be attached to several
+/// command handlers and read from elsewhere.
+#[derive(Debug, Clone, Default)]
+pub struct DecisionLog {
+    rejections: Option<Arc<Mutex<RejectionBuffer>>>,
+}
+
+impl DecisionLog {
+    /// Creates a log that only writes to tracing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a log that also keeps the last `capacity` rejections.
+    pub fn with_rejection_buffer(capacity: usize) -> Self {
+        Self {
+            rejections: Some(Arc::new(Mutex::new(RejectionBuffer {
+                capacity,
+                entries: VecDeque::with_capacity(capacity),
+            }))),
+        }
+    }
+
+    /// Returns true if rejections are kept in memory.
+    pub fn buffers_rejections(&self) -> bool {
+        self.rejections.is_some()
+    }
+
+    /// Logs a decision, buffering it if it is a rejection.
+    pub fn record(&self, decision: Decision) {
+        match decision.outcome {
+            DecisionOutcome::Accepted | DecisionOutcome::Unchanged | DecisionOutcome::Replayed => {
+                tracing::info!(
+                    command = decision.command,
+                    aggregate_type = decision.aggregate_type,
+                    aggregate_id = %decision.aggregate_id,
+                    outcome = decision.outcome.as_str(),
+                    event_types = ?decision.event_types,
+                    "command decided"
+                )
+            }
+            DecisionOutcome::Rejected => tracing::info!(
+                command = decision.command,
+                aggregate_type = decision.aggregate_type,
+                aggregate_id = %decision.aggregate_id,
+                outcome = decision.outcome.as_str(),
+                invariant = decision.invariant,
+                reason = decision.reason.as_deref(),
+                "command decided"
+            ),
+            DecisionOutcome::Failed => tracing::warn!(
+                command = decision.command,
+                aggregate_type = decision.aggregate_type,
+                aggregate_id = %decision.aggregate_id,
+                outcome = decision.outcome.as_str(),
+                reason = decision.reason.as_deref(),
+                "command decided"
+            ),
+        }
+
+        if decision.outcome != DecisionOutcome::Rejected {
+            return;
+        }
+        if let Some(buffer) = &self.rejections {
+            let mut buffer = buffer.lock().unwrap();
+            if buffer.capacity == 0 {
+                return;
+            }
+            if buffer.entries.len() == buffer.capacity {
+                buffer.entries.pop_front();
+            }
+            buffer.entries.push_back(decision);
+        }
+    }
+
+    /// Returns up to `limit` buffered rejections, newest first.
+    pub fn recent_rejections(&self, limit: usize) -> Vec<Decision> {
+        self.rejections
+            .as_ref()
+            .map(|buffer| {
+                buffer
+                    .lock()
+                    .unwrap()
+                    .entries
+                    .iter()
+                    .rev()
+                    .take(limit)
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decision(outcome: DecisionOutcome, invariant: Option<&'static str>) -> Decision {
+        Decision {
+            recorded_at: Utc::now(),
+            command: Some("AddItem"),
+            aggregate_type: "Order",
+            aggregate_id: AggregateId::new(),
+            outcome,
+            invariant,
+            reason: None,
+            event_types: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_buffers_only_rejections() {
+        let log = DecisionLog::with_rejection_buffer(10);
+        log.record(decision(DecisionOutcome::Accepted, None));
+        log.record(decision(DecisionOutcome::Failed, None));
+        log.record(decision(
+            DecisionOutcome::Rejected,
+            Some("order.invalid_quantity"),
+        ));
+
+        let rejections = log.recent_rejections(10);
+        assert_eq!(rejections.len(), 1);
+        assert_eq!(rejections[0].invariant, Some("order.invalid_quantity"));
+    }
+
+    #[test]
+    fn test_buffer_drops_oldest_rejections() {
+        let log = DecisionLog::with_rejection_buffer(2);
+        log.record(decision(DecisionOutcome::Rejected, Some("first")));
+        log.record(decision(DecisionOutcome::Rejected, Some("second")));
+        log.record(decision(DecisionOutcome::Rejected, Some("third")));
+
+        let invariants: Vec<_> = log
+            .recent_rejections(10)
+            .into_iter()
+            .map(|d| d.invariant.unwrap())
+            .collect();
+        assert_eq!(invariants, ["third", "second"]);
+        assert_eq!(log.recent_rejections(1).len(), 1);
+    }
+
+    #[test]
+    fn test_log_without_buffer_keeps_nothing() {
+        let log = DecisionLog::new();
+        log.record(decision(DecisionOutcome::Rejected, Some("order.no_items")));
+        assert!(!log.buffers_rejections());
+        assert!(log.recent_rejections(10).is_empty());
+    }
+}
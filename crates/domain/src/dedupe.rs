@@ -0,0 +1,48 @@
+//! Content hashing of command payloads for duplicate detection.
+//!
+//! Commands that opt in stamp the hash of their payload on the events they
+//! append, under [`PAYLOAD_HASH_KEY`]. A later command with the same hash on
+//! the same aggregate is a repeat of the earlier one, e.g. a double-clicked
+//! submit, and can be refused within a dedupe window.
+
+use sha2::{Digest, Sha256};
+
+/// Metadata key holding the hash of the command payload behind an event.
+pub const PAYLOAD_HASH_KEY: &str = "payload_hash";
+
+/// Computes content hashes of serialized command payloads.
+///
+/// Hashes are compared against those stored on earlier events, so an
+/// implementation must produce the same hash for the same bytes across
+/// processes and releases.
+pub trait PayloadHasher: Send + Sync {
+    /// Returns the hash of `payload` as a string.
+    fn hash(&self, payload: &[u8]) -> String;
+}
+
+/// Hex-encoded SHA-256 hashing, the default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sha256PayloadHasher;
+
+impl PayloadHasher for Sha256PayloadHasher {
+    fn hash(&self, payload: &[u8]) -> String {
+        hex::encode(Sha256::digest(payload))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sha256_hash_is_stable() {
+        assert_eq!(
+            Sha256PayloadHasher.hash(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_ne!(
+            Sha256PayloadHasher.hash(b"abc"),
+            Sha256PayloadHasher.hash(b"abd")
+        );
+    }
+}
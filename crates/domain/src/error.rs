@@ -3,7 +3,12 @@
 use event_store::EventStoreError;
 use thiserror::Error;
 
+use crate::aggregate::Aggregate;
+use crate::customer::CustomerError;
+use crate::feature_flag::FeatureFlagError;
+use crate::inventory::InventoryError;
 use crate::order::OrderError;
+use crate::payment::PaymentError;
 
 /// Errors that can occur during domain operations.
 #[derive(Debug, Error)]
@@ -16,6 +21,30 @@ pub enum DomainError {
     #[error("Order error: {0}")]
     Order(OrderError),
 
+    /// An error occurred in the customer aggregate.
+    #[error("Customer error: {0}")]
+    Customer(CustomerError),
+
+    /// An error occurred in a feature flag.
+    #[error("Feature flag error: {0}")]
+    FeatureFlag(FeatureFlagError),
+
+    /// An error occurred in an inventory item.
+    #[error("Inventory error: {0}")]
+    Inventory(InventoryError),
+
+    /// An error occurred in a payment.
+    #[error("Payment error: {0}")]
+    Payment(PaymentError),
+
+    /// A command was rejected by an aggregate defined outside this crate.
+    #[error("{aggregate_type} error: {message}")]
+    Rejected {
+        aggregate_type: &'static str,
+        code: &'static str,
+        message: String,
+    },
+
     /// Aggregate not found.
     #[error("Aggregate not found: {aggregate_type} with id {aggregate_id}")]
     AggregateNotFound {
@@ -26,4 +55,47 @@ pub enum DomainError {
     /// Serialization error.
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
+
+    /// The command lacked metadata required by the handler's policy.
+    #[error("Missing required metadata: {}", keys.join(", "))]
+    MissingMetadata { keys: Vec<String> },
+
+    /// The command's ID was already used by a command on another aggregate.
+    #[error("Command ID {command_id} was already used on aggregate {aggregate_id}")]
+    CommandIdReused {
+        command_id: String,
+        aggregate_id: String,
+    },
+
+    /// The caller cancelled the command before it was persisted.
+    #[error("Command cancelled")]
+    Cancelled,
+}
+
+impl DomainError {
+    /// Wraps a rejection by an aggregate defined outside this crate, which
+    /// has no variant of its own. `code` names the violated invariant.
+    pub fn rejected<A: Aggregate>(code: &'static str, err: impl std::fmt::Display) -> Self {
+        DomainError::Rejected {
+            aggregate_type: A::aggregate_type(),
+            code,
+            message: err.to_string(),
+        }
+    }
+
+    /// Returns the code of the violated invariant, or None if the error
+    /// isn't a rejection by the domain (e.g. a store failure).
+    pub fn invariant(&self) -> Option<&'static str> {
+        match self {
+            DomainError::Order(e) => Some(e.code()),
+            DomainError::Customer(e) => Some(e.code()),
+            DomainError::FeatureFlag(e) => Some(e.code()),
+            DomainError::Inventory(e) => Some(e.code()),
+            DomainError::Payment(e) => Some(e.code()),
+            DomainError::Rejected { code, .. } => Some(code),
+            DomainError::MissingMetadata { .. } => Some("metadata.missing"),
+            DomainError::CommandIdReused { .. } => Some("command.id_reused"),
+            _ => None,
+        }
+    }
 }
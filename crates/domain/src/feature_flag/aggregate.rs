@@ -0,0 +1,216 @@
+//! Feature flag aggregate implementation.
+
+use std::collections::HashMap;
+
+use common::AggregateId;
+use event_store::Version;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::aggregate::Aggregate;
+use crate::order::CustomerId;
+
+use super::{FeatureFlagError, FeatureFlagEvent, FlagContext, FlagScope, events::FlagSetData};
+
+/// Namespace for deriving flag stream IDs from flag names.
+const FLAG_NAMESPACE: Uuid = Uuid::from_u128(0x6f1c_2a43_8d5e_4b7a_9c01_f3e2_d4b5_a697);
+
+/// Feature flag aggregate root.
+///
+/// Evaluation precedence: customer override, then segment override, then the
+/// global setting. Unknown flags are off.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FeatureFlag {
+    /// Unique stream identifier.
+    id: Option<AggregateId>,
+
+    /// Current version for optimistic concurrency.
+    #[serde(default)]
+    version: Version,
+
+    /// The flag name.
+    name: String,
+
+    /// Global setting.
+    global: Option<bool>,
+
+    /// Per-segment overrides.
+    segments: HashMap<String, bool>,
+
+    /// Per-customer overrides.
+    customers: HashMap<CustomerId, bool>,
+}
+
+impl Aggregate for FeatureFlag {
+    type Event = FeatureFlagEvent;
+    type Error = FeatureFlagError;
+
+    fn aggregate_type() -> &'static str {
+        "FeatureFlag"
+    }
+
+    fn id(&self) -> Option<AggregateId> {
+        self.id
+    }
+
+    fn version(&self) -> Version {
+        self.version
+    }
+
+    fn set_version(&mut self, version: Version) {
+        self.version = version;
+    }
+
+    fn apply(&mut self, event: Self::Event) {
+        match event {
+            FeatureFlagEvent::FlagSet(data) => self.apply_flag_set(data),
+        }
+    }
+}
+
+// Query methods
+impl FeatureFlag {
+    /// Returns the stream ID for a flag name.
+    pub fn stream_id(name: &str) -> AggregateId {
+        AggregateId::from_uuid(Uuid::new_v5(&FLAG_NAMESPACE, name.as_bytes()))
+    }
+
+    /// Returns the flag name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the global setting, if set.
+    pub fn global(&self) -> Option<bool> {
+        self.global
+    }
+
+    /// Returns the per-segment overrides.
+    pub fn segments(&self) -> &HashMap<String, bool> {
+        &self.segments
+    }
+
+    /// Returns the per-customer overrides.
+    pub fn customers(&self) -> &HashMap<CustomerId, bool> {
+        &self.customers
+    }
+
+    /// Returns true if the flag is on for the given caller.
+    pub fn is_enabled(&self, ctx: &FlagContext) -> bool {
+        ctx.customer_id
+            .and_then(|id| self.customers.get(&id).copied())
+            .or_else(|| {
+                ctx.segment
+                    .as_ref()
+                    .and_then(|s| self.segments.get(s).copied())
+            })
+            .or(self.global)
+            .unwrap_or(false)
+    }
+}
+
+// Command methods (return events)
+impl FeatureFlag {
+    /// Turns the flag on or off for a scope.
+    pub fn set(
+        &self,
+        name: &str,
+        scope: FlagScope,
+        enabled: bool,
+        changed_by: Option<String>,
+    ) -> Result<Vec<FeatureFlagEvent>, FeatureFlagError> {
+        let valid = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "_-.".contains(c));
+        if !valid {
+            return Err(FeatureFlagError::InvalidName {
+                name: name.to_string(),
+            });
+        }
+
+        Ok(vec![FeatureFlagEvent::flag_set(
+            name, scope, enabled, changed_by,
+        )])
+    }
+}
+
+// Apply event helpers
+impl FeatureFlag {
+    fn apply_flag_set(&mut self, data: FlagSetData) {
+        self.id = Some(Self::stream_id(&data.name));
+        self.name = data.name;
+        match data.scope {
+            FlagScope::Global => self.global = Some(data.enabled),
+            FlagScope::Segment(segment) => {
+                self.segments.insert(segment, data.enabled);
+            }
+            FlagScope::Customer(customer_id) => {
+                self.customers.insert(customer_id, data.enabled);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flag_with(settings: &[(FlagScope, bool)]) -> FeatureFlag {
+        let mut flag = FeatureFlag::default();
+        for (scope, enabled) in settings {
+            let events = flag.set("returns", scope.clone(), *enabled, None).unwrap();
+            flag.apply_events(events);
+        }
+        flag
+    }
+
+    #[test]
+    fn test_unknown_flag_is_off() {
+        let flag = FeatureFlag::default();
+        assert!(!flag.is_enabled(&FlagContext::default()));
+    }
+
+    #[test]
+    fn test_global_setting() {
+        let flag = flag_with(&[(FlagScope::Global, true)]);
+        assert_eq!(flag.name(), "returns");
+        assert_eq!(flag.id(), Some(FeatureFlag::stream_id("returns")));
+        assert!(flag.is_enabled(&FlagContext::default()));
+    }
+
+    #[test]
+    fn test_precedence() {
+        let vip = CustomerId::new();
+        let flag = flag_with(&[
+            (FlagScope::Global, false),
+            (FlagScope::Segment("beta".into()), true),
+            (FlagScope::Customer(vip), false),
+        ]);
+
+        assert!(!flag.is_enabled(&FlagContext::default()));
+        assert!(
+            flag.is_enabled(&FlagContext::for_customer(CustomerId::new()).with_segment("beta"))
+        );
+        assert!(!flag.is_enabled(&FlagContext::for_customer(vip).with_segment("beta")));
+    }
+
+    #[test]
+    fn test_invalid_name_rejected() {
+        let flag = FeatureFlag::default();
+        let result = flag.set("Bad Name", FlagScope::Global, true, None);
+        assert!(matches!(result, Err(FeatureFlagError::InvalidName { .. })));
+    }
+
+    #[test]
+    fn test_stream_id_is_stable() {
+        assert_eq!(
+            FeatureFlag::stream_id("returns"),
+            FeatureFlag::stream_id("returns")
+        );
+        assert_ne!(
+            FeatureFlag::stream_id("returns"),
+            FeatureFlag::stream_id("approvals")
+        );
+    }
+}
@@ -0,0 +1,49 @@
+//! Feature flag commands.
+
+use common::AggregateId;
+
+use crate::command::Command;
+
+use super::{FeatureFlag, FlagScope};
+
+/// Command to turn a flag on or off for a scope.
+#[derive(Debug, Clone)]
+pub struct SetFeatureFlag {
+    /// The flag name.
+    pub name: String,
+
+    /// Who the setting applies to.
+    pub scope: FlagScope,
+
+    /// Whether the flag is on.
+    pub enabled: bool,
+
+    /// Who changed the flag.
+    pub changed_by: Option<String>,
+}
+
+impl SetFeatureFlag {
+    /// Creates a new SetFeatureFlag command.
+    pub fn new(name: impl Into<String>, scope: FlagScope, enabled: bool) -> Self {
+        Self {
+            name: name.into(),
+            scope,
+            enabled,
+            changed_by: None,
+        }
+    }
+
+    /// Records who changed the flag.
+    pub fn changed_by(mut self, changed_by: impl Into<String>) -> Self {
+        self.changed_by = Some(changed_by.into());
+        self
+    }
+}
+
+impl Command for SetFeatureFlag {
+    type Aggregate = FeatureFlag;
+
+    fn aggregate_id(&self) -> AggregateId {
+        FeatureFlag::stream_id(&self.name)
+    }
+}
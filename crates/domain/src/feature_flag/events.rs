@@ -0,0 +1,62 @@
+//! Feature flag domain events.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::aggregate::DomainEvent;
+
+use super::FlagScope;
+
+/// Events that can occur on a feature flag.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data")]
+pub enum FeatureFlagEvent {
+    /// Flag was turned on or off for a scope.
+    FlagSet(FlagSetData),
+}
+
+impl DomainEvent for FeatureFlagEvent {
+    fn event_type(&self) -> &'static str {
+        match self {
+            FeatureFlagEvent::FlagSet(_) => "FeatureFlagSet",
+        }
+    }
+}
+
+/// Data for FlagSet event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlagSetData {
+    /// The flag name.
+    pub name: String,
+
+    /// Who the setting applies to.
+    pub scope: FlagScope,
+
+    /// Whether the flag is on.
+    pub enabled: bool,
+
+    /// Who changed the flag.
+    pub changed_by: Option<String>,
+
+    /// When the flag was changed.
+    pub changed_at: DateTime<Utc>,
+}
+
+// Convenience constructors for events
+impl FeatureFlagEvent {
+    /// Creates a FlagSet event.
+    pub fn flag_set(
+        name: impl Into<String>,
+        scope: FlagScope,
+        enabled: bool,
+        changed_by: Option<String>,
+    ) -> Self {
+        FeatureFlagEvent::FlagSet(FlagSetData {
+            name: name.into(),
+            scope,
+            enabled,
+            changed_by,
+            changed_at: Utc::now(),
+        })
+    }
+}
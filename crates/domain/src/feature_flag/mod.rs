@@ -0,0 +1,82 @@
+//! Feature flags for gradually rolling out new capabilities.
+//!
+//! Each flag is its own event stream, so flags live in the same store as
+//! everything else (Postgres in production) and every change is audited.
+
+mod aggregate;
+mod commands;
+mod events;
+mod service;
+
+pub use aggregate::FeatureFlag;
+pub use commands::*;
+pub use events::{FeatureFlagEvent, FlagSetData};
+pub use service::FeatureFlagService;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::order::CustomerId;
+
+/// Names of flags checked by the system.
+pub mod flags {
+    /// Allows creating orders in a currency other than the default.
+    pub const MULTI_CURRENCY: &str = "multi_currency";
+}
+
+/// Who a flag setting applies to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum FlagScope {
+    /// Everyone, unless overridden more specifically.
+    Global,
+
+    /// A named customer segment or tenant.
+    Segment(String),
+
+    /// A single customer.
+    Customer(CustomerId),
+}
+
+/// The caller a flag is evaluated for.
+#[derive(Debug, Clone, Default)]
+pub struct FlagContext {
+    /// The customer making the request, if known.
+    pub customer_id: Option<CustomerId>,
+
+    /// The customer's segment or tenant, if known.
+    pub segment: Option<String>,
+}
+
+impl FlagContext {
+    /// Creates a context for a customer.
+    pub fn for_customer(customer_id: CustomerId) -> Self {
+        Self {
+            customer_id: Some(customer_id),
+            segment: None,
+        }
+    }
+
+    /// Sets the segment.
+    pub fn with_segment(mut self, segment: impl Into<String>) -> Self {
+        self.segment = Some(segment.into());
+        self
+    }
+}
+
+/// Errors that can occur during feature flag operations.
+#[derive(Debug, Error)]
+pub enum FeatureFlagError {
+    /// Flag name is empty or contains unsupported characters.
+    #[error("Invalid flag name: {name} (use lowercase letters, digits, '_', '-' or '.')")]
+    InvalidName { name: String },
+}
+
+impl FeatureFlagError {
+    /// Returns a stable code naming the violated invariant.
+    pub fn code(&self) -> &'static str {
+        match self {
+            FeatureFlagError::InvalidName { .. } => "feature_flag.invalid_name",
+        }
+    }
+}
@@ -0,0 +1,210 @@
+//! Feature flag service with a read-through cache.
+
+use std::collections::{BTreeSet, HashMap};
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+use event_store::{EnvelopeFactory, EventStore, SchemaDeprecations};
+
+use crate::command::{Command, CommandHandler, CommandResult};
+use crate::decision::DecisionLog;
+use crate::error::DomainError;
+
+use super::{FeatureFlag, FeatureFlagError, FlagContext, SetFeatureFlag};
+
+/// Default time a cached flag is trusted before reloading it from the store.
+const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(30);
+
+impl From<FeatureFlagError> for DomainError {
+    fn from(e: FeatureFlagError) -> Self {
+        DomainError::FeatureFlag(e)
+    }
+}
+
+/// Service for reading and toggling feature flags.
+///
+/// Flag lookups are cached per process; changes made through this service
+/// are visible immediately, changes made by other processes within the TTL.
+pub struct FeatureFlagService<S: EventStore> {
+    handler: CommandHandler<S, FeatureFlag>,
+    cache: RwLock<HashMap<String, (Instant, FeatureFlag)>>,
+    ttl: Duration,
+}
+
+impl<S: EventStore> FeatureFlagService<S> {
+    /// Creates a new feature flag service with the given event store.
+    pub fn new(store: S) -> Self {
+        Self {
+            handler: CommandHandler::new(store),
+            cache: RwLock::new(HashMap::new()),
+            ttl: DEFAULT_CACHE_TTL,
+        }
+    }
+
+    /// Records command decisions in `log`.
+    pub fn with_decision_log(mut self, log: DecisionLog) -> Self {
+        self.handler = self.handler.with_decision_log(log);
+        self
+    }
+
+    /// Counts reads of deprecated event schema versions in `deprecations`.
+    pub fn with_schema_deprecations(mut self, deprecations: SchemaDeprecations) -> Self {
+        self.handler = self.handler.with_schema_deprecations(deprecations);
+        self
+    }
+
+    /// Builds this service's event envelopes with `factory`.
+    pub fn with_envelope_factory(mut self, factory: EnvelopeFactory) -> Self {
+        self.handler = self.handler.with_envelope_factory(factory);
+        self
+    }
+
+    /// Sets how long cached flags are trusted.
+    pub fn with_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Turns a flag on or off for a scope.
+    #[tracing::instrument(skip(self))]
+    pub async fn set_flag(
+        &self,
+        cmd: SetFeatureFlag,
+    ) -> Result<CommandResult<FeatureFlag>, DomainError> {
+        let name = cmd.name.clone();
+        let result = self
+            .handler
+            .execute_named(cmd.name(), FeatureFlag::stream_id(&cmd.name), |flag| {
+                flag.set(&cmd.name, cmd.scope, cmd.enabled, cmd.changed_by)
+            })
+            .await?;
+
+        self.cache_put(name, result.aggregate.clone());
+        Ok(result)
+    }
+
+    /// Returns true if the flag is on for the given caller.
+    #[tracing::instrument(skip(self))]
+    pub async fn is_enabled(&self, name: &str, ctx: &FlagContext) -> Result<bool, DomainError> {
+        Ok(self.get_flag(name).await?.is_enabled(ctx))
+    }
+
+    /// Loads a flag, using the cache when fresh.
+    ///
+    /// Unknown flags load as an empty (off) flag.
+    pub async fn get_flag(&self, name: &str) -> Result<FeatureFlag, DomainError> {
+        if let Some(flag) = self.cache_get(name) {
+            return Ok(flag);
+        }
+
+        let flag = self.handler.load(FeatureFlag::stream_id(name)).await?;
+        self.cache_put(name.to_string(), flag.clone());
+        Ok(flag)
+    }
+
+    /// Lists every flag that has ever been set.
+    pub async fn list_flags(&self) -> Result<Vec<FeatureFlag>, DomainError> {
+        let events = self
+            .handler
+            .store()
+            .get_events_by_type("FeatureFlagSet")
+            .await?;
+
+        let names: BTreeSet<String> = events
+            .iter()
+            .filter_map(|e| e.payload.get("data")?.get("name")?.as_str())
+            .map(String::from)
+            .collect();
+
+        let mut flags = Vec::with_capacity(names.len());
+        for name in names {
+            flags.push(self.get_flag(&name).await?);
+        }
+        Ok(flags)
+    }
+
+    /// Drops all cached flags.
+    pub fn invalidate_cache(&self) {
+        if let Ok(mut cache) = self.cache.write() {
+            cache.clear();
+        }
+    }
+
+    fn cache_get(&self, name: &str) -> Option<FeatureFlag> {
+        let cache = self.cache.read().ok()?;
+        let (loaded_at, flag) = cache.get(name)?;
+        (loaded_at.elapsed() < self.ttl).then(|| flag.clone())
+    }
+
+    fn cache_put(&self, name: String, flag: FeatureFlag) {
+        if let Ok(mut cache) = self.cache.write() {
+            cache.insert(name, (Instant::now(), flag));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::feature_flag::FlagScope;
+    use crate::order::CustomerId;
+    use event_store::InMemoryEventStore;
+
+    #[tokio::test]
+    async fn test_set_and_check_flag() {
+        let service = FeatureFlagService::new(InMemoryEventStore::new());
+        let ctx = FlagContext::for_customer(CustomerId::new());
+
+        assert!(!service.is_enabled("returns", &ctx).await.unwrap());
+
+        service
+            .set_flag(SetFeatureFlag::new("returns", FlagScope::Global, true))
+            .await
+            .unwrap();
+
+        assert!(service.is_enabled("returns", &ctx).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_cache_picks_up_external_changes_after_ttl() {
+        let store = InMemoryEventStore::new();
+        let reader = FeatureFlagService::new(store.clone()).with_cache_ttl(Duration::ZERO);
+        let writer = FeatureFlagService::new(store);
+        let ctx = FlagContext::default();
+
+        assert!(!reader.is_enabled("approvals", &ctx).await.unwrap());
+
+        writer
+            .set_flag(SetFeatureFlag::new("approvals", FlagScope::Global, true))
+            .await
+            .unwrap();
+
+        assert!(reader.is_enabled("approvals", &ctx).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_list_flags() {
+        let service = FeatureFlagService::new(InMemoryEventStore::new());
+
+        service
+            .set_flag(SetFeatureFlag::new("returns", FlagScope::Global, true))
+            .await
+            .unwrap();
+        service
+            .set_flag(
+                SetFeatureFlag::new("partial_shipments", FlagScope::Segment("beta".into()), true)
+                    .changed_by("ops"),
+            )
+            .await
+            .unwrap();
+        service
+            .set_flag(SetFeatureFlag::new("returns", FlagScope::Global, false))
+            .await
+            .unwrap();
+
+        let flags = service.list_flags().await.unwrap();
+        let names: Vec<_> = flags.iter().map(|f| f.name()).collect();
+        assert_eq!(names, vec!["partial_shipments", "returns"]);
+        assert_eq!(flags[1].global(), Some(false));
+    }
+}
@@ -0,0 +1,351 @@
+//! Inventory item aggregate implementation.
+
+use std::collections::BTreeMap;
+
+use common::AggregateId;
+use event_store::Version;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::aggregate::Aggregate;
+use crate::order::ProductId;
+
+use super::events::{StockAdjustedData, StockReleasedData, StockReservedData, StockRestockedData};
+use super::{InventoryError, InventoryEvent};
+
+/// Namespace for deriving inventory stream IDs from product IDs.
+const INVENTORY_NAMESPACE: Uuid = Uuid::from_u128(0x2b7e_91d4_5c3a_4f86_a1e0_7d9c_3b58_e624);
+
+/// Inventory item aggregate root.
+///
+/// One stream per product, keyed by a name-based UUID of the product ID.
+/// Tracks stock received and adjusted, and the reservations held against
+/// it, refusing reservations beyond the unreserved stock. A reservation
+/// stays held once its order ships, so shipped units count as reserved
+/// here; the stock levels read model tells shipments apart using order
+/// events.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InventoryItem {
+    /// Unique stream identifier.
+    id: Option<AggregateId>,
+
+    /// Current version for optimistic concurrency.
+    #[serde(default)]
+    version: Version,
+
+    /// The product this stream tracks.
+    product_id: Option<ProductId>,
+
+    /// Total units received over the item's lifetime.
+    total_received: u64,
+
+    /// Net units added or removed by adjustments.
+    #[serde(default)]
+    adjusted: i64,
+
+    /// Units held per reservation ID.
+    #[serde(default)]
+    reservations: BTreeMap<String, u32>,
+}
+
+impl Aggregate for InventoryItem {
+    type Event = InventoryEvent;
+    type Error = InventoryError;
+
+    fn aggregate_type() -> &'static str {
+        "InventoryItem"
+    }
+
+    fn id(&self) -> Option<AggregateId> {
+        self.id
+    }
+
+    fn version(&self) -> Version {
+        self.version
+    }
+
+    fn set_version(&mut self, version: Version) {
+        self.version = version;
+    }
+
+    fn apply(&mut self, event: Self::Event) {
+        match event {
+            InventoryEvent::StockRestocked(data) => self.apply_stock_restocked(data),
+            InventoryEvent::StockReserved(data) => self.apply_stock_reserved(data),
+            InventoryEvent::StockReleased(data) => self.apply_stock_released(data),
+            InventoryEvent::StockAdjusted(data) => self.apply_stock_adjusted(data),
+        }
+    }
+}
+
+// Query methods
+impl InventoryItem {
+    /// Returns the stream ID for a product's inventory.
+    pub fn stream_id(product_id: &ProductId) -> AggregateId {
+        AggregateId::from_uuid(Uuid::new_v5(
+            &INVENTORY_NAMESPACE,
+            product_id.as_str().as_bytes(),
+        ))
+    }
+
+    /// Returns the product ID.
+    pub fn product_id(&self) -> Option<&ProductId> {
+        self.product_id.as_ref()
+    }
+
+    /// Returns a new reservation ID for `product_id`.
+    ///
+    /// The ID starts with the product's stream ID, so
+    /// [`reservation_stream`](Self::reservation_stream) can find the
+    /// reservation again from the ID alone.
+    pub fn new_reservation_id(product_id: &ProductId) -> String {
+        format!(
+            "{}.{}",
+            Self::stream_id(product_id),
+            Uuid::new_v4().simple()
+        )
+    }
+
+    /// Returns the stream holding a reservation made with an ID from
+    /// [`new_reservation_id`](Self::new_reservation_id).
+    pub fn reservation_stream(reservation_id: &str) -> Option<AggregateId> {
+        let (stream, _) = reservation_id.split_once('.')?;
+        Uuid::parse_str(stream).ok().map(AggregateId::from_uuid)
+    }
+
+    /// Returns the total units received.
+    pub fn total_received(&self) -> u64 {
+        self.total_received
+    }
+
+    /// Returns the units in stock: received plus adjustments.
+    pub fn on_hand(&self) -> i64 {
+        self.total_received as i64 + self.adjusted
+    }
+
+    /// Returns the units held by reservations.
+    pub fn reserved(&self) -> u64 {
+        self.reservations.values().map(|q| *q as u64).sum()
+    }
+
+    /// Returns the units that can still be reserved.
+    pub fn available(&self) -> i64 {
+        self.on_hand() - self.reserved() as i64
+    }
+
+    /// Returns the units held by a reservation, if it's held.
+    pub fn reservation(&self, reservation_id: &str) -> Option<u32> {
+        self.reservations.get(reservation_id).copied()
+    }
+}
+
+// Command methods (return events)
+impl InventoryItem {
+    /// Records stock received for the product.
+    pub fn restock(
+        &self,
+        product_id: ProductId,
+        quantity: u32,
+        reference: Option<String>,
+    ) -> Result<Vec<InventoryEvent>, InventoryError> {
+        if quantity == 0 {
+            return Err(InventoryError::InvalidQuantity {
+                product_id,
+                quantity,
+            });
+        }
+
+        Ok(vec![InventoryEvent::stock_restocked(
+            product_id, quantity, reference,
+        )])
+    }
+
+    /// Holds `quantity` units for an order under `reservation_id`.
+    pub fn reserve(
+        &self,
+        product_id: ProductId,
+        reservation_id: String,
+        order_id: AggregateId,
+        quantity: u32,
+    ) -> Result<Vec<InventoryEvent>, InventoryError> {
+        if quantity == 0 {
+            return Err(InventoryError::InvalidQuantity {
+                product_id,
+                quantity,
+            });
+        }
+        if self.reservations.contains_key(&reservation_id) {
+            return Ok(vec![]);
+        }
+
+        let available = self.available();
+        if quantity as i64 > available {
+            return Err(InventoryError::InsufficientStock {
+                product_id,
+                requested: quantity,
+                available,
+            });
+        }
+
+        Ok(vec![InventoryEvent::stock_reserved(
+            product_id,
+            reservation_id,
+            order_id,
+            quantity,
+        )])
+    }
+
+    /// Releases a reservation, returning its units. Releasing one that
+    /// isn't held does nothing, so compensations can be repeated.
+    pub fn release(&self, reservation_id: &str) -> Result<Vec<InventoryEvent>, InventoryError> {
+        let (Some(product_id), Some(quantity)) =
+            (self.product_id.clone(), self.reservation(reservation_id))
+        else {
+            return Ok(vec![]);
+        };
+
+        Ok(vec![InventoryEvent::stock_released(
+            product_id,
+            reservation_id,
+            quantity,
+        )])
+    }
+
+    /// Corrects on-hand stock by `delta` units.
+    pub fn adjust(
+        &self,
+        product_id: ProductId,
+        delta: i64,
+        reason: String,
+    ) -> Result<Vec<InventoryEvent>, InventoryError> {
+        if delta == 0 || self.on_hand() + delta < 0 {
+            return Err(InventoryError::InvalidAdjustment { product_id, delta });
+        }
+
+        Ok(vec![InventoryEvent::stock_adjusted(
+            product_id, delta, reason,
+        )])
+    }
+}
+
+// Apply event helpers
+impl InventoryItem {
+    fn apply_stock_restocked(&mut self, data: StockRestockedData) {
+        self.id = Some(Self::stream_id(&data.product_id));
+        self.product_id = Some(data.product_id);
+        self.total_received += data.quantity as u64;
+    }
+
+    fn apply_stock_reserved(&mut self, data: StockReservedData) {
+        self.id = Some(Self::stream_id(&data.product_id));
+        self.product_id = Some(data.product_id);
+        self.reservations.insert(data.reservation_id, data.quantity);
+    }
+
+    fn apply_stock_released(&mut self, data: StockReleasedData) {
+        self.reservations.remove(&data.reservation_id);
+    }
+
+    fn apply_stock_adjusted(&mut self, data: StockAdjustedData) {
+        self.id = Some(Self::stream_id(&data.product_id));
+        self.product_id = Some(data.product_id);
+        self.adjusted += data.delta;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_restock_accumulates() {
+        let mut item = InventoryItem::default();
+        let product_id = ProductId::new("SKU-001");
+
+        let events = item.restock(product_id.clone(), 10, None).unwrap();
+        item.apply_events(events);
+        let events = item.restock(product_id.clone(), 5, None).unwrap();
+        item.apply_events(events);
+
+        assert_eq!(item.id(), Some(InventoryItem::stream_id(&product_id)));
+        assert_eq!(item.product_id(), Some(&product_id));
+        assert_eq!(item.total_received(), 15);
+    }
+
+    #[test]
+    fn test_restock_zero_fails() {
+        let item = InventoryItem::default();
+        let result = item.restock(ProductId::new("SKU-001"), 0, None);
+        assert!(matches!(
+            result,
+            Err(InventoryError::InvalidQuantity { .. })
+        ));
+    }
+
+    #[test]
+    fn test_reserve_release_and_adjust() {
+        let mut item = InventoryItem::default();
+        let product_id = ProductId::new("SKU-001");
+        let order_id = AggregateId::new();
+        item.apply_events(item.restock(product_id.clone(), 5, None).unwrap());
+
+        let first = InventoryItem::new_reservation_id(&product_id);
+        let events = item
+            .reserve(product_id.clone(), first.clone(), order_id, 3)
+            .unwrap();
+        item.apply_events(events);
+        assert_eq!(item.reserved(), 3);
+        assert_eq!(item.available(), 2);
+        // Reserving under the same ID again holds nothing more
+        assert!(
+            item.reserve(product_id.clone(), first.clone(), order_id, 3)
+                .unwrap()
+                .is_empty()
+        );
+
+        let second = InventoryItem::new_reservation_id(&product_id);
+        assert!(matches!(
+            item.reserve(product_id.clone(), second.clone(), order_id, 3),
+            Err(InventoryError::InsufficientStock { available: 2, .. })
+        ));
+
+        item.apply_events(item.release(&first).unwrap());
+        assert_eq!(item.available(), 5);
+        assert!(item.release(&first).unwrap().is_empty());
+
+        item.apply_events(
+            item.adjust(product_id.clone(), -2, "damaged".into())
+                .unwrap(),
+        );
+        assert_eq!(item.on_hand(), 3);
+        assert_eq!(item.total_received(), 5);
+        assert!(matches!(
+            item.adjust(product_id.clone(), -4, "count".into()),
+            Err(InventoryError::InvalidAdjustment { delta: -4, .. })
+        ));
+        assert!(item.adjust(product_id, 0, "noop".into()).is_err());
+    }
+
+    #[test]
+    fn test_reservation_ids_name_their_stream() {
+        let product_id = ProductId::new("SKU-001");
+        let id = InventoryItem::new_reservation_id(&product_id);
+        assert_eq!(
+            InventoryItem::reservation_stream(&id),
+            Some(InventoryItem::stream_id(&product_id))
+        );
+        assert_eq!(InventoryItem::reservation_stream("RES-0001"), None);
+    }
+
+    #[test]
+    fn test_stream_id_is_stable_per_product() {
+        assert_eq!(
+            InventoryItem::stream_id(&ProductId::new("SKU-001")),
+            InventoryItem::stream_id(&ProductId::new("SKU-001"))
+        );
+        assert_ne!(
+            InventoryItem::stream_id(&ProductId::new("SKU-001")),
+            InventoryItem::stream_id(&ProductId::new("SKU-002"))
+        );
+    }
+}
@@ -0,0 +1,139 @@
+//! Inventory commands.
+
+use common::AggregateId;
+
+use crate::command::Command;
+use crate::order::ProductId;
+
+use super::InventoryItem;
+
+/// Command to record stock received for a product.
+#[derive(Debug, Clone)]
+pub struct RestockItem {
+    /// The product being restocked.
+    pub product_id: ProductId,
+
+    /// Number of units received.
+    pub quantity: u32,
+
+    /// External reference, such as a purchase order number.
+    pub reference: Option<String>,
+}
+
+impl RestockItem {
+    /// Creates a new RestockItem command.
+    pub fn new(product_id: impl Into<ProductId>, quantity: u32, reference: Option<String>) -> Self {
+        Self {
+            product_id: product_id.into(),
+            quantity,
+            reference,
+        }
+    }
+}
+
+impl Command for RestockItem {
+    type Aggregate = InventoryItem;
+
+    fn aggregate_id(&self) -> AggregateId {
+        InventoryItem::stream_id(&self.product_id)
+    }
+}
+
+/// Command to hold stock of a product for an order.
+#[derive(Debug, Clone)]
+pub struct ReserveStock {
+    /// The product to reserve.
+    pub product_id: ProductId,
+
+    /// Identifies the reservation; see [`InventoryItem::new_reservation_id`].
+    pub reservation_id: String,
+
+    /// The order the stock is held for.
+    pub order_id: AggregateId,
+
+    /// Number of units to hold.
+    pub quantity: u32,
+}
+
+impl ReserveStock {
+    /// Creates a command reserving under a new reservation ID.
+    pub fn new(product_id: impl Into<ProductId>, order_id: AggregateId, quantity: u32) -> Self {
+        let product_id = product_id.into();
+        Self {
+            reservation_id: InventoryItem::new_reservation_id(&product_id),
+            product_id,
+            order_id,
+            quantity,
+        }
+    }
+}
+
+impl Command for ReserveStock {
+    type Aggregate = InventoryItem;
+
+    fn aggregate_id(&self) -> AggregateId {
+        InventoryItem::stream_id(&self.product_id)
+    }
+}
+
+/// Command to release a reservation.
+#[derive(Debug, Clone)]
+pub struct ReleaseStock {
+    /// The stream holding the reservation.
+    pub item_id: AggregateId,
+
+    /// The reservation to release.
+    pub reservation_id: String,
+}
+
+impl ReleaseStock {
+    /// Creates a command releasing `reservation_id`, or None if the ID
+    /// doesn't name an inventory stream.
+    pub fn new(reservation_id: impl Into<String>) -> Option<Self> {
+        let reservation_id = reservation_id.into();
+        Some(Self {
+            item_id: InventoryItem::reservation_stream(&reservation_id)?,
+            reservation_id,
+        })
+    }
+}
+
+impl Command for ReleaseStock {
+    type Aggregate = InventoryItem;
+
+    fn aggregate_id(&self) -> AggregateId {
+        self.item_id
+    }
+}
+
+/// Command to correct a product's on-hand stock.
+#[derive(Debug, Clone)]
+pub struct AdjustStock {
+    /// The product to adjust.
+    pub product_id: ProductId,
+
+    /// Units to add (positive) or remove (negative).
+    pub delta: i64,
+
+    /// Why the stock changed.
+    pub reason: String,
+}
+
+impl AdjustStock {
+    /// Creates a new AdjustStock command.
+    pub fn new(product_id: impl Into<ProductId>, delta: i64, reason: impl Into<String>) -> Self {
+        Self {
+            product_id: product_id.into(),
+            delta,
+            reason: reason.into(),
+        }
+    }
+}
+
+impl Command for AdjustStock {
+    type Aggregate = InventoryItem;
+
+    fn aggregate_id(&self) -> AggregateId {
+        InventoryItem::stream_id(&self.product_id)
+    }
+}
@@ -0,0 +1,207 @@
+//! Inventory domain events.
+
+use chrono::{DateTime, Utc};
+use common::AggregateId;
+use serde::{Deserialize, Serialize};
+
+use crate::aggregate::DomainEvent;
+use crate::order::ProductId;
+
+/// Events that can occur on an inventory item.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data")]
+pub enum InventoryEvent {
+    /// Stock was received for a product.
+    StockRestocked(StockRestockedData),
+
+    /// Stock was held for an order.
+    StockReserved(StockReservedData),
+
+    /// A reservation was let go, returning its stock.
+    StockReleased(StockReleasedData),
+
+    /// On-hand stock was corrected, such as after a count.
+    StockAdjusted(StockAdjustedData),
+}
+
+impl DomainEvent for InventoryEvent {
+    fn event_type(&self) -> &'static str {
+        match self {
+            InventoryEvent::StockRestocked(_) => "StockRestocked",
+            InventoryEvent::StockReserved(_) => "StockReserved",
+            InventoryEvent::StockReleased(_) => "StockReleased",
+            InventoryEvent::StockAdjusted(_) => "StockAdjusted",
+        }
+    }
+}
+
+/// Data for StockRestocked event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StockRestockedData {
+    /// The product that was restocked.
+    pub product_id: ProductId,
+
+    /// Number of units received.
+    pub quantity: u32,
+
+    /// External reference, such as a purchase order number.
+    pub reference: Option<String>,
+
+    /// When the stock was received.
+    pub restocked_at: DateTime<Utc>,
+}
+
+/// Data for StockReserved event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StockReservedData {
+    /// The product reserved.
+    pub product_id: ProductId,
+
+    /// Identifies the reservation when it's released.
+    pub reservation_id: String,
+
+    /// The order the stock is held for.
+    pub order_id: AggregateId,
+
+    /// Number of units held.
+    pub quantity: u32,
+
+    /// When the stock was reserved.
+    pub reserved_at: DateTime<Utc>,
+}
+
+/// Data for StockReleased event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StockReleasedData {
+    /// The product released.
+    pub product_id: ProductId,
+
+    /// The reservation let go.
+    pub reservation_id: String,
+
+    /// Number of units returned.
+    pub quantity: u32,
+
+    /// When the reservation was released.
+    pub released_at: DateTime<Utc>,
+}
+
+/// Data for StockAdjusted event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StockAdjustedData {
+    /// The product adjusted.
+    pub product_id: ProductId,
+
+    /// Units added to (positive) or removed from (negative) on-hand stock.
+    pub delta: i64,
+
+    /// Why the stock changed, such as "cycle count" or "damaged".
+    pub reason: String,
+
+    /// When the adjustment was made.
+    pub adjusted_at: DateTime<Utc>,
+}
+
+// Convenience constructors for events
+impl InventoryEvent {
+    /// Creates a StockRestocked event.
+    pub fn stock_restocked(
+        product_id: ProductId,
+        quantity: u32,
+        reference: Option<String>,
+    ) -> Self {
+        InventoryEvent::StockRestocked(StockRestockedData {
+            product_id,
+            quantity,
+            reference,
+            restocked_at: Utc::now(),
+        })
+    }
+
+    /// Creates a StockReserved event.
+    pub fn stock_reserved(
+        product_id: ProductId,
+        reservation_id: impl Into<String>,
+        order_id: AggregateId,
+        quantity: u32,
+    ) -> Self {
+        InventoryEvent::StockReserved(StockReservedData {
+            product_id,
+            reservation_id: reservation_id.into(),
+            order_id,
+            quantity,
+            reserved_at: Utc::now(),
+        })
+    }
+
+    /// Creates a StockReleased event.
+    pub fn stock_released(
+        product_id: ProductId,
+        reservation_id: impl Into<String>,
+        quantity: u32,
+    ) -> Self {
+        InventoryEvent::StockReleased(StockReleasedData {
+            product_id,
+            reservation_id: reservation_id.into(),
+            quantity,
+            released_at: Utc::now(),
+        })
+    }
+
+    /// Creates a StockAdjusted event.
+    pub fn stock_adjusted(product_id: ProductId, delta: i64, reason: impl Into<String>) -> Self {
+        InventoryEvent::StockAdjusted(StockAdjustedData {
+            product_id,
+            delta,
+            reason: reason.into(),
+            adjusted_at: Utc::now(),
+        })
+    }
+
+    /// Returns the product the event is about.
+    pub fn product_id(&self) -> &ProductId {
+        match self {
+            InventoryEvent::StockRestocked(data) => &data.product_id,
+            InventoryEvent::StockReserved(data) => &data.product_id,
+            InventoryEvent::StockReleased(data) => &data.product_id,
+            InventoryEvent::StockAdjusted(data) => &data.product_id,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serialization_roundtrip() {
+        let event =
+            InventoryEvent::stock_restocked(ProductId::new("SKU-001"), 10, Some("PO-1".into()));
+        assert_eq!(event.event_type(), "StockRestocked");
+
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["type"], "StockRestocked");
+        assert_eq!(json["data"]["quantity"], 10);
+
+        let parsed: InventoryEvent = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.event_type(), "StockRestocked");
+    }
+
+    #[test]
+    fn test_event_types() {
+        let product_id = ProductId::new("SKU-001");
+        assert_eq!(
+            InventoryEvent::stock_reserved(product_id.clone(), "R-1", AggregateId::new(), 2)
+                .event_type(),
+            "StockReserved"
+        );
+        assert_eq!(
+            InventoryEvent::stock_released(product_id.clone(), "R-1", 2).event_type(),
+            "StockReleased"
+        );
+        assert_eq!(
+            InventoryEvent::stock_adjusted(product_id, -1, "damaged").event_type(),
+            "StockAdjusted"
+        );
+    }
+}
@@ -0,0 +1,53 @@
+//! Inventory item aggregate and related types.
+
+mod aggregate;
+mod commands;
+mod events;
+mod service;
+
+pub use aggregate::InventoryItem;
+pub use commands::*;
+pub use events::{
+    InventoryEvent, StockAdjustedData, StockReleasedData, StockReservedData, StockRestockedData,
+};
+pub use service::InventoryItemService;
+
+use thiserror::Error;
+
+use crate::order::ProductId;
+
+/// Errors that can occur during inventory operations.
+#[derive(Debug, Error)]
+pub enum InventoryError {
+    /// Restocked quantity must be positive.
+    #[error("Invalid restock quantity {quantity} for product {product_id}")]
+    InvalidQuantity {
+        product_id: ProductId,
+        quantity: u32,
+    },
+
+    /// Not enough unreserved stock to hold the requested quantity.
+    #[error(
+        "Insufficient stock for product {product_id}: requested {requested}, available {available}"
+    )]
+    InsufficientStock {
+        product_id: ProductId,
+        requested: u32,
+        available: i64,
+    },
+
+    /// An adjustment that changes nothing or leaves negative stock.
+    #[error("Invalid stock adjustment of {delta} for product {product_id}")]
+    InvalidAdjustment { product_id: ProductId, delta: i64 },
+}
+
+impl InventoryError {
+    /// Returns a stable code naming the violated invariant.
+    pub fn code(&self) -> &'static str {
+        match self {
+            InventoryError::InvalidQuantity { .. } => "inventory.invalid_quantity",
+            InventoryError::InsufficientStock { .. } => "inventory.insufficient_stock",
+            InventoryError::InvalidAdjustment { .. } => "inventory.invalid_adjustment",
+        }
+    }
+}
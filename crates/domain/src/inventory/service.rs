@@ -0,0 +1,233 @@
+//! Inventory item service providing a simplified API for stock operations.
+
+use event_store::{EnvelopeFactory, EventStore, SchemaDeprecations};
+
+use crate::command::{Command, CommandHandler, CommandResult};
+use crate::contention::ContentionTracker;
+use crate::decision::DecisionLog;
+use crate::error::DomainError;
+use crate::order::ProductId;
+
+use super::{AdjustStock, InventoryError, InventoryItem, ReleaseStock, ReserveStock, RestockItem};
+
+/// Attempts made to record a stock change before giving up on conflicts.
+///
+/// Every change is decided against the item's current stock, so re-running
+/// one against newer state is always safe.
+const STOCK_MAX_ATTEMPTS: u32 = 3;
+
+impl From<InventoryError> for DomainError {
+    fn from(e: InventoryError) -> Self {
+        DomainError::Inventory(e)
+    }
+}
+
+/// Service for managing inventory items.
+pub struct InventoryItemService<S: EventStore> {
+    handler: CommandHandler<S, InventoryItem>,
+}
+
+impl<S: EventStore> InventoryItemService<S> {
+    /// Creates a new inventory item service with the given event store.
+    pub fn new(store: S) -> Self {
+        Self {
+            handler: CommandHandler::new(store),
+        }
+    }
+
+    /// Records concurrency conflicts on inventory items in `tracker`.
+    pub fn with_contention_tracker(mut self, tracker: ContentionTracker) -> Self {
+        self.handler = self.handler.with_contention_tracker(tracker);
+        self
+    }
+
+    /// Records command decisions in `log`.
+    pub fn with_decision_log(mut self, log: DecisionLog) -> Self {
+        self.handler = self.handler.with_decision_log(log);
+        self
+    }
+
+    /// Counts reads of deprecated event schema versions in `deprecations`.
+    pub fn with_schema_deprecations(mut self, deprecations: SchemaDeprecations) -> Self {
+        self.handler = self.handler.with_schema_deprecations(deprecations);
+        self
+    }
+
+    /// Builds this service's event envelopes with `factory`.
+    pub fn with_envelope_factory(mut self, factory: EnvelopeFactory) -> Self {
+        self.handler = self.handler.with_envelope_factory(factory);
+        self
+    }
+
+    /// Returns a reference to the underlying command handler.
+    pub fn handler(&self) -> &CommandHandler<S, InventoryItem> {
+        &self.handler
+    }
+
+    /// Records stock received for a product.
+    ///
+    /// Concurrent restocks of the same product are retried on conflict.
+    #[tracing::instrument(skip(self))]
+    pub async fn restock(
+        &self,
+        cmd: RestockItem,
+    ) -> Result<CommandResult<InventoryItem>, DomainError> {
+        let command = cmd.name();
+        let RestockItem {
+            product_id,
+            quantity,
+            reference,
+        } = cmd;
+
+        self.handler
+            .execute_named_with_retry(
+                command,
+                InventoryItem::stream_id(&product_id),
+                STOCK_MAX_ATTEMPTS,
+                |item| item.restock(product_id.clone(), quantity, reference.clone()),
+            )
+            .await
+    }
+
+    /// Holds stock of a product for an order, failing if not enough is
+    /// unreserved.
+    ///
+    /// Concurrent changes to the same product are retried on conflict.
+    #[tracing::instrument(skip(self))]
+    pub async fn reserve(
+        &self,
+        cmd: ReserveStock,
+    ) -> Result<CommandResult<InventoryItem>, DomainError> {
+        let command = cmd.name();
+        let aggregate_id = cmd.aggregate_id();
+        let ReserveStock {
+            product_id,
+            reservation_id,
+            order_id,
+            quantity,
+        } = cmd;
+
+        self.handler
+            .execute_named_with_retry(command, aggregate_id, STOCK_MAX_ATTEMPTS, |item| {
+                item.reserve(
+                    product_id.clone(),
+                    reservation_id.clone(),
+                    order_id,
+                    quantity,
+                )
+            })
+            .await
+    }
+
+    /// Releases a reservation; releasing one that isn't held does nothing.
+    #[tracing::instrument(skip(self))]
+    pub async fn release(
+        &self,
+        cmd: ReleaseStock,
+    ) -> Result<CommandResult<InventoryItem>, DomainError> {
+        let reservation_id = cmd.reservation_id.clone();
+        self.handler
+            .execute_named_with_retry(cmd.name(), cmd.aggregate_id(), STOCK_MAX_ATTEMPTS, |item| {
+                item.release(&reservation_id)
+            })
+            .await
+    }
+
+    /// Corrects a product's on-hand stock.
+    #[tracing::instrument(skip(self))]
+    pub async fn adjust(
+        &self,
+        cmd: AdjustStock,
+    ) -> Result<CommandResult<InventoryItem>, DomainError> {
+        let command = cmd.name();
+        let aggregate_id = cmd.aggregate_id();
+        let AdjustStock {
+            product_id,
+            delta,
+            reason,
+        } = cmd;
+
+        self.handler
+            .execute_named_with_retry(command, aggregate_id, STOCK_MAX_ATTEMPTS, |item| {
+                item.adjust(product_id.clone(), delta, reason.clone())
+            })
+            .await
+    }
+
+    /// Loads a product's inventory item.
+    ///
+    /// Returns None if the product has no stock history.
+    #[tracing::instrument(skip(self))]
+    pub async fn get_item(
+        &self,
+        product_id: &ProductId,
+    ) -> Result<Option<InventoryItem>, DomainError> {
+        self.handler
+            .load_existing(InventoryItem::stream_id(product_id))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use event_store::InMemoryEventStore;
+
+    #[tokio::test]
+    async fn test_restock_and_load() {
+        let service = InventoryItemService::new(InMemoryEventStore::new());
+        let product_id = ProductId::new("SKU-001");
+
+        assert!(service.get_item(&product_id).await.unwrap().is_none());
+
+        service
+            .restock(RestockItem::new("SKU-001", 10, Some("PO-1".into())))
+            .await
+            .unwrap();
+        let result = service
+            .restock(RestockItem::new("SKU-001", 4, None))
+            .await
+            .unwrap();
+        assert_eq!(result.aggregate.total_received(), 14);
+
+        let item = service.get_item(&product_id).await.unwrap().unwrap();
+        assert_eq!(item.total_received(), 14);
+    }
+
+    #[tokio::test]
+    async fn test_reserve_and_release_stock() {
+        let service = InventoryItemService::new(InMemoryEventStore::new());
+        let order_id = common::AggregateId::new();
+        service
+            .restock(RestockItem::new("SKU-001", 4, None))
+            .await
+            .unwrap();
+
+        let reserve = ReserveStock::new("SKU-001", order_id, 3);
+        let reservation_id = reserve.reservation_id.clone();
+        let result = service.reserve(reserve).await.unwrap();
+        assert_eq!(result.aggregate.available(), 1);
+
+        let refused = service
+            .reserve(ReserveStock::new("SKU-001", order_id, 2))
+            .await;
+        assert!(matches!(
+            refused,
+            Err(DomainError::Inventory(
+                InventoryError::InsufficientStock { .. }
+            ))
+        ));
+
+        let release = ReleaseStock::new(reservation_id).unwrap();
+        let result = service.release(release.clone()).await.unwrap();
+        assert_eq!(result.aggregate.available(), 4);
+        // Released again after a retried compensation
+        assert!(service.release(release).await.unwrap().events.is_empty());
+
+        let result = service
+            .adjust(AdjustStock::new("SKU-001", -1, "damaged"))
+            .await
+            .unwrap();
+        assert_eq!(result.aggregate.on_hand(), 3);
+    }
+}
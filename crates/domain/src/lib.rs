@@ -3,19 +3,70 @@
 //! This crate provides the core domain abstractions including:
 //! - Aggregate trait for event-sourced entities
 //! - DomainEvent trait for domain events
-//! - Command trait and CommandHandler for command processing
-//! - Order aggregate implementation with state machine
+//! - Command trait and CommandHandler for command processing, with an
+//!   optional required-metadata policy
+//! - Cancellation scopes for cancelling commands from the caller's request
+//! - Concurrency conflict tracking for spotting contended aggregates
+//! - Payload hashing for refusing repeated commands
+//! - Command IDs for processing retried commands once
+//! - Structured decision records for every command, with an optional buffer
+//!   of recent rejections
+//! - Order aggregate implementation with state machine, and diffs between
+//!   its replayed versions
+//! - Customer aggregate for identity linkage (merges)
+//! - Store-backed feature flags
+//! - Inventory item aggregate for stock received
+//! - Payment aggregate for each charge's lifecycle
+//! - Background snapshots written while the event log is idle
 
 pub mod aggregate;
+pub mod cancellation;
 pub mod command;
+pub mod contention;
+pub mod customer;
+pub mod decision;
+pub mod dedupe;
 pub mod error;
+pub mod feature_flag;
+pub mod inventory;
+pub mod metadata;
 pub mod order;
+pub mod payment;
+pub mod processed;
+pub mod snapshotter;
 
 pub use aggregate::{Aggregate, DomainEvent};
-pub use command::{Command, CommandHandler, CommandResult};
+pub use command::{BatchedCommand, Command, CommandHandler, CommandResult};
+pub use contention::{ContentionReport, ContentionTracker, HotAggregate};
+pub use customer::{
+    ContactInfo, Customer, CustomerError, CustomerEvent, CustomerService, DeactivateCustomer,
+    MergeCustomers, RegisterCustomer, UpdateContactInfo,
+};
+pub use decision::{Decision, DecisionLog, DecisionOutcome};
+pub use dedupe::{PAYLOAD_HASH_KEY, PayloadHasher, Sha256PayloadHasher};
 pub use error::DomainError;
+pub use feature_flag::{
+    FeatureFlag, FeatureFlagError, FeatureFlagService, FlagContext, FlagScope, SetFeatureFlag,
+};
+pub use inventory::{
+    AdjustStock, InventoryError, InventoryEvent, InventoryItem, InventoryItemService, ReleaseStock,
+    ReserveStock, RestockItem,
+};
+pub use metadata::{CommandMetadata, MetadataPolicy};
 pub use order::{
-    AddItem, CancelOrder, CompleteOrder, CreateOrder, CustomerId, MarkReserved, Money, Order,
-    OrderError, OrderEvent, OrderItem, OrderService, OrderState, ProductId, RemoveItem,
-    StartProcessing, SubmitOrder, UpdateItemQuantity,
+    AddItem, AddTag, Address, CancelOrder, Change, CommandMiddleware, CompleteOrder, CreateOrder,
+    Currency, CustomerId, DenyListFilter, DraftThrottle, FilterAction, ImportReport,
+    ImportValidator, InMemoryPriceCatalog, ItemChange, MarkDelivered, MarkInTransit, MarkReserved,
+    MarkShipped, Money, Order, OrderCommands, OrderDiff, OrderError, OrderEvent, OrderItem,
+    OrderLimits, OrderQueries, OrderService, OrderState, PiiMasker, PriceCatalog, PriceDrift,
+    PriceDriftPolicy, ProductId, RecordDeliveryFailure, RemoveItem, RemoveTag, RenewReservations,
+    SetOrderMetadata, SetShippingAddress, ShippedItem, StartProcessing, SubmitOrder,
+    SubstituteItem, TextField, UpdateItemPrice, UpdateItemQuantity, UpdateShippingAddress,
+    WhitespaceNormalizer,
+};
+pub use payment::{
+    AuthorizePayment, CapturePayment, DeclinePayment, Payment, PaymentError, PaymentEvent,
+    PaymentLedger, PaymentStatus, RefundPayment, RequestPayment, RequestRefund,
 };
+pub use processed::{COMMAND_ID_KEY, CommandIds, ProcessedCommand, ProcessedCommands};
+pub use snapshotter::Snapshotter;
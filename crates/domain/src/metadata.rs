@@ -0,0 +1,77 @@
+//! Audit metadata policy for command execution.
+
+use std::collections::HashMap;
+
+/// Metadata attached to every event a command appends.
+pub type CommandMetadata = HashMap<String, serde_json::Value>;
+
+/// Metadata keys that every append must carry.
+///
+/// Checked by [`CommandHandler`](crate::CommandHandler) before events are
+/// persisted. Keys with a `null` value count as missing.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MetadataPolicy {
+    required: Vec<String>,
+}
+
+impl MetadataPolicy {
+    /// Creates a policy that requires nothing.
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    /// Creates a policy requiring the given keys.
+    pub fn require<I, K>(keys: I) -> Self
+    where
+        I: IntoIterator<Item = K>,
+        K: Into<String>,
+    {
+        let mut required: Vec<String> = keys.into_iter().map(Into::into).collect();
+        required.sort();
+        required.dedup();
+        Self { required }
+    }
+
+    /// Returns the required keys, sorted.
+    pub fn required_keys(&self) -> &[String] {
+        &self.required
+    }
+
+    /// Returns the required keys missing from `metadata`, sorted.
+    pub fn missing(&self, metadata: &CommandMetadata) -> Vec<String> {
+        self.required
+            .iter()
+            .filter(|key| metadata.get(*key).is_none_or(|v| v.is_null()))
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_none_requires_nothing() {
+        assert!(
+            MetadataPolicy::none()
+                .missing(&CommandMetadata::new())
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_reports_missing_and_null_keys() {
+        let policy = MetadataPolicy::require(["source", "actor", "correlation_id", "actor"]);
+        assert_eq!(
+            policy.required_keys(),
+            ["actor", "correlation_id", "source"]
+        );
+
+        let mut metadata = CommandMetadata::new();
+        metadata.insert("actor".to_string(), serde_json::json!("ops@example.com"));
+        metadata.insert("source".to_string(), serde_json::Value::Null);
+
+        assert_eq!(policy.missing(&metadata), ["correlation_id", "source"]);
+    }
+}
@@ -1,7 +1,8 @@
 //! Order aggregate implementation.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
+use chrono::{DateTime, Utc};
 use common::AggregateId;
 use event_store::Version;
 use serde::{Deserialize, Serialize};
@@ -9,8 +10,12 @@ use serde::{Deserialize, Serialize};
 use crate::aggregate::{Aggregate, SnapshotCapable};
 
 use super::{
-    CustomerId, Money, OrderError, OrderEvent, OrderItem, OrderState, ProductId,
-    events::{ItemAddedData, ItemQuantityUpdatedData, OrderCreatedData},
+    Address, Currency, CustomerId, Money, OrderError, OrderEvent, OrderItem, OrderLimits,
+    OrderState, PriceCatalog, PriceDrift, PriceDriftPolicy, ProductId, ShippedItem,
+    events::{
+        ItemAddedData, ItemPriceAdjustedData, ItemQuantityUpdatedData, ItemSubstitutedData,
+        OrderCreatedData,
+    },
 };
 
 /// Order aggregate root.
@@ -29,6 +34,10 @@ pub struct Order {
     /// Customer who placed the order.
     customer_id: Option<CustomerId>,
 
+    /// Currency all amounts are denominated in.
+    #[serde(default)]
+    currency: Currency,
+
     /// Current state of the order.
     state: OrderState,
 
@@ -37,8 +46,52 @@ pub struct Order {
 
     /// Total amount of the order.
     total_amount: Money,
+
+    /// Address the order ships to, once given.
+    #[serde(default)]
+    shipping_address: Option<Address>,
+
+    /// Carrier handling the shipment, once shipped.
+    #[serde(default)]
+    carrier: Option<String>,
+
+    /// Shipment tracking number.
+    #[serde(default)]
+    tracking_number: Option<String>,
+
+    /// Last known shipment location.
+    #[serde(default)]
+    last_location: Option<String>,
+
+    /// Serial and lot numbers recorded at fulfillment.
+    #[serde(default)]
+    shipped_items: Vec<ShippedItem>,
+
+    /// Inventory reservation ID held for each line item.
+    #[serde(default)]
+    reservations: BTreeMap<ProductId, String>,
+
+    /// Free-form labels attached by operators, normalized to lowercase.
+    #[serde(default)]
+    tags: BTreeSet<String>,
+
+    /// Customer-supplied attachments such as a gift message, keyed by one
+    /// of [`METADATA_KEYS`].
+    #[serde(default)]
+    metadata: BTreeMap<String, String>,
 }
 
+/// Longest tag accepted, in characters.
+const MAX_TAG_LEN: usize = 64;
+
+/// Metadata keys an order accepts, with the longest value allowed for each,
+/// in characters.
+const METADATA_KEYS: &[(&str, usize)] = &[
+    ("gift_message", 500),
+    ("delivery_instructions", 500),
+    ("po_number", 64),
+];
+
 impl Aggregate for Order {
     type Event = OrderEvent;
     type Error = OrderError;
@@ -59,27 +112,102 @@ impl Aggregate for Order {
         self.version = version;
     }
 
+    /// Rejects commands that emit more than one item change (add, remove,
+    /// quantity update, price adjustment, or substitution) for the same
+    /// product.
+    fn validate_emitted(&self, events: &[Self::Event]) -> Result<(), Self::Error> {
+        let mut touched = HashSet::new();
+        for event in events {
+            let product_ids = match event {
+                OrderEvent::ItemAdded(data) => vec![&data.product_id],
+                OrderEvent::ItemRemoved(data) => vec![&data.product_id],
+                OrderEvent::ItemQuantityUpdated(data) => vec![&data.product_id],
+                OrderEvent::ItemPriceAdjusted(data) => vec![&data.product_id],
+                OrderEvent::ItemSubstituted(data) => {
+                    vec![&data.original_product_id, &data.substitute_product_id]
+                }
+                _ => continue,
+            };
+            for product_id in product_ids {
+                if !touched.insert(product_id) {
+                    return Err(OrderError::ConflictingItemEvents {
+                        product_id: product_id.to_string(),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
     fn apply(&mut self, event: Self::Event) {
         match event {
             OrderEvent::OrderCreated(data) => self.apply_order_created(data),
             OrderEvent::ItemAdded(data) => self.apply_item_added(data),
             OrderEvent::ItemRemoved(data) => self.apply_item_removed(data.product_id),
             OrderEvent::ItemQuantityUpdated(data) => self.apply_item_quantity_updated(data),
+            OrderEvent::ItemPriceAdjusted(data) => self.apply_item_price_adjusted(data),
+            OrderEvent::ItemSubstituted(data) => self.apply_item_substituted(data),
             OrderEvent::OrderSubmitted(_) => {
                 // State transition happens in OrderReserved
             }
-            OrderEvent::OrderReserved(_) => {
+            OrderEvent::OrderReserved(data) => {
                 self.state = OrderState::Reserved;
+                self.reservations = data.reservations;
+            }
+            OrderEvent::ReservationRenewed(data) => {
+                self.reservations
+                    .insert(data.product_id, data.reservation_id);
             }
             OrderEvent::OrderProcessing(_) => {
                 self.state = OrderState::Processing;
             }
-            OrderEvent::OrderCompleted(_) => {
+            OrderEvent::OrderCompleted(data) => {
                 self.state = OrderState::Completed;
+                self.tracking_number = data.tracking_number;
+                self.shipped_items = data.shipped_items;
+            }
+            OrderEvent::OrderShipped(data) => {
+                self.state = OrderState::Shipped;
+                self.carrier = Some(data.carrier);
+                if data.tracking_number.is_some() {
+                    self.tracking_number = data.tracking_number;
+                }
+            }
+            OrderEvent::OrderInTransit(data) => {
+                self.state = OrderState::InTransit;
+                if data.location.is_some() {
+                    self.last_location = data.location;
+                }
+            }
+            OrderEvent::OrderDelivered(_) => {
+                self.state = OrderState::Delivered;
+            }
+            OrderEvent::DeliveryFailed(_) => {
+                self.state = OrderState::DeliveryFailed;
             }
             OrderEvent::OrderCancelled(_) => {
                 self.state = OrderState::Cancelled;
             }
+            OrderEvent::TagAdded(data) => {
+                self.tags.insert(data.tag);
+            }
+            OrderEvent::TagRemoved(data) => {
+                self.tags.remove(&data.tag);
+            }
+            OrderEvent::OrderMetadataSet(data) => match data.value {
+                Some(value) => {
+                    self.metadata.insert(data.key, value);
+                }
+                None => {
+                    self.metadata.remove(&data.key);
+                }
+            },
+            OrderEvent::ShippingAddressSet(data) => {
+                self.shipping_address = Some(data.address);
+            }
+            OrderEvent::ShippingAddressUpdated(data) => {
+                self.shipping_address = Some(data.address);
+            }
         }
     }
 }
@@ -97,6 +225,11 @@ impl Order {
         self.customer_id
     }
 
+    /// Returns the currency the order is denominated in.
+    pub fn currency(&self) -> Currency {
+        self.currency
+    }
+
     /// Returns the current state.
     pub fn state(&self) -> OrderState {
         self.state
@@ -132,6 +265,63 @@ impl Order {
         !self.items.is_empty()
     }
 
+    /// Returns the carrier handling the shipment.
+    pub fn carrier(&self) -> Option<&str> {
+        self.carrier.as_deref()
+    }
+
+    /// Returns the shipment tracking number.
+    pub fn tracking_number(&self) -> Option<&str> {
+        self.tracking_number.as_deref()
+    }
+
+    /// Returns the serial and lot numbers recorded for a shipped product.
+    pub fn shipped_item(&self, product_id: &ProductId) -> Option<&ShippedItem> {
+        self.shipped_items
+            .iter()
+            .find(|item| &item.product_id == product_id)
+    }
+
+    /// Returns the inventory reservation ID held for a line item.
+    pub fn reservation(&self, product_id: &ProductId) -> Option<&str> {
+        self.reservations.get(product_id).map(String::as_str)
+    }
+
+    /// Returns the inventory reservation IDs, keyed by product.
+    pub fn reservations(&self) -> &BTreeMap<ProductId, String> {
+        &self.reservations
+    }
+
+    /// Returns the address the order ships to, if one was given.
+    pub fn shipping_address(&self) -> Option<&Address> {
+        self.shipping_address.as_ref()
+    }
+
+    /// Returns the last known shipment location.
+    pub fn last_location(&self) -> Option<&str> {
+        self.last_location.as_deref()
+    }
+
+    /// Returns the order's metadata attachments, keyed by name.
+    pub fn metadata(&self) -> &BTreeMap<String, String> {
+        &self.metadata
+    }
+
+    /// Returns the value of a metadata attachment.
+    pub fn metadata_value(&self, key: &str) -> Option<&str> {
+        self.metadata.get(key).map(String::as_str)
+    }
+
+    /// Returns the order's tags, in alphabetical order.
+    pub fn tags(&self) -> impl Iterator<Item = &str> {
+        self.tags.iter().map(String::as_str)
+    }
+
+    /// Returns true if the order carries `tag`, compared after normalizing.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.contains(&tag.trim().to_lowercase())
+    }
+
     /// Returns true if the order is in a terminal state.
     pub fn is_terminal(&self) -> bool {
         self.state.is_terminal()
@@ -145,18 +335,44 @@ impl Order {
         &self,
         order_id: AggregateId,
         customer_id: CustomerId,
+    ) -> Result<Vec<OrderEvent>, OrderError> {
+        self.create_in(order_id, customer_id, Currency::default())
+    }
+
+    /// Creates a new order for a customer in the given currency.
+    pub fn create_in(
+        &self,
+        order_id: AggregateId,
+        customer_id: CustomerId,
+        currency: Currency,
     ) -> Result<Vec<OrderEvent>, OrderError> {
         if self.id.is_some() {
             return Err(OrderError::AlreadyCreated);
         }
 
-        Ok(vec![OrderEvent::order_created(order_id, customer_id)])
+        Ok(vec![OrderEvent::order_created_in(
+            order_id,
+            customer_id,
+            currency,
+        )])
     }
 
     /// Adds an item to the order.
     ///
     /// If the item already exists, updates the quantity instead.
     pub fn add_item(&self, item: OrderItem) -> Result<Vec<OrderEvent>, OrderError> {
+        self.add_item_within(item, &OrderLimits::unlimited())
+    }
+
+    /// Adds an item to the order, refusing it if the order would exceed
+    /// `limits`.
+    ///
+    /// If the item already exists, updates the quantity instead.
+    pub fn add_item_within(
+        &self,
+        item: OrderItem,
+        limits: &OrderLimits,
+    ) -> Result<Vec<OrderEvent>, OrderError> {
         if !self.state.can_modify_items() {
             return Err(OrderError::InvalidStateTransition {
                 current_state: self.state,
@@ -175,16 +391,29 @@ impl Order {
                 price: item.unit_price.cents(),
             });
         }
+        self.check_currency(item.unit_price)?;
 
         // Check if item already exists
         if let Some(existing) = self.items.get(&item.product_id) {
-            let new_quantity = existing.quantity + item.quantity;
+            let new_quantity = existing.quantity.saturating_add(item.quantity);
+            limits.check(
+                self.items.len(),
+                &item.product_id,
+                new_quantity,
+                self.total_with(&item.product_id, new_quantity, existing.unit_price),
+            )?;
             Ok(vec![OrderEvent::item_quantity_updated(
                 item.product_id,
                 existing.quantity,
                 new_quantity,
             )])
         } else {
+            limits.check(
+                self.items.len() + 1,
+                &item.product_id,
+                item.quantity,
+                self.total_with(&item.product_id, item.quantity, item.unit_price),
+            )?;
             Ok(vec![OrderEvent::item_added(&item)])
         }
     }
@@ -212,6 +441,17 @@ impl Order {
         &self,
         product_id: ProductId,
         new_quantity: u32,
+    ) -> Result<Vec<OrderEvent>, OrderError> {
+        self.update_item_quantity_within(product_id, new_quantity, &OrderLimits::unlimited())
+    }
+
+    /// Updates the quantity of an existing item, refusing an increase that
+    /// would take the order past `limits`. Decreases are always allowed.
+    pub fn update_item_quantity_within(
+        &self,
+        product_id: ProductId,
+        new_quantity: u32,
+        limits: &OrderLimits,
     ) -> Result<Vec<OrderEvent>, OrderError> {
         if !self.state.can_modify_items() {
             return Err(OrderError::InvalidStateTransition {
@@ -231,6 +471,14 @@ impl Order {
             // Remove the item if quantity is 0
             Ok(vec![OrderEvent::item_removed(product_id)])
         } else if new_quantity != existing.quantity {
+            if new_quantity > existing.quantity {
+                limits.check(
+                    self.items.len(),
+                    &product_id,
+                    new_quantity,
+                    self.total_with(&product_id, new_quantity, existing.unit_price),
+                )?;
+            }
             Ok(vec![OrderEvent::item_quantity_updated(
                 product_id,
                 existing.quantity,
@@ -242,6 +490,125 @@ impl Order {
         }
     }
 
+    /// Returns the order total with `product_id` at `quantity` and
+    /// `unit_price`, or None if it overflows.
+    fn total_with(
+        &self,
+        product_id: &ProductId,
+        quantity: u32,
+        unit_price: Money,
+    ) -> Option<Money> {
+        let current = self
+            .items
+            .get(product_id)
+            .map_or(0, |item| item.total_price().cents());
+        unit_price
+            .cents()
+            .checked_mul(i64::from(quantity))
+            .and_then(|line| (self.total_amount.cents() - current).checked_add(line))
+            .map(|cents| Money::from_cents_in(cents, self.currency))
+    }
+
+    /// Refuses an amount in a currency other than the order's.
+    fn check_currency(&self, amount: Money) -> Result<(), OrderError> {
+        if amount.currency() != self.currency {
+            return Err(OrderError::CurrencyMismatch {
+                expected: self.currency,
+                actual: amount.currency(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Changes the unit price of an item.
+    pub fn update_item_price(
+        &self,
+        product_id: ProductId,
+        new_unit_price: Money,
+    ) -> Result<Vec<OrderEvent>, OrderError> {
+        if !self.state.can_modify_items() {
+            return Err(OrderError::InvalidStateTransition {
+                current_state: self.state,
+                action: "update item price",
+            });
+        }
+
+        if !new_unit_price.is_positive() {
+            return Err(OrderError::InvalidPrice {
+                price: new_unit_price.cents(),
+            });
+        }
+        self.check_currency(new_unit_price)?;
+
+        let existing = self
+            .items
+            .get(&product_id)
+            .ok_or_else(|| OrderError::ItemNotFound {
+                product_id: product_id.to_string(),
+            })?;
+
+        if new_unit_price == existing.unit_price {
+            // No change
+            return Ok(vec![]);
+        }
+
+        Ok(vec![OrderEvent::item_price_adjusted(
+            product_id,
+            existing.unit_price,
+            new_unit_price,
+        )])
+    }
+
+    /// Replaces an item with a substitute product during fulfillment.
+    ///
+    /// The substitute takes over the original line's quantity at its own
+    /// unit price. `reservation_id` is the inventory reservation held for
+    /// the substitute, replacing the original line's.
+    pub fn substitute_item(
+        &self,
+        original_product_id: &ProductId,
+        substitute: OrderItem,
+        reservation_id: Option<String>,
+    ) -> Result<Vec<OrderEvent>, OrderError> {
+        if !self.state.can_substitute_items() {
+            return Err(OrderError::InvalidStateTransition {
+                current_state: self.state,
+                action: "substitute item",
+            });
+        }
+
+        if !substitute.unit_price.is_positive() {
+            return Err(OrderError::InvalidPrice {
+                price: substitute.unit_price.cents(),
+            });
+        }
+        self.check_currency(substitute.unit_price)?;
+
+        let original =
+            self.items
+                .get(original_product_id)
+                .ok_or_else(|| OrderError::ItemNotFound {
+                    product_id: original_product_id.to_string(),
+                })?;
+
+        if self.items.contains_key(&substitute.product_id) {
+            return Err(OrderError::InvalidSubstitution {
+                product_id: substitute.product_id.to_string(),
+                reason: "already in the order",
+            });
+        }
+
+        let substitute = OrderItem {
+            quantity: original.quantity,
+            ..substitute
+        };
+        Ok(vec![OrderEvent::item_substituted(
+            original,
+            &substitute,
+            reservation_id,
+        )])
+    }
+
     /// Submits the order for processing.
     pub fn submit(&self) -> Result<Vec<OrderEvent>, OrderError> {
         if !self.state.can_submit() {
@@ -261,10 +628,72 @@ impl Order {
         )])
     }
 
+    /// Submits the order after reconciling item prices with `catalog`.
+    ///
+    /// Items whose stored unit price differs from the catalog's are adjusted
+    /// to the current price or, under [`PriceDriftPolicy::Reject`], fail the
+    /// submit with every drifted item listed. Products the catalog doesn't
+    /// list keep their stored price.
+    pub fn submit_with_prices(
+        &self,
+        catalog: &dyn PriceCatalog,
+        policy: PriceDriftPolicy,
+    ) -> Result<Vec<OrderEvent>, OrderError> {
+        let submitted = self.submit()?;
+        let drifts = self.price_drifts(catalog);
+        if drifts.is_empty() {
+            return Ok(submitted);
+        }
+        if policy == PriceDriftPolicy::Reject {
+            return Err(OrderError::PriceDrift { drifts });
+        }
+
+        let mut total = self.total_amount;
+        let mut events = Vec::with_capacity(drifts.len() + 1);
+        for drift in drifts {
+            let quantity = self.items[&drift.product_id].quantity;
+            total = total
+                .checked_sub(drift.stored.multiply(quantity))?
+                .checked_add(drift.current.multiply(quantity))?;
+            events.push(OrderEvent::item_price_adjusted(
+                drift.product_id,
+                drift.stored,
+                drift.current,
+            ));
+        }
+        events.push(OrderEvent::order_submitted(total, self.items.len()));
+        Ok(events)
+    }
+
+    /// Returns items whose stored price differs from the catalog's, in
+    /// product ID order. Catalog prices in another currency than the
+    /// order's can't be compared and are skipped.
+    pub fn price_drifts(&self, catalog: &dyn PriceCatalog) -> Vec<PriceDrift> {
+        let mut drifts: Vec<PriceDrift> = self
+            .items
+            .values()
+            .filter_map(|item| {
+                let current = catalog
+                    .current_price(&item.product_id)
+                    .filter(|price| price.currency() == self.currency)?;
+                (current != item.unit_price).then(|| PriceDrift {
+                    product_id: item.product_id.clone(),
+                    stored: item.unit_price,
+                    current,
+                })
+            })
+            .collect();
+        drifts.sort_by(|a, b| a.product_id.as_str().cmp(b.product_id.as_str()));
+        drifts
+    }
+
     /// Marks inventory as reserved.
+    ///
+    /// `reservations` maps each reserved line item to the inventory
+    /// reservation holding it; every key must be an item on the order.
     pub fn mark_reserved(
         &self,
-        reservation_id: Option<String>,
+        reservations: BTreeMap<ProductId, String>,
     ) -> Result<Vec<OrderEvent>, OrderError> {
         if !self.state.can_reserve() {
             return Err(OrderError::InvalidStateTransition {
@@ -273,7 +702,51 @@ impl Order {
             });
         }
 
-        Ok(vec![OrderEvent::order_reserved(reservation_id)])
+        if let Some(product_id) = reservations
+            .keys()
+            .find(|product_id| !self.items.contains_key(*product_id))
+        {
+            return Err(OrderError::ItemNotFound {
+                product_id: product_id.to_string(),
+            });
+        }
+
+        Ok(vec![OrderEvent::order_reserved(reservations)])
+    }
+
+    /// Replaces line items' reservations after the inventory service let
+    /// the previous ones lapse.
+    ///
+    /// `reservations` maps each renewed line item to its new reservation.
+    /// Only a reserved order can renew: once processing starts, the
+    /// reservations are no longer waited on.
+    pub fn renew_reservations(
+        &self,
+        reservations: BTreeMap<ProductId, String>,
+    ) -> Result<Vec<OrderEvent>, OrderError> {
+        if !self.state.can_renew_reservations() {
+            return Err(OrderError::InvalidStateTransition {
+                current_state: self.state,
+                action: "renew reservations",
+            });
+        }
+
+        if let Some(product_id) = reservations
+            .keys()
+            .find(|product_id| !self.items.contains_key(*product_id))
+        {
+            return Err(OrderError::ItemNotFound {
+                product_id: product_id.to_string(),
+            });
+        }
+
+        Ok(reservations
+            .into_iter()
+            .map(|(product_id, reservation_id)| {
+                let previous = self.reservations.get(&product_id).cloned();
+                OrderEvent::reservation_renewed(product_id, reservation_id, previous)
+            })
+            .collect())
     }
 
     /// Starts processing the order.
@@ -292,7 +765,14 @@ impl Order {
     }
 
     /// Completes the order.
-    pub fn complete(&self, tracking_number: Option<String>) -> Result<Vec<OrderEvent>, OrderError> {
+    ///
+    /// `shipped_items` may carry serial or lot numbers for any of the order's
+    /// products, with at most one serial number per unit ordered.
+    pub fn complete(
+        &self,
+        tracking_number: Option<String>,
+        shipped_items: Vec<ShippedItem>,
+    ) -> Result<Vec<OrderEvent>, OrderError> {
         if !self.state.can_complete() {
             return Err(OrderError::InvalidStateTransition {
                 current_state: self.state,
@@ -300,14 +780,107 @@ impl Order {
             });
         }
 
-        Ok(vec![OrderEvent::order_completed(tracking_number)])
+        for (index, shipped) in shipped_items.iter().enumerate() {
+            let invalid = |reason: &str| OrderError::InvalidShippedItem {
+                product_id: shipped.product_id.to_string(),
+                reason: reason.to_string(),
+            };
+            let item = self
+                .items
+                .get(&shipped.product_id)
+                .ok_or_else(|| invalid("not in order"))?;
+            if shipped.serial_numbers.len() > item.quantity as usize {
+                return Err(invalid("more serial numbers than units ordered"));
+            }
+            if shipped_items[..index]
+                .iter()
+                .any(|other| other.product_id == shipped.product_id)
+            {
+                return Err(invalid("listed more than once"));
+            }
+        }
+
+        Ok(vec![OrderEvent::order_completed_with_items(
+            tracking_number,
+            shipped_items,
+        )])
+    }
+
+    /// Records that the carrier picked up the shipment.
+    pub fn mark_shipped(
+        &self,
+        carrier: impl Into<String>,
+        tracking_number: Option<String>,
+        shipped_at: DateTime<Utc>,
+    ) -> Result<Vec<OrderEvent>, OrderError> {
+        if !self.state.can_ship() {
+            return Err(OrderError::InvalidStateTransition {
+                current_state: self.state,
+                action: "mark shipped",
+            });
+        }
+
+        Ok(vec![OrderEvent::order_shipped(
+            carrier,
+            tracking_number,
+            shipped_at,
+        )])
+    }
+
+    /// Records a carrier transit update.
+    pub fn mark_in_transit(
+        &self,
+        location: Option<String>,
+        updated_at: DateTime<Utc>,
+    ) -> Result<Vec<OrderEvent>, OrderError> {
+        if !self.state.can_mark_in_transit() {
+            return Err(OrderError::InvalidStateTransition {
+                current_state: self.state,
+                action: "mark in transit",
+            });
+        }
+
+        Ok(vec![OrderEvent::order_in_transit(location, updated_at)])
+    }
+
+    /// Records that the carrier delivered the shipment.
+    pub fn mark_delivered(
+        &self,
+        received_by: Option<String>,
+        delivered_at: DateTime<Utc>,
+    ) -> Result<Vec<OrderEvent>, OrderError> {
+        if !self.state.can_record_delivery() {
+            return Err(OrderError::InvalidStateTransition {
+                current_state: self.state,
+                action: "mark delivered",
+            });
+        }
+
+        Ok(vec![OrderEvent::order_delivered(received_by, delivered_at)])
+    }
+
+    /// Records that the carrier failed to deliver the shipment.
+    pub fn record_delivery_failure(
+        &self,
+        reason: impl Into<String>,
+        failed_at: DateTime<Utc>,
+    ) -> Result<Vec<OrderEvent>, OrderError> {
+        if !self.state.can_record_delivery() {
+            return Err(OrderError::InvalidStateTransition {
+                current_state: self.state,
+                action: "record delivery failure",
+            });
+        }
+
+        Ok(vec![OrderEvent::delivery_failed(reason, failed_at)])
     }
 
-    /// Cancels the order.
+    /// Cancels the order, recording an optional machine-readable cause.
     pub fn cancel(
         &self,
         reason: impl Into<String>,
         cancelled_by: Option<String>,
+        reason_code: Option<String>,
     ) -> Result<Vec<OrderEvent>, OrderError> {
         if !self.state.can_cancel() {
             return Err(OrderError::InvalidStateTransition {
@@ -316,8 +889,146 @@ impl Order {
             });
         }
 
-        Ok(vec![OrderEvent::order_cancelled(reason, cancelled_by)])
+        Ok(vec![
+            OrderEvent::order_cancelled(reason, cancelled_by).with_reason_code(reason_code),
+        ])
+    }
+
+    /// Attaches a tag to the order.
+    ///
+    /// Tags are labels for grouping orders and don't affect the lifecycle,
+    /// so they can be changed in any state. Adding a tag the order already
+    /// carries is a no-op.
+    pub fn add_tag(&self, tag: &str) -> Result<Vec<OrderEvent>, OrderError> {
+        let tag = normalize_tag(tag)?;
+        if self.tags.contains(&tag) {
+            return Ok(vec![]);
+        }
+        Ok(vec![OrderEvent::tag_added(tag)])
+    }
+
+    /// Removes a tag from the order.
+    ///
+    /// Removing a tag the order doesn't carry is a no-op.
+    pub fn remove_tag(&self, tag: &str) -> Result<Vec<OrderEvent>, OrderError> {
+        let tag = normalize_tag(tag)?;
+        if !self.tags.contains(&tag) {
+            return Ok(vec![]);
+        }
+        Ok(vec![OrderEvent::tag_removed(tag)])
+    }
+
+    /// Sets or clears (`value` of None or blank) a metadata attachment.
+    ///
+    /// Attachments feed fulfillment, so they can only change while the
+    /// order is still a draft. Setting the current value is a no-op.
+    pub fn set_metadata(
+        &self,
+        key: &str,
+        value: Option<String>,
+    ) -> Result<Vec<OrderEvent>, OrderError> {
+        if !self.state.can_modify_items() {
+            return Err(OrderError::InvalidStateTransition {
+                current_state: self.state,
+                action: "set metadata",
+            });
+        }
+
+        let value = validate_metadata(key, value)?;
+        if self.metadata.get(key) == value.as_ref() {
+            return Ok(vec![]);
+        }
+        Ok(vec![OrderEvent::metadata_set(key, value)])
+    }
+
+    /// Gives the order its shipping address.
+    ///
+    /// The address goes to shipping when the shipment is created, so it
+    /// can be set until the order is completed. Fails if the order already
+    /// has one; use [`Order::update_shipping_address`] to change it.
+    pub fn set_shipping_address(&self, address: Address) -> Result<Vec<OrderEvent>, OrderError> {
+        self.ensure_shipping_address_changeable("set shipping address")?;
+        if self.shipping_address.is_some() {
+            return Err(OrderError::ShippingAddressAlreadySet);
+        }
+        Ok(vec![OrderEvent::shipping_address_set(address.validated()?)])
+    }
+
+    /// Changes the order's shipping address, until the order is completed.
+    ///
+    /// Fails if the order has no address yet. Changing it to the current
+    /// address is a no-op.
+    pub fn update_shipping_address(&self, address: Address) -> Result<Vec<OrderEvent>, OrderError> {
+        self.ensure_shipping_address_changeable("update shipping address")?;
+        let Some(previous) = &self.shipping_address else {
+            return Err(OrderError::NoShippingAddress);
+        };
+        let address = address.validated()?;
+        if &address == previous {
+            return Ok(vec![]);
+        }
+        Ok(vec![OrderEvent::shipping_address_updated(
+            address,
+            previous.clone(),
+        )])
+    }
+
+    fn ensure_shipping_address_changeable(&self, action: &'static str) -> Result<(), OrderError> {
+        if !self.state.can_change_shipping_address() {
+            return Err(OrderError::InvalidStateTransition {
+                current_state: self.state,
+                action,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Checks `key` against the allowlist and trims `value`, treating a blank
+/// value as a clear.
+fn validate_metadata(key: &str, value: Option<String>) -> Result<Option<String>, OrderError> {
+    let Some(&(_, max_len)) = METADATA_KEYS.iter().find(|(k, _)| *k == key) else {
+        return Err(OrderError::InvalidMetadata {
+            key: key.to_string(),
+            reason: "unknown key".to_string(),
+        });
+    };
+    let value = value
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty());
+    if let Some(v) = &value
+        && v.chars().count() > max_len
+    {
+        return Err(OrderError::InvalidMetadata {
+            key: key.to_string(),
+            reason: format!("must be at most {max_len} characters"),
+        });
     }
+    Ok(value)
+}
+
+/// Trims and lowercases a tag, rejecting ones that aren't short slugs.
+fn normalize_tag(tag: &str) -> Result<String, OrderError> {
+    let normalized = tag.trim().to_lowercase();
+    let invalid = |reason| OrderError::InvalidTag {
+        tag: tag.to_string(),
+        reason,
+    };
+    if normalized.is_empty() {
+        return Err(invalid("must not be empty"));
+    }
+    if normalized.chars().count() > MAX_TAG_LEN {
+        return Err(invalid("must be at most 64 characters"));
+    }
+    if !normalized
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | ':'))
+    {
+        return Err(invalid(
+            "may only contain letters, digits, '-', '_' and ':'",
+        ));
+    }
+    Ok(normalized)
 }
 
 // Apply event helpers
@@ -325,15 +1036,19 @@ impl Order {
     fn apply_order_created(&mut self, data: OrderCreatedData) {
         self.id = Some(data.order_id);
         self.customer_id = Some(data.customer_id);
+        self.currency = data.currency;
+        self.total_amount = Money::zero_in(data.currency);
         self.state = OrderState::Draft;
     }
 
+    // Prices stored before they carried a currency read as the default one,
+    // so they are taken in the order's currency, which they always were.
     fn apply_item_added(&mut self, data: ItemAddedData) {
         let item = OrderItem::new(
             data.product_id.clone(),
             data.product_name,
             data.quantity,
-            data.unit_price,
+            data.unit_price.in_currency(self.currency),
         );
         self.total_amount += item.total_price();
         self.items.insert(data.product_id, item);
@@ -345,9 +1060,35 @@ impl Order {
         }
     }
 
-    fn apply_item_quantity_updated(&mut self, data: ItemQuantityUpdatedData) {
+    fn apply_item_price_adjusted(&mut self, data: ItemPriceAdjustedData) {
         if let Some(item) = self.items.get_mut(&data.product_id) {
-            // Subtract old total
+            self.total_amount -= item.total_price();
+            item.unit_price = data.new_unit_price.in_currency(self.currency);
+            self.total_amount += item.total_price();
+        }
+    }
+
+    fn apply_item_substituted(&mut self, data: ItemSubstitutedData) {
+        self.apply_item_removed(data.original_product_id.clone());
+        let item = OrderItem::new(
+            data.substitute_product_id.clone(),
+            data.substitute_product_name,
+            data.quantity,
+            data.unit_price.in_currency(self.currency),
+        );
+        self.total_amount += item.total_price();
+        self.items.insert(data.substitute_product_id.clone(), item);
+
+        self.reservations.remove(&data.original_product_id);
+        if let Some(reservation_id) = data.reservation_id {
+            self.reservations
+                .insert(data.substitute_product_id, reservation_id);
+        }
+    }
+
+    fn apply_item_quantity_updated(&mut self, data: ItemQuantityUpdatedData) {
+        if let Some(item) = self.items.get_mut(&data.product_id) {
+            // Subtract old total
             self.total_amount -= item.total_price();
 
             // Update quantity
@@ -363,6 +1104,7 @@ impl Order {
 mod tests {
     use super::*;
     use crate::aggregate::{Aggregate, DomainEvent};
+    use crate::order::InMemoryPriceCatalog;
 
     fn create_order() -> (Order, AggregateId) {
         let mut order = Order::default();
@@ -380,6 +1122,47 @@ mod tests {
         assert!(order.customer_id().is_some());
         assert_eq!(order.state(), OrderState::Draft);
         assert!(!order.has_items());
+        assert_eq!(order.currency(), Currency::USD);
+    }
+
+    #[test]
+    fn test_create_order_in_currency() {
+        let mut order = Order::default();
+        let events = order
+            .create_in(AggregateId::new(), CustomerId::new(), Currency::EUR)
+            .unwrap();
+        order.apply_events(events);
+        assert_eq!(order.currency(), Currency::EUR);
+        assert_eq!(order.total_amount(), Money::zero_in(Currency::EUR));
+
+        let price = Money::from_cents_in(1000, Currency::EUR);
+        let events = order
+            .add_item(OrderItem::new("SKU-001", "Widget", 2, price))
+            .unwrap();
+        order.apply_events(events);
+        assert_eq!(order.total_amount().to_string(), "20.00 EUR");
+
+        // Amounts in another currency are refused
+        let result = order.add_item(OrderItem::new(
+            "SKU-002",
+            "Gadget",
+            1,
+            Money::from_cents(500),
+        ));
+        assert!(matches!(
+            result,
+            Err(OrderError::CurrencyMismatch {
+                expected: Currency::EUR,
+                actual: Currency::USD,
+            })
+        ));
+        let result = order.update_item_price(ProductId::new("SKU-001"), Money::from_cents(900));
+        assert!(matches!(result, Err(OrderError::CurrencyMismatch { .. })));
+
+        // A total cap in dollars doesn't apply to a euro order
+        let limits = OrderLimits::unlimited().with_max_total(Money::from_cents(1000));
+        let item = OrderItem::new("SKU-003", "Gizmo", 1, price);
+        assert!(order.add_item_within(item, &limits).is_ok());
     }
 
     #[test]
@@ -491,6 +1274,122 @@ mod tests {
         assert_eq!(order.item_count(), 0);
     }
 
+    #[test]
+    fn test_add_item_within_limits() {
+        let (mut order, _) = create_order();
+        let limits = OrderLimits::unlimited()
+            .with_max_items(2)
+            .with_max_line_quantity(5)
+            .with_max_total(Money::from_cents(10_000));
+        let widget =
+            |quantity| OrderItem::new("SKU-001", "Widget", quantity, Money::from_cents(1000));
+
+        order.apply_events(order.add_item_within(widget(3), &limits).unwrap());
+        assert!(matches!(
+            order.add_item_within(widget(3), &limits),
+            Err(OrderError::LineQuantityExceeded {
+                quantity: 6,
+                limit: 5,
+                ..
+            })
+        ));
+
+        let gadget = OrderItem::new("SKU-002", "Gadget", 1, Money::from_cents(7500));
+        assert!(matches!(
+            order.add_item_within(gadget, &limits),
+            Err(OrderError::OrderTotalExceeded { .. })
+        ));
+        let gadget = OrderItem::new("SKU-002", "Gadget", 1, Money::from_cents(7000));
+        order.apply_events(order.add_item_within(gadget, &limits).unwrap());
+        assert_eq!(order.total_amount(), Money::from_cents(10_000));
+
+        let gizmo = OrderItem::new("SKU-003", "Gizmo", 1, Money::from_cents(1));
+        assert!(matches!(
+            order.add_item_within(gizmo, &limits),
+            Err(OrderError::TooManyItems { limit: 2 })
+        ));
+    }
+
+    #[test]
+    fn test_update_item_quantity_within_limits_allows_decreases() {
+        let (mut order, _) = create_order();
+        let item = OrderItem::new("SKU-001", "Widget", 4, Money::from_cents(1000));
+        order.apply_events(order.add_item(item).unwrap());
+        let limits = OrderLimits::unlimited().with_max_total(Money::from_cents(3000));
+
+        // Already over a limit introduced after the item was added
+        let product_id = ProductId::new("SKU-001");
+        assert!(matches!(
+            order.update_item_quantity_within(product_id.clone(), 5, &limits),
+            Err(OrderError::OrderTotalExceeded { .. })
+        ));
+        order.apply_events(
+            order
+                .update_item_quantity_within(product_id, 2, &limits)
+                .unwrap(),
+        );
+        assert_eq!(order.total_amount(), Money::from_cents(2000));
+
+        // A total too large to represent is refused rather than wrapping
+        let bullion = OrderItem::new("SKU-002", "Bullion", 2, Money::from_cents(i64::MAX));
+        assert!(matches!(
+            order.add_item_within(bullion, &limits),
+            Err(OrderError::OrderTotalExceeded { .. })
+        ));
+    }
+
+    #[test]
+    fn test_update_item_price() {
+        let (mut order, _) = create_order();
+        let item = OrderItem::new("SKU-001", "Widget", 2, Money::from_cents(1000));
+        order.apply_events(order.add_item(item).unwrap());
+
+        let events = order
+            .update_item_price(ProductId::new("SKU-001"), Money::from_cents(1250))
+            .unwrap();
+        assert_eq!(events.len(), 1);
+        order.apply_events(events);
+
+        let item = order.get_item(&ProductId::new("SKU-001")).unwrap();
+        assert_eq!(item.unit_price.cents(), 1250);
+        assert_eq!(order.total_amount().cents(), 2500);
+    }
+
+    #[test]
+    fn test_update_item_price_unchanged_is_noop() {
+        let (mut order, _) = create_order();
+        let item = OrderItem::new("SKU-001", "Widget", 2, Money::from_cents(1000));
+        order.apply_events(order.add_item(item).unwrap());
+
+        let events = order
+            .update_item_price(ProductId::new("SKU-001"), Money::from_cents(1000))
+            .unwrap();
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn test_update_item_price_validation() {
+        let (mut order, _) = create_order();
+        let item = OrderItem::new("SKU-001", "Widget", 2, Money::from_cents(1000));
+        order.apply_events(order.add_item(item).unwrap());
+
+        assert!(matches!(
+            order.update_item_price(ProductId::new("SKU-001"), Money::zero()),
+            Err(OrderError::InvalidPrice { price: 0 })
+        ));
+        assert!(matches!(
+            order.update_item_price(ProductId::new("SKU-999"), Money::from_cents(500)),
+            Err(OrderError::ItemNotFound { .. })
+        ));
+
+        order.apply_events(order.submit().unwrap());
+        order.apply_events(order.mark_reserved(BTreeMap::new()).unwrap());
+        assert!(matches!(
+            order.update_item_price(ProductId::new("SKU-001"), Money::from_cents(500)),
+            Err(OrderError::InvalidStateTransition { .. })
+        ));
+    }
+
     #[test]
     fn test_submit_order() {
         let (mut order, _) = create_order();
@@ -511,6 +1410,202 @@ mod tests {
         assert!(matches!(result, Err(OrderError::NoItems)));
     }
 
+    fn order_for_pricing() -> Order {
+        let (mut order, _) = create_order();
+        for item in [
+            OrderItem::new("SKU-001", "Widget", 2, Money::from_cents(1000)),
+            OrderItem::new("SKU-002", "Gadget", 1, Money::from_cents(500)),
+            OrderItem::new("SKU-003", "Gizmo", 1, Money::from_cents(250)),
+        ] {
+            order.apply_events(order.add_item(item).unwrap());
+        }
+        order
+    }
+
+    fn catalog() -> InMemoryPriceCatalog {
+        let catalog = InMemoryPriceCatalog::new();
+        catalog.set_price("SKU-001", Money::from_cents(1200));
+        catalog.set_price("SKU-002", Money::from_cents(500));
+        // SKU-003 is not listed
+        catalog
+    }
+
+    #[test]
+    fn test_submit_with_prices_adjusts_drifted_items() {
+        let mut order = order_for_pricing();
+
+        let events = order
+            .submit_with_prices(&catalog(), PriceDriftPolicy::Adjust)
+            .unwrap();
+        let types: Vec<_> = events.iter().map(|e| e.event_type()).collect();
+        assert_eq!(types, ["ItemPriceAdjusted", "OrderSubmitted"]);
+        match &events[1] {
+            OrderEvent::OrderSubmitted(data) => assert_eq!(data.total_amount.cents(), 3150),
+            other => panic!("unexpected event: {other:?}"),
+        }
+
+        order.apply_events(events);
+        assert_eq!(order.total_amount().cents(), 3150);
+        let widget = order.items().find(|i| i.product_id.as_str() == "SKU-001");
+        assert_eq!(widget.unwrap().unit_price.cents(), 1200);
+    }
+
+    #[test]
+    fn test_submit_with_prices_rejects_drift() {
+        let order = order_for_pricing();
+
+        let result = order.submit_with_prices(&catalog(), PriceDriftPolicy::Reject);
+        let Err(OrderError::PriceDrift { drifts }) = result else {
+            panic!("expected price drift, got {result:?}");
+        };
+        assert_eq!(
+            drifts,
+            [PriceDrift {
+                product_id: ProductId::new("SKU-001"),
+                stored: Money::from_cents(1000),
+                current: Money::from_cents(1200),
+            }]
+        );
+        let message = OrderError::PriceDrift { drifts }.to_string();
+        assert!(message.contains("SKU-001 ($10.00 -> $12.00)"), "{message}");
+    }
+
+    #[test]
+    fn test_submit_with_prices_without_drift_matches_submit() {
+        let order = order_for_pricing();
+        let catalog = InMemoryPriceCatalog::new();
+        catalog.set_price("SKU-001", Money::from_cents(1000));
+
+        let events = order
+            .submit_with_prices(&catalog, PriceDriftPolicy::Reject)
+            .unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event_type(), "OrderSubmitted");
+
+        let empty = create_order().0;
+        assert!(matches!(
+            empty.submit_with_prices(&catalog, PriceDriftPolicy::Adjust),
+            Err(OrderError::NoItems)
+        ));
+    }
+
+    #[test]
+    fn test_complete_records_shipped_items() {
+        let (mut order, _) = create_order();
+        let item = OrderItem::new("SKU-001", "Widget", 2, Money::from_cents(1000));
+        order.apply_events(order.add_item(item).unwrap());
+        order.apply_events(order.submit().unwrap());
+        order.apply_events(order.mark_reserved(BTreeMap::new()).unwrap());
+        order.apply_events(order.start_processing(None).unwrap());
+
+        let unknown = order.complete(None, vec![ShippedItem::new("SKU-404")]);
+        assert!(matches!(
+            unknown,
+            Err(OrderError::InvalidShippedItem { .. })
+        ));
+
+        let too_many = order.complete(
+            None,
+            vec![ShippedItem::new("SKU-001").with_serial_numbers(["SN-1", "SN-2", "SN-3"])],
+        );
+        assert!(matches!(
+            too_many,
+            Err(OrderError::InvalidShippedItem { .. })
+        ));
+
+        let duplicated = order.complete(
+            None,
+            vec![ShippedItem::new("SKU-001"), ShippedItem::new("SKU-001")],
+        );
+        assert!(matches!(
+            duplicated,
+            Err(OrderError::InvalidShippedItem { .. })
+        ));
+
+        let shipped = ShippedItem::new("SKU-001")
+            .with_serial_numbers(["SN-1", "SN-2"])
+            .with_lot_number("LOT-7");
+        order.apply_events(order.complete(None, vec![shipped.clone()]).unwrap());
+        assert_eq!(order.state(), OrderState::Completed);
+        assert_eq!(
+            order.shipped_item(&ProductId::new("SKU-001")),
+            Some(&shipped)
+        );
+    }
+
+    #[test]
+    fn test_validate_emitted_rejects_conflicting_item_events() {
+        let (order, _) = create_order();
+        let added = OrderEvent::item_added(&OrderItem::new(
+            "SKU-001",
+            "Widget",
+            1,
+            Money::from_cents(1000),
+        ));
+        let other = OrderEvent::item_added(&OrderItem::new(
+            "SKU-002",
+            "Gadget",
+            1,
+            Money::from_cents(500),
+        ));
+        assert!(order.validate_emitted(&[added.clone(), other]).is_ok());
+
+        let removed = OrderEvent::item_removed(ProductId::new("SKU-001"));
+        let result = order.validate_emitted(&[added, removed]);
+        assert!(matches!(
+            result,
+            Err(OrderError::ConflictingItemEvents { product_id }) if product_id == "SKU-001"
+        ));
+    }
+
+    #[test]
+    fn test_mark_reserved_rejects_unknown_line_item() {
+        let (mut order, _) = create_order();
+        let item = OrderItem::new("SKU-001", "Widget", 2, Money::from_cents(1000));
+        order.apply_events(order.add_item(item).unwrap());
+        order.apply_events(order.submit().unwrap());
+
+        let reservations = BTreeMap::from([(ProductId::new("SKU-999"), "RES-1".to_string())]);
+        let result = order.mark_reserved(reservations);
+        assert!(matches!(
+            result,
+            Err(OrderError::ItemNotFound { product_id }) if product_id == "SKU-999"
+        ));
+        assert!(order.reservations().is_empty());
+    }
+
+    #[test]
+    fn test_renew_reservation_replaces_lapsed_one() {
+        let (mut order, _) = create_order();
+        let item = OrderItem::new("SKU-001", "Widget", 2, Money::from_cents(1000));
+        order.apply_events(order.add_item(item).unwrap());
+        order.apply_events(order.submit().unwrap());
+
+        let renewed = BTreeMap::from([(ProductId::new("SKU-001"), "RES-2".to_string())]);
+        // Not yet reserved
+        let result = order.renew_reservations(renewed.clone());
+        assert!(matches!(
+            result,
+            Err(OrderError::InvalidStateTransition { .. })
+        ));
+
+        let reservations = BTreeMap::from([(ProductId::new("SKU-001"), "RES-1".to_string())]);
+        order.apply_events(order.mark_reserved(reservations).unwrap());
+        let unknown = BTreeMap::from([(ProductId::new("SKU-999"), "RES-2".to_string())]);
+        let result = order.renew_reservations(unknown);
+        assert!(matches!(result, Err(OrderError::ItemNotFound { .. })));
+
+        let events = order.renew_reservations(renewed).unwrap();
+        assert!(matches!(
+            &events[0],
+            OrderEvent::ReservationRenewed(data)
+                if data.previous_reservation_id.as_deref() == Some("RES-1")
+        ));
+        order.apply_events(events);
+        assert_eq!(order.state(), OrderState::Reserved);
+        assert_eq!(order.reservation(&ProductId::new("SKU-001")), Some("RES-2"));
+    }
+
     #[test]
     fn test_full_order_lifecycle() {
         let (mut order, _) = create_order();
@@ -523,9 +1618,14 @@ mod tests {
         order.apply_events(order.submit().unwrap());
 
         // Reserve
-        let events = order.mark_reserved(Some("RES-123".to_string())).unwrap();
+        let reservations = BTreeMap::from([(ProductId::new("SKU-001"), "RES-123".to_string())]);
+        let events = order.mark_reserved(reservations).unwrap();
         order.apply_events(events);
         assert_eq!(order.state(), OrderState::Reserved);
+        assert_eq!(
+            order.reservation(&ProductId::new("SKU-001")),
+            Some("RES-123")
+        );
 
         // Start processing
         let events = order.start_processing(Some("PAY-123".to_string())).unwrap();
@@ -533,32 +1633,297 @@ mod tests {
         assert_eq!(order.state(), OrderState::Processing);
 
         // Complete
-        let events = order.complete(Some("TRACK-123".to_string())).unwrap();
+        let events = order
+            .complete(Some("TRACK-123".to_string()), vec![])
+            .unwrap();
         order.apply_events(events);
         assert_eq!(order.state(), OrderState::Completed);
+        assert!(!order.is_terminal());
+
+        // Carrier callbacks
+        let events = order
+            .mark_shipped("UPS", Some("TRACK-123".to_string()), Utc::now())
+            .unwrap();
+        order.apply_events(events);
+        assert_eq!(order.state(), OrderState::Shipped);
+        assert_eq!(order.carrier(), Some("UPS"));
+        assert_eq!(order.tracking_number(), Some("TRACK-123"));
+
+        let events = order
+            .mark_in_transit(Some("Memphis, TN".to_string()), Utc::now())
+            .unwrap();
+        order.apply_events(events);
+        assert_eq!(order.state(), OrderState::InTransit);
+        assert_eq!(order.last_location(), Some("Memphis, TN"));
+
+        let events = order
+            .mark_delivered(Some("J. Doe".to_string()), Utc::now())
+            .unwrap();
+        order.apply_events(events);
+        assert_eq!(order.state(), OrderState::Delivered);
         assert!(order.is_terminal());
     }
 
+    #[test]
+    fn test_failed_delivery_can_be_reshipped() {
+        let (mut order, _) = create_order();
+        let item = OrderItem::new("SKU-001", "Widget", 1, Money::from_cents(1000));
+        order.apply_events(order.add_item(item).unwrap());
+        order.apply_events(order.submit().unwrap());
+        order.apply_events(order.mark_reserved(BTreeMap::new()).unwrap());
+        order.apply_events(order.start_processing(None).unwrap());
+
+        // Carrier can't deliver what hasn't shipped
+        assert!(matches!(
+            order.mark_delivered(None, Utc::now()),
+            Err(OrderError::InvalidStateTransition { .. })
+        ));
+
+        order.apply_events(order.complete(None, vec![]).unwrap());
+        assert!(matches!(
+            order.mark_in_transit(None, Utc::now()),
+            Err(OrderError::InvalidStateTransition { .. })
+        ));
+        order.apply_events(order.mark_shipped("UPS", None, Utc::now()).unwrap());
+        order.apply_events(
+            order
+                .record_delivery_failure("Recipient unavailable", Utc::now())
+                .unwrap(),
+        );
+        assert_eq!(order.state(), OrderState::DeliveryFailed);
+        assert!(matches!(
+            order.mark_delivered(None, Utc::now()),
+            Err(OrderError::InvalidStateTransition { .. })
+        ));
+
+        order.apply_events(order.mark_shipped("UPS", None, Utc::now()).unwrap());
+        assert_eq!(order.state(), OrderState::Shipped);
+    }
+
+    #[test]
+    fn test_add_and_remove_tags() {
+        let (mut order, _) = create_order();
+
+        let events = order.add_tag("  Wholesale ").unwrap();
+        assert_eq!(events.len(), 1);
+        order.apply_events(events);
+        order.apply_events(order.add_tag("priority-review").unwrap());
+        assert_eq!(
+            order.tags().collect::<Vec<_>>(),
+            ["priority-review", "wholesale"]
+        );
+        assert!(order.has_tag("WHOLESALE"));
+
+        // Re-adding or removing an absent tag changes nothing
+        assert!(order.add_tag("wholesale").unwrap().is_empty());
+        assert!(order.remove_tag("clearance").unwrap().is_empty());
+
+        order.apply_events(order.remove_tag("wholesale").unwrap());
+        assert!(!order.has_tag("wholesale"));
+    }
+
+    #[test]
+    fn test_tags_allowed_after_completion() {
+        let (mut order, _) = create_order();
+        order.apply_events(vec![OrderEvent::order_cancelled("Customer request", None)]);
+
+        let events = order.add_tag("refund-audit").unwrap();
+        assert_eq!(events.len(), 1);
+    }
+
+    #[test]
+    fn test_invalid_tags_rejected() {
+        let (order, _) = create_order();
+
+        for tag in ["", "   ", "two words", "café", &"x".repeat(65)] {
+            let err = order.add_tag(tag).unwrap_err();
+            assert!(matches!(err, OrderError::InvalidTag { .. }), "{tag:?}");
+        }
+    }
+
+    #[test]
+    fn test_set_and_clear_metadata() {
+        let (mut order, _) = create_order();
+
+        let events = order
+            .set_metadata("gift_message", Some(" Happy birthday! ".to_string()))
+            .unwrap();
+        assert_eq!(events.len(), 1);
+        order.apply_events(events);
+        assert_eq!(
+            order.metadata_value("gift_message"),
+            Some("Happy birthday!")
+        );
+
+        // Setting the same value changes nothing
+        assert!(
+            order
+                .set_metadata("gift_message", Some("Happy birthday!".to_string()))
+                .unwrap()
+                .is_empty()
+        );
+
+        order.apply_events(
+            order
+                .set_metadata("gift_message", Some("  ".to_string()))
+                .unwrap(),
+        );
+        assert!(order.metadata().is_empty());
+    }
+
+    #[test]
+    fn test_invalid_metadata_rejected() {
+        let (order, _) = create_order();
+
+        let err = order
+            .set_metadata("coupon", Some("FREE".to_string()))
+            .unwrap_err();
+        assert!(matches!(err, OrderError::InvalidMetadata { .. }));
+
+        let err = order
+            .set_metadata("po_number", Some("x".repeat(65)))
+            .unwrap_err();
+        assert!(matches!(err, OrderError::InvalidMetadata { .. }));
+    }
+
+    #[test]
+    fn test_metadata_locked_once_reserved() {
+        let (mut order, _) = create_order();
+        let item = OrderItem::new("SKU-001", "Widget", 1, Money::from_cents(1000));
+        order.apply_events(order.add_item(item).unwrap());
+        order.apply_events(order.submit().unwrap());
+        order.apply_events(order.mark_reserved(BTreeMap::new()).unwrap());
+
+        assert!(matches!(
+            order.set_metadata("po_number", Some("PO-1".to_string())),
+            Err(OrderError::InvalidStateTransition { .. })
+        ));
+    }
+
+    #[test]
+    fn test_set_and_update_shipping_address() {
+        let (mut order, _) = create_order();
+        let address = Address::new("1 Main St", "Springfield", "12345", "us");
+
+        assert!(matches!(
+            order.update_shipping_address(address.clone()),
+            Err(OrderError::NoShippingAddress)
+        ));
+        order.apply_events(order.set_shipping_address(address.clone()).unwrap());
+        assert_eq!(order.shipping_address().unwrap().country, "US");
+        assert!(matches!(
+            order.set_shipping_address(address.clone()),
+            Err(OrderError::ShippingAddressAlreadySet)
+        ));
+
+        // The same address after validation changes nothing
+        assert!(order.update_shipping_address(address).unwrap().is_empty());
+        let moved = Address::new("9 Elm St", "Springfield", "12345", "US");
+        let events = order.update_shipping_address(moved.clone()).unwrap();
+        assert!(matches!(
+            &events[0],
+            OrderEvent::ShippingAddressUpdated(data) if data.previous.line1 == "1 Main St"
+        ));
+        order.apply_events(events);
+        assert_eq!(order.shipping_address(), Some(&moved));
+
+        assert!(matches!(
+            order.update_shipping_address(Address::new("", "Springfield", "12345", "US")),
+            Err(OrderError::InvalidAddress { field: "line1", .. })
+        ));
+    }
+
+    #[test]
+    fn test_shipping_address_locked_once_completed() {
+        let (mut order, _) = create_order();
+        let item = OrderItem::new("SKU-001", "Widget", 1, Money::from_cents(1000));
+        order.apply_events(order.add_item(item).unwrap());
+        order.apply_events(order.submit().unwrap());
+        order.apply_events(order.mark_reserved(BTreeMap::new()).unwrap());
+        order.apply_events(order.start_processing(None).unwrap());
+
+        // Still changeable while processing
+        let address = Address::new("1 Main St", "Springfield", "12345", "US");
+        order.apply_events(order.set_shipping_address(address.clone()).unwrap());
+        order.apply_events(order.complete(None, vec![]).unwrap());
+
+        assert!(matches!(
+            order.update_shipping_address(address.with_line2("Apt 4")),
+            Err(OrderError::InvalidStateTransition { .. })
+        ));
+    }
+
     #[test]
     fn test_cancel_order() {
         let (mut order, _) = create_order();
         let item = OrderItem::new("SKU-001", "Widget", 2, Money::from_cents(1000));
         order.apply_events(order.add_item(item).unwrap());
 
-        let events = order.cancel("Customer request", None).unwrap();
+        let events = order.cancel("Customer request", None, None).unwrap();
         order.apply_events(events);
 
         assert_eq!(order.state(), OrderState::Cancelled);
         assert!(order.is_terminal());
     }
 
+    #[test]
+    fn test_substitute_item_moves_line_and_reservation() {
+        let (mut order, _) = create_order();
+        let item = OrderItem::new("SKU-001", "Widget", 2, Money::from_cents(1000));
+        order.apply_events(order.add_item(item).unwrap());
+        let item = OrderItem::new("SKU-002", "Gadget", 1, Money::from_cents(500));
+        order.apply_events(order.add_item(item).unwrap());
+        order.apply_events(order.submit().unwrap());
+
+        let substitute = OrderItem::new("SKU-003", "Widget Plus", 0, Money::from_cents(1200));
+        // Items can't be substituted before inventory is reserved
+        assert!(matches!(
+            order.substitute_item(&ProductId::new("SKU-001"), substitute.clone(), None),
+            Err(OrderError::InvalidStateTransition { .. })
+        ));
+
+        let reservations = BTreeMap::from([
+            (ProductId::new("SKU-001"), "RES-1".to_string()),
+            (ProductId::new("SKU-002"), "RES-2".to_string()),
+        ]);
+        order.apply_events(order.mark_reserved(reservations).unwrap());
+
+        let taken = OrderItem::new("SKU-002", "Gadget", 0, Money::from_cents(500));
+        assert!(matches!(
+            order.substitute_item(&ProductId::new("SKU-001"), taken, None),
+            Err(OrderError::InvalidSubstitution { product_id, .. }) if product_id == "SKU-002"
+        ));
+
+        let events = order
+            .substitute_item(
+                &ProductId::new("SKU-001"),
+                substitute,
+                Some("RES-3".to_string()),
+            )
+            .unwrap();
+        let OrderEvent::ItemSubstituted(data) = &events[0] else {
+            panic!("expected ItemSubstituted");
+        };
+        assert_eq!(data.quantity, 2);
+        assert_eq!(data.price_delta, Money::from_cents(400));
+        order.apply_events(events);
+
+        assert!(order.get_item(&ProductId::new("SKU-001")).is_none());
+        let line = order.get_item(&ProductId::new("SKU-003")).unwrap();
+        assert_eq!(line.quantity, 2);
+        assert_eq!(order.total_amount(), Money::from_cents(2900));
+        assert_eq!(order.reservation(&ProductId::new("SKU-001")), None);
+        assert_eq!(order.reservation(&ProductId::new("SKU-003")), Some("RES-3"));
+        assert_eq!(order.state(), OrderState::Reserved);
+    }
+
     #[test]
     fn test_cannot_modify_after_reserved() {
         let (mut order, _) = create_order();
         let item = OrderItem::new("SKU-001", "Widget", 2, Money::from_cents(1000));
         order.apply_events(order.add_item(item).unwrap());
         order.apply_events(order.submit().unwrap());
-        order.apply_events(order.mark_reserved(None).unwrap());
+        order.apply_events(order.mark_reserved(BTreeMap::new()).unwrap());
 
         let item2 = OrderItem::new("SKU-002", "Gadget", 1, Money::from_cents(500));
         let result = order.add_item(item2);
@@ -575,11 +1940,11 @@ mod tests {
         let item = OrderItem::new("SKU-001", "Widget", 1, Money::from_cents(1000));
         order.apply_events(order.add_item(item).unwrap());
         order.apply_events(order.submit().unwrap());
-        order.apply_events(order.mark_reserved(None).unwrap());
+        order.apply_events(order.mark_reserved(BTreeMap::new()).unwrap());
         order.apply_events(order.start_processing(None).unwrap());
-        order.apply_events(order.complete(None).unwrap());
+        order.apply_events(order.complete(None, vec![]).unwrap());
 
-        let result = order.cancel("Too late", None);
+        let result = order.cancel("Too late", None, None);
         assert!(matches!(
             result,
             Err(OrderError::InvalidStateTransition { .. })
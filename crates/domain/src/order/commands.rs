@@ -1,10 +1,14 @@
 //! Order commands.
 
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
 use common::AggregateId;
 
 use crate::command::Command;
+use crate::metadata::CommandMetadata;
 
-use super::{CustomerId, Money, Order, OrderItem, ProductId};
+use super::{Address, Currency, CustomerId, Money, Order, OrderItem, ProductId, ShippedItem};
 
 /// Command to create a new order.
 #[derive(Debug, Clone)]
@@ -14,6 +18,12 @@ pub struct CreateOrder {
 
     /// The customer placing the order.
     pub customer_id: CustomerId,
+
+    /// Currency the order is denominated in.
+    pub currency: Currency,
+
+    /// Items the order is created with, appended together with it.
+    pub items: Vec<OrderItem>,
 }
 
 impl CreateOrder {
@@ -22,15 +32,33 @@ impl CreateOrder {
         Self {
             order_id,
             customer_id,
+            currency: Currency::default(),
+            items: Vec::new(),
         }
     }
 
     /// Creates a new CreateOrder command with a generated order ID.
+    ///
+    /// Within a [`with_command_ids`](crate::processed::with_command_ids)
+    /// scope the ID is derived from the scope, so a retried request creates
+    /// the order the first attempt did rather than a second one.
     pub fn for_customer(customer_id: CustomerId) -> Self {
-        Self {
-            order_id: AggregateId::new(),
-            customer_id,
-        }
+        let order_id = crate::processed::scoped_uuid("order")
+            .map(AggregateId::from_uuid)
+            .unwrap_or_default();
+        Self::new(order_id, customer_id)
+    }
+
+    /// Sets the currency the order is denominated in.
+    pub fn with_currency(mut self, currency: Currency) -> Self {
+        self.currency = currency;
+        self
+    }
+
+    /// Sets the items the order is created with.
+    pub fn with_items(mut self, items: Vec<OrderItem>) -> Self {
+        self.items = items;
+        self
     }
 }
 
@@ -141,6 +169,106 @@ impl Command for UpdateItemQuantity {
     }
 }
 
+/// Command to change the unit price of an item.
+#[derive(Debug, Clone)]
+pub struct UpdateItemPrice {
+    /// The order containing the item.
+    pub order_id: AggregateId,
+
+    /// The product to update.
+    pub product_id: ProductId,
+
+    /// The new unit price.
+    pub new_unit_price: Money,
+}
+
+impl UpdateItemPrice {
+    /// Creates a new UpdateItemPrice command.
+    pub fn new(
+        order_id: AggregateId,
+        product_id: impl Into<ProductId>,
+        new_unit_price: Money,
+    ) -> Self {
+        Self {
+            order_id,
+            product_id: product_id.into(),
+            new_unit_price,
+        }
+    }
+}
+
+impl Command for UpdateItemPrice {
+    type Aggregate = Order;
+
+    fn aggregate_id(&self) -> AggregateId {
+        self.order_id
+    }
+}
+
+/// Command to replace an item with a substitute product during fulfillment.
+#[derive(Debug, Clone)]
+pub struct SubstituteItem {
+    /// The order containing the item.
+    pub order_id: AggregateId,
+
+    /// The product being replaced.
+    pub original_product_id: ProductId,
+
+    /// The substitute; its quantity is taken from the original line.
+    pub substitute: OrderItem,
+
+    /// Inventory reservation held for the substitute.
+    pub reservation_id: Option<String>,
+
+    /// Metadata attached to the appended events.
+    pub metadata: CommandMetadata,
+}
+
+impl SubstituteItem {
+    /// Creates a new SubstituteItem command.
+    pub fn new(
+        order_id: AggregateId,
+        original_product_id: impl Into<ProductId>,
+        substitute_product_id: impl Into<ProductId>,
+        substitute_product_name: impl Into<String>,
+        unit_price: Money,
+    ) -> Self {
+        Self {
+            order_id,
+            original_product_id: original_product_id.into(),
+            substitute: OrderItem::new(
+                substitute_product_id,
+                substitute_product_name,
+                0,
+                unit_price,
+            ),
+            reservation_id: None,
+            metadata: CommandMetadata::new(),
+        }
+    }
+
+    /// Sets the inventory reservation held for the substitute.
+    pub fn with_reservation(mut self, reservation_id: impl Into<String>) -> Self {
+        self.reservation_id = Some(reservation_id.into());
+        self
+    }
+
+    /// Attaches metadata, such as who approved the substitution, to the
+    /// appended events.
+    pub fn with_metadata(mut self, metadata: CommandMetadata) -> Self {
+        self.metadata = metadata;
+        self
+    }
+}
+
+impl Command for SubstituteItem {
+    type Aggregate = Order;
+
+    fn aggregate_id(&self) -> AggregateId {
+        self.order_id
+    }
+}
+
 /// Command to submit an order for processing.
 #[derive(Debug, Clone)]
 pub struct SubmitOrder {
@@ -174,6 +302,12 @@ pub struct CancelOrder {
 
     /// Who is cancelling the order.
     pub cancelled_by: Option<String>,
+
+    /// Machine-readable cause recorded alongside the reason.
+    pub reason_code: Option<String>,
+
+    /// Metadata attached to the appended events.
+    pub metadata: CommandMetadata,
 }
 
 impl CancelOrder {
@@ -187,8 +321,23 @@ impl CancelOrder {
             order_id,
             reason: reason.into(),
             cancelled_by,
+            reason_code: None,
+            metadata: CommandMetadata::new(),
         }
     }
+
+    /// Records a machine-readable cause, such as `payment_declined`.
+    pub fn with_reason_code(mut self, reason_code: impl Into<String>) -> Self {
+        self.reason_code = Some(reason_code.into());
+        self
+    }
+
+    /// Attaches metadata, such as what caused the cancellation, to the
+    /// appended events.
+    pub fn with_metadata(mut self, metadata: CommandMetadata) -> Self {
+        self.metadata = metadata;
+        self
+    }
 }
 
 impl Command for CancelOrder {
@@ -205,18 +354,35 @@ pub struct MarkReserved {
     /// The order to mark as reserved.
     pub order_id: AggregateId,
 
-    /// Reservation reference ID.
-    pub reservation_id: Option<String>,
+    /// Reservation reference ID per line item.
+    pub reservations: BTreeMap<ProductId, String>,
+
+    /// Metadata attached to the appended events.
+    pub metadata: CommandMetadata,
 }
 
 impl MarkReserved {
     /// Creates a new MarkReserved command.
-    pub fn new(order_id: AggregateId, reservation_id: Option<String>) -> Self {
+    pub fn new(order_id: AggregateId) -> Self {
         Self {
             order_id,
-            reservation_id,
+            reservations: BTreeMap::new(),
+            metadata: CommandMetadata::new(),
         }
     }
+
+    /// Sets the reservation ID held for each line item.
+    pub fn with_reservations(mut self, reservations: BTreeMap<ProductId, String>) -> Self {
+        self.reservations = reservations;
+        self
+    }
+
+    /// Attaches metadata, such as what caused the reservation, to the
+    /// appended events.
+    pub fn with_metadata(mut self, metadata: CommandMetadata) -> Self {
+        self.metadata = metadata;
+        self
+    }
 }
 
 impl Command for MarkReserved {
@@ -227,6 +393,45 @@ impl Command for MarkReserved {
     }
 }
 
+/// Command to replace line items' lapsed inventory reservations.
+#[derive(Debug, Clone)]
+pub struct RenewReservations {
+    /// The reserved order.
+    pub order_id: AggregateId,
+
+    /// The new reservation ID per renewed line item.
+    pub reservations: BTreeMap<ProductId, String>,
+
+    /// Metadata attached to the appended events.
+    pub metadata: CommandMetadata,
+}
+
+impl RenewReservations {
+    /// Creates a new RenewReservations command.
+    pub fn new(order_id: AggregateId, reservations: BTreeMap<ProductId, String>) -> Self {
+        Self {
+            order_id,
+            reservations,
+            metadata: CommandMetadata::new(),
+        }
+    }
+
+    /// Attaches metadata, such as what noticed the lapse, to the appended
+    /// events.
+    pub fn with_metadata(mut self, metadata: CommandMetadata) -> Self {
+        self.metadata = metadata;
+        self
+    }
+}
+
+impl Command for RenewReservations {
+    type Aggregate = Order;
+
+    fn aggregate_id(&self) -> AggregateId {
+        self.order_id
+    }
+}
+
 /// Command to start processing an order.
 #[derive(Debug, Clone)]
 pub struct StartProcessing {
@@ -235,6 +440,9 @@ pub struct StartProcessing {
 
     /// Payment reference ID.
     pub payment_id: Option<String>,
+
+    /// Metadata attached to the appended events.
+    pub metadata: CommandMetadata,
 }
 
 impl StartProcessing {
@@ -243,8 +451,16 @@ impl StartProcessing {
         Self {
             order_id,
             payment_id,
+            metadata: CommandMetadata::new(),
         }
     }
+
+    /// Attaches metadata, such as what caused processing to start, to the
+    /// appended events.
+    pub fn with_metadata(mut self, metadata: CommandMetadata) -> Self {
+        self.metadata = metadata;
+        self
+    }
 }
 
 impl Command for StartProcessing {
@@ -263,6 +479,12 @@ pub struct CompleteOrder {
 
     /// Shipment tracking number.
     pub tracking_number: Option<String>,
+
+    /// Serial and lot numbers reported by the warehouse, per line item.
+    pub shipped_items: Vec<ShippedItem>,
+
+    /// Metadata attached to the appended events.
+    pub metadata: CommandMetadata,
 }
 
 impl CompleteOrder {
@@ -271,8 +493,23 @@ impl CompleteOrder {
         Self {
             order_id,
             tracking_number,
+            shipped_items: Vec::new(),
+            metadata: CommandMetadata::new(),
         }
     }
+
+    /// Sets the serial and lot numbers of the shipped items.
+    pub fn with_shipped_items(mut self, shipped_items: Vec<ShippedItem>) -> Self {
+        self.shipped_items = shipped_items;
+        self
+    }
+
+    /// Attaches metadata, such as what caused the completion, to the
+    /// appended events.
+    pub fn with_metadata(mut self, metadata: CommandMetadata) -> Self {
+        self.metadata = metadata;
+        self
+    }
 }
 
 impl Command for CompleteOrder {
@@ -283,6 +520,338 @@ impl Command for CompleteOrder {
     }
 }
 
+/// Command to record that the carrier picked up an order.
+#[derive(Debug, Clone)]
+pub struct MarkShipped {
+    /// The order that shipped.
+    pub order_id: AggregateId,
+
+    /// Carrier handling the shipment.
+    pub carrier: String,
+
+    /// Carrier tracking number.
+    pub tracking_number: Option<String>,
+
+    /// When the carrier picked up the shipment.
+    pub shipped_at: DateTime<Utc>,
+
+    /// Metadata attached to the appended events.
+    pub metadata: CommandMetadata,
+}
+
+impl MarkShipped {
+    /// Creates a new MarkShipped command.
+    pub fn new(
+        order_id: AggregateId,
+        carrier: impl Into<String>,
+        tracking_number: Option<String>,
+        shipped_at: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            order_id,
+            carrier: carrier.into(),
+            tracking_number,
+            shipped_at,
+            metadata: CommandMetadata::new(),
+        }
+    }
+
+    /// Attaches metadata, such as the webhook delivery that reported the
+    /// update, to the appended events.
+    pub fn with_metadata(mut self, metadata: CommandMetadata) -> Self {
+        self.metadata = metadata;
+        self
+    }
+}
+
+impl Command for MarkShipped {
+    type Aggregate = Order;
+
+    fn aggregate_id(&self) -> AggregateId {
+        self.order_id
+    }
+}
+
+/// Command to record a carrier transit update for an order.
+#[derive(Debug, Clone)]
+pub struct MarkInTransit {
+    /// The order in transit.
+    pub order_id: AggregateId,
+
+    /// Last known location of the shipment.
+    pub location: Option<String>,
+
+    /// When the carrier reported the update.
+    pub updated_at: DateTime<Utc>,
+
+    /// Metadata attached to the appended events.
+    pub metadata: CommandMetadata,
+}
+
+impl MarkInTransit {
+    /// Creates a new MarkInTransit command.
+    pub fn new(order_id: AggregateId, location: Option<String>, updated_at: DateTime<Utc>) -> Self {
+        Self {
+            order_id,
+            location,
+            updated_at,
+            metadata: CommandMetadata::new(),
+        }
+    }
+
+    /// Attaches metadata, such as the webhook delivery that reported the
+    /// update, to the appended events.
+    pub fn with_metadata(mut self, metadata: CommandMetadata) -> Self {
+        self.metadata = metadata;
+        self
+    }
+}
+
+impl Command for MarkInTransit {
+    type Aggregate = Order;
+
+    fn aggregate_id(&self) -> AggregateId {
+        self.order_id
+    }
+}
+
+/// Command to record that the carrier delivered an order.
+#[derive(Debug, Clone)]
+pub struct MarkDelivered {
+    /// The order that was delivered.
+    pub order_id: AggregateId,
+
+    /// Who signed for the shipment.
+    pub received_by: Option<String>,
+
+    /// When the shipment was delivered.
+    pub delivered_at: DateTime<Utc>,
+
+    /// Metadata attached to the appended events.
+    pub metadata: CommandMetadata,
+}
+
+impl MarkDelivered {
+    /// Creates a new MarkDelivered command.
+    pub fn new(
+        order_id: AggregateId,
+        received_by: Option<String>,
+        delivered_at: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            order_id,
+            received_by,
+            delivered_at,
+            metadata: CommandMetadata::new(),
+        }
+    }
+
+    /// Attaches metadata, such as the webhook delivery that reported the
+    /// update, to the appended events.
+    pub fn with_metadata(mut self, metadata: CommandMetadata) -> Self {
+        self.metadata = metadata;
+        self
+    }
+}
+
+impl Command for MarkDelivered {
+    type Aggregate = Order;
+
+    fn aggregate_id(&self) -> AggregateId {
+        self.order_id
+    }
+}
+
+/// Command to record a failed delivery attempt.
+#[derive(Debug, Clone)]
+pub struct RecordDeliveryFailure {
+    /// The order whose delivery failed.
+    pub order_id: AggregateId,
+
+    /// Reason reported by the carrier.
+    pub reason: String,
+
+    /// When the delivery attempt failed.
+    pub failed_at: DateTime<Utc>,
+
+    /// Metadata attached to the appended events.
+    pub metadata: CommandMetadata,
+}
+
+impl RecordDeliveryFailure {
+    /// Creates a new RecordDeliveryFailure command.
+    pub fn new(order_id: AggregateId, reason: impl Into<String>, failed_at: DateTime<Utc>) -> Self {
+        Self {
+            order_id,
+            reason: reason.into(),
+            failed_at,
+            metadata: CommandMetadata::new(),
+        }
+    }
+
+    /// Attaches metadata, such as the webhook delivery that reported the
+    /// update, to the appended events.
+    pub fn with_metadata(mut self, metadata: CommandMetadata) -> Self {
+        self.metadata = metadata;
+        self
+    }
+}
+
+impl Command for RecordDeliveryFailure {
+    type Aggregate = Order;
+
+    fn aggregate_id(&self) -> AggregateId {
+        self.order_id
+    }
+}
+
+/// Command to attach a tag to an order.
+#[derive(Debug, Clone)]
+pub struct AddTag {
+    /// The order to tag.
+    pub order_id: AggregateId,
+
+    /// The tag to attach; normalized to lowercase.
+    pub tag: String,
+}
+
+impl AddTag {
+    /// Creates a new AddTag command.
+    pub fn new(order_id: AggregateId, tag: impl Into<String>) -> Self {
+        Self {
+            order_id,
+            tag: tag.into(),
+        }
+    }
+}
+
+impl Command for AddTag {
+    type Aggregate = Order;
+
+    fn aggregate_id(&self) -> AggregateId {
+        self.order_id
+    }
+}
+
+/// Command to remove a tag from an order.
+#[derive(Debug, Clone)]
+pub struct RemoveTag {
+    /// The order to untag.
+    pub order_id: AggregateId,
+
+    /// The tag to remove.
+    pub tag: String,
+}
+
+impl RemoveTag {
+    /// Creates a new RemoveTag command.
+    pub fn new(order_id: AggregateId, tag: impl Into<String>) -> Self {
+        Self {
+            order_id,
+            tag: tag.into(),
+        }
+    }
+}
+
+impl Command for RemoveTag {
+    type Aggregate = Order;
+
+    fn aggregate_id(&self) -> AggregateId {
+        self.order_id
+    }
+}
+
+/// Command to set or clear a metadata attachment on a draft order.
+#[derive(Debug, Clone)]
+pub struct SetOrderMetadata {
+    /// The order to update.
+    pub order_id: AggregateId,
+
+    /// The attachment key, such as `gift_message` or `po_number`.
+    pub key: String,
+
+    /// The new value, or None to clear the key.
+    pub value: Option<String>,
+}
+
+impl SetOrderMetadata {
+    /// Creates a command setting `key` to `value`.
+    pub fn new(order_id: AggregateId, key: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            order_id,
+            key: key.into(),
+            value: Some(value.into()),
+        }
+    }
+
+    /// Creates a command clearing `key`.
+    pub fn clear(order_id: AggregateId, key: impl Into<String>) -> Self {
+        Self {
+            order_id,
+            key: key.into(),
+            value: None,
+        }
+    }
+}
+
+impl Command for SetOrderMetadata {
+    type Aggregate = Order;
+
+    fn aggregate_id(&self) -> AggregateId {
+        self.order_id
+    }
+}
+
+/// Command to give an order its shipping address.
+#[derive(Debug, Clone)]
+pub struct SetShippingAddress {
+    /// The order to update.
+    pub order_id: AggregateId,
+
+    /// The address to ship to.
+    pub address: Address,
+}
+
+impl SetShippingAddress {
+    /// Creates a new SetShippingAddress command.
+    pub fn new(order_id: AggregateId, address: Address) -> Self {
+        Self { order_id, address }
+    }
+}
+
+impl Command for SetShippingAddress {
+    type Aggregate = Order;
+
+    fn aggregate_id(&self) -> AggregateId {
+        self.order_id
+    }
+}
+
+/// Command to change an order's shipping address.
+#[derive(Debug, Clone)]
+pub struct UpdateShippingAddress {
+    /// The order to update.
+    pub order_id: AggregateId,
+
+    /// The new address to ship to.
+    pub address: Address,
+}
+
+impl UpdateShippingAddress {
+    /// Creates a new UpdateShippingAddress command.
+    pub fn new(order_id: AggregateId, address: Address) -> Self {
+        Self { order_id, address }
+    }
+}
+
+impl Command for UpdateShippingAddress {
+    type Aggregate = Order;
+
+    fn aggregate_id(&self) -> AggregateId {
+        self.order_id
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,211 @@
+//! Differences between two versions of an order.
+//!
+//! Support tooling replays an order at two versions and compares the
+//! resulting states, rather than reading the raw events in between.
+
+use std::collections::HashMap;
+
+use event_store::Version;
+use serde::Serialize;
+
+use super::{Address, Money, Order, OrderItem, OrderState, ProductId};
+
+/// A field's value before and after.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Change<T> {
+    pub from: T,
+    pub to: T,
+}
+
+impl<T: PartialEq> Change<T> {
+    /// Returns a change if the values differ.
+    fn between(from: T, to: T) -> Option<Self> {
+        (from != to).then_some(Self { from, to })
+    }
+}
+
+/// An item present at both versions whose quantity or price changed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ItemChange {
+    pub product_id: ProductId,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quantity: Option<Change<u32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unit_price: Option<Change<Money>>,
+}
+
+/// What changed on an order between two versions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OrderDiff {
+    pub from_version: Version,
+    pub to_version: Version,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state: Option<Change<OrderState>>,
+    /// Items present only at the later version.
+    pub items_added: Vec<OrderItem>,
+    /// Items present only at the earlier version.
+    pub items_removed: Vec<OrderItem>,
+    pub items_changed: Vec<ItemChange>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_amount: Option<Change<Money>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub carrier: Option<Change<Option<String>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tracking_number: Option<Change<Option<String>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shipping_address: Option<Change<Option<Address>>>,
+}
+
+impl OrderDiff {
+    /// Compares two replayed states of the same order.
+    ///
+    /// Items are listed in product ID order.
+    pub fn between(from: &Order, to: &Order) -> Self {
+        use crate::Aggregate;
+
+        let before: HashMap<&ProductId, &OrderItem> =
+            from.items().map(|item| (&item.product_id, item)).collect();
+        let after: HashMap<&ProductId, &OrderItem> =
+            to.items().map(|item| (&item.product_id, item)).collect();
+
+        let mut items_added: Vec<OrderItem> = after
+            .iter()
+            .filter(|(id, _)| !before.contains_key(*id))
+            .map(|(_, item)| (*item).clone())
+            .collect();
+        let mut items_removed: Vec<OrderItem> = before
+            .iter()
+            .filter(|(id, _)| !after.contains_key(*id))
+            .map(|(_, item)| (*item).clone())
+            .collect();
+        let mut items_changed: Vec<ItemChange> = before
+            .iter()
+            .filter_map(|(id, old)| {
+                let new = after.get(id)?;
+                let change = ItemChange {
+                    product_id: (*id).clone(),
+                    quantity: Change::between(old.quantity, new.quantity),
+                    unit_price: Change::between(old.unit_price, new.unit_price),
+                };
+                (change.quantity.is_some() || change.unit_price.is_some()).then_some(change)
+            })
+            .collect();
+        items_added.sort_by(|a, b| a.product_id.as_str().cmp(b.product_id.as_str()));
+        items_removed.sort_by(|a, b| a.product_id.as_str().cmp(b.product_id.as_str()));
+        items_changed.sort_by(|a, b| a.product_id.as_str().cmp(b.product_id.as_str()));
+
+        Self {
+            from_version: from.version(),
+            to_version: to.version(),
+            state: Change::between(from.state(), to.state()),
+            items_added,
+            items_removed,
+            items_changed,
+            total_amount: Change::between(from.total_amount(), to.total_amount()),
+            carrier: Change::between(
+                from.carrier().map(str::to_string),
+                to.carrier().map(str::to_string),
+            ),
+            tracking_number: Change::between(
+                from.tracking_number().map(str::to_string),
+                to.tracking_number().map(str::to_string),
+            ),
+            shipping_address: Change::between(
+                from.shipping_address().cloned(),
+                to.shipping_address().cloned(),
+            ),
+        }
+    }
+
+    /// Returns true if nothing tracked by the diff changed.
+    pub fn is_empty(&self) -> bool {
+        self.state.is_none()
+            && self.items_added.is_empty()
+            && self.items_removed.is_empty()
+            && self.items_changed.is_empty()
+            && self.total_amount.is_none()
+            && self.carrier.is_none()
+            && self.tracking_number.is_none()
+            && self.shipping_address.is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Aggregate, CustomerId};
+    use common::AggregateId;
+
+    fn replay(events: &[crate::OrderEvent]) -> Order {
+        let mut order = Order::default();
+        for (i, event) in events.iter().enumerate() {
+            order.apply(event.clone());
+            order.set_version(Version::new(i as i64 + 1));
+        }
+        order
+    }
+
+    #[test]
+    fn test_diff_reports_item_and_state_changes() {
+        let widget = OrderItem::new("SKU-001", "Widget", 2, Money::from_cents(1000));
+        let gadget = OrderItem::new("SKU-002", "Gadget", 1, Money::from_cents(500));
+        let gizmo = OrderItem::new("SKU-003", "Gizmo", 1, Money::from_cents(250));
+        let mut events = vec![
+            crate::OrderEvent::order_created(AggregateId::new(), CustomerId::new()),
+            crate::OrderEvent::item_added(&widget),
+            crate::OrderEvent::item_added(&gadget),
+        ];
+        let before = replay(&events);
+
+        events.push(crate::OrderEvent::item_removed(ProductId::new("SKU-002")));
+        events.push(crate::OrderEvent::item_quantity_updated(
+            ProductId::new("SKU-001"),
+            2,
+            5,
+        ));
+        events.push(crate::OrderEvent::item_added(&gizmo));
+        events.push(crate::OrderEvent::order_submitted(
+            Money::from_cents(5250),
+            2,
+        ));
+        events.push(crate::OrderEvent::order_reserved(Default::default()));
+        let after = replay(&events);
+
+        let diff = OrderDiff::between(&before, &after);
+        assert_eq!(diff.from_version, Version::new(3));
+        assert_eq!(diff.to_version, Version::new(8));
+        assert_eq!(
+            diff.state,
+            Some(Change {
+                from: OrderState::Draft,
+                to: OrderState::Reserved
+            })
+        );
+        assert_eq!(diff.items_added, vec![gizmo]);
+        assert_eq!(diff.items_removed, vec![gadget]);
+        assert_eq!(diff.items_changed.len(), 1);
+        assert_eq!(diff.items_changed[0].product_id.as_str(), "SKU-001");
+        assert_eq!(
+            diff.items_changed[0].quantity,
+            Some(Change { from: 2, to: 5 })
+        );
+        assert!(diff.items_changed[0].unit_price.is_none());
+        assert_eq!(
+            diff.total_amount,
+            Some(Change {
+                from: Money::from_cents(2500),
+                to: Money::from_cents(5250)
+            })
+        );
+        assert!(!diff.is_empty());
+    }
+
+    #[test]
+    fn test_diff_of_same_version_is_empty() {
+        let order = replay(&[crate::OrderEvent::order_created(
+            AggregateId::new(),
+            CustomerId::new(),
+        )]);
+        assert!(OrderDiff::between(&order, &order).is_empty());
+    }
+}
@@ -1,12 +1,14 @@
 //! Order domain events.
 
+use std::collections::BTreeMap;
+
 use chrono::{DateTime, Utc};
 use common::AggregateId;
 use serde::{Deserialize, Serialize};
 
 use crate::aggregate::DomainEvent;
 
-use super::{CustomerId, Money, OrderItem, ProductId};
+use super::{Address, Currency, CustomerId, Money, OrderItem, ProductId, ShippedItem};
 
 /// Events that can occur on an order aggregate.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,20 +26,56 @@ pub enum OrderEvent {
     /// Item quantity was updated.
     ItemQuantityUpdated(ItemQuantityUpdatedData),
 
+    /// Item unit price was reconciled with the catalog.
+    ItemPriceAdjusted(ItemPriceAdjustedData),
+
+    /// Item was replaced by a substitute product during fulfillment.
+    ItemSubstituted(ItemSubstitutedData),
+
     /// Order was submitted for processing.
     OrderSubmitted(OrderSubmittedData),
 
     /// Inventory was reserved for the order.
     OrderReserved(OrderReservedData),
 
+    /// A line item's lapsed inventory reservation was replaced.
+    ReservationRenewed(ReservationRenewedData),
+
     /// Order payment was confirmed and processing started.
     OrderProcessing(OrderProcessingData),
 
     /// Order was completed/shipped.
     OrderCompleted(OrderCompletedData),
 
+    /// Carrier picked up the shipment.
+    OrderShipped(OrderShippedData),
+
+    /// Carrier reported the shipment moving through its network.
+    OrderInTransit(OrderInTransitData),
+
+    /// Carrier confirmed delivery.
+    OrderDelivered(OrderDeliveredData),
+
+    /// Carrier failed to deliver the shipment.
+    DeliveryFailed(DeliveryFailedData),
+
     /// Order was cancelled.
     OrderCancelled(OrderCancelledData),
+
+    /// A label was attached to the order.
+    TagAdded(TagAddedData),
+
+    /// A label was removed from the order.
+    TagRemoved(TagRemovedData),
+
+    /// A metadata attachment was set or cleared.
+    OrderMetadataSet(OrderMetadataSetData),
+
+    /// The order was given a shipping address.
+    ShippingAddressSet(ShippingAddressSetData),
+
+    /// The order's shipping address was changed.
+    ShippingAddressUpdated(ShippingAddressUpdatedData),
 }
 
 impl DomainEvent for OrderEvent {
@@ -47,11 +85,23 @@ impl DomainEvent for OrderEvent {
             OrderEvent::ItemAdded(_) => "ItemAdded",
             OrderEvent::ItemRemoved(_) => "ItemRemoved",
             OrderEvent::ItemQuantityUpdated(_) => "ItemQuantityUpdated",
+            OrderEvent::ItemPriceAdjusted(_) => "ItemPriceAdjusted",
+            OrderEvent::ItemSubstituted(_) => "ItemSubstituted",
             OrderEvent::OrderSubmitted(_) => "OrderSubmitted",
             OrderEvent::OrderReserved(_) => "OrderReserved",
+            OrderEvent::ReservationRenewed(_) => "ReservationRenewed",
             OrderEvent::OrderProcessing(_) => "OrderProcessing",
             OrderEvent::OrderCompleted(_) => "OrderCompleted",
+            OrderEvent::OrderShipped(_) => "OrderShipped",
+            OrderEvent::OrderInTransit(_) => "OrderInTransit",
+            OrderEvent::OrderDelivered(_) => "OrderDelivered",
+            OrderEvent::DeliveryFailed(_) => "DeliveryFailed",
             OrderEvent::OrderCancelled(_) => "OrderCancelled",
+            OrderEvent::TagAdded(_) => "TagAdded",
+            OrderEvent::TagRemoved(_) => "TagRemoved",
+            OrderEvent::OrderMetadataSet(_) => "OrderMetadataSet",
+            OrderEvent::ShippingAddressSet(_) => "ShippingAddressSet",
+            OrderEvent::ShippingAddressUpdated(_) => "ShippingAddressUpdated",
         }
     }
 }
@@ -65,6 +115,10 @@ pub struct OrderCreatedData {
     /// The customer who created the order.
     pub customer_id: CustomerId,
 
+    /// Currency all amounts in the order are denominated in.
+    #[serde(default)]
+    pub currency: Currency,
+
     /// When the order was created.
     pub created_at: DateTime<Utc>,
 }
@@ -105,6 +159,50 @@ pub struct ItemQuantityUpdatedData {
     pub new_quantity: u32,
 }
 
+/// Data for ItemPriceAdjusted event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemPriceAdjustedData {
+    /// The product whose price was adjusted.
+    pub product_id: ProductId,
+
+    /// Unit price captured when the item was added.
+    pub old_unit_price: Money,
+
+    /// Current catalog unit price.
+    pub new_unit_price: Money,
+}
+
+/// Data for ItemSubstituted event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemSubstitutedData {
+    /// The product that was replaced.
+    pub original_product_id: ProductId,
+
+    /// The product shipped in its place.
+    pub substitute_product_id: ProductId,
+
+    /// Substitute product name.
+    pub substitute_product_name: String,
+
+    /// Quantity, carried over from the original line.
+    pub quantity: u32,
+
+    /// Unit price of the substitute.
+    pub unit_price: Money,
+
+    /// Change to the order total: the substitute line's total minus the
+    /// original line's. Negative when the substitute is cheaper.
+    pub price_delta: Money,
+
+    /// Inventory reservation held for the substitute, replacing the
+    /// original line's.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reservation_id: Option<String>,
+
+    /// When the item was substituted.
+    pub substituted_at: DateTime<Utc>,
+}
+
 /// Data for OrderSubmitted event.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OrderSubmittedData {
@@ -124,10 +222,32 @@ pub struct OrderReservedData {
     /// When the inventory was reserved.
     pub reserved_at: DateTime<Utc>,
 
-    /// Reservation reference ID (from inventory service).
+    /// Reservation reference ID per line item (from inventory service).
+    #[serde(default)]
+    pub reservations: BTreeMap<ProductId, String>,
+
+    /// Whole-order reservation ID recorded by events written before
+    /// reservations were tracked per line item.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub reservation_id: Option<String>,
 }
 
+/// Data for ReservationRenewed event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReservationRenewedData {
+    /// The line item whose reservation lapsed.
+    pub product_id: ProductId,
+
+    /// The reservation now holding the line item.
+    pub reservation_id: String,
+
+    /// The lapsed reservation, if the line item had one.
+    pub previous_reservation_id: Option<String>,
+
+    /// When the reservation was renewed.
+    pub renewed_at: DateTime<Utc>,
+}
+
 /// Data for OrderProcessing event.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OrderProcessingData {
@@ -146,6 +266,53 @@ pub struct OrderCompletedData {
 
     /// Shipment tracking number.
     pub tracking_number: Option<String>,
+
+    /// Serial and lot numbers reported by the warehouse, per line item.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub shipped_items: Vec<ShippedItem>,
+}
+
+/// Data for OrderShipped event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderShippedData {
+    /// When the carrier picked up the shipment.
+    pub shipped_at: DateTime<Utc>,
+
+    /// Carrier handling the shipment.
+    pub carrier: String,
+
+    /// Carrier tracking number.
+    pub tracking_number: Option<String>,
+}
+
+/// Data for OrderInTransit event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderInTransitData {
+    /// When the carrier reported the update.
+    pub updated_at: DateTime<Utc>,
+
+    /// Last known location of the shipment.
+    pub location: Option<String>,
+}
+
+/// Data for OrderDelivered event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderDeliveredData {
+    /// When the shipment was delivered.
+    pub delivered_at: DateTime<Utc>,
+
+    /// Who signed for the shipment, if reported.
+    pub received_by: Option<String>,
+}
+
+/// Data for DeliveryFailed event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeliveryFailedData {
+    /// When the delivery attempt failed.
+    pub failed_at: DateTime<Utc>,
+
+    /// Reason reported by the carrier.
+    pub reason: String,
 }
 
 /// Data for OrderCancelled event.
@@ -159,15 +326,87 @@ pub struct OrderCancelledData {
 
     /// Who cancelled the order.
     pub cancelled_by: Option<String>,
+
+    /// Machine-readable cause, such as `payment_declined` for orders
+    /// cancelled by a failed saga. Missing on manual cancellations and on
+    /// events written before codes were recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason_code: Option<String>,
+}
+
+/// Data for TagAdded event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagAddedData {
+    /// The normalized tag.
+    pub tag: String,
+
+    /// When the tag was added.
+    pub added_at: DateTime<Utc>,
+}
+
+/// Data for TagRemoved event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagRemovedData {
+    /// The normalized tag.
+    pub tag: String,
+
+    /// When the tag was removed.
+    pub removed_at: DateTime<Utc>,
+}
+
+/// Data for OrderMetadataSet event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderMetadataSetData {
+    /// The attachment key, such as `gift_message`.
+    pub key: String,
+
+    /// The new value, or None if the attachment was cleared.
+    pub value: Option<String>,
+
+    /// When the attachment was changed.
+    pub set_at: DateTime<Utc>,
+}
+
+/// Data for ShippingAddressSet event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShippingAddressSetData {
+    /// The validated address.
+    pub address: Address,
+
+    /// When the address was set.
+    pub set_at: DateTime<Utc>,
+}
+
+/// Data for ShippingAddressUpdated event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShippingAddressUpdatedData {
+    /// The validated new address.
+    pub address: Address,
+
+    /// The address it replaced.
+    pub previous: Address,
+
+    /// When the address was changed.
+    pub updated_at: DateTime<Utc>,
 }
 
 // Convenience constructors for events
 impl OrderEvent {
     /// Creates an OrderCreated event.
     pub fn order_created(order_id: AggregateId, customer_id: CustomerId) -> Self {
+        Self::order_created_in(order_id, customer_id, Currency::default())
+    }
+
+    /// Creates an OrderCreated event for an order in the given currency.
+    pub fn order_created_in(
+        order_id: AggregateId,
+        customer_id: CustomerId,
+        currency: Currency,
+    ) -> Self {
         OrderEvent::OrderCreated(OrderCreatedData {
             order_id,
             customer_id,
+            currency,
             created_at: Utc::now(),
         })
     }
@@ -200,6 +439,38 @@ impl OrderEvent {
         })
     }
 
+    /// Creates an ItemPriceAdjusted event.
+    pub fn item_price_adjusted(
+        product_id: ProductId,
+        old_unit_price: Money,
+        new_unit_price: Money,
+    ) -> Self {
+        OrderEvent::ItemPriceAdjusted(ItemPriceAdjustedData {
+            product_id,
+            old_unit_price,
+            new_unit_price,
+        })
+    }
+
+    /// Creates an ItemSubstituted event replacing `original` with
+    /// `substitute`, which carries the original's quantity.
+    pub fn item_substituted(
+        original: &OrderItem,
+        substitute: &OrderItem,
+        reservation_id: Option<String>,
+    ) -> Self {
+        OrderEvent::ItemSubstituted(ItemSubstitutedData {
+            original_product_id: original.product_id.clone(),
+            substitute_product_id: substitute.product_id.clone(),
+            substitute_product_name: substitute.product_name.clone(),
+            quantity: substitute.quantity,
+            unit_price: substitute.unit_price,
+            price_delta: substitute.total_price() - original.total_price(),
+            reservation_id,
+            substituted_at: Utc::now(),
+        })
+    }
+
     /// Creates an OrderSubmitted event.
     pub fn order_submitted(total_amount: Money, item_count: usize) -> Self {
         OrderEvent::OrderSubmitted(OrderSubmittedData {
@@ -210,10 +481,25 @@ impl OrderEvent {
     }
 
     /// Creates an OrderReserved event.
-    pub fn order_reserved(reservation_id: Option<String>) -> Self {
+    pub fn order_reserved(reservations: BTreeMap<ProductId, String>) -> Self {
         OrderEvent::OrderReserved(OrderReservedData {
             reserved_at: Utc::now(),
-            reservation_id,
+            reservations,
+            reservation_id: None,
+        })
+    }
+
+    /// Creates a ReservationRenewed event.
+    pub fn reservation_renewed(
+        product_id: ProductId,
+        reservation_id: impl Into<String>,
+        previous_reservation_id: Option<String>,
+    ) -> Self {
+        OrderEvent::ReservationRenewed(ReservationRenewedData {
+            product_id,
+            reservation_id: reservation_id.into(),
+            previous_reservation_id,
+            renewed_at: Utc::now(),
         })
     }
 
@@ -227,9 +513,55 @@ impl OrderEvent {
 
     /// Creates an OrderCompleted event.
     pub fn order_completed(tracking_number: Option<String>) -> Self {
+        Self::order_completed_with_items(tracking_number, Vec::new())
+    }
+
+    /// Creates an OrderCompleted event carrying per-item serial/lot numbers.
+    pub fn order_completed_with_items(
+        tracking_number: Option<String>,
+        shipped_items: Vec<ShippedItem>,
+    ) -> Self {
         OrderEvent::OrderCompleted(OrderCompletedData {
             completed_at: Utc::now(),
             tracking_number,
+            shipped_items,
+        })
+    }
+
+    /// Creates an OrderShipped event.
+    pub fn order_shipped(
+        carrier: impl Into<String>,
+        tracking_number: Option<String>,
+        shipped_at: DateTime<Utc>,
+    ) -> Self {
+        OrderEvent::OrderShipped(OrderShippedData {
+            shipped_at,
+            carrier: carrier.into(),
+            tracking_number,
+        })
+    }
+
+    /// Creates an OrderInTransit event.
+    pub fn order_in_transit(location: Option<String>, updated_at: DateTime<Utc>) -> Self {
+        OrderEvent::OrderInTransit(OrderInTransitData {
+            updated_at,
+            location,
+        })
+    }
+
+    /// Creates an OrderDelivered event.
+    pub fn order_delivered(received_by: Option<String>, delivered_at: DateTime<Utc>) -> Self {
+        OrderEvent::OrderDelivered(OrderDeliveredData {
+            delivered_at,
+            received_by,
+        })
+    }
+
+    /// Creates a DeliveryFailed event.
+    pub fn delivery_failed(reason: impl Into<String>, failed_at: DateTime<Utc>) -> Self {
+        OrderEvent::DeliveryFailed(DeliveryFailedData {
+            failed_at,
+            reason: reason.into(),
         })
     }
 
@@ -239,6 +571,58 @@ impl OrderEvent {
             cancelled_at: Utc::now(),
             reason: reason.into(),
             cancelled_by,
+            reason_code: None,
+        })
+    }
+
+    /// Attaches a reason code to an OrderCancelled event. Other events are
+    /// returned unchanged.
+    pub fn with_reason_code(mut self, reason_code: Option<String>) -> Self {
+        if let OrderEvent::OrderCancelled(data) = &mut self {
+            data.reason_code = reason_code;
+        }
+        self
+    }
+
+    /// Creates a TagAdded event.
+    pub fn tag_added(tag: impl Into<String>) -> Self {
+        OrderEvent::TagAdded(TagAddedData {
+            tag: tag.into(),
+            added_at: Utc::now(),
+        })
+    }
+
+    /// Creates a TagRemoved event.
+    pub fn tag_removed(tag: impl Into<String>) -> Self {
+        OrderEvent::TagRemoved(TagRemovedData {
+            tag: tag.into(),
+            removed_at: Utc::now(),
+        })
+    }
+
+    /// Creates an OrderMetadataSet event. A `value` of None clears the key.
+    pub fn metadata_set(key: impl Into<String>, value: Option<String>) -> Self {
+        OrderEvent::OrderMetadataSet(OrderMetadataSetData {
+            key: key.into(),
+            value,
+            set_at: Utc::now(),
+        })
+    }
+
+    /// Creates a ShippingAddressSet event.
+    pub fn shipping_address_set(address: Address) -> Self {
+        OrderEvent::ShippingAddressSet(ShippingAddressSetData {
+            address,
+            set_at: Utc::now(),
+        })
+    }
+
+    /// Creates a ShippingAddressUpdated event.
+    pub fn shipping_address_updated(address: Address, previous: Address) -> Self {
+        OrderEvent::ShippingAddressUpdated(ShippingAddressUpdatedData {
+            address,
+            previous,
+            updated_at: Utc::now(),
         })
     }
 }
@@ -268,17 +652,44 @@ mod tests {
         let event = OrderEvent::order_submitted(Money::from_cents(2000), 2);
         assert_eq!(event.event_type(), "OrderSubmitted");
 
-        let event = OrderEvent::order_reserved(Some("RES-123".to_string()));
+        let event = OrderEvent::order_reserved(BTreeMap::from([(
+            ProductId::new("SKU-001"),
+            "RES-123".to_string(),
+        )]));
         assert_eq!(event.event_type(), "OrderReserved");
 
+        let event = OrderEvent::reservation_renewed(ProductId::new("SKU-001"), "RES-124", None);
+        assert_eq!(event.event_type(), "ReservationRenewed");
+
         let event = OrderEvent::order_processing(Some("PAY-123".to_string()));
         assert_eq!(event.event_type(), "OrderProcessing");
 
         let event = OrderEvent::order_completed(Some("TRACK-123".to_string()));
         assert_eq!(event.event_type(), "OrderCompleted");
 
+        let event = OrderEvent::order_shipped("UPS", Some("1Z999".to_string()), Utc::now());
+        assert_eq!(event.event_type(), "OrderShipped");
+
+        let event = OrderEvent::order_in_transit(Some("Memphis, TN".to_string()), Utc::now());
+        assert_eq!(event.event_type(), "OrderInTransit");
+
+        let event = OrderEvent::order_delivered(None, Utc::now());
+        assert_eq!(event.event_type(), "OrderDelivered");
+
+        let event = OrderEvent::delivery_failed("Address not found", Utc::now());
+        assert_eq!(event.event_type(), "DeliveryFailed");
+
         let event = OrderEvent::order_cancelled("Customer request", None);
         assert_eq!(event.event_type(), "OrderCancelled");
+
+        let event = OrderEvent::tag_added("wholesale");
+        assert_eq!(event.event_type(), "TagAdded");
+
+        let event = OrderEvent::tag_removed("wholesale");
+        assert_eq!(event.event_type(), "TagRemoved");
+
+        let event = OrderEvent::metadata_set("po_number", Some("PO-1".to_string()));
+        assert_eq!(event.event_type(), "OrderMetadataSet");
     }
 
     #[test]
@@ -0,0 +1,444 @@
+//! Write-ahead validation for bulk order imports.
+//!
+//! Legacy data is imported as synthetic event streams. Before any of it is
+//! written, [`ImportValidator`] replays the proposed events through the
+//! [`Order`] aggregate in memory and checks each one against the state it
+//! lands on, the same way the command methods would have. Violations carry
+//! the input line they came from, so bad records can be fixed at the source
+//! instead of being found later in the store.
+
+use std::collections::HashMap;
+
+use common::AggregateId;
+use serde::{Deserialize, Serialize};
+
+use crate::aggregate::{Aggregate, DomainEvent};
+
+use super::{Order, OrderError, OrderEvent, OrderState, ProductId};
+
+/// One proposed event, as a line of the import file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportRecord {
+    /// The stream the event belongs to.
+    pub order_id: AggregateId,
+
+    /// The event, in its stored `{"type": ..., "data": ...}` form.
+    pub event: OrderEvent,
+}
+
+/// A problem found on one input line.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ImportViolation {
+    /// 1-based line number in the input.
+    pub line: usize,
+
+    /// Stable code naming the violated rule, e.g.
+    /// `order.invalid_state_transition`.
+    pub code: &'static str,
+
+    /// Human-readable description.
+    pub message: String,
+}
+
+impl ImportViolation {
+    fn new(line: usize, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            line,
+            code,
+            message: message.into(),
+        }
+    }
+
+    fn from_error(line: usize, error: OrderError) -> Self {
+        Self::new(line, error.code(), error.to_string())
+    }
+}
+
+/// Validation result for one proposed stream.
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamReport {
+    pub order_id: AggregateId,
+
+    /// Events proposed for the stream.
+    pub events: usize,
+
+    /// State the stream would end in once written.
+    pub state: OrderState,
+
+    /// Total the stream would end with, in cents.
+    pub total_amount_cents: i64,
+
+    pub violations: Vec<ImportViolation>,
+}
+
+/// Validation result for a whole import.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportReport {
+    /// Streams in order of first appearance.
+    pub streams: Vec<StreamReport>,
+
+    /// Lines that could not be parsed and belong to no stream.
+    pub malformed: Vec<ImportViolation>,
+}
+
+impl ImportReport {
+    /// Returns true if nothing in the import violates a rule.
+    pub fn is_valid(&self) -> bool {
+        self.malformed.is_empty() && self.streams.iter().all(|s| s.violations.is_empty())
+    }
+
+    /// Returns the total number of violations.
+    pub fn violation_count(&self) -> usize {
+        self.malformed.len()
+            + self
+                .streams
+                .iter()
+                .map(|s| s.violations.len())
+                .sum::<usize>()
+    }
+}
+
+#[derive(Default)]
+struct StreamReplay {
+    order: Order,
+    events: usize,
+    violations: Vec<ImportViolation>,
+}
+
+/// Replays proposed order events in memory and reports rule violations.
+///
+/// Records are checked in input order; a stream's events must appear in the
+/// order they are to be written. Every event is applied after it is
+/// checked, even one that violates a rule, so the report describes the
+/// streams exactly as an import would write them.
+#[derive(Default)]
+pub struct ImportValidator {
+    streams: HashMap<AggregateId, StreamReplay>,
+    order: Vec<AggregateId>,
+    malformed: Vec<ImportViolation>,
+}
+
+impl ImportValidator {
+    /// Creates a validator with no records.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Validates a JSON Lines import, one [`ImportRecord`] per line. Blank
+    /// lines are skipped but still counted.
+    pub fn validate_jsonl(input: &str) -> ImportReport {
+        let mut validator = Self::new();
+        for (i, line) in input.lines().enumerate() {
+            validator.push_line(i + 1, line);
+        }
+        validator.finish()
+    }
+
+    /// Parses and checks one input line.
+    pub fn push_line(&mut self, line: usize, text: &str) {
+        if text.trim().is_empty() {
+            return;
+        }
+        match serde_json::from_str::<ImportRecord>(text) {
+            Ok(record) => self.push(line, record),
+            Err(e) => self.malformed.push(ImportViolation::new(
+                line,
+                "import.malformed",
+                format!("Invalid record: {e}"),
+            )),
+        }
+    }
+
+    /// Checks one record against its stream's replayed state, then applies it.
+    pub fn push(&mut self, line: usize, record: ImportRecord) {
+        let stream = self.streams.entry(record.order_id).or_insert_with(|| {
+            self.order.push(record.order_id);
+            StreamReplay::default()
+        });
+
+        let violations = check(&stream.order, record.order_id, &record.event, line);
+        stream.violations.extend(violations);
+
+        let before = stream.order.total_amount().cents();
+        stream.order.apply(record.event);
+        stream.events += 1;
+
+        // Reported once, on the event that takes the total below zero
+        let total = stream.order.total_amount().cents();
+        if total < 0 && before >= 0 {
+            stream.violations.push(ImportViolation::new(
+                line,
+                "order.negative_total",
+                format!("Order total would be {total} cents"),
+            ));
+        }
+    }
+
+    /// Returns the report for every record pushed so far.
+    pub fn finish(mut self) -> ImportReport {
+        let streams = self
+            .order
+            .iter()
+            .filter_map(|id| self.streams.remove(id).map(|s| (*id, s)))
+            .map(|(order_id, stream)| StreamReport {
+                order_id,
+                events: stream.events,
+                state: stream.order.state(),
+                total_amount_cents: stream.order.total_amount().cents(),
+                violations: stream.violations,
+            })
+            .collect();
+        ImportReport {
+            streams,
+            malformed: self.malformed,
+        }
+    }
+}
+
+/// Checks `event` against the state it would be applied to.
+fn check(
+    order: &Order,
+    order_id: AggregateId,
+    event: &OrderEvent,
+    line: usize,
+) -> Vec<ImportViolation> {
+    let created = order.id().is_some();
+    match event {
+        OrderEvent::OrderCreated(data) => {
+            let mut violations = Vec::new();
+            if created {
+                violations.push(ImportViolation::from_error(
+                    line,
+                    OrderError::AlreadyCreated,
+                ));
+            }
+            if data.order_id != order_id {
+                violations.push(ImportViolation::new(
+                    line,
+                    "import.stream_mismatch",
+                    format!(
+                        "OrderCreated names order {} in stream {order_id}",
+                        data.order_id
+                    ),
+                ));
+            }
+            return violations;
+        }
+        _ if !created => {
+            return vec![ImportViolation::new(
+                line,
+                "import.not_created",
+                format!("{} before OrderCreated", event.event_type()),
+            )];
+        }
+        _ => {}
+    }
+
+    let state = order.state();
+    let mut errors = Vec::new();
+    let mut require = |allowed: bool, action: &'static str| {
+        if !allowed {
+            errors.push(OrderError::InvalidStateTransition {
+                current_state: state,
+                action,
+            });
+        }
+    };
+    match event {
+        OrderEvent::OrderCreated(_) | OrderEvent::TagAdded(_) | OrderEvent::TagRemoved(_) => {}
+        OrderEvent::ItemAdded(_) => require(state.can_modify_items(), "add item"),
+        OrderEvent::ItemRemoved(_) => require(state.can_modify_items(), "remove item"),
+        OrderEvent::ItemQuantityUpdated(_) => require(state.can_modify_items(), "update item"),
+        OrderEvent::ItemPriceAdjusted(_) => require(state.can_modify_items(), "adjust price"),
+        OrderEvent::ItemSubstituted(_) => require(state.can_substitute_items(), "substitute item"),
+        OrderEvent::OrderMetadataSet(_) => require(state.can_modify_items(), "set metadata"),
+        OrderEvent::ShippingAddressSet(_) | OrderEvent::ShippingAddressUpdated(_) => require(
+            state.can_change_shipping_address(),
+            "change shipping address",
+        ),
+        OrderEvent::OrderSubmitted(_) => require(state.can_submit(), "submit"),
+        OrderEvent::OrderReserved(_) => require(state.can_reserve(), "reserve"),
+        OrderEvent::ReservationRenewed(_) => {
+            require(state.can_renew_reservations(), "renew reservation")
+        }
+        OrderEvent::OrderProcessing(_) => require(state.can_start_processing(), "start processing"),
+        OrderEvent::OrderCompleted(_) => require(state.can_complete(), "complete"),
+        OrderEvent::OrderShipped(_) => require(state.can_ship(), "ship"),
+        OrderEvent::OrderInTransit(_) => require(state.can_mark_in_transit(), "mark in transit"),
+        OrderEvent::OrderDelivered(_) | OrderEvent::DeliveryFailed(_) => {
+            require(state.can_record_delivery(), "record delivery")
+        }
+        OrderEvent::OrderCancelled(_) => require(state.can_cancel(), "cancel"),
+    }
+
+    let has_item = |product_id: &ProductId| order.items().any(|i| &i.product_id == product_id);
+    let mut violations = Vec::new();
+    match event {
+        OrderEvent::ItemAdded(data) => {
+            if data.quantity == 0 {
+                errors.push(OrderError::InvalidQuantity { quantity: 0 });
+            }
+            if data.unit_price.cents() <= 0 {
+                errors.push(OrderError::InvalidPrice {
+                    price: data.unit_price.cents(),
+                });
+            }
+            // Replaying a second ItemAdded would count the item twice
+            if has_item(&data.product_id) {
+                violations.push(ImportViolation::new(
+                    line,
+                    "import.duplicate_item",
+                    format!("{} is already in the order", data.product_id),
+                ));
+            }
+        }
+        OrderEvent::ItemQuantityUpdated(data) if data.new_quantity == 0 => {
+            errors.push(OrderError::InvalidQuantity { quantity: 0 });
+        }
+        OrderEvent::ItemPriceAdjusted(data) if data.new_unit_price.cents() <= 0 => {
+            errors.push(OrderError::InvalidPrice {
+                price: data.new_unit_price.cents(),
+            });
+        }
+        OrderEvent::ItemSubstituted(data) => {
+            if data.unit_price.cents() <= 0 {
+                errors.push(OrderError::InvalidPrice {
+                    price: data.unit_price.cents(),
+                });
+            }
+            if has_item(&data.substitute_product_id) {
+                errors.push(OrderError::InvalidSubstitution {
+                    product_id: data.substitute_product_id.to_string(),
+                    reason: "already in the order",
+                });
+            }
+        }
+        OrderEvent::OrderSubmitted(_) if order.item_count() == 0 => {
+            errors.push(OrderError::NoItems)
+        }
+        _ => {}
+    }
+    let changed_item = match event {
+        OrderEvent::ItemRemoved(data) => Some(&data.product_id),
+        OrderEvent::ItemQuantityUpdated(data) => Some(&data.product_id),
+        OrderEvent::ItemPriceAdjusted(data) => Some(&data.product_id),
+        OrderEvent::ItemSubstituted(data) => Some(&data.original_product_id),
+        _ => None,
+    };
+    if let Some(product_id) = changed_item.filter(|p| !has_item(p)) {
+        errors.push(OrderError::ItemNotFound {
+            product_id: product_id.to_string(),
+        });
+    }
+
+    errors
+        .into_iter()
+        .map(|e| ImportViolation::from_error(line, e))
+        .chain(violations)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::order::{CustomerId, Money, OrderItem};
+
+    fn line(order_id: AggregateId, event: OrderEvent) -> String {
+        serde_json::to_string(&ImportRecord { order_id, event }).unwrap()
+    }
+
+    fn widget(quantity: u32, cents: i64) -> OrderItem {
+        OrderItem::new("SKU-1", "Widget", quantity, Money::from_cents(cents))
+    }
+
+    #[test]
+    fn test_clean_streams_are_valid() {
+        let order_id = AggregateId::new();
+        let input = [
+            line(
+                order_id,
+                OrderEvent::order_created(order_id, CustomerId::new()),
+            ),
+            line(order_id, OrderEvent::item_added(&widget(2, 500))),
+            String::new(),
+            line(
+                order_id,
+                OrderEvent::order_submitted(Money::from_cents(1000), 1),
+            ),
+            line(order_id, OrderEvent::order_reserved(Default::default())),
+        ]
+        .join("\n");
+
+        let report = ImportValidator::validate_jsonl(&input);
+        assert!(report.is_valid(), "{report:?}");
+        assert_eq!(report.streams.len(), 1);
+        assert_eq!(report.streams[0].events, 4);
+        assert_eq!(report.streams[0].state, OrderState::Reserved);
+        assert_eq!(report.streams[0].total_amount_cents, 1000);
+    }
+
+    #[test]
+    fn test_reports_violations_with_lines() {
+        let good = AggregateId::new();
+        let bad = AggregateId::new();
+        let orphan = AggregateId::new();
+        let input = [
+            line(bad, OrderEvent::order_created(bad, CustomerId::new())),
+            line(good, OrderEvent::order_created(good, CustomerId::new())),
+            line(bad, OrderEvent::item_added(&widget(1, -700))),
+            "{not json".to_string(),
+            line(bad, OrderEvent::order_completed(None)),
+            line(orphan, OrderEvent::tag_added("legacy")),
+        ]
+        .join("\n");
+
+        let report = ImportValidator::validate_jsonl(&input);
+        assert!(!report.is_valid());
+        assert_eq!(report.violation_count(), 5);
+
+        let streams: Vec<_> = report.streams.iter().map(|s| s.order_id).collect();
+        assert_eq!(streams, vec![bad, good, orphan]);
+        assert!(report.streams[1].violations.is_empty());
+
+        let codes: Vec<_> = report.streams[0]
+            .violations
+            .iter()
+            .map(|v| (v.line, v.code))
+            .collect();
+        assert_eq!(
+            codes,
+            vec![
+                (3, "order.invalid_price"),
+                (3, "order.negative_total"),
+                (5, "order.invalid_state_transition"),
+            ]
+        );
+        assert_eq!(report.streams[2].violations[0].code, "import.not_created");
+        assert_eq!(report.malformed[0].line, 4);
+    }
+
+    #[test]
+    fn test_duplicate_item_added_is_flagged() {
+        let order_id = AggregateId::new();
+        let mut validator = ImportValidator::new();
+        let created = OrderEvent::order_created(order_id, CustomerId::new());
+        validator.push(
+            1,
+            ImportRecord {
+                order_id,
+                event: created,
+            },
+        );
+        for n in [2, 3] {
+            let event = OrderEvent::item_added(&widget(1, 100));
+            validator.push(n, ImportRecord { order_id, event });
+        }
+
+        let report = validator.finish();
+        let violation = &report.streams[0].violations[0];
+        assert_eq!(
+            (violation.line, violation.code),
+            (3, "import.duplicate_item")
+        );
+    }
+}
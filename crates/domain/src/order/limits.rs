@@ -0,0 +1,80 @@
+//! Size limits on a single order.
+//!
+//! Limits are checked when items are added or their quantity changes, so
+//! an order can never grow past them. Orders that already exceed a limit
+//! when it is introduced are left alone, but can only shrink until they
+//! fit.
+
+use super::{Money, OrderError, ProductId};
+
+/// Caps on how large an order may grow. Each cap is off when unset.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OrderLimits {
+    /// Most distinct products in one order.
+    pub max_items: Option<usize>,
+    /// Largest quantity of any one product.
+    pub max_line_quantity: Option<u32>,
+    /// Largest order total. Only orders in the limit's currency are held to
+    /// it, as amounts in other currencies can't be compared with it.
+    pub max_total: Option<Money>,
+}
+
+impl OrderLimits {
+    /// Enforces no limits.
+    pub fn unlimited() -> Self {
+        Self::default()
+    }
+
+    /// Caps the number of distinct products in an order.
+    pub fn with_max_items(mut self, max_items: usize) -> Self {
+        self.max_items = Some(max_items);
+        self
+    }
+
+    /// Caps the quantity of each product.
+    pub fn with_max_line_quantity(mut self, max_line_quantity: u32) -> Self {
+        self.max_line_quantity = Some(max_line_quantity);
+        self
+    }
+
+    /// Caps the order total.
+    pub fn with_max_total(mut self, max_total: Money) -> Self {
+        self.max_total = Some(max_total);
+        self
+    }
+
+    /// Checks an order holding `item_count` products after one of them,
+    /// `product_id`, goes to `quantity` and the total to `total`.
+    ///
+    /// `total` is None when it overflowed, which exceeds any limit.
+    pub(crate) fn check(
+        &self,
+        item_count: usize,
+        product_id: &ProductId,
+        quantity: u32,
+        total: Option<Money>,
+    ) -> Result<(), OrderError> {
+        if let Some(limit) = self.max_items
+            && item_count > limit
+        {
+            return Err(OrderError::TooManyItems { limit });
+        }
+        if let Some(limit) = self.max_line_quantity
+            && quantity > limit
+        {
+            return Err(OrderError::LineQuantityExceeded {
+                product_id: product_id.to_string(),
+                quantity,
+                limit,
+            });
+        }
+        if let Some(limit) = self.max_total
+            && total.is_none_or(|total| {
+                total.currency() == limit.currency() && total.cents() > limit.cents()
+            })
+        {
+            return Err(OrderError::OrderTotalExceeded { limit });
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,406 @@
+//! Command middleware for sanitizing free-text fields before events are persisted.
+//!
+//! Events are immutable, so any text that reaches the event store lives forever.
+//! Middleware registered on [`OrderService`](super::OrderService) runs over every
+//! free-text field of a command and can normalize it, mask parts of it, or reject
+//! the command outright.
+
+use std::collections::HashSet;
+
+use super::OrderError;
+
+/// Free-text command fields that pass through the middleware chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TextField {
+    /// `OrderItem::product_name` on `AddItem`.
+    ProductName,
+
+    /// `CancelOrder::reason`.
+    CancellationReason,
+
+    /// `RecordDeliveryFailure::reason`, as reported by the carrier.
+    DeliveryFailureReason,
+
+    /// `SetOrderMetadata::value`.
+    OrderMetadata,
+}
+
+impl TextField {
+    /// Returns the field name as a string.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TextField::ProductName => "product_name",
+            TextField::CancellationReason => "reason",
+            TextField::DeliveryFailureReason => "failure_reason",
+            TextField::OrderMetadata => "metadata",
+        }
+    }
+}
+
+impl std::fmt::Display for TextField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// A step in the command middleware chain.
+///
+/// Middleware receives the current value of a free-text field and returns the
+/// (possibly rewritten) value, or an error to reject the command.
+pub trait CommandMiddleware: Send + Sync {
+    /// Processes the value of a free-text field.
+    fn process_text(&self, field: TextField, value: String) -> Result<String, OrderError>;
+}
+
+/// Trims surrounding whitespace, collapses internal runs of whitespace to a
+/// single space, and strips control characters.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WhitespaceNormalizer;
+
+impl CommandMiddleware for WhitespaceNormalizer {
+    fn process_text(&self, _field: TextField, value: String) -> Result<String, OrderError> {
+        let normalized = value
+            .split(|c: char| c.is_whitespace() || c.is_control())
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        Ok(normalized)
+    }
+}
+
+/// What to do when disallowed content is found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FilterAction {
+    /// Reject the command with [`OrderError::DisallowedContent`].
+    #[default]
+    Reject,
+
+    /// Replace the offending characters with `*` and let the command through.
+    Mask,
+}
+
+/// Case-insensitive word deny list (profanity, banned terms).
+///
+/// Words are matched as whole alphanumeric runs, so "class" does not match a
+/// denied "ass".
+#[derive(Debug, Clone, Default)]
+pub struct DenyListFilter {
+    words: HashSet<String>,
+    action: FilterAction,
+}
+
+impl DenyListFilter {
+    /// Creates a filter for the given words that rejects matching commands.
+    pub fn new<I, W>(words: I) -> Self
+    where
+        I: IntoIterator<Item = W>,
+        W: AsRef<str>,
+    {
+        Self {
+            words: words
+                .into_iter()
+                .map(|w| w.as_ref().to_lowercase())
+                .collect(),
+            action: FilterAction::Reject,
+        }
+    }
+
+    /// Sets the action taken when a denied word is found.
+    pub fn with_action(mut self, action: FilterAction) -> Self {
+        self.action = action;
+        self
+    }
+}
+
+impl CommandMiddleware for DenyListFilter {
+    fn process_text(&self, field: TextField, value: String) -> Result<String, OrderError> {
+        let mut output = String::with_capacity(value.len());
+        let mut word = String::new();
+
+        let flush = |word: &mut String, output: &mut String| -> Result<(), OrderError> {
+            if word.is_empty() {
+                return Ok(());
+            }
+            if self.words.contains(&word.to_lowercase()) {
+                match self.action {
+                    FilterAction::Reject => {
+                        return Err(OrderError::DisallowedContent {
+                            field: field.as_str(),
+                            reason: "contains a denied word".to_string(),
+                        });
+                    }
+                    FilterAction::Mask => {
+                        output.extend(std::iter::repeat_n('*', word.chars().count()));
+                    }
+                }
+            } else {
+                output.push_str(word);
+            }
+            word.clear();
+            Ok(())
+        };
+
+        for c in value.chars() {
+            if c.is_alphanumeric() {
+                word.push(c);
+            } else {
+                flush(&mut word, &mut output)?;
+                output.push(c);
+            }
+        }
+        flush(&mut word, &mut output)?;
+
+        Ok(output)
+    }
+}
+
+/// Detects personally identifiable information (email addresses and long
+/// digit sequences such as phone or card numbers).
+#[derive(Debug, Clone, Copy)]
+pub struct PiiMasker {
+    action: FilterAction,
+    min_digits: usize,
+}
+
+/// Replacement text for masked PII.
+const REDACTED: &str = "[redacted]";
+
+impl PiiMasker {
+    /// Creates a masker that redacts detected PII.
+    pub fn new() -> Self {
+        Self {
+            action: FilterAction::Mask,
+            min_digits: 10,
+        }
+    }
+
+    /// Sets the action taken when PII is found.
+    pub fn with_action(mut self, action: FilterAction) -> Self {
+        self.action = action;
+        self
+    }
+
+    /// Sets how many digits a number must contain to be treated as PII.
+    pub fn with_min_digits(mut self, min_digits: usize) -> Self {
+        self.min_digits = min_digits;
+        self
+    }
+
+    fn is_email(token: &str) -> bool {
+        let token = token.trim_matches(|c: char| !c.is_alphanumeric());
+        match token.split_once('@') {
+            Some((local, domain)) => {
+                !local.is_empty() && domain.contains('.') && !domain.starts_with('.')
+            }
+            None => false,
+        }
+    }
+
+    /// Returns byte ranges of numbers that contain at least `min_digits`
+    /// digits.
+    ///
+    /// A number is a run of digit groups joined by single spaces, hyphens,
+    /// or dots, optionally led by `+` and with its first group in
+    /// parentheses, as in `+1 (555) 123-4567`. Groups joined by a space need
+    /// at least three digits, so a list of short numbers isn't read as one.
+    /// Punctuation around a number stays outside its range.
+    fn number_spans(&self, value: &str) -> Vec<(usize, usize)> {
+        let bytes = value.as_bytes();
+        let mut spans = Vec::new();
+        let mut i = 0;
+        while i < bytes.len() {
+            match Self::number_at(bytes, i) {
+                Some((end, digits)) => {
+                    if digits >= self.min_digits {
+                        spans.push((i, end));
+                    }
+                    i = end;
+                }
+                None => i += 1,
+            }
+        }
+        spans
+    }
+
+    /// Parses a number starting at `start`, returning where it ends and how
+    /// many digits it has.
+    fn number_at(bytes: &[u8], start: usize) -> Option<(usize, usize)> {
+        let digits_from = |pos: usize| {
+            bytes[pos.min(bytes.len())..]
+                .iter()
+                .take_while(|b| b.is_ascii_digit())
+                .count()
+        };
+
+        let mut pos = start;
+        if bytes[pos] == b'+' {
+            pos += 1;
+        }
+        let mut digits;
+        if bytes.get(pos) == Some(&b'(') {
+            digits = digits_from(pos + 1);
+            if digits == 0 || bytes.get(pos + 1 + digits) != Some(&b')') {
+                return None;
+            }
+            pos += digits + 2;
+        } else {
+            digits = digits_from(pos);
+            if digits == 0 {
+                return None;
+            }
+            pos += digits;
+        }
+
+        while let Some(&sep) = bytes.get(pos) {
+            let group = digits_from(pos + 1);
+            let joined = match sep {
+                b' ' => group >= 3,
+                b'-' | b'.' => group > 0,
+                _ => false,
+            };
+            if !joined {
+                break;
+            }
+            digits += group;
+            pos += group + 1;
+        }
+        Some((pos, digits))
+    }
+
+    /// Returns the byte range of `token` without surrounding punctuation.
+    fn trimmed_range(token: &str) -> (usize, usize) {
+        let is_punct = |c: char| !c.is_alphanumeric();
+        let start = token.len() - token.trim_start_matches(is_punct).len();
+        let end = token.trim_end_matches(is_punct).len();
+        (start, end.max(start))
+    }
+}
+
+impl Default for PiiMasker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CommandMiddleware for PiiMasker {
+    fn process_text(&self, field: TextField, value: String) -> Result<String, OrderError> {
+        let reject = || OrderError::DisallowedContent {
+            field: field.as_str(),
+            reason: "contains personal information".to_string(),
+        };
+
+        // Mask long numbers first, then emails token by token.
+        let spans = self.number_spans(&value);
+        let mut masked = String::with_capacity(value.len());
+        let mut last = 0;
+        for (start, end) in spans {
+            if self.action == FilterAction::Reject {
+                return Err(reject());
+            }
+            masked.push_str(&value[last..start]);
+            masked.push_str(REDACTED);
+            last = end;
+        }
+        masked.push_str(&value[last..]);
+
+        let mut output = Vec::new();
+        for token in masked.split(' ') {
+            if Self::is_email(token) {
+                if self.action == FilterAction::Reject {
+                    return Err(reject());
+                }
+                // Punctuation around the address, e.g. a closing period, stays
+                let (start, end) = Self::trimmed_range(token);
+                output.push(format!("{}{REDACTED}{}", &token[..start], &token[end..]));
+            } else {
+                output.push(token.to_string());
+            }
+        }
+
+        Ok(output.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_whitespace_normalizer() {
+        let result = WhitespaceNormalizer
+            .process_text(
+                TextField::ProductName,
+                "  Blue\t\tWidget \n XL\u{7} ".into(),
+            )
+            .unwrap();
+        assert_eq!(result, "Blue Widget XL");
+    }
+
+    #[test]
+    fn test_deny_list_rejects_whole_words_only() {
+        let filter = DenyListFilter::new(["darn"]);
+
+        let result = filter.process_text(TextField::ProductName, "Darn Widget".into());
+        assert!(matches!(
+            result,
+            Err(OrderError::DisallowedContent {
+                field: "product_name",
+                ..
+            })
+        ));
+
+        let result = filter
+            .process_text(TextField::ProductName, "Darnell Widget".into())
+            .unwrap();
+        assert_eq!(result, "Darnell Widget");
+    }
+
+    #[test]
+    fn test_deny_list_mask() {
+        let filter = DenyListFilter::new(["darn"]).with_action(FilterAction::Mask);
+        let result = filter
+            .process_text(TextField::CancellationReason, "darn, too slow!".into())
+            .unwrap();
+        assert_eq!(result, "****, too slow!");
+    }
+
+    #[test]
+    fn test_pii_masker_masks_emails_and_phone_numbers() {
+        let result = PiiMasker::new()
+            .process_text(
+                TextField::CancellationReason,
+                "call me at (555) 123-4567 or <jane@example.com>.".into(),
+            )
+            .unwrap();
+        assert_eq!(result, "call me at [redacted] or <[redacted]>.");
+
+        let result = PiiMasker::new()
+            .process_text(
+                TextField::DeliveryFailureReason,
+                "no answer on +1 555.123.4567, left at depot.".into(),
+            )
+            .unwrap();
+        assert_eq!(result, "no answer on [redacted], left at depot.");
+    }
+
+    #[test]
+    fn test_pii_masker_keeps_short_numbers() {
+        let result = PiiMasker::new()
+            .process_text(TextField::ProductName, "Widget 2000 Pack of 12".into())
+            .unwrap();
+        assert_eq!(result, "Widget 2000 Pack of 12");
+
+        // Short numbers next to each other aren't one long number
+        let result = PiiMasker::new()
+            .process_text(TextField::ProductName, "Sizes 10 12 14 16 18 20".into())
+            .unwrap();
+        assert_eq!(result, "Sizes 10 12 14 16 18 20");
+    }
+
+    #[test]
+    fn test_pii_masker_reject() {
+        let masker = PiiMasker::new().with_action(FilterAction::Reject);
+        let result = masker.process_text(TextField::ProductName, "gift for bob@example.org".into());
+        assert!(matches!(result, Err(OrderError::DisallowedContent { .. })));
+    }
+}
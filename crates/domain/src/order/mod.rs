@@ -2,21 +2,37 @@
 
 mod aggregate;
 mod commands;
+mod diff;
 mod events;
+mod import;
+mod limits;
+mod middleware;
+mod pricing;
 mod service;
 mod state;
+mod throttle;
 mod value_objects;
 
 pub use aggregate::Order;
 pub use commands::*;
+pub use diff::{Change, ItemChange, OrderDiff};
 pub use events::{
-    ItemAddedData, ItemQuantityUpdatedData, ItemRemovedData, OrderCancelledData,
-    OrderCompletedData, OrderCreatedData, OrderEvent, OrderProcessingData, OrderReservedData,
-    OrderSubmittedData,
+    DeliveryFailedData, ItemAddedData, ItemPriceAdjustedData, ItemQuantityUpdatedData,
+    ItemRemovedData, ItemSubstitutedData, OrderCancelledData, OrderCompletedData, OrderCreatedData,
+    OrderDeliveredData, OrderEvent, OrderInTransitData, OrderMetadataSetData, OrderProcessingData,
+    OrderReservedData, OrderShippedData, OrderSubmittedData, ReservationRenewedData,
+    ShippingAddressSetData, ShippingAddressUpdatedData, TagAddedData, TagRemovedData,
 };
-pub use service::OrderService;
+pub use import::{ImportRecord, ImportReport, ImportValidator, ImportViolation, StreamReport};
+pub use limits::OrderLimits;
+pub use middleware::{
+    CommandMiddleware, DenyListFilter, FilterAction, PiiMasker, TextField, WhitespaceNormalizer,
+};
+pub use pricing::{InMemoryPriceCatalog, PriceCatalog, PriceDrift, PriceDriftPolicy};
+pub use service::{OrderCommands, OrderQueries, OrderService};
 pub use state::OrderState;
-pub use value_objects::{CustomerId, Money, OrderItem, ProductId};
+pub use throttle::DraftThrottle;
+pub use value_objects::{Address, Currency, CustomerId, Money, OrderItem, ProductId, ShippedItem};
 
 use thiserror::Error;
 
@@ -53,4 +69,127 @@ pub enum OrderError {
     /// Order is already created.
     #[error("Order already created")]
     AlreadyCreated,
+
+    /// A free-text field was rejected by command middleware.
+    #[error("Disallowed content in {field}: {reason}")]
+    DisallowedContent { field: &'static str, reason: String },
+
+    /// Currency code is not a valid ISO 4217 code.
+    #[error("Invalid currency code: {code}")]
+    InvalidCurrency { code: String },
+
+    /// An amount is in a different currency from the order.
+    #[error("Amount in {actual} doesn't match the order currency {expected}")]
+    CurrencyMismatch {
+        expected: Currency,
+        actual: Currency,
+    },
+
+    /// A command emitted more than one item change for the same product.
+    #[error("Conflicting item changes for {product_id} in a single command")]
+    ConflictingItemEvents { product_id: String },
+
+    /// Shipped item details don't match the order's items.
+    #[error("Invalid shipped item {product_id}: {reason}")]
+    InvalidShippedItem { product_id: String, reason: String },
+
+    /// Item prices no longer match the catalog at submit time.
+    #[error("Prices changed since items were added: {}", drifts.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "))]
+    PriceDrift { drifts: Vec<PriceDrift> },
+
+    /// The substitute for an item can't replace it.
+    #[error("Invalid substitute {product_id}: {reason}")]
+    InvalidSubstitution {
+        product_id: String,
+        reason: &'static str,
+    },
+
+    /// Tag is empty, too long, or has characters outside `[a-z0-9_:-]`.
+    #[error("Invalid tag {tag:?}: {reason}")]
+    InvalidTag { tag: String, reason: &'static str },
+
+    /// Metadata key isn't on the allowlist, or its value is too long.
+    #[error("Invalid metadata {key:?}: {reason}")]
+    InvalidMetadata { key: String, reason: String },
+
+    /// A shipping address field is missing or malformed.
+    #[error("Invalid shipping address: {field} {reason}")]
+    InvalidAddress {
+        field: &'static str,
+        reason: &'static str,
+    },
+
+    /// The order already has a shipping address; update it instead.
+    #[error("Order already has a shipping address")]
+    ShippingAddressAlreadySet,
+
+    /// The order has no shipping address to update; set one instead.
+    #[error("Order has no shipping address")]
+    NoShippingAddress,
+
+    /// The same command was already applied within the dedupe window.
+    #[error("Duplicate {command}: an identical command was applied in the last {window_secs}s")]
+    DuplicateCommand {
+        command: &'static str,
+        window_secs: u64,
+    },
+
+    /// Adding the item would put more products in the order than allowed.
+    #[error("Order already has the maximum of {limit} items")]
+    TooManyItems { limit: usize },
+
+    /// An item's quantity would exceed the per-line limit.
+    #[error("Quantity {quantity} of {product_id} exceeds the limit of {limit}")]
+    LineQuantityExceeded {
+        product_id: String,
+        quantity: u32,
+        limit: u32,
+    },
+
+    /// The order total would exceed the limit.
+    #[error("Order total would exceed the limit of {limit}")]
+    OrderTotalExceeded { limit: Money },
+
+    /// The customer created too many orders within the throttle window.
+    #[error(
+        "Customer {customer_id} created {limit} orders in the last {window_secs}s; retry in {retry_after_secs}s"
+    )]
+    DraftLimitExceeded {
+        customer_id: CustomerId,
+        limit: u32,
+        window_secs: u64,
+        retry_after_secs: u64,
+    },
+}
+
+impl OrderError {
+    /// Returns a stable code naming the violated invariant.
+    pub fn code(&self) -> &'static str {
+        match self {
+            OrderError::CustomerIdRequired => "order.customer_id_required",
+            OrderError::InvalidStateTransition { .. } => "order.invalid_state_transition",
+            OrderError::ItemNotFound { .. } => "order.item_not_found",
+            OrderError::InvalidQuantity { .. } => "order.invalid_quantity",
+            OrderError::InvalidPrice { .. } => "order.invalid_price",
+            OrderError::NoItems => "order.no_items",
+            OrderError::AlreadyCreated => "order.already_created",
+            OrderError::DisallowedContent { .. } => "order.disallowed_content",
+            OrderError::InvalidCurrency { .. } => "order.invalid_currency",
+            OrderError::CurrencyMismatch { .. } => "order.currency_mismatch",
+            OrderError::ConflictingItemEvents { .. } => "order.conflicting_item_events",
+            OrderError::InvalidShippedItem { .. } => "order.invalid_shipped_item",
+            OrderError::InvalidSubstitution { .. } => "order.invalid_substitution",
+            OrderError::PriceDrift { .. } => "order.price_drift",
+            OrderError::DuplicateCommand { .. } => "order.duplicate_command",
+            OrderError::InvalidTag { .. } => "order.invalid_tag",
+            OrderError::InvalidMetadata { .. } => "order.invalid_metadata",
+            OrderError::InvalidAddress { .. } => "order.invalid_address",
+            OrderError::ShippingAddressAlreadySet => "order.shipping_address_already_set",
+            OrderError::NoShippingAddress => "order.no_shipping_address",
+            OrderError::DraftLimitExceeded { .. } => "order.draft_limit_exceeded",
+            OrderError::TooManyItems { .. } => "order.too_many_items",
+            OrderError::LineQuantityExceeded { .. } => "order.line_quantity_exceeded",
+            OrderError::OrderTotalExceeded { .. } => "order.total_exceeded",
+        }
+    }
 }
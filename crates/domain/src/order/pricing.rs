@@ -0,0 +1,113 @@
+//! Submit-time price reconciliation against a catalog.
+//!
+//! Item prices are captured when items are added. When a deployment enables
+//! reconciliation, submitting an order compares those prices with the
+//! catalog's current ones and either adjusts the items or rejects the submit,
+//! depending on the [`PriceDriftPolicy`].
+
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::RwLock;
+
+use serde::Serialize;
+
+use super::{Money, ProductId};
+
+/// Source of current product prices.
+pub trait PriceCatalog: Send + Sync {
+    /// Returns the product's current unit price, or None if the catalog
+    /// doesn't list it. Unlisted products are not reconciled.
+    fn current_price(&self, product_id: &ProductId) -> Option<Money>;
+}
+
+/// Price catalog held in memory.
+#[derive(Debug, Default)]
+pub struct InMemoryPriceCatalog {
+    prices: RwLock<HashMap<ProductId, Money>>,
+}
+
+impl InMemoryPriceCatalog {
+    /// Creates an empty catalog.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets a product's current unit price.
+    pub fn set_price(&self, product_id: impl Into<ProductId>, unit_price: Money) {
+        self.prices
+            .write()
+            .unwrap()
+            .insert(product_id.into(), unit_price);
+    }
+}
+
+impl PriceCatalog for InMemoryPriceCatalog {
+    fn current_price(&self, product_id: &ProductId) -> Option<Money> {
+        self.prices.read().unwrap().get(product_id).copied()
+    }
+}
+
+/// What to do when an item's stored price differs from the catalog's.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PriceDriftPolicy {
+    /// Update the item to the current price before submitting.
+    Adjust,
+    /// Refuse the submit, reporting every drifted item.
+    Reject,
+}
+
+impl FromStr for PriceDriftPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "adjust" => Ok(PriceDriftPolicy::Adjust),
+            "reject" => Ok(PriceDriftPolicy::Reject),
+            other => Err(format!("unknown price drift policy: {other}")),
+        }
+    }
+}
+
+/// An item whose stored price no longer matches the catalog.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PriceDrift {
+    pub product_id: ProductId,
+    /// Unit price captured when the item was added.
+    pub stored: Money,
+    /// Unit price currently listed in the catalog.
+    pub current: Money,
+}
+
+impl std::fmt::Display for PriceDrift {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} ({} -> {})",
+            self.product_id, self.stored, self.current
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_catalog_lookup() {
+        let catalog = InMemoryPriceCatalog::new();
+        catalog.set_price("SKU-001", Money::from_cents(1200));
+
+        assert_eq!(
+            catalog.current_price(&ProductId::new("SKU-001")),
+            Some(Money::from_cents(1200))
+        );
+        assert_eq!(catalog.current_price(&ProductId::new("SKU-002")), None);
+    }
+
+    #[test]
+    fn test_policy_from_str() {
+        assert_eq!("adjust".parse(), Ok(PriceDriftPolicy::Adjust));
+        assert_eq!("REJECT".parse(), Ok(PriceDriftPolicy::Reject));
+        assert!("ignore".parse::<PriceDriftPolicy>().is_err());
+    }
+}
@@ -1,14 +1,28 @@
 //! Order service providing a simplified API for order operations.
 
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::Utc;
 use common::AggregateId;
-use event_store::EventStore;
+use event_store::{EnvelopeFactory, EventStore, SchemaDeprecations, Version};
 
-use crate::command::{CommandHandler, CommandResult};
+use crate::aggregate::Aggregate;
+use crate::command::{BatchedCommand, Command, CommandHandler, CommandResult};
+use crate::contention::ContentionTracker;
+use crate::decision::DecisionLog;
+use crate::dedupe::{PAYLOAD_HASH_KEY, PayloadHasher, Sha256PayloadHasher};
 use crate::error::DomainError;
+use crate::metadata::CommandMetadata;
 
 use super::{
-    AddItem, CancelOrder, CompleteOrder, CreateOrder, CustomerId, MarkReserved, Money, Order,
-    OrderItem, ProductId, RemoveItem, StartProcessing, SubmitOrder, UpdateItemQuantity,
+    AddItem, AddTag, CancelOrder, CommandMiddleware, CompleteOrder, CreateOrder, CustomerId,
+    DraftThrottle, MarkDelivered, MarkInTransit, MarkReserved, MarkShipped, Money, Order,
+    OrderDiff, OrderError, OrderItem, OrderLimits, PriceCatalog, PriceDriftPolicy, ProductId,
+    RecordDeliveryFailure, RemoveItem, RemoveTag, RenewReservations, SetOrderMetadata,
+    SetShippingAddress, StartProcessing, SubmitOrder, SubstituteItem, TextField, UpdateItemPrice,
+    UpdateItemQuantity, UpdateShippingAddress,
 };
 
 impl From<super::OrderError> for DomainError {
@@ -17,12 +31,103 @@ impl From<super::OrderError> for DomainError {
     }
 }
 
+/// Order commands, as used by callers that only need to change orders.
+///
+/// Implemented by [`OrderService`]; callers that take this trait can be
+/// tested against a mock that returns canned results or errors.
+#[async_trait]
+pub trait OrderCommands: Send + Sync {
+    async fn create_order(&self, cmd: CreateOrder) -> Result<CommandResult<Order>, DomainError>;
+    async fn add_item(&self, cmd: AddItem) -> Result<CommandResult<Order>, DomainError>;
+    async fn remove_item(&self, cmd: RemoveItem) -> Result<CommandResult<Order>, DomainError>;
+    async fn update_item_quantity(
+        &self,
+        cmd: UpdateItemQuantity,
+    ) -> Result<CommandResult<Order>, DomainError>;
+    async fn update_item_price(
+        &self,
+        cmd: UpdateItemPrice,
+    ) -> Result<CommandResult<Order>, DomainError>;
+    async fn substitute_item(
+        &self,
+        cmd: SubstituteItem,
+    ) -> Result<CommandResult<Order>, DomainError>;
+    async fn submit_order(&self, cmd: SubmitOrder) -> Result<CommandResult<Order>, DomainError>;
+    async fn mark_reserved(&self, cmd: MarkReserved) -> Result<CommandResult<Order>, DomainError>;
+    async fn renew_reservations(
+        &self,
+        cmd: RenewReservations,
+    ) -> Result<CommandResult<Order>, DomainError>;
+    async fn start_processing(
+        &self,
+        cmd: StartProcessing,
+    ) -> Result<CommandResult<Order>, DomainError>;
+    async fn complete_order(&self, cmd: CompleteOrder)
+    -> Result<CommandResult<Order>, DomainError>;
+    async fn mark_shipped(&self, cmd: MarkShipped) -> Result<CommandResult<Order>, DomainError>;
+    async fn mark_in_transit(
+        &self,
+        cmd: MarkInTransit,
+    ) -> Result<CommandResult<Order>, DomainError>;
+    async fn mark_delivered(&self, cmd: MarkDelivered)
+    -> Result<CommandResult<Order>, DomainError>;
+    async fn record_delivery_failure(
+        &self,
+        cmd: RecordDeliveryFailure,
+    ) -> Result<CommandResult<Order>, DomainError>;
+    async fn cancel_order(&self, cmd: CancelOrder) -> Result<CommandResult<Order>, DomainError>;
+    async fn add_tag(&self, cmd: AddTag) -> Result<CommandResult<Order>, DomainError>;
+    async fn remove_tag(&self, cmd: RemoveTag) -> Result<CommandResult<Order>, DomainError>;
+    async fn set_metadata(
+        &self,
+        cmd: SetOrderMetadata,
+    ) -> Result<CommandResult<Order>, DomainError>;
+    async fn set_shipping_address(
+        &self,
+        cmd: SetShippingAddress,
+    ) -> Result<CommandResult<Order>, DomainError>;
+    async fn update_shipping_address(
+        &self,
+        cmd: UpdateShippingAddress,
+    ) -> Result<CommandResult<Order>, DomainError>;
+}
+
+/// Order reads, as used by callers that only need to look orders up.
+#[async_trait]
+pub trait OrderQueries: Send + Sync {
+    /// Loads an order by ID, or None if it doesn't exist.
+    async fn get_order(&self, order_id: AggregateId) -> Result<Option<Order>, DomainError>;
+
+    /// Replays an order at two versions and returns what changed between them.
+    async fn diff_versions(
+        &self,
+        order_id: AggregateId,
+        from: Version,
+        to: Version,
+    ) -> Result<Option<OrderDiff>, DomainError>;
+
+    /// Replays an order and returns its state after each event in
+    /// `from..=to`.
+    async fn replay_range(
+        &self,
+        order_id: AggregateId,
+        from: Version,
+        to: Version,
+    ) -> Result<Vec<(Version, Order)>, DomainError>;
+}
+
 /// Service for managing orders.
 ///
 /// Provides a high-level API for order operations, wrapping the command handler
 /// and providing convenient methods for common operations.
 pub struct OrderService<S: EventStore> {
     handler: CommandHandler<S, Order>,
+    middleware: Vec<Box<dyn CommandMiddleware>>,
+    pricing: Option<(Arc<dyn PriceCatalog>, PriceDriftPolicy)>,
+    hasher: Arc<dyn PayloadHasher>,
+    dedupe_window: Option<Duration>,
+    draft_throttle: Option<DraftThrottle>,
+    limits: OrderLimits,
 }
 
 impl<S: EventStore> OrderService<S> {
@@ -30,45 +135,225 @@ impl<S: EventStore> OrderService<S> {
     pub fn new(store: S) -> Self {
         Self {
             handler: CommandHandler::new(store),
+            middleware: Vec::new(),
+            pricing: None,
+            hasher: Arc::new(Sha256PayloadHasher),
+            dedupe_window: None,
+            draft_throttle: None,
+            limits: OrderLimits::unlimited(),
         }
     }
 
+    /// Adds middleware to run over free-text command fields.
+    ///
+    /// Middleware runs in registration order before any events are produced.
+    pub fn with_middleware(mut self, middleware: impl CommandMiddleware + 'static) -> Self {
+        self.middleware.push(Box::new(middleware));
+        self
+    }
+
+    /// Reconciles item prices with `catalog` when orders are submitted,
+    /// handling drift according to `policy`.
+    pub fn with_price_reconciliation(
+        mut self,
+        catalog: Arc<dyn PriceCatalog>,
+        policy: PriceDriftPolicy,
+    ) -> Self {
+        self.pricing = Some((catalog, policy));
+        self
+    }
+
+    /// Replaces the SHA-256 hasher used to fingerprint item additions.
+    pub fn with_payload_hasher(mut self, hasher: Arc<dyn PayloadHasher>) -> Self {
+        self.hasher = hasher;
+        self
+    }
+
+    /// Refuses an item addition identical to one applied to the same order
+    /// within `window`, such as a double-clicked submit.
+    pub fn with_dedupe_window(mut self, window: Duration) -> Self {
+        self.dedupe_window = Some(window);
+        self
+    }
+
+    /// Limits how many orders each customer can create, refusing the rest
+    /// with [`OrderError::DraftLimitExceeded`].
+    pub fn with_draft_throttle(mut self, throttle: DraftThrottle) -> Self {
+        self.draft_throttle = Some(throttle);
+        self
+    }
+
+    /// Refuses item additions and quantity increases that would take an
+    /// order past `limits`.
+    pub fn with_limits(mut self, limits: OrderLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Records concurrency conflicts on orders in `tracker`.
+    pub fn with_contention_tracker(mut self, tracker: ContentionTracker) -> Self {
+        self.handler = self.handler.with_contention_tracker(tracker);
+        self
+    }
+
+    /// Records command decisions in `log`.
+    pub fn with_decision_log(mut self, log: DecisionLog) -> Self {
+        self.handler = self.handler.with_decision_log(log);
+        self
+    }
+
+    /// Counts reads of deprecated event schema versions in `deprecations`.
+    pub fn with_schema_deprecations(mut self, deprecations: SchemaDeprecations) -> Self {
+        self.handler = self.handler.with_schema_deprecations(deprecations);
+        self
+    }
+
+    /// Builds this service's event envelopes with `factory`.
+    pub fn with_envelope_factory(mut self, factory: EnvelopeFactory) -> Self {
+        self.handler = self.handler.with_envelope_factory(factory);
+        self
+    }
+
+    /// Runs a free-text field through the middleware chain.
+    fn sanitize(&self, field: TextField, value: String) -> Result<String, OrderError> {
+        self.middleware
+            .iter()
+            .try_fold(value, |value, m| m.process_text(field, value))
+    }
+
     /// Returns a reference to the underlying command handler.
     pub fn handler(&self) -> &CommandHandler<S, Order> {
         &self.handler
     }
 
-    /// Creates a new order for a customer.
+    /// Creates a new order for a customer, appending its creation and every
+    /// item it is created with in one batch.
     #[tracing::instrument(skip(self))]
     pub async fn create_order(
         &self,
         cmd: CreateOrder,
     ) -> Result<CommandResult<Order>, DomainError> {
-        let order_id = cmd.order_id;
-        let customer_id = cmd.customer_id;
+        let command = cmd.name();
+        let CreateOrder {
+            order_id,
+            customer_id,
+            currency,
+            items,
+        } = cmd;
+        let (slot, refused) = match &self.draft_throttle {
+            Some(throttle) => match throttle.acquire(customer_id, Utc::now()) {
+                Ok(slot) => (Some(slot), None),
+                Err(e) => (None, Some(e)),
+            },
+            None => (None, None),
+        };
+
+        let create: BatchedCommand<'_, Order> = Box::new(move |order: &Order| {
+            // Refused inside the command so the rejection is logged
+            if let Some(e) = refused {
+                return Err(e);
+            }
+            order.create_in(order_id, customer_id, currency)
+        });
+        let mut commands = vec![(CommandMetadata::new(), create)];
+        for mut item in items {
+            let name = std::mem::take(&mut item.product_name);
+            let sanitized = self.sanitize(TextField::ProductName, name).map(|name| {
+                item.product_name = name;
+                item
+            });
+            // Hashed like add_item's, so repeating the item later is caught
+            let metadata = match &sanitized {
+                Ok(item) => payload_metadata(self.item_hash(item)?),
+                Err(_) => CommandMetadata::new(),
+            };
+            let add: BatchedCommand<'_, Order> =
+                Box::new(move |order: &Order| order.add_item_within(sanitized?, &self.limits));
+            commands.push((metadata, add));
+        }
 
-        self.handler
-            .execute(order_id, |order| order.create(order_id, customer_id))
-            .await
+        let result = self
+            .handler
+            .execute_many_named_with_metadata(command, order_id, commands)
+            .await;
+        // An order that wasn't created doesn't count against the customer
+        if result.is_err()
+            && let (Some(throttle), Some(slot)) = (&self.draft_throttle, slot)
+        {
+            throttle.release(customer_id, slot);
+        }
+        result
     }
 
     /// Adds an item to an order.
     #[tracing::instrument(skip(self))]
     pub async fn add_item(&self, cmd: AddItem) -> Result<CommandResult<Order>, DomainError> {
-        let item = cmd.item.clone();
+        let command = cmd.name();
+        let AddItem { order_id, mut item } = cmd;
+        item.product_name = self.sanitize(TextField::ProductName, item.product_name)?;
+
+        let hash = self.item_hash(&item)?;
+        let duplicate = match self.dedupe_window {
+            Some(window) => self.applied_recently(order_id, &hash, window).await?,
+            None => false,
+        };
+        let metadata = payload_metadata(hash);
 
         self.handler
-            .execute(cmd.order_id, |order| order.add_item(item))
+            .execute_named_with_metadata(command, order_id, metadata, |order| {
+                if let Some(window) = self.dedupe_window.filter(|_| duplicate) {
+                    return Err(OrderError::DuplicateCommand {
+                        command,
+                        window_secs: window.as_secs(),
+                    });
+                }
+                order.add_item_within(item, &self.limits)
+            })
             .await
     }
 
+    /// Hashes an item's payload. Items are hashed after sanitizing, so
+    /// inputs differing only in whitespace count as the same item.
+    fn item_hash(&self, item: &OrderItem) -> Result<String, DomainError> {
+        let payload = serde_json::to_vec(item)?;
+        Ok(self.hasher.hash(&payload))
+    }
+
+    /// Returns true if an event carrying payload `hash` was appended to the
+    /// order within `window`.
+    async fn applied_recently(
+        &self,
+        order_id: AggregateId,
+        hash: &str,
+        window: Duration,
+    ) -> Result<bool, DomainError> {
+        let cutoff = Utc::now() - window;
+        let events = self
+            .handler
+            .store()
+            .get_events_for_aggregate(order_id)
+            .await?;
+        Ok(events
+            .iter()
+            .rev()
+            .take_while(|event| event.timestamp >= cutoff)
+            .any(|event| {
+                event
+                    .metadata
+                    .get(PAYLOAD_HASH_KEY)
+                    .is_some_and(|value| value == hash)
+            }))
+    }
+
     /// Removes an item from an order.
     #[tracing::instrument(skip(self))]
     pub async fn remove_item(&self, cmd: RemoveItem) -> Result<CommandResult<Order>, DomainError> {
         let product_id = cmd.product_id.clone();
 
         self.handler
-            .execute(cmd.order_id, |order| order.remove_item(product_id))
+            .execute_named(cmd.name(), cmd.order_id, |order| {
+                order.remove_item(product_id)
+            })
             .await
     }
 
@@ -82,20 +367,65 @@ impl<S: EventStore> OrderService<S> {
         let new_quantity = cmd.new_quantity;
 
         self.handler
-            .execute(cmd.order_id, |order| {
-                order.update_item_quantity(product_id, new_quantity)
+            .execute_named(cmd.name(), cmd.order_id, |order| {
+                order.update_item_quantity_within(product_id, new_quantity, &self.limits)
+            })
+            .await
+    }
+
+    /// Changes the unit price of an item in an order.
+    #[tracing::instrument(skip(self))]
+    pub async fn update_item_price(
+        &self,
+        cmd: UpdateItemPrice,
+    ) -> Result<CommandResult<Order>, DomainError> {
+        let product_id = cmd.product_id.clone();
+        let new_unit_price = cmd.new_unit_price;
+
+        self.handler
+            .execute_named(cmd.name(), cmd.order_id, |order| {
+                order.update_item_price(product_id, new_unit_price)
+            })
+            .await
+    }
+
+    /// Replaces an item in a reserved or processing order with a substitute.
+    #[tracing::instrument(skip(self))]
+    pub async fn substitute_item(
+        &self,
+        cmd: SubstituteItem,
+    ) -> Result<CommandResult<Order>, DomainError> {
+        let command = cmd.name();
+        let SubstituteItem {
+            order_id,
+            original_product_id,
+            mut substitute,
+            reservation_id,
+            metadata,
+        } = cmd;
+        substitute.product_name = self.sanitize(TextField::ProductName, substitute.product_name)?;
+
+        self.handler
+            .execute_named_with_metadata(command, order_id, metadata, |order| {
+                order.substitute_item(&original_product_id, substitute, reservation_id)
             })
             .await
     }
 
     /// Submits an order for processing.
+    ///
+    /// With price reconciliation configured, item prices are checked against
+    /// the catalog first.
     #[tracing::instrument(skip(self))]
     pub async fn submit_order(
         &self,
         cmd: SubmitOrder,
     ) -> Result<CommandResult<Order>, DomainError> {
         self.handler
-            .execute(cmd.order_id, |order| order.submit())
+            .execute_named(cmd.name(), cmd.order_id, |order| match &self.pricing {
+                Some((catalog, policy)) => order.submit_with_prices(catalog.as_ref(), *policy),
+                None => order.submit(),
+            })
             .await
     }
 
@@ -105,10 +435,37 @@ impl<S: EventStore> OrderService<S> {
         &self,
         cmd: MarkReserved,
     ) -> Result<CommandResult<Order>, DomainError> {
-        let reservation_id = cmd.reservation_id.clone();
+        let command = cmd.name();
+        let MarkReserved {
+            order_id,
+            reservations,
+            metadata,
+        } = cmd;
 
         self.handler
-            .execute(cmd.order_id, |order| order.mark_reserved(reservation_id))
+            .execute_named_with_metadata(command, order_id, metadata, |order| {
+                order.mark_reserved(reservations)
+            })
+            .await
+    }
+
+    /// Replaces a reserved order's lapsed line-item reservations.
+    #[tracing::instrument(skip(self))]
+    pub async fn renew_reservations(
+        &self,
+        cmd: RenewReservations,
+    ) -> Result<CommandResult<Order>, DomainError> {
+        let command = cmd.name();
+        let RenewReservations {
+            order_id,
+            reservations,
+            metadata,
+        } = cmd;
+
+        self.handler
+            .execute_named_with_metadata(command, order_id, metadata, |order| {
+                order.renew_reservations(reservations)
+            })
             .await
     }
 
@@ -118,10 +475,17 @@ impl<S: EventStore> OrderService<S> {
         &self,
         cmd: StartProcessing,
     ) -> Result<CommandResult<Order>, DomainError> {
-        let payment_id = cmd.payment_id.clone();
+        let command = cmd.name();
+        let StartProcessing {
+            order_id,
+            payment_id,
+            metadata,
+        } = cmd;
 
         self.handler
-            .execute(cmd.order_id, |order| order.start_processing(payment_id))
+            .execute_named_with_metadata(command, order_id, metadata, |order| {
+                order.start_processing(payment_id)
+            })
             .await
     }
 
@@ -131,10 +495,104 @@ impl<S: EventStore> OrderService<S> {
         &self,
         cmd: CompleteOrder,
     ) -> Result<CommandResult<Order>, DomainError> {
-        let tracking_number = cmd.tracking_number.clone();
+        let command = cmd.name();
+        let CompleteOrder {
+            order_id,
+            tracking_number,
+            shipped_items,
+            metadata,
+        } = cmd;
 
         self.handler
-            .execute(cmd.order_id, |order| order.complete(tracking_number))
+            .execute_named_with_metadata(command, order_id, metadata, |order| {
+                order.complete(tracking_number, shipped_items)
+            })
+            .await
+    }
+
+    /// Records that the carrier picked up an order.
+    #[tracing::instrument(skip(self))]
+    pub async fn mark_shipped(
+        &self,
+        cmd: MarkShipped,
+    ) -> Result<CommandResult<Order>, DomainError> {
+        let command = cmd.name();
+        let MarkShipped {
+            order_id,
+            carrier,
+            tracking_number,
+            shipped_at,
+            metadata,
+        } = cmd;
+
+        self.handler
+            .execute_named_with_metadata(command, order_id, metadata, |order| {
+                order.mark_shipped(carrier, tracking_number, shipped_at)
+            })
+            .await
+    }
+
+    /// Records a carrier transit update for an order.
+    #[tracing::instrument(skip(self))]
+    pub async fn mark_in_transit(
+        &self,
+        cmd: MarkInTransit,
+    ) -> Result<CommandResult<Order>, DomainError> {
+        let command = cmd.name();
+        let MarkInTransit {
+            order_id,
+            location,
+            updated_at,
+            metadata,
+        } = cmd;
+
+        self.handler
+            .execute_named_with_metadata(command, order_id, metadata, |order| {
+                order.mark_in_transit(location, updated_at)
+            })
+            .await
+    }
+
+    /// Records that the carrier delivered an order.
+    #[tracing::instrument(skip(self))]
+    pub async fn mark_delivered(
+        &self,
+        cmd: MarkDelivered,
+    ) -> Result<CommandResult<Order>, DomainError> {
+        let command = cmd.name();
+        let MarkDelivered {
+            order_id,
+            received_by,
+            delivered_at,
+            metadata,
+        } = cmd;
+
+        self.handler
+            .execute_named_with_metadata(command, order_id, metadata, |order| {
+                order.mark_delivered(received_by, delivered_at)
+            })
+            .await
+    }
+
+    /// Records a failed delivery attempt for an order.
+    #[tracing::instrument(skip(self))]
+    pub async fn record_delivery_failure(
+        &self,
+        cmd: RecordDeliveryFailure,
+    ) -> Result<CommandResult<Order>, DomainError> {
+        let command = cmd.name();
+        let RecordDeliveryFailure {
+            order_id,
+            reason,
+            failed_at,
+            metadata,
+        } = cmd;
+        let reason = self.sanitize(TextField::DeliveryFailureReason, reason)?;
+
+        self.handler
+            .execute_named_with_metadata(command, order_id, metadata, |order| {
+                order.record_delivery_failure(reason, failed_at)
+            })
             .await
     }
 
@@ -144,11 +602,98 @@ impl<S: EventStore> OrderService<S> {
         &self,
         cmd: CancelOrder,
     ) -> Result<CommandResult<Order>, DomainError> {
-        let reason = cmd.reason.clone();
-        let cancelled_by = cmd.cancelled_by.clone();
+        let command = cmd.name();
+        let CancelOrder {
+            order_id,
+            reason,
+            cancelled_by,
+            reason_code,
+            metadata,
+        } = cmd;
+        let reason = self.sanitize(TextField::CancellationReason, reason)?;
 
         self.handler
-            .execute(cmd.order_id, |order| order.cancel(reason, cancelled_by))
+            .execute_named_with_metadata(command, order_id, metadata, |order| {
+                order.cancel(reason, cancelled_by, reason_code)
+            })
+            .await
+    }
+
+    /// Attaches a tag to an order.
+    #[tracing::instrument(skip(self))]
+    pub async fn add_tag(&self, cmd: AddTag) -> Result<CommandResult<Order>, DomainError> {
+        let command = cmd.name();
+        let AddTag { order_id, tag } = cmd;
+
+        self.handler
+            .execute_named(command, order_id, |order| order.add_tag(&tag))
+            .await
+    }
+
+    /// Removes a tag from an order.
+    #[tracing::instrument(skip(self))]
+    pub async fn remove_tag(&self, cmd: RemoveTag) -> Result<CommandResult<Order>, DomainError> {
+        let command = cmd.name();
+        let RemoveTag { order_id, tag } = cmd;
+
+        self.handler
+            .execute_named(command, order_id, |order| order.remove_tag(&tag))
+            .await
+    }
+
+    /// Sets or clears a metadata attachment on a draft order.
+    ///
+    /// Values are free text, so they pass through the middleware chain as
+    /// [`TextField::OrderMetadata`].
+    #[tracing::instrument(skip(self))]
+    pub async fn set_metadata(
+        &self,
+        cmd: SetOrderMetadata,
+    ) -> Result<CommandResult<Order>, DomainError> {
+        let command = cmd.name();
+        let SetOrderMetadata {
+            order_id,
+            key,
+            value,
+        } = cmd;
+        let value = value
+            .map(|v| self.sanitize(TextField::OrderMetadata, v))
+            .transpose()?;
+
+        self.handler
+            .execute_named(command, order_id, |order| order.set_metadata(&key, value))
+            .await
+    }
+
+    /// Gives an order its shipping address.
+    #[tracing::instrument(skip(self))]
+    pub async fn set_shipping_address(
+        &self,
+        cmd: SetShippingAddress,
+    ) -> Result<CommandResult<Order>, DomainError> {
+        let command = cmd.name();
+        let SetShippingAddress { order_id, address } = cmd;
+
+        self.handler
+            .execute_named(command, order_id, |order| {
+                order.set_shipping_address(address)
+            })
+            .await
+    }
+
+    /// Changes an order's shipping address.
+    #[tracing::instrument(skip(self))]
+    pub async fn update_shipping_address(
+        &self,
+        cmd: UpdateShippingAddress,
+    ) -> Result<CommandResult<Order>, DomainError> {
+        let command = cmd.name();
+        let UpdateShippingAddress { order_id, address } = cmd;
+
+        self.handler
+            .execute_named(command, order_id, |order| {
+                order.update_shipping_address(address)
+            })
             .await
     }
 
@@ -160,41 +705,56 @@ impl<S: EventStore> OrderService<S> {
         self.handler.load_existing(order_id).await
     }
 
+    /// Replays an order at two versions and returns what changed between them.
+    ///
+    /// Returns None if the order doesn't exist. A version beyond the order's
+    /// latest is clamped to it; check [`OrderDiff::to_version`].
+    #[tracing::instrument(skip(self))]
+    pub async fn diff_versions(
+        &self,
+        order_id: AggregateId,
+        from: Version,
+        to: Version,
+    ) -> Result<Option<OrderDiff>, DomainError> {
+        let after = self.handler.load_at_version(order_id, to).await?;
+        if after.id().is_none() {
+            return Ok(None);
+        }
+        let before = self.handler.load_at_version(order_id, from).await?;
+        Ok(Some(OrderDiff::between(&before, &after)))
+    }
+
+    /// Replays an order and returns its state after each event in
+    /// `from..=to`, oldest first.
+    ///
+    /// The result is empty when the order doesn't exist or has no events in
+    /// the range.
+    #[tracing::instrument(skip(self))]
+    pub async fn replay_range(
+        &self,
+        order_id: AggregateId,
+        from: Version,
+        to: Version,
+    ) -> Result<Vec<(Version, Order)>, DomainError> {
+        self.handler.replay_range(order_id, from, to).await
+    }
+
     // Convenience methods
 
     /// Creates an order and adds items in a single operation.
     ///
-    /// This is a convenience method that creates the order and adds all items
-    /// in sequence.
+    /// The order's creation and all its items are appended together, so
+    /// either the whole order is stored or, if any item is refused, none of
+    /// it is. Item additions are not checked for duplicates, as the order
+    /// has no earlier items to repeat, but they record their payload hash so
+    /// a later [`add_item`](Self::add_item) repeating one is.
     pub async fn create_order_with_items(
         &self,
         customer_id: CustomerId,
         items: Vec<OrderItem>,
     ) -> Result<CommandResult<Order>, DomainError> {
-        let order_id = AggregateId::new();
-
-        // Create order
-        self.create_order(CreateOrder::new(order_id, customer_id))
-            .await?;
-
-        // Add items
-        let mut result = None;
-        for item in items {
-            result = Some(self.add_item(AddItem::new(order_id, item)).await?);
-        }
-
-        // Return the final state, or load if no items were added
-        match result {
-            Some(r) => Ok(r),
-            None => {
-                let order = self.handler.load(order_id).await?;
-                Ok(CommandResult {
-                    aggregate: order,
-                    events: vec![],
-                    new_version: event_store::Version::first(),
-                })
-            }
-        }
+        self.create_order(CreateOrder::for_customer(customer_id).with_items(items))
+            .await
     }
 
     /// Adds an item using individual fields.
@@ -211,12 +771,169 @@ impl<S: EventStore> OrderService<S> {
     }
 }
 
+#[async_trait]
+impl<S: EventStore> OrderCommands for OrderService<S> {
+    async fn create_order(&self, cmd: CreateOrder) -> Result<CommandResult<Order>, DomainError> {
+        OrderService::create_order(self, cmd).await
+    }
+
+    async fn add_item(&self, cmd: AddItem) -> Result<CommandResult<Order>, DomainError> {
+        OrderService::add_item(self, cmd).await
+    }
+
+    async fn remove_item(&self, cmd: RemoveItem) -> Result<CommandResult<Order>, DomainError> {
+        OrderService::remove_item(self, cmd).await
+    }
+
+    async fn update_item_quantity(
+        &self,
+        cmd: UpdateItemQuantity,
+    ) -> Result<CommandResult<Order>, DomainError> {
+        OrderService::update_item_quantity(self, cmd).await
+    }
+
+    async fn update_item_price(
+        &self,
+        cmd: UpdateItemPrice,
+    ) -> Result<CommandResult<Order>, DomainError> {
+        OrderService::update_item_price(self, cmd).await
+    }
+
+    async fn substitute_item(
+        &self,
+        cmd: SubstituteItem,
+    ) -> Result<CommandResult<Order>, DomainError> {
+        OrderService::substitute_item(self, cmd).await
+    }
+
+    async fn submit_order(&self, cmd: SubmitOrder) -> Result<CommandResult<Order>, DomainError> {
+        OrderService::submit_order(self, cmd).await
+    }
+
+    async fn mark_reserved(&self, cmd: MarkReserved) -> Result<CommandResult<Order>, DomainError> {
+        OrderService::mark_reserved(self, cmd).await
+    }
+
+    async fn renew_reservations(
+        &self,
+        cmd: RenewReservations,
+    ) -> Result<CommandResult<Order>, DomainError> {
+        OrderService::renew_reservations(self, cmd).await
+    }
+
+    async fn start_processing(
+        &self,
+        cmd: StartProcessing,
+    ) -> Result<CommandResult<Order>, DomainError> {
+        OrderService::start_processing(self, cmd).await
+    }
+
+    async fn complete_order(
+        &self,
+        cmd: CompleteOrder,
+    ) -> Result<CommandResult<Order>, DomainError> {
+        OrderService::complete_order(self, cmd).await
+    }
+
+    async fn mark_shipped(&self, cmd: MarkShipped) -> Result<CommandResult<Order>, DomainError> {
+        OrderService::mark_shipped(self, cmd).await
+    }
+
+    async fn mark_in_transit(
+        &self,
+        cmd: MarkInTransit,
+    ) -> Result<CommandResult<Order>, DomainError> {
+        OrderService::mark_in_transit(self, cmd).await
+    }
+
+    async fn mark_delivered(
+        &self,
+        cmd: MarkDelivered,
+    ) -> Result<CommandResult<Order>, DomainError> {
+        OrderService::mark_delivered(self, cmd).await
+    }
+
+    async fn record_delivery_failure(
+        &self,
+        cmd: RecordDeliveryFailure,
+    ) -> Result<CommandResult<Order>, DomainError> {
+        OrderService::record_delivery_failure(self, cmd).await
+    }
+
+    async fn cancel_order(&self, cmd: CancelOrder) -> Result<CommandResult<Order>, DomainError> {
+        OrderService::cancel_order(self, cmd).await
+    }
+
+    async fn add_tag(&self, cmd: AddTag) -> Result<CommandResult<Order>, DomainError> {
+        OrderService::add_tag(self, cmd).await
+    }
+
+    async fn remove_tag(&self, cmd: RemoveTag) -> Result<CommandResult<Order>, DomainError> {
+        OrderService::remove_tag(self, cmd).await
+    }
+
+    async fn set_metadata(
+        &self,
+        cmd: SetOrderMetadata,
+    ) -> Result<CommandResult<Order>, DomainError> {
+        OrderService::set_metadata(self, cmd).await
+    }
+
+    async fn set_shipping_address(
+        &self,
+        cmd: SetShippingAddress,
+    ) -> Result<CommandResult<Order>, DomainError> {
+        OrderService::set_shipping_address(self, cmd).await
+    }
+
+    async fn update_shipping_address(
+        &self,
+        cmd: UpdateShippingAddress,
+    ) -> Result<CommandResult<Order>, DomainError> {
+        OrderService::update_shipping_address(self, cmd).await
+    }
+}
+
+#[async_trait]
+impl<S: EventStore> OrderQueries for OrderService<S> {
+    async fn get_order(&self, order_id: AggregateId) -> Result<Option<Order>, DomainError> {
+        OrderService::get_order(self, order_id).await
+    }
+
+    async fn diff_versions(
+        &self,
+        order_id: AggregateId,
+        from: Version,
+        to: Version,
+    ) -> Result<Option<OrderDiff>, DomainError> {
+        OrderService::diff_versions(self, order_id, from, to).await
+    }
+
+    async fn replay_range(
+        &self,
+        order_id: AggregateId,
+        from: Version,
+        to: Version,
+    ) -> Result<Vec<(Version, Order)>, DomainError> {
+        OrderService::replay_range(self, order_id, from, to).await
+    }
+}
+
+/// Metadata recording an item's payload `hash` for duplicate detection.
+fn payload_metadata(hash: String) -> CommandMetadata {
+    CommandMetadata::from([(
+        PAYLOAD_HASH_KEY.to_string(),
+        serde_json::Value::String(hash),
+    )])
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::aggregate::Aggregate;
     use crate::order::OrderState;
     use event_store::InMemoryEventStore;
+    use std::collections::BTreeMap;
 
     #[tokio::test]
     async fn test_create_order() {
@@ -280,7 +997,12 @@ mod tests {
 
         // Reserve
         service
-            .mark_reserved(MarkReserved::new(order_id, Some("RES-123".to_string())))
+            .mark_reserved(
+                MarkReserved::new(order_id).with_reservations(BTreeMap::from([(
+                    ProductId::new("SKU-001"),
+                    "RES-123".to_string(),
+                )])),
+            )
             .await
             .unwrap();
 
@@ -362,6 +1084,28 @@ mod tests {
 
         assert_eq!(result.aggregate.item_count(), 2);
         assert_eq!(result.aggregate.total_amount().cents(), 2500);
+        assert_eq!(result.events.len(), 3);
+        assert_eq!(result.new_version, Version::new(3));
+    }
+
+    #[tokio::test]
+    async fn test_create_order_with_refused_item_stores_nothing() {
+        let store = InMemoryEventStore::new();
+        let service = OrderService::new(store.clone()).with_limits(OrderLimits {
+            max_items: Some(1),
+            ..OrderLimits::unlimited()
+        });
+
+        let items = vec![
+            OrderItem::new("SKU-001", "Widget", 2, Money::from_cents(1000)),
+            OrderItem::new("SKU-002", "Gadget", 1, Money::from_cents(500)),
+        ];
+        let result = service
+            .create_order_with_items(CustomerId::new(), items)
+            .await;
+
+        assert!(matches!(result, Err(DomainError::Order(_))));
+        assert_eq!(store.event_count().await, 0);
     }
 
     #[tokio::test]
@@ -419,4 +1163,234 @@ mod tests {
         assert_eq!(result.aggregate.item_count(), 0);
         assert_eq!(result.aggregate.total_amount().cents(), 0);
     }
+
+    #[tokio::test]
+    async fn test_middleware_sanitizes_text_fields() {
+        let store = InMemoryEventStore::new();
+        let service = OrderService::new(store)
+            .with_middleware(crate::order::WhitespaceNormalizer)
+            .with_middleware(crate::order::PiiMasker::new())
+            .with_middleware(crate::order::DenyListFilter::new(["banned"]));
+
+        let customer_id = CustomerId::new();
+        let cmd = CreateOrder::for_customer(customer_id);
+        let order_id = cmd.order_id;
+        service.create_order(cmd).await.unwrap();
+
+        let result = service
+            .add_item_to_order(
+                order_id,
+                "SKU-001",
+                "  Blue   Widget ",
+                1,
+                Money::from_cents(1000),
+            )
+            .await
+            .unwrap();
+        let item = result
+            .aggregate
+            .get_item(&ProductId::new("SKU-001"))
+            .unwrap();
+        assert_eq!(item.product_name, "Blue Widget");
+
+        // Rejected content never reaches the store
+        let result = service
+            .add_item_to_order(
+                order_id,
+                "SKU-002",
+                "Banned Gadget",
+                1,
+                Money::from_cents(500),
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(DomainError::Order(OrderError::DisallowedContent { .. }))
+        ));
+
+        let result = service
+            .set_metadata(SetOrderMetadata::new(
+                order_id,
+                "delivery_instructions",
+                "Leave   with bob@example.org",
+            ))
+            .await
+            .unwrap();
+        assert_eq!(
+            result.aggregate.metadata_value("delivery_instructions"),
+            Some("Leave with [redacted]")
+        );
+
+        let result = service
+            .cancel_order(CancelOrder::new(order_id, "email me: a@b.com", None))
+            .await
+            .unwrap();
+        match &result.events[0] {
+            crate::order::OrderEvent::OrderCancelled(data) => {
+                assert_eq!(data.reason, "email me: [redacted]");
+            }
+            other => panic!("unexpected event: {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_decisions_name_the_command() {
+        let log = DecisionLog::with_rejection_buffer(10);
+        let service = OrderService::new(InMemoryEventStore::new()).with_decision_log(log.clone());
+
+        let customer_id = CustomerId::new();
+        let cmd = CreateOrder::for_customer(customer_id);
+        let order_id = cmd.order_id;
+        service.create_order(cmd).await.unwrap();
+
+        let result = service.submit_order(SubmitOrder::new(order_id)).await;
+        assert!(result.is_err());
+
+        let rejections = log.recent_rejections(10);
+        assert_eq!(rejections.len(), 1);
+        assert_eq!(rejections[0].command, Some("SubmitOrder"));
+        assert_eq!(rejections[0].aggregate_id, order_id);
+        assert_eq!(rejections[0].invariant, Some("order.no_items"));
+    }
+
+    #[tokio::test]
+    async fn test_draft_throttle_limits_orders_per_customer() {
+        let log = DecisionLog::with_rejection_buffer(10);
+        let service = OrderService::new(InMemoryEventStore::new())
+            .with_draft_throttle(DraftThrottle::per_minute(2))
+            .with_decision_log(log.clone());
+        let customer_id = CustomerId::new();
+
+        let first = CreateOrder::for_customer(customer_id);
+        let first_id = first.order_id;
+        service.create_order(first).await.unwrap();
+        // A create that fails gives its slot back
+        let mut again = CreateOrder::for_customer(customer_id);
+        again.order_id = first_id;
+        assert!(service.create_order(again).await.is_err());
+        service
+            .create_order(CreateOrder::for_customer(customer_id))
+            .await
+            .unwrap();
+
+        let result = service
+            .create_order(CreateOrder::for_customer(customer_id))
+            .await;
+        assert!(matches!(
+            result,
+            Err(DomainError::Order(OrderError::DraftLimitExceeded {
+                limit: 2,
+                window_secs: 60,
+                ..
+            }))
+        ));
+        assert_eq!(
+            log.recent_rejections(1)[0].invariant,
+            Some("order.draft_limit_exceeded")
+        );
+
+        // Other customers are unaffected
+        service
+            .create_order(CreateOrder::for_customer(CustomerId::new()))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_dedupe_window_refuses_repeated_item() {
+        let service = OrderService::new(InMemoryEventStore::new())
+            .with_middleware(crate::order::WhitespaceNormalizer)
+            .with_dedupe_window(Duration::from_secs(30));
+
+        let customer_id = CustomerId::new();
+        let cmd = CreateOrder::for_customer(customer_id);
+        let order_id = cmd.order_id;
+        service.create_order(cmd).await.unwrap();
+
+        service
+            .add_item_to_order(order_id, "SKU-001", "Widget", 2, Money::from_cents(1000))
+            .await
+            .unwrap();
+        let events = service
+            .handler()
+            .store()
+            .get_events_for_aggregate(order_id)
+            .await
+            .unwrap();
+        assert!(events[1].metadata.contains_key(PAYLOAD_HASH_KEY));
+
+        // A double click, with stray whitespace normalized away
+        let repeat = service
+            .add_item_to_order(order_id, "SKU-001", " Widget ", 2, Money::from_cents(1000))
+            .await;
+        assert!(matches!(
+            repeat,
+            Err(DomainError::Order(OrderError::DuplicateCommand {
+                command: "AddItem",
+                window_secs: 30,
+            }))
+        ));
+
+        // A different quantity is a different command
+        let more = service
+            .add_item_to_order(order_id, "SKU-001", "Widget", 1, Money::from_cents(1000))
+            .await
+            .unwrap();
+        assert_eq!(more.aggregate.total_amount().cents(), 3000);
+    }
+
+    #[tokio::test]
+    async fn test_dedupe_window_refuses_repeat_of_batched_item() {
+        let service = OrderService::new(InMemoryEventStore::new())
+            .with_dedupe_window(Duration::from_secs(30));
+
+        let items = vec![
+            OrderItem::new("SKU-001", "Widget", 2, Money::from_cents(1000)),
+            OrderItem::new("SKU-002", "Gadget", 1, Money::from_cents(500)),
+        ];
+        let created = service
+            .create_order_with_items(CustomerId::new(), items)
+            .await
+            .unwrap();
+        let order_id = created.aggregate.id().unwrap();
+        let events = service
+            .handler()
+            .store()
+            .get_events_for_aggregate(order_id)
+            .await
+            .unwrap();
+        assert!(!events[0].metadata.contains_key(PAYLOAD_HASH_KEY));
+        assert_ne!(
+            events[1].metadata.get(PAYLOAD_HASH_KEY),
+            events[2].metadata.get(PAYLOAD_HASH_KEY)
+        );
+
+        let repeat = service
+            .add_item_to_order(order_id, "SKU-002", "Gadget", 1, Money::from_cents(500))
+            .await;
+        assert!(matches!(
+            repeat,
+            Err(DomainError::Order(OrderError::DuplicateCommand { .. }))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_repeated_item_allowed_without_dedupe_window() {
+        let service = OrderService::new(InMemoryEventStore::new());
+
+        let customer_id = CustomerId::new();
+        let cmd = CreateOrder::for_customer(customer_id);
+        let order_id = cmd.order_id;
+        service.create_order(cmd).await.unwrap();
+
+        for _ in 0..2 {
+            service
+                .add_item_to_order(order_id, "SKU-001", "Widget", 2, Money::from_cents(1000))
+                .await
+                .unwrap();
+        }
+
+        let order = service.get_order(order_id).await.unwrap().unwrap();
+        assert_eq!(order.total_amount().cents(), 4000);
+    }
 }
@@ -6,10 +6,14 @@ use serde::{Deserialize, Serialize};
 ///
 /// State transitions:
 /// ```text
-/// Draft ──────┬──► Reserved ──► Processing ──► Completed
-///             │        │            │
-///             └────────┴────────────┴──► Cancelled
+/// Draft ──────┬──► Reserved ──► Processing ──► Completed ──► Shipped ──► InTransit ──► Delivered
+///             │        │            │                          ▲     │         │
+///             └────────┴────────────┴──► Cancelled             │     ▼         ▼
+///                                                              └─ DeliveryFailed ◄┘
 /// ```
+///
+/// `Completed` means payment was captured and the order handed off for
+/// fulfillment; the carrier-driven states track physical delivery.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
 pub enum OrderState {
     /// Order is being created, items can be added/removed.
@@ -22,9 +26,21 @@ pub enum OrderState {
     /// Payment confirmed, order is being fulfilled.
     Processing,
 
-    /// Order has been completed/shipped (terminal state).
+    /// Order has been completed and handed to the carrier.
     Completed,
 
+    /// Carrier has picked up the shipment.
+    Shipped,
+
+    /// Shipment is moving through the carrier network.
+    InTransit,
+
+    /// Carrier confirmed delivery (terminal state).
+    Delivered,
+
+    /// Carrier failed to deliver; the order may be shipped again.
+    DeliveryFailed,
+
     /// Order was cancelled (terminal state).
     Cancelled,
 }
@@ -35,6 +51,21 @@ impl OrderState {
         matches!(self, OrderState::Draft)
     }
 
+    /// Returns true if an item can be swapped for a substitute in this
+    /// state: once inventory is reserved and until the order ships.
+    pub fn can_substitute_items(&self) -> bool {
+        matches!(self, OrderState::Reserved | OrderState::Processing)
+    }
+
+    /// Returns true if the shipping address can be set or changed in this
+    /// state: until the order is handed to shipping.
+    pub fn can_change_shipping_address(&self) -> bool {
+        matches!(
+            self,
+            OrderState::Draft | OrderState::Reserved | OrderState::Processing
+        )
+    }
+
     /// Returns true if the order can be submitted in this state.
     pub fn can_submit(&self) -> bool {
         matches!(self, OrderState::Draft)
@@ -45,6 +76,12 @@ impl OrderState {
         matches!(self, OrderState::Draft)
     }
 
+    /// Returns true if a line item's lapsed reservation can be replaced in
+    /// this state: while the order waits on its reservations for payment.
+    pub fn can_renew_reservations(&self) -> bool {
+        matches!(self, OrderState::Reserved)
+    }
+
     /// Returns true if processing can start in this state.
     pub fn can_start_processing(&self) -> bool {
         matches!(self, OrderState::Reserved)
@@ -55,6 +92,21 @@ impl OrderState {
         matches!(self, OrderState::Processing)
     }
 
+    /// Returns true if the order can be marked shipped in this state.
+    pub fn can_ship(&self) -> bool {
+        matches!(self, OrderState::Completed | OrderState::DeliveryFailed)
+    }
+
+    /// Returns true if a transit update can be recorded in this state.
+    pub fn can_mark_in_transit(&self) -> bool {
+        matches!(self, OrderState::Shipped | OrderState::InTransit)
+    }
+
+    /// Returns true if delivery can be confirmed or failed in this state.
+    pub fn can_record_delivery(&self) -> bool {
+        matches!(self, OrderState::Shipped | OrderState::InTransit)
+    }
+
     /// Returns true if the order can be cancelled in this state.
     pub fn can_cancel(&self) -> bool {
         matches!(
@@ -65,7 +117,7 @@ impl OrderState {
 
     /// Returns true if this is a terminal state (no further transitions possible).
     pub fn is_terminal(&self) -> bool {
-        matches!(self, OrderState::Completed | OrderState::Cancelled)
+        matches!(self, OrderState::Delivered | OrderState::Cancelled)
     }
 
     /// Returns the state name as a string.
@@ -75,6 +127,10 @@ impl OrderState {
             OrderState::Reserved => "Reserved",
             OrderState::Processing => "Processing",
             OrderState::Completed => "Completed",
+            OrderState::Shipped => "Shipped",
+            OrderState::InTransit => "InTransit",
+            OrderState::Delivered => "Delivered",
+            OrderState::DeliveryFailed => "DeliveryFailed",
             OrderState::Cancelled => "Cancelled",
         }
     }
@@ -104,6 +160,25 @@ mod tests {
         assert!(!OrderState::Cancelled.can_modify_items());
     }
 
+    #[test]
+    fn test_reserved_and_processing_can_substitute_items() {
+        assert!(!OrderState::Draft.can_substitute_items());
+        assert!(OrderState::Reserved.can_substitute_items());
+        assert!(OrderState::Processing.can_substitute_items());
+        assert!(!OrderState::Completed.can_substitute_items());
+        assert!(!OrderState::Cancelled.can_substitute_items());
+    }
+
+    #[test]
+    fn test_shipping_address_changeable_until_completed() {
+        assert!(OrderState::Draft.can_change_shipping_address());
+        assert!(OrderState::Reserved.can_change_shipping_address());
+        assert!(OrderState::Processing.can_change_shipping_address());
+        assert!(!OrderState::Completed.can_change_shipping_address());
+        assert!(!OrderState::Shipped.can_change_shipping_address());
+        assert!(!OrderState::Cancelled.can_change_shipping_address());
+    }
+
     #[test]
     fn test_draft_can_submit() {
         assert!(OrderState::Draft.can_submit());
@@ -122,6 +197,14 @@ mod tests {
         assert!(!OrderState::Cancelled.can_start_processing());
     }
 
+    #[test]
+    fn test_only_reserved_can_renew_reservations() {
+        assert!(OrderState::Reserved.can_renew_reservations());
+        assert!(!OrderState::Draft.can_renew_reservations());
+        assert!(!OrderState::Processing.can_renew_reservations());
+        assert!(!OrderState::Cancelled.can_renew_reservations());
+    }
+
     #[test]
     fn test_processing_can_complete() {
         assert!(!OrderState::Draft.can_complete());
@@ -145,16 +228,46 @@ mod tests {
         assert!(!OrderState::Draft.is_terminal());
         assert!(!OrderState::Reserved.is_terminal());
         assert!(!OrderState::Processing.is_terminal());
-        assert!(OrderState::Completed.is_terminal());
+        assert!(!OrderState::Completed.is_terminal());
+        assert!(!OrderState::Shipped.is_terminal());
+        assert!(!OrderState::InTransit.is_terminal());
+        assert!(!OrderState::DeliveryFailed.is_terminal());
+        assert!(OrderState::Delivered.is_terminal());
         assert!(OrderState::Cancelled.is_terminal());
     }
 
+    #[test]
+    fn test_shipping_transitions() {
+        assert!(!OrderState::Processing.can_ship());
+        assert!(OrderState::Completed.can_ship());
+        assert!(OrderState::DeliveryFailed.can_ship());
+        assert!(!OrderState::Shipped.can_ship());
+        assert!(!OrderState::Delivered.can_ship());
+
+        assert!(OrderState::Shipped.can_record_delivery());
+        assert!(OrderState::InTransit.can_record_delivery());
+        assert!(!OrderState::Completed.can_record_delivery());
+        assert!(!OrderState::Delivered.can_record_delivery());
+
+        assert!(OrderState::Shipped.can_mark_in_transit());
+        assert!(OrderState::InTransit.can_mark_in_transit());
+        assert!(!OrderState::Completed.can_mark_in_transit());
+        assert!(!OrderState::DeliveryFailed.can_mark_in_transit());
+
+        assert!(!OrderState::Shipped.can_cancel());
+        assert!(!OrderState::InTransit.can_cancel());
+    }
+
     #[test]
     fn test_display() {
         assert_eq!(OrderState::Draft.to_string(), "Draft");
         assert_eq!(OrderState::Reserved.to_string(), "Reserved");
         assert_eq!(OrderState::Processing.to_string(), "Processing");
         assert_eq!(OrderState::Completed.to_string(), "Completed");
+        assert_eq!(OrderState::Shipped.to_string(), "Shipped");
+        assert_eq!(OrderState::InTransit.to_string(), "InTransit");
+        assert_eq!(OrderState::Delivered.to_string(), "Delivered");
+        assert_eq!(OrderState::DeliveryFailed.to_string(), "DeliveryFailed");
         assert_eq!(OrderState::Cancelled.to_string(), "Cancelled");
     }
 
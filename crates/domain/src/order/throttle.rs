@@ -0,0 +1,171 @@
+//! Per-customer limit on draft order creation.
+//!
+//! Each customer may create a fixed number of orders within a sliding
+//! window. Creation times are counted in memory, so the limit applies per
+//! process and resets on restart. The limit can be changed or lifted while
+//! running.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use common::Tunable;
+
+use super::{CustomerId, OrderError};
+
+/// Sliding-window limit on how many orders each customer can create.
+///
+/// Clones share the counts and the limit.
+#[derive(Debug, Clone)]
+pub struct DraftThrottle {
+    limit: Tunable<Option<u32>>,
+    window: Duration,
+    created: Arc<Mutex<HashMap<CustomerId, VecDeque<DateTime<Utc>>>>>,
+}
+
+impl DraftThrottle {
+    /// Allows each customer `limit` orders in any `window`.
+    pub fn new(limit: u32, window: Duration) -> Self {
+        let throttle = Self::unlimited(window);
+        throttle.set_limit(Some(limit));
+        throttle
+    }
+
+    /// Allows each customer `limit` orders in any minute.
+    pub fn per_minute(limit: u32) -> Self {
+        Self::new(limit, Duration::from_secs(60))
+    }
+
+    /// Counts nothing until a limit is set with
+    /// [`set_limit`](Self::set_limit).
+    pub fn unlimited(window: Duration) -> Self {
+        Self {
+            limit: Tunable::new(None),
+            window,
+            created: Arc::default(),
+        }
+    }
+
+    /// Returns the current limit, or None if creation is unlimited.
+    pub fn limit(&self) -> Option<u32> {
+        self.limit.value()
+    }
+
+    /// Changes the limit for every clone; None lifts it. Creations already
+    /// counted still count against a new limit.
+    pub fn set_limit(&self, limit: Option<u32>) {
+        self.limit.set(limit.map(|limit| limit.max(1)));
+    }
+
+    /// Takes one of the customer's slots in the window ending at `now`.
+    ///
+    /// Returns the slot to hand back with [`release`](Self::release) if the
+    /// order isn't created after all.
+    pub fn acquire(
+        &self,
+        customer_id: CustomerId,
+        now: DateTime<Utc>,
+    ) -> Result<DateTime<Utc>, OrderError> {
+        let Some(limit) = self.limit() else {
+            return Ok(now);
+        };
+        let cutoff = now - self.window;
+        let mut created = self.created.lock().expect("draft throttle poisoned");
+        // Forget customers with nothing left in the window, so the map
+        // doesn't grow with every customer ever seen
+        created.retain(|_, times| {
+            while times.front().is_some_and(|t| *t <= cutoff) {
+                times.pop_front();
+            }
+            !times.is_empty()
+        });
+
+        let times = created.entry(customer_id).or_default();
+        if times.len() >= limit as usize {
+            let oldest = times.front().copied().unwrap_or(now);
+            let retry_after = (oldest - cutoff).num_seconds().max(1) as u64;
+            metrics::counter!("draft_orders_throttled").increment(1);
+            return Err(OrderError::DraftLimitExceeded {
+                customer_id,
+                limit,
+                window_secs: self.window.as_secs(),
+                retry_after_secs: retry_after,
+            });
+        }
+        times.push_back(now);
+        Ok(now)
+    }
+
+    /// Gives back a slot taken by [`acquire`](Self::acquire).
+    pub fn release(&self, customer_id: CustomerId, slot: DateTime<Utc>) {
+        let mut created = self.created.lock().expect("draft throttle poisoned");
+        if let Some(times) = created.get_mut(&customer_id)
+            && let Some(i) = times.iter().position(|t| *t == slot)
+        {
+            times.remove(i);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limit_applies_per_customer_over_a_sliding_window() {
+        let throttle = DraftThrottle::per_minute(2);
+        let alice = CustomerId::new();
+        let bob = CustomerId::new();
+        let start = Utc::now();
+        let at = |secs: i64| start + chrono::Duration::seconds(secs);
+
+        throttle.acquire(alice, at(0)).unwrap();
+        throttle.acquire(alice, at(20)).unwrap();
+        match throttle.acquire(alice, at(30)) {
+            Err(OrderError::DraftLimitExceeded {
+                limit,
+                retry_after_secs,
+                ..
+            }) => {
+                assert_eq!(limit, 2);
+                assert_eq!(retry_after_secs, 30);
+            }
+            other => panic!("expected DraftLimitExceeded, got {other:?}"),
+        }
+        throttle.acquire(bob, at(30)).unwrap();
+
+        // The first creation has left the window
+        throttle.acquire(alice, at(61)).unwrap();
+    }
+
+    #[test]
+    fn test_released_slots_can_be_reused() {
+        let throttle = DraftThrottle::per_minute(1);
+        let customer = CustomerId::new();
+        let now = Utc::now();
+
+        let slot = throttle.acquire(customer, now).unwrap();
+        assert!(throttle.acquire(customer, now).is_err());
+        throttle.release(customer, slot);
+        throttle.acquire(customer, now).unwrap();
+    }
+
+    #[test]
+    fn test_limit_changes_apply_to_every_clone() {
+        let throttle = DraftThrottle::unlimited(Duration::from_secs(60));
+        let shared = throttle.clone();
+        let customer = CustomerId::new();
+        let now = Utc::now();
+
+        throttle.acquire(customer, now).unwrap();
+        throttle.acquire(customer, now).unwrap();
+
+        shared.set_limit(Some(1));
+        throttle.acquire(customer, now).unwrap();
+        assert!(throttle.acquire(customer, now).is_err());
+
+        shared.set_limit(None);
+        throttle.acquire(customer, now).unwrap();
+    }
+}
@@ -3,6 +3,8 @@
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use super::OrderError;
+
 /// Unique identifier for a customer.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(transparent)]
@@ -50,7 +52,7 @@ impl From<CustomerId> for Uuid {
 }
 
 /// Product identifier (SKU).
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 #[serde(transparent)]
 pub struct ProductId(String);
 
@@ -90,31 +92,116 @@ impl AsRef<str> for ProductId {
     }
 }
 
-/// Money amount represented in cents to avoid floating point issues.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+/// ISO 4217 currency code (e.g. `USD`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Currency([u8; 3]);
+
+impl Currency {
+    /// US dollar.
+    pub const USD: Currency = Currency(*b"USD");
+
+    /// Euro.
+    pub const EUR: Currency = Currency(*b"EUR");
+
+    /// Pound sterling.
+    pub const GBP: Currency = Currency(*b"GBP");
+
+    /// Returns the currency code as a string slice.
+    pub fn as_str(&self) -> &str {
+        // Only constructed from ASCII letters
+        std::str::from_utf8(&self.0).unwrap_or("???")
+    }
+}
+
+impl Default for Currency {
+    fn default() -> Self {
+        Self::USD
+    }
+}
+
+impl std::str::FromStr for Currency {
+    type Err = OrderError;
+
+    /// Parses a three-letter currency code, case-insensitively.
+    fn from_str(code: &str) -> Result<Self, Self::Err> {
+        let bytes = code.as_bytes();
+        if bytes.len() != 3 || !bytes.iter().all(u8::is_ascii_alphabetic) {
+            return Err(OrderError::InvalidCurrency {
+                code: code.to_string(),
+            });
+        }
+        Ok(Self([
+            bytes[0].to_ascii_uppercase(),
+            bytes[1].to_ascii_uppercase(),
+            bytes[2].to_ascii_uppercase(),
+        ]))
+    }
+}
+
+impl std::fmt::Display for Currency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl Serialize for Currency {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Currency {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = String::deserialize(deserializer)?;
+        code.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Money amount represented in cents to avoid floating point issues, in a
+/// single currency.
+///
+/// "Cents" are the currency's minor units. Only amounts in the same currency
+/// can be added or subtracted, except that a zero amount takes the other
+/// side's currency, so sums can start from [`Money::zero`].
+/// [`Money::checked_add`] and [`Money::checked_sub`] return an error on a
+/// mismatch; the `+` and `-` operators panic, for amounts already known to
+/// share a currency. Amounts aren't ordered, as amounts in different
+/// currencies can't be compared. Amounts serialized before they carried a
+/// currency read as the default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Money {
     /// Amount in cents (e.g., 1000 = $10.00)
     cents: i64,
+    #[serde(default)]
+    currency: Currency,
 }
 
 impl Money {
-    /// Creates a new Money amount from cents.
+    /// Creates a new Money amount from cents, in the default currency.
     pub fn from_cents(cents: i64) -> Self {
-        Self { cents }
+        Self::from_cents_in(cents, Currency::default())
+    }
+
+    /// Creates a new Money amount from cents of `currency`.
+    pub fn from_cents_in(cents: i64, currency: Currency) -> Self {
+        Self { cents, currency }
     }
 
     /// Creates a new Money amount from a dollar value.
     ///
     /// The cents portion is calculated as dollars * 100.
     pub fn from_dollars(dollars: i64) -> Self {
-        Self {
-            cents: dollars * 100,
-        }
+        Self::from_cents(dollars * 100)
     }
 
     /// Returns zero money.
     pub fn zero() -> Self {
-        Self { cents: 0 }
+        Self::from_cents(0)
+    }
+
+    /// Returns zero money in `currency`.
+    pub fn zero_in(currency: Currency) -> Self {
+        Self::from_cents_in(0, currency)
     }
 
     /// Returns the amount in cents.
@@ -122,6 +209,17 @@ impl Money {
         self.cents
     }
 
+    /// Returns the currency the amount is in.
+    pub fn currency(&self) -> Currency {
+        self.currency
+    }
+
+    /// Returns the same number of cents in `currency`. This relabels the
+    /// amount; it doesn't convert it.
+    pub fn in_currency(self, currency: Currency) -> Money {
+        Money { currency, ..self }
+    }
+
     /// Returns the dollar portion (whole number).
     pub fn dollars(&self) -> i64 {
         self.cents / 100
@@ -147,24 +245,37 @@ impl Money {
         self.cents < 0
     }
 
-    /// Adds another money amount.
-    pub fn add(&self, other: Money) -> Money {
-        Money {
-            cents: self.cents + other.cents,
-        }
+    /// Adds another money amount, failing if it is in another currency.
+    pub fn checked_add(&self, other: Money) -> Result<Money, OrderError> {
+        let currency = self.common_currency(other)?;
+        Ok(Money::from_cents_in(self.cents + other.cents, currency))
     }
 
-    /// Subtracts another money amount.
-    pub fn subtract(&self, other: Money) -> Money {
-        Money {
-            cents: self.cents - other.cents,
-        }
+    /// Subtracts another money amount, failing if it is in another currency.
+    pub fn checked_sub(&self, other: Money) -> Result<Money, OrderError> {
+        let currency = self.common_currency(other)?;
+        Ok(Money::from_cents_in(self.cents - other.cents, currency))
     }
 
     /// Multiplies by a quantity.
     pub fn multiply(&self, quantity: u32) -> Money {
         Money {
             cents: self.cents * quantity as i64,
+            currency: self.currency,
+        }
+    }
+
+    /// Returns the currency of a sum or difference with `other`.
+    fn common_currency(&self, other: Money) -> Result<Currency, OrderError> {
+        if self.currency == other.currency || other.is_zero() {
+            Ok(self.currency)
+        } else if self.is_zero() {
+            Ok(other.currency)
+        } else {
+            Err(OrderError::CurrencyMismatch {
+                expected: self.currency,
+                actual: other.currency,
+            })
         }
     }
 }
@@ -177,10 +288,12 @@ impl Default for Money {
 
 impl std::fmt::Display for Money {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if self.cents < 0 {
-            write!(f, "-${}.{:02}", self.dollars().abs(), self.cents_part())
+        let sign = if self.cents < 0 { "-" } else { "" };
+        let (dollars, cents) = (self.dollars().abs(), self.cents_part());
+        if self.currency == Currency::USD {
+            write!(f, "{sign}${dollars}.{cents:02}")
         } else {
-            write!(f, "${}.{:02}", self.dollars(), self.cents_part())
+            write!(f, "{sign}{dollars}.{cents:02} {}", self.currency)
         }
     }
 }
@@ -188,32 +301,32 @@ impl std::fmt::Display for Money {
 impl std::ops::Add for Money {
     type Output = Money;
 
+    /// Panics if the amounts are in different currencies.
     fn add(self, rhs: Self) -> Self::Output {
-        Money {
-            cents: self.cents + rhs.cents,
-        }
+        self.checked_add(rhs)
+            .expect("added money amounts in different currencies")
     }
 }
 
 impl std::ops::Sub for Money {
     type Output = Money;
 
+    /// Panics if the amounts are in different currencies.
     fn sub(self, rhs: Self) -> Self::Output {
-        Money {
-            cents: self.cents - rhs.cents,
-        }
+        self.checked_sub(rhs)
+            .expect("subtracted money amounts in different currencies")
     }
 }
 
 impl std::ops::AddAssign for Money {
     fn add_assign(&mut self, rhs: Self) {
-        self.cents += rhs.cents;
+        *self = *self + rhs;
     }
 }
 
 impl std::ops::SubAssign for Money {
     fn sub_assign(&mut self, rhs: Self) {
-        self.cents -= rhs.cents;
+        *self = *self - rhs;
     }
 }
 
@@ -255,6 +368,170 @@ impl OrderItem {
     }
 }
 
+/// Serial or lot numbers recorded for a line item when it ships.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShippedItem {
+    /// The product shipped.
+    pub product_id: ProductId,
+
+    /// One serial number per serialized unit shipped.
+    #[serde(default)]
+    pub serial_numbers: Vec<String>,
+
+    /// Manufacturing lot or batch the units came from.
+    #[serde(default)]
+    pub lot_number: Option<String>,
+}
+
+impl ShippedItem {
+    /// Creates a shipped item with no serial or lot numbers.
+    pub fn new(product_id: impl Into<ProductId>) -> Self {
+        Self {
+            product_id: product_id.into(),
+            serial_numbers: Vec::new(),
+            lot_number: None,
+        }
+    }
+
+    /// Sets the serial numbers of the units shipped.
+    pub fn with_serial_numbers<I, S>(mut self, serial_numbers: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.serial_numbers = serial_numbers.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Sets the lot number of the units shipped.
+    pub fn with_lot_number(mut self, lot_number: impl Into<String>) -> Self {
+        self.lot_number = Some(lot_number.into());
+        self
+    }
+}
+
+/// Longest address line, city, or region accepted, in characters.
+const MAX_ADDRESS_FIELD_LEN: usize = 100;
+
+/// Longest postal code accepted, in characters.
+const MAX_POSTAL_CODE_LEN: usize = 16;
+
+/// Postal address an order ships to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Address {
+    /// Street address.
+    pub line1: String,
+
+    /// Apartment, suite, or building.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line2: Option<String>,
+
+    pub city: String,
+
+    /// State, province, or county, where the country uses one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+
+    pub postal_code: String,
+
+    /// ISO 3166-1 alpha-2 country code (e.g. `US`).
+    pub country: String,
+}
+
+impl Address {
+    /// Creates an address with no second line or region.
+    pub fn new(
+        line1: impl Into<String>,
+        city: impl Into<String>,
+        postal_code: impl Into<String>,
+        country: impl Into<String>,
+    ) -> Self {
+        Self {
+            line1: line1.into(),
+            line2: None,
+            city: city.into(),
+            region: None,
+            postal_code: postal_code.into(),
+            country: country.into(),
+        }
+    }
+
+    /// Sets the second address line.
+    pub fn with_line2(mut self, line2: impl Into<String>) -> Self {
+        self.line2 = Some(line2.into());
+        self
+    }
+
+    /// Sets the region.
+    pub fn with_region(mut self, region: impl Into<String>) -> Self {
+        self.region = Some(region.into());
+        self
+    }
+
+    /// Returns the address with fields trimmed, blank optional fields
+    /// dropped, and the country code uppercased, or an error naming the
+    /// first field that is missing or too long, or a country that isn't
+    /// two letters.
+    pub fn validated(self) -> Result<Self, OrderError> {
+        let required = |field: &'static str, value: String, max_len: usize| {
+            let value = value.trim().to_string();
+            if value.is_empty() {
+                return Err(invalid_address(field, "is required"));
+            }
+            if value.chars().count() > max_len {
+                return Err(invalid_address(field, "is too long"));
+            }
+            Ok(value)
+        };
+        let optional = |field: &'static str, value: Option<String>| {
+            let value = value
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty());
+            if value
+                .as_ref()
+                .is_some_and(|v| v.chars().count() > MAX_ADDRESS_FIELD_LEN)
+            {
+                return Err(invalid_address(field, "is too long"));
+            }
+            Ok(value)
+        };
+
+        let country = self.country.trim().to_ascii_uppercase();
+        if country.len() != 2 || !country.bytes().all(|b| b.is_ascii_uppercase()) {
+            return Err(invalid_address(
+                "country",
+                "must be a two-letter ISO 3166 code",
+            ));
+        }
+        Ok(Self {
+            line1: required("line1", self.line1, MAX_ADDRESS_FIELD_LEN)?,
+            line2: optional("line2", self.line2)?,
+            city: required("city", self.city, MAX_ADDRESS_FIELD_LEN)?,
+            region: optional("region", self.region)?,
+            postal_code: required("postal_code", self.postal_code, MAX_POSTAL_CODE_LEN)?,
+            country,
+        })
+    }
+}
+
+impl std::fmt::Display for Address {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.line1)?;
+        if let Some(line2) = &self.line2 {
+            write!(f, ", {line2}")?;
+        }
+        write!(f, ", {}", self.city)?;
+        if let Some(region) = &self.region {
+            write!(f, ", {region}")?;
+        }
+        write!(f, " {}, {}", self.postal_code, self.country)
+    }
+}
+
+fn invalid_address(field: &'static str, reason: &'static str) -> OrderError {
+    OrderError::InvalidAddress { field, reason }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -273,6 +550,24 @@ mod tests {
         assert_eq!(id.as_uuid(), uuid);
     }
 
+    #[test]
+    fn test_currency_parse() {
+        let currency: Currency = "eur".parse().unwrap();
+        assert_eq!(currency, Currency::EUR);
+        assert_eq!(currency.to_string(), "EUR");
+        assert!("EURO".parse::<Currency>().is_err());
+        assert!("U$D".parse::<Currency>().is_err());
+    }
+
+    #[test]
+    fn test_currency_serde_roundtrip() {
+        let json = serde_json::to_string(&Currency::GBP).unwrap();
+        assert_eq!(json, "\"GBP\"");
+        let parsed: Currency = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, Currency::GBP);
+        assert!(serde_json::from_str::<Currency>("\"12\"").is_err());
+    }
+
     #[test]
     fn test_product_id_string_conversion() {
         let id = ProductId::new("SKU-001");
@@ -350,4 +645,85 @@ mod tests {
         money -= Money::from_cents(30);
         assert_eq!(money.cents(), 70);
     }
+
+    #[test]
+    fn test_money_currency() {
+        let euros = Money::from_cents_in(1250, Currency::EUR);
+        assert_eq!(euros.to_string(), "12.50 EUR");
+        assert_eq!(euros.multiply(2).currency(), Currency::EUR);
+        assert_eq!(Money::zero() + euros, euros);
+        assert_ne!(euros, Money::from_cents(1250));
+
+        assert!(matches!(
+            euros.checked_add(Money::from_cents(100)),
+            Err(OrderError::CurrencyMismatch {
+                expected: Currency::EUR,
+                actual: Currency::USD,
+            })
+        ));
+        assert_eq!(
+            Money::zero().checked_add(euros).unwrap().currency(),
+            Currency::EUR
+        );
+        assert!(matches!(
+            euros.checked_sub(Money::from_cents(100)),
+            Err(OrderError::CurrencyMismatch { .. })
+        ));
+        assert_eq!(
+            Money::zero().checked_sub(euros).unwrap(),
+            Money::from_cents_in(-1250, Currency::EUR)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "different currencies")]
+    fn test_money_operators_refuse_mixed_currencies() {
+        let _ = Money::from_cents_in(1250, Currency::EUR) + Money::from_cents(100);
+    }
+
+    #[test]
+    fn test_money_serde() {
+        let json = serde_json::to_value(Money::from_cents_in(500, Currency::GBP)).unwrap();
+        assert_eq!(json, serde_json::json!({ "cents": 500, "currency": "GBP" }));
+
+        // Amounts stored before they carried a currency
+        let legacy: Money = serde_json::from_value(serde_json::json!({ "cents": 500 })).unwrap();
+        assert_eq!(legacy, Money::from_cents_in(500, Currency::USD));
+    }
+
+    #[test]
+    fn test_address_validation() {
+        let address = Address::new(" 1 Main St ", "Springfield", "12345", "us")
+            .with_line2("  ")
+            .with_region("IL")
+            .validated()
+            .unwrap();
+        assert_eq!(address.line1, "1 Main St");
+        assert_eq!(address.line2, None);
+        assert_eq!(address.country, "US");
+        assert_eq!(address.to_string(), "1 Main St, Springfield, IL 12345, US");
+
+        let missing = Address::new("1 Main St", " ", "12345", "US").validated();
+        assert!(matches!(
+            missing,
+            Err(OrderError::InvalidAddress { field: "city", .. })
+        ));
+        let country = Address::new("1 Main St", "Springfield", "12345", "USA").validated();
+        assert!(matches!(
+            country,
+            Err(OrderError::InvalidAddress {
+                field: "country",
+                ..
+            })
+        ));
+        let postal_code =
+            Address::new("1 Main St", "Springfield", "1".repeat(17), "US").validated();
+        assert!(matches!(
+            postal_code,
+            Err(OrderError::InvalidAddress {
+                field: "postal_code",
+                reason: "is too long",
+            })
+        ));
+    }
 }
@@ -0,0 +1,460 @@
+//! Payment aggregate implementation.
+
+use std::fmt;
+
+use common::AggregateId;
+use event_store::Version;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::aggregate::Aggregate;
+use crate::order::{CustomerId, Money};
+
+use super::events::{
+    PaymentAuthorizedData, PaymentCapturedData, PaymentDeclinedData, PaymentRefundedData,
+    PaymentRequestedData, RefundRequestedData,
+};
+use super::{PaymentError, PaymentEvent};
+
+/// Namespace for payment IDs derived from their order.
+const PAYMENT_NAMESPACE: Uuid = Uuid::from_u128(0x4d8a_16f2_b93c_4e05_8a7d_c1f6_0e29_b53e);
+
+/// Where a payment is in its lifecycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PaymentStatus {
+    /// The charge was recorded but the provider's answer isn't.
+    Pending,
+    /// The provider agreed to the charge; nothing is collected yet.
+    Authorized,
+    /// The provider refused the charge. Final.
+    Declined,
+    /// The amount was collected.
+    Captured,
+    /// The refund was recorded but the provider's answer isn't.
+    RefundPending,
+    /// The payment was returned to the customer. Final.
+    Refunded,
+}
+
+impl fmt::Display for PaymentStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self {
+            PaymentStatus::Pending => "pending",
+            PaymentStatus::Authorized => "authorized",
+            PaymentStatus::Declined => "declined",
+            PaymentStatus::Captured => "captured",
+            PaymentStatus::RefundPending => "refund pending",
+            PaymentStatus::Refunded => "refunded",
+        };
+        f.write_str(status)
+    }
+}
+
+/// Payment aggregate root.
+///
+/// One stream per charge attempt. A charge is requested before it goes to
+/// the provider and is then authorized or declined, an authorized payment
+/// is captured, and an authorized or captured payment can be refunded,
+/// again requested first. Capturing or refunding again does nothing, so
+/// saga steps and compensations can be repeated.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Payment {
+    /// Unique stream identifier.
+    id: Option<AggregateId>,
+
+    /// Current version for optimistic concurrency.
+    #[serde(default)]
+    version: Version,
+
+    /// The order being paid for.
+    order_id: Option<AggregateId>,
+
+    /// The customer charged.
+    customer_id: Option<CustomerId>,
+
+    /// Amount charged.
+    amount: Money,
+
+    /// The provider's ID for the charge.
+    provider_reference: Option<String>,
+
+    /// Lifecycle status; None until the charge is recorded.
+    status: Option<PaymentStatus>,
+
+    /// The provider's decline code, if declined.
+    decline_code: Option<String>,
+}
+
+impl Aggregate for Payment {
+    type Event = PaymentEvent;
+    type Error = PaymentError;
+
+    fn aggregate_type() -> &'static str {
+        "Payment"
+    }
+
+    fn id(&self) -> Option<AggregateId> {
+        self.id
+    }
+
+    fn version(&self) -> Version {
+        self.version
+    }
+
+    fn set_version(&mut self, version: Version) {
+        self.version = version;
+    }
+
+    fn apply(&mut self, event: Self::Event) {
+        match event {
+            PaymentEvent::PaymentRequested(data) => self.apply_payment_requested(data),
+            PaymentEvent::PaymentAuthorized(data) => self.apply_payment_authorized(data),
+            PaymentEvent::PaymentDeclined(data) => self.apply_payment_declined(data),
+            PaymentEvent::PaymentCaptured(data) => self.apply_payment_captured(data),
+            PaymentEvent::RefundRequested(data) => self.apply_refund_requested(data),
+            PaymentEvent::PaymentRefunded(data) => self.apply_payment_refunded(data),
+        }
+    }
+}
+
+// Query methods
+impl Payment {
+    /// Returns the ID of an order's `attempt`th charge, counting from 0.
+    ///
+    /// The same order and attempt always give the same ID, so a charge
+    /// repeated after a crash lands on the payment already requested.
+    pub fn id_for_order(order_id: AggregateId, attempt: u32) -> AggregateId {
+        AggregateId::from_uuid(Uuid::new_v5(
+            &PAYMENT_NAMESPACE,
+            format!("{order_id}#{attempt}").as_bytes(),
+        ))
+    }
+
+    /// Returns the order being paid for.
+    pub fn order_id(&self) -> Option<AggregateId> {
+        self.order_id
+    }
+
+    /// Returns the customer charged.
+    pub fn customer_id(&self) -> Option<CustomerId> {
+        self.customer_id
+    }
+
+    /// Returns the amount charged.
+    pub fn amount(&self) -> Money {
+        self.amount
+    }
+
+    /// Returns the provider's ID for the charge.
+    pub fn provider_reference(&self) -> Option<&str> {
+        self.provider_reference.as_deref()
+    }
+
+    /// Returns the lifecycle status, or None if nothing is recorded.
+    pub fn status(&self) -> Option<PaymentStatus> {
+        self.status
+    }
+
+    /// Returns the provider's decline code, if declined.
+    pub fn decline_code(&self) -> Option<&str> {
+        self.decline_code.as_deref()
+    }
+
+    /// Returns true if money was collected and not returned.
+    pub fn is_captured(&self) -> bool {
+        self.status == Some(PaymentStatus::Captured)
+    }
+}
+
+// Command methods (return events)
+impl Payment {
+    /// Records a charge of `amount` about to be sent to the provider.
+    pub fn request(
+        &self,
+        payment_id: AggregateId,
+        order_id: AggregateId,
+        customer_id: CustomerId,
+        amount: Money,
+    ) -> Result<Vec<PaymentEvent>, PaymentError> {
+        self.ensure_new(payment_id, amount)?;
+        Ok(vec![PaymentEvent::payment_requested(
+            payment_id,
+            order_id,
+            customer_id,
+            amount,
+        )])
+    }
+
+    /// Records that the provider agreed to charge `amount`.
+    pub fn authorize(
+        &self,
+        payment_id: AggregateId,
+        order_id: AggregateId,
+        customer_id: CustomerId,
+        amount: Money,
+        provider_reference: Option<String>,
+    ) -> Result<Vec<PaymentEvent>, PaymentError> {
+        self.ensure_undecided(payment_id, amount)?;
+        Ok(vec![PaymentEvent::payment_authorized(
+            payment_id,
+            order_id,
+            customer_id,
+            amount,
+            provider_reference,
+        )])
+    }
+
+    /// Records that the provider refused to charge `amount`.
+    pub fn decline(
+        &self,
+        payment_id: AggregateId,
+        order_id: AggregateId,
+        customer_id: CustomerId,
+        amount: Money,
+        code: String,
+    ) -> Result<Vec<PaymentEvent>, PaymentError> {
+        self.ensure_undecided(payment_id, amount)?;
+        Ok(vec![PaymentEvent::payment_declined(
+            payment_id,
+            order_id,
+            customer_id,
+            amount,
+            code,
+        )])
+    }
+
+    /// Collects the authorized amount.
+    pub fn capture(&self) -> Result<Vec<PaymentEvent>, PaymentError> {
+        match self.status {
+            Some(PaymentStatus::Authorized) => {
+                Ok(vec![PaymentEvent::payment_captured(self.amount)])
+            }
+            Some(PaymentStatus::Captured) => Ok(vec![]),
+            status => Err(PaymentError::InvalidTransition {
+                action: "capture",
+                status,
+            }),
+        }
+    }
+
+    /// Records a refund about to be sent to the provider.
+    pub fn request_refund(&self) -> Result<Vec<PaymentEvent>, PaymentError> {
+        match self.status {
+            Some(PaymentStatus::Authorized | PaymentStatus::Captured) => {
+                Ok(vec![PaymentEvent::refund_requested(self.amount)])
+            }
+            Some(PaymentStatus::RefundPending | PaymentStatus::Refunded) => Ok(vec![]),
+            status => Err(PaymentError::InvalidTransition {
+                action: "refund",
+                status,
+            }),
+        }
+    }
+
+    /// Returns the payment to the customer.
+    pub fn refund(&self) -> Result<Vec<PaymentEvent>, PaymentError> {
+        match self.status {
+            Some(
+                PaymentStatus::Authorized | PaymentStatus::Captured | PaymentStatus::RefundPending,
+            ) => Ok(vec![PaymentEvent::payment_refunded(self.amount)]),
+            Some(PaymentStatus::Refunded) => Ok(vec![]),
+            status => Err(PaymentError::InvalidTransition {
+                action: "refund",
+                status,
+            }),
+        }
+    }
+
+    fn ensure_new(&self, payment_id: AggregateId, amount: Money) -> Result<(), PaymentError> {
+        if self.status.is_some() {
+            return Err(PaymentError::AlreadyRecorded(payment_id));
+        }
+        if amount.is_negative() {
+            return Err(PaymentError::InvalidAmount { amount });
+        }
+        Ok(())
+    }
+
+    /// Like `ensure_new`, but also accepts a requested payment awaiting
+    /// the provider's answer.
+    fn ensure_undecided(&self, payment_id: AggregateId, amount: Money) -> Result<(), PaymentError> {
+        if self.status == Some(PaymentStatus::Pending) && self.id == Some(payment_id) {
+            return Ok(());
+        }
+        self.ensure_new(payment_id, amount)
+    }
+}
+
+// Apply event helpers
+impl Payment {
+    fn apply_payment_requested(&mut self, data: PaymentRequestedData) {
+        self.id = Some(data.payment_id);
+        self.order_id = Some(data.order_id);
+        self.customer_id = Some(data.customer_id);
+        self.amount = data.amount;
+        self.status = Some(PaymentStatus::Pending);
+    }
+
+    fn apply_payment_authorized(&mut self, data: PaymentAuthorizedData) {
+        self.id = Some(data.payment_id);
+        self.order_id = Some(data.order_id);
+        self.customer_id = Some(data.customer_id);
+        self.amount = data.amount;
+        self.provider_reference = data.provider_reference;
+        self.status = Some(PaymentStatus::Authorized);
+    }
+
+    fn apply_payment_declined(&mut self, data: PaymentDeclinedData) {
+        self.id = Some(data.payment_id);
+        self.order_id = Some(data.order_id);
+        self.customer_id = Some(data.customer_id);
+        self.amount = data.amount;
+        self.decline_code = Some(data.code);
+        self.status = Some(PaymentStatus::Declined);
+    }
+
+    fn apply_payment_captured(&mut self, _data: PaymentCapturedData) {
+        self.status = Some(PaymentStatus::Captured);
+    }
+
+    fn apply_refund_requested(&mut self, _data: RefundRequestedData) {
+        self.status = Some(PaymentStatus::RefundPending);
+    }
+
+    fn apply_payment_refunded(&mut self, _data: PaymentRefundedData) {
+        self.status = Some(PaymentStatus::Refunded);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn authorized() -> Payment {
+        let mut payment = Payment::default();
+        let events = payment
+            .authorize(
+                AggregateId::new(),
+                AggregateId::new(),
+                CustomerId::new(),
+                Money::from_cents(5000),
+                Some("PAY-0001".into()),
+            )
+            .unwrap();
+        payment.apply_events(events);
+        payment
+    }
+
+    #[test]
+    fn test_authorize_capture_refund() {
+        let mut payment = authorized();
+        assert_eq!(payment.status(), Some(PaymentStatus::Authorized));
+        assert_eq!(payment.provider_reference(), Some("PAY-0001"));
+
+        payment.apply_events(payment.capture().unwrap());
+        assert!(payment.is_captured());
+        assert!(payment.capture().unwrap().is_empty());
+
+        payment.apply_events(payment.refund().unwrap());
+        assert_eq!(payment.status(), Some(PaymentStatus::Refunded));
+        // Repeated compensations refund nothing more
+        assert!(payment.refund().unwrap().is_empty());
+        assert!(matches!(
+            payment.capture(),
+            Err(PaymentError::InvalidTransition {
+                action: "capture",
+                status: Some(PaymentStatus::Refunded),
+            })
+        ));
+    }
+
+    #[test]
+    fn test_declined_payment_is_final() {
+        let mut payment = Payment::default();
+        let payment_id = AggregateId::new();
+        let events = payment
+            .decline(
+                payment_id,
+                AggregateId::new(),
+                CustomerId::new(),
+                Money::from_cents(5000),
+                "insufficient_funds".into(),
+            )
+            .unwrap();
+        payment.apply_events(events);
+        assert_eq!(payment.status(), Some(PaymentStatus::Declined));
+        assert_eq!(payment.decline_code(), Some("insufficient_funds"));
+
+        assert!(payment.capture().is_err());
+        assert!(payment.refund().is_err());
+        assert!(matches!(
+            payment.authorize(
+                payment_id,
+                AggregateId::new(),
+                CustomerId::new(),
+                Money::from_cents(5000),
+                None
+            ),
+            Err(PaymentError::AlreadyRecorded(id)) if id == payment_id
+        ));
+    }
+
+    #[test]
+    fn test_requested_payment_awaits_the_provider() {
+        let order_id = AggregateId::new();
+        let payment_id = Payment::id_for_order(order_id, 0);
+        assert_eq!(payment_id, Payment::id_for_order(order_id, 0));
+        assert_ne!(payment_id, Payment::id_for_order(order_id, 1));
+
+        let mut payment = Payment::default();
+        let events = payment
+            .request(
+                payment_id,
+                order_id,
+                CustomerId::new(),
+                Money::from_cents(5000),
+            )
+            .unwrap();
+        payment.apply_events(events);
+        assert_eq!(payment.status(), Some(PaymentStatus::Pending));
+        assert!(payment.capture().is_err());
+        assert!(payment.request_refund().is_err());
+
+        let events = payment
+            .authorize(
+                payment_id,
+                order_id,
+                CustomerId::new(),
+                Money::from_cents(5000),
+                Some("PAY-0001".into()),
+            )
+            .unwrap();
+        payment.apply_events(events);
+        assert_eq!(payment.status(), Some(PaymentStatus::Authorized));
+
+        payment.apply_events(payment.request_refund().unwrap());
+        assert_eq!(payment.status(), Some(PaymentStatus::RefundPending));
+        assert!(payment.request_refund().unwrap().is_empty());
+        payment.apply_events(payment.refund().unwrap());
+        assert_eq!(payment.status(), Some(PaymentStatus::Refunded));
+    }
+
+    #[test]
+    fn test_invalid_amount_and_missing_payment() {
+        let payment = Payment::default();
+        assert!(matches!(
+            payment.authorize(
+                AggregateId::new(),
+                AggregateId::new(),
+                CustomerId::new(),
+                Money::from_cents(-100),
+                None
+            ),
+            Err(PaymentError::InvalidAmount { .. })
+        ));
+        assert!(matches!(
+            payment.capture(),
+            Err(PaymentError::InvalidTransition { status: None, .. })
+        ));
+    }
+}
@@ -0,0 +1,218 @@
+//! Payment commands.
+
+use common::AggregateId;
+
+use crate::command::Command;
+use crate::order::{CustomerId, Money};
+
+use super::Payment;
+
+/// Command to record a charge before it is sent to the provider.
+#[derive(Debug, Clone)]
+pub struct RequestPayment {
+    /// The new payment's stream ID, usually [`Payment::id_for_order`].
+    pub payment_id: AggregateId,
+
+    /// The order being paid for.
+    pub order_id: AggregateId,
+
+    /// The customer charged.
+    pub customer_id: CustomerId,
+
+    /// Amount requested.
+    pub amount: Money,
+}
+
+impl RequestPayment {
+    /// Creates a command requesting a new payment.
+    pub fn new(
+        payment_id: AggregateId,
+        order_id: AggregateId,
+        customer_id: CustomerId,
+        amount: Money,
+    ) -> Self {
+        Self {
+            payment_id,
+            order_id,
+            customer_id,
+            amount,
+        }
+    }
+}
+
+impl Command for RequestPayment {
+    type Aggregate = Payment;
+
+    fn aggregate_id(&self) -> AggregateId {
+        self.payment_id
+    }
+}
+
+/// Command to record a charge the provider agreed to.
+#[derive(Debug, Clone)]
+pub struct AuthorizePayment {
+    /// The new payment's stream ID.
+    pub payment_id: AggregateId,
+
+    /// The order being paid for.
+    pub order_id: AggregateId,
+
+    /// The customer charged.
+    pub customer_id: CustomerId,
+
+    /// Amount authorized.
+    pub amount: Money,
+
+    /// The provider's ID for the charge.
+    pub provider_reference: Option<String>,
+}
+
+impl AuthorizePayment {
+    /// Creates a command authorizing a new payment.
+    pub fn new(order_id: AggregateId, customer_id: CustomerId, amount: Money) -> Self {
+        Self {
+            payment_id: AggregateId::new(),
+            order_id,
+            customer_id,
+            amount,
+            provider_reference: None,
+        }
+    }
+
+    /// Authorizes the already requested payment `payment_id`.
+    pub fn with_payment_id(mut self, payment_id: AggregateId) -> Self {
+        self.payment_id = payment_id;
+        self
+    }
+
+    /// Records the provider's ID for the charge.
+    pub fn with_provider_reference(mut self, reference: impl Into<String>) -> Self {
+        self.provider_reference = Some(reference.into());
+        self
+    }
+}
+
+impl Command for AuthorizePayment {
+    type Aggregate = Payment;
+
+    fn aggregate_id(&self) -> AggregateId {
+        self.payment_id
+    }
+}
+
+/// Command to record a charge the provider refused.
+#[derive(Debug, Clone)]
+pub struct DeclinePayment {
+    /// The new payment's stream ID.
+    pub payment_id: AggregateId,
+
+    /// The order being paid for.
+    pub order_id: AggregateId,
+
+    /// The customer charged.
+    pub customer_id: CustomerId,
+
+    /// Amount requested.
+    pub amount: Money,
+
+    /// The provider's decline code.
+    pub code: String,
+}
+
+impl DeclinePayment {
+    /// Creates a command declining a new payment.
+    pub fn new(
+        order_id: AggregateId,
+        customer_id: CustomerId,
+        amount: Money,
+        code: impl Into<String>,
+    ) -> Self {
+        Self {
+            payment_id: AggregateId::new(),
+            order_id,
+            customer_id,
+            amount,
+            code: code.into(),
+        }
+    }
+
+    /// Declines the already requested payment `payment_id`.
+    pub fn with_payment_id(mut self, payment_id: AggregateId) -> Self {
+        self.payment_id = payment_id;
+        self
+    }
+}
+
+impl Command for DeclinePayment {
+    type Aggregate = Payment;
+
+    fn aggregate_id(&self) -> AggregateId {
+        self.payment_id
+    }
+}
+
+/// Command to collect an authorized payment.
+#[derive(Debug, Clone)]
+pub struct CapturePayment {
+    /// The payment to capture.
+    pub payment_id: AggregateId,
+}
+
+impl CapturePayment {
+    /// Creates a new CapturePayment command.
+    pub fn new(payment_id: AggregateId) -> Self {
+        Self { payment_id }
+    }
+}
+
+impl Command for CapturePayment {
+    type Aggregate = Payment;
+
+    fn aggregate_id(&self) -> AggregateId {
+        self.payment_id
+    }
+}
+
+/// Command to record a refund before it is sent to the provider.
+#[derive(Debug, Clone)]
+pub struct RequestRefund {
+    /// The payment to refund.
+    pub payment_id: AggregateId,
+}
+
+impl RequestRefund {
+    /// Creates a new RequestRefund command.
+    pub fn new(payment_id: AggregateId) -> Self {
+        Self { payment_id }
+    }
+}
+
+impl Command for RequestRefund {
+    type Aggregate = Payment;
+
+    fn aggregate_id(&self) -> AggregateId {
+        self.payment_id
+    }
+}
+
+/// Command to return a payment to the customer.
+#[derive(Debug, Clone)]
+pub struct RefundPayment {
+    /// The payment to refund.
+    pub payment_id: AggregateId,
+}
+
+impl RefundPayment {
+    /// Creates a new RefundPayment command.
+    pub fn new(payment_id: AggregateId) -> Self {
+        Self { payment_id }
+    }
+}
+
+impl Command for RefundPayment {
+    type Aggregate = Payment;
+
+    fn aggregate_id(&self) -> AggregateId {
+        self.payment_id
+    }
+}
@@ -0,0 +1,240 @@
+//! Payment domain events.
+
+use chrono::{DateTime, Utc};
+use common::AggregateId;
+use serde::{Deserialize, Serialize};
+
+use crate::aggregate::DomainEvent;
+use crate::order::{CustomerId, Money};
+
+/// Events that can occur on a payment.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data")]
+pub enum PaymentEvent {
+    /// A charge is about to be sent to the provider.
+    PaymentRequested(PaymentRequestedData),
+
+    /// The provider agreed to the charge.
+    PaymentAuthorized(PaymentAuthorizedData),
+
+    /// The provider refused the charge.
+    PaymentDeclined(PaymentDeclinedData),
+
+    /// The authorized amount was collected.
+    PaymentCaptured(PaymentCapturedData),
+
+    /// A refund is about to be sent to the provider.
+    RefundRequested(RefundRequestedData),
+
+    /// The payment was returned to the customer.
+    PaymentRefunded(PaymentRefundedData),
+}
+
+impl DomainEvent for PaymentEvent {
+    fn event_type(&self) -> &'static str {
+        match self {
+            PaymentEvent::PaymentRequested(_) => "PaymentRequested",
+            PaymentEvent::PaymentAuthorized(_) => "PaymentAuthorized",
+            PaymentEvent::PaymentDeclined(_) => "PaymentDeclined",
+            PaymentEvent::PaymentCaptured(_) => "PaymentCaptured",
+            PaymentEvent::RefundRequested(_) => "RefundRequested",
+            PaymentEvent::PaymentRefunded(_) => "PaymentRefunded",
+        }
+    }
+}
+
+/// Data for PaymentRequested event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentRequestedData {
+    /// The payment's stream ID, sent to the provider as the idempotency key.
+    pub payment_id: AggregateId,
+
+    /// The order being paid for.
+    pub order_id: AggregateId,
+
+    /// The customer charged.
+    pub customer_id: CustomerId,
+
+    /// Amount requested.
+    pub amount: Money,
+
+    /// When the charge was requested.
+    pub requested_at: DateTime<Utc>,
+}
+
+/// Data for PaymentAuthorized event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentAuthorizedData {
+    /// The payment's stream ID.
+    pub payment_id: AggregateId,
+
+    /// The order being paid for.
+    pub order_id: AggregateId,
+
+    /// The customer charged.
+    pub customer_id: CustomerId,
+
+    /// Amount authorized.
+    pub amount: Money,
+
+    /// The provider's ID for the charge, if a provider was involved.
+    pub provider_reference: Option<String>,
+
+    /// When the charge was authorized.
+    pub authorized_at: DateTime<Utc>,
+}
+
+/// Data for PaymentDeclined event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentDeclinedData {
+    /// The payment's stream ID.
+    pub payment_id: AggregateId,
+
+    /// The order being paid for.
+    pub order_id: AggregateId,
+
+    /// The customer charged.
+    pub customer_id: CustomerId,
+
+    /// Amount requested.
+    pub amount: Money,
+
+    /// The provider's decline code, such as `insufficient_funds`.
+    pub code: String,
+
+    /// When the charge was declined.
+    pub declined_at: DateTime<Utc>,
+}
+
+/// Data for PaymentCaptured event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentCapturedData {
+    /// Amount collected.
+    pub amount: Money,
+
+    /// When the payment was captured.
+    pub captured_at: DateTime<Utc>,
+}
+
+/// Data for RefundRequested event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefundRequestedData {
+    /// Amount to return.
+    pub amount: Money,
+
+    /// When the refund was requested.
+    pub requested_at: DateTime<Utc>,
+}
+
+/// Data for PaymentRefunded event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentRefundedData {
+    /// Amount returned.
+    pub amount: Money,
+
+    /// When the payment was refunded.
+    pub refunded_at: DateTime<Utc>,
+}
+
+// Convenience constructors for events
+impl PaymentEvent {
+    /// Creates a PaymentRequested event.
+    pub fn payment_requested(
+        payment_id: AggregateId,
+        order_id: AggregateId,
+        customer_id: CustomerId,
+        amount: Money,
+    ) -> Self {
+        PaymentEvent::PaymentRequested(PaymentRequestedData {
+            payment_id,
+            order_id,
+            customer_id,
+            amount,
+            requested_at: Utc::now(),
+        })
+    }
+
+    /// Creates a PaymentAuthorized event.
+    pub fn payment_authorized(
+        payment_id: AggregateId,
+        order_id: AggregateId,
+        customer_id: CustomerId,
+        amount: Money,
+        provider_reference: Option<String>,
+    ) -> Self {
+        PaymentEvent::PaymentAuthorized(PaymentAuthorizedData {
+            payment_id,
+            order_id,
+            customer_id,
+            amount,
+            provider_reference,
+            authorized_at: Utc::now(),
+        })
+    }
+
+    /// Creates a PaymentDeclined event.
+    pub fn payment_declined(
+        payment_id: AggregateId,
+        order_id: AggregateId,
+        customer_id: CustomerId,
+        amount: Money,
+        code: impl Into<String>,
+    ) -> Self {
+        PaymentEvent::PaymentDeclined(PaymentDeclinedData {
+            payment_id,
+            order_id,
+            customer_id,
+            amount,
+            code: code.into(),
+            declined_at: Utc::now(),
+        })
+    }
+
+    /// Creates a PaymentCaptured event.
+    pub fn payment_captured(amount: Money) -> Self {
+        PaymentEvent::PaymentCaptured(PaymentCapturedData {
+            amount,
+            captured_at: Utc::now(),
+        })
+    }
+
+    /// Creates a RefundRequested event.
+    pub fn refund_requested(amount: Money) -> Self {
+        PaymentEvent::RefundRequested(RefundRequestedData {
+            amount,
+            requested_at: Utc::now(),
+        })
+    }
+
+    /// Creates a PaymentRefunded event.
+    pub fn payment_refunded(amount: Money) -> Self {
+        PaymentEvent::PaymentRefunded(PaymentRefundedData {
+            amount,
+            refunded_at: Utc::now(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serialization_roundtrip() {
+        let event = PaymentEvent::payment_declined(
+            AggregateId::new(),
+            AggregateId::new(),
+            CustomerId::new(),
+            Money::from_cents(5000),
+            "card_declined",
+        );
+        assert_eq!(event.event_type(), "PaymentDeclined");
+
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["type"], "PaymentDeclined");
+        assert_eq!(json["data"]["code"], "card_declined");
+
+        let parsed: PaymentEvent = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.event_type(), "PaymentDeclined");
+    }
+}
@@ -0,0 +1,54 @@
+//! Payment aggregate and related types.
+
+mod aggregate;
+mod commands;
+mod events;
+mod service;
+
+pub use aggregate::{Payment, PaymentStatus};
+pub use commands::*;
+pub use events::{
+    PaymentAuthorizedData, PaymentCapturedData, PaymentDeclinedData, PaymentEvent,
+    PaymentRefundedData, PaymentRequestedData, RefundRequestedData,
+};
+pub use service::PaymentLedger;
+
+use common::AggregateId;
+use thiserror::Error;
+
+use crate::order::Money;
+
+/// Errors that can occur during payment operations.
+#[derive(Debug, Error)]
+pub enum PaymentError {
+    /// Charges can't be for a negative amount.
+    #[error("Invalid payment amount {amount}")]
+    InvalidAmount { amount: Money },
+
+    /// The charge attempt already has an outcome.
+    #[error("Payment {0} is already recorded")]
+    AlreadyRecorded(AggregateId),
+
+    /// The payment's status doesn't allow the action.
+    #[error("Cannot {action} a payment that is {}", describe(.status))]
+    InvalidTransition {
+        action: &'static str,
+        /// None if no charge was recorded.
+        status: Option<PaymentStatus>,
+    },
+}
+
+impl PaymentError {
+    /// Returns a stable code naming the violated invariant.
+    pub fn code(&self) -> &'static str {
+        match self {
+            PaymentError::InvalidAmount { .. } => "payment.invalid_amount",
+            PaymentError::AlreadyRecorded(_) => "payment.already_recorded",
+            PaymentError::InvalidTransition { .. } => "payment.invalid_transition",
+        }
+    }
+}
+
+fn describe(status: &Option<PaymentStatus>) -> String {
+    status.map_or_else(|| "not recorded".to_string(), |s| s.to_string())
+}
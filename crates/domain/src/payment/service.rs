@@ -0,0 +1,217 @@
+//! Payment ledger recording each charge's lifecycle.
+
+use common::AggregateId;
+use event_store::{EnvelopeFactory, EventStore, SchemaDeprecations};
+
+use crate::command::{Command, CommandHandler, CommandResult};
+use crate::contention::ContentionTracker;
+use crate::decision::DecisionLog;
+use crate::error::DomainError;
+
+use super::{
+    AuthorizePayment, CapturePayment, DeclinePayment, Payment, PaymentError, RefundPayment,
+    RequestPayment, RequestRefund,
+};
+
+impl From<PaymentError> for DomainError {
+    fn from(e: PaymentError) -> Self {
+        DomainError::Payment(e)
+    }
+}
+
+/// Service for recording payments.
+pub struct PaymentLedger<S: EventStore> {
+    handler: CommandHandler<S, Payment>,
+}
+
+impl<S: EventStore> PaymentLedger<S> {
+    /// Creates a new payment ledger with the given event store.
+    pub fn new(store: S) -> Self {
+        Self {
+            handler: CommandHandler::new(store),
+        }
+    }
+
+    /// Records concurrency conflicts on payments in `tracker`.
+    pub fn with_contention_tracker(mut self, tracker: ContentionTracker) -> Self {
+        self.handler = self.handler.with_contention_tracker(tracker);
+        self
+    }
+
+    /// Records command decisions in `log`.
+    pub fn with_decision_log(mut self, log: DecisionLog) -> Self {
+        self.handler = self.handler.with_decision_log(log);
+        self
+    }
+
+    /// Counts reads of deprecated event schema versions in `deprecations`.
+    pub fn with_schema_deprecations(mut self, deprecations: SchemaDeprecations) -> Self {
+        self.handler = self.handler.with_schema_deprecations(deprecations);
+        self
+    }
+
+    /// Builds this service's event envelopes with `factory`.
+    pub fn with_envelope_factory(mut self, factory: EnvelopeFactory) -> Self {
+        self.handler = self.handler.with_envelope_factory(factory);
+        self
+    }
+
+    /// Returns a reference to the underlying command handler.
+    pub fn handler(&self) -> &CommandHandler<S, Payment> {
+        &self.handler
+    }
+
+    /// Records a charge about to be sent to the provider.
+    #[tracing::instrument(skip(self))]
+    pub async fn request(
+        &self,
+        cmd: RequestPayment,
+    ) -> Result<CommandResult<Payment>, DomainError> {
+        self.handler
+            .execute_named(cmd.name(), cmd.aggregate_id(), |payment| {
+                payment.request(cmd.payment_id, cmd.order_id, cmd.customer_id, cmd.amount)
+            })
+            .await
+    }
+
+    /// Records a charge the provider agreed to.
+    #[tracing::instrument(skip(self))]
+    pub async fn authorize(
+        &self,
+        cmd: AuthorizePayment,
+    ) -> Result<CommandResult<Payment>, DomainError> {
+        self.handler
+            .execute_named(cmd.name(), cmd.aggregate_id(), |payment| {
+                payment.authorize(
+                    cmd.payment_id,
+                    cmd.order_id,
+                    cmd.customer_id,
+                    cmd.amount,
+                    cmd.provider_reference.clone(),
+                )
+            })
+            .await
+    }
+
+    /// Records a charge the provider refused.
+    #[tracing::instrument(skip(self))]
+    pub async fn decline(
+        &self,
+        cmd: DeclinePayment,
+    ) -> Result<CommandResult<Payment>, DomainError> {
+        self.handler
+            .execute_named(cmd.name(), cmd.aggregate_id(), |payment| {
+                payment.decline(
+                    cmd.payment_id,
+                    cmd.order_id,
+                    cmd.customer_id,
+                    cmd.amount,
+                    cmd.code.clone(),
+                )
+            })
+            .await
+    }
+
+    /// Collects an authorized payment; capturing again does nothing.
+    #[tracing::instrument(skip(self))]
+    pub async fn capture(
+        &self,
+        cmd: CapturePayment,
+    ) -> Result<CommandResult<Payment>, DomainError> {
+        self.handler
+            .execute_named(cmd.name(), cmd.aggregate_id(), |payment| payment.capture())
+            .await
+    }
+
+    /// Records a refund about to be sent to the provider; requesting again
+    /// does nothing.
+    #[tracing::instrument(skip(self))]
+    pub async fn request_refund(
+        &self,
+        cmd: RequestRefund,
+    ) -> Result<CommandResult<Payment>, DomainError> {
+        self.handler
+            .execute_named(cmd.name(), cmd.aggregate_id(), |payment| {
+                payment.request_refund()
+            })
+            .await
+    }
+
+    /// Returns a payment to the customer; refunding again does nothing.
+    #[tracing::instrument(skip(self))]
+    pub async fn refund(&self, cmd: RefundPayment) -> Result<CommandResult<Payment>, DomainError> {
+        self.handler
+            .execute_named(cmd.name(), cmd.aggregate_id(), |payment| payment.refund())
+            .await
+    }
+
+    /// Loads a payment.
+    ///
+    /// Returns None if no charge was recorded under the ID.
+    #[tracing::instrument(skip(self))]
+    pub async fn get_payment(
+        &self,
+        payment_id: AggregateId,
+    ) -> Result<Option<Payment>, DomainError> {
+        self.handler.load_existing(payment_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::order::{CustomerId, Money};
+    use crate::payment::PaymentStatus;
+    use event_store::InMemoryEventStore;
+
+    #[tokio::test]
+    async fn test_payment_lifecycle() {
+        let ledger = PaymentLedger::new(InMemoryEventStore::new());
+        let cmd = AuthorizePayment::new(
+            AggregateId::new(),
+            CustomerId::new(),
+            Money::from_cents(2500),
+        )
+        .with_provider_reference("ch_123");
+        let payment_id = cmd.payment_id;
+        assert!(ledger.get_payment(payment_id).await.unwrap().is_none());
+
+        ledger.authorize(cmd.clone()).await.unwrap();
+        assert!(matches!(
+            ledger.authorize(cmd).await,
+            Err(DomainError::Payment(PaymentError::AlreadyRecorded(_)))
+        ));
+
+        ledger
+            .capture(CapturePayment::new(payment_id))
+            .await
+            .unwrap();
+        let result = ledger.refund(RefundPayment::new(payment_id)).await.unwrap();
+        assert_eq!(result.aggregate.status(), Some(PaymentStatus::Refunded));
+
+        let payment = ledger.get_payment(payment_id).await.unwrap().unwrap();
+        assert_eq!(payment.amount(), Money::from_cents(2500));
+        assert_eq!(payment.provider_reference(), Some("ch_123"));
+        assert!(
+            ledger
+                .refund(RefundPayment::new(payment_id))
+                .await
+                .unwrap()
+                .events
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_refunding_unknown_payment_fails() {
+        let ledger = PaymentLedger::new(InMemoryEventStore::new());
+        let result = ledger.refund(RefundPayment::new(AggregateId::new())).await;
+        assert!(matches!(
+            result,
+            Err(DomainError::Payment(PaymentError::InvalidTransition {
+                status: None,
+                ..
+            }))
+        ));
+    }
+}
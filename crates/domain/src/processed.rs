@@ -0,0 +1,291 @@
+//! Processed command tracking for idempotent retries.
+//!
+//! A command whose metadata carries a [`COMMAND_ID_KEY`] is processed once.
+//! The ID is stamped on every event the command appends, and the
+//! [`CommandHandler`](crate::CommandHandler) remembers which versions of
+//! which aggregate it produced. Sending the command again, e.g. after a
+//! timeout left the caller unsure whether it went through, returns the
+//! original result instead of deciding it a second time.
+//!
+//! The index only holds recent IDs and is lost on restart; the event log is
+//! the source of truth, so an ID missing from the index is looked up in the
+//! aggregate's events before the command runs.
+//!
+//! Callers that can't thread an ID through every command, such as an HTTP
+//! request carrying an `Idempotency-Key`, run them in a [`with_command_ids`]
+//! scope instead. Commands without an ID of their own are then named after
+//! the command and the aggregate it targets, so a retried request gives each
+//! of its commands the ID it had the first time even if they run in another
+//! order. Aggregates the scope creates take their IDs from [`scoped_uuid`],
+//! so a retry targets the aggregate the first attempt created.
+
+use std::borrow::Cow;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+use common::AggregateId;
+use event_store::Version;
+use uuid::Uuid;
+
+use crate::metadata::CommandMetadata;
+
+/// Metadata key holding the caller-chosen ID of the command behind an event.
+pub const COMMAND_ID_KEY: &str = "command_id";
+
+/// How many command IDs an index keeps by default.
+pub const DEFAULT_PROCESSED_CAPACITY: usize = 10_000;
+
+/// Namespace of the aggregate IDs derived from a scope.
+const SCOPED_ID_NAMESPACE: Uuid = Uuid::from_u128(0x8c1f_2b6e_47d3_4a90_9e15_d2c7_63b8_0f4a);
+
+/// Returns the command ID in `metadata`, if it carries one.
+pub fn command_id(metadata: &CommandMetadata) -> Option<&str> {
+    metadata.get(COMMAND_ID_KEY).and_then(|v| v.as_str())
+}
+
+/// Command IDs handed out to the commands of one scope, e.g. one request.
+#[derive(Debug, Clone)]
+pub struct CommandIds {
+    base: Arc<str>,
+    /// How many IDs were issued per name, to tell repeats apart.
+    issued: Arc<Mutex<HashMap<String, u64>>>,
+}
+
+tokio::task_local! {
+    static COMMAND_IDS: CommandIds;
+}
+
+impl CommandIds {
+    /// Creates IDs `{base}:{name}#n`, where `n` counts the IDs issued for
+    /// `name` so far, starting from 1.
+    pub fn new(base: impl Into<String>) -> Self {
+        Self {
+            base: Arc::from(base.into()),
+            issued: Arc::default(),
+        }
+    }
+
+    /// Returns the IDs of the enclosing scope, if any, so a spawned task can
+    /// keep numbering from where its parent is.
+    pub fn current() -> Option<Self> {
+        COMMAND_IDS.try_with(Self::clone).ok()
+    }
+
+    fn next(&self, name: &str) -> String {
+        let mut issued = self.issued.lock().expect("command IDs poisoned");
+        let n = issued.entry(name.to_string()).or_default();
+        *n += 1;
+        format!("{}:{name}#{n}", self.base)
+    }
+}
+
+/// Runs `future` with `ids` naming the commands it executes.
+pub async fn with_command_ids<F: Future>(ids: CommandIds, future: F) -> F::Output {
+    COMMAND_IDS.scope(ids, future).await
+}
+
+/// Returns `metadata` with an ID of the enclosing scope attached, named
+/// after `command` and the aggregate it targets, unless it already carries
+/// a command ID or no scope is set.
+pub fn scoped_metadata<'a>(
+    command: &str,
+    aggregate_id: AggregateId,
+    metadata: &'a CommandMetadata,
+) -> Cow<'a, CommandMetadata> {
+    match CommandIds::current() {
+        Some(ids) if command_id(metadata).is_none() => {
+            let mut metadata = metadata.clone();
+            let id = ids.next(&format!("{command}:{aggregate_id}"));
+            metadata.insert(COMMAND_ID_KEY.to_string(), id.into());
+            Cow::Owned(metadata)
+        }
+        _ => Cow::Borrowed(metadata),
+    }
+}
+
+/// Returns an ID for a new `kind` of aggregate derived from the enclosing
+/// scope, the same each time the scope is retried, or None when no scope is
+/// set. Repeated calls in one scope return distinct IDs.
+pub fn scoped_uuid(kind: &str) -> Option<Uuid> {
+    CommandIds::current().map(|ids| {
+        let name = ids.next(&format!("new-{kind}"));
+        Uuid::new_v5(&SCOPED_ID_NAMESPACE, name.as_bytes())
+    })
+}
+
+/// Where a processed command's events sit in its aggregate's stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProcessedCommand {
+    pub aggregate_id: AggregateId,
+    /// The aggregate's version before the command.
+    pub from: Version,
+    /// The aggregate's version after the command; equal to `from` when the
+    /// command had nothing to change.
+    pub to: Version,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    by_id: HashMap<String, ProcessedCommand>,
+    order: VecDeque<String>,
+}
+
+/// Index of recently processed command IDs, shared by clones.
+///
+/// Once full, the oldest IDs are forgotten first.
+#[derive(Debug, Clone)]
+pub struct ProcessedCommands {
+    capacity: usize,
+    entries: Arc<Mutex<Entries>>,
+}
+
+impl Default for ProcessedCommands {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_PROCESSED_CAPACITY)
+    }
+}
+
+impl ProcessedCommands {
+    /// Creates an index keeping [`DEFAULT_PROCESSED_CAPACITY`] IDs.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an index keeping at most `capacity` IDs.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Arc::new(Mutex::new(Entries::default())),
+        }
+    }
+
+    /// Returns where the command with `command_id` left its events.
+    pub fn get(&self, command_id: &str) -> Option<ProcessedCommand> {
+        self.entries
+            .lock()
+            .expect("processed commands poisoned")
+            .by_id
+            .get(command_id)
+            .copied()
+    }
+
+    /// Records that the command with `command_id` was processed.
+    pub fn record(&self, command_id: &str, processed: ProcessedCommand) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().expect("processed commands poisoned");
+        if entries
+            .by_id
+            .insert(command_id.to_string(), processed)
+            .is_none()
+        {
+            entries.order.push_back(command_id.to_string());
+        }
+        while entries.order.len() > self.capacity {
+            if let Some(oldest) = entries.order.pop_front() {
+                entries.by_id.remove(&oldest);
+            }
+        }
+    }
+
+    /// Returns how many IDs are held.
+    pub fn len(&self) -> usize {
+        self.entries
+            .lock()
+            .expect("processed commands poisoned")
+            .by_id
+            .len()
+    }
+
+    /// Returns true if no IDs are held.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn processed(to: i64) -> ProcessedCommand {
+        ProcessedCommand {
+            aggregate_id: AggregateId::new(),
+            from: Version::new(to - 1),
+            to: Version::new(to),
+        }
+    }
+
+    #[test]
+    fn test_reads_command_id_from_metadata() {
+        let metadata = CommandMetadata::from([(COMMAND_ID_KEY.to_string(), "cmd-1".into())]);
+        assert_eq!(command_id(&metadata), Some("cmd-1"));
+        assert_eq!(command_id(&CommandMetadata::new()), None);
+    }
+
+    #[tokio::test]
+    async fn test_scope_names_commands_without_an_id() {
+        let order = AggregateId::new();
+        let other = AggregateId::new();
+        assert_eq!(
+            scoped_metadata("AddItem", order, &CommandMetadata::new()).get(COMMAND_ID_KEY),
+            None
+        );
+
+        let own = CommandMetadata::from([(COMMAND_ID_KEY.to_string(), "own".into())]);
+        let ids = with_command_ids(CommandIds::new("req"), async {
+            let scoped = |command, aggregate_id, metadata| {
+                command_id(&scoped_metadata(command, aggregate_id, metadata)).map(String::from)
+            };
+            [
+                scoped("AddItem", order, &CommandMetadata::new()),
+                scoped("AddItem", order, &own),
+                scoped("AddItem", other, &CommandMetadata::new()),
+                scoped("AddItem", order, &CommandMetadata::new()),
+            ]
+        })
+        .await;
+        assert_eq!(
+            ids,
+            [
+                Some(format!("req:AddItem:{order}#1")),
+                Some("own".into()),
+                Some(format!("req:AddItem:{other}#1")),
+                Some(format!("req:AddItem:{order}#2")),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_scoped_uuids_repeat_when_the_scope_is_retried() {
+        assert_eq!(scoped_uuid("order"), None);
+
+        let attempt = || {
+            with_command_ids(CommandIds::new("req"), async {
+                [scoped_uuid("order"), scoped_uuid("order")]
+            })
+        };
+        let first = attempt().await;
+        assert_eq!(first, attempt().await);
+        assert_ne!(first[0], first[1]);
+        let other = with_command_ids(CommandIds::new("other"), async { scoped_uuid("order") });
+        assert_ne!(first[0], other.await);
+    }
+
+    #[test]
+    fn test_forgets_oldest_ids_when_full() {
+        let index = ProcessedCommands::with_capacity(2);
+        let first = processed(1);
+        index.record("a", first);
+        index.record("b", processed(2));
+        // Recording an ID again doesn't make it younger
+        index.record("a", first);
+        index.record("c", processed(3));
+
+        assert_eq!(index.len(), 2);
+        assert_eq!(index.get("a"), None);
+        assert_eq!(index.get("b").map(|p| p.to), Some(Version::new(2)));
+        assert!(index.get("c").is_some());
+    }
+}
@@ -0,0 +1,216 @@
+//! Background snapshot creation.
+//!
+//! Commands never write snapshots themselves. A [`Snapshotter`] waits for
+//! the log to go quiet, then snapshots every aggregate whose events since
+//! its last snapshot reached the threshold, so long-lived aggregates stay
+//! cheap to load without adding a write to the command path.
+
+use std::time::Duration;
+
+use common::AggregateId;
+use event_store::{EventStore, EventStoreExt, Snapshot};
+use tokio_util::sync::CancellationToken;
+
+use crate::aggregate::SnapshotCapable;
+use crate::command::CommandHandler;
+use crate::error::DomainError;
+
+/// Snapshots aggregates of type `A` that have run past the threshold.
+pub struct Snapshotter<S, A>
+where
+    S: EventStore,
+    A: SnapshotCapable,
+{
+    handler: CommandHandler<S, A>,
+    threshold: u64,
+}
+
+impl<S, A> Snapshotter<S, A>
+where
+    S: EventStore,
+    A: SnapshotCapable,
+    A::Event: serde::de::DeserializeOwned,
+{
+    /// Creates a snapshotter using the aggregate's snapshot interval as the
+    /// threshold.
+    pub fn new(store: S) -> Self {
+        Self {
+            handler: CommandHandler::new(store),
+            threshold: A::snapshot_interval() as u64,
+        }
+    }
+
+    /// Snapshots aggregates once they have this many events past their last
+    /// snapshot.
+    pub fn with_threshold(mut self, events: u64) -> Self {
+        self.threshold = events.max(1);
+        self
+    }
+
+    /// Snapshots every aggregate at or past the threshold, returning how
+    /// many snapshots were written.
+    ///
+    /// An aggregate that fails to load or save is logged and skipped, so one
+    /// bad stream doesn't hold back the rest.
+    #[tracing::instrument(skip(self), fields(aggregate_type = A::aggregate_type()))]
+    pub async fn snapshot_due(&self) -> Result<usize, DomainError> {
+        let stats = self
+            .handler
+            .store()
+            .aggregate_stats(A::aggregate_type())
+            .await?;
+
+        let mut written = 0;
+        // Stats come most-behind first
+        for stats in stats
+            .iter()
+            .take_while(|s| s.events_since_snapshot() >= self.threshold)
+        {
+            match self.snapshot(stats.aggregate_id).await {
+                Ok(()) => written += 1,
+                Err(e) => tracing::warn!(
+                    aggregate_id = %stats.aggregate_id,
+                    error = %e,
+                    "background snapshot failed"
+                ),
+            }
+        }
+
+        if written > 0 {
+            metrics::counter!("snapshots_created", "aggregate_type" => A::aggregate_type())
+                .increment(written as u64);
+            tracing::info!(written, "background snapshots written");
+        }
+        Ok(written)
+    }
+
+    /// Runs [`snapshot_due`](Self::snapshot_due) in idle windows until
+    /// `shutdown` is cancelled.
+    ///
+    /// The store's head sequence is checked every `quiet_period`; when it
+    /// hasn't moved since the last check, the log has been idle that long
+    /// and a pass runs. Each idle window gets one pass.
+    pub async fn run_when_idle(&self, quiet_period: Duration, shutdown: CancellationToken) {
+        let mut last_head = None;
+        let mut snapshotted_at = None;
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => return,
+                _ = tokio::time::sleep(quiet_period) => {}
+            }
+
+            let head = match self.handler.store().head_sequence().await {
+                Ok(head) => head,
+                Err(e) => {
+                    tracing::warn!(error = %e, "snapshotter could not read head sequence");
+                    continue;
+                }
+            };
+            if last_head == Some(head) && snapshotted_at != Some(head) {
+                if let Err(e) = self.snapshot_due().await {
+                    tracing::warn!(error = %e, "background snapshot pass failed");
+                }
+                snapshotted_at = Some(head);
+            }
+            last_head = Some(head);
+        }
+    }
+
+    async fn snapshot(&self, aggregate_id: AggregateId) -> Result<(), DomainError> {
+        let aggregate = self.handler.load(aggregate_id).await?;
+        let snapshot = Snapshot::from_state(
+            aggregate_id,
+            A::aggregate_type(),
+            aggregate.version(),
+            &aggregate,
+        )?;
+        self.handler.store().save_snapshot(snapshot).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::order::{AddItem, CreateOrder, Order, OrderItem, OrderService};
+    use crate::{CustomerId, Money};
+    use event_store::{FaultyEventStore, InMemoryEventStore, StoreOperation, Version};
+
+    async fn order_with_items(service: &OrderService<impl EventStore>, items: u32) -> AggregateId {
+        let cmd = CreateOrder::for_customer(CustomerId::new());
+        let order_id = cmd.order_id;
+        service.create_order(cmd).await.unwrap();
+        for i in 0..items {
+            let item = OrderItem::new(format!("SKU-{i}"), "Widget", 1, Money::from_cents(100));
+            service
+                .add_item(AddItem::new(order_id, item))
+                .await
+                .unwrap();
+        }
+        order_id
+    }
+
+    #[tokio::test]
+    async fn test_snapshots_only_aggregates_past_threshold() {
+        let store = InMemoryEventStore::new();
+        let service = OrderService::new(store.clone());
+        let busy = order_with_items(&service, 9).await;
+        let quiet = order_with_items(&service, 2).await;
+
+        let snapshotter = Snapshotter::<_, Order>::new(store.clone()).with_threshold(5);
+        assert_eq!(snapshotter.snapshot_due().await.unwrap(), 1);
+
+        let snapshot = store.get_snapshot(busy).await.unwrap().unwrap();
+        assert_eq!(snapshot.version, Version::new(10));
+        assert!(store.get_snapshot(quiet).await.unwrap().is_none());
+
+        // Caught up; nothing more to do until more events arrive
+        assert_eq!(snapshotter.snapshot_due().await.unwrap(), 0);
+        let order = service.get_order(busy).await.unwrap().unwrap();
+        assert_eq!(order.items().count(), 9);
+    }
+
+    #[tokio::test]
+    async fn test_failed_snapshot_is_skipped() {
+        let store = FaultyEventStore::new(InMemoryEventStore::new());
+        let service = OrderService::new(store.clone());
+        for _ in 0..2 {
+            order_with_items(&service, 5).await;
+        }
+
+        store.fail_next(StoreOperation::SaveSnapshot, 1);
+        let snapshotter = Snapshotter::<_, Order>::new(store.clone()).with_threshold(5);
+        assert_eq!(snapshotter.snapshot_due().await.unwrap(), 1);
+        assert_eq!(snapshotter.snapshot_due().await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_runs_once_log_is_idle() {
+        let store = InMemoryEventStore::new();
+        let service = OrderService::new(store.clone());
+        let order_id = order_with_items(&service, 5).await;
+
+        let snapshotter = Snapshotter::<_, Order>::new(store.clone()).with_threshold(5);
+        let shutdown = CancellationToken::new();
+        let task = tokio::spawn({
+            let shutdown = shutdown.clone();
+            async move {
+                snapshotter
+                    .run_when_idle(Duration::from_millis(20), shutdown)
+                    .await
+            }
+        });
+
+        // The first check only records the head; a later one finds it idle
+        let written = tokio::time::timeout(Duration::from_secs(2), async {
+            while store.get_snapshot(order_id).await.unwrap().is_none() {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await;
+        assert!(written.is_ok());
+
+        shutdown.cancel();
+        task.await.unwrap();
+    }
+}
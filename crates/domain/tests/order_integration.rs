@@ -3,11 +3,14 @@
 //! These tests verify the full order lifecycle including event persistence,
 //! aggregate reconstruction, and concurrency handling.
 
+use std::collections::BTreeMap;
+
 use common::AggregateId;
 use domain::{
     AddItem, Aggregate, CancelOrder, CompleteOrder, CreateOrder, CustomerId, DomainError,
-    DomainEvent, MarkReserved, Money, OrderError, OrderEvent, OrderItem, OrderService, OrderState,
-    ProductId, StartProcessing, SubmitOrder,
+    DomainEvent, MarkDelivered, MarkReserved, MarkShipped, Money, OrderError, OrderEvent,
+    OrderItem, OrderService, OrderState, ProductId, RecordDeliveryFailure, StartProcessing,
+    SubmitOrder,
 };
 use event_store::{EventStore, EventStoreError, InMemoryEventStore, Version};
 
@@ -62,10 +65,19 @@ mod order_lifecycle {
 
         // Reserve inventory
         let result = service
-            .mark_reserved(MarkReserved::new(order_id, Some("RES-123".to_string())))
+            .mark_reserved(
+                MarkReserved::new(order_id).with_reservations(BTreeMap::from([
+                    (ProductId::new("SKU-001"), "RES-123".to_string()),
+                    (ProductId::new("SKU-002"), "RES-124".to_string()),
+                ])),
+            )
             .await
             .unwrap();
         assert_eq!(result.aggregate.state(), OrderState::Reserved);
+        assert_eq!(
+            result.aggregate.reservation(&ProductId::new("SKU-002")),
+            Some("RES-124")
+        );
 
         // Start processing
         let result = service
@@ -81,6 +93,39 @@ mod order_lifecycle {
             .unwrap();
 
         assert_eq!(result.aggregate.state(), OrderState::Completed);
+        assert!(!result.aggregate.is_terminal());
+
+        // Carrier picks up, fails once, then delivers
+        let result = service
+            .mark_shipped(MarkShipped::new(
+                order_id,
+                "UPS",
+                Some("TRACK-789".to_string()),
+                chrono::Utc::now(),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(result.aggregate.state(), OrderState::Shipped);
+
+        let result = service
+            .record_delivery_failure(RecordDeliveryFailure::new(
+                order_id,
+                "Recipient unavailable",
+                chrono::Utc::now(),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(result.aggregate.state(), OrderState::DeliveryFailed);
+
+        service
+            .mark_shipped(MarkShipped::new(order_id, "UPS", None, chrono::Utc::now()))
+            .await
+            .unwrap();
+        let result = service
+            .mark_delivered(MarkDelivered::new(order_id, None, chrono::Utc::now()))
+            .await
+            .unwrap();
+        assert_eq!(result.aggregate.state(), OrderState::Delivered);
         assert!(result.aggregate.is_terminal());
     }
 
@@ -132,7 +177,7 @@ mod order_lifecycle {
             .unwrap();
 
         service
-            .mark_reserved(MarkReserved::new(order_id2, None))
+            .mark_reserved(MarkReserved::new(order_id2))
             .await
             .unwrap();
 
@@ -172,7 +217,7 @@ mod order_lifecycle {
             .unwrap();
 
         service
-            .mark_reserved(MarkReserved::new(order_id, None))
+            .mark_reserved(MarkReserved::new(order_id))
             .await
             .unwrap();
 
@@ -308,6 +353,68 @@ mod concurrency {
         assert_eq!(result.aggregate.item_count(), 2);
         assert_eq!(result.aggregate.total_amount().cents(), 1500);
     }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_restocks_are_not_lost() {
+        use domain::{ContentionTracker, InventoryItemService, RestockItem};
+        use std::sync::Arc;
+
+        const WRITERS: u32 = 16;
+
+        let tracker = ContentionTracker::new();
+        let service = Arc::new(
+            InventoryItemService::new(InMemoryEventStore::new())
+                .with_contention_tracker(tracker.clone()),
+        );
+        let barrier = Arc::new(tokio::sync::Barrier::new(WRITERS as usize));
+
+        // Every writer starts at once, so appends race on the same stream
+        let handles: Vec<_> = (1..=WRITERS)
+            .map(|quantity| {
+                let service = service.clone();
+                let barrier = barrier.clone();
+                tokio::spawn(async move {
+                    barrier.wait().await;
+                    let result = service
+                        .restock(RestockItem::new("SKU-HOT", quantity, None))
+                        .await;
+                    (quantity, result)
+                })
+            })
+            .collect();
+
+        let mut landed = 0u64;
+        let mut gave_up = 0u64;
+        for handle in handles {
+            match handle.await.unwrap() {
+                (quantity, Ok(_)) => landed += u64::from(quantity),
+                (_, Err(DomainError::EventStore(EventStoreError::ConcurrencyConflict { .. }))) => {
+                    gave_up += 1
+                }
+                (_, Err(e)) => panic!("unexpected error: {e}"),
+            }
+        }
+
+        // Every restock either landed or was reported as a conflict
+        let item = service
+            .get_item(&ProductId::new("SKU-HOT"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(item.total_received(), landed);
+
+        // A writer that gave up conflicted on each of its attempts
+        let report = tracker.report(1);
+        let conflicts = report
+            .conflicts_by_type
+            .get("InventoryItem")
+            .copied()
+            .unwrap_or(0);
+        assert!(conflicts >= gave_up * 3);
+        if conflicts > 0 {
+            assert_eq!(report.hot_aggregates[0].conflicts, conflicts);
+        }
+    }
 }
 
 mod error_handling {
@@ -339,7 +446,7 @@ mod error_handling {
             .unwrap();
 
         service
-            .mark_reserved(MarkReserved::new(order_id, None))
+            .mark_reserved(MarkReserved::new(order_id))
             .await
             .unwrap();
 
@@ -419,7 +526,7 @@ mod error_handling {
             .unwrap();
 
         service
-            .mark_reserved(MarkReserved::new(order_id, None))
+            .mark_reserved(MarkReserved::new(order_id))
             .await
             .unwrap();
 
@@ -470,7 +577,7 @@ mod error_handling {
 
 mod item_management {
     use super::*;
-    use domain::UpdateItemQuantity;
+    use domain::{UpdateItemPrice, UpdateItemQuantity};
 
     #[tokio::test]
     async fn adding_same_product_increases_quantity() {
@@ -540,6 +647,42 @@ mod item_management {
         assert_eq!(result.aggregate.total_amount().cents(), 0);
     }
 
+    #[tokio::test]
+    async fn update_price_recalculates_total() {
+        let service = create_service();
+
+        let customer_id = CustomerId::new();
+        let order_id = AggregateId::new();
+
+        service
+            .create_order(CreateOrder::new(order_id, customer_id))
+            .await
+            .unwrap();
+
+        service
+            .add_item(AddItem::new(
+                order_id,
+                OrderItem::new("SKU-001", "Widget", 3, Money::from_cents(1000)),
+            ))
+            .await
+            .unwrap();
+
+        let result = service
+            .update_item_price(UpdateItemPrice::new(
+                order_id,
+                "SKU-001",
+                Money::from_cents(800),
+            ))
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            result.events.as_slice(),
+            [OrderEvent::ItemPriceAdjusted(_)]
+        ));
+        assert_eq!(result.aggregate.total_amount().cents(), 2400);
+    }
+
     #[tokio::test]
     async fn total_calculation_with_multiple_items() {
         let service = create_service();
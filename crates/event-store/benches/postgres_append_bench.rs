@@ -0,0 +1,116 @@
+//! PostgreSQL append throughput for multi-event commands.
+//!
+//! Compares `PostgresEventStore::append`, which writes a batch in a single
+//! statement, with inserting the same events one statement at a time. Starts
+//! a PostgreSQL container, so Docker must be running:
+//!
+//! ```bash
+//! cargo bench -p event-store --bench postgres_append_bench
+//! ```
+
+use common::AggregateId;
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use event_store::{AppendOptions, EventEnvelope, PostgresEventStore, Version, store::EventStore};
+use sqlx::PgPool;
+use testcontainers::{ImageExt, core::IntoContainerPort, runners::AsyncRunner};
+use testcontainers_modules::postgres::Postgres;
+
+fn make_event(aggregate_id: AggregateId, version: i64) -> EventEnvelope {
+    EventEnvelope::builder()
+        .aggregate_id(aggregate_id)
+        .aggregate_type("Order")
+        .event_type("ItemAdded")
+        .version(Version::new(version))
+        .payload_raw(serde_json::json!({
+            "type": "ItemAdded",
+            "data": {
+                "product_id": format!("SKU-{version:03}"),
+                "product_name": "Widget",
+                "quantity": 1,
+                "unit_price": 1000
+            }
+        }))
+        .build()
+}
+
+/// Appends `events` with one INSERT per event, as the store did before
+/// batching.
+async fn append_row_by_row(pool: &PgPool, events: &[EventEnvelope]) {
+    let mut tx = pool.begin().await.unwrap();
+    for event in events {
+        sqlx::query(
+            r#"
+            INSERT INTO events (id, event_type, aggregate_id, aggregate_type, version, timestamp, payload, metadata)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+        )
+        .bind(event.event_id.as_uuid())
+        .bind(&event.event_type)
+        .bind(event.aggregate_id.as_uuid())
+        .bind(&event.aggregate_type)
+        .bind(event.version.as_i64())
+        .bind(event.timestamp)
+        .bind(&event.payload)
+        .bind(serde_json::to_value(&event.metadata).unwrap())
+        .execute(&mut *tx)
+        .await
+        .unwrap();
+    }
+    tx.commit().await.unwrap();
+}
+
+fn bench_multi_event_append(c: &mut Criterion) {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let (_container, pool) = rt.block_on(async {
+        let container = Postgres::default()
+            .with_tag("18-alpine")
+            .start()
+            .await
+            .expect("failed to start PostgreSQL container");
+        let host = container.get_host().await.unwrap();
+        let port = container.get_host_port_ipv4(5432.tcp()).await.unwrap();
+        let pool = PgPool::connect(&format!(
+            "postgres://postgres:postgres@{host}:{port}/postgres"
+        ))
+        .await
+        .unwrap();
+        PostgresEventStore::new(pool.clone())
+            .run_migrations()
+            .await
+            .unwrap();
+        (container, pool)
+    });
+    let store = PostgresEventStore::new(pool.clone());
+
+    let mut group = c.benchmark_group("postgres/append");
+    for batch_size in [1_i64, 10, 100] {
+        group.throughput(Throughput::Elements(batch_size as u64));
+        group.bench_with_input(
+            BenchmarkId::new("batched", batch_size),
+            &batch_size,
+            |b, &n| {
+                b.iter(|| {
+                    let agg_id = AggregateId::new();
+                    let events = (1..=n).map(|v| make_event(agg_id, v)).collect();
+                    rt.block_on(store.append(events, AppendOptions::expect_new()))
+                        .unwrap();
+                });
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("row_by_row", batch_size),
+            &batch_size,
+            |b, &n| {
+                b.iter(|| {
+                    let agg_id = AggregateId::new();
+                    let events: Vec<_> = (1..=n).map(|v| make_event(agg_id, v)).collect();
+                    rt.block_on(append_row_by_row(&pool, &events));
+                });
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_multi_event_append);
+criterion_main!(benches);
@@ -0,0 +1,126 @@
+//! Operator notes on past events.
+//!
+//! Events never change once appended, so what operators learn about one
+//! afterwards, such as "this is where the bug started" during an incident
+//! review, is kept beside the log rather than in it. Annotations are keyed
+//! by event ID; aggregates and projections never see them.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::{EventId, Result};
+
+/// A note an operator attached to an event.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventAnnotation {
+    pub annotation_id: Uuid,
+    pub event_id: EventId,
+    /// Who wrote the note.
+    pub author: String,
+    pub note: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl EventAnnotation {
+    /// Creates an annotation on `event_id`, written now.
+    pub fn new(event_id: EventId, author: impl Into<String>, note: impl Into<String>) -> Self {
+        Self {
+            annotation_id: Uuid::new_v4(),
+            event_id,
+            author: author.into(),
+            note: note.into(),
+            created_at: Utc::now(),
+        }
+    }
+}
+
+/// Storage for event annotations.
+#[async_trait]
+pub trait AnnotationStore: Send + Sync {
+    /// Stores an annotation alongside any others on its event.
+    async fn add_annotation(&self, annotation: EventAnnotation) -> Result<()>;
+
+    /// Returns the annotations on any of `event_ids`, oldest first.
+    async fn get_annotations(&self, event_ids: &[EventId]) -> Result<Vec<EventAnnotation>>;
+}
+
+/// Annotations held in memory, lost on restart.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryAnnotationStore {
+    annotations: Arc<RwLock<HashMap<EventId, Vec<EventAnnotation>>>>,
+}
+
+impl InMemoryAnnotationStore {
+    /// Creates an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl AnnotationStore for InMemoryAnnotationStore {
+    async fn add_annotation(&self, annotation: EventAnnotation) -> Result<()> {
+        self.annotations
+            .write()
+            .await
+            .entry(annotation.event_id)
+            .or_default()
+            .push(annotation);
+        Ok(())
+    }
+
+    async fn get_annotations(&self, event_ids: &[EventId]) -> Result<Vec<EventAnnotation>> {
+        let annotations = self.annotations.read().await;
+        let requested: HashSet<_> = event_ids.iter().collect();
+        let mut found: Vec<_> = requested
+            .into_iter()
+            .filter_map(|event_id| annotations.get(event_id))
+            .flatten()
+            .cloned()
+            .collect();
+        found.sort_by_key(|annotation| (annotation.created_at, annotation.annotation_id));
+        Ok(found)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_annotations_returned_for_requested_events() {
+        let store = InMemoryAnnotationStore::new();
+        let (first, second, other) = (EventId::new(), EventId::new(), EventId::new());
+        let started = EventAnnotation::new(first, "alice", "Bug starts here");
+        let fixed = EventAnnotation::new(second, "bob", "Fixed by the retry");
+        store.add_annotation(started.clone()).await.unwrap();
+        store.add_annotation(fixed.clone()).await.unwrap();
+        store
+            .add_annotation(EventAnnotation::new(other, "alice", "Unrelated"))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store.get_annotations(&[second, first]).await.unwrap(),
+            [started.clone(), fixed]
+        );
+        // Asking for an event twice doesn't repeat its notes
+        assert_eq!(
+            store.get_annotations(&[first, first]).await.unwrap(),
+            [started]
+        );
+        assert!(
+            store
+                .get_annotations(&[EventId::new()])
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
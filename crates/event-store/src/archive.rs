@@ -0,0 +1,156 @@
+//! Event archival for compacting the live event log.
+//!
+//! Compaction moves events that are already covered by an aggregate snapshot
+//! out of the live store and into an [`ArchiveSink`]. Aggregates still load
+//! from the snapshot plus the remaining events, and full projection rebuilds
+//! read the archive back alongside the live log.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use futures_util::StreamExt;
+use tokio::sync::RwLock;
+
+use crate::{EventEnvelope, Result, store::EventStream};
+
+/// Destination for events removed from the live store by compaction.
+#[async_trait]
+pub trait ArchiveSink: Send + Sync {
+    /// Persists archived events.
+    ///
+    /// Must be durable before returning: the caller deletes the events from
+    /// the live store afterwards.
+    async fn archive(&self, events: Vec<EventEnvelope>) -> Result<()>;
+
+    /// Streams all archived events in global sequence order.
+    async fn stream_archived(&self) -> Result<EventStream>;
+
+    /// Returns the highest global sequence in the archive, or 0 if empty.
+    async fn archived_through(&self) -> Result<u64>;
+}
+
+/// In-memory archive, for testing and single-process deployments.
+#[derive(Clone, Default)]
+pub struct InMemoryArchive {
+    events: Arc<RwLock<Vec<EventEnvelope>>>,
+}
+
+impl InMemoryArchive {
+    /// Creates an empty archive.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of archived events.
+    pub async fn event_count(&self) -> usize {
+        self.events.read().await.len()
+    }
+}
+
+#[async_trait]
+impl ArchiveSink for InMemoryArchive {
+    async fn archive(&self, events: Vec<EventEnvelope>) -> Result<()> {
+        let mut archived = self.events.write().await;
+        archived.extend(events);
+        archived.sort_by_key(|e| e.sequence);
+        archived.dedup_by_key(|e| e.event_id);
+        Ok(())
+    }
+
+    async fn stream_archived(&self) -> Result<EventStream> {
+        let events = self.events.read().await.clone();
+        Ok(Box::pin(futures_util::stream::iter(
+            events.into_iter().map(Ok),
+        )))
+    }
+
+    async fn archived_through(&self) -> Result<u64> {
+        Ok(self
+            .events
+            .read()
+            .await
+            .last()
+            .and_then(|e| e.sequence)
+            .unwrap_or(0))
+    }
+}
+
+/// Merges two streams that are each in global sequence order into one.
+///
+/// Events without a sequence are emitted as soon as they are reached.
+pub fn merge_by_sequence(left: EventStream, right: EventStream) -> EventStream {
+    let state = (left.peekable(), right.peekable());
+    let merged = futures_util::stream::unfold(state, |(mut left, mut right)| async move {
+        let take_left = match (
+            std::pin::Pin::new(&mut left).peek().await,
+            std::pin::Pin::new(&mut right).peek().await,
+        ) {
+            (None, None) => return None,
+            (Some(_), None) => true,
+            (None, Some(_)) => false,
+            (Some(Ok(l)), Some(Ok(r))) => l.sequence.unwrap_or(0) <= r.sequence.unwrap_or(0),
+            // Surface errors as soon as they are seen
+            (Some(Err(_)), _) => true,
+            (_, Some(Err(_))) => false,
+        };
+        let next = if take_left {
+            left.next().await
+        } else {
+            right.next().await
+        };
+        next.map(|item| (item, (left, right)))
+    });
+    Box::pin(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AggregateId, Version};
+
+    fn event_at(sequence: u64) -> EventEnvelope {
+        let mut event = EventEnvelope::builder()
+            .aggregate_id(AggregateId::new())
+            .aggregate_type("TestAggregate")
+            .event_type("TestEvent")
+            .version(Version::first())
+            .payload_raw(serde_json::json!({}))
+            .build();
+        event.sequence = Some(sequence);
+        event
+    }
+
+    fn stream_of(sequences: &[u64]) -> EventStream {
+        let events: Vec<_> = sequences.iter().map(|s| Ok(event_at(*s))).collect();
+        Box::pin(futures_util::stream::iter(events))
+    }
+
+    #[tokio::test]
+    async fn merge_interleaves_by_sequence() {
+        let merged = merge_by_sequence(stream_of(&[1, 3, 4]), stream_of(&[2, 5]));
+        let sequences: Vec<_> = merged.map(|e| e.unwrap().sequence.unwrap()).collect().await;
+        assert_eq!(sequences, vec![1, 2, 3, 4, 5]);
+    }
+
+    #[tokio::test]
+    async fn archive_keeps_sequence_order() {
+        let archive = InMemoryArchive::new();
+        archive
+            .archive(vec![event_at(4), event_at(2)])
+            .await
+            .unwrap();
+        archive.archive(vec![event_at(1)]).await.unwrap();
+
+        assert_eq!(archive.event_count().await, 3);
+        assert_eq!(archive.archived_through().await.unwrap(), 4);
+
+        let sequences: Vec<_> = archive
+            .stream_archived()
+            .await
+            .unwrap()
+            .map(|e| e.unwrap().sequence.unwrap())
+            .collect()
+            .await;
+        assert_eq!(sequences, vec![1, 2, 4]);
+    }
+}
@@ -0,0 +1,95 @@
+//! CloudEvents 1.0 JSON format for stored events.
+//!
+//! Each [`EventEnvelope`] maps to one [`CloudEvent`]: the event ID becomes
+//! `id`, the event type `type`, the aggregate ID `subject`, and the payload
+//! `data`. Aggregate type, version, and log position travel as extension
+//! attributes. Envelope metadata is not carried.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::EventEnvelope;
+
+/// CloudEvents specification version produced.
+pub const SPEC_VERSION: &str = "1.0";
+
+/// Media type of a single event in structured JSON mode.
+pub const CLOUDEVENTS_JSON: &str = "application/cloudevents+json";
+
+/// Media type of a JSON array of events.
+pub const CLOUDEVENTS_BATCH_JSON: &str = "application/cloudevents-batch+json";
+
+/// A stored event in CloudEvents structured JSON form.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CloudEvent {
+    pub specversion: String,
+    pub id: String,
+    /// Context the event happened in, e.g. `/orders`.
+    pub source: String,
+    #[serde(rename = "type")]
+    pub event_type: String,
+    /// ID of the aggregate the event belongs to.
+    pub subject: String,
+    pub time: DateTime<Utc>,
+    pub datacontenttype: String,
+    pub data: serde_json::Value,
+    /// Extension: type of the aggregate, e.g. `Order`.
+    pub aggregatetype: String,
+    /// Extension: version of the aggregate after the event.
+    pub aggregateversion: i64,
+    /// Extension: position in the store's global log, once stored.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sequence: Option<u64>,
+}
+
+impl CloudEvent {
+    /// Formats `envelope` as a CloudEvent from `source`.
+    pub fn from_envelope(envelope: &EventEnvelope, source: impl Into<String>) -> Self {
+        Self {
+            specversion: SPEC_VERSION.to_string(),
+            id: envelope.event_id.to_string(),
+            source: source.into(),
+            event_type: envelope.event_type.clone(),
+            subject: envelope.aggregate_id.to_string(),
+            time: envelope.timestamp,
+            datacontenttype: "application/json".to_string(),
+            data: envelope.payload.clone(),
+            aggregatetype: envelope.aggregate_type.clone(),
+            aggregateversion: envelope.version.as_i64(),
+            sequence: envelope.sequence,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AggregateId, Version};
+
+    #[test]
+    fn test_envelope_maps_to_required_attributes() {
+        let aggregate_id = AggregateId::new();
+        let mut envelope = EventEnvelope::builder()
+            .aggregate_id(aggregate_id)
+            .aggregate_type("Order")
+            .event_type("OrderCreated")
+            .version(Version::new(1))
+            .payload(&serde_json::json!({ "customer_id": "c-1" }))
+            .unwrap()
+            .build();
+        envelope.sequence = Some(42);
+
+        let event = CloudEvent::from_envelope(&envelope, "/orders");
+        let json = serde_json::to_value(&event).unwrap();
+
+        assert_eq!(json["specversion"], "1.0");
+        assert_eq!(json["id"], envelope.event_id.to_string());
+        assert_eq!(json["source"], "/orders");
+        assert_eq!(json["type"], "OrderCreated");
+        assert_eq!(json["subject"], aggregate_id.to_string());
+        assert_eq!(json["data"]["customer_id"], "c-1");
+        assert_eq!(json["aggregateversion"], 1);
+        assert_eq!(json["sequence"], 42);
+        assert_eq!(serde_json::from_value::<CloudEvent>(json).unwrap(), event);
+    }
+}
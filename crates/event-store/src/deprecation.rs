@@ -0,0 +1,282 @@
+//! Telemetry for reads of deprecated event schema versions.
+//!
+//! An event records the schema version of its payload under the
+//! [`EVENT_VERSION_KEY`] metadata key; events without it are version 1.
+//! Once a version of an event type is deprecated on a [`SchemaDeprecations`]
+//! registry, every read of such an event while loading an aggregate or
+//! feeding a projection is counted in
+//! `deprecated_event_reads{event_type,version,source}` and in the registry's
+//! report. When a version's reads stay at zero, nothing still depends on its
+//! upcaster and it can be removed.
+
+use std::collections::{BTreeMap, HashSet};
+use std::sync::{Arc, Mutex, RwLock};
+
+use chrono::{DateTime, Utc};
+use common::AggregateId;
+use serde::Serialize;
+
+use crate::EventEnvelope;
+
+/// Metadata key holding the schema version of an event's payload.
+pub const EVENT_VERSION_KEY: &str = "event_version";
+
+/// Returns the schema version an event was written with.
+pub fn event_version(envelope: &EventEnvelope) -> u32 {
+    envelope
+        .metadata
+        .get(EVENT_VERSION_KEY)
+        .and_then(|value| value.as_u64())
+        .and_then(|version| u32::try_from(version).ok())
+        .unwrap_or(1)
+}
+
+/// What read an event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadSource {
+    /// An aggregate was loaded to handle a command or a query.
+    Load,
+    /// A projection was fed the event.
+    Projection,
+}
+
+impl ReadSource {
+    fn as_str(self) -> &'static str {
+        match self {
+            ReadSource::Load => "load",
+            ReadSource::Projection => "projection",
+        }
+    }
+}
+
+/// Reads of one deprecated schema version.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DeprecatedVersionUsage {
+    pub event_type: String,
+    pub version: u32,
+    pub load_reads: u64,
+    pub projection_reads: u64,
+    pub last_read_at: Option<DateTime<Utc>>,
+    /// Aggregates seen with events at this version, when aggregate
+    /// tracking is on.
+    pub aggregates: Vec<AggregateId>,
+}
+
+/// Reads of every deprecated schema version since startup.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DeprecationReport {
+    pub generated_at: DateTime<Utc>,
+    /// Whether `aggregates` is being filled in.
+    pub tracking_aggregates: bool,
+    /// One entry per deprecated version, ordered by event type and version.
+    pub versions: Vec<DeprecatedVersionUsage>,
+}
+
+#[derive(Debug, Default)]
+struct Usage {
+    load_reads: u64,
+    projection_reads: u64,
+    last_read_at: Option<DateTime<Utc>>,
+    aggregates: HashSet<AggregateId>,
+}
+
+/// Deprecated event schema versions and how often they're still read.
+///
+/// Cloning shares the registry, so one registry can be attached to the
+/// command handlers and the projection processor and reported from the
+/// admin API.
+#[derive(Debug, Clone, Default)]
+pub struct SchemaDeprecations {
+    deprecated: Arc<RwLock<HashSet<(String, u32)>>>,
+    usage: Arc<Mutex<BTreeMap<(String, u32), Usage>>>,
+    track_aggregates: bool,
+}
+
+impl SchemaDeprecations {
+    /// Creates a registry with no deprecated versions.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remembers which aggregates still have events at deprecated versions,
+    /// logging each one the first time it's seen.
+    ///
+    /// Memory grows with the number of such aggregates, so this is meant
+    /// for finding the last few stragglers rather than running always.
+    pub fn with_aggregate_tracking(mut self) -> Self {
+        self.track_aggregates = true;
+        self
+    }
+
+    /// Marks `version` of `event_type` as deprecated.
+    pub fn deprecate(&self, event_type: impl Into<String>, version: u32) {
+        let key = (event_type.into(), version);
+        self.usage
+            .lock()
+            .expect("deprecation usage poisoned")
+            .entry(key.clone())
+            .or_default();
+        self.deprecated
+            .write()
+            .expect("deprecations poisoned")
+            .insert(key);
+    }
+
+    /// Returns true if `version` of `event_type` is deprecated.
+    pub fn is_deprecated(&self, event_type: &str, version: u32) -> bool {
+        let deprecated = self.deprecated.read().expect("deprecations poisoned");
+        !deprecated.is_empty() && deprecated.contains(&(event_type.to_string(), version))
+    }
+
+    /// Records that `source` read an event, if its version is deprecated.
+    pub fn record_read(&self, envelope: &EventEnvelope, source: ReadSource) {
+        let version = event_version(envelope);
+        if !self.is_deprecated(&envelope.event_type, version) {
+            return;
+        }
+
+        metrics::counter!(
+            "deprecated_event_reads",
+            "event_type" => envelope.event_type.clone(),
+            "version" => version.to_string(),
+            "source" => source.as_str()
+        )
+        .increment(1);
+
+        let mut usage = self.usage.lock().expect("deprecation usage poisoned");
+        let entry = usage
+            .entry((envelope.event_type.clone(), version))
+            .or_default();
+        match source {
+            ReadSource::Load => entry.load_reads += 1,
+            ReadSource::Projection => entry.projection_reads += 1,
+        }
+        entry.last_read_at = Some(Utc::now());
+        if self.track_aggregates && entry.aggregates.insert(envelope.aggregate_id) {
+            tracing::warn!(
+                aggregate_id = %envelope.aggregate_id,
+                aggregate_type = %envelope.aggregate_type,
+                event_type = %envelope.event_type,
+                version,
+                "aggregate still has a deprecated event schema version"
+            );
+        }
+    }
+
+    /// Returns the reads of each deprecated version so far.
+    pub fn report(&self) -> DeprecationReport {
+        let usage = self.usage.lock().expect("deprecation usage poisoned");
+        DeprecationReport {
+            generated_at: Utc::now(),
+            tracking_aggregates: self.track_aggregates,
+            versions: usage
+                .iter()
+                .map(|((event_type, version), usage)| {
+                    let mut aggregates: Vec<_> = usage.aggregates.iter().copied().collect();
+                    aggregates.sort_by_key(|id| id.as_uuid());
+                    DeprecatedVersionUsage {
+                        event_type: event_type.clone(),
+                        version: *version,
+                        load_reads: usage.load_reads,
+                        projection_reads: usage.projection_reads,
+                        last_read_at: usage.last_read_at,
+                        aggregates,
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /// Parses a comma-separated list of `EventType:version` entries, e.g.
+    /// `ItemAdded:1,OrderCreated:2`.
+    pub fn parse_list(list: &str) -> Result<Vec<(String, u32)>, String> {
+        list.split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let (event_type, version) = entry
+                    .split_once(':')
+                    .ok_or_else(|| format!("expected EventType:version, got {entry:?}"))?;
+                let version = version
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("invalid version in {entry:?}"))?;
+                Ok((event_type.trim().to_string(), version))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Version;
+
+    fn event(event_type: &str, aggregate_id: AggregateId, version: Option<u32>) -> EventEnvelope {
+        let mut builder = EventEnvelope::builder()
+            .aggregate_id(aggregate_id)
+            .aggregate_type("Order")
+            .event_type(event_type)
+            .version(Version::new(1))
+            .payload_raw(serde_json::json!({}));
+        if let Some(version) = version {
+            builder = builder.metadata(EVENT_VERSION_KEY, serde_json::json!(version));
+        }
+        builder.build()
+    }
+
+    #[test]
+    fn test_events_without_a_version_are_version_one() {
+        let id = AggregateId::new();
+        assert_eq!(event_version(&event("ItemAdded", id, None)), 1);
+        assert_eq!(event_version(&event("ItemAdded", id, Some(3))), 3);
+    }
+
+    #[test]
+    fn test_only_deprecated_versions_are_counted() {
+        let deprecations = SchemaDeprecations::new().with_aggregate_tracking();
+        deprecations.deprecate("ItemAdded", 1);
+        deprecations.deprecate("OrderCreated", 1);
+
+        let old = AggregateId::new();
+        deprecations.record_read(&event("ItemAdded", old, None), ReadSource::Load);
+        deprecations.record_read(&event("ItemAdded", old, None), ReadSource::Projection);
+        deprecations.record_read(&event("ItemAdded", old, None), ReadSource::Projection);
+        deprecations.record_read(
+            &event("ItemAdded", AggregateId::new(), Some(2)),
+            ReadSource::Load,
+        );
+        deprecations.record_read(&event("OrderSubmitted", old, None), ReadSource::Load);
+
+        let report = deprecations.report();
+        assert!(report.tracking_aggregates);
+        assert_eq!(report.versions.len(), 2);
+        let item_added = &report.versions[0];
+        assert_eq!(
+            (item_added.event_type.as_str(), item_added.version),
+            ("ItemAdded", 1)
+        );
+        assert_eq!((item_added.load_reads, item_added.projection_reads), (1, 2));
+        assert_eq!(item_added.aggregates, vec![old]);
+        assert!(item_added.last_read_at.is_some());
+
+        // Deprecated but unread: safe to drop its upcaster
+        let order_created = &report.versions[1];
+        assert_eq!(order_created.load_reads + order_created.projection_reads, 0);
+        assert!(order_created.last_read_at.is_none());
+    }
+
+    #[test]
+    fn test_parse_list() {
+        assert_eq!(
+            SchemaDeprecations::parse_list("ItemAdded:1, OrderCreated:2,").unwrap(),
+            vec![
+                ("ItemAdded".to_string(), 1),
+                ("OrderCreated".to_string(), 2)
+            ]
+        );
+        assert!(SchemaDeprecations::parse_list("ItemAdded").is_err());
+        assert!(SchemaDeprecations::parse_list("ItemAdded:v1").is_err());
+    }
+}
@@ -1,6 +1,10 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
 use thiserror::Error;
 
-use crate::{AggregateId, Version};
+use crate::pending::PendingBatchId;
+use crate::quota::QuotaExceeded;
+use crate::{AggregateId, EventEnvelope, EventId, Version};
 
 /// Errors that can occur when interacting with the event store.
 #[derive(Debug, Error)]
@@ -14,6 +18,10 @@ pub enum EventStoreError {
         aggregate_id: AggregateId,
         expected: Version,
         actual: Version,
+        /// Events appended after `expected`, once looked up with
+        /// [`describe_conflict`](crate::EventStoreExt::describe_conflict);
+        /// empty until then.
+        conflicting: Vec<ConflictingEvent>,
     },
 
     /// The aggregate was not found in the event store.
@@ -28,10 +36,51 @@ pub enum EventStoreError {
     #[error("Migration error: {0}")]
     Migration(#[from] sqlx::migrate::MigrateError),
 
+    /// No pending batch with this ID is staged.
+    #[error("Pending batch not found: {0}")]
+    PendingBatchNotFound(PendingBatchId),
+
+    /// The store does not accept writes (e.g. a time-travel view).
+    #[error("Event store is read-only: {0}")]
+    ReadOnly(String),
+
+    /// The append would exceed a configured quota.
+    #[error("Quota exceeded: {0}")]
+    QuotaExceeded(#[from] QuotaExceeded),
+
+    /// A read given a session token timed out before the store reached it.
+    #[error("Store has not caught up to session token: requires sequence {required}, at {head}")]
+    SessionNotCaughtUp { required: u64, head: u64 },
+
+    /// A failure injected by a `FaultyEventStore`, naming the operation.
+    #[error("Injected fault in {0}")]
+    Injected(String),
+
     /// A serialization/deserialization error occurred.
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
 }
 
+/// An event appended after the version a writer expected, reported with a
+/// concurrency conflict.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConflictingEvent {
+    pub event_id: EventId,
+    pub event_type: String,
+    pub version: Version,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl From<&EventEnvelope> for ConflictingEvent {
+    fn from(envelope: &EventEnvelope) -> Self {
+        Self {
+            event_id: envelope.event_id,
+            event_type: envelope.event_type.clone(),
+            version: envelope.version,
+            timestamp: envelope.timestamp,
+        }
+    }
+}
+
 /// Result type for event store operations.
 pub type Result<T> = std::result::Result<T, EventStoreError>;
@@ -136,6 +136,12 @@ pub struct EventEnvelope {
 
     /// Additional metadata about the event.
     pub metadata: HashMap<String, serde_json::Value>,
+
+    /// Position of this event in the store's global log, starting at 1.
+    ///
+    /// Assigned by the store on append; None for events not yet stored.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sequence: Option<u64>,
 }
 
 impl EventEnvelope {
@@ -229,6 +235,7 @@ impl EventEnvelopeBuilder {
             timestamp: self.timestamp.unwrap_or_else(Utc::now),
             payload: self.payload.expect("payload is required"),
             metadata: self.metadata,
+            sequence: None,
         }
     }
 
@@ -243,6 +250,7 @@ impl EventEnvelopeBuilder {
             timestamp: self.timestamp.unwrap_or_else(Utc::now),
             payload: self.payload?,
             metadata: self.metadata,
+            sequence: None,
         })
     }
 }
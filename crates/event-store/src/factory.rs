@@ -0,0 +1,259 @@
+//! One place for the conventions every stored event follows.
+//!
+//! An [`EnvelopeFactory`] is configured once per service with the clock
+//! that stamps events, how event IDs are generated, the schema version
+//! each event type is written at, and enrichers that add or check metadata
+//! on every envelope. Command handlers and the saga coordinator build their
+//! envelopes through it rather than filling in an
+//! [`EventEnvelopeBuilder`](crate::EventEnvelopeBuilder) by hand.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use common::{AggregateId, Clock, SystemClock};
+use serde::Serialize;
+
+use crate::deprecation::EVENT_VERSION_KEY;
+use crate::event::{EventEnvelope, EventId, Version};
+
+/// Generates the IDs of new events.
+pub trait EventIdGenerator: Send + Sync {
+    /// Returns the ID for the next event.
+    fn next_id(&self) -> EventId;
+}
+
+/// Random (v4) event IDs.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RandomEventIds;
+
+impl EventIdGenerator for RandomEventIds {
+    fn next_id(&self) -> EventId {
+        EventId::new()
+    }
+}
+
+impl<F> EventIdGenerator for F
+where
+    F: Fn() -> EventId + Send + Sync,
+{
+    fn next_id(&self) -> EventId {
+        self()
+    }
+}
+
+/// Adjusts every envelope a factory builds, after the caller's metadata
+/// has been added.
+pub trait EnvelopeEnricher: Send + Sync {
+    /// Adds to or corrects `envelope`.
+    fn enrich(&self, envelope: &mut EventEnvelope);
+}
+
+impl<F> EnvelopeEnricher for F
+where
+    F: Fn(&mut EventEnvelope) + Send + Sync,
+{
+    fn enrich(&self, envelope: &mut EventEnvelope) {
+        self(envelope)
+    }
+}
+
+/// Builds event envelopes following a service's conventions.
+///
+/// Cloning is cheap and shares the clock, ID generator, and enrichers, so
+/// one factory can be configured at startup and handed to every service;
+/// each service sets its own aggregate type with
+/// [`for_aggregate`](Self::for_aggregate).
+#[derive(Clone)]
+pub struct EnvelopeFactory {
+    aggregate_type: Option<String>,
+    clock: Arc<dyn Clock>,
+    ids: Arc<dyn EventIdGenerator>,
+    schema_versions: Arc<HashMap<String, u32>>,
+    enrichers: Vec<Arc<dyn EnvelopeEnricher>>,
+}
+
+impl EnvelopeFactory {
+    /// Creates a factory stamping events with the system clock and random
+    /// IDs, and no aggregate type.
+    pub fn new() -> Self {
+        Self {
+            aggregate_type: None,
+            clock: Arc::new(SystemClock),
+            ids: Arc::new(RandomEventIds),
+            schema_versions: Arc::new(HashMap::new()),
+            enrichers: Vec::new(),
+        }
+    }
+
+    /// Returns a copy of this factory building events of `aggregate_type`.
+    pub fn for_aggregate(&self, aggregate_type: impl Into<String>) -> Self {
+        Self {
+            aggregate_type: Some(aggregate_type.into()),
+            ..self.clone()
+        }
+    }
+
+    /// Stamps events with the time from `clock`.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Takes event IDs from `ids`.
+    pub fn with_id_generator(mut self, ids: Arc<dyn EventIdGenerator>) -> Self {
+        self.ids = ids;
+        self
+    }
+
+    /// Records new `event_type` events as schema `version` under
+    /// [`EVENT_VERSION_KEY`]. Event types without a version are left
+    /// unstamped, which reads as version 1.
+    pub fn with_schema_version(mut self, event_type: impl Into<String>, version: u32) -> Self {
+        Arc::make_mut(&mut self.schema_versions).insert(event_type.into(), version);
+        self
+    }
+
+    /// Runs `enricher` on every envelope, after any added before it.
+    pub fn with_enricher(mut self, enricher: Arc<dyn EnvelopeEnricher>) -> Self {
+        self.enrichers.push(enricher);
+        self
+    }
+
+    /// Returns the aggregate type envelopes are built for, if set.
+    pub fn aggregate_type(&self) -> Option<&str> {
+        self.aggregate_type.as_deref()
+    }
+
+    /// Returns the schema version new `event_type` events are written at.
+    pub fn schema_version(&self, event_type: &str) -> u32 {
+        self.schema_versions.get(event_type).copied().unwrap_or(1)
+    }
+
+    /// Builds the envelope of an event at `version` of `aggregate_id`.
+    ///
+    /// `metadata` is added before the enrichers run, so enrichers see, and
+    /// may override, what the caller supplied.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no aggregate type was set with
+    /// [`for_aggregate`](Self::for_aggregate).
+    pub fn build<T, M>(
+        &self,
+        aggregate_id: AggregateId,
+        version: Version,
+        event_type: impl Into<String>,
+        payload: &T,
+        metadata: M,
+    ) -> Result<EventEnvelope, serde_json::Error>
+    where
+        T: Serialize,
+        M: IntoIterator<Item = (String, serde_json::Value)>,
+    {
+        let event_type = event_type.into();
+        let mut builder = EventEnvelope::builder()
+            .event_id(self.ids.next_id())
+            .aggregate_id(aggregate_id)
+            .aggregate_type(
+                self.aggregate_type
+                    .clone()
+                    .expect("envelope factory has no aggregate type"),
+            )
+            .version(version)
+            .timestamp(self.clock.now())
+            .payload(payload)?;
+        if let Some(schema_version) = self.schema_versions.get(&event_type) {
+            builder = builder.metadata(EVENT_VERSION_KEY, serde_json::json!(schema_version));
+        }
+        for (key, value) in metadata {
+            builder = builder.metadata(key, value);
+        }
+
+        let mut envelope = builder.event_type(event_type).build();
+        for enricher in &self.enrichers {
+            enricher.enrich(&mut envelope);
+        }
+        Ok(envelope)
+    }
+}
+
+impl Default for EnvelopeFactory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for EnvelopeFactory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EnvelopeFactory")
+            .field("aggregate_type", &self.aggregate_type)
+            .field("schema_versions", &self.schema_versions)
+            .field("enrichers", &self.enrichers.len())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deprecation::event_version;
+    use chrono::DateTime;
+    use common::VirtualClock;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use uuid::Uuid;
+
+    #[test]
+    fn test_builds_with_configured_conventions() {
+        let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let next = Arc::new(AtomicU64::new(1));
+        let factory = EnvelopeFactory::new()
+            .with_clock(Arc::new(VirtualClock::new(start)))
+            .with_id_generator(Arc::new(move || {
+                EventId::from_uuid(Uuid::from_u64_pair(0, next.fetch_add(1, Ordering::Relaxed)))
+            }))
+            .with_schema_version("ItemAdded", 2)
+            .with_enricher(Arc::new(|envelope: &mut EventEnvelope| {
+                envelope
+                    .metadata
+                    .entry("service".to_string())
+                    .or_insert_with(|| serde_json::json!("orders"));
+            }))
+            .for_aggregate("Order");
+
+        let id = AggregateId::new();
+        let added = factory
+            .build(
+                id,
+                Version::new(2),
+                "ItemAdded",
+                &serde_json::json!({"sku": "A"}),
+                [("actor".to_string(), serde_json::json!("ops"))],
+            )
+            .unwrap();
+        assert_eq!(added.aggregate_type, "Order");
+        assert_eq!(added.aggregate_id, id);
+        assert_eq!(added.version, Version::new(2));
+        assert_eq!(added.timestamp, start);
+        assert_eq!(added.event_id.as_uuid(), Uuid::from_u64_pair(0, 1));
+        assert_eq!(event_version(&added), 2);
+        assert_eq!(added.metadata["actor"], "ops");
+        assert_eq!(added.metadata["service"], "orders");
+
+        // Unversioned types stay unstamped, and IDs keep coming from the
+        // shared generator
+        let created = factory
+            .build(id, Version::new(1), "OrderCreated", &(), [])
+            .unwrap();
+        assert!(!created.metadata.contains_key(EVENT_VERSION_KEY));
+        assert_eq!(created.event_id.as_uuid(), Uuid::from_u64_pair(0, 2));
+        assert_eq!(factory.schema_version("OrderCreated"), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "no aggregate type")]
+    fn test_building_without_an_aggregate_type_panics() {
+        let _ =
+            EnvelopeFactory::new().build(AggregateId::new(), Version::new(1), "Created", &(), []);
+    }
+}
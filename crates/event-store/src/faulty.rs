@@ -0,0 +1,365 @@
+//! Fault injection for resilience tests.
+//!
+//! [`FaultyEventStore`] wraps a store and fails or delays chosen operations,
+//! so the error paths of command handlers, sagas, and projection processors
+//! can be exercised against a real store instead of a hand-written mock.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::sync::watch;
+
+use crate::store::{AppendOptions, EventStore, EventStream, StreamAppend};
+use crate::{
+    AggregateId, ConsumerOffset, ConsumerOffsetStore, EventEnvelope, EventId, EventQuery,
+    EventStoreError, Result, Snapshot, Version,
+};
+
+/// A class of store operation that faults can target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StoreOperation {
+    /// `append` and `append_batch`.
+    Append,
+    /// Loading an aggregate's events or version.
+    Load,
+    /// `query_events`, `get_events_by_type`, `stream_all_events`,
+    /// `stream_events_after`, and `head_sequence`.
+    Query,
+    /// `save_snapshot`.
+    SaveSnapshot,
+    /// `get_snapshot`.
+    GetSnapshot,
+}
+
+impl StoreOperation {
+    fn name(&self) -> &'static str {
+        match self {
+            StoreOperation::Append => "append",
+            StoreOperation::Load => "load",
+            StoreOperation::Query => "query",
+            StoreOperation::SaveSnapshot => "save_snapshot",
+            StoreOperation::GetSnapshot => "get_snapshot",
+        }
+    }
+}
+
+/// Faults configured for one operation.
+#[derive(Debug, Clone, Copy, Default)]
+struct Faults {
+    /// Calls left to fail before the error rate applies again.
+    fail_next: u32,
+    /// Fraction of calls to fail, from 0.0 to 1.0.
+    error_rate: f64,
+    /// Added latency on every call, failed or not.
+    delay: Option<Duration>,
+}
+
+#[derive(Debug)]
+struct FaultState {
+    faults: HashMap<StoreOperation, Faults>,
+    injected: HashMap<StoreOperation, u64>,
+    /// SplitMix64 state, so error rates are reproducible for a seed.
+    rng: u64,
+}
+
+impl FaultState {
+    fn next_unit(&mut self) -> f64 {
+        self.rng = self.rng.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.rng;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Decides whether this call fails, returning its delay either way.
+    fn decide(&mut self, operation: StoreOperation) -> (bool, Option<Duration>) {
+        let Some(faults) = self.faults.get(&operation).copied() else {
+            return (false, None);
+        };
+        let fail = if faults.fail_next > 0 {
+            if let Some(f) = self.faults.get_mut(&operation) {
+                f.fail_next -= 1;
+            }
+            true
+        } else {
+            faults.error_rate > 0.0 && self.next_unit() < faults.error_rate
+        };
+        if fail {
+            *self.injected.entry(operation).or_default() += 1;
+        }
+        (fail, faults.delay)
+    }
+}
+
+/// An event store wrapper that fails or slows down chosen operations.
+///
+/// Faults are set per [`StoreOperation`]: fail the next `n` calls, fail a
+/// fraction of calls, or add latency. Injected failures return
+/// [`EventStoreError::Injected`] without reaching the inner store, so a
+/// failed append writes nothing. Error rates draw from a seeded generator,
+/// making a test's sequence of failures the same on every run.
+///
+/// Clones share their faults, so a test can keep one clone and change the
+/// faults after handing another to the code under test.
+#[derive(Debug, Clone)]
+pub struct FaultyEventStore<S> {
+    inner: S,
+    state: Arc<Mutex<FaultState>>,
+}
+
+impl<S: EventStore> FaultyEventStore<S> {
+    /// Wraps `inner` with no faults configured.
+    pub fn new(inner: S) -> Self {
+        Self::with_seed(inner, 0)
+    }
+
+    /// Wraps `inner`, seeding the generator behind error rates.
+    pub fn with_seed(inner: S, seed: u64) -> Self {
+        Self {
+            inner,
+            state: Arc::new(Mutex::new(FaultState {
+                faults: HashMap::new(),
+                injected: HashMap::new(),
+                rng: seed,
+            })),
+        }
+    }
+
+    /// Returns the wrapped store.
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Fails the next `count` calls of `operation`.
+    pub fn fail_next(&self, operation: StoreOperation, count: u32) {
+        self.update(operation, |f| f.fail_next = count);
+    }
+
+    /// Fails roughly `rate` (0.0 to 1.0) of the calls of `operation`.
+    pub fn set_error_rate(&self, operation: StoreOperation, rate: f64) {
+        self.update(operation, |f| f.error_rate = rate.clamp(0.0, 1.0));
+    }
+
+    /// Delays every call of `operation` by `delay`.
+    pub fn set_delay(&self, operation: StoreOperation, delay: Duration) {
+        self.update(operation, |f| f.delay = Some(delay));
+    }
+
+    /// Removes every configured fault. Injection counts are kept.
+    pub fn heal(&self) {
+        self.lock().faults.clear();
+    }
+
+    /// Returns how many failures were injected into `operation`.
+    pub fn injected(&self, operation: StoreOperation) -> u64 {
+        self.lock().injected.get(&operation).copied().unwrap_or(0)
+    }
+
+    fn update(&self, operation: StoreOperation, f: impl FnOnce(&mut Faults)) {
+        f(self.lock().faults.entry(operation).or_default());
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, FaultState> {
+        self.state.lock().expect("fault state poisoned")
+    }
+
+    /// Applies the faults for one call of `operation`.
+    async fn disrupt(&self, operation: StoreOperation) -> Result<()> {
+        let (fail, delay) = self.lock().decide(operation);
+        if let Some(delay) = delay {
+            tokio::time::sleep(delay).await;
+        }
+        if fail {
+            return Err(EventStoreError::Injected(operation.name().to_string()));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<S: EventStore> EventStore for FaultyEventStore<S> {
+    async fn append(&self, events: Vec<EventEnvelope>, options: AppendOptions) -> Result<Version> {
+        self.disrupt(StoreOperation::Append).await?;
+        self.inner.append(events, options).await
+    }
+
+    async fn append_batch(&self, streams: Vec<StreamAppend>) -> Result<Vec<Version>> {
+        self.disrupt(StoreOperation::Append).await?;
+        self.inner.append_batch(streams).await
+    }
+
+    async fn get_events_for_aggregate(
+        &self,
+        aggregate_id: AggregateId,
+    ) -> Result<Vec<EventEnvelope>> {
+        self.disrupt(StoreOperation::Load).await?;
+        self.inner.get_events_for_aggregate(aggregate_id).await
+    }
+
+    async fn get_events_for_aggregate_from_version(
+        &self,
+        aggregate_id: AggregateId,
+        from_version: Version,
+    ) -> Result<Vec<EventEnvelope>> {
+        self.disrupt(StoreOperation::Load).await?;
+        self.inner
+            .get_events_for_aggregate_from_version(aggregate_id, from_version)
+            .await
+    }
+
+    async fn query_events(&self, query: EventQuery) -> Result<Vec<EventEnvelope>> {
+        self.disrupt(StoreOperation::Query).await?;
+        self.inner.query_events(query).await
+    }
+
+    async fn get_events_by_type(&self, event_type: &str) -> Result<Vec<EventEnvelope>> {
+        self.disrupt(StoreOperation::Query).await?;
+        self.inner.get_events_by_type(event_type).await
+    }
+
+    async fn stream_all_events(&self) -> Result<EventStream> {
+        self.disrupt(StoreOperation::Query).await?;
+        self.inner.stream_all_events().await
+    }
+
+    async fn stream_events_after(&self, after_sequence: u64) -> Result<EventStream> {
+        self.disrupt(StoreOperation::Query).await?;
+        self.inner.stream_events_after(after_sequence).await
+    }
+
+    async fn subscribe(&self, after_sequence: u64) -> Result<EventStream> {
+        self.disrupt(StoreOperation::Query).await?;
+        self.inner.subscribe(after_sequence).await
+    }
+
+    async fn get_event(&self, event_id: EventId) -> Result<Option<EventEnvelope>> {
+        self.disrupt(StoreOperation::Query).await?;
+        self.inner.get_event(event_id).await
+    }
+
+    async fn head_sequence(&self) -> Result<u64> {
+        self.disrupt(StoreOperation::Query).await?;
+        self.inner.head_sequence().await
+    }
+
+    fn append_notifications(&self) -> Option<watch::Receiver<u64>> {
+        self.inner.append_notifications()
+    }
+
+    async fn get_aggregate_version(&self, aggregate_id: AggregateId) -> Result<Option<Version>> {
+        self.disrupt(StoreOperation::Load).await?;
+        self.inner.get_aggregate_version(aggregate_id).await
+    }
+
+    async fn save_snapshot(&self, snapshot: Snapshot) -> Result<()> {
+        self.disrupt(StoreOperation::SaveSnapshot).await?;
+        self.inner.save_snapshot(snapshot).await
+    }
+
+    async fn get_snapshot(&self, aggregate_id: AggregateId) -> Result<Option<Snapshot>> {
+        self.disrupt(StoreOperation::GetSnapshot).await?;
+        self.inner.get_snapshot(aggregate_id).await
+    }
+}
+
+#[async_trait]
+impl<S: ConsumerOffsetStore> ConsumerOffsetStore for FaultyEventStore<S> {
+    async fn get_offset(&self, consumer: &str) -> Result<Option<ConsumerOffset>> {
+        self.inner.get_offset(consumer).await
+    }
+
+    async fn set_offset(&self, consumer: &str, sequence: u64) -> Result<ConsumerOffset> {
+        self.inner.set_offset(consumer, sequence).await
+    }
+
+    async fn list_offsets(&self) -> Result<Vec<ConsumerOffset>> {
+        self.inner.list_offsets().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InMemoryEventStore;
+
+    fn event(aggregate_id: AggregateId, version: i64) -> EventEnvelope {
+        EventEnvelope::builder()
+            .aggregate_id(aggregate_id)
+            .aggregate_type("TestAggregate")
+            .event_type("TestEvent")
+            .version(Version::new(version))
+            .payload_raw(serde_json::json!({ "version": version }))
+            .build()
+    }
+
+    #[tokio::test]
+    async fn test_fail_next_fails_without_appending() {
+        let store = FaultyEventStore::new(InMemoryEventStore::new());
+        let aggregate_id = AggregateId::new();
+        store.fail_next(StoreOperation::Append, 1);
+
+        let result = store
+            .append(vec![event(aggregate_id, 1)], AppendOptions::expect_new())
+            .await;
+        assert!(matches!(result, Err(EventStoreError::Injected(_))));
+        assert!(
+            store
+                .get_events_for_aggregate(aggregate_id)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        // Only the next call was set to fail
+        store
+            .append(vec![event(aggregate_id, 1)], AppendOptions::expect_new())
+            .await
+            .unwrap();
+        assert_eq!(store.injected(StoreOperation::Append), 1);
+    }
+
+    #[tokio::test]
+    async fn test_error_rate_is_reproducible_for_a_seed() {
+        let failures = |seed| async move {
+            let store = FaultyEventStore::with_seed(InMemoryEventStore::new(), seed);
+            store.set_error_rate(StoreOperation::Query, 0.3);
+            let mut failed = Vec::new();
+            for _ in 0..50 {
+                failed.push(store.head_sequence().await.is_err());
+            }
+            failed
+        };
+
+        let first = failures(7).await;
+        assert_eq!(first, failures(7).await);
+        let count = first.iter().filter(|f| **f).count();
+        assert!((5..=25).contains(&count), "{count} of 50 failed");
+    }
+
+    #[tokio::test]
+    async fn test_faults_shared_between_clones_and_healed() {
+        let store = FaultyEventStore::new(InMemoryEventStore::new());
+        let handle = store.clone();
+        handle.set_error_rate(StoreOperation::SaveSnapshot, 1.0);
+        handle.set_delay(StoreOperation::Load, Duration::from_millis(20));
+
+        let snapshot =
+            Snapshot::from_state(AggregateId::new(), "TestAggregate", Version::first(), &1)
+                .unwrap();
+        assert!(store.save_snapshot(snapshot.clone()).await.is_err());
+
+        let started = std::time::Instant::now();
+        store
+            .get_events_for_aggregate(AggregateId::new())
+            .await
+            .unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(20));
+
+        handle.heal();
+        store.save_snapshot(snapshot).await.unwrap();
+        assert_eq!(handle.injected(StoreOperation::SaveSnapshot), 1);
+    }
+}
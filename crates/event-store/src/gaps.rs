@@ -0,0 +1,77 @@
+//! Aging of gaps in the log's global sequences.
+//!
+//! Sequences are assigned as appends insert, not as they commit, so a reader
+//! can see a later append before an earlier one. A reader waits at such a
+//! gap for it to fill, unless it has stayed open longer than a window, in
+//! which case it is taken to be a rolled-back append and read past.
+//!
+//! A gap is aged from when the reader first saw it. The timestamps of the
+//! events around it are set by whoever wrote them, and an imported,
+//! backdated, or clock-skewed event would make a gap look old at once.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// The gaps a reader has seen in the log and when it first saw each.
+///
+/// Keep one for as long as the reader runs, so a gap seen by one read is
+/// still aged on the next.
+#[derive(Debug, Clone)]
+pub struct SequenceGaps {
+    window: Duration,
+    /// When each open gap was first seen, by the sequence right after it.
+    first_seen: HashMap<u64, Instant>,
+}
+
+impl SequenceGaps {
+    /// Waits at a gap for up to `window` after first seeing it.
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            first_seen: HashMap::new(),
+        }
+    }
+
+    /// Returns how long a gap is waited for.
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Returns true if a reader that last read `previous` should stop
+    /// before `sequence`, because the sequences between them are missing
+    /// and were first seen missing less than the window ago.
+    pub fn holds(&mut self, previous: u64, sequence: u64) -> bool {
+        if sequence <= previous + 1 {
+            return false;
+        }
+        let first_seen = *self.first_seen.entry(sequence).or_insert_with(Instant::now);
+        first_seen.elapsed() < self.window
+    }
+
+    /// Forgets the gaps before sequences up to `position`, which the reader
+    /// has read past.
+    pub fn forget_through(&mut self, position: u64) {
+        self.first_seen.retain(|&sequence, _| sequence > position);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gaps_are_aged_from_when_first_seen() {
+        let mut gaps = SequenceGaps::new(Duration::from_millis(20));
+        assert!(!gaps.holds(1, 2));
+        assert!(gaps.holds(2, 4));
+        assert!(gaps.holds(2, 4));
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(!gaps.holds(2, 4));
+        // A gap seen for the first time now waits its own window
+        assert!(gaps.holds(4, 6));
+
+        gaps.forget_through(6);
+        assert!(gaps.holds(2, 4));
+    }
+}
@@ -0,0 +1,270 @@
+//! Idempotency keys for replayed commands.
+//!
+//! A client that may retry a command sends it with a key. The first request
+//! under a key claims it along with a hash of the request; once handled, its
+//! response is stored under the key until the key expires. A retry with the
+//! same request gets the stored response back instead of running again, and
+//! reusing the key for a different request is refused.
+//!
+//! A claim on a key whose request is still running is a lease: a key whose
+//! request never finished, e.g. because the process stopped during a
+//! deploy, can be claimed again once its lease runs out, rather than only
+//! once the key expires.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use tokio::sync::RwLock;
+
+use crate::Result;
+
+/// The response recorded for a finished request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredResponse {
+    /// HTTP status code.
+    pub status: u16,
+    /// Response headers as (name, value) pairs, in order, so a replay
+    /// carries `Content-Type`, `Location`, and the like as sent.
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+/// What claiming a key found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdempotencyClaim {
+    /// The key was free and is now claimed for this request.
+    New,
+    /// The same request already finished; here is its response.
+    Replay(StoredResponse),
+    /// The key was used for a request with a different hash.
+    Mismatch,
+    /// The same request is still being handled.
+    InProgress,
+}
+
+/// Storage for idempotency keys and the responses recorded under them.
+#[async_trait]
+pub trait IdempotencyStore: Send + Sync {
+    /// Claims `key` for a request hashing to `request_hash` until
+    /// `expires_at`. An expired key is claimed as if it were free, and so
+    /// is a key whose request hasn't finished within `lease` of its claim.
+    async fn claim(
+        &self,
+        key: &str,
+        request_hash: &str,
+        lease: Duration,
+        expires_at: DateTime<Utc>,
+    ) -> Result<IdempotencyClaim>;
+
+    /// Records the response to the request that claimed `key`.
+    async fn complete(&self, key: &str, response: StoredResponse) -> Result<()>;
+
+    /// Frees `key` so the request can be retried, unless its response was
+    /// already recorded.
+    async fn release(&self, key: &str) -> Result<()>;
+
+    /// Removes keys that expired before `now`, returning how many.
+    async fn purge_expired(&self, now: DateTime<Utc>) -> Result<usize>;
+}
+
+#[derive(Debug, Clone)]
+struct KeyRecord {
+    request_hash: String,
+    response: Option<StoredResponse>,
+    claimed_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+}
+
+impl KeyRecord {
+    /// Returns true if the key can no longer be claimed by another request
+    /// at `now`.
+    fn is_held(&self, lease: Duration, now: DateTime<Utc>) -> bool {
+        if self.expires_at <= now {
+            return false;
+        }
+        self.response.is_some()
+            || self.claimed_at + chrono::Duration::from_std(lease).unwrap_or(chrono::Duration::MAX)
+                > now
+    }
+}
+
+/// Idempotency keys held in memory, lost on restart.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryIdempotencyStore {
+    keys: Arc<RwLock<HashMap<String, KeyRecord>>>,
+}
+
+impl InMemoryIdempotencyStore {
+    /// Creates an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl IdempotencyStore for InMemoryIdempotencyStore {
+    async fn claim(
+        &self,
+        key: &str,
+        request_hash: &str,
+        lease: Duration,
+        expires_at: DateTime<Utc>,
+    ) -> Result<IdempotencyClaim> {
+        let now = Utc::now();
+        let mut keys = self.keys.write().await;
+        if let Some(record) = keys.get(key)
+            && record.is_held(lease, now)
+        {
+            return Ok(if record.request_hash != request_hash {
+                IdempotencyClaim::Mismatch
+            } else {
+                match &record.response {
+                    Some(response) => IdempotencyClaim::Replay(response.clone()),
+                    None => IdempotencyClaim::InProgress,
+                }
+            });
+        }
+        keys.insert(
+            key.to_string(),
+            KeyRecord {
+                request_hash: request_hash.to_string(),
+                response: None,
+                claimed_at: now,
+                expires_at,
+            },
+        );
+        Ok(IdempotencyClaim::New)
+    }
+
+    async fn complete(&self, key: &str, response: StoredResponse) -> Result<()> {
+        if let Some(record) = self.keys.write().await.get_mut(key) {
+            record.response = Some(response);
+        }
+        Ok(())
+    }
+
+    async fn release(&self, key: &str) -> Result<()> {
+        let mut keys = self.keys.write().await;
+        if keys
+            .get(key)
+            .is_some_and(|record| record.response.is_none())
+        {
+            keys.remove(key);
+        }
+        Ok(())
+    }
+
+    async fn purge_expired(&self, now: DateTime<Utc>) -> Result<usize> {
+        let mut keys = self.keys.write().await;
+        let before = keys.len();
+        keys.retain(|_, record| record.expires_at > now);
+        Ok(before - keys.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    const LEASE: std::time::Duration = std::time::Duration::from_secs(60);
+
+    fn response() -> StoredResponse {
+        StoredResponse {
+            status: 201,
+            headers: vec![("content-type".to_string(), "application/json".to_string())],
+            body: br#"{"id":"1"}"#.to_vec(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_claim_replay_and_mismatch() {
+        let store = InMemoryIdempotencyStore::new();
+        let expires_at = Utc::now() + Duration::hours(1);
+
+        assert_eq!(
+            store.claim("k", "a", LEASE, expires_at).await.unwrap(),
+            IdempotencyClaim::New
+        );
+        assert_eq!(
+            store.claim("k", "a", LEASE, expires_at).await.unwrap(),
+            IdempotencyClaim::InProgress
+        );
+        store.complete("k", response()).await.unwrap();
+
+        assert_eq!(
+            store.claim("k", "a", LEASE, expires_at).await.unwrap(),
+            IdempotencyClaim::Replay(response())
+        );
+        assert_eq!(
+            store.claim("k", "b", LEASE, expires_at).await.unwrap(),
+            IdempotencyClaim::Mismatch
+        );
+    }
+
+    #[tokio::test]
+    async fn test_release_and_expiry_free_the_key() {
+        let store = InMemoryIdempotencyStore::new();
+        let now = Utc::now();
+
+        store
+            .claim("k", "a", LEASE, now + Duration::hours(1))
+            .await
+            .unwrap();
+        store.release("k").await.unwrap();
+        assert_eq!(
+            store
+                .claim("k", "b", LEASE, now + Duration::hours(1))
+                .await
+                .unwrap(),
+            IdempotencyClaim::New
+        );
+
+        store
+            .claim("old", "a", LEASE, now - Duration::seconds(1))
+            .await
+            .unwrap();
+        assert_eq!(
+            store
+                .claim("old", "b", LEASE, now + Duration::hours(1))
+                .await
+                .unwrap(),
+            IdempotencyClaim::New
+        );
+
+        store
+            .claim("gone", "a", LEASE, now - Duration::seconds(1))
+            .await
+            .unwrap();
+        assert_eq!(store.purge_expired(now).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_claim_is_taken_over_after_its_lease() {
+        let store = InMemoryIdempotencyStore::new();
+        let expires_at = Utc::now() + Duration::hours(1);
+        let no_lease = std::time::Duration::ZERO;
+
+        store.claim("k", "a", LEASE, expires_at).await.unwrap();
+        assert_eq!(
+            store.claim("k", "a", LEASE, expires_at).await.unwrap(),
+            IdempotencyClaim::InProgress
+        );
+        // The first request never finished; once its lease is over the
+        // retry takes the key
+        assert_eq!(
+            store.claim("k", "a", no_lease, expires_at).await.unwrap(),
+            IdempotencyClaim::New
+        );
+
+        // A finished request's response is kept whatever the lease
+        store.complete("k", response()).await.unwrap();
+        assert_eq!(
+            store.claim("k", "a", no_lease, expires_at).await.unwrap(),
+            IdempotencyClaim::Replay(response())
+        );
+    }
+}
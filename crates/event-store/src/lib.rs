@@ -1,16 +1,65 @@
+pub mod annotations;
+pub mod archive;
+pub mod cloudevents;
+pub mod deprecation;
 pub mod error;
 pub mod event;
+pub mod factory;
+pub mod faulty;
+pub mod gaps;
+pub mod idempotency;
 pub mod memory;
+pub mod migrate;
+pub mod offsets;
+pub mod pending;
+pub mod planner;
 pub mod postgres;
 pub mod query;
+pub mod quota;
+pub mod schema;
+pub mod session;
 pub mod snapshot;
+pub mod stats;
 pub mod store;
+pub mod subscription;
+pub mod time_travel;
+pub mod visibility;
 
+pub use annotations::{AnnotationStore, EventAnnotation, InMemoryAnnotationStore};
+pub use archive::{ArchiveSink, InMemoryArchive, merge_by_sequence};
+pub use cloudevents::CloudEvent;
 pub use common::AggregateId;
-pub use error::{EventStoreError, Result};
+pub use deprecation::{
+    DeprecatedVersionUsage, DeprecationReport, EVENT_VERSION_KEY, ReadSource, SchemaDeprecations,
+};
+pub use error::{ConflictingEvent, EventStoreError, Result};
 pub use event::{EventEnvelope, EventEnvelopeBuilder, EventId, Version};
+pub use factory::{EnvelopeEnricher, EnvelopeFactory, EventIdGenerator, RandomEventIds};
+pub use faulty::{FaultyEventStore, StoreOperation};
+pub use gaps::SequenceGaps;
+pub use idempotency::{
+    IdempotencyClaim, IdempotencyStore, InMemoryIdempotencyStore, StoredResponse,
+};
 pub use memory::InMemoryEventStore;
+pub use migrate::{
+    MigrationProgress, MigrationReport, StoreMigration, VersionMismatch, migrate_store,
+    verify_versions,
+};
+pub use offsets::{ConsumerOffset, ConsumerOffsetStore};
+pub use pending::{PendingBatch, PendingBatchId, PendingEventStore};
+pub use planner::{IndexAdvisory, QueryAnalysis, QueryAnalyzer};
 pub use postgres::PostgresEventStore;
 pub use query::EventQuery;
+pub use quota::{
+    QuotaEnforcer, QuotaEventStore, QuotaExceeded, QuotaLimits, QuotaUsage, TENANT_METADATA_KEY,
+};
+pub use schema::{SchemaDrift, SchemaVersion, SchemaVersionSource};
+pub use session::{InvalidSessionToken, LoadOptions, SessionToken};
 pub use snapshot::Snapshot;
-pub use store::{AppendOptions, EventStore, EventStoreExt, EventStream};
+pub use stats::AggregateStats;
+pub use store::{AppendOptions, EventStore, EventStoreExt, EventStream, StreamAppend};
+pub use time_travel::{Cutoff, TimeTravelEventStore};
+pub use visibility::{
+    Audience, InMemoryStreamVisibilityStore, StreamVisibility, StreamVisibilityStore,
+    VisibilityPolicy,
+};
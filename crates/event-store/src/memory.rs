@@ -1,12 +1,21 @@
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 use async_trait::async_trait;
-use tokio::sync::RwLock;
+use tokio::sync::{RwLock, watch};
 
 use crate::{
-    AggregateId, EventEnvelope, EventQuery, EventStoreError, Result, Snapshot, Version,
-    store::{AppendOptions, EventStore, EventStream, validate_events_for_append},
+    AggregateId, EventEnvelope, EventId, EventQuery, EventStoreError, Result, Snapshot, Version,
+    archive::ArchiveSink,
+    offsets::{ConsumerOffset, ConsumerOffsetStore},
+    pending::{PendingBatch, PendingBatchId, PendingEventStore},
+    session::{self, DEFAULT_SESSION_WAIT},
+    store::{
+        AppendOptions, EventStore, EventStream, StreamAppend, validate_batch_for_append,
+        validate_events_for_append,
+    },
+    subscription::{self, SUBSCRIPTION_BATCH_SIZE},
 };
 
 /// In-memory event store implementation for testing.
@@ -17,6 +26,10 @@ use crate::{
 pub struct InMemoryEventStore {
     events: Arc<RwLock<Vec<EventEnvelope>>>,
     snapshots: Arc<RwLock<HashMap<AggregateId, Snapshot>>>,
+    head_sequence: Arc<AtomicU64>,
+    offsets: Arc<RwLock<HashMap<String, ConsumerOffset>>>,
+    pending: Arc<RwLock<HashMap<PendingBatchId, PendingBatch>>>,
+    appended: Arc<watch::Sender<u64>>,
 }
 
 impl InMemoryEventStore {
@@ -30,11 +43,111 @@ impl InMemoryEventStore {
         self.events.read().await.len()
     }
 
-    /// Clears all events and snapshots.
+    /// Clears all events, snapshots, consumer offsets, and pending batches.
     pub async fn clear(&self) {
         self.events.write().await.clear();
         self.snapshots.write().await.clear();
+        self.offsets.write().await.clear();
+        self.pending.write().await.clear();
+        self.head_sequence.store(0, Ordering::SeqCst);
     }
+
+    /// Moves events already covered by a snapshot into the archive.
+    ///
+    /// For each aggregate with a snapshot, events older than the snapshot
+    /// version are archived and removed. The event at the snapshot version is
+    /// kept so the aggregate's current version stays visible.
+    ///
+    /// Returns the number of events archived.
+    #[tracing::instrument(skip(self, sink))]
+    pub async fn compact(&self, sink: &dyn ArchiveSink) -> Result<usize> {
+        let mut store = self.events.write().await;
+        let snapshots = self.snapshots.read().await;
+
+        let is_compactable = |e: &EventEnvelope| {
+            snapshots
+                .get(&e.aggregate_id)
+                .is_some_and(|s| e.version < s.version)
+        };
+        let archived: Vec<_> = store
+            .iter()
+            .filter(|e| is_compactable(e))
+            .cloned()
+            .collect();
+        if archived.is_empty() {
+            return Ok(0);
+        }
+
+        let count = archived.len();
+        sink.archive(archived).await?;
+        store.retain(|e| !is_compactable(e));
+
+        tracing::info!(count, "events compacted");
+        metrics::counter!("events_archived").increment(count as u64);
+
+        Ok(count)
+    }
+
+    /// Stores already checked events, numbering them from the head of the
+    /// log. Returns the aggregate's new version.
+    fn store_events(&self, store: &mut Vec<EventEnvelope>, events: Vec<EventEnvelope>) -> Version {
+        let event_count = events.len();
+        let last_version = events
+            .last()
+            .map(|e| e.version)
+            .unwrap_or(Version::initial());
+        let next_sequence = self.head_sequence.load(Ordering::SeqCst) + 1;
+        store.extend(events.into_iter().enumerate().map(|(i, mut event)| {
+            event.sequence = Some(next_sequence + i as u64);
+            event
+        }));
+        self.head_sequence
+            .store(next_sequence + event_count as u64 - 1, Ordering::SeqCst);
+        last_version
+    }
+}
+
+/// Checks that `events` can follow what `store` holds for their aggregate.
+fn check_append(
+    store: &[EventEnvelope],
+    events: &[EventEnvelope],
+    options: &AppendOptions,
+) -> Result<()> {
+    let first_event = &events[0];
+    let aggregate_id = first_event.aggregate_id;
+
+    // Get current version for this aggregate
+    let current_version = store
+        .iter()
+        .filter(|e| e.aggregate_id == aggregate_id)
+        .map(|e| e.version)
+        .max()
+        .unwrap_or(Version::initial());
+
+    // Check expected version if specified
+    if let Some(expected) = options.expected_version
+        && current_version != expected
+    {
+        return Err(EventStoreError::ConcurrencyConflict {
+            aggregate_id,
+            expected,
+            actual: current_version,
+            conflicting: Vec::new(),
+        });
+    }
+
+    // Check for version conflicts (unique constraint simulation)
+    let first_new_version = first_event.version;
+    if first_new_version <= current_version && current_version != Version::initial() {
+        return Err(EventStoreError::ConcurrencyConflict {
+            aggregate_id,
+            expected: options.expected_version.unwrap_or(current_version),
+            actual: current_version,
+            conflicting: Vec::new(),
+        });
+    }
+
+    Ok(())
 }
 
 #[async_trait]
@@ -45,52 +158,48 @@ impl EventStore for InMemoryEventStore {
             EventStoreError::Serialization(serde_json::Error::io(std::io::Error::other(e.message)))
         })?;
 
-        let first_event = &events[0];
-        let aggregate_id = first_event.aggregate_id;
+        let aggregate_id = events[0].aggregate_id;
 
         let mut store = self.events.write().await;
+        check_append(&store, &events, &options)?;
 
-        // Get current version for this aggregate
-        let current_version = store
-            .iter()
-            .filter(|e| e.aggregate_id == aggregate_id)
-            .map(|e| e.version)
-            .max()
-            .unwrap_or(Version::initial());
+        let event_count = events.len();
+        let last_version = self.store_events(&mut store, events);
+        drop(store);
+        self.appended
+            .send_replace(self.head_sequence.load(Ordering::SeqCst));
 
-        // Check expected version if specified
-        if let Some(expected) = options.expected_version
-            && current_version != expected
-        {
-            return Err(EventStoreError::ConcurrencyConflict {
-                aggregate_id,
-                expected,
-                actual: current_version,
-            });
-        }
+        tracing::info!(event_count, %aggregate_id, "events appended");
+        metrics::counter!("events_appended").increment(event_count as u64);
+
+        Ok(last_version)
+    }
 
-        // Check for version conflicts (unique constraint simulation)
-        let first_new_version = first_event.version;
-        if first_new_version <= current_version && current_version != Version::initial() {
-            return Err(EventStoreError::ConcurrencyConflict {
-                aggregate_id,
-                expected: options.expected_version.unwrap_or(current_version),
-                actual: current_version,
-            });
+    #[tracing::instrument(skip(self, streams), fields(stream_count = streams.len()))]
+    async fn append_batch(&self, streams: Vec<StreamAppend>) -> Result<Vec<Version>> {
+        validate_batch_for_append(&streams).map_err(|e| {
+            EventStoreError::Serialization(serde_json::Error::io(std::io::Error::other(e.message)))
+        })?;
+
+        // Check every stream before storing any, under one lock
+        let mut store = self.events.write().await;
+        for stream in &streams {
+            check_append(&store, &stream.events, &stream.options)?;
         }
 
-        // Store all events
-        let event_count = events.len();
-        let last_version = events
-            .last()
-            .map(|e| e.version)
-            .unwrap_or(Version::initial());
-        store.extend(events);
+        let event_count: usize = streams.iter().map(|s| s.events.len()).sum();
+        let versions = streams
+            .into_iter()
+            .map(|stream| self.store_events(&mut store, stream.events))
+            .collect();
+        drop(store);
+        self.appended
+            .send_replace(self.head_sequence.load(Ordering::SeqCst));
 
-        tracing::info!(event_count, %aggregate_id, "events appended");
+        tracing::info!(event_count, "event batch appended");
         metrics::counter!("events_appended").increment(event_count as u64);
 
-        Ok(last_version)
+        Ok(versions)
     }
 
     #[tracing::instrument(skip(self))]
@@ -124,6 +233,9 @@ impl EventStore for InMemoryEventStore {
     }
 
     async fn query_events(&self, query: EventQuery) -> Result<Vec<EventEnvelope>> {
+        if let Some(token) = query.after_token {
+            session::wait_for_token(self, token, DEFAULT_SESSION_WAIT).await?;
+        }
         let store = self.events.read().await;
         let mut events: Vec<_> = store
             .iter()
@@ -174,6 +286,9 @@ impl EventStore for InMemoryEventStore {
                 .cmp(&b.timestamp)
                 .then(a.version.cmp(&b.version))
         });
+        if query.descending {
+            events.reverse();
+        }
 
         // Apply offset and limit
         let offset = query.offset.unwrap_or(0);
@@ -195,25 +310,64 @@ impl EventStore for InMemoryEventStore {
             .filter(|e| e.event_type == event_type)
             .cloned()
             .collect();
-        events.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+        events.sort_by_key(|a| a.timestamp);
         Ok(events)
     }
 
     async fn stream_all_events(&self) -> Result<EventStream> {
         use futures_util::stream;
 
-        let store = self.events.read().await;
-        let mut events = store.clone();
-        events.sort_by(|a, b| {
-            a.timestamp
-                .cmp(&b.timestamp)
-                .then(a.event_id.as_uuid().cmp(&b.event_id.as_uuid()))
-        });
+        // Events are kept in append order, which is global sequence order
+        let events = self.events.read().await.clone();
 
         let stream = stream::iter(events.into_iter().map(Ok));
         Ok(Box::pin(stream))
     }
 
+    async fn stream_events_after(&self, after_sequence: u64) -> Result<EventStream> {
+        use futures_util::stream;
+
+        let events = self.events.read().await;
+        let start = events.partition_point(|e| e.sequence.is_some_and(|s| s <= after_sequence));
+        let events = events[start..].to_vec();
+
+        Ok(Box::pin(stream::iter(events.into_iter().map(Ok))))
+    }
+
+    async fn subscribe(&self, after_sequence: u64) -> Result<EventStream> {
+        let events = self.events.clone();
+        let read_after = move |after: u64| {
+            let events = events.clone();
+            async move {
+                let events = events.read().await;
+                let start = events.partition_point(|e| e.sequence.is_some_and(|s| s <= after));
+                Ok(events[start..]
+                    .iter()
+                    .take(SUBSCRIPTION_BATCH_SIZE)
+                    .cloned()
+                    .collect())
+            }
+        };
+        Ok(subscription::tail(
+            after_sequence,
+            Some(self.appended.subscribe()),
+            read_after,
+        ))
+    }
+
+    async fn get_event(&self, event_id: EventId) -> Result<Option<EventEnvelope>> {
+        let store = self.events.read().await;
+        Ok(store.iter().find(|e| e.event_id == event_id).cloned())
+    }
+
+    async fn head_sequence(&self) -> Result<u64> {
+        Ok(self.head_sequence.load(Ordering::SeqCst))
+    }
+
+    fn append_notifications(&self) -> Option<watch::Receiver<u64>> {
+        Some(self.appended.subscribe())
+    }
+
     async fn get_aggregate_version(&self, aggregate_id: AggregateId) -> Result<Option<Version>> {
         let store = self.events.read().await;
         let version = store
@@ -236,9 +390,121 @@ impl EventStore for InMemoryEventStore {
     }
 }
 
+#[async_trait]
+impl ConsumerOffsetStore for InMemoryEventStore {
+    async fn get_offset(&self, consumer: &str) -> Result<Option<ConsumerOffset>> {
+        Ok(self.offsets.read().await.get(consumer).cloned())
+    }
+
+    async fn set_offset(&self, consumer: &str, sequence: u64) -> Result<ConsumerOffset> {
+        let offset = ConsumerOffset {
+            consumer: consumer.to_string(),
+            sequence,
+            updated_at: chrono::Utc::now(),
+        };
+        self.offsets
+            .write()
+            .await
+            .insert(consumer.to_string(), offset.clone());
+        Ok(offset)
+    }
+
+    async fn list_offsets(&self) -> Result<Vec<ConsumerOffset>> {
+        let mut offsets: Vec<_> = self.offsets.read().await.values().cloned().collect();
+        offsets.sort_by(|a, b| a.consumer.cmp(&b.consumer));
+        Ok(offsets)
+    }
+}
+
+#[async_trait]
+impl PendingEventStore for InMemoryEventStore {
+    #[tracing::instrument(skip(self, events))]
+    async fn append_pending(
+        &self,
+        events: Vec<EventEnvelope>,
+        options: AppendOptions,
+    ) -> Result<PendingBatchId> {
+        validate_events_for_append(&events).map_err(|e| {
+            EventStoreError::Serialization(serde_json::Error::io(std::io::Error::other(e.message)))
+        })?;
+
+        let aggregate_id = events[0].aggregate_id;
+        if let Some(expected) = options.expected_version {
+            let actual = self
+                .get_aggregate_version(aggregate_id)
+                .await?
+                .unwrap_or(Version::initial());
+            if actual != expected {
+                return Err(EventStoreError::ConcurrencyConflict {
+                    aggregate_id,
+                    expected,
+                    actual,
+                    conflicting: Vec::new(),
+                });
+            }
+        }
+
+        let batch = PendingBatch {
+            batch_id: PendingBatchId::new(),
+            aggregate_id,
+            expected_version: options.expected_version,
+            events,
+            staged_at: chrono::Utc::now(),
+        };
+        let batch_id = batch.batch_id;
+        self.pending.write().await.insert(batch_id, batch);
+
+        tracing::info!(%batch_id, %aggregate_id, "events staged");
+        Ok(batch_id)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn commit_pending(&self, batch_id: PendingBatchId) -> Result<Version> {
+        let batch = self
+            .pending
+            .write()
+            .await
+            .remove(&batch_id)
+            .ok_or(EventStoreError::PendingBatchNotFound(batch_id))?;
+
+        match self
+            .append(batch.events.clone(), batch.append_options())
+            .await
+        {
+            Ok(version) => Ok(version),
+            Err(e) => {
+                // Keep the batch so the caller can inspect or discard it
+                self.pending.write().await.insert(batch_id, batch);
+                Err(e)
+            }
+        }
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn discard_pending(&self, batch_id: PendingBatchId) -> Result<()> {
+        self.pending
+            .write()
+            .await
+            .remove(&batch_id)
+            .map(|_| ())
+            .ok_or(EventStoreError::PendingBatchNotFound(batch_id))
+    }
+
+    async fn get_pending(&self, batch_id: PendingBatchId) -> Result<Option<PendingBatch>> {
+        Ok(self.pending.read().await.get(&batch_id).cloned())
+    }
+
+    async fn list_pending(&self) -> Result<Vec<PendingBatch>> {
+        let mut batches: Vec<_> = self.pending.read().await.values().cloned().collect();
+        batches.sort_by_key(|b| b.staged_at);
+        Ok(batches)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use futures_util::StreamExt;
 
     fn create_test_event(
         aggregate_id: AggregateId,
@@ -314,6 +580,79 @@ mod tests {
         ));
     }
 
+    #[tokio::test]
+    async fn append_batch_is_all_or_nothing() {
+        let store = InMemoryEventStore::new();
+        let (order, saga) = (AggregateId::new(), AggregateId::new());
+        store
+            .append(
+                vec![create_test_event(order, Version::first(), "Created")],
+                AppendOptions::expect_new(),
+            )
+            .await
+            .unwrap();
+
+        // A stale version for the order keeps the saga's events out too
+        let result = store
+            .append_batch(vec![
+                StreamAppend::new(
+                    vec![create_test_event(saga, Version::first(), "Started")],
+                    AppendOptions::expect_new(),
+                ),
+                StreamAppend::new(
+                    vec![create_test_event(order, Version::new(2), "Reserved")],
+                    AppendOptions::expect_new(),
+                ),
+            ])
+            .await;
+        assert!(matches!(
+            result,
+            Err(EventStoreError::ConcurrencyConflict { aggregate_id, .. }) if aggregate_id == order
+        ));
+        assert_eq!(store.event_count().await, 1);
+
+        let versions = store
+            .append_batch(vec![
+                StreamAppend::new(
+                    vec![create_test_event(saga, Version::first(), "Started")],
+                    AppendOptions::expect_new(),
+                ),
+                StreamAppend::new(
+                    vec![
+                        create_test_event(order, Version::new(2), "Reserved"),
+                        create_test_event(order, Version::new(3), "Paid"),
+                    ],
+                    AppendOptions::expect_version(Version::first()),
+                ),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(versions, [Version::first(), Version::new(3)]);
+        let sequences: Vec<_> = store
+            .stream_all_events()
+            .await
+            .unwrap()
+            .map(|e| e.unwrap().sequence)
+            .collect()
+            .await;
+        assert_eq!(sequences, [Some(1), Some(2), Some(3), Some(4)]);
+
+        // Each aggregate may appear once
+        let result = store
+            .append_batch(vec![
+                StreamAppend::new(
+                    vec![create_test_event(saga, Version::new(2), "Stepped")],
+                    AppendOptions::new(),
+                ),
+                StreamAppend::new(
+                    vec![create_test_event(saga, Version::new(3), "Stepped")],
+                    AppendOptions::new(),
+                ),
+            ])
+            .await;
+        assert!(result.is_err());
+    }
+
     #[tokio::test]
     async fn concurrency_conflict_success() {
         let store = InMemoryEventStore::new();
@@ -447,6 +786,29 @@ mod tests {
         assert_eq!(results[0].version, Version::new(2));
     }
 
+    #[tokio::test]
+    async fn query_events_descending() {
+        let store = InMemoryEventStore::new();
+        let id1 = AggregateId::new();
+
+        let events = vec![
+            create_test_event(id1, Version::new(1), "Event1"),
+            create_test_event(id1, Version::new(2), "Event2"),
+            create_test_event(id1, Version::new(3), "Event3"),
+        ];
+        store.append(events, AppendOptions::new()).await.unwrap();
+
+        // Offset and limit page from the newest event
+        let query = EventQuery::for_aggregate(id1)
+            .descending()
+            .offset(1)
+            .limit(1);
+
+        let results = store.query_events(query).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].version, Version::new(2));
+    }
+
     #[tokio::test]
     async fn stream_all_events() {
         use futures_util::StreamExt;
@@ -475,6 +837,127 @@ mod tests {
         assert_eq!(events.len(), 2);
     }
 
+    #[tokio::test]
+    async fn stream_events_after_sequence() {
+        use futures_util::TryStreamExt;
+
+        let store = InMemoryEventStore::new();
+        let id = AggregateId::new();
+        store
+            .append(
+                vec![
+                    create_test_event(id, Version::new(1), "Event1"),
+                    create_test_event(id, Version::new(2), "Event2"),
+                    create_test_event(id, Version::new(3), "Event3"),
+                ],
+                AppendOptions::new(),
+            )
+            .await
+            .unwrap();
+
+        let events: Vec<_> = store
+            .stream_events_after(1)
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        let sequences: Vec<_> = events.iter().map(|e| e.sequence.unwrap()).collect();
+        assert_eq!(sequences, [2, 3]);
+
+        let stream = store.stream_events_after(3).await.unwrap();
+        assert!(stream.try_collect::<Vec<_>>().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn append_assigns_global_sequence() {
+        use futures_util::StreamExt;
+
+        let store = InMemoryEventStore::new();
+        let id1 = AggregateId::new();
+        let id2 = AggregateId::new();
+        assert_eq!(store.head_sequence().await.unwrap(), 0);
+
+        store
+            .append(
+                vec![
+                    create_test_event(id1, Version::new(1), "Event1"),
+                    create_test_event(id1, Version::new(2), "Event2"),
+                ],
+                AppendOptions::new(),
+            )
+            .await
+            .unwrap();
+        store
+            .append(
+                vec![create_test_event(id2, Version::first(), "Event3")],
+                AppendOptions::new(),
+            )
+            .await
+            .unwrap();
+
+        let events: Vec<_> = store.stream_all_events().await.unwrap().collect().await;
+        let sequences: Vec<_> = events
+            .into_iter()
+            .map(|e| e.unwrap().sequence.unwrap())
+            .collect();
+        assert_eq!(sequences, vec![1, 2, 3]);
+        assert_eq!(store.head_sequence().await.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn compact_archives_events_covered_by_snapshot() {
+        use crate::{EventStoreExt, InMemoryArchive};
+
+        let store = InMemoryEventStore::new();
+        let compacted = AggregateId::new();
+        let untouched = AggregateId::new();
+
+        store
+            .append(
+                (1..=4)
+                    .map(|v| create_test_event(compacted, Version::new(v), "Event"))
+                    .collect(),
+                AppendOptions::new(),
+            )
+            .await
+            .unwrap();
+        store
+            .append(
+                vec![create_test_event(untouched, Version::first(), "Event")],
+                AppendOptions::new(),
+            )
+            .await
+            .unwrap();
+        store
+            .save_snapshot(Snapshot::new(
+                compacted,
+                "TestAggregate",
+                Version::new(3),
+                serde_json::json!({}),
+            ))
+            .await
+            .unwrap();
+
+        let archive = InMemoryArchive::new();
+        assert_eq!(store.compact(&archive).await.unwrap(), 2);
+        assert_eq!(archive.event_count().await, 2);
+        assert_eq!(store.event_count().await, 3);
+
+        // Version and head are unaffected; the aggregate still loads from its snapshot
+        assert_eq!(
+            store.get_aggregate_version(compacted).await.unwrap(),
+            Some(Version::new(4))
+        );
+        assert_eq!(store.head_sequence().await.unwrap(), 5);
+        let (snapshot, events) = store.load_aggregate(compacted).await.unwrap();
+        assert!(snapshot.is_some());
+        assert_eq!(events.len(), 1);
+
+        // Nothing left to compact
+        assert_eq!(store.compact(&archive).await.unwrap(), 0);
+    }
+
     #[tokio::test]
     async fn get_aggregate_version() {
         let store = InMemoryEventStore::new();
@@ -494,4 +977,161 @@ mod tests {
         let version = store.get_aggregate_version(aggregate_id).await.unwrap();
         assert_eq!(version, Some(Version::new(2)));
     }
+
+    #[tokio::test]
+    async fn consumer_offsets_upsert_and_list() {
+        let store = InMemoryEventStore::new();
+        assert!(store.get_offset("warehouse").await.unwrap().is_none());
+
+        store.set_offset("warehouse", 3).await.unwrap();
+        store.set_offset("billing", 1).await.unwrap();
+        let updated = store.set_offset("warehouse", 7).await.unwrap();
+        assert_eq!(updated.sequence, 7);
+        assert_eq!(updated.lag(10), 3);
+
+        let offsets = store.list_offsets().await.unwrap();
+        let names: Vec<_> = offsets.iter().map(|o| o.consumer.as_str()).collect();
+        assert_eq!(names, ["billing", "warehouse"]);
+        assert_eq!(
+            store
+                .get_offset("warehouse")
+                .await
+                .unwrap()
+                .unwrap()
+                .sequence,
+            7
+        );
+
+        store.clear().await;
+        assert!(store.list_offsets().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn append_notifies_subscribers() {
+        let store = InMemoryEventStore::new();
+        let mut notifications = store.append_notifications().unwrap();
+        let aggregate_id = AggregateId::new();
+
+        let events = vec![
+            create_test_event(aggregate_id, Version::new(1), "Created"),
+            create_test_event(aggregate_id, Version::new(2), "Updated"),
+        ];
+        store
+            .append(events, AppendOptions::expect_new())
+            .await
+            .unwrap();
+
+        assert!(notifications.has_changed().unwrap());
+        assert_eq!(*notifications.borrow_and_update(), 2);
+        assert!(!notifications.has_changed().unwrap());
+    }
+
+    #[tokio::test]
+    async fn subscribe_replays_then_tails_appends() {
+        use futures_util::StreamExt;
+
+        let store = InMemoryEventStore::new();
+        let aggregate_id = AggregateId::new();
+        store
+            .append(
+                vec![
+                    create_test_event(aggregate_id, Version::new(1), "Created"),
+                    create_test_event(aggregate_id, Version::new(2), "Updated"),
+                ],
+                AppendOptions::expect_new(),
+            )
+            .await
+            .unwrap();
+
+        let mut subscription = store.subscribe(1).await.unwrap();
+        let replayed = subscription.next().await.unwrap().unwrap();
+        assert_eq!(replayed.sequence, Some(2));
+
+        let appender = store.clone();
+        tokio::spawn(async move {
+            appender
+                .append(
+                    vec![create_test_event(aggregate_id, Version::new(3), "Shipped")],
+                    AppendOptions::expect_version(Version::new(2)),
+                )
+                .await
+                .unwrap();
+        });
+        // Well under the poll interval, so the append notification woke it
+        let live = tokio::time::timeout(std::time::Duration::from_millis(500), subscription.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(live.sequence, Some(3));
+        assert_eq!(live.event_type, "Shipped");
+    }
+
+    #[tokio::test]
+    async fn pending_events_hidden_until_committed() {
+        let store = InMemoryEventStore::new();
+        let aggregate_id = AggregateId::new();
+
+        let batch_id = store
+            .append_pending(
+                vec![create_test_event(aggregate_id, Version::new(1), "Created")],
+                AppendOptions::expect_new(),
+            )
+            .await
+            .unwrap();
+
+        assert!(
+            store
+                .get_events_for_aggregate(aggregate_id)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(store.head_sequence().await.unwrap(), 0);
+        assert_eq!(store.list_pending().await.unwrap().len(), 1);
+
+        let version = store.commit_pending(batch_id).await.unwrap();
+        assert_eq!(version, Version::new(1));
+        assert_eq!(store.event_count().await, 1);
+        assert!(store.get_pending(batch_id).await.unwrap().is_none());
+
+        let result = store.commit_pending(batch_id).await;
+        assert!(matches!(
+            result,
+            Err(EventStoreError::PendingBatchNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn pending_commit_conflicts_if_aggregate_moved_on() {
+        let store = InMemoryEventStore::new();
+        let aggregate_id = AggregateId::new();
+
+        let batch_id = store
+            .append_pending(
+                vec![create_test_event(aggregate_id, Version::new(1), "Created")],
+                AppendOptions::expect_new(),
+            )
+            .await
+            .unwrap();
+        store
+            .append(
+                vec![create_test_event(aggregate_id, Version::new(1), "Created")],
+                AppendOptions::expect_new(),
+            )
+            .await
+            .unwrap();
+
+        let result = store.commit_pending(batch_id).await;
+        assert!(matches!(
+            result,
+            Err(EventStoreError::ConcurrencyConflict { .. })
+        ));
+
+        // The batch survives the failed commit until discarded
+        assert!(store.get_pending(batch_id).await.unwrap().is_some());
+        store.discard_pending(batch_id).await.unwrap();
+        assert!(store.list_pending().await.unwrap().is_empty());
+        assert_eq!(store.event_count().await, 1);
+    }
 }
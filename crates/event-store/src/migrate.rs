@@ -0,0 +1,344 @@
+//! Copying the event log from one store to another.
+//!
+//! Meant for moving data accumulated in an in-memory store into Postgres
+//! without stopping the process. Events are read from the source in global
+//! sequence order and appended to the target with their IDs, timestamps,
+//! metadata, and versions unchanged; the target assigns its own global
+//! sequences. After each append the source sequence copied so far is saved
+//! as a consumer offset in the target, so a run that stops partway, or a
+//! later run after more events arrive, picks up where the last one ended.
+//! Snapshots are not copied; they are rebuilt from the events.
+
+use std::collections::HashMap;
+
+use futures_util::StreamExt;
+
+use crate::offsets::ConsumerOffsetStore;
+use crate::store::{AppendOptions, EventStore};
+use crate::{AggregateId, EventEnvelope, Result, Version};
+
+/// Consumer offset name a migration records its checkpoint under by default.
+pub const DEFAULT_CHECKPOINT: &str = "store_migration";
+
+/// Most events appended to the target at once.
+const MAX_BATCH: usize = 500;
+
+/// How far a migration has got.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MigrationProgress {
+    /// Events appended to the target in this run.
+    pub copied: u64,
+    /// Events the target already held, from an interrupted earlier run.
+    pub skipped: u64,
+    /// Last source sequence copied.
+    pub checkpoint: u64,
+    /// Source head sequence when the run started.
+    pub source_head: u64,
+}
+
+/// An aggregate whose version differs between source and target.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionMismatch {
+    pub aggregate_id: AggregateId,
+    pub source: Version,
+    /// None if the target has no events for the aggregate.
+    pub target: Option<Version>,
+}
+
+/// Outcome of a migration run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationReport {
+    pub progress: MigrationProgress,
+    /// Aggregates whose versions don't match after the copy.
+    pub mismatches: Vec<VersionMismatch>,
+}
+
+impl MigrationReport {
+    /// Returns true if every aggregate has the same version in both stores.
+    pub fn is_verified(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+type ProgressFn<'a> = Box<dyn Fn(&MigrationProgress) + Send + Sync + 'a>;
+
+/// A copy of every event in `from` into `to`.
+pub struct StoreMigration<'a, F, T> {
+    from: &'a F,
+    to: &'a T,
+    checkpoint: String,
+    progress_every: u64,
+    progress: Option<ProgressFn<'a>>,
+}
+
+impl<'a, F, T> StoreMigration<'a, F, T>
+where
+    F: EventStore,
+    T: EventStore + ConsumerOffsetStore,
+{
+    /// Creates a migration checkpointed under [`DEFAULT_CHECKPOINT`], with
+    /// no progress reporting.
+    pub fn new(from: &'a F, to: &'a T) -> Self {
+        Self {
+            from,
+            to,
+            checkpoint: DEFAULT_CHECKPOINT.to_string(),
+            progress_every: 1000,
+            progress: None,
+        }
+    }
+
+    /// Records the checkpoint under `name`, for copying several sources
+    /// into one target.
+    pub fn with_checkpoint(mut self, name: impl Into<String>) -> Self {
+        self.checkpoint = name.into();
+        self
+    }
+
+    /// Calls `report` after roughly every `every` events, and once at the
+    /// end.
+    pub fn with_progress(
+        mut self,
+        every: u64,
+        report: impl Fn(&MigrationProgress) + Send + Sync + 'a,
+    ) -> Self {
+        self.progress_every = every.max(1);
+        self.progress = Some(Box::new(report));
+        self
+    }
+
+    /// Copies the events after the checkpoint, then checks every
+    /// aggregate's version in the target.
+    pub async fn run(&self) -> Result<MigrationReport> {
+        let start = self
+            .to
+            .get_offset(&self.checkpoint)
+            .await?
+            .map_or(0, |offset| offset.sequence);
+        let mut progress = MigrationProgress {
+            checkpoint: start,
+            source_head: self.from.head_sequence().await?,
+            ..MigrationProgress::default()
+        };
+        tracing::info!(
+            checkpoint = start,
+            source_head = progress.source_head,
+            "starting store migration"
+        );
+
+        let mut reported = 0;
+        let mut batch: Vec<EventEnvelope> = Vec::new();
+        let mut events = self.from.stream_all_events().await?;
+        while let Some(event) = events.next().await {
+            let event = event?;
+            if event.sequence.is_some_and(|sequence| sequence <= start) {
+                continue;
+            }
+            let switches_aggregate = batch
+                .last()
+                .is_some_and(|last| last.aggregate_id != event.aggregate_id);
+            if switches_aggregate || batch.len() >= MAX_BATCH {
+                self.copy(std::mem::take(&mut batch), &mut progress).await?;
+                if progress.copied + progress.skipped - reported >= self.progress_every {
+                    reported = progress.copied + progress.skipped;
+                    self.report(&progress);
+                }
+            }
+            batch.push(event);
+        }
+        if !batch.is_empty() {
+            self.copy(batch, &mut progress).await?;
+        }
+        self.report(&progress);
+
+        let mismatches = verify_versions(self.from, self.to).await?;
+        metrics::counter!("store_migration_events_copied").increment(progress.copied);
+        tracing::info!(
+            copied = progress.copied,
+            skipped = progress.skipped,
+            checkpoint = progress.checkpoint,
+            mismatches = mismatches.len(),
+            "store migration finished"
+        );
+        Ok(MigrationReport {
+            progress,
+            mismatches,
+        })
+    }
+
+    /// Appends a run of one aggregate's events the target doesn't have yet
+    /// and advances the checkpoint past them.
+    async fn copy(
+        &self,
+        batch: Vec<EventEnvelope>,
+        progress: &mut MigrationProgress,
+    ) -> Result<()> {
+        let Some(last) = batch.last() else {
+            return Ok(());
+        };
+        let sequence = last.sequence;
+        let current = self
+            .to
+            .get_aggregate_version(last.aggregate_id)
+            .await?
+            .unwrap_or_else(Version::initial);
+
+        // A run that stopped between the append and the checkpoint left
+        // these behind already
+        let (held, missing): (Vec<_>, Vec<_>) = batch
+            .into_iter()
+            .partition(|event| event.version <= current);
+        progress.skipped += held.len() as u64;
+        if !missing.is_empty() {
+            let count = missing.len() as u64;
+            self.to
+                .append(missing, AppendOptions::expect_version(current))
+                .await?;
+            progress.copied += count;
+        }
+
+        if let Some(sequence) = sequence {
+            self.to.set_offset(&self.checkpoint, sequence).await?;
+            progress.checkpoint = sequence;
+        }
+        Ok(())
+    }
+
+    fn report(&self, progress: &MigrationProgress) {
+        if let Some(report) = &self.progress {
+            report(progress);
+        }
+    }
+}
+
+/// Copies every event in `from` into `to`, resuming from the default
+/// checkpoint, and verifies the result.
+pub async fn migrate_store<F, T>(from: &F, to: &T) -> Result<MigrationReport>
+where
+    F: EventStore,
+    T: EventStore + ConsumerOffsetStore,
+{
+    StoreMigration::new(from, to).run().await
+}
+
+/// Compares each aggregate's version in `from` with its version in `to`.
+///
+/// Aggregates the target holds but the source doesn't are not reported.
+pub async fn verify_versions<F, T>(from: &F, to: &T) -> Result<Vec<VersionMismatch>>
+where
+    F: EventStore,
+    T: EventStore,
+{
+    let mut versions: HashMap<AggregateId, Version> = HashMap::new();
+    let mut events = from.stream_all_events().await?;
+    while let Some(event) = events.next().await {
+        let event = event?;
+        let version = versions.entry(event.aggregate_id).or_insert(event.version);
+        *version = (*version).max(event.version);
+    }
+
+    let mut mismatches = Vec::new();
+    for (aggregate_id, source) in versions {
+        let target = to.get_aggregate_version(aggregate_id).await?;
+        if target != Some(source) {
+            mismatches.push(VersionMismatch {
+                aggregate_id,
+                source,
+                target,
+            });
+        }
+    }
+    mismatches.sort_by_key(|m| m.aggregate_id.as_uuid());
+    Ok(mismatches)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::InMemoryEventStore;
+
+    fn event(aggregate_id: AggregateId, version: i64) -> EventEnvelope {
+        EventEnvelope::builder()
+            .aggregate_id(aggregate_id)
+            .aggregate_type("Order")
+            .event_type("ItemAdded")
+            .version(Version::new(version))
+            .payload_raw(serde_json::json!({ "n": version }))
+            .build()
+    }
+
+    async fn append(store: &InMemoryEventStore, aggregate_id: AggregateId, versions: &[i64]) {
+        let events = versions.iter().map(|v| event(aggregate_id, *v)).collect();
+        store.append(events, AppendOptions::new()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_copies_events_with_their_versions() {
+        let (a, b) = (AggregateId::new(), AggregateId::new());
+        let source = InMemoryEventStore::new();
+        append(&source, a, &[1, 2]).await;
+        append(&source, b, &[1]).await;
+        append(&source, a, &[3]).await;
+        let target = InMemoryEventStore::new();
+
+        let reports = Mutex::new(Vec::new());
+        let report = StoreMigration::new(&source, &target)
+            .with_progress(1, |p| reports.lock().unwrap().push(*p))
+            .run()
+            .await
+            .unwrap();
+        assert!(report.is_verified());
+        assert_eq!(report.progress.copied, 4);
+        assert_eq!(report.progress.checkpoint, 4);
+        assert_eq!(reports.lock().unwrap().last(), Some(&report.progress));
+
+        let copied = target.get_events_for_aggregate(a).await.unwrap();
+        let original = source.get_events_for_aggregate(a).await.unwrap();
+        let ids = |events: &[EventEnvelope]| events.iter().map(|e| e.event_id).collect::<Vec<_>>();
+        assert_eq!(ids(&copied), ids(&original));
+        assert_eq!(copied[2].version, Version::new(3));
+    }
+
+    #[tokio::test]
+    async fn test_resumes_from_checkpoint() {
+        let a = AggregateId::new();
+        let source = InMemoryEventStore::new();
+        append(&source, a, &[1, 2]).await;
+        let target = InMemoryEventStore::new();
+        migrate_store(&source, &target).await.unwrap();
+
+        // Events appended after the first run are copied by the next one
+        append(&source, a, &[3]).await;
+        let report = migrate_store(&source, &target).await.unwrap();
+        assert_eq!(report.progress.copied, 1);
+        assert_eq!(report.progress.checkpoint, 3);
+        assert!(report.is_verified());
+
+        // A run interrupted before saving its checkpoint skips what it copied
+        target.set_offset(DEFAULT_CHECKPOINT, 0).await.unwrap();
+        let report = migrate_store(&source, &target).await.unwrap();
+        assert_eq!(report.progress.copied, 0);
+        assert_eq!(report.progress.skipped, 3);
+        assert_eq!(target.head_sequence().await.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_reports_version_mismatches() {
+        let a = AggregateId::new();
+        let source = InMemoryEventStore::new();
+        append(&source, a, &[1, 2]).await;
+        let target = InMemoryEventStore::new();
+        append(&target, a, &[1]).await;
+
+        assert_eq!(
+            verify_versions(&source, &target).await.unwrap(),
+            [VersionMismatch {
+                aggregate_id: a,
+                source: Version::new(2),
+                target: Some(Version::new(1)),
+            }]
+        );
+    }
+}
@@ -0,0 +1,39 @@
+//! Named read positions for downstream consumers.
+//!
+//! External integrations that read the event log record the last global
+//! sequence they processed under a consumer name. Comparing that offset with
+//! the store's head sequence shows how far each consumer is behind.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::Result;
+
+/// The last global sequence a named consumer has processed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConsumerOffset {
+    pub consumer: String,
+    pub sequence: u64,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ConsumerOffset {
+    /// Returns how many events the consumer is behind `head_sequence`.
+    pub fn lag(&self, head_sequence: u64) -> u64 {
+        head_sequence.saturating_sub(self.sequence)
+    }
+}
+
+/// Storage for downstream consumer offsets.
+#[async_trait]
+pub trait ConsumerOffsetStore: Send + Sync {
+    /// Returns the offset stored for `consumer`, if any.
+    async fn get_offset(&self, consumer: &str) -> Result<Option<ConsumerOffset>>;
+
+    /// Records `sequence` as the consumer's offset, replacing any previous one.
+    async fn set_offset(&self, consumer: &str, sequence: u64) -> Result<ConsumerOffset>;
+
+    /// Returns all stored offsets, ordered by consumer name.
+    async fn list_offsets(&self) -> Result<Vec<ConsumerOffset>>;
+}
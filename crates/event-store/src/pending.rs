@@ -0,0 +1,101 @@
+//! Staged appends awaiting confirmation.
+//!
+//! Two-phase workflows (e.g. an append that needs external approval) stage
+//! their events as a pending batch first. Pending events are held outside
+//! the log: aggregate loads, queries, and projections don't see them until
+//! the batch is committed, at which point they are appended like any other
+//! events. Discarding a batch drops it without a trace in the log.
+
+use std::fmt;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::store::{AppendOptions, EventStore};
+use crate::{AggregateId, EventEnvelope, Result, Version};
+
+/// Identifier of a batch of pending events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PendingBatchId(Uuid);
+
+impl PendingBatchId {
+    /// Creates a new random batch ID.
+    pub fn new() -> Self {
+        Self(Uuid::new_v4())
+    }
+
+    /// Creates a batch ID from an existing UUID.
+    pub fn from_uuid(uuid: Uuid) -> Self {
+        Self(uuid)
+    }
+
+    /// Returns the underlying UUID.
+    pub fn as_uuid(&self) -> Uuid {
+        self.0
+    }
+}
+
+impl Default for PendingBatchId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for PendingBatchId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Events staged by one `append_pending` call.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingBatch {
+    pub batch_id: PendingBatchId,
+    pub aggregate_id: AggregateId,
+    /// Version the aggregate must still be at when the batch is committed.
+    pub expected_version: Option<Version>,
+    pub events: Vec<EventEnvelope>,
+    pub staged_at: DateTime<Utc>,
+}
+
+impl PendingBatch {
+    /// Returns the options the batch will be appended with on commit.
+    pub fn append_options(&self) -> AppendOptions {
+        AppendOptions {
+            expected_version: self.expected_version,
+        }
+    }
+}
+
+/// An event store that can stage appends until they are confirmed.
+#[async_trait]
+pub trait PendingEventStore: EventStore {
+    /// Stages events for a later commit and returns the batch ID.
+    ///
+    /// The events are validated as for [`EventStore::append`], and the
+    /// expected version (if any) is checked now and again on commit.
+    async fn append_pending(
+        &self,
+        events: Vec<EventEnvelope>,
+        options: AppendOptions,
+    ) -> Result<PendingBatchId>;
+
+    /// Appends a pending batch to the log and removes it from staging.
+    ///
+    /// Fails with `ConcurrencyConflict` if the aggregate moved past the
+    /// expected version since the batch was staged; the batch is then kept
+    /// so the caller can inspect or discard it.
+    async fn commit_pending(&self, batch_id: PendingBatchId) -> Result<Version>;
+
+    /// Drops a pending batch without appending it.
+    async fn discard_pending(&self, batch_id: PendingBatchId) -> Result<()>;
+
+    /// Returns a pending batch, if it is still staged.
+    async fn get_pending(&self, batch_id: PendingBatchId) -> Result<Option<PendingBatch>>;
+
+    /// Returns all staged batches, oldest first.
+    async fn list_pending(&self) -> Result<Vec<PendingBatch>>;
+}
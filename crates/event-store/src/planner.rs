@@ -0,0 +1,310 @@
+//! Index coverage for event queries.
+//!
+//! Each combination of [`EventQuery`] filters compiles to one SQL shape.
+//! [`index_advisory`] checks a query's shape against the indexes the bundled
+//! migrations create and explains when none of them narrows its range
+//! filter, which leaves the database filtering rows it has already read.
+//! Filtering on an aggregate ID is always considered indexed, since an
+//! aggregate holds few events.
+//!
+//! [`QueryAnalyzer`] asks the database itself how it would run a query.
+
+use async_trait::async_trait;
+use serde::Serialize;
+
+use crate::{EventQuery, Result};
+
+/// A column an [`EventQuery`] can filter on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryColumn {
+    AggregateId,
+    AggregateType,
+    EventType,
+    Version,
+    Timestamp,
+}
+
+impl QueryColumn {
+    /// Returns the column's name in the `events` table.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QueryColumn::AggregateId => "aggregate_id",
+            QueryColumn::AggregateType => "aggregate_type",
+            QueryColumn::EventType => "event_type",
+            QueryColumn::Version => "version",
+            QueryColumn::Timestamp => "timestamp",
+        }
+    }
+
+    /// Returns true for columns filtered by range rather than equality.
+    pub fn is_range(&self) -> bool {
+        matches!(self, QueryColumn::Version | QueryColumn::Timestamp)
+    }
+}
+
+/// An index on the `events` table.
+#[derive(Debug, Clone, Copy)]
+pub struct EventIndex {
+    pub name: &'static str,
+    pub columns: &'static [QueryColumn],
+}
+
+/// Indexes on the `events` table that serve [`EventQuery`] filters, as the
+/// bundled migrations leave them.
+pub const EVENT_INDEXES: &[EventIndex] = &[
+    EventIndex {
+        name: "unique_aggregate_version",
+        columns: &[QueryColumn::AggregateId, QueryColumn::Version],
+    },
+    EventIndex {
+        name: "idx_events_event_type_timestamp",
+        columns: &[
+            QueryColumn::EventType,
+            QueryColumn::Timestamp,
+            QueryColumn::Version,
+        ],
+    },
+    EventIndex {
+        name: "idx_events_aggregate_type_timestamp",
+        columns: &[
+            QueryColumn::AggregateType,
+            QueryColumn::Timestamp,
+            QueryColumn::Version,
+        ],
+    },
+    EventIndex {
+        name: "idx_events_timestamp",
+        columns: &[QueryColumn::Timestamp],
+    },
+];
+
+/// Why a query shape isn't fully served by an index, and the index that
+/// would serve it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct IndexAdvisory {
+    /// The filtered columns, e.g. `event_type+version`.
+    pub shape: String,
+    pub reason: String,
+    /// Statement creating an index that would serve the shape.
+    pub suggested_index: String,
+}
+
+/// Returns the columns `query` filters on, equality filters first.
+pub fn filtered_columns(query: &EventQuery) -> Vec<QueryColumn> {
+    [
+        (QueryColumn::AggregateId, query.aggregate_id.is_some()),
+        (QueryColumn::AggregateType, query.aggregate_type.is_some()),
+        (QueryColumn::EventType, query.event_types.is_some()),
+        (
+            QueryColumn::Timestamp,
+            query.from_timestamp.is_some() || query.to_timestamp.is_some(),
+        ),
+        (
+            QueryColumn::Version,
+            query.from_version.is_some() || query.to_version.is_some(),
+        ),
+    ]
+    .into_iter()
+    .filter_map(|(column, filtered)| filtered.then_some(column))
+    .collect()
+}
+
+/// Returns the shape of `query`: its filtered columns joined by `+`, or
+/// `all` when it filters on nothing.
+pub fn query_shape(query: &EventQuery) -> String {
+    let columns = filtered_columns(query);
+    if columns.is_empty() {
+        return "all".to_string();
+    }
+    columns
+        .iter()
+        .map(QueryColumn::as_str)
+        .collect::<Vec<_>>()
+        .join("+")
+}
+
+/// Checks `query` against [`EVENT_INDEXES`], returning advice when no index
+/// narrows every range it filters on. Unfiltered queries read the whole
+/// table by design and get no advice.
+pub fn index_advisory(query: &EventQuery) -> Option<IndexAdvisory> {
+    let columns = filtered_columns(query);
+    if columns.is_empty() || columns.contains(&QueryColumn::AggregateId) {
+        return None;
+    }
+
+    // The index whose leading columns cover the most filters
+    let (best, covered) = EVENT_INDEXES
+        .iter()
+        .map(|index| {
+            let covered = index
+                .columns
+                .iter()
+                .take_while(|column| columns.contains(column))
+                .count();
+            (index, &index.columns[..covered])
+        })
+        .max_by_key(|(_, covered)| covered.len())?;
+
+    let reason = if covered.is_empty() {
+        "no index leads with a filtered column, so the table is scanned".to_string()
+    } else {
+        let missed: Vec<_> = columns
+            .iter()
+            .filter(|column| column.is_range() && !covered.contains(column))
+            .map(QueryColumn::as_str)
+            .collect();
+        if missed.is_empty() {
+            return None;
+        }
+        format!(
+            "{} narrows by {} but not by {}, so those rows are filtered after reading",
+            best.name,
+            covered
+                .iter()
+                .map(QueryColumn::as_str)
+                .collect::<Vec<_>>()
+                .join(", "),
+            missed.join(", ")
+        )
+    };
+    Some(IndexAdvisory {
+        shape: query_shape(query),
+        reason,
+        suggested_index: format!(
+            "CREATE INDEX ON events ({})",
+            columns
+                .iter()
+                .map(QueryColumn::as_str)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    })
+}
+
+/// How the database would run an event query.
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryAnalysis {
+    /// The SQL the query compiles to.
+    pub sql: String,
+    /// The database's plan, as `EXPLAIN (FORMAT JSON)` reports it.
+    pub plan: serde_json::Value,
+    /// Indexes the plan reads, in plan order.
+    pub indexes_used: Vec<String>,
+    /// True if the plan reads the whole `events` table.
+    pub sequential_scan: bool,
+    /// Advice from [`index_advisory`], if any.
+    pub advisory: Option<IndexAdvisory>,
+}
+
+impl QueryAnalysis {
+    /// Summarizes `plan`, the `EXPLAIN (FORMAT JSON)` output for `query`
+    /// compiled to `sql`.
+    pub fn from_plan(query: &EventQuery, sql: String, plan: serde_json::Value) -> Self {
+        let mut indexes_used = Vec::new();
+        let mut sequential_scan = false;
+        let mut nodes: Vec<&serde_json::Value> = match &plan {
+            serde_json::Value::Array(roots) => roots.iter().map(|root| &root["Plan"]).collect(),
+            root => vec![&root["Plan"]],
+        };
+        while let Some(node) = nodes.pop() {
+            if let Some(index) = node["Index Name"].as_str() {
+                indexes_used.push(index.to_string());
+            }
+            if node["Node Type"] == "Seq Scan" && node["Relation Name"] == "events" {
+                sequential_scan = true;
+            }
+            if let Some(children) = node["Plans"].as_array() {
+                nodes.extend(children.iter().rev());
+            }
+        }
+        Self {
+            sql,
+            plan,
+            indexes_used,
+            sequential_scan,
+            advisory: index_advisory(query),
+        }
+    }
+}
+
+/// A store that can explain how its database would run a query.
+#[async_trait]
+pub trait QueryAnalyzer: Send + Sync {
+    /// Plans `query` without running it.
+    async fn analyze_query(&self, query: &EventQuery) -> Result<QueryAnalysis>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AggregateId, Version};
+    use chrono::Utc;
+
+    #[test]
+    fn test_common_shapes_are_indexed() {
+        let now = Utc::now();
+        for query in [
+            EventQuery::for_aggregate(AggregateId::new()).from_version(Version::new(3)),
+            EventQuery::for_event_type("OrderCreated").from_timestamp(now),
+            EventQuery::new()
+                .aggregate_type("Order")
+                .event_type("OrderCancelled")
+                .to_timestamp(now),
+            EventQuery::new().from_timestamp(now),
+            EventQuery::for_event_type("OrderCreated"),
+            EventQuery::new().limit(10),
+        ] {
+            assert_eq!(index_advisory(&query), None, "{}", query_shape(&query));
+        }
+    }
+
+    #[test]
+    fn test_unindexed_range_is_advised() {
+        let query = EventQuery::for_event_type("OrderCreated").from_version(Version::new(2));
+        let advisory = index_advisory(&query).unwrap();
+        assert_eq!(advisory.shape, "event_type+version");
+        assert!(advisory.reason.contains("idx_events_event_type_timestamp"));
+        assert_eq!(
+            advisory.suggested_index,
+            "CREATE INDEX ON events (event_type, version)"
+        );
+
+        let query = EventQuery::new().to_version(Version::new(5));
+        assert!(
+            index_advisory(&query)
+                .unwrap()
+                .reason
+                .contains("table is scanned")
+        );
+    }
+
+    #[test]
+    fn test_plan_summary() {
+        let plan = serde_json::json!([{
+            "Plan": {
+                "Node Type": "Limit",
+                "Plans": [{
+                    "Node Type": "Sort",
+                    "Plans": [
+                        {
+                            "Node Type": "Bitmap Heap Scan",
+                            "Relation Name": "events",
+                            "Plans": [{
+                                "Node Type": "Bitmap Index Scan",
+                                "Index Name": "idx_events_event_type_timestamp"
+                            }]
+                        },
+                        { "Node Type": "Seq Scan", "Relation Name": "snapshots" }
+                    ]
+                }]
+            }
+        }]);
+        let query = EventQuery::for_event_type("OrderCreated");
+
+        let analysis = QueryAnalysis::from_plan(&query, "SELECT".to_string(), plan);
+
+        assert_eq!(analysis.indexes_used, ["idx_events_event_type_timestamp"]);
+        assert!(!analysis.sequential_scan);
+        assert!(analysis.advisory.is_none());
+    }
+}
@@ -1,39 +1,121 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use sqlx::{
-    PgPool, Row,
-    postgres::{PgPoolOptions, PgRow},
+    PgPool, Postgres, Row, Transaction,
+    migrate::Migrator,
+    postgres::{PgArguments, PgListener, PgPoolOptions, PgRow},
+    query::Query,
 };
+use tokio::sync::watch;
 use uuid::Uuid;
 
 use crate::{
     AggregateId, EventEnvelope, EventId, EventQuery, EventStoreError, Result, Snapshot, Version,
-    store::{AppendOptions, EventStore, EventStream, validate_events_for_append},
+    annotations::{AnnotationStore, EventAnnotation},
+    archive::ArchiveSink,
+    idempotency::{IdempotencyClaim, IdempotencyStore, StoredResponse},
+    offsets::{ConsumerOffset, ConsumerOffsetStore},
+    pending::{PendingBatch, PendingBatchId, PendingEventStore},
+    planner::{QueryAnalysis, QueryAnalyzer, index_advisory},
+    schema::{SchemaVersion, SchemaVersionSource},
+    session::{self, DEFAULT_SESSION_WAIT},
+    store::{
+        AppendOptions, EventStore, EventStream, StreamAppend, validate_batch_for_append,
+        validate_events_for_append,
+    },
+    subscription::{self, SUBSCRIPTION_BATCH_SIZE},
+    visibility::{StreamVisibility, StreamVisibilityStore},
 };
 
+/// Channel the `events` table trigger announces appends on.
+const APPEND_CHANNEL: &str = "events_appended";
+
+/// Migrations bundled with this build.
+static MIGRATOR: Migrator = sqlx::migrate!("../../migrations");
+
+/// Delay before the append listener retries after a connection error.
+const LISTENER_RETRY_DELAY: Duration = Duration::from_secs(1);
+
 /// PostgreSQL-backed event store implementation.
 #[derive(Clone)]
 pub struct PostgresEventStore {
     pool: PgPool,
+    appended: Option<Arc<watch::Sender<u64>>>,
+    advised_shapes: Arc<Mutex<HashSet<String>>>,
 }
 
 impl PostgresEventStore {
     /// Creates a new PostgreSQL event store.
     pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+        Self {
+            pool,
+            appended: None,
+            advised_shapes: Arc::default(),
+        }
+    }
+
+    /// Listens for append notifications from the database, including
+    /// appends made by other processes, and exposes them through
+    /// [`EventStore::append_notifications`].
+    ///
+    /// Holds one connection outside the pool. If the connection drops, the
+    /// listener reconnects; notifications sent in between are lost.
+    pub async fn with_append_listener(mut self) -> Result<Self> {
+        let mut listener = PgListener::connect_with(&self.pool).await?;
+        listener.listen(APPEND_CHANNEL).await?;
+
+        let appended = Arc::new(watch::Sender::new(0));
+        let sender = Arc::downgrade(&appended);
+        tokio::spawn(async move {
+            loop {
+                let notification = match listener.recv().await {
+                    Ok(notification) => notification,
+                    Err(e) => {
+                        tracing::warn!(error = %e, "append listener error");
+                        tokio::time::sleep(LISTENER_RETRY_DELAY).await;
+                        continue;
+                    }
+                };
+                // Stop once every store clone is gone
+                let Some(sender) = sender.upgrade() else {
+                    return;
+                };
+                if let Ok(sequence) = notification.payload().parse() {
+                    sender.send_if_modified(|head| {
+                        let advanced = sequence > *head;
+                        *head = (*head).max(sequence);
+                        advanced
+                    });
+                }
+            }
+        });
+
+        self.appended = Some(appended);
+        Ok(self)
     }
 
     /// Connects to PostgreSQL, creates a connection pool, and runs migrations.
     pub async fn connect(database_url: &str, max_connections: u32) -> Result<Self> {
+        let store = Self::connect_without_migrations(database_url, max_connections).await?;
+        store.run_migrations().await?;
+        Ok(store)
+    }
+
+    /// Connects to PostgreSQL and creates a connection pool, leaving the
+    /// schema as it is.
+    pub async fn connect_without_migrations(
+        database_url: &str,
+        max_connections: u32,
+    ) -> Result<Self> {
         let pool = PgPoolOptions::new()
             .max_connections(max_connections)
             .connect(database_url)
             .await?;
-        let store = Self::new(pool);
-        store.run_migrations().await?;
-        Ok(store)
+        Ok(Self::new(pool))
     }
 
     /// Gets a reference to the underlying connection pool.
@@ -43,45 +125,74 @@ impl PostgresEventStore {
 
     /// Runs the database migrations.
     pub async fn run_migrations(&self) -> std::result::Result<(), sqlx::migrate::MigrateError> {
-        sqlx::migrate!("../../migrations").run(&self.pool).await
+        MIGRATOR.run(&self.pool).await
     }
 
-    fn row_to_event(row: PgRow) -> Result<EventEnvelope> {
-        let metadata_json: serde_json::Value = row.try_get("metadata")?;
-        let metadata: HashMap<String, serde_json::Value> = serde_json::from_value(metadata_json)?;
-
-        Ok(EventEnvelope {
-            event_id: EventId::from_uuid(row.try_get::<Uuid, _>("id")?),
-            event_type: row.try_get("event_type")?,
-            aggregate_id: AggregateId::from_uuid(row.try_get::<Uuid, _>("aggregate_id")?),
-            aggregate_type: row.try_get("aggregate_type")?,
-            version: Version::new(row.try_get("version")?),
-            timestamp: row.try_get("timestamp")?,
-            payload: row.try_get("payload")?,
-            metadata,
-        })
+    /// Returns the highest migration bundled with this build.
+    pub fn expected_schema_version() -> i64 {
+        MIGRATOR
+            .iter()
+            .map(|migration| migration.version)
+            .max()
+            .unwrap_or(0)
     }
-}
 
-#[async_trait]
-impl EventStore for PostgresEventStore {
-    async fn append(&self, events: Vec<EventEnvelope>, options: AppendOptions) -> Result<Version> {
-        validate_events_for_append(&events).map_err(|e| {
-            EventStoreError::Serialization(serde_json::Error::io(std::io::Error::other(e.message)))
-        })?;
+    /// Moves events already covered by a snapshot into the archive.
+    ///
+    /// Events older than their aggregate's snapshot version are deleted in a
+    /// transaction that only commits once the sink has accepted them.
+    ///
+    /// Returns the number of events archived.
+    #[tracing::instrument(skip(self, sink))]
+    pub async fn compact(&self, sink: &dyn ArchiveSink) -> Result<usize> {
+        let mut tx = self.pool.begin().await?;
 
+        let rows = sqlx::query(
+            r#"
+            DELETE FROM events e
+            USING snapshots s
+            WHERE e.aggregate_id = s.aggregate_id AND e.version < s.version
+            RETURNING e.id, e.event_type, e.aggregate_id, e.aggregate_type, e.version, e.timestamp, e.payload, e.metadata, e.sequence
+            "#,
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let mut archived = rows
+            .into_iter()
+            .map(Self::row_to_event)
+            .collect::<Result<Vec<_>>>()?;
+        if archived.is_empty() {
+            return Ok(0);
+        }
+        archived.sort_by_key(|e| e.sequence);
+
+        let count = archived.len();
+        sink.archive(archived).await?;
+        tx.commit().await?;
+
+        tracing::info!(count, "events compacted");
+        metrics::counter!("events_archived").increment(count as u64);
+
+        Ok(count)
+    }
+
+    /// Inserts a validated batch of events within `tx`, checking the
+    /// expected version first.
+    async fn insert_events(
+        tx: &mut Transaction<'_, Postgres>,
+        events: &[EventEnvelope],
+        options: &AppendOptions,
+    ) -> Result<Version> {
         let first_event = &events[0];
         let aggregate_id = first_event.aggregate_id;
 
-        // Start a transaction
-        let mut tx = self.pool.begin().await?;
-
         // Check expected version if specified
         if let Some(expected) = options.expected_version {
             let current_version: Option<i64> =
                 sqlx::query_scalar("SELECT MAX(version) FROM events WHERE aggregate_id = $1")
                     .bind(aggregate_id.as_uuid())
-                    .fetch_one(&mut *tx)
+                    .fetch_one(&mut **tx)
                     .await?;
 
             let actual = Version::new(current_version.unwrap_or(0));
@@ -91,95 +202,78 @@ impl EventStore for PostgresEventStore {
                     aggregate_id,
                     expected,
                     actual,
+                    conflicting: Vec::new(),
                 });
             }
         }
 
-        // Insert all events
-        let mut last_version = Version::initial();
-        for event in &events {
-            let metadata_json = serde_json::to_value(&event.metadata)?;
-
-            sqlx::query(
-                r#"
-                INSERT INTO events (id, event_type, aggregate_id, aggregate_type, version, timestamp, payload, metadata)
-                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
-                "#,
-            )
-            .bind(event.event_id.as_uuid())
-            .bind(&event.event_type)
-            .bind(event.aggregate_id.as_uuid())
-            .bind(&event.aggregate_type)
-            .bind(event.version.as_i64())
-            .bind(event.timestamp)
-            .bind(&event.payload)
-            .bind(metadata_json)
-            .execute(&mut *tx)
-            .await
-            .map_err(|e| {
-                // Check if this is a unique constraint violation (concurrency conflict)
-                if let sqlx::Error::Database(ref db_err) = e
-                    && db_err.constraint() == Some("unique_aggregate_version")
-                {
-                    return EventStoreError::ConcurrencyConflict {
-                        aggregate_id,
-                        expected: options.expected_version.unwrap_or(Version::initial()),
-                        actual: event.version,
-                    };
-                }
-                EventStoreError::Database(e)
-            })?;
-
-            last_version = event.version;
+        // Insert all events in one statement. Each column is bound as an
+        // array, so the parameter count stays fixed however large the batch;
+        // ordinality keeps the global sequence in event order.
+        let mut ids = Vec::with_capacity(events.len());
+        let mut event_types = Vec::with_capacity(events.len());
+        let mut aggregate_ids = Vec::with_capacity(events.len());
+        let mut aggregate_types = Vec::with_capacity(events.len());
+        let mut versions = Vec::with_capacity(events.len());
+        let mut timestamps = Vec::with_capacity(events.len());
+        let mut payloads = Vec::with_capacity(events.len());
+        let mut metadata = Vec::with_capacity(events.len());
+        for event in events {
+            ids.push(event.event_id.as_uuid());
+            event_types.push(event.event_type.as_str());
+            aggregate_ids.push(event.aggregate_id.as_uuid());
+            aggregate_types.push(event.aggregate_type.as_str());
+            versions.push(event.version.as_i64());
+            timestamps.push(event.timestamp);
+            payloads.push(&event.payload);
+            metadata.push(serde_json::to_value(&event.metadata)?);
         }
 
-        tx.commit().await?;
-        Ok(last_version)
-    }
-
-    async fn get_events_for_aggregate(
-        &self,
-        aggregate_id: AggregateId,
-    ) -> Result<Vec<EventEnvelope>> {
-        let rows = sqlx::query(
-            r#"
-            SELECT id, event_type, aggregate_id, aggregate_type, version, timestamp, payload, metadata
-            FROM events
-            WHERE aggregate_id = $1
-            ORDER BY version ASC
-            "#,
-        )
-        .bind(aggregate_id.as_uuid())
-        .fetch_all(&self.pool)
-        .await?;
-
-        rows.into_iter().map(Self::row_to_event).collect()
-    }
-
-    async fn get_events_for_aggregate_from_version(
-        &self,
-        aggregate_id: AggregateId,
-        from_version: Version,
-    ) -> Result<Vec<EventEnvelope>> {
-        let rows = sqlx::query(
+        sqlx::query(
             r#"
+            INSERT INTO events (id, event_type, aggregate_id, aggregate_type, version, timestamp, payload, metadata)
             SELECT id, event_type, aggregate_id, aggregate_type, version, timestamp, payload, metadata
-            FROM events
-            WHERE aggregate_id = $1 AND version >= $2
-            ORDER BY version ASC
+            FROM UNNEST($1::uuid[], $2::text[], $3::uuid[], $4::text[], $5::bigint[], $6::timestamptz[], $7::jsonb[], $8::jsonb[])
+                WITH ORDINALITY AS e(id, event_type, aggregate_id, aggregate_type, version, timestamp, payload, metadata, ord)
+            ORDER BY ord
             "#,
         )
-        .bind(aggregate_id.as_uuid())
-        .bind(from_version.as_i64())
-        .fetch_all(&self.pool)
-        .await?;
+        .bind(&ids)
+        .bind(&event_types)
+        .bind(&aggregate_ids)
+        .bind(&aggregate_types)
+        .bind(&versions)
+        .bind(&timestamps)
+        .bind(&payloads)
+        .bind(&metadata)
+        .execute(&mut **tx)
+        .await
+        .map_err(|e| {
+            // Check if this is a unique constraint violation (concurrency conflict)
+            if let sqlx::Error::Database(ref db_err) = e
+                && db_err.constraint() == Some("unique_aggregate_version")
+            {
+                return EventStoreError::ConcurrencyConflict {
+                    aggregate_id,
+                    expected: options.expected_version.unwrap_or(Version::initial()),
+                    actual: first_event.version,
+                    conflicting: Vec::new(),
+                };
+            }
+            EventStoreError::Database(e)
+        })?;
 
-        rows.into_iter().map(Self::row_to_event).collect()
+        let last_version = events
+            .last()
+            .map_or(Version::initial(), |event| event.version);
+        Ok(last_version)
     }
 
-    async fn query_events(&self, query: EventQuery) -> Result<Vec<EventEnvelope>> {
+    /// Compiles `query` to SQL, with a numbered parameter for each filter
+    /// in the order [`bind_query`](Self::bind_query) binds them.
+    fn query_sql(query: &EventQuery) -> String {
         let mut sql = String::from(
-            "SELECT id, event_type, aggregate_id, aggregate_type, version, timestamp, payload, metadata FROM events WHERE 1=1",
+            "SELECT id, event_type, aggregate_id, aggregate_type, version, timestamp, payload, metadata, sequence FROM events WHERE 1=1",
         );
         let mut param_count = 0;
 
@@ -213,7 +307,11 @@ impl EventStore for PostgresEventStore {
             sql.push_str(&format!(" AND timestamp <= ${param_count}"));
         }
 
-        sql.push_str(" ORDER BY timestamp ASC, version ASC");
+        if query.descending {
+            sql.push_str(" ORDER BY timestamp DESC, version DESC");
+        } else {
+            sql.push_str(" ORDER BY timestamp ASC, version ASC");
+        }
 
         if query.limit.is_some() {
             param_count += 1;
@@ -224,8 +322,12 @@ impl EventStore for PostgresEventStore {
             sql.push_str(&format!(" OFFSET ${param_count}"));
         }
 
-        // Build and execute query with parameters
-        let mut sqlx_query = sqlx::query(&sql);
+        sql
+    }
+
+    /// Binds the filters of `query` to `sql` from [`query_sql`](Self::query_sql).
+    fn bind_query(sql: &str, query: EventQuery) -> Query<'_, Postgres, PgArguments> {
+        let mut sqlx_query = sqlx::query(sql);
 
         if let Some(id) = query.aggregate_id {
             sqlx_query = sqlx_query.bind(id.as_uuid());
@@ -255,14 +357,164 @@ impl EventStore for PostgresEventStore {
             sqlx_query = sqlx_query.bind(offset as i64);
         }
 
-        let rows = sqlx_query.fetch_all(&self.pool).await?;
+        sqlx_query
+    }
+
+    /// Warns, once per shape, when `query` filters in a way no index
+    /// fully serves, and counts every such query.
+    fn advise_on(&self, query: &EventQuery) {
+        let Some(advisory) = index_advisory(query) else {
+            return;
+        };
+        metrics::counter!("event_queries_unindexed", "shape" => advisory.shape.clone())
+            .increment(1);
+        let first = self
+            .advised_shapes
+            .lock()
+            .expect("advised shapes poisoned")
+            .insert(advisory.shape.clone());
+        if first {
+            tracing::warn!(
+                shape = %advisory.shape,
+                reason = %advisory.reason,
+                suggested_index = %advisory.suggested_index,
+                "event query shape not fully indexed"
+            );
+        }
+    }
+
+    fn row_to_event(row: PgRow) -> Result<EventEnvelope> {
+        let metadata_json: serde_json::Value = row.try_get("metadata")?;
+        let metadata: HashMap<String, serde_json::Value> = serde_json::from_value(metadata_json)?;
+
+        Ok(EventEnvelope {
+            event_id: EventId::from_uuid(row.try_get::<Uuid, _>("id")?),
+            event_type: row.try_get("event_type")?,
+            aggregate_id: AggregateId::from_uuid(row.try_get::<Uuid, _>("aggregate_id")?),
+            aggregate_type: row.try_get("aggregate_type")?,
+            version: Version::new(row.try_get("version")?),
+            timestamp: row.try_get("timestamp")?,
+            payload: row.try_get("payload")?,
+            metadata,
+            sequence: Some(row.try_get::<i64, _>("sequence")? as u64),
+        })
+    }
+
+    fn row_to_pending(row: PgRow) -> Result<PendingBatch> {
+        let expected_version: Option<i64> = row.try_get("expected_version")?;
+        let events: serde_json::Value = row.try_get("events")?;
+
+        Ok(PendingBatch {
+            batch_id: PendingBatchId::from_uuid(row.try_get::<Uuid, _>("batch_id")?),
+            aggregate_id: AggregateId::from_uuid(row.try_get::<Uuid, _>("aggregate_id")?),
+            expected_version: expected_version.map(Version::new),
+            events: serde_json::from_value(events)?,
+            staged_at: row.try_get::<DateTime<Utc>, _>("staged_at")?,
+        })
+    }
+
+    fn row_to_annotation(row: PgRow) -> Result<EventAnnotation> {
+        Ok(EventAnnotation {
+            annotation_id: row.try_get("annotation_id")?,
+            event_id: EventId::from_uuid(row.try_get::<Uuid, _>("event_id")?),
+            author: row.try_get("author")?,
+            note: row.try_get("note")?,
+            created_at: row.try_get::<DateTime<Utc>, _>("created_at")?,
+        })
+    }
+
+    fn row_to_offset(row: PgRow) -> Result<ConsumerOffset> {
+        Ok(ConsumerOffset {
+            consumer: row.try_get("consumer")?,
+            sequence: row.try_get::<i64, _>("sequence")? as u64,
+            updated_at: row.try_get::<DateTime<Utc>, _>("updated_at")?,
+        })
+    }
+}
+
+#[async_trait]
+impl EventStore for PostgresEventStore {
+    async fn append(&self, events: Vec<EventEnvelope>, options: AppendOptions) -> Result<Version> {
+        validate_events_for_append(&events).map_err(|e| {
+            EventStoreError::Serialization(serde_json::Error::io(std::io::Error::other(e.message)))
+        })?;
+
+        let mut tx = self.pool.begin().await?;
+        let last_version = Self::insert_events(&mut tx, &events, &options).await?;
+        tx.commit().await?;
+        Ok(last_version)
+    }
+
+    async fn append_batch(&self, streams: Vec<StreamAppend>) -> Result<Vec<Version>> {
+        validate_batch_for_append(&streams).map_err(|e| {
+            EventStoreError::Serialization(serde_json::Error::io(std::io::Error::other(e.message)))
+        })?;
+
+        // A conflict on any stream returns before commit, rolling back the
+        // streams inserted so far
+        let mut tx = self.pool.begin().await?;
+        let mut versions = Vec::with_capacity(streams.len());
+        for stream in &streams {
+            versions.push(Self::insert_events(&mut tx, &stream.events, &stream.options).await?);
+        }
+        tx.commit().await?;
+        Ok(versions)
+    }
+
+    async fn get_events_for_aggregate(
+        &self,
+        aggregate_id: AggregateId,
+    ) -> Result<Vec<EventEnvelope>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, event_type, aggregate_id, aggregate_type, version, timestamp, payload, metadata, sequence
+            FROM events
+            WHERE aggregate_id = $1
+            ORDER BY version ASC
+            "#,
+        )
+        .bind(aggregate_id.as_uuid())
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(Self::row_to_event).collect()
+    }
+
+    async fn get_events_for_aggregate_from_version(
+        &self,
+        aggregate_id: AggregateId,
+        from_version: Version,
+    ) -> Result<Vec<EventEnvelope>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, event_type, aggregate_id, aggregate_type, version, timestamp, payload, metadata, sequence
+            FROM events
+            WHERE aggregate_id = $1 AND version >= $2
+            ORDER BY version ASC
+            "#,
+        )
+        .bind(aggregate_id.as_uuid())
+        .bind(from_version.as_i64())
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(Self::row_to_event).collect()
+    }
+
+    async fn query_events(&self, query: EventQuery) -> Result<Vec<EventEnvelope>> {
+        if let Some(token) = query.after_token {
+            session::wait_for_token(self, token, DEFAULT_SESSION_WAIT).await?;
+        }
+        self.advise_on(&query);
+        let sql = Self::query_sql(&query);
+        let rows = Self::bind_query(&sql, query).fetch_all(&self.pool).await?;
         rows.into_iter().map(Self::row_to_event).collect()
     }
 
     async fn get_events_by_type(&self, event_type: &str) -> Result<Vec<EventEnvelope>> {
         let rows = sqlx::query(
             r#"
-            SELECT id, event_type, aggregate_id, aggregate_type, version, timestamp, payload, metadata
+            SELECT id, event_type, aggregate_id, aggregate_type, version, timestamp, payload, metadata, sequence
             FROM events
             WHERE event_type = $1
             ORDER BY timestamp ASC
@@ -280,11 +532,32 @@ impl EventStore for PostgresEventStore {
 
         let stream = sqlx::query(
             r#"
-            SELECT id, event_type, aggregate_id, aggregate_type, version, timestamp, payload, metadata
+            SELECT id, event_type, aggregate_id, aggregate_type, version, timestamp, payload, metadata, sequence
+            FROM events
+            ORDER BY sequence ASC
+            "#,
+        )
+        .fetch(&self.pool)
+        .map(|result| match result {
+            Ok(row) => Self::row_to_event(row),
+            Err(e) => Err(EventStoreError::Database(e)),
+        });
+
+        Ok(Box::pin(stream))
+    }
+
+    async fn stream_events_after(&self, after_sequence: u64) -> Result<EventStream> {
+        use futures_util::StreamExt;
+
+        let stream = sqlx::query(
+            r#"
+            SELECT id, event_type, aggregate_id, aggregate_type, version, timestamp, payload, metadata, sequence
             FROM events
-            ORDER BY timestamp ASC, id ASC
+            WHERE sequence > $1
+            ORDER BY sequence ASC
             "#,
         )
+        .bind(after_sequence as i64)
         .fetch(&self.pool)
         .map(|result| match result {
             Ok(row) => Self::row_to_event(row),
@@ -294,6 +567,60 @@ impl EventStore for PostgresEventStore {
         Ok(Box::pin(stream))
     }
 
+    async fn subscribe(&self, after_sequence: u64) -> Result<EventStream> {
+        let pool = self.pool.clone();
+        let read_after = move |after: u64| {
+            let pool = pool.clone();
+            async move {
+                let rows: Vec<PgRow> = sqlx::query(
+                    r#"
+                    SELECT id, event_type, aggregate_id, aggregate_type, version, timestamp, payload, metadata, sequence
+                    FROM events
+                    WHERE sequence > $1
+                    ORDER BY sequence ASC
+                    LIMIT $2
+                    "#,
+                )
+                .bind(after as i64)
+                .bind(SUBSCRIPTION_BATCH_SIZE as i64)
+                .fetch_all(&pool)
+                .await?;
+                rows.into_iter().map(Self::row_to_event).collect()
+            }
+        };
+        Ok(subscription::tail(
+            after_sequence,
+            self.append_notifications(),
+            read_after,
+        ))
+    }
+
+    async fn get_event(&self, event_id: EventId) -> Result<Option<EventEnvelope>> {
+        let row: Option<PgRow> = sqlx::query(
+            r#"
+            SELECT id, event_type, aggregate_id, aggregate_type, version, timestamp, payload, metadata, sequence
+            FROM events
+            WHERE id = $1
+            "#,
+        )
+        .bind(event_id.as_uuid())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.map(Self::row_to_event).transpose()
+    }
+
+    async fn head_sequence(&self) -> Result<u64> {
+        let head: Option<i64> = sqlx::query_scalar("SELECT MAX(sequence) FROM events")
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(head.unwrap_or(0) as u64)
+    }
+
+    fn append_notifications(&self) -> Option<watch::Receiver<u64>> {
+        self.appended.as_ref().map(|sender| sender.subscribe())
+    }
+
     async fn get_aggregate_version(&self, aggregate_id: AggregateId) -> Result<Option<Version>> {
         let version: Option<i64> =
             sqlx::query_scalar("SELECT MAX(version) FROM events WHERE aggregate_id = $1")
@@ -351,3 +678,395 @@ impl EventStore for PostgresEventStore {
         }
     }
 }
+
+#[async_trait]
+impl SchemaVersionSource for PostgresEventStore {
+    async fn schema_version(&self) -> Result<SchemaVersion> {
+        // sqlx records applied migrations in _sqlx_migrations, created by
+        // the first migration run
+        let tracked: bool =
+            sqlx::query_scalar("SELECT to_regclass('_sqlx_migrations') IS NOT NULL")
+                .fetch_one(&self.pool)
+                .await?;
+        let applied = if tracked {
+            sqlx::query_scalar("SELECT MAX(version) FROM _sqlx_migrations WHERE success")
+                .fetch_one(&self.pool)
+                .await?
+        } else {
+            None
+        };
+        Ok(SchemaVersion {
+            applied,
+            expected: Self::expected_schema_version(),
+        })
+    }
+}
+
+#[async_trait]
+impl QueryAnalyzer for PostgresEventStore {
+    async fn analyze_query(&self, query: &EventQuery) -> Result<QueryAnalysis> {
+        let sql = Self::query_sql(query);
+        let explain = format!("EXPLAIN (FORMAT JSON) {sql}");
+        let plan: serde_json::Value = Self::bind_query(&explain, query.clone())
+            .fetch_one(&self.pool)
+            .await?
+            .try_get(0)?;
+        Ok(QueryAnalysis::from_plan(query, sql, plan))
+    }
+}
+
+#[async_trait]
+impl ConsumerOffsetStore for PostgresEventStore {
+    async fn get_offset(&self, consumer: &str) -> Result<Option<ConsumerOffset>> {
+        let row: Option<PgRow> = sqlx::query(
+            r#"
+            SELECT consumer, sequence, updated_at
+            FROM consumer_offsets
+            WHERE consumer = $1
+            "#,
+        )
+        .bind(consumer)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.map(Self::row_to_offset).transpose()
+    }
+
+    async fn set_offset(&self, consumer: &str, sequence: u64) -> Result<ConsumerOffset> {
+        let row: PgRow = sqlx::query(
+            r#"
+            INSERT INTO consumer_offsets (consumer, sequence, updated_at)
+            VALUES ($1, $2, NOW())
+            ON CONFLICT (consumer) DO UPDATE SET
+                sequence = EXCLUDED.sequence,
+                updated_at = EXCLUDED.updated_at
+            RETURNING consumer, sequence, updated_at
+            "#,
+        )
+        .bind(consumer)
+        .bind(sequence as i64)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Self::row_to_offset(row)
+    }
+
+    async fn list_offsets(&self) -> Result<Vec<ConsumerOffset>> {
+        let rows: Vec<PgRow> = sqlx::query(
+            r#"
+            SELECT consumer, sequence, updated_at
+            FROM consumer_offsets
+            ORDER BY consumer
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(Self::row_to_offset).collect()
+    }
+}
+
+#[async_trait]
+impl AnnotationStore for PostgresEventStore {
+    async fn add_annotation(&self, annotation: EventAnnotation) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO event_annotations (annotation_id, event_id, author, note, created_at)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+        )
+        .bind(annotation.annotation_id)
+        .bind(annotation.event_id.as_uuid())
+        .bind(annotation.author)
+        .bind(annotation.note)
+        .bind(annotation.created_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_annotations(&self, event_ids: &[EventId]) -> Result<Vec<EventAnnotation>> {
+        let ids: Vec<Uuid> = event_ids.iter().map(EventId::as_uuid).collect();
+        let rows: Vec<PgRow> = sqlx::query(
+            r#"
+            SELECT annotation_id, event_id, author, note, created_at
+            FROM event_annotations
+            WHERE event_id = ANY($1)
+            ORDER BY created_at, annotation_id
+            "#,
+        )
+        .bind(ids)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(Self::row_to_annotation).collect()
+    }
+}
+
+#[async_trait]
+impl StreamVisibilityStore for PostgresEventStore {
+    async fn set_stream_visibility(
+        &self,
+        aggregate_id: AggregateId,
+        visibility: Option<StreamVisibility>,
+    ) -> Result<()> {
+        match visibility {
+            Some(visibility) => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO stream_visibility (aggregate_id, visibility, updated_at)
+                    VALUES ($1, $2, now())
+                    ON CONFLICT (aggregate_id)
+                    DO UPDATE SET visibility = EXCLUDED.visibility, updated_at = now()
+                    "#,
+                )
+                .bind(aggregate_id.as_uuid())
+                .bind(visibility.as_str())
+                .execute(&self.pool)
+                .await?;
+            }
+            None => {
+                sqlx::query("DELETE FROM stream_visibility WHERE aggregate_id = $1")
+                    .bind(aggregate_id.as_uuid())
+                    .execute(&self.pool)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn get_stream_visibilities(&self) -> Result<HashMap<AggregateId, StreamVisibility>> {
+        let rows: Vec<PgRow> =
+            sqlx::query("SELECT aggregate_id, visibility FROM stream_visibility")
+                .fetch_all(&self.pool)
+                .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                let aggregate_id = AggregateId::from_uuid(row.try_get::<Uuid, _>("aggregate_id")?);
+                let visibility: String = row.try_get("visibility")?;
+                let visibility = serde_json::from_value(serde_json::Value::String(visibility))?;
+                Ok((aggregate_id, visibility))
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl IdempotencyStore for PostgresEventStore {
+    async fn claim(
+        &self,
+        key: &str,
+        request_hash: &str,
+        lease: std::time::Duration,
+        expires_at: DateTime<Utc>,
+    ) -> Result<IdempotencyClaim> {
+        // Takes the key if it is free, expired, or held past its lease by a
+        // request that never finished
+        let claimed: Option<PgRow> = sqlx::query(
+            r#"
+            INSERT INTO idempotency_keys (key, request_hash, claimed_at, expires_at)
+            VALUES ($1, $2, NOW(), $3)
+            ON CONFLICT (key) DO UPDATE SET
+                request_hash = EXCLUDED.request_hash,
+                status = NULL,
+                headers = NULL,
+                body = NULL,
+                claimed_at = EXCLUDED.claimed_at,
+                expires_at = EXCLUDED.expires_at
+            WHERE idempotency_keys.expires_at <= NOW()
+               OR (idempotency_keys.status IS NULL
+                   AND idempotency_keys.claimed_at <= NOW() - $4 * INTERVAL '1 millisecond')
+            RETURNING key
+            "#,
+        )
+        .bind(key)
+        .bind(request_hash)
+        .bind(expires_at)
+        .bind(i64::try_from(lease.as_millis()).unwrap_or(i64::MAX))
+        .fetch_optional(&self.pool)
+        .await?;
+        if claimed.is_some() {
+            return Ok(IdempotencyClaim::New);
+        }
+
+        let row: Option<PgRow> = sqlx::query(
+            r#"
+            SELECT request_hash, status, headers, body
+            FROM idempotency_keys
+            WHERE key = $1
+            "#,
+        )
+        .bind(key)
+        .fetch_optional(&self.pool)
+        .await?;
+        // A key purged in between is still taken by the request that
+        // reclaims it first
+        let Some(row) = row else {
+            return Ok(IdempotencyClaim::InProgress);
+        };
+        if row.try_get::<String, _>("request_hash")? != request_hash {
+            return Ok(IdempotencyClaim::Mismatch);
+        }
+        Ok(match row.try_get::<Option<i16>, _>("status")? {
+            Some(status) => IdempotencyClaim::Replay(StoredResponse {
+                status: status as u16,
+                headers: row
+                    .try_get::<Option<sqlx::types::Json<Vec<(String, String)>>>, _>("headers")?
+                    .map(|headers| headers.0)
+                    .unwrap_or_default(),
+                body: row
+                    .try_get::<Option<Vec<u8>>, _>("body")?
+                    .unwrap_or_default(),
+            }),
+            None => IdempotencyClaim::InProgress,
+        })
+    }
+
+    async fn complete(&self, key: &str, response: StoredResponse) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE idempotency_keys
+            SET status = $2, headers = $3, body = $4
+            WHERE key = $1
+            "#,
+        )
+        .bind(key)
+        .bind(response.status as i16)
+        .bind(sqlx::types::Json(response.headers))
+        .bind(response.body)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn release(&self, key: &str) -> Result<()> {
+        sqlx::query("DELETE FROM idempotency_keys WHERE key = $1 AND status IS NULL")
+            .bind(key)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn purge_expired(&self, now: DateTime<Utc>) -> Result<usize> {
+        let result = sqlx::query("DELETE FROM idempotency_keys WHERE expires_at <= $1")
+            .bind(now)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() as usize)
+    }
+}
+
+#[async_trait]
+impl PendingEventStore for PostgresEventStore {
+    #[tracing::instrument(skip(self, events))]
+    async fn append_pending(
+        &self,
+        events: Vec<EventEnvelope>,
+        options: AppendOptions,
+    ) -> Result<PendingBatchId> {
+        validate_events_for_append(&events).map_err(|e| {
+            EventStoreError::Serialization(serde_json::Error::io(std::io::Error::other(e.message)))
+        })?;
+
+        let aggregate_id = events[0].aggregate_id;
+        if let Some(expected) = options.expected_version {
+            let actual = self
+                .get_aggregate_version(aggregate_id)
+                .await?
+                .unwrap_or(Version::initial());
+            if actual != expected {
+                return Err(EventStoreError::ConcurrencyConflict {
+                    aggregate_id,
+                    expected,
+                    actual,
+                    conflicting: Vec::new(),
+                });
+            }
+        }
+
+        let batch_id = PendingBatchId::new();
+        sqlx::query(
+            r#"
+            INSERT INTO pending_batches (batch_id, aggregate_id, expected_version, events, staged_at)
+            VALUES ($1, $2, $3, $4, NOW())
+            "#,
+        )
+        .bind(batch_id.as_uuid())
+        .bind(aggregate_id.as_uuid())
+        .bind(options.expected_version.map(|v| v.as_i64()))
+        .bind(serde_json::to_value(&events)?)
+        .execute(&self.pool)
+        .await?;
+
+        tracing::info!(%batch_id, %aggregate_id, "events staged");
+        Ok(batch_id)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn commit_pending(&self, batch_id: PendingBatchId) -> Result<Version> {
+        let mut tx = self.pool.begin().await?;
+
+        // Locks the batch so concurrent commits can't both append it
+        let row: Option<PgRow> = sqlx::query(
+            r#"
+            DELETE FROM pending_batches
+            WHERE batch_id = $1
+            RETURNING batch_id, aggregate_id, expected_version, events, staged_at
+            "#,
+        )
+        .bind(batch_id.as_uuid())
+        .fetch_optional(&mut *tx)
+        .await?;
+        let batch = row
+            .map(Self::row_to_pending)
+            .transpose()?
+            .ok_or(EventStoreError::PendingBatchNotFound(batch_id))?;
+
+        // On failure the transaction rolls back and the batch stays staged
+        let version = Self::insert_events(&mut tx, &batch.events, &batch.append_options()).await?;
+        tx.commit().await?;
+        Ok(version)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn discard_pending(&self, batch_id: PendingBatchId) -> Result<()> {
+        let result = sqlx::query("DELETE FROM pending_batches WHERE batch_id = $1")
+            .bind(batch_id.as_uuid())
+            .execute(&self.pool)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(EventStoreError::PendingBatchNotFound(batch_id));
+        }
+        Ok(())
+    }
+
+    async fn get_pending(&self, batch_id: PendingBatchId) -> Result<Option<PendingBatch>> {
+        let row: Option<PgRow> = sqlx::query(
+            r#"
+            SELECT batch_id, aggregate_id, expected_version, events, staged_at
+            FROM pending_batches
+            WHERE batch_id = $1
+            "#,
+        )
+        .bind(batch_id.as_uuid())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.map(Self::row_to_pending).transpose()
+    }
+
+    async fn list_pending(&self) -> Result<Vec<PendingBatch>> {
+        let rows: Vec<PgRow> = sqlx::query(
+            r#"
+            SELECT batch_id, aggregate_id, expected_version, events, staged_at
+            FROM pending_batches
+            ORDER BY staged_at
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(Self::row_to_pending).collect()
+    }
+}
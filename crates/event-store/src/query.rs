@@ -1,12 +1,15 @@
 use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 
-use crate::{AggregateId, Version};
+use crate::{AggregateId, SessionToken, Version};
 
 /// Builder for constructing event queries.
 ///
 /// Allows filtering events by various criteria such as aggregate ID,
-/// event type, version range, and time range.
-#[derive(Debug, Clone, Default)]
+/// event type, version range, and time range. Deserializes from an object
+/// with any of its fields.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct EventQuery {
     /// Filter by aggregate ID.
     pub aggregate_id: Option<AggregateId>,
@@ -34,6 +37,12 @@ pub struct EventQuery {
 
     /// Number of events to skip.
     pub offset: Option<usize>,
+
+    /// Return newest events first. Offset and limit apply in this order.
+    pub descending: bool,
+
+    /// Wait until the store has observed this token before reading.
+    pub after_token: Option<SessionToken>,
 }
 
 impl EventQuery {
@@ -117,6 +126,20 @@ impl EventQuery {
         self.offset = Some(offset);
         self
     }
+
+    /// Returns newest events first.
+    pub fn descending(mut self) -> Self {
+        self.descending = true;
+        self
+    }
+
+    /// Waits, up to [`DEFAULT_SESSION_WAIT`](crate::session::DEFAULT_SESSION_WAIT),
+    /// for the store to observe `token` before reading, so the results
+    /// include the append that returned it.
+    pub fn after_token(mut self, token: SessionToken) -> Self {
+        self.after_token = Some(token);
+        self
+    }
 }
 
 #[cfg(test)]
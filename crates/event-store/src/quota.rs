@@ -0,0 +1,668 @@
+//! Append quotas for shared deployments.
+//!
+//! A [`QuotaEventStore`] wraps another store and refuses appends that would
+//! push an aggregate past its event limit, a tenant past its aggregate limit,
+//! or carry an oversized payload. Limits live in a [`QuotaEnforcer`], which
+//! can be inspected and adjusted while the store is in use.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+
+use async_trait::async_trait;
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::sync::watch;
+
+use crate::offsets::{ConsumerOffset, ConsumerOffsetStore};
+use crate::pending::{PendingBatch, PendingBatchId, PendingEventStore};
+use crate::store::{AppendOptions, EventStore, EventStream, StreamAppend};
+use crate::{
+    AggregateId, EventEnvelope, EventId, EventQuery, EventStoreError, Result, Snapshot, Version,
+};
+
+/// Metadata key naming the tenant an event belongs to.
+///
+/// Events without it don't count toward any tenant's quota.
+pub const TENANT_METADATA_KEY: &str = "tenant_id";
+
+/// Configured append limits. `None` means unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuotaLimits {
+    /// Maximum number of events in a single aggregate's stream.
+    pub max_events_per_aggregate: Option<u64>,
+    /// Maximum number of distinct aggregates a tenant may write to.
+    pub max_aggregates_per_tenant: Option<u64>,
+    /// Maximum size of a single event payload, in bytes of JSON.
+    pub max_payload_bytes: Option<usize>,
+}
+
+impl QuotaLimits {
+    /// Returns limits that allow everything.
+    pub fn unlimited() -> Self {
+        Self::default()
+    }
+}
+
+/// An append refused because it would exceed a quota.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum QuotaExceeded {
+    #[error("aggregate {aggregate_id} would reach {requested} events, over the limit of {limit}")]
+    EventsPerAggregate {
+        aggregate_id: AggregateId,
+        requested: u64,
+        limit: u64,
+    },
+
+    #[error("tenant {tenant} would own {requested} aggregates, over the limit of {limit}")]
+    AggregatesPerTenant {
+        tenant: String,
+        requested: u64,
+        limit: u64,
+    },
+
+    #[error("{event_type} payload is {size} bytes, over the limit of {limit}")]
+    PayloadSize {
+        event_type: String,
+        size: usize,
+        limit: usize,
+    },
+}
+
+impl QuotaExceeded {
+    /// Returns the name of the quota, as used in metric labels.
+    pub fn quota(&self) -> &'static str {
+        match self {
+            QuotaExceeded::EventsPerAggregate { .. } => "events_per_aggregate",
+            QuotaExceeded::AggregatesPerTenant { .. } => "aggregates_per_tenant",
+            QuotaExceeded::PayloadSize { .. } => "payload_size",
+        }
+    }
+}
+
+/// Current limits and how much of the per-tenant quota is in use.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct QuotaUsage {
+    pub limits: QuotaLimits,
+    /// Number of aggregates each tenant has written to.
+    pub aggregates_per_tenant: BTreeMap<String, u64>,
+}
+
+#[derive(Debug, Default)]
+struct QuotaState {
+    limits: QuotaLimits,
+    tenant_aggregates: HashMap<String, HashSet<AggregateId>>,
+}
+
+/// Shared quota limits and per-tenant usage.
+///
+/// Clones share state, so an admin endpoint holding one clone adjusts the
+/// limits its [`QuotaEventStore`] enforces.
+#[derive(Debug, Clone, Default)]
+pub struct QuotaEnforcer {
+    state: Arc<RwLock<QuotaState>>,
+}
+
+impl QuotaEnforcer {
+    /// Creates an enforcer with the given limits and no recorded usage.
+    pub fn new(limits: QuotaLimits) -> Self {
+        Self {
+            state: Arc::new(RwLock::new(QuotaState {
+                limits,
+                tenant_aggregates: HashMap::new(),
+            })),
+        }
+    }
+
+    /// Returns the limits currently enforced.
+    pub fn limits(&self) -> QuotaLimits {
+        self.state.read().unwrap().limits
+    }
+
+    /// Replaces the enforced limits. Existing data over a new limit is kept;
+    /// only later appends are refused.
+    pub fn set_limits(&self, limits: QuotaLimits) {
+        self.state.write().unwrap().limits = limits;
+    }
+
+    /// Returns the limits together with per-tenant usage.
+    pub fn usage(&self) -> QuotaUsage {
+        let state = self.state.read().unwrap();
+        QuotaUsage {
+            limits: state.limits,
+            aggregates_per_tenant: state
+                .tenant_aggregates
+                .iter()
+                .map(|(tenant, aggregates)| (tenant.clone(), aggregates.len() as u64))
+                .collect(),
+        }
+    }
+
+    /// Checks a batch of events for one aggregate against the limits.
+    pub fn check(&self, events: &[EventEnvelope]) -> std::result::Result<(), QuotaExceeded> {
+        self.check_batch(&[events])
+    }
+
+    /// Checks the streams of one atomic append against the limits.
+    ///
+    /// Each stream is checked against the per-event and per-aggregate limits,
+    /// and the aggregates new to each tenant across the whole batch are
+    /// counted together against the tenant limit.
+    pub fn check_batch(
+        &self,
+        streams: &[&[EventEnvelope]],
+    ) -> std::result::Result<(), QuotaExceeded> {
+        let state = self.state.read().unwrap();
+        let limits = state.limits;
+        let mut added: HashMap<&str, HashSet<AggregateId>> = HashMap::new();
+
+        for events in streams {
+            if let Some(limit) = limits.max_payload_bytes {
+                for event in *events {
+                    let size = event.payload.to_string().len();
+                    if size > limit {
+                        return Err(QuotaExceeded::PayloadSize {
+                            event_type: event.event_type.clone(),
+                            size,
+                            limit,
+                        });
+                    }
+                }
+            }
+
+            let Some(last) = events.last() else {
+                continue;
+            };
+
+            // Versions start at 1 and have no gaps, so the last version is the
+            // stream length after the append.
+            if let Some(limit) = limits.max_events_per_aggregate {
+                let requested = last.version.as_i64().max(0) as u64;
+                if requested > limit {
+                    return Err(QuotaExceeded::EventsPerAggregate {
+                        aggregate_id: last.aggregate_id,
+                        requested,
+                        limit,
+                    });
+                }
+            }
+
+            if let (Some(limit), Some(tenant)) =
+                (limits.max_aggregates_per_tenant, tenant_of(events))
+            {
+                let owned = state.tenant_aggregates.get(tenant);
+                if owned.is_some_and(|ids| ids.contains(&last.aggregate_id)) {
+                    continue;
+                }
+                let new = added.entry(tenant).or_default();
+                let requested = owned.map_or(0, |ids| ids.len() as u64) + new.len() as u64 + 1;
+                if new.insert(last.aggregate_id) && requested > limit {
+                    return Err(QuotaExceeded::AggregatesPerTenant {
+                        tenant: tenant.to_string(),
+                        requested,
+                        limit,
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Records the tenant of stored events, so later appends count them.
+    pub fn record(&self, events: &[EventEnvelope]) {
+        self.record_owners(owners(events));
+    }
+
+    fn record_owners(&self, owners: Vec<(String, AggregateId)>) {
+        if owners.is_empty() {
+            return;
+        }
+        let mut state = self.state.write().unwrap();
+        for (tenant, aggregate_id) in owners {
+            state
+                .tenant_aggregates
+                .entry(tenant)
+                .or_default()
+                .insert(aggregate_id);
+        }
+    }
+}
+
+/// Returns the tenant and aggregate of each tenant-tagged event.
+fn owners(events: &[EventEnvelope]) -> Vec<(String, AggregateId)> {
+    events
+        .iter()
+        .filter_map(|event| {
+            let tenant = tenant_of(std::slice::from_ref(event))?;
+            Some((tenant.to_string(), event.aggregate_id))
+        })
+        .collect()
+}
+
+/// Returns the tenant named in the events' metadata, if any.
+fn tenant_of(events: &[EventEnvelope]) -> Option<&str> {
+    events
+        .iter()
+        .find_map(|e| e.metadata.get(TENANT_METADATA_KEY)?.as_str())
+}
+
+/// An event store that enforces [`QuotaLimits`] on append.
+///
+/// Refused appends fail with [`EventStoreError::QuotaExceeded`] and are
+/// counted in `quota_rejections` by quota. Limits are checked before the
+/// inner append, so concurrent appends for one tenant can overshoot its
+/// aggregate limit slightly.
+#[derive(Debug, Clone)]
+pub struct QuotaEventStore<S> {
+    inner: S,
+    enforcer: QuotaEnforcer,
+}
+
+impl<S: EventStore> QuotaEventStore<S> {
+    /// Wraps `inner`, enforcing the enforcer's limits.
+    pub fn new(inner: S, enforcer: QuotaEnforcer) -> Self {
+        Self { inner, enforcer }
+    }
+
+    /// Returns the enforcer, for inspecting and adjusting limits.
+    pub fn enforcer(&self) -> &QuotaEnforcer {
+        &self.enforcer
+    }
+
+    /// Returns the wrapped store.
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Records which tenants own the aggregates already in the store.
+    ///
+    /// Call once at startup; afterwards usage is tracked as events are
+    /// appended.
+    pub async fn load_tenant_usage(&self) -> Result<()> {
+        let mut stream = self.inner.stream_all_events().await?;
+        while let Some(event) = stream.next().await {
+            self.enforcer.record(std::slice::from_ref(&event?));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<S: EventStore> EventStore for QuotaEventStore<S> {
+    async fn append(&self, events: Vec<EventEnvelope>, options: AppendOptions) -> Result<Version> {
+        if let Err(exceeded) = self.enforcer.check(&events) {
+            tracing::warn!(error = %exceeded, "append refused by quota");
+            metrics::counter!("quota_rejections", "quota" => exceeded.quota()).increment(1);
+            return Err(EventStoreError::QuotaExceeded(exceeded));
+        }
+
+        let owners = owners(&events);
+        let version = self.inner.append(events, options).await?;
+        self.enforcer.record_owners(owners);
+        Ok(version)
+    }
+
+    async fn append_batch(&self, streams: Vec<StreamAppend>) -> Result<Vec<Version>> {
+        let events: Vec<&[EventEnvelope]> = streams.iter().map(|s| s.events.as_slice()).collect();
+        if let Err(exceeded) = self.enforcer.check_batch(&events) {
+            tracing::warn!(error = %exceeded, "batch append refused by quota");
+            metrics::counter!("quota_rejections", "quota" => exceeded.quota()).increment(1);
+            return Err(EventStoreError::QuotaExceeded(exceeded));
+        }
+
+        let owners = streams.iter().flat_map(|s| owners(&s.events)).collect();
+        let versions = self.inner.append_batch(streams).await?;
+        self.enforcer.record_owners(owners);
+        Ok(versions)
+    }
+
+    async fn get_events_for_aggregate(
+        &self,
+        aggregate_id: AggregateId,
+    ) -> Result<Vec<EventEnvelope>> {
+        self.inner.get_events_for_aggregate(aggregate_id).await
+    }
+
+    async fn get_events_for_aggregate_from_version(
+        &self,
+        aggregate_id: AggregateId,
+        from_version: Version,
+    ) -> Result<Vec<EventEnvelope>> {
+        self.inner
+            .get_events_for_aggregate_from_version(aggregate_id, from_version)
+            .await
+    }
+
+    async fn query_events(&self, query: EventQuery) -> Result<Vec<EventEnvelope>> {
+        self.inner.query_events(query).await
+    }
+
+    async fn get_events_by_type(&self, event_type: &str) -> Result<Vec<EventEnvelope>> {
+        self.inner.get_events_by_type(event_type).await
+    }
+
+    async fn stream_all_events(&self) -> Result<EventStream> {
+        self.inner.stream_all_events().await
+    }
+
+    async fn stream_events_after(&self, after_sequence: u64) -> Result<EventStream> {
+        self.inner.stream_events_after(after_sequence).await
+    }
+
+    async fn subscribe(&self, after_sequence: u64) -> Result<EventStream> {
+        self.inner.subscribe(after_sequence).await
+    }
+
+    async fn get_event(&self, event_id: EventId) -> Result<Option<EventEnvelope>> {
+        self.inner.get_event(event_id).await
+    }
+
+    async fn head_sequence(&self) -> Result<u64> {
+        self.inner.head_sequence().await
+    }
+
+    fn append_notifications(&self) -> Option<watch::Receiver<u64>> {
+        self.inner.append_notifications()
+    }
+
+    async fn get_aggregate_version(&self, aggregate_id: AggregateId) -> Result<Option<Version>> {
+        self.inner.get_aggregate_version(aggregate_id).await
+    }
+
+    async fn save_snapshot(&self, snapshot: Snapshot) -> Result<()> {
+        self.inner.save_snapshot(snapshot).await
+    }
+
+    async fn get_snapshot(&self, aggregate_id: AggregateId) -> Result<Option<Snapshot>> {
+        self.inner.get_snapshot(aggregate_id).await
+    }
+}
+
+#[async_trait]
+impl<S: ConsumerOffsetStore> ConsumerOffsetStore for QuotaEventStore<S> {
+    async fn get_offset(&self, consumer: &str) -> Result<Option<ConsumerOffset>> {
+        self.inner.get_offset(consumer).await
+    }
+
+    async fn set_offset(&self, consumer: &str, sequence: u64) -> Result<ConsumerOffset> {
+        self.inner.set_offset(consumer, sequence).await
+    }
+
+    async fn list_offsets(&self) -> Result<Vec<ConsumerOffset>> {
+        self.inner.list_offsets().await
+    }
+}
+
+#[async_trait]
+impl<S: PendingEventStore> PendingEventStore for QuotaEventStore<S> {
+    async fn append_pending(
+        &self,
+        events: Vec<EventEnvelope>,
+        options: AppendOptions,
+    ) -> Result<PendingBatchId> {
+        self.inner.append_pending(events, options).await
+    }
+
+    /// Checks the batch against the quotas in force at commit time.
+    async fn commit_pending(&self, batch_id: PendingBatchId) -> Result<Version> {
+        let batch = self
+            .inner
+            .get_pending(batch_id)
+            .await?
+            .ok_or(EventStoreError::PendingBatchNotFound(batch_id))?;
+        if let Err(exceeded) = self.enforcer.check(&batch.events) {
+            tracing::warn!(error = %exceeded, %batch_id, "pending commit refused by quota");
+            metrics::counter!("quota_rejections", "quota" => exceeded.quota()).increment(1);
+            return Err(EventStoreError::QuotaExceeded(exceeded));
+        }
+
+        let version = self.inner.commit_pending(batch_id).await?;
+        self.enforcer.record(&batch.events);
+        Ok(version)
+    }
+
+    async fn discard_pending(&self, batch_id: PendingBatchId) -> Result<()> {
+        self.inner.discard_pending(batch_id).await
+    }
+
+    async fn get_pending(&self, batch_id: PendingBatchId) -> Result<Option<PendingBatch>> {
+        self.inner.get_pending(batch_id).await
+    }
+
+    async fn list_pending(&self) -> Result<Vec<PendingBatch>> {
+        self.inner.list_pending().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InMemoryEventStore;
+
+    fn event(aggregate_id: AggregateId, version: i64, tenant: Option<&str>) -> EventEnvelope {
+        let mut builder = EventEnvelope::builder()
+            .aggregate_id(aggregate_id)
+            .aggregate_type("TestAggregate")
+            .event_type("Changed")
+            .version(Version::new(version))
+            .payload_raw(serde_json::json!({ "note": "x" }));
+        if let Some(tenant) = tenant {
+            builder = builder.metadata(TENANT_METADATA_KEY, serde_json::json!(tenant));
+        }
+        builder.build()
+    }
+
+    fn store(limits: QuotaLimits) -> QuotaEventStore<InMemoryEventStore> {
+        QuotaEventStore::new(InMemoryEventStore::new(), QuotaEnforcer::new(limits))
+    }
+
+    #[tokio::test]
+    async fn limits_events_per_aggregate() {
+        let store = store(QuotaLimits {
+            max_events_per_aggregate: Some(2),
+            ..QuotaLimits::unlimited()
+        });
+        let id = AggregateId::new();
+
+        store
+            .append(
+                vec![event(id, 1, None), event(id, 2, None)],
+                AppendOptions::new(),
+            )
+            .await
+            .unwrap();
+        let result = store
+            .append(vec![event(id, 3, None)], AppendOptions::new())
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(EventStoreError::QuotaExceeded(
+                QuotaExceeded::EventsPerAggregate {
+                    requested: 3,
+                    limit: 2,
+                    ..
+                }
+            ))
+        ));
+        assert_eq!(store.head_sequence().await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn limits_aggregates_per_tenant() {
+        let store = store(QuotaLimits {
+            max_aggregates_per_tenant: Some(1),
+            ..QuotaLimits::unlimited()
+        });
+        let first = AggregateId::new();
+
+        store
+            .append(vec![event(first, 1, Some("acme"))], AppendOptions::new())
+            .await
+            .unwrap();
+        // More events on an aggregate the tenant already owns are fine
+        store
+            .append(vec![event(first, 2, Some("acme"))], AppendOptions::new())
+            .await
+            .unwrap();
+        // Other tenants and untagged events have their own budget
+        store
+            .append(
+                vec![event(AggregateId::new(), 1, Some("globex"))],
+                AppendOptions::new(),
+            )
+            .await
+            .unwrap();
+        store
+            .append(
+                vec![event(AggregateId::new(), 1, None)],
+                AppendOptions::new(),
+            )
+            .await
+            .unwrap();
+
+        let result = store
+            .append(
+                vec![event(AggregateId::new(), 1, Some("acme"))],
+                AppendOptions::new(),
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(EventStoreError::QuotaExceeded(
+                QuotaExceeded::AggregatesPerTenant { .. }
+            ))
+        ));
+
+        let usage = store.enforcer().usage();
+        assert_eq!(usage.aggregates_per_tenant["acme"], 1);
+        assert_eq!(usage.aggregates_per_tenant["globex"], 1);
+    }
+
+    #[tokio::test]
+    async fn batch_counts_new_aggregates_together_against_tenant_limit() {
+        let store = store(QuotaLimits {
+            max_aggregates_per_tenant: Some(2),
+            ..QuotaLimits::unlimited()
+        });
+        let owned = AggregateId::new();
+        store
+            .append(vec![event(owned, 1, Some("acme"))], AppendOptions::new())
+            .await
+            .unwrap();
+
+        // Each stream fits on its own, but together they add two aggregates
+        let result = store
+            .append_batch(vec![
+                StreamAppend::new(vec![event(owned, 2, Some("acme"))], AppendOptions::new()),
+                StreamAppend::new(
+                    vec![event(AggregateId::new(), 1, Some("acme"))],
+                    AppendOptions::new(),
+                ),
+                StreamAppend::new(
+                    vec![event(AggregateId::new(), 1, Some("acme"))],
+                    AppendOptions::new(),
+                ),
+            ])
+            .await;
+        assert!(matches!(
+            result,
+            Err(EventStoreError::QuotaExceeded(
+                QuotaExceeded::AggregatesPerTenant {
+                    requested: 3,
+                    limit: 2,
+                    ..
+                }
+            ))
+        ));
+        assert_eq!(store.head_sequence().await.unwrap(), 1);
+
+        store
+            .append_batch(vec![
+                StreamAppend::new(vec![event(owned, 2, Some("acme"))], AppendOptions::new()),
+                StreamAppend::new(
+                    vec![event(AggregateId::new(), 1, Some("acme"))],
+                    AppendOptions::new(),
+                ),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(store.enforcer().usage().aggregates_per_tenant["acme"], 2);
+    }
+
+    #[tokio::test]
+    async fn limits_payload_size() {
+        let store = store(QuotaLimits {
+            max_payload_bytes: Some(8),
+            ..QuotaLimits::unlimited()
+        });
+
+        let result = store
+            .append(
+                vec![event(AggregateId::new(), 1, None)],
+                AppendOptions::new(),
+            )
+            .await;
+
+        let Err(EventStoreError::QuotaExceeded(exceeded)) = result else {
+            panic!("expected a quota error");
+        };
+        assert_eq!(exceeded.quota(), "payload_size");
+        assert_eq!(store.head_sequence().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn adjusted_limits_apply_to_later_appends() {
+        let store = store(QuotaLimits {
+            max_events_per_aggregate: Some(1),
+            ..QuotaLimits::unlimited()
+        });
+        let id = AggregateId::new();
+        store
+            .append(vec![event(id, 1, None)], AppendOptions::new())
+            .await
+            .unwrap();
+        assert!(
+            store
+                .append(vec![event(id, 2, None)], AppendOptions::new())
+                .await
+                .is_err()
+        );
+
+        store.enforcer().set_limits(QuotaLimits::unlimited());
+        store
+            .append(vec![event(id, 2, None)], AppendOptions::new())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn loads_tenant_usage_from_existing_events() {
+        let inner = InMemoryEventStore::new();
+        inner
+            .append(
+                vec![event(AggregateId::new(), 1, Some("acme"))],
+                AppendOptions::new(),
+            )
+            .await
+            .unwrap();
+
+        let store = QuotaEventStore::new(
+            inner,
+            QuotaEnforcer::new(QuotaLimits {
+                max_aggregates_per_tenant: Some(1),
+                ..QuotaLimits::unlimited()
+            }),
+        );
+        store.load_tenant_usage().await.unwrap();
+
+        let result = store
+            .append(
+                vec![event(AggregateId::new(), 1, Some("acme"))],
+                AppendOptions::new(),
+            )
+            .await;
+        assert!(result.is_err());
+    }
+}
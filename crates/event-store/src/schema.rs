@@ -0,0 +1,68 @@
+//! Database schema version checks.
+//!
+//! A binary expects the schema its bundled migrations produce. Running it
+//! against a database that is missing some of them (or has newer ones from a
+//! later release) fails in confusing ways at query time, so deployments
+//! compare the two at startup and from readiness probes.
+
+use async_trait::async_trait;
+use serde::Serialize;
+
+use crate::Result;
+
+/// The schema migrations applied to a database, against those this build
+/// ships.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct SchemaVersion {
+    /// Highest migration applied to the database, or None if none has been.
+    pub applied: Option<i64>,
+    /// Highest migration bundled with this build.
+    pub expected: i64,
+}
+
+/// How a database schema compares with the one a build expects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SchemaDrift {
+    /// The database has exactly the migrations this build ships.
+    UpToDate,
+    /// The database is missing migrations; running them brings it up to date.
+    Behind,
+    /// The database has migrations from a newer build.
+    Ahead,
+}
+
+impl SchemaVersion {
+    /// Compares the applied schema with the expected one.
+    pub fn drift(&self) -> SchemaDrift {
+        match self.applied {
+            Some(applied) if applied == self.expected => SchemaDrift::UpToDate,
+            Some(applied) if applied > self.expected => SchemaDrift::Ahead,
+            _ => SchemaDrift::Behind,
+        }
+    }
+}
+
+/// A store whose backing database has a versioned schema.
+#[async_trait]
+pub trait SchemaVersionSource: Send + Sync {
+    /// Reads the applied schema version from the database.
+    async fn schema_version(&self) -> Result<SchemaVersion>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drift() {
+        let version = |applied| SchemaVersion {
+            applied,
+            expected: 5,
+        };
+        assert_eq!(version(Some(5)).drift(), SchemaDrift::UpToDate);
+        assert_eq!(version(Some(4)).drift(), SchemaDrift::Behind);
+        assert_eq!(version(None).drift(), SchemaDrift::Behind);
+        assert_eq!(version(Some(6)).drift(), SchemaDrift::Ahead);
+    }
+}
@@ -0,0 +1,247 @@
+//! "Read your own writes" session tokens.
+//!
+//! [`append_with_session`](crate::EventStoreExt::append_with_session)
+//! returns a [`SessionToken`] naming a global sequence the store had
+//! reached once the append was done. A read given the token, through
+//! [`EventQuery::after_token`](crate::EventQuery::after_token) or
+//! [`LoadOptions::after_token`], first waits for the store it's served
+//! from to reach that sequence, so a caller reading from a store that
+//! trails the one it wrote to still sees its own append. A read that can't
+//! catch up in time fails with [`EventStoreError::SessionNotCaughtUp`]
+//! rather than returning stale results.
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::store::EventStore;
+use crate::{EventStoreError, Result};
+
+/// How long a query given a session token waits for the store to reach it.
+pub const DEFAULT_SESSION_WAIT: Duration = Duration::from_secs(2);
+
+/// How often a store without append notifications is polled while a read
+/// waits for its token.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// A point in the global log a reader must have observed.
+///
+/// Encoded as an opaque string (`s1.<sequence>`) so it can be handed to
+/// clients and sent back. Tokens from one session combine with `max`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct SessionToken {
+    sequence: u64,
+}
+
+impl SessionToken {
+    /// Creates a token requiring events up to global `sequence`.
+    pub fn at_sequence(sequence: u64) -> Self {
+        Self { sequence }
+    }
+
+    /// Returns the global sequence a reader must have reached.
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// Returns true if a store at `head` has observed this token.
+    pub fn is_covered_by(&self, head: u64) -> bool {
+        head >= self.sequence
+    }
+}
+
+/// A string that isn't a session token.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("invalid session token: {0:?}")]
+pub struct InvalidSessionToken(String);
+
+impl fmt::Display for SessionToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "s1.{}", self.sequence)
+    }
+}
+
+impl FromStr for SessionToken {
+    type Err = InvalidSessionToken;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        s.strip_prefix("s1.")
+            .and_then(|sequence| sequence.parse().ok())
+            .map(Self::at_sequence)
+            .ok_or_else(|| InvalidSessionToken(s.to_string()))
+    }
+}
+
+impl TryFrom<String> for SessionToken {
+    type Error = InvalidSessionToken;
+
+    fn try_from(s: String) -> std::result::Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<SessionToken> for String {
+    fn from(token: SessionToken) -> Self {
+        token.to_string()
+    }
+}
+
+/// Options for loading an aggregate.
+#[derive(Debug, Clone, Copy)]
+pub struct LoadOptions {
+    /// Token the load must observe before reading.
+    pub after_token: Option<SessionToken>,
+    /// How long to wait for the store to reach `after_token`.
+    pub max_wait: Duration,
+}
+
+impl LoadOptions {
+    /// Creates options that read whatever the store has now.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Waits until the store has observed `token` before reading.
+    pub fn after_token(mut self, token: SessionToken) -> Self {
+        self.after_token = Some(token);
+        self
+    }
+
+    /// Sets how long to wait for the token.
+    pub fn with_max_wait(mut self, max_wait: Duration) -> Self {
+        self.max_wait = max_wait;
+        self
+    }
+}
+
+impl Default for LoadOptions {
+    fn default() -> Self {
+        Self {
+            after_token: None,
+            max_wait: DEFAULT_SESSION_WAIT,
+        }
+    }
+}
+
+/// Waits until `store` has observed `token`, for at most `max_wait`.
+///
+/// Wakes on the store's append notifications when it has them and polls
+/// otherwise.
+pub async fn wait_for_token<S: EventStore + ?Sized>(
+    store: &S,
+    token: SessionToken,
+    max_wait: Duration,
+) -> Result<()> {
+    let mut notifications = store.append_notifications();
+    let deadline = tokio::time::Instant::now() + max_wait;
+    loop {
+        let head = store.head_sequence().await?;
+        if token.is_covered_by(head) {
+            return Ok(());
+        }
+        if tokio::time::Instant::now() >= deadline {
+            metrics::counter!("session_token_timeouts").increment(1);
+            return Err(EventStoreError::SessionNotCaughtUp {
+                required: token.sequence(),
+                head,
+            });
+        }
+
+        let wake = async {
+            let notified = match notifications.as_mut() {
+                Some(receiver) => receiver.changed().await.is_ok(),
+                None => false,
+            };
+            if !notified {
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+        };
+        let _ = tokio::time::timeout_at(deadline, wake).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::EventStoreExt;
+    use crate::{AppendOptions, EventEnvelope, EventQuery, InMemoryEventStore, Version};
+    use common::AggregateId;
+
+    fn event(aggregate_id: AggregateId, version: i64) -> EventEnvelope {
+        EventEnvelope::builder()
+            .aggregate_id(aggregate_id)
+            .aggregate_type("Order")
+            .event_type("OrderCreated")
+            .version(Version::new(version))
+            .payload_raw(serde_json::json!({}))
+            .build()
+    }
+
+    #[test]
+    fn test_token_round_trips_as_string() {
+        let token = SessionToken::at_sequence(42);
+        assert_eq!(token.to_string(), "s1.42");
+        assert_eq!("s1.42".parse::<SessionToken>().unwrap(), token);
+        assert_eq!(
+            serde_json::to_value(token).unwrap(),
+            serde_json::json!("s1.42")
+        );
+        assert!("42".parse::<SessionToken>().is_err());
+        assert!("s1.x".parse::<SessionToken>().is_err());
+
+        let query: EventQuery =
+            serde_json::from_value(serde_json::json!({"after_token": "s1.7"})).unwrap();
+        assert_eq!(query.after_token, Some(SessionToken::at_sequence(7)));
+    }
+
+    #[tokio::test]
+    async fn test_reads_wait_for_the_token() {
+        let store = InMemoryEventStore::new();
+        let id = AggregateId::new();
+        let (version, token) = store
+            .append_with_session(vec![event(id, 1)], AppendOptions::expect_new())
+            .await
+            .unwrap();
+        assert_eq!(version, Version::new(1));
+        assert_eq!(token, SessionToken::at_sequence(1));
+
+        // A store that has the write answers at once
+        let events = store
+            .query_events(EventQuery::for_aggregate(id).after_token(token))
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 1);
+
+        // One that trails the writer waits for the write to arrive
+        let ahead = SessionToken::at_sequence(2);
+        let load = store.load_aggregate_with(id, LoadOptions::new().after_token(ahead));
+        let append = async {
+            tokio::task::yield_now().await;
+            store
+                .append(vec![event(id, 2)], AppendOptions::expect_version(version))
+                .await
+                .unwrap();
+        };
+        let ((_, events), ()) = tokio::join!(async { load.await.unwrap() }, append);
+        assert_eq!(events.len(), 2);
+
+        // and gives up rather than answering stale
+        let result = wait_for_token(
+            &store,
+            SessionToken::at_sequence(10),
+            Duration::from_millis(20),
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(EventStoreError::SessionNotCaughtUp {
+                required: 10,
+                head: 2
+            })
+        ));
+    }
+}
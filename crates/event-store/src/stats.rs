@@ -0,0 +1,78 @@
+//! Per-aggregate event counts relative to snapshots.
+
+use crate::{AggregateId, Version};
+
+/// How far an aggregate's events run past its latest snapshot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AggregateStats {
+    pub aggregate_id: AggregateId,
+    pub aggregate_type: String,
+    /// Version of the aggregate's latest event.
+    pub version: Version,
+    /// Version of the latest snapshot, if any.
+    pub snapshot_version: Option<Version>,
+}
+
+impl AggregateStats {
+    /// Returns how many events a load replays on top of the snapshot (or
+    /// from the start, without one).
+    pub fn events_since_snapshot(&self) -> u64 {
+        let base = self.snapshot_version.map_or(0, |v| v.as_i64());
+        (self.version.as_i64() - base).max(0) as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        AppendOptions, EventEnvelope, EventStore, EventStoreExt, InMemoryEventStore, Snapshot,
+    };
+
+    fn events(aggregate_id: AggregateId, aggregate_type: &str, count: i64) -> Vec<EventEnvelope> {
+        (1..=count)
+            .map(|version| {
+                EventEnvelope::builder()
+                    .aggregate_id(aggregate_id)
+                    .aggregate_type(aggregate_type)
+                    .event_type("TestEvent")
+                    .version(Version::new(version))
+                    .payload_raw(serde_json::json!({}))
+                    .build()
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_stats_count_events_past_snapshot() {
+        let store = InMemoryEventStore::new();
+        let snapshotted = AggregateId::new();
+        let fresh = AggregateId::new();
+        store
+            .append(events(snapshotted, "Order", 12), AppendOptions::new())
+            .await
+            .unwrap();
+        store
+            .append(events(fresh, "Order", 3), AppendOptions::new())
+            .await
+            .unwrap();
+        store
+            .append(
+                events(AggregateId::new(), "Customer", 40),
+                AppendOptions::new(),
+            )
+            .await
+            .unwrap();
+        let snapshot = Snapshot::from_state(snapshotted, "Order", Version::new(10), &()).unwrap();
+        store.save_snapshot(snapshot).await.unwrap();
+
+        let stats = store.aggregate_stats("Order").await.unwrap();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].aggregate_id, fresh);
+        assert_eq!(stats[0].events_since_snapshot(), 3);
+        assert_eq!(stats[1].aggregate_id, snapshotted);
+        assert_eq!(stats[1].version, Version::new(12));
+        assert_eq!(stats[1].snapshot_version, Some(Version::new(10)));
+        assert_eq!(stats[1].events_since_snapshot(), 2);
+    }
+}
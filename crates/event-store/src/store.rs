@@ -1,9 +1,18 @@
 use std::pin::Pin;
 
+use std::collections::HashMap;
+use std::time::Duration;
+
 use async_trait::async_trait;
 use futures_core::Stream;
+use futures_util::StreamExt;
+use tokio::sync::watch;
 
-use crate::{AggregateId, EventEnvelope, EventQuery, Result, Snapshot, Version};
+use crate::session::{self, LoadOptions, SessionToken};
+use crate::{
+    AggregateId, AggregateStats, ConflictingEvent, EventEnvelope, EventId, EventQuery,
+    EventStoreError, Result, Snapshot, Version,
+};
 
 /// Options for appending events to the store.
 #[derive(Debug, Clone, Default)]
@@ -34,6 +43,20 @@ impl AppendOptions {
     }
 }
 
+/// One aggregate's share of a multi-aggregate append.
+#[derive(Debug, Clone)]
+pub struct StreamAppend {
+    pub events: Vec<EventEnvelope>,
+    pub options: AppendOptions,
+}
+
+impl StreamAppend {
+    /// Creates an append of `events` checked against `options`.
+    pub fn new(events: Vec<EventEnvelope>, options: AppendOptions) -> Self {
+        Self { events, options }
+    }
+}
+
 /// A stream of events.
 pub type EventStream = Pin<Box<dyn Stream<Item = Result<EventEnvelope>> + Send>>;
 
@@ -52,6 +75,16 @@ pub trait EventStore: Send + Sync {
     /// Returns the new version of the aggregate after appending.
     async fn append(&self, events: Vec<EventEnvelope>, options: AppendOptions) -> Result<Version>;
 
+    /// Appends events for several aggregates in one atomic write, e.g. an
+    /// order and the saga driving it.
+    ///
+    /// Each stream is checked as [`append`](Self::append) would check it,
+    /// against its own `expected_version`; a conflict on any of them fails
+    /// the whole batch and nothing is stored. Each aggregate may appear once.
+    ///
+    /// Returns the new version of each aggregate, in the order given.
+    async fn append_batch(&self, streams: Vec<StreamAppend>) -> Result<Vec<Version>>;
+
     /// Retrieves all events for a specific aggregate.
     ///
     /// Events are returned in version order (oldest first).
@@ -77,9 +110,56 @@ pub trait EventStore: Send + Sync {
 
     /// Streams all events in the store.
     ///
-    /// Events are returned in insertion order.
+    /// Events are returned in global sequence order.
     async fn stream_all_events(&self) -> Result<EventStream>;
 
+    /// Streams the events after global sequence `after_sequence`, in global
+    /// sequence order.
+    ///
+    /// Unlike [`EventStore::subscribe`], the stream ends at the last event
+    /// appended so far and doesn't wait at gaps in the sequences.
+    async fn stream_events_after(&self, after_sequence: u64) -> Result<EventStream>;
+
+    /// Streams the events after global sequence `after_sequence`, then keeps
+    /// streaming new events as they are appended.
+    ///
+    /// Pass 0 to replay the whole log. The stream doesn't end on its own;
+    /// read errors are yielded as items and reading resumes where it left
+    /// off. Events compaction moved into an archive are not replayed. See
+    /// [`crate::subscription`].
+    async fn subscribe(&self, after_sequence: u64) -> Result<EventStream>;
+
+    /// Retrieves a single event by its ID.
+    ///
+    /// Returns None if no such event exists. The default scans the whole
+    /// log; stores that index events by ID should override it.
+    async fn get_event(&self, event_id: EventId) -> Result<Option<EventEnvelope>> {
+        let mut events = self.stream_all_events().await?;
+        while let Some(event) = events.next().await {
+            let event = event?;
+            if event.event_id == event_id {
+                return Ok(Some(event));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the global sequence of the most recently appended event.
+    ///
+    /// Returns 0 if the store is empty.
+    async fn head_sequence(&self) -> Result<u64>;
+
+    /// Returns a receiver holding the global sequence of the latest append,
+    /// if the store can push append notifications.
+    ///
+    /// Notifications are hints: rapid appends coalesce into the latest
+    /// sequence, and some may be lost (e.g. while a listener reconnects).
+    /// Readers should still poll occasionally. The default returns None,
+    /// meaning readers must rely on polling alone.
+    fn append_notifications(&self) -> Option<watch::Receiver<u64>> {
+        None
+    }
+
     /// Gets the current version of an aggregate.
     ///
     /// Returns None if the aggregate doesn't exist.
@@ -104,6 +184,26 @@ pub trait EventStoreExt: EventStore {
         self.append(vec![event], options).await
     }
 
+    /// Appends events, also returning a session token that reads can
+    /// require to be sure they observe this append.
+    ///
+    /// The token is the store's head right after the append, so it may
+    /// also cover other writers' appends made in the meantime.
+    async fn append_with_session(
+        &self,
+        events: Vec<EventEnvelope>,
+        options: AppendOptions,
+    ) -> Result<(Version, SessionToken)> {
+        let version = self.append(events, options).await?;
+        let head = self.head_sequence().await?;
+        Ok((version, SessionToken::at_sequence(head)))
+    }
+
+    /// Waits until this store has observed `token`, for at most `max_wait`.
+    async fn wait_for_token(&self, token: SessionToken, max_wait: Duration) -> Result<()> {
+        session::wait_for_token(self, token, max_wait).await
+    }
+
     /// Checks if an aggregate exists (has any events).
     async fn aggregate_exists(&self, aggregate_id: AggregateId) -> Result<bool> {
         Ok(self.get_aggregate_version(aggregate_id).await?.is_some())
@@ -127,6 +227,82 @@ pub trait EventStoreExt: EventStore {
             Ok((None, events))
         }
     }
+
+    /// Loads an aggregate like [`load_aggregate`](Self::load_aggregate),
+    /// first waiting for this store to observe `options.after_token`.
+    async fn load_aggregate_with(
+        &self,
+        aggregate_id: AggregateId,
+        options: LoadOptions,
+    ) -> Result<(Option<Snapshot>, Vec<EventEnvelope>)> {
+        if let Some(token) = options.after_token {
+            self.wait_for_token(token, options.max_wait).await?;
+        }
+        self.load_aggregate(aggregate_id).await
+    }
+
+    /// Fills in the events behind a concurrency conflict: those appended
+    /// since the version the writer expected.
+    ///
+    /// Other errors are returned unchanged, and so is the conflict if the
+    /// events can't be read.
+    async fn describe_conflict(&self, error: EventStoreError) -> EventStoreError {
+        let EventStoreError::ConcurrencyConflict {
+            aggregate_id,
+            expected,
+            actual,
+            conflicting,
+        } = error
+        else {
+            return error;
+        };
+        let conflicting = match self
+            .get_events_for_aggregate_from_version(aggregate_id, expected.next())
+            .await
+        {
+            Ok(events) => events.iter().map(ConflictingEvent::from).collect(),
+            Err(e) => {
+                tracing::warn!(%aggregate_id, error = %e, "reading conflicting events failed");
+                conflicting
+            }
+        };
+        EventStoreError::ConcurrencyConflict {
+            aggregate_id,
+            expected,
+            actual,
+            conflicting,
+        }
+    }
+
+    /// Reports, for every aggregate of `aggregate_type`, its latest version
+    /// and the version of its latest snapshot.
+    ///
+    /// Streams the whole log, so it suits background jobs rather than
+    /// request paths. Sorted by events since snapshot, most first.
+    async fn aggregate_stats(&self, aggregate_type: &str) -> Result<Vec<AggregateStats>> {
+        let mut versions: HashMap<AggregateId, Version> = HashMap::new();
+        let mut stream = self.stream_all_events().await?;
+        while let Some(event) = stream.next().await {
+            let event = event?;
+            if event.aggregate_type == aggregate_type {
+                let version = versions.entry(event.aggregate_id).or_insert(event.version);
+                *version = (*version).max(event.version);
+            }
+        }
+
+        let mut stats = Vec::with_capacity(versions.len());
+        for (aggregate_id, version) in versions {
+            let snapshot_version = self.get_snapshot(aggregate_id).await?.map(|s| s.version);
+            stats.push(AggregateStats {
+                aggregate_id,
+                aggregate_type: aggregate_type.to_string(),
+                version,
+                snapshot_version,
+            });
+        }
+        stats.sort_by_key(|s| std::cmp::Reverse(s.events_since_snapshot()));
+        Ok(stats)
+    }
 }
 
 // Blanket implementation for all EventStore implementations
@@ -146,6 +322,32 @@ impl std::fmt::Display for AppendValidationError {
 
 impl std::error::Error for AppendValidationError {}
 
+/// Validates a multi-aggregate batch before appending.
+pub fn validate_batch_for_append(
+    streams: &[StreamAppend],
+) -> std::result::Result<(), AppendValidationError> {
+    if streams.is_empty() {
+        return Err(AppendValidationError {
+            message: "Cannot append empty batch".to_string(),
+        });
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    for stream in streams {
+        validate_events_for_append(&stream.events)?;
+        if !seen.insert(stream.events[0].aggregate_id) {
+            return Err(AppendValidationError {
+                message: format!(
+                    "Aggregate {} appears more than once in the batch",
+                    stream.events[0].aggregate_id
+                ),
+            });
+        }
+    }
+
+    Ok(())
+}
+
 /// Validates events before appending.
 pub fn validate_events_for_append(
     events: &[EventEnvelope],
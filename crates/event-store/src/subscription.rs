@@ -0,0 +1,208 @@
+//! Live subscriptions to the event log.
+//!
+//! A subscription replays the events after a position and then keeps
+//! yielding new ones as they are appended. It reads the log in batches from
+//! the last event it yielded; once a read comes back empty it waits for the
+//! store's append notifications, reading again at least every
+//! [`SUBSCRIPTION_POLL_INTERVAL`]. Since each read starts from the last
+//! event yielded, lost or coalesced notifications only delay events.
+//!
+//! Sequences are assigned as appends insert, not as they commit, so a read
+//! can see a later append before an earlier one commits. A read stops at a
+//! gap in the sequences and waits for it to fill, unless the subscription
+//! first saw it longer than [`SUBSCRIPTION_GAP_WINDOW`] ago, in which case
+//! the gap is taken to be a rolled-back append; see [`crate::gaps`].
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::time::Duration;
+
+use futures_util::stream;
+use tokio::sync::watch;
+
+use crate::{EventEnvelope, EventStream, Result, SequenceGaps};
+
+/// Most events a subscription reads from the log at once.
+pub const SUBSCRIPTION_BATCH_SIZE: usize = 500;
+
+/// Longest a subscription at the head of the log waits before reading it
+/// again without a notification.
+pub const SUBSCRIPTION_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long a subscription waits for a gap in the sequences to fill.
+pub const SUBSCRIPTION_GAP_WINDOW: Duration = Duration::from_secs(5);
+
+struct Tail<F> {
+    position: u64,
+    buffered: VecDeque<EventEnvelope>,
+    notifications: Option<watch::Receiver<u64>>,
+    read_after: F,
+    at_head: bool,
+    gaps: SequenceGaps,
+}
+
+/// Returns a subscription to the events after `after_sequence`.
+///
+/// `read_after(sequence)` returns up to [`SUBSCRIPTION_BATCH_SIZE`] events
+/// following `sequence`, in sequence order. Read errors are yielded and
+/// the read is retried after the poll interval. `notifications` must be
+/// taken before the first read so no append in between goes unnoticed.
+pub(crate) fn tail<F, Fut>(
+    after_sequence: u64,
+    notifications: Option<watch::Receiver<u64>>,
+    read_after: F,
+) -> EventStream
+where
+    F: Fn(u64) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<Vec<EventEnvelope>>> + Send + 'static,
+{
+    let tail = Tail {
+        position: after_sequence,
+        buffered: VecDeque::new(),
+        notifications,
+        read_after,
+        at_head: false,
+        gaps: SequenceGaps::new(SUBSCRIPTION_GAP_WINDOW),
+    };
+    Box::pin(stream::unfold(tail, |mut tail| async move {
+        loop {
+            if let Some(event) = tail.buffered.pop_front() {
+                tail.position = event.sequence.unwrap_or(tail.position);
+                return Some((Ok(event), tail));
+            }
+            if tail.at_head {
+                wait_for_append(&mut tail.notifications).await;
+            }
+            match (tail.read_after)(tail.position).await {
+                Ok(events) => {
+                    tail.gaps.forget_through(tail.position);
+                    let events = until_gap(tail.position, events, &mut tail.gaps);
+                    tail.at_head = events.is_empty();
+                    tail.buffered = events.into();
+                }
+                Err(e) => {
+                    tail.at_head = true;
+                    return Some((Err(e), tail));
+                }
+            }
+        }
+    }))
+}
+
+/// Returns the events before the first gap in their sequences, counting
+/// from `position`, that `gaps` holds.
+fn until_gap(
+    position: u64,
+    mut events: Vec<EventEnvelope>,
+    gaps: &mut SequenceGaps,
+) -> Vec<EventEnvelope> {
+    let mut previous = position;
+    let gap = events.iter().position(|event| {
+        let Some(sequence) = event.sequence else {
+            return false;
+        };
+        let held = gaps.holds(previous, sequence);
+        previous = sequence;
+        held
+    });
+    if let Some(gap) = gap {
+        tracing::debug!(after = previous, "subscription waiting for a sequence gap");
+        events.truncate(gap);
+    }
+    events
+}
+
+/// Waits for the next append notification or the poll interval, whichever
+/// comes first. Falls back to polling alone once notifications close.
+async fn wait_for_append(notifications: &mut Option<watch::Receiver<u64>>) {
+    let Some(receiver) = notifications else {
+        tokio::time::sleep(SUBSCRIPTION_POLL_INTERVAL).await;
+        return;
+    };
+    tokio::select! {
+        changed = receiver.changed() => {
+            if changed.is_err() {
+                *notifications = None;
+            }
+        }
+        _ = tokio::time::sleep(SUBSCRIPTION_POLL_INTERVAL) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::AggregateId;
+    use futures_util::StreamExt;
+
+    fn event(sequence: u64, age: chrono::Duration) -> EventEnvelope {
+        let mut event = EventEnvelope::builder()
+            .aggregate_id(AggregateId::new())
+            .aggregate_type("Order")
+            .event_type("TestEvent")
+            .version(crate::Version::new(1))
+            .timestamp(chrono::Utc::now() - age)
+            .payload_raw(serde_json::json!({}))
+            .build();
+        event.sequence = Some(sequence);
+        event
+    }
+
+    fn sequences(events: &[EventEnvelope]) -> Vec<u64> {
+        events.iter().filter_map(|e| e.sequence).collect()
+    }
+
+    #[test]
+    fn test_reads_stop_at_a_recent_gap() {
+        let now = chrono::Duration::zero();
+        let mut gaps = SequenceGaps::new(Duration::from_secs(60));
+
+        let read = until_gap(
+            0,
+            vec![event(1, now), event(2, now), event(4, now)],
+            &mut gaps,
+        );
+        assert_eq!(sequences(&read), [1, 2]);
+        assert!(until_gap(2, vec![event(4, now)], &mut gaps).is_empty());
+        // A backdated event doesn't make the gap before it look old
+        let backdated = chrono::Duration::days(30);
+        let read = until_gap(4, vec![event(6, backdated)], &mut gaps);
+        assert!(read.is_empty());
+
+        // A gap open for longer than the window is a rolled-back append
+        let mut gaps = SequenceGaps::new(Duration::from_millis(20));
+        let read = until_gap(0, vec![event(1, now), event(3, now)], &mut gaps);
+        assert_eq!(sequences(&read), [1]);
+        std::thread::sleep(Duration::from_millis(30));
+        let read = until_gap(1, vec![event(3, now), event(4, now)], &mut gaps);
+        assert_eq!(sequences(&read), [3, 4]);
+    }
+
+    #[tokio::test]
+    async fn test_tail_yields_an_append_that_commits_late() {
+        use std::sync::{Arc, Mutex};
+
+        // Sequence 2 is visible only on the second read
+        let reads = Arc::new(Mutex::new(0));
+        let read_after = move |after: u64| {
+            let reads = reads.clone();
+            async move {
+                let mut reads = reads.lock().unwrap();
+                *reads += 1;
+                let visible: &[u64] = if *reads == 1 { &[1, 3] } else { &[1, 2, 3] };
+                Ok(visible
+                    .iter()
+                    .filter(|&&s| s > after)
+                    .map(|&s| event(s, chrono::Duration::zero()))
+                    .collect())
+            }
+        };
+        let (_sender, receiver) = watch::channel(0);
+        let events: Vec<_> = tail(0, Some(receiver), read_after)
+            .take(3)
+            .map(|e| e.unwrap().sequence.unwrap())
+            .collect()
+            .await;
+        assert_eq!(events, [1, 2, 3]);
+    }
+}
@@ -0,0 +1,358 @@
+//! Read-only view of an event store as it was at a point in the past.
+//!
+//! Intended for regression tests: wrap a store holding the events from an
+//! incident, pick the cutoff, and replay projections or load aggregates
+//! exactly as they were at that moment.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures_util::{StreamExt, future};
+
+use crate::store::{AppendOptions, EventStore, EventStream, StreamAppend};
+use crate::{AggregateId, EventEnvelope, EventQuery, EventStoreError, Result, Snapshot, Version};
+
+/// The point in history a [`TimeTravelEventStore`] presents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cutoff {
+    /// Events up to and including this global sequence.
+    Sequence(u64),
+    /// Events recorded at or before this timestamp.
+    Timestamp(DateTime<Utc>),
+}
+
+impl Cutoff {
+    /// Returns true if the event existed at the cutoff.
+    pub fn includes(&self, event: &EventEnvelope) -> bool {
+        match self {
+            Cutoff::Sequence(sequence) => event.sequence.is_some_and(|s| s <= *sequence),
+            Cutoff::Timestamp(timestamp) => event.timestamp <= *timestamp,
+        }
+    }
+}
+
+/// An event store wrapper that hides every event after a cutoff.
+///
+/// Reads return only events visible at the cutoff, and snapshots taken past
+/// it are ignored. The view is frozen: appends and snapshot saves fail with
+/// [`EventStoreError::ReadOnly`], and no append notifications are offered.
+#[derive(Debug, Clone)]
+pub struct TimeTravelEventStore<S> {
+    inner: S,
+    cutoff: Cutoff,
+}
+
+impl<S: EventStore> TimeTravelEventStore<S> {
+    /// Presents `inner` as of the given cutoff.
+    pub fn new(inner: S, cutoff: Cutoff) -> Self {
+        Self { inner, cutoff }
+    }
+
+    /// Presents `inner` with only events up to `sequence` visible.
+    pub fn at_sequence(inner: S, sequence: u64) -> Self {
+        Self::new(inner, Cutoff::Sequence(sequence))
+    }
+
+    /// Presents `inner` with only events recorded by `timestamp` visible.
+    pub fn at_time(inner: S, timestamp: DateTime<Utc>) -> Self {
+        Self::new(inner, Cutoff::Timestamp(timestamp))
+    }
+
+    /// Returns the cutoff this view presents.
+    pub fn cutoff(&self) -> Cutoff {
+        self.cutoff
+    }
+
+    /// Returns the wrapped store.
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    fn visible(&self, events: Vec<EventEnvelope>) -> Vec<EventEnvelope> {
+        events
+            .into_iter()
+            .filter(|e| self.cutoff.includes(e))
+            .collect()
+    }
+}
+
+#[async_trait]
+impl<S: EventStore> EventStore for TimeTravelEventStore<S> {
+    async fn append(
+        &self,
+        _events: Vec<EventEnvelope>,
+        _options: AppendOptions,
+    ) -> Result<Version> {
+        Err(EventStoreError::ReadOnly(
+            "cannot append to a time-travel view".to_string(),
+        ))
+    }
+
+    async fn append_batch(&self, _streams: Vec<StreamAppend>) -> Result<Vec<Version>> {
+        Err(EventStoreError::ReadOnly(
+            "cannot append to a time-travel view".to_string(),
+        ))
+    }
+
+    async fn get_events_for_aggregate(
+        &self,
+        aggregate_id: AggregateId,
+    ) -> Result<Vec<EventEnvelope>> {
+        let events = self.inner.get_events_for_aggregate(aggregate_id).await?;
+        Ok(self.visible(events))
+    }
+
+    async fn get_events_for_aggregate_from_version(
+        &self,
+        aggregate_id: AggregateId,
+        from_version: Version,
+    ) -> Result<Vec<EventEnvelope>> {
+        let events = self
+            .inner
+            .get_events_for_aggregate_from_version(aggregate_id, from_version)
+            .await?;
+        Ok(self.visible(events))
+    }
+
+    /// A session token past the cutoff fails at once, since the view will
+    /// never reach it.
+    async fn query_events(&self, query: EventQuery) -> Result<Vec<EventEnvelope>> {
+        if let Some(token) = query.after_token {
+            let head = self.head_sequence().await?;
+            if !token.is_covered_by(head) {
+                return Err(EventStoreError::SessionNotCaughtUp {
+                    required: token.sequence(),
+                    head,
+                });
+            }
+        }
+
+        // Paginate after hiding events, so pages match the historical store
+        let offset = query.offset.unwrap_or(0);
+        let limit = query.limit.unwrap_or(usize::MAX);
+        let unpaged = EventQuery {
+            limit: None,
+            offset: None,
+            ..query
+        };
+
+        let events = self.inner.query_events(unpaged).await?;
+        Ok(self
+            .visible(events)
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .collect())
+    }
+
+    async fn get_events_by_type(&self, event_type: &str) -> Result<Vec<EventEnvelope>> {
+        let events = self.inner.get_events_by_type(event_type).await?;
+        Ok(self.visible(events))
+    }
+
+    async fn stream_all_events(&self) -> Result<EventStream> {
+        let cutoff = self.cutoff;
+        let stream = self.inner.stream_all_events().await?;
+        Ok(Box::pin(stream.filter(move |event| {
+            future::ready(event.as_ref().map_or(true, |e| cutoff.includes(e)))
+        })))
+    }
+
+    async fn stream_events_after(&self, after_sequence: u64) -> Result<EventStream> {
+        let cutoff = self.cutoff;
+        let stream = self.inner.stream_events_after(after_sequence).await?;
+        Ok(Box::pin(stream.filter(move |event| {
+            future::ready(event.as_ref().map_or(true, |e| cutoff.includes(e)))
+        })))
+    }
+
+    /// Nothing is appended to a frozen view, so the subscription ends after
+    /// replaying the events up to the cutoff.
+    async fn subscribe(&self, after_sequence: u64) -> Result<EventStream> {
+        self.stream_events_after(after_sequence).await
+    }
+
+    async fn head_sequence(&self) -> Result<u64> {
+        let head = self.inner.head_sequence().await?;
+        match self.cutoff {
+            Cutoff::Sequence(sequence) => Ok(head.min(sequence)),
+            Cutoff::Timestamp(_) => {
+                let mut stream = self.stream_all_events().await?;
+                let mut head = 0;
+                while let Some(event) = stream.next().await {
+                    head = head.max(event?.sequence.unwrap_or(0));
+                }
+                Ok(head)
+            }
+        }
+    }
+
+    async fn get_aggregate_version(&self, aggregate_id: AggregateId) -> Result<Option<Version>> {
+        let events = self.get_events_for_aggregate(aggregate_id).await?;
+        Ok(events.last().map(|e| e.version))
+    }
+
+    async fn save_snapshot(&self, _snapshot: Snapshot) -> Result<()> {
+        Err(EventStoreError::ReadOnly(
+            "cannot save snapshots to a time-travel view".to_string(),
+        ))
+    }
+
+    async fn get_snapshot(&self, aggregate_id: AggregateId) -> Result<Option<Snapshot>> {
+        let Some(snapshot) = self.inner.get_snapshot(aggregate_id).await? else {
+            return Ok(None);
+        };
+
+        // A snapshot past the cutoff would leak later state into the view
+        let visible_version = self.get_aggregate_version(aggregate_id).await?;
+        Ok(visible_version
+            .filter(|version| snapshot.version <= *version)
+            .map(|_| snapshot))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InMemoryEventStore;
+    use crate::store::EventStoreExt;
+    use chrono::Duration;
+
+    fn event_at(
+        aggregate_id: AggregateId,
+        version: i64,
+        event_type: &str,
+        timestamp: DateTime<Utc>,
+    ) -> EventEnvelope {
+        EventEnvelope::builder()
+            .aggregate_id(aggregate_id)
+            .aggregate_type("TestAggregate")
+            .event_type(event_type)
+            .version(Version::new(version))
+            .timestamp(timestamp)
+            .payload_raw(serde_json::json!({ "version": version }))
+            .build()
+    }
+
+    /// Appends three events to one aggregate, an hour apart.
+    async fn store_with_history() -> (InMemoryEventStore, AggregateId, DateTime<Utc>) {
+        let store = InMemoryEventStore::new();
+        let id = AggregateId::new();
+        let start = Utc::now() - Duration::hours(3);
+
+        for version in 1..=3 {
+            let at = start + Duration::hours(version - 1);
+            store
+                .append(
+                    vec![event_at(id, version, "Changed", at)],
+                    AppendOptions::new(),
+                )
+                .await
+                .unwrap();
+        }
+        (store, id, start)
+    }
+
+    #[tokio::test]
+    async fn hides_events_after_sequence() {
+        let (store, id, _) = store_with_history().await;
+        let view = TimeTravelEventStore::at_sequence(store, 2);
+
+        let events = view.get_events_for_aggregate(id).await.unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(
+            view.get_aggregate_version(id).await.unwrap(),
+            Some(Version::new(2))
+        );
+        assert_eq!(view.head_sequence().await.unwrap(), 2);
+        assert_eq!(view.get_events_by_type("Changed").await.unwrap().len(), 2);
+
+        let streamed: Vec<_> = view.stream_all_events().await.unwrap().collect().await;
+        assert_eq!(streamed.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn hides_events_after_timestamp() {
+        let (store, id, start) = store_with_history().await;
+        let view = TimeTravelEventStore::at_time(store, start + Duration::minutes(30));
+
+        let events = view.get_events_for_aggregate(id).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(view.head_sequence().await.unwrap(), 1);
+
+        let view = TimeTravelEventStore::at_time(view.inner().clone(), start - Duration::hours(1));
+        assert!(!view.aggregate_exists(id).await.unwrap());
+        assert_eq!(view.head_sequence().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn refuses_session_tokens_past_the_cutoff() {
+        let (store, id, _) = store_with_history().await;
+        let view = TimeTravelEventStore::at_sequence(store, 2);
+
+        let query = EventQuery::for_aggregate(id).after_token(crate::SessionToken::at_sequence(2));
+        assert_eq!(view.query_events(query).await.unwrap().len(), 2);
+
+        let query = EventQuery::for_aggregate(id).after_token(crate::SessionToken::at_sequence(3));
+        assert!(matches!(
+            view.query_events(query).await,
+            Err(EventStoreError::SessionNotCaughtUp {
+                required: 3,
+                head: 2
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn paginates_after_hiding_events() {
+        let (store, id, _) = store_with_history().await;
+        let view = TimeTravelEventStore::at_sequence(store, 2);
+
+        let query = EventQuery::for_aggregate(id).offset(1).limit(5);
+        let events = view.query_events(query).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].version, Version::new(2));
+    }
+
+    #[tokio::test]
+    async fn ignores_snapshots_past_cutoff() {
+        let (store, id, _) = store_with_history().await;
+        store
+            .save_snapshot(Snapshot::new(
+                id,
+                "TestAggregate",
+                Version::new(3),
+                serde_json::json!({}),
+            ))
+            .await
+            .unwrap();
+
+        let before = TimeTravelEventStore::at_sequence(store.clone(), 2);
+        let (snapshot, events) = before.load_aggregate(id).await.unwrap();
+        assert!(snapshot.is_none());
+        assert_eq!(events.len(), 2);
+
+        let after = TimeTravelEventStore::at_sequence(store, 3);
+        let (snapshot, events) = after.load_aggregate(id).await.unwrap();
+        assert_eq!(snapshot.unwrap().version, Version::new(3));
+        assert!(events.is_empty());
+    }
+
+    #[tokio::test]
+    async fn rejects_writes() {
+        let (store, id, _) = store_with_history().await;
+        let view = TimeTravelEventStore::at_sequence(store, 1);
+
+        let result = view
+            .append(
+                vec![event_at(id, 4, "Changed", Utc::now())],
+                AppendOptions::new(),
+            )
+            .await;
+        assert!(matches!(result, Err(EventStoreError::ReadOnly(_))));
+
+        let snapshot = Snapshot::new(id, "TestAggregate", Version::new(1), serde_json::json!({}));
+        let result = view.save_snapshot(snapshot).await;
+        assert!(matches!(result, Err(EventStoreError::ReadOnly(_))));
+        assert!(view.append_notifications().is_none());
+    }
+}
@@ -0,0 +1,362 @@
+//! Who gets to see which event streams through feeds and exports.
+//!
+//! Every stream is [`Normal`](StreamVisibility::Normal) unless its aggregate
+//! type or the stream itself says otherwise. [`Internal`] streams, such as
+//! saga instances, are left out of consumer-facing feeds but still reach
+//! internal readers; [`Hidden`] streams reach neither. The store itself is
+//! unaffected: loading an aggregate, running projections, or migrating the
+//! log still reads every event.
+//!
+//! Defaults are set per aggregate type when a [`VisibilityPolicy`] is built.
+//! Overrides for individual streams are kept in a [`StreamVisibilityStore`]
+//! and cached by the policy, so checking an event never touches storage.
+//!
+//! [`Internal`]: StreamVisibility::Internal
+//! [`Hidden`]: StreamVisibility::Hidden
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, RwLock};
+
+use async_trait::async_trait;
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+
+use crate::store::{EventStore, EventStream};
+use crate::{AggregateId, EventEnvelope, Result};
+
+/// How widely a stream's events are shown.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum StreamVisibility {
+    /// Shown to every reader.
+    #[default]
+    Normal,
+    /// Shown to internal readers only.
+    Internal,
+    /// Shown to no reader of feeds or exports.
+    Hidden,
+}
+
+impl StreamVisibility {
+    /// Returns the visibility's name as stored and configured.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StreamVisibility::Normal => "normal",
+            StreamVisibility::Internal => "internal",
+            StreamVisibility::Hidden => "hidden",
+        }
+    }
+}
+
+impl std::fmt::Display for StreamVisibility {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for StreamVisibility {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim() {
+            "normal" => Ok(StreamVisibility::Normal),
+            "internal" => Ok(StreamVisibility::Internal),
+            "hidden" => Ok(StreamVisibility::Hidden),
+            other => Err(format!(
+                "unknown stream visibility '{other}', expected normal, internal, or hidden"
+            )),
+        }
+    }
+}
+
+/// Who is reading a feed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Audience {
+    /// Consumers outside the system, e.g. customers and partner services.
+    Consumer,
+    /// Operators and services of this system.
+    Internal,
+}
+
+impl Audience {
+    /// Returns true if this audience may see streams with `visibility`.
+    pub fn can_see(&self, visibility: StreamVisibility) -> bool {
+        match self {
+            Audience::Consumer => visibility == StreamVisibility::Normal,
+            Audience::Internal => visibility != StreamVisibility::Hidden,
+        }
+    }
+}
+
+/// Storage for per-stream visibility overrides.
+#[async_trait]
+pub trait StreamVisibilityStore: Send + Sync {
+    /// Sets the visibility of one stream, or removes its override when
+    /// `visibility` is None.
+    async fn set_stream_visibility(
+        &self,
+        aggregate_id: AggregateId,
+        visibility: Option<StreamVisibility>,
+    ) -> Result<()>;
+
+    /// Returns every stream override.
+    async fn get_stream_visibilities(&self) -> Result<HashMap<AggregateId, StreamVisibility>>;
+}
+
+/// Stream overrides held in memory, lost on restart.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryStreamVisibilityStore {
+    streams: Arc<tokio::sync::RwLock<HashMap<AggregateId, StreamVisibility>>>,
+}
+
+impl InMemoryStreamVisibilityStore {
+    /// Creates an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl StreamVisibilityStore for InMemoryStreamVisibilityStore {
+    async fn set_stream_visibility(
+        &self,
+        aggregate_id: AggregateId,
+        visibility: Option<StreamVisibility>,
+    ) -> Result<()> {
+        let mut streams = self.streams.write().await;
+        match visibility {
+            Some(visibility) => streams.insert(aggregate_id, visibility),
+            None => streams.remove(&aggregate_id),
+        };
+        Ok(())
+    }
+
+    async fn get_stream_visibilities(&self) -> Result<HashMap<AggregateId, StreamVisibility>> {
+        Ok(self.streams.read().await.clone())
+    }
+}
+
+/// Decides which events each audience sees, shared by clones.
+#[derive(Clone)]
+pub struct VisibilityPolicy {
+    by_type: Arc<BTreeMap<String, StreamVisibility>>,
+    streams: Arc<RwLock<HashMap<AggregateId, StreamVisibility>>>,
+    store: Arc<dyn StreamVisibilityStore>,
+}
+
+impl Default for VisibilityPolicy {
+    /// Every stream normal, with overrides kept in memory.
+    fn default() -> Self {
+        Self::new(Arc::new(InMemoryStreamVisibilityStore::new()))
+    }
+}
+
+impl VisibilityPolicy {
+    /// Creates a policy keeping stream overrides in `store`. Call
+    /// [`load`](Self::load) to pick up overrides already stored.
+    pub fn new(store: Arc<dyn StreamVisibilityStore>) -> Self {
+        Self {
+            by_type: Arc::new(BTreeMap::new()),
+            streams: Arc::new(RwLock::new(HashMap::new())),
+            store,
+        }
+    }
+
+    /// Makes `visibility` the default for streams of `aggregate_type`.
+    pub fn with_type_visibility(
+        mut self,
+        aggregate_type: impl Into<String>,
+        visibility: StreamVisibility,
+    ) -> Self {
+        Arc::make_mut(&mut self.by_type).insert(aggregate_type.into(), visibility);
+        self
+    }
+
+    /// Reads the stored stream overrides into the cache.
+    pub async fn load(&self) -> Result<()> {
+        let streams = self.store.get_stream_visibilities().await?;
+        *self.streams.write().expect("stream visibilities poisoned") = streams;
+        Ok(())
+    }
+
+    /// Overrides the visibility of one stream, or returns it to its type's
+    /// default when `visibility` is None.
+    pub async fn set_stream(
+        &self,
+        aggregate_id: AggregateId,
+        visibility: Option<StreamVisibility>,
+    ) -> Result<()> {
+        self.store
+            .set_stream_visibility(aggregate_id, visibility)
+            .await?;
+        let mut streams = self.streams.write().expect("stream visibilities poisoned");
+        match visibility {
+            Some(visibility) => streams.insert(aggregate_id, visibility),
+            None => streams.remove(&aggregate_id),
+        };
+        Ok(())
+    }
+
+    /// Returns the default visibility of each configured aggregate type.
+    pub fn type_visibilities(&self) -> &BTreeMap<String, StreamVisibility> {
+        &self.by_type
+    }
+
+    /// Returns the cached stream overrides.
+    pub fn stream_visibilities(&self) -> HashMap<AggregateId, StreamVisibility> {
+        self.streams
+            .read()
+            .expect("stream visibilities poisoned")
+            .clone()
+    }
+
+    /// Returns the visibility of a stream: its override if it has one,
+    /// otherwise its type's default.
+    pub fn visibility(&self, aggregate_type: &str, aggregate_id: AggregateId) -> StreamVisibility {
+        if let Some(visibility) = self
+            .streams
+            .read()
+            .expect("stream visibilities poisoned")
+            .get(&aggregate_id)
+        {
+            return *visibility;
+        }
+        self.by_type
+            .get(aggregate_type)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Parses a comma-separated list of `AggregateType=visibility` entries,
+    /// e.g. `OrderFulfillmentSaga=internal,TestOrder=hidden`.
+    pub fn parse_list(list: &str) -> std::result::Result<Vec<(String, StreamVisibility)>, String> {
+        list.split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let (aggregate_type, visibility) = entry
+                    .split_once('=')
+                    .ok_or_else(|| format!("expected AggregateType=visibility, got {entry:?}"))?;
+                Ok((aggregate_type.trim().to_string(), visibility.parse()?))
+            })
+            .collect()
+    }
+
+    /// Returns true if `audience` may see `event`.
+    pub fn is_visible(&self, event: &EventEnvelope, audience: Audience) -> bool {
+        audience.can_see(self.visibility(&event.aggregate_type, event.aggregate_id))
+    }
+
+    /// Streams the events in `store` that `audience` may see, in global
+    /// sequence order.
+    pub async fn stream_all_events<S: EventStore + ?Sized>(
+        &self,
+        store: &S,
+        audience: Audience,
+    ) -> Result<EventStream> {
+        let policy = self.clone();
+        let events = store.stream_all_events().await?.filter(move |event| {
+            let keep = match event {
+                Ok(event) => policy.is_visible(event, audience),
+                Err(_) => true,
+            };
+            std::future::ready(keep)
+        });
+        Ok(Box::pin(events))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::AppendOptions;
+    use crate::{InMemoryEventStore, Version};
+
+    fn event(aggregate_type: &str, aggregate_id: AggregateId) -> EventEnvelope {
+        EventEnvelope::builder()
+            .aggregate_id(aggregate_id)
+            .aggregate_type(aggregate_type)
+            .event_type("Happened")
+            .version(Version::first())
+            .payload_raw(serde_json::json!({}))
+            .build()
+    }
+
+    #[test]
+    fn test_parses_visibility_names() {
+        for visibility in [
+            StreamVisibility::Normal,
+            StreamVisibility::Internal,
+            StreamVisibility::Hidden,
+        ] {
+            assert_eq!(visibility.as_str().parse(), Ok(visibility));
+        }
+        assert!("secret".parse::<StreamVisibility>().is_err());
+
+        assert_eq!(
+            VisibilityPolicy::parse_list("Saga=internal, TestOrder = hidden,").unwrap(),
+            vec![
+                ("Saga".to_string(), StreamVisibility::Internal),
+                ("TestOrder".to_string(), StreamVisibility::Hidden),
+            ]
+        );
+        assert!(VisibilityPolicy::parse_list("Saga").is_err());
+        assert!(VisibilityPolicy::parse_list("Saga=secret").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_stream_overrides_win_over_type_defaults() {
+        let store = InMemoryEventStore::new();
+        let (order, saga, test_order) =
+            (AggregateId::new(), AggregateId::new(), AggregateId::new());
+        for event in [
+            event("Order", order),
+            event("Saga", saga),
+            event("Order", test_order),
+        ] {
+            store
+                .append(vec![event], AppendOptions::new())
+                .await
+                .unwrap();
+        }
+
+        let overrides = Arc::new(InMemoryStreamVisibilityStore::new());
+        let policy = VisibilityPolicy::new(overrides.clone())
+            .with_type_visibility("Saga", StreamVisibility::Internal);
+        policy
+            .set_stream(test_order, Some(StreamVisibility::Hidden))
+            .await
+            .unwrap();
+
+        let ids = |audience| {
+            let policy = policy.clone();
+            let store = store.clone();
+            async move {
+                let events = policy.stream_all_events(&store, audience).await.unwrap();
+                events
+                    .map(|e| e.unwrap().aggregate_id)
+                    .collect::<Vec<_>>()
+                    .await
+            }
+        };
+        assert_eq!(ids(Audience::Consumer).await, [order]);
+        assert_eq!(ids(Audience::Internal).await, [order, saga]);
+
+        // A new policy over the same store picks the override up on load
+        let reloaded = VisibilityPolicy::new(overrides);
+        assert_eq!(
+            reloaded.visibility("Order", test_order),
+            StreamVisibility::Normal
+        );
+        reloaded.load().await.unwrap();
+        assert_eq!(
+            reloaded.visibility("Order", test_order),
+            StreamVisibility::Hidden
+        );
+
+        policy.set_stream(test_order, None).await.unwrap();
+        assert_eq!(ids(Audience::Consumer).await, [order, test_order]);
+    }
+}
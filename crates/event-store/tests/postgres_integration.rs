@@ -10,8 +10,10 @@
 //! ```
 
 use event_store::{
-    AggregateId, AppendOptions, EventEnvelope, EventQuery, EventStore, EventStoreExt,
-    PostgresEventStore, Snapshot, Version,
+    AggregateId, AnnotationStore, AppendOptions, EventAnnotation, EventEnvelope, EventId,
+    EventQuery, EventStore, EventStoreError, EventStoreExt, IdempotencyClaim, IdempotencyStore,
+    PendingEventStore, PostgresEventStore, QueryAnalyzer, SchemaDrift, SchemaVersionSource,
+    Snapshot, StoredResponse, StreamAppend, StreamVisibility, StreamVisibilityStore, Version,
 };
 use serial_test::serial;
 use sqlx::PgPool;
@@ -67,12 +69,10 @@ async fn get_container() -> Arc<TestContainer> {
 
             // Run migrations
             let pool = PgPool::connect(&connection_string).await.unwrap();
-            sqlx::raw_sql(include_str!(
-                "../../../migrations/001_create_events_table.sql"
-            ))
-            .execute(&pool)
-            .await
-            .unwrap();
+            PostgresEventStore::new(pool.clone())
+                .run_migrations()
+                .await
+                .unwrap();
             pool.close().await;
 
             Arc::new(TestContainer {
@@ -96,10 +96,13 @@ async fn get_test_store() -> PostgresEventStore {
         .unwrap();
 
     // Clear tables for test isolation
-    sqlx::query("TRUNCATE TABLE events, snapshots")
-        .execute(&pool)
-        .await
-        .unwrap();
+    sqlx::query(
+        "TRUNCATE TABLE events, snapshots, pending_batches, idempotency_keys, event_annotations, \
+         stream_visibility",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
 
     PostgresEventStore::new(pool)
 }
@@ -158,6 +161,118 @@ async fn append_multiple_events_atomically() {
     assert_eq!(stored[2].version, Version::new(3));
 }
 
+#[tokio::test]
+#[serial]
+async fn large_batch_keeps_event_order() {
+    let store = get_test_store().await;
+    let aggregate_id = AggregateId::new();
+
+    let events: Vec<_> = (1..=250)
+        .map(|v| create_test_event(aggregate_id, Version::new(v), &format!("Event{v}")))
+        .collect();
+    let last = store
+        .append(events, AppendOptions::expect_new())
+        .await
+        .unwrap();
+    assert_eq!(last, Version::new(250));
+
+    let stored = store.get_events_for_aggregate(aggregate_id).await.unwrap();
+    assert_eq!(stored.len(), 250);
+    for pair in stored.windows(2) {
+        assert!(pair[0].sequence < pair[1].sequence);
+    }
+    assert_eq!(stored[249].event_type, "Event250");
+}
+
+#[tokio::test]
+#[serial]
+async fn batch_with_conflicting_version_appends_nothing() {
+    let store = get_test_store().await;
+    let aggregate_id = AggregateId::new();
+    store
+        .append(
+            vec![create_test_event(aggregate_id, Version::first(), "Event1")],
+            AppendOptions::expect_new(),
+        )
+        .await
+        .unwrap();
+
+    // Without an expected version, the duplicate version 1 is only caught
+    // by the unique constraint
+    let events = vec![
+        create_test_event(aggregate_id, Version::new(1), "Event1"),
+        create_test_event(aggregate_id, Version::new(2), "Event2"),
+    ];
+    let result = store.append(events, AppendOptions::new()).await;
+
+    assert!(matches!(
+        result,
+        Err(EventStoreError::ConcurrencyConflict { .. })
+    ));
+    let stored = store.get_events_for_aggregate(aggregate_id).await.unwrap();
+    assert_eq!(stored.len(), 1);
+}
+
+#[tokio::test]
+#[serial]
+async fn append_batch_spans_aggregates_in_one_transaction() {
+    let store = get_test_store().await;
+    let (order, saga) = (AggregateId::new(), AggregateId::new());
+    store
+        .append(
+            vec![create_test_event(order, Version::first(), "Created")],
+            AppendOptions::expect_new(),
+        )
+        .await
+        .unwrap();
+
+    // The saga's events are inserted first, then rolled back with the
+    // order's conflict
+    let result = store
+        .append_batch(vec![
+            StreamAppend::new(
+                vec![create_test_event(saga, Version::first(), "Started")],
+                AppendOptions::expect_new(),
+            ),
+            StreamAppend::new(
+                vec![create_test_event(order, Version::new(2), "Reserved")],
+                AppendOptions::expect_new(),
+            ),
+        ])
+        .await;
+    assert!(matches!(
+        result,
+        Err(EventStoreError::ConcurrencyConflict { aggregate_id, .. }) if aggregate_id == order
+    ));
+    assert!(
+        store
+            .get_events_for_aggregate(saga)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+
+    let versions = store
+        .append_batch(vec![
+            StreamAppend::new(
+                vec![create_test_event(saga, Version::first(), "Started")],
+                AppendOptions::expect_new(),
+            ),
+            StreamAppend::new(
+                vec![create_test_event(order, Version::new(2), "Reserved")],
+                AppendOptions::expect_version(Version::first()),
+            ),
+        ])
+        .await
+        .unwrap();
+    assert_eq!(versions, [Version::first(), Version::new(2)]);
+    let saga_events = store.get_events_for_aggregate(saga).await.unwrap();
+    let order_events = store.get_events_for_aggregate(order).await.unwrap();
+    assert_eq!(saga_events.len(), 1);
+    assert_eq!(order_events.len(), 2);
+    assert!(saga_events[0].sequence < order_events[1].sequence);
+}
+
 #[tokio::test]
 #[serial]
 async fn optimistic_concurrency_conflict() {
@@ -299,6 +414,29 @@ async fn query_events_with_filters() {
     assert_eq!(results[0].version, Version::new(2));
 }
 
+#[tokio::test]
+#[serial]
+async fn analyze_query_reports_plan_without_running_it() {
+    let store = get_test_store().await;
+    let query = EventQuery::for_event_type("Event1")
+        .from_timestamp(chrono::Utc::now() - chrono::Duration::hours(1))
+        .limit(10);
+
+    let analysis = store.analyze_query(&query).await.unwrap();
+
+    assert!(analysis.sql.contains("event_type = ANY($1)"));
+    assert!(analysis.plan.is_array());
+    assert!(analysis.advisory.is_none());
+
+    // Version ranges across aggregates have no index to use
+    let query = EventQuery::for_event_type("Event1").from_version(Version::new(2));
+    let analysis = store.analyze_query(&query).await.unwrap();
+    assert_eq!(
+        analysis.advisory.unwrap().suggested_index,
+        "CREATE INDEX ON events (event_type, version)"
+    );
+}
+
 #[tokio::test]
 #[serial]
 async fn query_events_with_limit_and_offset() {
@@ -414,6 +552,45 @@ async fn stream_all_events() {
     let events: Vec<_> = stream.collect().await;
     assert_eq!(events.len(), 2);
     assert!(events.iter().all(|e| e.is_ok()));
+
+    let sequences: Vec<_> = events
+        .into_iter()
+        .map(|e| e.unwrap().sequence.unwrap())
+        .collect();
+    assert!(sequences[0] < sequences[1]);
+    assert_eq!(store.head_sequence().await.unwrap(), sequences[1]);
+}
+
+#[tokio::test]
+#[serial]
+async fn stream_events_after_sequence() {
+    use futures_util::TryStreamExt;
+
+    let store = get_test_store().await;
+    let id = AggregateId::new();
+
+    store
+        .append(
+            vec![
+                create_test_event(id, Version::new(1), "Event1"),
+                create_test_event(id, Version::new(2), "Event2"),
+            ],
+            AppendOptions::new(),
+        )
+        .await
+        .unwrap();
+    let head = store.head_sequence().await.unwrap();
+
+    let events: Vec<_> = store
+        .stream_events_after(head - 1)
+        .await
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].sequence, Some(head));
+    assert_eq!(events[0].event_type, "Event2");
 }
 
 #[tokio::test]
@@ -549,3 +726,258 @@ async fn event_metadata_preserved() {
         Some(&serde_json::json!("cause-456"))
     );
 }
+
+#[tokio::test]
+#[serial]
+async fn append_notifies_listener() {
+    let store = get_test_store().await.with_append_listener().await.unwrap();
+    let mut notifications = store.append_notifications().unwrap();
+
+    let event = create_test_event(AggregateId::new(), Version::first(), "TestEvent");
+    store
+        .append(vec![event], AppendOptions::expect_new())
+        .await
+        .unwrap();
+
+    tokio::time::timeout(std::time::Duration::from_secs(5), notifications.changed())
+        .await
+        .expect("no append notification")
+        .unwrap();
+    assert_eq!(
+        *notifications.borrow(),
+        store.head_sequence().await.unwrap()
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn subscription_replays_then_tails_appends() {
+    use futures_util::StreamExt;
+
+    let store = get_test_store().await.with_append_listener().await.unwrap();
+    let aggregate_id = AggregateId::new();
+    store
+        .append(
+            vec![create_test_event(aggregate_id, Version::first(), "Created")],
+            AppendOptions::expect_new(),
+        )
+        .await
+        .unwrap();
+
+    let mut subscription = store.subscribe(0).await.unwrap();
+    let replayed = subscription.next().await.unwrap().unwrap();
+    assert_eq!(replayed.event_type, "Created");
+
+    store
+        .append(
+            vec![create_test_event(aggregate_id, Version::new(2), "Updated")],
+            AppendOptions::expect_version(Version::first()),
+        )
+        .await
+        .unwrap();
+    let live = tokio::time::timeout(std::time::Duration::from_secs(5), subscription.next())
+        .await
+        .expect("subscription did not yield the append")
+        .unwrap()
+        .unwrap();
+    assert_eq!(live.event_type, "Updated");
+    assert!(live.sequence > replayed.sequence);
+}
+
+#[tokio::test]
+#[serial]
+async fn pending_batch_commits_atomically() {
+    let store = get_test_store().await;
+    let aggregate_id = AggregateId::new();
+
+    let events = vec![
+        create_test_event(aggregate_id, Version::new(1), "Created"),
+        create_test_event(aggregate_id, Version::new(2), "Updated"),
+    ];
+    let batch_id = store
+        .append_pending(events, AppendOptions::expect_new())
+        .await
+        .unwrap();
+
+    assert!(!store.aggregate_exists(aggregate_id).await.unwrap());
+    let staged = store.get_pending(batch_id).await.unwrap().unwrap();
+    assert_eq!(staged.events.len(), 2);
+
+    let version = store.commit_pending(batch_id).await.unwrap();
+    assert_eq!(version, Version::new(2));
+    assert_eq!(
+        store
+            .get_events_for_aggregate(aggregate_id)
+            .await
+            .unwrap()
+            .len(),
+        2
+    );
+    assert!(store.list_pending().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+#[serial]
+async fn pending_batch_discard_leaves_no_events() {
+    let store = get_test_store().await;
+    let aggregate_id = AggregateId::new();
+
+    let batch_id = store
+        .append_pending(
+            vec![create_test_event(aggregate_id, Version::new(1), "Created")],
+            AppendOptions::expect_new(),
+        )
+        .await
+        .unwrap();
+    store.discard_pending(batch_id).await.unwrap();
+
+    assert!(!store.aggregate_exists(aggregate_id).await.unwrap());
+    let result = store.commit_pending(batch_id).await;
+    assert!(matches!(
+        result,
+        Err(EventStoreError::PendingBatchNotFound(_))
+    ));
+}
+
+#[tokio::test]
+#[serial]
+async fn idempotency_key_replays_stored_response() {
+    let store = get_test_store().await;
+    let now = chrono::Utc::now();
+    let expires_at = now + chrono::Duration::hours(1);
+    let lease = std::time::Duration::from_secs(60);
+    let response = StoredResponse {
+        status: 201,
+        headers: vec![
+            ("content-type".to_string(), "application/json".to_string()),
+            ("location".to_string(), "/orders/1".to_string()),
+        ],
+        body: b"{}".to_vec(),
+    };
+
+    assert_eq!(
+        store
+            .claim("key-1", "hash-a", lease, expires_at)
+            .await
+            .unwrap(),
+        IdempotencyClaim::New
+    );
+    assert_eq!(
+        store
+            .claim("key-1", "hash-a", lease, expires_at)
+            .await
+            .unwrap(),
+        IdempotencyClaim::InProgress
+    );
+    store.complete("key-1", response.clone()).await.unwrap();
+    store.release("key-1").await.unwrap();
+
+    assert_eq!(
+        store
+            .claim("key-1", "hash-a", lease, expires_at)
+            .await
+            .unwrap(),
+        IdempotencyClaim::Replay(response)
+    );
+    assert_eq!(
+        store
+            .claim("key-1", "hash-b", lease, expires_at)
+            .await
+            .unwrap(),
+        IdempotencyClaim::Mismatch
+    );
+
+    store
+        .claim("key-2", "hash-a", lease, now - chrono::Duration::seconds(1))
+        .await
+        .unwrap();
+    assert_eq!(store.purge_expired(now).await.unwrap(), 1);
+
+    // A claim whose request never finished is taken over after its lease
+    store
+        .claim("key-3", "hash-a", lease, expires_at)
+        .await
+        .unwrap();
+    assert_eq!(
+        store
+            .claim("key-3", "hash-a", std::time::Duration::ZERO, expires_at)
+            .await
+            .unwrap(),
+        IdempotencyClaim::New
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn event_annotations_are_kept_beside_the_event() {
+    let store = get_test_store().await;
+    let aggregate_id = AggregateId::new();
+    let event = create_test_event(aggregate_id, Version::new(1), "ItemAdded");
+    let event_id = event.event_id;
+    store
+        .append(vec![event.clone()], AppendOptions::expect_new())
+        .await
+        .unwrap();
+
+    let found = store.get_event(event_id).await.unwrap().unwrap();
+    assert_eq!(found.event_id, event_id);
+    assert!(store.get_event(EventId::new()).await.unwrap().is_none());
+
+    let annotation = EventAnnotation::new(event_id, "alice", "Bug starts here");
+    store.add_annotation(annotation.clone()).await.unwrap();
+    let stored = store.get_annotations(&[event_id]).await.unwrap();
+    assert_eq!(stored.len(), 1);
+    assert_eq!(stored[0].note, annotation.note);
+    assert!(
+        store
+            .get_annotations(&[EventId::new()])
+            .await
+            .unwrap()
+            .is_empty()
+    );
+
+    // The event itself is untouched
+    let events = store.get_events_for_aggregate(aggregate_id).await.unwrap();
+    assert_eq!(events[0].payload, event.payload);
+}
+
+#[tokio::test]
+#[serial]
+async fn stream_visibility_overrides_are_stored() {
+    let store = get_test_store().await;
+    let (hidden, internal) = (AggregateId::new(), AggregateId::new());
+
+    store
+        .set_stream_visibility(hidden, Some(StreamVisibility::Internal))
+        .await
+        .unwrap();
+    store
+        .set_stream_visibility(hidden, Some(StreamVisibility::Hidden))
+        .await
+        .unwrap();
+    store
+        .set_stream_visibility(internal, Some(StreamVisibility::Internal))
+        .await
+        .unwrap();
+    let stored = store.get_stream_visibilities().await.unwrap();
+    assert_eq!(stored.len(), 2);
+    assert_eq!(stored[&hidden], StreamVisibility::Hidden);
+
+    store.set_stream_visibility(internal, None).await.unwrap();
+    let stored = store.get_stream_visibilities().await.unwrap();
+    assert!(!stored.contains_key(&internal));
+}
+
+#[tokio::test]
+#[serial]
+async fn schema_version_is_up_to_date_after_migrations() {
+    let store = get_test_store().await;
+
+    let version = store.schema_version().await.unwrap();
+
+    assert_eq!(
+        version.applied,
+        Some(PostgresEventStore::expected_schema_version())
+    );
+    assert_eq!(version.drift(), SchemaDrift::UpToDate);
+}
@@ -0,0 +1,44 @@
+//! Row-level access policies for read model queries.
+//!
+//! A [`RowPolicy`] decides which rows of a view a caller may see and masks
+//! the fields it may not. View methods that take a policy apply it while
+//! reading, before any paging, so totals and pages count only the rows the
+//! caller can see.
+
+/// Which rows of type `T` a caller may see, and how they are masked.
+pub trait RowPolicy<T>: Send + Sync {
+    /// Returns true if the caller may see `row`.
+    fn permits(&self, row: &T) -> bool;
+
+    /// Masks the fields of a permitted row the caller may not see. The
+    /// default leaves the row unchanged.
+    fn mask(&self, _row: &mut T) {}
+}
+
+/// Permits every row unchanged.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Unrestricted;
+
+impl<T> RowPolicy<T> for Unrestricted {
+    fn permits(&self, _row: &T) -> bool {
+        true
+    }
+}
+
+/// Returns masked copies of the rows `policy` permits.
+pub fn visible_rows<'a, T: Clone + 'a>(
+    policy: &dyn RowPolicy<T>,
+    rows: impl IntoIterator<Item = &'a T>,
+) -> Vec<T> {
+    rows.into_iter()
+        .filter(|row| policy.permits(row))
+        .map(|row| masked(policy, row))
+        .collect()
+}
+
+/// Returns a copy of `row` masked by `policy`.
+pub fn masked<T: Clone>(policy: &dyn RowPolicy<T>, row: &T) -> T {
+    let mut row = row.clone();
+    policy.mask(&mut row);
+    row
+}
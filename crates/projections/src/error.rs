@@ -1,5 +1,7 @@
 //! Projection error types.
 
+use std::time::Duration;
+
 use thiserror::Error;
 
 /// Errors that can occur during projection processing.
@@ -13,10 +15,72 @@ pub enum ProjectionError {
     #[error("Event deserialization error: {0}")]
     Deserialization(#[from] serde_json::Error),
 
+    /// No exchange rate is available for a currency pair.
+    #[error("No exchange rate from {from} to {to}")]
+    MissingExchangeRate {
+        from: domain::Currency,
+        to: domain::Currency,
+    },
+
+    /// Amounts in different currencies were added together.
+    #[error("Currency mismatch: expected {expected}, got {actual}")]
+    CurrencyMismatch {
+        expected: domain::Currency,
+        actual: domain::Currency,
+    },
+
+    /// A projection with this name is already registered.
+    #[error("Projection already registered: {0}")]
+    AlreadyRegistered(String),
+
+    /// No projection with this name is registered.
+    #[error("Projection not registered: {0}")]
+    NotRegistered(String),
+
+    /// A read model query gave up waiting for the view's lock.
+    #[error("Read model {read_model} busy: lock not acquired within {waited:?}")]
+    LockTimeout {
+        read_model: &'static str,
+        waited: Duration,
+    },
+
+    /// A read model's backing store can't be reached.
+    #[error("Read model {read_model} unavailable: {reason}")]
+    Unavailable {
+        read_model: &'static str,
+        reason: String,
+    },
+
+    /// Publishing to or reading from the event bus failed.
+    #[error("Event bus error: {0}")]
+    EventBus(String),
+
     /// A projection-specific error.
     #[error("Projection error: {0}")]
     Projection(String),
 }
 
+impl From<domain::OrderError> for ProjectionError {
+    fn from(e: domain::OrderError) -> Self {
+        match e {
+            domain::OrderError::CurrencyMismatch { expected, actual } => {
+                ProjectionError::CurrencyMismatch { expected, actual }
+            }
+            other => ProjectionError::Projection(other.to_string()),
+        }
+    }
+}
+
+impl ProjectionError {
+    /// Returns true if the error is transient: the read model can't be
+    /// queried right now but may be on retry.
+    pub fn is_unavailable(&self) -> bool {
+        matches!(
+            self,
+            ProjectionError::LockTimeout { .. } | ProjectionError::Unavailable { .. }
+        )
+    }
+}
+
 /// Result type for projection operations.
 pub type Result<T> = std::result::Result<T, ProjectionError>;
@@ -0,0 +1,104 @@
+//! Exchange rates for consolidated multi-currency reporting.
+//!
+//! Read models keep revenue bucketed per currency and never convert implicitly.
+//! Conversion only happens at query time through an [`ExchangeRateProvider`].
+
+use std::collections::HashMap;
+
+use domain::{Currency, Money};
+
+use crate::{ProjectionError, Result};
+
+/// Source of exchange rates used to consolidate amounts at query time.
+pub trait ExchangeRateProvider: Send + Sync {
+    /// Returns how many units of `to` one unit of `from` is worth.
+    ///
+    /// Returns None if the rate is unknown.
+    fn rate(&self, from: Currency, to: Currency) -> Option<f64>;
+
+    /// Converts an amount from one currency into another.
+    fn convert(&self, amount: Money, from: Currency, to: Currency) -> Result<Money> {
+        if from == to {
+            return Ok(amount.in_currency(to));
+        }
+
+        let rate = self
+            .rate(from, to)
+            .ok_or(ProjectionError::MissingExchangeRate { from, to })?;
+        Ok(Money::from_cents_in(
+            (amount.cents() as f64 * rate).round() as i64,
+            to,
+        ))
+    }
+}
+
+/// Exchange rate provider backed by a fixed table of rates.
+///
+/// If only the inverse pair is known, its reciprocal is used.
+#[derive(Debug, Clone, Default)]
+pub struct FixedExchangeRates {
+    rates: HashMap<(Currency, Currency), f64>,
+}
+
+impl FixedExchangeRates {
+    /// Creates an empty rate table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the rate for converting `from` into `to`.
+    pub fn with_rate(mut self, from: Currency, to: Currency, rate: f64) -> Self {
+        self.rates.insert((from, to), rate);
+        self
+    }
+}
+
+impl ExchangeRateProvider for FixedExchangeRates {
+    fn rate(&self, from: Currency, to: Currency) -> Option<f64> {
+        self.rates.get(&(from, to)).copied().or_else(|| {
+            self.rates
+                .get(&(to, from))
+                .filter(|rate| **rate != 0.0)
+                .map(|rate| 1.0 / rate)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_same_currency_is_identity() {
+        let rates = FixedExchangeRates::new();
+        let amount = rates
+            .convert(Money::from_cents(1234), Currency::USD, Currency::USD)
+            .unwrap();
+        assert_eq!(amount.cents(), 1234);
+    }
+
+    #[test]
+    fn test_convert_uses_direct_and_inverse_rates() {
+        let rates = FixedExchangeRates::new().with_rate(Currency::EUR, Currency::USD, 1.25);
+
+        let usd = rates
+            .convert(Money::from_cents(1000), Currency::EUR, Currency::USD)
+            .unwrap();
+        assert_eq!(usd.cents(), 1250);
+
+        let eur = rates
+            .convert(Money::from_cents(1250), Currency::USD, Currency::EUR)
+            .unwrap();
+        assert_eq!(eur.cents(), 1000);
+    }
+
+    #[test]
+    fn test_convert_missing_rate_fails() {
+        let rates = FixedExchangeRates::new();
+        let result = rates.convert(Money::from_cents(1000), Currency::GBP, Currency::USD);
+        assert!(matches!(
+            result,
+            Err(ProjectionError::MissingExchangeRate { .. })
+        ));
+    }
+}
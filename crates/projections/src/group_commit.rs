@@ -0,0 +1,530 @@
+//! Group commit of row writes for database-backed views.
+//!
+//! Writing a view's rows one event at a time costs a round trip and a
+//! transaction per event, which can't keep up with a catch-up over millions
+//! of events. A [`GroupCommit`] buffers the row changes of many events,
+//! keeping only the latest change per key, and hands them to a [`RowSink`]
+//! in one transaction once [`GroupCommitConfig::max_rows`] rows are
+//! pending or the oldest has waited [`GroupCommitConfig::max_delay`]. The
+//! view's position is committed with its rows, so after a crash the view
+//! resumes from the last group it stored.
+//!
+//! [`PostgresRowSink`] stores rows in the `read_model_rows` table with
+//! multi-row upserts.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use chrono::Utc;
+use event_store::EventId;
+use sqlx::postgres::{PgPool, PgRow};
+use sqlx::{Row, types::Uuid};
+use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+
+use crate::projection::ProjectionPosition;
+use crate::{ProjectionError, Result};
+
+/// A change to one row of a view.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RowChange {
+    /// Inserts the row, or replaces it if `key` exists.
+    Upsert { key: String, row: serde_json::Value },
+    /// Removes the row, if `key` exists.
+    Delete { key: String },
+}
+
+impl RowChange {
+    /// Returns the key of the changed row.
+    pub fn key(&self) -> &str {
+        match self {
+            RowChange::Upsert { key, .. } | RowChange::Delete { key } => key,
+        }
+    }
+}
+
+/// Durable storage for a view's rows.
+#[async_trait]
+pub trait RowSink: Send + Sync {
+    /// Applies `changes` and records `position` in one transaction.
+    async fn commit(&self, changes: &[RowChange], position: ProjectionPosition) -> Result<()>;
+
+    /// Returns the position recorded by the last commit.
+    async fn committed_position(&self) -> Result<ProjectionPosition>;
+
+    /// Removes every row and the recorded position, e.g. for a rebuild.
+    async fn clear(&self) -> Result<()>;
+}
+
+/// When a [`GroupCommit`] flushes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GroupCommitConfig {
+    /// Flush once this many rows are pending.
+    pub max_rows: usize,
+    /// Flush once the oldest pending change has waited this long.
+    pub max_delay: Duration,
+}
+
+impl GroupCommitConfig {
+    /// Flushes at `max_rows` pending rows.
+    pub fn with_max_rows(mut self, max_rows: usize) -> Self {
+        self.max_rows = max_rows.max(1);
+        self
+    }
+
+    /// Flushes once changes have waited `max_delay`.
+    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+}
+
+impl Default for GroupCommitConfig {
+    fn default() -> Self {
+        Self {
+            max_rows: 1000,
+            max_delay: Duration::from_millis(500),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Buffer {
+    changes: Vec<RowChange>,
+    /// Index into `changes` of each pending key.
+    keys: HashMap<String, usize>,
+    /// Position after the last staged event, if it hasn't been committed.
+    position: Option<ProjectionPosition>,
+    oldest: Option<Instant>,
+}
+
+impl Buffer {
+    fn push(&mut self, change: RowChange) {
+        match self.keys.get(change.key()) {
+            Some(&i) => self.changes[i] = change,
+            None => {
+                self.keys
+                    .insert(change.key().to_string(), self.changes.len());
+                self.changes.push(change);
+            }
+        }
+    }
+}
+
+/// Buffers a view's row changes and commits them in groups.
+pub struct GroupCommit<S> {
+    sink: S,
+    config: GroupCommitConfig,
+    buffer: Mutex<Buffer>,
+}
+
+impl<S: RowSink> GroupCommit<S> {
+    /// Creates a group commit writing to `sink`.
+    pub fn new(sink: S, config: GroupCommitConfig) -> Self {
+        Self {
+            sink,
+            config,
+            buffer: Mutex::new(Buffer::default()),
+        }
+    }
+
+    /// Returns the sink rows are committed to.
+    pub fn sink(&self) -> &S {
+        &self.sink
+    }
+
+    /// Returns when groups are flushed.
+    pub fn config(&self) -> GroupCommitConfig {
+        self.config
+    }
+
+    /// Buffers the row changes of an event, flushing if the group is full
+    /// or has waited long enough.
+    ///
+    /// `position` is the view's position after the event; an event that
+    /// changes no rows still moves it.
+    pub async fn stage(
+        &self,
+        changes: impl IntoIterator<Item = RowChange>,
+        position: ProjectionPosition,
+    ) -> Result<()> {
+        let mut buffer = self.buffer.lock().await;
+        for change in changes {
+            buffer.push(change);
+        }
+        buffer.position = Some(position);
+        buffer.oldest.get_or_insert_with(Instant::now);
+
+        if buffer.changes.len() >= self.config.max_rows || self.is_due(&buffer) {
+            self.flush_locked(&mut buffer).await?;
+        }
+        Ok(())
+    }
+
+    /// Commits everything pending, returning the number of rows written.
+    pub async fn flush(&self) -> Result<usize> {
+        let mut buffer = self.buffer.lock().await;
+        self.flush_locked(&mut buffer).await
+    }
+
+    /// Commits what's pending if the oldest change has waited
+    /// [`max_delay`](GroupCommitConfig::max_delay).
+    pub async fn flush_if_due(&self) -> Result<usize> {
+        let mut buffer = self.buffer.lock().await;
+        if !self.is_due(&buffer) {
+            return Ok(0);
+        }
+        self.flush_locked(&mut buffer).await
+    }
+
+    /// Returns the number of rows waiting to be committed.
+    pub async fn pending_rows(&self) -> usize {
+        self.buffer.lock().await.changes.len()
+    }
+
+    /// Drops everything pending and clears the sink.
+    pub async fn reset(&self) -> Result<()> {
+        let mut buffer = self.buffer.lock().await;
+        *buffer = Buffer::default();
+        self.sink.clear().await
+    }
+
+    /// Flushes groups that have waited too long until `shutdown` is
+    /// cancelled, then flushes whatever is left.
+    ///
+    /// Without it, the last changes before the log goes quiet wait for the
+    /// next event to be written. Failed flushes are logged and retried on
+    /// the next tick.
+    pub async fn run_flusher(&self, shutdown: CancellationToken) -> Result<()> {
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = tokio::time::sleep(self.config.max_delay) => {}
+            }
+            if let Err(e) = self.flush_if_due().await {
+                tracing::warn!(error = %e, "group commit flush failed");
+            }
+        }
+        self.flush().await.map(|_| ())
+    }
+
+    fn is_due(&self, buffer: &Buffer) -> bool {
+        buffer
+            .oldest
+            .is_some_and(|oldest| oldest.elapsed() >= self.config.max_delay)
+    }
+
+    /// Commits the buffer. It's left intact if the commit fails, so the
+    /// next flush retries the same group.
+    async fn flush_locked(&self, buffer: &mut Buffer) -> Result<usize> {
+        let Some(position) = buffer.position else {
+            return Ok(0);
+        };
+        let rows = buffer.changes.len();
+
+        let started = Instant::now();
+        self.sink.commit(&buffer.changes, position).await?;
+        metrics::counter!("projection_group_commits").increment(1);
+        metrics::histogram!("projection_group_commit_rows").record(rows as f64);
+        metrics::histogram!("projection_group_commit_seconds")
+            .record(started.elapsed().as_secs_f64());
+
+        *buffer = Buffer::default();
+        Ok(rows)
+    }
+}
+
+/// Rows of one view in the `read_model_rows` table.
+#[derive(Debug, Clone)]
+pub struct PostgresRowSink {
+    pool: PgPool,
+    view: &'static str,
+}
+
+impl PostgresRowSink {
+    /// Creates a sink for the rows of `view`.
+    pub fn new(pool: PgPool, view: &'static str) -> Self {
+        Self { pool, view }
+    }
+
+    /// Returns the stored row under `key`, if any.
+    pub async fn get(&self, key: &str) -> Result<Option<serde_json::Value>> {
+        let row: Option<PgRow> =
+            sqlx::query("SELECT row FROM read_model_rows WHERE view = $1 AND key = $2")
+                .bind(self.view)
+                .bind(key)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(|e| self.unavailable(e))?;
+        Ok(row.map(|row| row.get("row")))
+    }
+
+    fn unavailable(&self, e: sqlx::Error) -> ProjectionError {
+        ProjectionError::Unavailable {
+            read_model: self.view,
+            reason: e.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl RowSink for PostgresRowSink {
+    async fn commit(&self, changes: &[RowChange], position: ProjectionPosition) -> Result<()> {
+        let mut upserted_keys = Vec::new();
+        let mut upserted_rows = Vec::new();
+        let mut deleted_keys = Vec::new();
+        for change in changes {
+            match change {
+                RowChange::Upsert { key, row } => {
+                    upserted_keys.push(key.as_str());
+                    upserted_rows.push(row);
+                }
+                RowChange::Delete { key } => deleted_keys.push(key.as_str()),
+            }
+        }
+        let now = Utc::now();
+
+        let mut tx = self.pool.begin().await.map_err(|e| self.unavailable(e))?;
+        if !deleted_keys.is_empty() {
+            sqlx::query("DELETE FROM read_model_rows WHERE view = $1 AND key = ANY($2::text[])")
+                .bind(self.view)
+                .bind(&deleted_keys)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| self.unavailable(e))?;
+        }
+        if !upserted_keys.is_empty() {
+            // Columns are bound as arrays, so one statement writes the whole
+            // group whatever its size
+            sqlx::query(
+                r#"
+                INSERT INTO read_model_rows (view, key, row, updated_at)
+                SELECT $1, key, row, $4
+                FROM UNNEST($2::text[], $3::jsonb[]) AS r(key, row)
+                ON CONFLICT (view, key) DO UPDATE
+                    SET row = EXCLUDED.row, updated_at = EXCLUDED.updated_at
+                "#,
+            )
+            .bind(self.view)
+            .bind(&upserted_keys)
+            .bind(&upserted_rows)
+            .bind(now)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| self.unavailable(e))?;
+        }
+        sqlx::query(
+            r#"
+            INSERT INTO read_model_positions (view, last_sequence, events_processed, last_event_id, updated_at)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (view) DO UPDATE
+                SET last_sequence = EXCLUDED.last_sequence,
+                    events_processed = EXCLUDED.events_processed,
+                    last_event_id = EXCLUDED.last_event_id,
+                    updated_at = EXCLUDED.updated_at
+            "#,
+        )
+        .bind(self.view)
+        .bind(position.last_sequence as i64)
+        .bind(position.events_processed as i64)
+        .bind(position.last_event_id.map(|id| id.as_uuid()))
+        .bind(now)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| self.unavailable(e))?;
+        tx.commit().await.map_err(|e| self.unavailable(e))
+    }
+
+    async fn committed_position(&self) -> Result<ProjectionPosition> {
+        let row: Option<PgRow> = sqlx::query(
+            r#"
+            SELECT last_sequence, events_processed, last_event_id
+            FROM read_model_positions
+            WHERE view = $1
+            "#,
+        )
+        .bind(self.view)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| self.unavailable(e))?;
+
+        Ok(row
+            .map(|row| ProjectionPosition {
+                last_sequence: row.get::<i64, _>("last_sequence") as u64,
+                events_processed: row.get::<i64, _>("events_processed") as u64,
+                last_event_id: row
+                    .get::<Option<Uuid>, _>("last_event_id")
+                    .map(EventId::from_uuid),
+            })
+            .unwrap_or_default())
+    }
+
+    async fn clear(&self) -> Result<()> {
+        let mut tx = self.pool.begin().await.map_err(|e| self.unavailable(e))?;
+        for table in ["read_model_rows", "read_model_positions"] {
+            sqlx::query(&format!("DELETE FROM {table} WHERE view = $1"))
+                .bind(self.view)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| self.unavailable(e))?;
+        }
+        tx.commit().await.map_err(|e| self.unavailable(e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    /// Records each commit, failing while `fail` is set.
+    #[derive(Default)]
+    struct RecordingSink {
+        commits: std::sync::Mutex<Vec<(Vec<RowChange>, ProjectionPosition)>>,
+        fail: AtomicBool,
+    }
+
+    #[async_trait]
+    impl RowSink for Arc<RecordingSink> {
+        async fn commit(&self, changes: &[RowChange], position: ProjectionPosition) -> Result<()> {
+            if self.fail.load(Ordering::SeqCst) {
+                return Err(ProjectionError::Unavailable {
+                    read_model: "test",
+                    reason: "down".to_string(),
+                });
+            }
+            self.commits
+                .lock()
+                .unwrap()
+                .push((changes.to_vec(), position));
+            Ok(())
+        }
+
+        async fn committed_position(&self) -> Result<ProjectionPosition> {
+            Ok(self
+                .commits
+                .lock()
+                .unwrap()
+                .last()
+                .map(|(_, position)| *position)
+                .unwrap_or_default())
+        }
+
+        async fn clear(&self) -> Result<()> {
+            self.commits.lock().unwrap().clear();
+            Ok(())
+        }
+    }
+
+    fn upsert(key: &str, n: u64) -> RowChange {
+        RowChange::Upsert {
+            key: key.to_string(),
+            row: serde_json::json!({ "n": n }),
+        }
+    }
+
+    fn at(sequence: u64) -> ProjectionPosition {
+        ProjectionPosition {
+            events_processed: sequence,
+            last_sequence: sequence,
+            last_event_id: None,
+        }
+    }
+
+    fn config() -> GroupCommitConfig {
+        GroupCommitConfig::default()
+            .with_max_rows(3)
+            .with_max_delay(Duration::from_secs(3600))
+    }
+
+    #[tokio::test]
+    async fn test_flushes_when_group_is_full_keeping_latest_change_per_key() {
+        let sink = Arc::new(RecordingSink::default());
+        let group = GroupCommit::new(sink.clone(), config());
+
+        group
+            .stage([upsert("a", 1), upsert("b", 1)], at(1))
+            .await
+            .unwrap();
+        group.stage([upsert("a", 2)], at(2)).await.unwrap();
+        group.stage([], at(3)).await.unwrap();
+        assert!(sink.commits.lock().unwrap().is_empty());
+        assert_eq!(group.pending_rows().await, 2);
+
+        group
+            .stage(
+                [
+                    RowChange::Delete {
+                        key: "b".to_string(),
+                    },
+                    upsert("c", 1),
+                ],
+                at(4),
+            )
+            .await
+            .unwrap();
+
+        let commits = sink.commits.lock().unwrap();
+        assert_eq!(commits.len(), 1);
+        let (changes, position) = &commits[0];
+        assert_eq!(
+            changes,
+            &vec![
+                upsert("a", 2),
+                RowChange::Delete {
+                    key: "b".to_string()
+                },
+                upsert("c", 1),
+            ]
+        );
+        assert_eq!(position.last_sequence, 4);
+    }
+
+    #[tokio::test]
+    async fn test_failed_commit_keeps_group_for_retry() {
+        let sink = Arc::new(RecordingSink::default());
+        let group = GroupCommit::new(sink.clone(), config());
+
+        group.stage([upsert("a", 1)], at(1)).await.unwrap();
+        sink.fail.store(true, Ordering::SeqCst);
+        assert!(group.flush().await.is_err());
+        assert_eq!(group.pending_rows().await, 1);
+
+        sink.fail.store(false, Ordering::SeqCst);
+        assert_eq!(group.flush().await.unwrap(), 1);
+        assert_eq!(sink.committed_position().await.unwrap().last_sequence, 1);
+
+        // Nothing pending: nothing to commit
+        assert_eq!(group.flush().await.unwrap(), 0);
+        assert_eq!(sink.commits.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_flusher_commits_groups_that_waited_too_long() {
+        let sink = Arc::new(RecordingSink::default());
+        let group = Arc::new(GroupCommit::new(
+            sink.clone(),
+            config().with_max_delay(Duration::from_millis(10)),
+        ));
+        group.stage([upsert("a", 1)], at(1)).await.unwrap();
+
+        let shutdown = CancellationToken::new();
+        let flusher = tokio::spawn({
+            let group = group.clone();
+            let shutdown = shutdown.clone();
+            async move { group.run_flusher(shutdown).await }
+        });
+        for _ in 0..100 {
+            if group.pending_rows().await == 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        assert_eq!(sink.commits.lock().unwrap().len(), 1);
+
+        shutdown.cancel();
+        flusher.await.unwrap().unwrap();
+    }
+}
@@ -4,16 +4,51 @@
 //! - [`Projection`] trait for processing events into read models
 //! - [`ReadModel`] trait for query access to denormalized data
 //! - [`ProjectionProcessor`] for feeding events from the store to projections
-//! - Four read model views: current orders, order history, customer orders, inventory
+//! - [`ProjectionRegistry`] for building a deployment's views by name
+//! - [`JetStreamPublisher`] and [`JetStreamSubscriber`] for serving the query
+//!   side from other services over NATS JetStream
+//! - [`ShadowProjection`] for comparing a rewritten view against the one it
+//!   replaces before it serves traffic
+//! - [`GroupCommit`] for writing the rows of database-backed views in
+//!   batched transactions
+//! - [`RowPolicy`] for filtering and masking view rows per caller
+//! - [`EventFixture`] and [`check_golden`] for testing views against golden
+//!   snapshots of their state
+//! - Five read model views: current orders, order history, customer orders, inventory,
+//!   stock levels
 
+pub mod access;
 pub mod error;
+pub mod exchange;
+pub mod group_commit;
+pub mod nats;
 pub mod processor;
 pub mod projection;
 pub mod read_model;
+pub mod registry;
+pub mod retention;
+pub mod sampling;
+pub mod shadow;
+pub mod testing;
 pub mod views;
 
+pub use access::{RowPolicy, Unrestricted};
 pub use error::{ProjectionError, Result};
-pub use processor::ProjectionProcessor;
+pub use exchange::{ExchangeRateProvider, FixedExchangeRates};
+pub use group_commit::{GroupCommit, GroupCommitConfig, PostgresRowSink, RowChange, RowSink};
+pub use nats::{JetStreamPublisher, JetStreamSubscriber};
+pub use processor::{DEFAULT_GAP_WINDOW, ProjectionProcessor};
 pub use projection::{Projection, ProjectionPosition};
-pub use read_model::ReadModel;
-pub use views::{CurrentOrdersView, CustomerOrdersView, InventoryView, OrderHistoryView};
+pub use read_model::{READ_LOCK_TIMEOUT, ReadModel, read_state};
+pub use registry::{
+    BuiltView, ProjectionRegistry, ProjectionSet, ViewBackend, ViewHandles, ViewSpec,
+};
+pub use retention::{InMemoryOverflow, OverflowStore, RetentionPolicy};
+pub use sampling::{EventSampler, SamplingConfig};
+pub use shadow::{ComparableView, ShadowProbe, ShadowProjection, ShadowStats};
+pub use testing::{EventFixture, GoldenError, check_golden, probe_state};
+pub use views::{
+    CurrentOrdersView, CustomerOrdersView, HistoryPage, HistoryQuery, InMemoryOversellAlerter,
+    InventoryView, OrderHistoryView, Oversell, OversellAlert, OversellAlerter,
+    OversellDetectionView, StockLevel, StockLevelsView,
+};
@@ -0,0 +1,269 @@
+//! NATS JetStream transport for running the query side in other services.
+//!
+//! [`JetStreamPublisher`] is a projection: registered on the processor of
+//! the service that owns the event store, it publishes every event to
+//! `{prefix}.{aggregate_type}.{aggregate_id}`, e.g. `events.order.<id>`. The
+//! event ID is sent as the JetStream message ID, so the server drops an
+//! event republished within its duplicate window. Given a
+//! [`VisibilityPolicy`], it leaves out the streams its audience may not see.
+//!
+//! [`JetStreamSubscriber`] runs in another service and feeds those events to
+//! its local [`ProjectionProcessor`] through
+//! [`process_remote`](ProjectionProcessor::process_remote), acknowledging
+//! each one once its projections have it. Events keep the sequence the
+//! owning store gave them, so a redelivered event is skipped.
+
+use async_nats::jetstream::consumer::{AckPolicy, DeliverPolicy, pull};
+use async_nats::jetstream::context::Publish;
+use async_nats::jetstream::{self, AckKind};
+use async_trait::async_trait;
+use event_store::{Audience, EventEnvelope, EventStore, VisibilityPolicy};
+use futures_util::StreamExt;
+use tokio::sync::RwLock;
+use tokio_util::sync::CancellationToken;
+
+use crate::processor::ProjectionProcessor;
+use crate::projection::{Projection, ProjectionPosition};
+use crate::{ProjectionError, Result};
+
+/// Subject prefix used when none is configured.
+pub const DEFAULT_SUBJECT_PREFIX: &str = "events";
+
+/// Returns the subject an event is published on:
+/// `{prefix}.{aggregate_type}.{aggregate_id}`, with the aggregate type in
+/// snake case (`OrderFulfillmentSaga` becomes `order_fulfillment_saga`).
+pub fn subject_for(prefix: &str, event: &EventEnvelope) -> String {
+    let mut aggregate_type = String::with_capacity(event.aggregate_type.len() + 4);
+    for (i, c) in event.aggregate_type.chars().enumerate() {
+        if c.is_ascii_uppercase() {
+            if i > 0 {
+                aggregate_type.push('_');
+            }
+            aggregate_type.push(c.to_ascii_lowercase());
+        } else if c.is_ascii_alphanumeric() || c == '_' {
+            aggregate_type.push(c);
+        } else {
+            // Dots and wildcards would change the subject's meaning
+            aggregate_type.push('_');
+        }
+    }
+    format!("{prefix}.{aggregate_type}.{}", event.aggregate_id)
+}
+
+/// Connects to the NATS server at `url` and returns its JetStream context.
+pub async fn connect(url: &str) -> Result<jetstream::Context> {
+    let client = async_nats::connect(url).await.map_err(bus_error)?;
+    Ok(jetstream::new(client))
+}
+
+fn bus_error(e: impl std::fmt::Display) -> ProjectionError {
+    ProjectionError::EventBus(e.to_string())
+}
+
+/// Publishes each event it's delivered to JetStream.
+///
+/// Its position is kept in memory, so after a restart or
+/// [`reset`](Projection::reset) it republishes the log from the start;
+/// subscribers skip the events they already have.
+pub struct JetStreamPublisher {
+    context: jetstream::Context,
+    prefix: String,
+    visibility: Option<(VisibilityPolicy, Audience)>,
+    position: RwLock<ProjectionPosition>,
+}
+
+impl JetStreamPublisher {
+    /// Creates a publisher sending subjects under `prefix`.
+    pub fn new(context: jetstream::Context, prefix: impl Into<String>) -> Self {
+        Self {
+            context,
+            prefix: prefix.into(),
+            visibility: None,
+            position: RwLock::new(ProjectionPosition::zero()),
+        }
+    }
+
+    /// Publishes only the streams `audience` may see under `visibility`.
+    pub fn with_visibility(mut self, visibility: VisibilityPolicy, audience: Audience) -> Self {
+        self.visibility = Some((visibility, audience));
+        self
+    }
+
+    /// Creates the stream `name` capturing this publisher's subjects, unless
+    /// it already exists.
+    pub async fn ensure_stream(&self, name: &str) -> Result<()> {
+        self.context
+            .get_or_create_stream(jetstream::stream::Config {
+                name: name.to_string(),
+                subjects: vec![format!("{}.>", self.prefix)],
+                ..Default::default()
+            })
+            .await
+            .map_err(bus_error)?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Projection for JetStreamPublisher {
+    fn name(&self) -> &'static str {
+        "JetStreamPublisher"
+    }
+
+    async fn handle(&self, event: &EventEnvelope) -> Result<()> {
+        let visible = self
+            .visibility
+            .as_ref()
+            .is_none_or(|(policy, audience)| policy.is_visible(event, *audience));
+        if !visible {
+            metrics::counter!("jetstream_events_withheld").increment(1);
+            let mut position = self.position.write().await;
+            *position = position.advance_to(event);
+            return Ok(());
+        }
+
+        let subject = subject_for(&self.prefix, event);
+        let payload = serde_json::to_vec(event)?;
+        let ack = self
+            .context
+            .send_publish(
+                subject,
+                Publish::build()
+                    .payload(payload.into())
+                    .message_id(event.event_id.to_string()),
+            )
+            .await
+            .map_err(bus_error)?;
+        // Only move on once the stream has stored it
+        ack.await.map_err(bus_error)?;
+
+        metrics::counter!("jetstream_events_published").increment(1);
+        let mut position = self.position.write().await;
+        *position = position.advance_to(event);
+        Ok(())
+    }
+
+    async fn position(&self) -> ProjectionPosition {
+        *self.position.read().await
+    }
+
+    async fn reset(&self) -> Result<()> {
+        *self.position.write().await = ProjectionPosition::zero();
+        Ok(())
+    }
+}
+
+/// Reads published events from a JetStream stream into a local processor.
+///
+/// Each run creates an ephemeral consumer that starts from the beginning
+/// of the stream, so in-memory views are rebuilt on every start; events
+/// the projections already passed are skipped.
+pub struct JetStreamSubscriber {
+    context: jetstream::Context,
+    stream: String,
+    prefix: String,
+}
+
+impl JetStreamSubscriber {
+    /// Creates a subscriber reading subjects under `prefix` from `stream`.
+    pub fn new(
+        context: jetstream::Context,
+        stream: impl Into<String>,
+        prefix: impl Into<String>,
+    ) -> Self {
+        Self {
+            context,
+            stream: stream.into(),
+            prefix: prefix.into(),
+        }
+    }
+
+    /// Delivers events to `processor` until `shutdown` is cancelled.
+    ///
+    /// Messages that aren't events are logged and terminated so they aren't
+    /// redelivered. A delivery error stops the run with the message
+    /// unacknowledged, so it's redelivered when the run is restarted.
+    #[tracing::instrument(skip_all, fields(stream = %self.stream))]
+    pub async fn run<S: EventStore>(
+        &self,
+        processor: &ProjectionProcessor<S>,
+        shutdown: CancellationToken,
+    ) -> Result<()> {
+        let consumer = self
+            .context
+            .get_stream(&self.stream)
+            .await
+            .map_err(bus_error)?
+            .create_consumer(pull::Config {
+                filter_subject: format!("{}.>", self.prefix),
+                deliver_policy: DeliverPolicy::All,
+                ack_policy: AckPolicy::Explicit,
+                ..Default::default()
+            })
+            .await
+            .map_err(bus_error)?;
+        let mut messages = consumer.messages().await.map_err(bus_error)?;
+        tracing::info!("following jetstream");
+
+        loop {
+            let message = tokio::select! {
+                _ = shutdown.cancelled() => return Ok(()),
+                next = messages.next() => match next {
+                    Some(Ok(message)) => message,
+                    Some(Err(e)) => {
+                        tracing::warn!(error = %e, "jetstream read failed");
+                        continue;
+                    }
+                    None => return Ok(()),
+                },
+            };
+
+            let event: EventEnvelope = match serde_json::from_slice(&message.payload) {
+                Ok(event) => event,
+                Err(e) => {
+                    tracing::warn!(subject = %message.subject, error = %e, "skipping malformed event");
+                    message.ack_with(AckKind::Term).await.map_err(bus_error)?;
+                    continue;
+                }
+            };
+            processor.process_remote(&event).await?;
+            message.ack().await.map_err(bus_error)?;
+            metrics::counter!("jetstream_events_received").increment(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::AggregateId;
+    use event_store::Version;
+
+    fn event(aggregate_type: &str) -> EventEnvelope {
+        EventEnvelope::builder()
+            .aggregate_id(AggregateId::new())
+            .aggregate_type(aggregate_type)
+            .event_type("TestEvent")
+            .version(Version::new(1))
+            .payload_raw(serde_json::json!({}))
+            .build()
+    }
+
+    #[test]
+    fn test_subjects_use_snake_case_aggregate_types() {
+        let order = event("Order");
+        assert_eq!(
+            subject_for("events", &order),
+            format!("events.order.{}", order.aggregate_id)
+        );
+
+        let saga = event("OrderFulfillmentSaga");
+        assert_eq!(
+            subject_for("events", &saga),
+            format!("events.order_fulfillment_saga.{}", saga.aggregate_id)
+        );
+
+        let odd = event("Order.*");
+        assert!(subject_for("events", &odd).starts_with("events.order__."));
+    }
+}
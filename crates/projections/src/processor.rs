@@ -1,10 +1,28 @@
 //! Projection processor for feeding events to projections.
 
-use event_store::{EventEnvelope, EventStore};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use common::Tunable;
+use event_store::subscription::SUBSCRIPTION_BATCH_SIZE;
+use event_store::{
+    ArchiveSink, EventEnvelope, EventStore, EventStream, ReadSource, SchemaDeprecations,
+    SequenceGaps, merge_by_sequence,
+};
 use futures_util::StreamExt;
+use futures_util::stream::Fuse;
+use tokio::sync::{RwLock, watch};
+use tokio_util::sync::CancellationToken;
+
+use crate::projection::{Projection, ProjectionPosition};
+use crate::sampling::EventSampler;
+use crate::{ProjectionError, Result};
 
-use crate::Result;
-use crate::projection::Projection;
+/// A projection registered under a name.
+struct Registration {
+    name: String,
+    projection: Arc<dyn Projection>,
+}
 
 /// Processes events from an event store and delivers them to projections.
 ///
@@ -12,81 +30,556 @@ use crate::projection::Projection;
 /// - Catch-up: replays all events from the store to bring projections up to date
 /// - Single event delivery: delivers a new event to all projections
 /// - Rebuild: resets all projections and replays from scratch
+/// - Sampling: logs a fraction of events with before/after view summaries
+/// - Runtime registration: projections can be added or removed while running
+/// - Compaction: with an archive attached, replays read archived events too
+/// - Continuous mode: catches up as the store announces appends, polling as
+///   a fallback
+/// - Subscribed mode: catches up once, then follows the store's live
+///   subscription
+/// - Remote delivery: applies events published from another service's log
+/// - Schema telemetry: counts deliveries of deprecated event schema versions
+/// - Gap tolerance: holds back events that follow a gap in the log's
+///   sequences until the gap fills or is old enough to be a rollback
 pub struct ProjectionProcessor<S: EventStore> {
     store: S,
-    projections: Vec<Box<dyn Projection>>,
+    projections: RwLock<Vec<Registration>>,
+    sampler: EventSampler,
+    archive: Option<Arc<dyn ArchiveSink>>,
+    schema_deprecations: Option<SchemaDeprecations>,
+    /// Gaps seen by catch-ups, kept so each is aged from its first sighting.
+    gaps: Mutex<SequenceGaps>,
 }
 
+/// How long events after a sequence gap are held back by default.
+pub const DEFAULT_GAP_WINDOW: Duration = Duration::from_secs(5);
+
 impl<S: EventStore> ProjectionProcessor<S> {
     /// Creates a new processor with the given event store.
     pub fn new(store: S) -> Self {
         Self {
             store,
-            projections: Vec::new(),
+            projections: RwLock::new(Vec::new()),
+            sampler: EventSampler::new(),
+            archive: None,
+            schema_deprecations: None,
+            gaps: Mutex::new(SequenceGaps::new(DEFAULT_GAP_WINDOW)),
         }
     }
 
-    /// Registers a projection with this processor.
+    /// Sets how long events after a gap in the log's sequences are held
+    /// back; [`DEFAULT_GAP_WINDOW`] by default.
+    ///
+    /// Sequences are assigned when a transaction inserts its events, not
+    /// when it commits, so a later append can become visible before an
+    /// earlier one. Delivering past the gap would move projections beyond
+    /// the earlier append for good. A gap this processor first saw longer
+    /// than the window ago is taken to be a rolled-back append and
+    /// delivered past.
+    pub fn with_gap_window(mut self, window: Duration) -> Self {
+        self.gaps = Mutex::new(SequenceGaps::new(window));
+        self
+    }
+
+    /// Attaches the archive that compaction moves old events into.
+    ///
+    /// Rebuilds and catch-ups that start before the end of the archive read
+    /// it back merged with the live log, so views stay complete after
+    /// compaction.
+    pub fn with_archive(mut self, archive: Arc<dyn ArchiveSink>) -> Self {
+        self.archive = Some(archive);
+        self
+    }
+
+    /// Counts events at deprecated schema versions in `deprecations`, once
+    /// per projection they're delivered to.
+    pub fn with_schema_deprecations(mut self, deprecations: SchemaDeprecations) -> Self {
+        self.schema_deprecations = Some(deprecations);
+        self
+    }
+
+    /// Returns the event sampler, which can be reconfigured at runtime.
+    pub fn sampler(&self) -> &EventSampler {
+        &self.sampler
+    }
+
+    /// Registers a projection with this processor under its own name.
+    ///
+    /// Used during startup, before the processor is shared.
     pub fn register(&mut self, projection: Box<dyn Projection>) {
-        self.projections.push(projection);
+        let name = projection.name().to_string();
+        self.projections.get_mut().push(Registration {
+            name,
+            projection: Arc::from(projection),
+        });
+    }
+
+    /// Registers a projection at runtime.
+    ///
+    /// The new projection is caught up on its own before it starts receiving
+    /// events alongside the others. The final part of the catch-up runs while
+    /// holding the registration lock, so no event delivered in between is
+    /// missed.
+    #[tracing::instrument(skip(self, projection))]
+    pub async fn register_dynamic(
+        &self,
+        name: impl Into<String> + std::fmt::Debug,
+        projection: Box<dyn Projection>,
+    ) -> Result<()> {
+        let name = name.into();
+        if self.is_registered(&name).await {
+            return Err(ProjectionError::AlreadyRegistered(name));
+        }
+
+        let projection: Arc<dyn Projection> = Arc::from(projection);
+        self.catch_up_one(projection.as_ref()).await?;
+
+        let mut projections = self.projections.write().await;
+        if projections.iter().any(|r| r.name == name) {
+            return Err(ProjectionError::AlreadyRegistered(name));
+        }
+        self.catch_up_one(projection.as_ref()).await?;
+        projections.push(Registration { name, projection });
+
+        tracing::info!("projection registered");
+        Ok(())
+    }
+
+    /// Removes a projection at runtime.
+    ///
+    /// Waits for any in-flight delivery to finish, then returns the removed
+    /// projection.
+    #[tracing::instrument(skip(self))]
+    pub async fn deregister(&self, name: &str) -> Result<Arc<dyn Projection>> {
+        let mut projections = self.projections.write().await;
+        let index = projections
+            .iter()
+            .position(|r| r.name == name)
+            .ok_or_else(|| ProjectionError::NotRegistered(name.to_string()))?;
+        let registration = projections.remove(index);
+
+        tracing::info!("projection deregistered");
+        Ok(registration.projection)
+    }
+
+    /// Returns true if a projection is registered under the given name.
+    pub async fn is_registered(&self, name: &str) -> bool {
+        self.projections.read().await.iter().any(|r| r.name == name)
+    }
+
+    /// Returns the registered projection names and their positions.
+    pub async fn registrations(&self) -> Vec<(String, ProjectionPosition)> {
+        let projections = self.projections.read().await;
+        let mut result = Vec::with_capacity(projections.len());
+        for r in projections.iter() {
+            result.push((r.name.clone(), r.projection.position().await));
+        }
+        result
+    }
+
+    /// Returns the global sequence at the head of the store's log.
+    pub async fn head_sequence(&self) -> Result<u64> {
+        Ok(self.store.head_sequence().await?)
     }
 
     /// Returns the number of registered projections.
-    pub fn projection_count(&self) -> usize {
-        self.projections.len()
+    pub async fn projection_count(&self) -> usize {
+        self.projections.read().await.len()
     }
 
     /// Runs catch-up processing: streams all events from the store and delivers
     /// them to each projection that hasn't already seen them.
+    ///
+    /// Events from one append are delivered together; see [`EventGroups`].
     #[tracing::instrument(skip(self))]
     pub async fn run_catch_up(&self) -> Result<()> {
-        let mut stream = self.store.stream_all_events().await?;
-        let mut event_index: u64 = 0;
-
-        while let Some(result) = stream.next().await {
-            let event = result?;
-            event_index += 1;
+        let mut oldest = u64::MAX;
+        for registration in self.projections.read().await.iter() {
+            oldest = oldest.min(registration.projection.position().await.last_sequence);
+        }
 
-            for projection in &self.projections {
-                let pos = projection.position().await;
-                if pos.events_processed < event_index {
-                    projection.handle(&event).await?;
-                    metrics::counter!("projections_events_processed").increment(1);
-                }
+        // Every projection has read past the gaps up to the oldest position
+        self.gaps.lock().unwrap().forget_through(oldest);
+        let mut groups = EventGroups::new(self.history_from(oldest).await?);
+        let mut gaps = GapGuard::new(&self.gaps, oldest);
+        while let Some(group) = groups.next().await? {
+            if !gaps.admits(&group) {
+                break;
+            }
+            let projections = self.projections.read().await;
+            for registration in projections.iter() {
+                self.deliver_unseen(registration.projection.as_ref(), &group)
+                    .await?;
             }
         }
 
-        tracing::info!(events_processed = event_index, "catch-up complete");
+        tracing::info!(events_streamed = groups.streamed, "catch-up complete");
 
         Ok(())
     }
 
+    /// Keeps projections caught up until `shutdown` is cancelled.
+    ///
+    /// Catches up whenever the store announces an append, and at least once
+    /// every `poll_interval` so lost or lagging notifications only delay the
+    /// read models. Stores without notifications are polled. Catch-up
+    /// failures are logged and retried on the next wake-up.
+    ///
+    /// A shared [`Tunable`] interval can be changed while this runs; it
+    /// takes effect from the next wait.
+    #[tracing::instrument(skip_all)]
+    pub async fn run_continuous(
+        &self,
+        poll_interval: impl Into<Tunable<Duration>>,
+        shutdown: CancellationToken,
+    ) {
+        let poll_interval = poll_interval.into();
+        let mut notifications = self.store.append_notifications();
+        if notifications.is_none() {
+            tracing::info!("store has no append notifications, polling");
+        }
+
+        loop {
+            if let Err(e) = self.run_catch_up().await {
+                tracing::warn!(error = %e, "continuous catch-up failed");
+            }
+
+            let notified = tokio::select! {
+                _ = shutdown.cancelled() => return,
+                _ = tokio::time::sleep(poll_interval.value()) => false,
+                changed = next_notification(&mut notifications) => changed,
+            };
+            if notified {
+                metrics::counter!("projection_wakeups", "trigger" => "notification").increment(1);
+            } else {
+                metrics::counter!("projection_wakeups", "trigger" => "poll").increment(1);
+            }
+        }
+    }
+
+    /// Catches up, then keeps projections caught up from the store's live
+    /// subscription until `shutdown` is cancelled.
+    ///
+    /// Unlike [`run_continuous`](Self::run_continuous), which re-reads the
+    /// log on every wake-up, each new event is read once. Events the
+    /// subscription yields together are delivered in append groups, as in
+    /// catch-up. Subscription read errors are logged and reading resumes;
+    /// a delivery error stops the run, and running again resumes from where
+    /// the projections left off.
+    #[tracing::instrument(skip_all)]
+    pub async fn run_subscribed(&self, shutdown: CancellationToken) -> Result<()> {
+        self.run_catch_up().await?;
+
+        let mut oldest = None;
+        for registration in self.projections.read().await.iter() {
+            let position = registration.projection.position().await.last_sequence;
+            oldest = Some(oldest.map_or(position, |oldest: u64| oldest.min(position)));
+        }
+        let from = match oldest {
+            Some(position) => position,
+            None => self.store.head_sequence().await?,
+        };
+        let mut subscription = self
+            .store
+            .subscribe(from)
+            .await?
+            .ready_chunks(SUBSCRIPTION_BATCH_SIZE);
+        tracing::info!(from, "following subscription");
+
+        loop {
+            let chunk = tokio::select! {
+                _ = shutdown.cancelled() => return Ok(()),
+                chunk = subscription.next() => match chunk {
+                    Some(chunk) => chunk,
+                    None => return Ok(()),
+                },
+            };
+            let mut events = Vec::with_capacity(chunk.len());
+            for result in chunk {
+                match result {
+                    Ok(event) => events.push(event),
+                    Err(e) => tracing::warn!(error = %e, "subscription read failed"),
+                }
+            }
+
+            let mut groups = EventGroups::new(Box::pin(futures_util::stream::iter(
+                events.into_iter().map(Ok),
+            )));
+            while let Some(group) = groups.next().await? {
+                let projections = self.projections.read().await;
+                for registration in projections.iter() {
+                    self.deliver_unseen(registration.projection.as_ref(), &group)
+                        .await?;
+                }
+            }
+        }
+    }
+
     /// Delivers a single event to all registered projections.
     #[tracing::instrument(skip(self, event), fields(event_type = %event.event_type))]
     pub async fn process_event(&self, event: &EventEnvelope) -> Result<()> {
-        for projection in &self.projections {
-            projection.handle(event).await?;
+        self.process_group(std::slice::from_ref(event)).await
+    }
+
+    /// Delivers the events from one append to all registered projections.
+    ///
+    /// Each projection receives the events as a unit through
+    /// [`Projection::handle_group`], so views that support rollback apply
+    /// either all of them or none.
+    #[tracing::instrument(skip(self, events), fields(group_size = events.len()))]
+    pub async fn process_group(&self, events: &[EventEnvelope]) -> Result<()> {
+        let projections = self.projections.read().await;
+        for registration in projections.iter() {
+            self.deliver(registration.projection.as_ref(), events)
+                .await?;
         }
         Ok(())
     }
 
-    /// Resets all projections and replays all events from the store.
+    /// Delivers an event read from another service's log, such as one
+    /// received through [`JetStreamSubscriber`](crate::JetStreamSubscriber),
+    /// to the projections that haven't seen it yet.
+    ///
+    /// Positions follow the remote log's sequences, so a redelivered event
+    /// is skipped instead of applied twice. Fails if the event has no
+    /// sequence.
+    #[tracing::instrument(skip(self, event), fields(event_type = %event.event_type))]
+    pub async fn process_remote(&self, event: &EventEnvelope) -> Result<()> {
+        let sequence = event.sequence.ok_or_else(|| {
+            ProjectionError::Projection(format!("remote event {} has no sequence", event.event_id))
+        })?;
+        let group = EventGroup {
+            events: vec![event.clone()],
+            sequences: vec![sequence],
+        };
+        let projections = self.projections.read().await;
+        for registration in projections.iter() {
+            self.deliver_unseen(registration.projection.as_ref(), &group)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Resets all projections and replays all events from the store,
+    /// including any that compaction moved into the archive.
     #[tracing::instrument(skip(self))]
     pub async fn rebuild_all(&self) -> Result<()> {
-        for projection in &self.projections {
-            projection.reset().await?;
+        for registration in self.projections.read().await.iter() {
+            registration.projection.reset().await?;
         }
         self.run_catch_up().await
     }
+
+    /// Brings a single projection up to date with the store.
+    async fn catch_up_one(&self, projection: &dyn Projection) -> Result<()> {
+        let position = projection.position().await.last_sequence;
+        let mut groups = EventGroups::new(self.history_from(position).await?);
+        let mut gaps = GapGuard::new(&self.gaps, position);
+        while let Some(group) = groups.next().await? {
+            if !gaps.admits(&group) {
+                break;
+            }
+            self.deliver_unseen(projection, &group).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Delivers the part of a group the projection hasn't seen yet.
+    async fn deliver_unseen(&self, projection: &dyn Projection, group: &EventGroup) -> Result<()> {
+        let position = projection.position().await.last_sequence;
+        let start = group
+            .sequences
+            .partition_point(|&sequence| sequence <= position);
+        let unseen = &group.events[start..];
+        if unseen.is_empty() {
+            return Ok(());
+        }
+
+        self.deliver(projection, unseen).await?;
+        metrics::counter!("projections_events_processed").increment(unseen.len() as u64);
+        Ok(())
+    }
+
+    /// Returns the events after the given sequence.
+    ///
+    /// Only the live log after `position` is read. Archived events are
+    /// merged in only if the reader hasn't already passed the end of the
+    /// archive, as when rebuilding.
+    async fn history_from(&self, position: u64) -> Result<EventStream> {
+        let live = self.store.stream_events_after(position).await?;
+        let Some(archive) = &self.archive else {
+            return Ok(live);
+        };
+
+        if position >= archive.archived_through().await? {
+            return Ok(live);
+        }
+        tracing::debug!(position, "replaying archived events");
+        Ok(merge_by_sequence(archive.stream_archived().await?, live))
+    }
+
+    /// Delivers a group of events to one projection, logging it if any of
+    /// them is sampled.
+    async fn deliver(&self, projection: &dyn Projection, events: &[EventEnvelope]) -> Result<()> {
+        if let Some(deprecations) = &self.schema_deprecations {
+            for event in events {
+                deprecations.record_read(event, ReadSource::Projection);
+            }
+        }
+
+        let Some(event) = events.iter().find(|e| self.sampler.should_sample(e)) else {
+            return projection.handle_group(events).await;
+        };
+
+        let before = projection.summary().await;
+        let result = projection.handle_group(events).await;
+        let after = projection.summary().await;
+
+        metrics::counter!("projection_events_sampled").increment(1);
+        tracing::info!(
+            target: "projection_sampling",
+            projection = projection.name(),
+            event_id = %event.event_id,
+            envelope = %serde_json::to_string(event).unwrap_or_default(),
+            group_size = events.len(),
+            before = %before,
+            after = %after,
+            success = result.is_ok(),
+            "sampled event"
+        );
+
+        result
+    }
+}
+
+/// Consecutive events from the log that were appended together.
+struct EventGroup {
+    events: Vec<EventEnvelope>,
+    /// Global sequence of each event, ascending.
+    sequences: Vec<u64>,
+}
+
+/// Splits the event log into append groups.
+///
+/// An append always targets a single aggregate and its events are
+/// consecutive in the log, but the log doesn't record where one append ends
+/// and the next begins. Consecutive events for the same aggregate are
+/// therefore grouped together, which may join back-to-back appends to one
+/// aggregate but never splits an append.
+struct EventGroups {
+    stream: Fuse<EventStream>,
+    pending: Option<(u64, EventEnvelope)>,
+    /// Number of events read from the log so far.
+    streamed: u64,
+}
+
+impl EventGroups {
+    fn new(stream: EventStream) -> Self {
+        Self {
+            stream: stream.fuse(),
+            pending: None,
+            streamed: 0,
+        }
+    }
+
+    /// Returns the next group, or None at the end of the log.
+    async fn next(&mut self) -> Result<Option<EventGroup>> {
+        let mut group = EventGroup {
+            events: Vec::new(),
+            sequences: Vec::new(),
+        };
+        if let Some((sequence, event)) = self.pending.take() {
+            group.sequences.push(sequence);
+            group.events.push(event);
+        }
+
+        while let Some(result) = self.stream.next().await {
+            let event = result?;
+            self.streamed += 1;
+            // Stores that don't assign sequences are read in log order
+            let sequence = event.sequence.unwrap_or(self.streamed);
+
+            if group
+                .events
+                .last()
+                .is_some_and(|last| last.aggregate_id != event.aggregate_id)
+            {
+                self.pending = Some((sequence, event));
+                return Ok(Some(group));
+            }
+            group.sequences.push(sequence);
+            group.events.push(event);
+        }
+
+        Ok((!group.events.is_empty()).then_some(group))
+    }
+}
+
+/// Finds where a read of the log should stop because of a sequence gap
+/// that may still fill, e.g. an append that hasn't committed yet.
+struct GapGuard<'a> {
+    gaps: &'a Mutex<SequenceGaps>,
+    /// Sequence every reader has passed; gaps at or below it don't matter.
+    floor: u64,
+    previous: Option<u64>,
+}
+
+impl<'a> GapGuard<'a> {
+    fn new(gaps: &'a Mutex<SequenceGaps>, floor: u64) -> Self {
+        Self {
+            gaps,
+            floor,
+            previous: None,
+        }
+    }
+
+    /// Returns false if `group` follows a gap above the floor that was
+    /// first seen less than the window ago; the read should stop there and
+    /// resume on a later catch-up.
+    fn admits(&mut self, group: &EventGroup) -> bool {
+        for &sequence in &group.sequences {
+            if let Some(previous) = self.previous
+                && sequence > previous + 1
+                && sequence - 1 > self.floor
+                && self.gaps.lock().unwrap().holds(previous, sequence)
+            {
+                metrics::counter!("projection_gap_holds").increment(1);
+                tracing::debug!(
+                    missing_from = previous + 1,
+                    missing_to = sequence - 1,
+                    "holding back events after a sequence gap"
+                );
+                return false;
+            }
+            self.previous = Some(sequence);
+        }
+        true
+    }
+}
+
+/// Waits for the next append notification.
+///
+/// Returns true when one arrived. If the store stops sending, notifications
+/// are dropped and this returns false, leaving the caller to poll.
+async fn next_notification(notifications: &mut Option<watch::Receiver<u64>>) -> bool {
+    let Some(receiver) = notifications else {
+        return std::future::pending().await;
+    };
+    if receiver.changed().await.is_ok() {
+        return true;
+    }
+    tracing::warn!("append notifications closed, polling");
+    *notifications = None;
+    false
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::projection::ProjectionPosition;
     use async_trait::async_trait;
     use common::AggregateId;
-    use event_store::{InMemoryEventStore, Version};
+    use event_store::{FaultyEventStore, InMemoryEventStore, StoreOperation, Version};
     use std::sync::Arc;
     use tokio::sync::RwLock;
 
@@ -111,11 +604,11 @@ mod tests {
             "CountingProjection"
         }
 
-        async fn handle(&self, _event: &EventEnvelope) -> Result<()> {
+        async fn handle(&self, event: &EventEnvelope) -> Result<()> {
             let mut count = self.count.write().await;
             *count += 1;
             let mut pos = self.position.write().await;
-            *pos = pos.advance();
+            *pos = pos.advance_to(event);
             Ok(())
         }
 
@@ -162,7 +655,6 @@ mod tests {
 
         // Use a shared projection to verify
         let shared = Arc::clone(&counting);
-        processor.projections.clear();
 
         // We need to register the Arc-wrapped projection differently
         // Instead, let's test via the processor directly
@@ -180,7 +672,7 @@ mod tests {
         let projection = CountingProjection::new();
         let count_ref = Arc::clone(&projection.count);
         let mut processor = ProjectionProcessor::new(store2);
-        processor.projections.push(Box::new(projection));
+        processor.register(Box::new(projection));
 
         processor.run_catch_up().await.unwrap();
 
@@ -189,6 +681,31 @@ mod tests {
         drop(counting);
     }
 
+    #[tokio::test]
+    async fn test_catch_up_recovers_after_store_fault() {
+        let store = FaultyEventStore::new(InMemoryEventStore::new());
+        let agg_id = AggregateId::new();
+        let events = (1..=3)
+            .map(|v| create_test_event(agg_id, Version::new(v)))
+            .collect();
+        store
+            .append(events, event_store::AppendOptions::new())
+            .await
+            .unwrap();
+
+        let projection = CountingProjection::new();
+        let count_ref = Arc::clone(&projection.count);
+        let mut processor = ProjectionProcessor::new(store.clone());
+        processor.register(Box::new(projection));
+
+        store.fail_next(StoreOperation::Query, 1);
+        assert!(processor.run_catch_up().await.is_err());
+        assert_eq!(*count_ref.read().await, 0);
+
+        processor.run_catch_up().await.unwrap();
+        assert_eq!(*count_ref.read().await, 3);
+    }
+
     #[tokio::test]
     async fn test_process_single_event() {
         let store = InMemoryEventStore::new();
@@ -196,7 +713,7 @@ mod tests {
         let count_ref = Arc::clone(&projection.count);
 
         let mut processor = ProjectionProcessor::new(store);
-        processor.projections.push(Box::new(projection));
+        processor.register(Box::new(projection));
 
         let event = create_test_event(AggregateId::new(), Version::new(1));
         processor.process_event(&event).await.unwrap();
@@ -223,7 +740,7 @@ mod tests {
         let pos_ref = Arc::clone(&projection.position);
 
         let mut processor = ProjectionProcessor::new(store);
-        processor.projections.push(Box::new(projection));
+        processor.register(Box::new(projection));
 
         // First catch-up
         processor.run_catch_up().await.unwrap();
@@ -254,7 +771,7 @@ mod tests {
         let count_ref = Arc::clone(&projection.count);
 
         let mut processor = ProjectionProcessor::new(store);
-        processor.projections.push(Box::new(projection));
+        processor.register(Box::new(projection));
 
         // First catch-up
         processor.run_catch_up().await.unwrap();
@@ -265,6 +782,136 @@ mod tests {
         assert_eq!(*count_ref.read().await, 3);
     }
 
+    #[tokio::test]
+    async fn test_catch_up_tracks_global_sequence() {
+        let store = InMemoryEventStore::new();
+        let agg_id = AggregateId::new();
+
+        let events = vec![
+            create_test_event(agg_id, Version::new(1)),
+            create_test_event(agg_id, Version::new(2)),
+        ];
+        store
+            .append(events, event_store::AppendOptions::new())
+            .await
+            .unwrap();
+
+        let projection = CountingProjection::new();
+        let pos_ref = Arc::clone(&projection.position);
+        let mut processor = ProjectionProcessor::new(store.clone());
+        processor.register(Box::new(projection));
+
+        processor.run_catch_up().await.unwrap();
+        let head = processor.head_sequence().await.unwrap();
+        let pos = *pos_ref.read().await;
+        assert_eq!(head, 2);
+        assert_eq!(pos.last_sequence, 2);
+        assert!(pos.last_event_id.is_some());
+        assert!(!pos.is_behind(head));
+
+        store
+            .append(
+                vec![create_test_event(agg_id, Version::new(3))],
+                event_store::AppendOptions::new(),
+            )
+            .await
+            .unwrap();
+        let head = processor.head_sequence().await.unwrap();
+        assert!(pos_ref.read().await.is_behind(head));
+        assert_eq!(pos_ref.read().await.lag(head), 1);
+    }
+
+    #[tokio::test]
+    async fn test_catch_up_resumes_from_sequence_not_counter() {
+        let store = InMemoryEventStore::new();
+        let agg_id = AggregateId::new();
+
+        let events = vec![
+            create_test_event(agg_id, Version::new(1)),
+            create_test_event(agg_id, Version::new(2)),
+            create_test_event(agg_id, Version::new(3)),
+        ];
+        store
+            .append(events, event_store::AppendOptions::new())
+            .await
+            .unwrap();
+
+        // The counter says one event, but the projection is really at sequence 2
+        let projection = CountingProjection::new();
+        *projection.position.write().await = ProjectionPosition {
+            events_processed: 1,
+            last_sequence: 2,
+            last_event_id: None,
+        };
+        let count_ref = Arc::clone(&projection.count);
+        let mut processor = ProjectionProcessor::new(store);
+        processor.register(Box::new(projection));
+
+        processor.run_catch_up().await.unwrap();
+        assert_eq!(*count_ref.read().await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_rebuild_after_compaction_reads_archive() {
+        use event_store::{InMemoryArchive, Snapshot};
+
+        let store = InMemoryEventStore::new();
+        let compacted = AggregateId::new();
+        let other = AggregateId::new();
+
+        store
+            .append(
+                vec![
+                    create_test_event(compacted, Version::new(1)),
+                    create_test_event(compacted, Version::new(2)),
+                ],
+                event_store::AppendOptions::new(),
+            )
+            .await
+            .unwrap();
+        store
+            .append(
+                vec![create_test_event(other, Version::new(1))],
+                event_store::AppendOptions::new(),
+            )
+            .await
+            .unwrap();
+        store
+            .append(
+                vec![create_test_event(compacted, Version::new(3))],
+                event_store::AppendOptions::new(),
+            )
+            .await
+            .unwrap();
+        store
+            .save_snapshot(Snapshot::new(
+                compacted,
+                "Order",
+                Version::new(3),
+                serde_json::json!({}),
+            ))
+            .await
+            .unwrap();
+
+        let archive = InMemoryArchive::new();
+        assert_eq!(store.compact(&archive).await.unwrap(), 2);
+
+        let projection = CountingProjection::new();
+        let count_ref = Arc::clone(&projection.count);
+        let pos_ref = Arc::clone(&projection.position);
+        let mut processor = ProjectionProcessor::new(store).with_archive(Arc::new(archive.clone()));
+        processor.register(Box::new(projection));
+
+        processor.rebuild_all().await.unwrap();
+        assert_eq!(*count_ref.read().await, 4);
+        assert_eq!(pos_ref.read().await.last_sequence, 4);
+        assert_eq!(pos_ref.read().await.counter_drift(), 0);
+
+        // Up-to-date projections don't re-read the archive
+        processor.run_catch_up().await.unwrap();
+        assert_eq!(*count_ref.read().await, 4);
+    }
+
     #[tokio::test]
     async fn test_empty_store_catch_up() {
         let store = InMemoryEventStore::new();
@@ -272,12 +919,109 @@ mod tests {
         let count_ref = Arc::clone(&projection.count);
 
         let mut processor = ProjectionProcessor::new(store);
-        processor.projections.push(Box::new(projection));
+        processor.register(Box::new(projection));
 
         processor.run_catch_up().await.unwrap();
         assert_eq!(*count_ref.read().await, 0);
     }
 
+    #[tokio::test]
+    async fn test_sampling_does_not_change_delivery() {
+        let store = InMemoryEventStore::new();
+        let projection = CountingProjection::new();
+        let count_ref = Arc::clone(&projection.count);
+
+        let mut processor = ProjectionProcessor::new(store);
+        processor.register(Box::new(projection));
+        processor.sampler().configure(true, 1.0);
+
+        let event = create_test_event(AggregateId::new(), Version::new(1));
+        processor.process_event(&event).await.unwrap();
+
+        assert!(processor.sampler().should_sample(&event));
+        assert_eq!(*count_ref.read().await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_register_dynamic_catches_up_first() {
+        let store = InMemoryEventStore::new();
+        let agg_id = AggregateId::new();
+
+        let events = vec![
+            create_test_event(agg_id, Version::new(1)),
+            create_test_event(agg_id, Version::new(2)),
+        ];
+        store
+            .append(events, event_store::AppendOptions::new())
+            .await
+            .unwrap();
+
+        let existing = CountingProjection::new();
+        let existing_count = Arc::clone(&existing.count);
+        let mut processor = ProjectionProcessor::new(store.clone());
+        processor.register(Box::new(existing));
+        processor.run_catch_up().await.unwrap();
+
+        // Added mid-stream: catches up on its own without replaying to others
+        let added = CountingProjection::new();
+        let added_count = Arc::clone(&added.count);
+        processor
+            .register_dynamic("late", Box::new(added))
+            .await
+            .unwrap();
+
+        assert_eq!(*added_count.read().await, 2);
+        assert_eq!(*existing_count.read().await, 2);
+        assert_eq!(processor.projection_count().await, 2);
+
+        // Both receive new events from then on
+        let event = create_test_event(agg_id, Version::new(3));
+        store
+            .append(vec![event], event_store::AppendOptions::new())
+            .await
+            .unwrap();
+        processor.run_catch_up().await.unwrap();
+
+        assert_eq!(*added_count.read().await, 3);
+        assert_eq!(*existing_count.read().await, 3);
+    }
+
+    #[tokio::test]
+    async fn test_register_dynamic_rejects_duplicate_name() {
+        let processor = ProjectionProcessor::new(InMemoryEventStore::new());
+        processor
+            .register_dynamic("counting", Box::new(CountingProjection::new()))
+            .await
+            .unwrap();
+
+        let result = processor
+            .register_dynamic("counting", Box::new(CountingProjection::new()))
+            .await;
+        assert!(matches!(result, Err(ProjectionError::AlreadyRegistered(_))));
+    }
+
+    #[tokio::test]
+    async fn test_deregister_stops_delivery() {
+        let store = InMemoryEventStore::new();
+        let projection = CountingProjection::new();
+        let count_ref = Arc::clone(&projection.count);
+
+        let mut processor = ProjectionProcessor::new(store);
+        processor.register(Box::new(projection));
+        assert!(processor.is_registered("CountingProjection").await);
+
+        let removed = processor.deregister("CountingProjection").await.unwrap();
+        assert_eq!(removed.name(), "CountingProjection");
+        assert_eq!(processor.projection_count().await, 0);
+
+        let event = create_test_event(AggregateId::new(), Version::new(1));
+        processor.process_event(&event).await.unwrap();
+        assert_eq!(*count_ref.read().await, 0);
+
+        let result = processor.deregister("CountingProjection").await;
+        assert!(matches!(result, Err(ProjectionError::NotRegistered(_))));
+    }
+
     #[tokio::test]
     async fn test_multiple_projections() {
         let store = InMemoryEventStore::new();
@@ -298,12 +1042,238 @@ mod tests {
         let count2 = Arc::clone(&proj2.count);
 
         let mut processor = ProjectionProcessor::new(store);
-        processor.projections.push(Box::new(proj1));
-        processor.projections.push(Box::new(proj2));
+        processor.register(Box::new(proj1));
+        processor.register(Box::new(proj2));
 
         processor.run_catch_up().await.unwrap();
 
         assert_eq!(*count1.read().await, 2);
         assert_eq!(*count2.read().await, 2);
     }
+
+    #[tokio::test]
+    async fn test_event_groups_split_by_aggregate() {
+        let store = InMemoryEventStore::new();
+        let first = AggregateId::new();
+        let second = AggregateId::new();
+        for (agg_id, versions) in [(first, 1..=2), (second, 1..=1), (first, 3..=3)] {
+            let events = versions
+                .map(|v| create_test_event(agg_id, Version::new(v)))
+                .collect();
+            store
+                .append(events, event_store::AppendOptions::new())
+                .await
+                .unwrap();
+        }
+
+        let mut groups = EventGroups::new(store.stream_all_events().await.unwrap());
+        let mut sequences = Vec::new();
+        while let Some(group) = groups.next().await.unwrap() {
+            sequences.push(group.sequences);
+        }
+
+        assert_eq!(sequences, vec![vec![1, 2], vec![3], vec![4]]);
+        assert_eq!(groups.streamed, 4);
+    }
+
+    #[tokio::test]
+    async fn test_continuous_mode_catches_up_on_append() {
+        let store = InMemoryEventStore::new();
+        let projection = CountingProjection::new();
+        let count_ref = Arc::clone(&projection.count);
+        let mut processor = ProjectionProcessor::new(store.clone());
+        processor.register(Box::new(projection));
+        let processor = Arc::new(processor);
+
+        // Polling alone would take a minute; only the notification is fast
+        let shutdown = CancellationToken::new();
+        let task = tokio::spawn({
+            let processor = processor.clone();
+            let shutdown = shutdown.clone();
+            async move {
+                processor
+                    .run_continuous(Duration::from_secs(60), shutdown)
+                    .await
+            }
+        });
+
+        let agg_id = AggregateId::new();
+        store
+            .append(
+                vec![create_test_event(agg_id, Version::new(1))],
+                event_store::AppendOptions::new(),
+            )
+            .await
+            .unwrap();
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while *count_ref.read().await < 1 {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("projection not caught up after append");
+
+        shutdown.cancel();
+        task.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_subscribed_mode_follows_appends() {
+        let store = InMemoryEventStore::new();
+        let agg_id = AggregateId::new();
+        store
+            .append(
+                vec![create_test_event(agg_id, Version::new(1))],
+                event_store::AppendOptions::new(),
+            )
+            .await
+            .unwrap();
+
+        let projection = CountingProjection::new();
+        let count_ref = Arc::clone(&projection.count);
+        let mut processor = ProjectionProcessor::new(store.clone());
+        processor.register(Box::new(projection));
+        let processor = Arc::new(processor);
+
+        let shutdown = CancellationToken::new();
+        let task = tokio::spawn({
+            let processor = processor.clone();
+            let shutdown = shutdown.clone();
+            async move { processor.run_subscribed(shutdown).await }
+        });
+
+        for version in 2..=3 {
+            store
+                .append(
+                    vec![create_test_event(agg_id, Version::new(version))],
+                    event_store::AppendOptions::new(),
+                )
+                .await
+                .unwrap();
+        }
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while *count_ref.read().await < 3 {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("projection did not follow appends");
+        // Each event is delivered once, whether by catch-up or subscription
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(*count_ref.read().await, 3);
+
+        shutdown.cancel();
+        task.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_process_remote_skips_redelivered_events() {
+        let projection = CountingProjection::new();
+        let count_ref = Arc::clone(&projection.count);
+        let pos_ref = Arc::clone(&projection.position);
+        let mut processor = ProjectionProcessor::new(InMemoryEventStore::new());
+        processor.register(Box::new(projection));
+
+        let agg_id = AggregateId::new();
+        let mut first = create_test_event(agg_id, Version::new(1));
+        first.sequence = Some(41);
+        let mut second = create_test_event(agg_id, Version::new(2));
+        second.sequence = Some(42);
+
+        processor.process_remote(&first).await.unwrap();
+        processor.process_remote(&second).await.unwrap();
+        processor.process_remote(&first).await.unwrap();
+        assert_eq!(*count_ref.read().await, 2);
+        assert_eq!(pos_ref.read().await.last_sequence, 42);
+
+        let unsequenced = create_test_event(agg_id, Version::new(3));
+        assert!(processor.process_remote(&unsequenced).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_deliveries_of_deprecated_versions_are_counted() {
+        let store = InMemoryEventStore::new();
+        let agg_id = AggregateId::new();
+        let current = EventEnvelope::builder()
+            .aggregate_id(agg_id)
+            .aggregate_type("Order")
+            .event_type("TestEvent")
+            .version(Version::new(2))
+            .metadata(event_store::EVENT_VERSION_KEY, serde_json::json!(2))
+            .payload_raw(serde_json::json!({"test": true}))
+            .build();
+        store
+            .append(
+                vec![create_test_event(agg_id, Version::new(1)), current],
+                event_store::AppendOptions::new(),
+            )
+            .await
+            .unwrap();
+
+        let deprecations = SchemaDeprecations::new();
+        deprecations.deprecate("TestEvent", 1);
+        let mut processor =
+            ProjectionProcessor::new(store).with_schema_deprecations(deprecations.clone());
+        processor.register(Box::new(CountingProjection::new()));
+        processor.run_catch_up().await.unwrap();
+
+        let report = deprecations.report();
+        assert_eq!(report.versions.len(), 1);
+        assert_eq!(report.versions[0].projection_reads, 1);
+        assert_eq!(report.versions[0].load_reads, 0);
+    }
+
+    #[test]
+    fn test_gap_guard_holds_back_events_after_a_recent_gap() {
+        let group = |sequences: &[u64], age: chrono::Duration| {
+            let aggregate_id = AggregateId::new();
+            let events = sequences
+                .iter()
+                .map(|&sequence| {
+                    let mut event = EventEnvelope::builder()
+                        .aggregate_id(aggregate_id)
+                        .aggregate_type("Order")
+                        .event_type("TestEvent")
+                        .version(Version::new(1))
+                        .timestamp(chrono::Utc::now() - age)
+                        .payload_raw(serde_json::json!({}))
+                        .build();
+                    event.sequence = Some(sequence);
+                    event
+                })
+                .collect();
+            EventGroup {
+                events,
+                sequences: sequences.to_vec(),
+            }
+        };
+        let recent = chrono::Duration::zero();
+        let gaps = Mutex::new(SequenceGaps::new(Duration::from_secs(60)));
+
+        // Contiguous sequences pass, and a gap after them stops the read
+        let mut guard = GapGuard::new(&gaps, 0);
+        assert!(guard.admits(&group(&[1, 2], recent)));
+        assert!(!guard.admits(&group(&[4], recent)));
+
+        // So does a gap inside a group
+        assert!(!GapGuard::new(&gaps, 0).admits(&group(&[1, 3], recent)));
+
+        // Gaps every reader has passed don't matter
+        let mut guard = GapGuard::new(&gaps, 3);
+        assert!(guard.admits(&group(&[1, 2], recent)));
+        assert!(guard.admits(&group(&[4], recent)));
+
+        // A backdated event doesn't make a new gap look old
+        let mut guard = GapGuard::new(&gaps, 0);
+        assert!(guard.admits(&group(&[5], recent)));
+        assert!(!guard.admits(&group(&[7], chrono::Duration::days(30))));
+
+        // Gaps seen open for longer than the window are rolled-back appends
+        let gaps = Mutex::new(SequenceGaps::new(Duration::from_millis(20)));
+        assert!(!GapGuard::new(&gaps, 0).admits(&group(&[1, 3], recent)));
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(GapGuard::new(&gaps, 0).admits(&group(&[1, 3], recent)));
+    }
 }
@@ -1,36 +1,77 @@
 //! Core projection trait and position tracking.
 
 use async_trait::async_trait;
-use event_store::EventEnvelope;
+use event_store::{EventEnvelope, EventId};
+use tokio::sync::RwLock;
 
 use crate::Result;
 
-/// Tracks how many events a projection has processed.
+/// Tracks where a projection is in the global event log.
+///
+/// `last_sequence` is the authoritative position used for catch-up;
+/// `events_processed` is a plain counter kept for reporting. The two can
+/// differ when events are skipped or redelivered after a partial failure.
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub struct ProjectionPosition {
     /// Number of events processed by this projection.
     pub events_processed: u64,
+
+    /// Global sequence of the last event processed, or 0 if none.
+    pub last_sequence: u64,
+
+    /// ID of the last event processed.
+    pub last_event_id: Option<EventId>,
 }
 
 impl ProjectionPosition {
     /// Creates a new position at zero.
     pub fn zero() -> Self {
+        Self::default()
+    }
+
+    /// Advances the position by one event without a known sequence.
+    pub fn advance(&self) -> Self {
         Self {
-            events_processed: 0,
+            events_processed: self.events_processed + 1,
+            last_sequence: self.last_sequence + 1,
+            last_event_id: self.last_event_id,
         }
     }
 
-    /// Advances the position by one event.
-    pub fn advance(&self) -> Self {
+    /// Advances the position past the given event.
+    ///
+    /// Uses the event's global sequence when the store assigned one, and
+    /// otherwise assumes it directly follows the last processed event.
+    pub fn advance_to(&self, event: &EventEnvelope) -> Self {
         Self {
             events_processed: self.events_processed + 1,
+            last_sequence: event.sequence.unwrap_or(self.last_sequence + 1),
+            last_event_id: Some(event.event_id),
         }
     }
+
+    /// Returns true if the log head is past this position.
+    pub fn is_behind(&self, head: u64) -> bool {
+        self.last_sequence < head
+    }
+
+    /// Returns how many log positions this projection trails the head by.
+    pub fn lag(&self, head: u64) -> u64 {
+        head.saturating_sub(self.last_sequence)
+    }
+
+    /// Returns how far the processed counter has drifted from the sequence.
+    ///
+    /// Positive means more events were handled than the sequence accounts
+    /// for (redelivery); negative means events were skipped.
+    pub fn counter_drift(&self) -> i64 {
+        self.events_processed as i64 - self.last_sequence as i64
+    }
 }
 
 impl std::fmt::Display for ProjectionPosition {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "position({})", self.events_processed)
+        write!(f, "position({})", self.last_sequence)
     }
 }
 
@@ -46,11 +87,69 @@ pub trait Projection: Send + Sync {
     /// Handles a single event, updating the projection's read model.
     async fn handle(&self, event: &EventEnvelope) -> Result<()>;
 
+    /// Handles the events from one append as a unit.
+    ///
+    /// The default handles them one at a time, so a failure part-way leaves
+    /// the earlier events applied. Views that can roll back override this to
+    /// apply either all of the events or none of them.
+    async fn handle_group(&self, events: &[EventEnvelope]) -> Result<()> {
+        for event in events {
+            self.handle(event).await?;
+        }
+        Ok(())
+    }
+
     /// Returns the current position of this projection.
     async fn position(&self) -> ProjectionPosition;
 
     /// Resets the projection to its initial state.
     async fn reset(&self) -> Result<()>;
+
+    /// Returns a short summary of the read model, used for sampled debug logs.
+    async fn summary(&self) -> serde_json::Value {
+        serde_json::json!({ "events_processed": self.position().await.events_processed })
+    }
+}
+
+/// Handles a group of events, restoring `state` to what it was before the
+/// group if any of them fails.
+///
+/// For views whose whole read model, position included, sits behind one
+/// lock. Single events are handled directly, without copying the state.
+pub(crate) async fn apply_or_rollback<P, T>(
+    projection: &P,
+    state: &RwLock<T>,
+    events: &[EventEnvelope],
+) -> Result<()>
+where
+    P: Projection + ?Sized,
+    T: Clone,
+{
+    if let [event] = events {
+        return projection.handle(event).await;
+    }
+
+    let saved = state.read().await.clone();
+    for event in events {
+        if let Err(e) = projection.handle(event).await {
+            *state.write().await = saved;
+            record_rollback(projection.name(), event, events.len());
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+/// Logs and counts a group that was rolled back after `event` failed.
+pub(crate) fn record_rollback(projection: &'static str, event: &EventEnvelope, group_size: usize) {
+    metrics::counter!("projection_group_rollbacks", "projection" => projection).increment(1);
+    tracing::warn!(
+        projection,
+        event_id = %event.event_id,
+        event_type = %event.event_type,
+        group_size,
+        "event group rolled back"
+    );
 }
 
 #[cfg(test)]
@@ -75,8 +174,46 @@ mod tests {
     #[test]
     fn position_display() {
         let pos = ProjectionPosition {
-            events_processed: 42,
+            events_processed: 40,
+            last_sequence: 42,
+            last_event_id: None,
         };
         assert_eq!(pos.to_string(), "position(42)");
     }
+
+    fn event_at(sequence: Option<u64>) -> EventEnvelope {
+        let mut event = EventEnvelope::builder()
+            .aggregate_id(common::AggregateId::new())
+            .aggregate_type("Order")
+            .event_type("TestEvent")
+            .version(event_store::Version::first())
+            .payload_raw(serde_json::json!({}))
+            .build();
+        event.sequence = sequence;
+        event
+    }
+
+    #[test]
+    fn position_advances_to_event_sequence() {
+        let event = event_at(Some(7));
+        let pos = ProjectionPosition::zero().advance_to(&event);
+        assert_eq!(pos.events_processed, 1);
+        assert_eq!(pos.last_sequence, 7);
+        assert_eq!(pos.last_event_id, Some(event.event_id));
+        assert_eq!(pos.counter_drift(), -6);
+
+        // Unsequenced events are assumed to follow on directly
+        let pos = pos.advance_to(&event_at(None));
+        assert_eq!(pos.last_sequence, 8);
+    }
+
+    #[test]
+    fn position_lag_against_head() {
+        let pos = ProjectionPosition::zero().advance_to(&event_at(Some(3)));
+        assert!(pos.is_behind(5));
+        assert_eq!(pos.lag(5), 2);
+        assert!(!pos.is_behind(3));
+        assert_eq!(pos.lag(3), 0);
+        assert_eq!(pos.lag(1), 0);
+    }
 }
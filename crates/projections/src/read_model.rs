@@ -1,13 +1,89 @@
 //! Read model trait for query-side views.
 
+use std::collections::HashMap;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::sync::{RwLock, RwLockReadGuard};
+
+use crate::error::{ProjectionError, Result};
+
+/// Longest a read model query waits for a view's state while events are
+/// being applied to it.
+pub const READ_LOCK_TIMEOUT: Duration = Duration::from_secs(1);
+
 /// A read model providing query access to denormalized data.
 ///
 /// Read models are the query-side data structures in CQRS.
 /// They are updated by projections and optimized for fast reads.
+///
+/// Queries fail rather than guess when the view can't be read: a lock held
+/// past [`READ_LOCK_TIMEOUT`] is [`ProjectionError::LockTimeout`], and a
+/// view whose backing store is down reports [`ProjectionError::Unavailable`].
+#[async_trait]
 pub trait ReadModel: Send + Sync {
     /// Returns the name of this read model.
     fn name(&self) -> &'static str;
 
     /// Returns the number of entries in this read model.
-    fn count(&self) -> usize;
+    async fn count(&self) -> Result<usize>;
+
+    /// Returns an estimate of the bytes this read model holds in memory.
+    ///
+    /// Counts table capacity and nested collections, not the contents of
+    /// strings, so it is a lower bound meant for spotting growth.
+    async fn memory_usage(&self) -> Result<usize>;
+}
+
+/// Read-locks a view's state for a query, waiting at most
+/// [`READ_LOCK_TIMEOUT`].
+pub async fn read_state<'a, T>(
+    read_model: &'static str,
+    state: &'a RwLock<T>,
+) -> Result<RwLockReadGuard<'a, T>> {
+    read_state_within(read_model, state, READ_LOCK_TIMEOUT).await
+}
+
+pub(crate) async fn read_state_within<'a, T>(
+    read_model: &'static str,
+    state: &'a RwLock<T>,
+    timeout: Duration,
+) -> Result<RwLockReadGuard<'a, T>> {
+    tokio::time::timeout(timeout, state.read())
+        .await
+        .map_err(|_| ProjectionError::LockTimeout {
+            read_model,
+            waited: timeout,
+        })
+}
+
+/// Estimates the bytes held by a hash map's table.
+pub(crate) fn map_memory_usage<K, V>(map: &HashMap<K, V>) -> usize {
+    map.capacity() * (size_of::<K>() + size_of::<V>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_state_times_out_while_written() {
+        let state = RwLock::new(1);
+
+        let writer = state.write().await;
+        let result = read_state_within("TestView", &state, Duration::from_millis(10)).await;
+        assert!(matches!(
+            result,
+            Err(ProjectionError::LockTimeout {
+                read_model: "TestView",
+                ..
+            })
+        ));
+
+        drop(writer);
+        let value = read_state_within("TestView", &state, Duration::from_millis(10))
+            .await
+            .unwrap();
+        assert_eq!(*value, 1);
+    }
 }
@@ -0,0 +1,427 @@
+//! Named projection factories.
+//!
+//! Deployments choose their read models by name instead of wiring views by
+//! hand: a lightweight deployment can leave out the heavier views, and an
+//! analytics deployment can run without the ones that back the command API.
+//! A [`ProjectionRegistry`] maps names to factories and builds the chosen
+//! [`ViewSpec`]s into a [`ProjectionSet`]. A view can also be built in
+//! shadow mode alongside the one of the same name, to try out another
+//! backend without serving from it.
+
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::retention::{InMemoryOverflow, RetentionPolicy};
+use crate::shadow::{ComparableView, ShadowProbe, ShadowProjection, ShadowStats};
+use crate::{
+    CurrentOrdersView, CustomerOrdersView, InventoryView, OrderHistoryView, OversellDetectionView,
+    Projection, ProjectionError, ReadModel, Result, StockLevelsView,
+};
+
+/// Registry name of [`CurrentOrdersView`].
+pub const CURRENT_ORDERS: &str = "current_orders";
+
+/// Registry name of [`OrderHistoryView`].
+pub const ORDER_HISTORY: &str = "order_history";
+
+/// Registry name of [`CustomerOrdersView`].
+pub const CUSTOMER_ORDERS: &str = "customer_orders";
+
+/// Registry name of [`InventoryView`].
+pub const INVENTORY: &str = "inventory";
+
+/// Registry name of [`StockLevelsView`].
+pub const STOCK_LEVELS: &str = "stock_levels";
+
+/// Registry name of [`OversellDetectionView`].
+pub const OVERSELL: &str = "oversell";
+
+/// Where a view keeps its entries.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ViewBackend {
+    /// Every entry stays in memory.
+    #[default]
+    Memory,
+    /// At most `max_entries` finished entries stay in memory; older ones
+    /// move to an overflow store. Only views with a retention policy
+    /// support it.
+    Overflow { max_entries: usize },
+}
+
+impl FromStr for ViewBackend {
+    type Err = String;
+
+    /// Parses `memory` or `overflow=<max_entries>`.
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let backend = s.trim().to_ascii_lowercase();
+        match backend.split_once('=') {
+            None if backend == "memory" => Ok(ViewBackend::Memory),
+            Some(("overflow", max_entries)) => match max_entries.trim().parse() {
+                Ok(max_entries) if max_entries > 0 => Ok(ViewBackend::Overflow { max_entries }),
+                _ => Err(format!("invalid overflow entry limit: {max_entries}")),
+            },
+            _ => Err(format!("unknown view backend: {s}")),
+        }
+    }
+}
+
+/// A view to build, by registry name, and the backend to build it with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ViewSpec {
+    pub name: String,
+    pub backend: ViewBackend,
+}
+
+impl ViewSpec {
+    /// Creates a spec for an in-memory view.
+    pub fn memory(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            backend: ViewBackend::Memory,
+        }
+    }
+
+    /// Parses a comma-separated list of specs, such as
+    /// `current_orders,order_history:overflow=10000`.
+    pub fn parse_list(s: &str) -> std::result::Result<Vec<Self>, String> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|spec| !spec.is_empty())
+            .map(str::parse)
+            .collect()
+    }
+}
+
+impl FromStr for ViewSpec {
+    type Err = String;
+
+    /// Parses `name` or `name:backend`.
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let (name, backend) = match s.split_once(':') {
+            Some((name, backend)) => (name, backend.parse()?),
+            None => (s, ViewBackend::Memory),
+        };
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(format!("missing view name in {s:?}"));
+        }
+        Ok(Self {
+            name: name.to_ascii_lowercase(),
+            backend,
+        })
+    }
+}
+
+/// A view built by a factory.
+///
+/// The projection, read model, and handle all share the view's state.
+pub struct BuiltView {
+    projection: Box<dyn Projection>,
+    read_model: Arc<dyn ReadModel>,
+    handle: Arc<dyn Any + Send + Sync>,
+    comparable: Option<Arc<dyn ComparableView>>,
+}
+
+impl BuiltView {
+    /// Wraps a view that is both the projection and its read model.
+    pub fn new<V>(view: V) -> Self
+    where
+        V: Projection + ReadModel + Clone + 'static,
+    {
+        let view = Arc::new(view);
+        Self {
+            projection: Box::new(view.as_ref().clone()),
+            read_model: view.clone(),
+            handle: view,
+            comparable: None,
+        }
+    }
+
+    /// Wraps a view that can also run in, or be compared against, shadow
+    /// mode.
+    pub fn comparable<V>(view: V) -> Self
+    where
+        V: Projection + ReadModel + ShadowProbe + Clone + 'static,
+    {
+        let comparable: Arc<dyn ComparableView> = Arc::new(view.clone());
+        Self {
+            comparable: Some(comparable),
+            ..Self::new(view)
+        }
+    }
+}
+
+type Factory = Box<dyn Fn(ViewBackend) -> Result<BuiltView> + Send + Sync>;
+
+/// Factories for views, by name.
+pub struct ProjectionRegistry {
+    factories: BTreeMap<&'static str, Factory>,
+}
+
+impl ProjectionRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self {
+            factories: BTreeMap::new(),
+        }
+    }
+
+    /// Creates a registry of the views in this crate.
+    pub fn with_builtin_views() -> Self {
+        let mut registry = Self::new();
+        registry.register(CURRENT_ORDERS, |backend| {
+            memory_only(CURRENT_ORDERS, backend)?;
+            Ok(BuiltView::comparable(CurrentOrdersView::new()))
+        });
+        registry.register(ORDER_HISTORY, |backend| {
+            let view = match backend {
+                ViewBackend::Memory => OrderHistoryView::new(),
+                ViewBackend::Overflow { max_entries } => OrderHistoryView::new()
+                    .with_retention(RetentionPolicy::unbounded().with_max_entries(max_entries))
+                    .with_overflow(Arc::new(InMemoryOverflow::new())),
+            };
+            Ok(BuiltView::comparable(view))
+        });
+        registry.register(CUSTOMER_ORDERS, |backend| {
+            let view = match backend {
+                ViewBackend::Memory => CustomerOrdersView::new(),
+                ViewBackend::Overflow { max_entries } => CustomerOrdersView::new()
+                    .with_retention(RetentionPolicy::unbounded().with_max_entries(max_entries))
+                    .with_overflow(Arc::new(InMemoryOverflow::new())),
+            };
+            Ok(BuiltView::new(view))
+        });
+        registry.register(INVENTORY, |backend| {
+            memory_only(INVENTORY, backend)?;
+            Ok(BuiltView::new(InventoryView::new()))
+        });
+        registry.register(STOCK_LEVELS, |backend| {
+            memory_only(STOCK_LEVELS, backend)?;
+            Ok(BuiltView::new(StockLevelsView::new()))
+        });
+        registry.register(OVERSELL, |backend| {
+            memory_only(OVERSELL, backend)?;
+            Ok(BuiltView::new(OversellDetectionView::new()))
+        });
+        registry
+    }
+
+    /// Adds a factory under `name`, replacing any earlier one.
+    pub fn register(
+        &mut self,
+        name: &'static str,
+        factory: impl Fn(ViewBackend) -> Result<BuiltView> + Send + Sync + 'static,
+    ) {
+        self.factories.insert(name, Box::new(factory));
+    }
+
+    /// Returns the registered names, alphabetically.
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.factories.keys().copied()
+    }
+
+    /// Builds the views in `specs`.
+    ///
+    /// Fails on an unknown name, a name listed twice, or a backend the view
+    /// doesn't support.
+    pub fn build(&self, specs: &[ViewSpec]) -> Result<ProjectionSet> {
+        let mut set = ProjectionSet {
+            projections: Vec::new(),
+            views: ViewHandles::default(),
+            comparable: Vec::new(),
+        };
+        let mut built = Vec::new();
+        for spec in specs {
+            let factory = self
+                .factories
+                .get(spec.name.as_str())
+                .ok_or_else(|| ProjectionError::NotRegistered(spec.name.clone()))?;
+            if built.contains(&spec.name) {
+                return Err(ProjectionError::AlreadyRegistered(spec.name.clone()));
+            }
+            let view = factory(spec.backend)?;
+            set.projections.push(view.projection);
+            set.views.read_models.push(view.read_model);
+            set.views.handles.push(view.handle);
+            if let Some(comparable) = view.comparable {
+                set.comparable.push((spec.name.clone(), comparable));
+            }
+            built.push(spec.name.clone());
+        }
+        Ok(set)
+    }
+
+    /// Adds `spec` to `set` as a shadow of the view of the same name,
+    /// comparing `sample_rate` of events.
+    ///
+    /// The shadow is registered under the view's registry name, such as
+    /// `order_history`, and isn't added to the set's query handles. Fails
+    /// if the set has no such view or the view can't be compared.
+    pub fn build_shadow(
+        &self,
+        spec: &ViewSpec,
+        set: &mut ProjectionSet,
+        sample_rate: f64,
+    ) -> Result<Arc<ShadowStats>> {
+        let (&name, factory) = self
+            .factories
+            .get_key_value(spec.name.as_str())
+            .ok_or_else(|| ProjectionError::NotRegistered(spec.name.clone()))?;
+        let authoritative = set
+            .comparable
+            .iter()
+            .find(|(built, _)| *built == spec.name)
+            .map(|(_, view)| view.clone())
+            .ok_or_else(|| {
+                ProjectionError::Projection(format!(
+                    "{name} must be built, and comparable, to run in shadow mode"
+                ))
+            })?;
+        let shadow = factory(spec.backend)?.comparable.ok_or_else(|| {
+            ProjectionError::Projection(format!("{name} can't run in shadow mode"))
+        })?;
+
+        let projection =
+            ShadowProjection::new(name, shadow, authoritative).with_sample_rate(sample_rate);
+        let stats = projection.stats();
+        set.projections.push(Box::new(projection));
+        Ok(stats)
+    }
+}
+
+impl Default for ProjectionRegistry {
+    fn default() -> Self {
+        Self::with_builtin_views()
+    }
+}
+
+/// Rejects any backend but [`ViewBackend::Memory`] for views without a
+/// retention policy.
+fn memory_only(name: &str, backend: ViewBackend) -> Result<()> {
+    match backend {
+        ViewBackend::Memory => Ok(()),
+        ViewBackend::Overflow { .. } => Err(ProjectionError::Projection(format!(
+            "{name} does not support the overflow backend"
+        ))),
+    }
+}
+
+/// Views built from a registry.
+pub struct ProjectionSet {
+    /// Projections to register with a processor.
+    pub projections: Vec<Box<dyn Projection>>,
+    /// Query handles on the same views.
+    pub views: ViewHandles,
+    /// Views that shadows can be compared against, by registry name.
+    comparable: Vec<(String, Arc<dyn ComparableView>)>,
+}
+
+/// Query handles on the views of a [`ProjectionSet`].
+#[derive(Clone, Default)]
+pub struct ViewHandles {
+    read_models: Vec<Arc<dyn ReadModel>>,
+    handles: Vec<Arc<dyn Any + Send + Sync>>,
+}
+
+impl ViewHandles {
+    /// Returns the view of type `V`, or None if it wasn't built.
+    pub fn get<V: Send + Sync + 'static>(&self) -> Option<Arc<V>> {
+        self.handles
+            .iter()
+            .find_map(|handle| handle.clone().downcast::<V>().ok())
+    }
+
+    /// Returns every built view as a read model, in build order.
+    pub fn read_models(&self) -> &[Arc<dyn ReadModel>] {
+        &self.read_models
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_specs() {
+        let specs = ViewSpec::parse_list("current_orders, Order_History:overflow=500,").unwrap();
+        assert_eq!(
+            specs,
+            [
+                ViewSpec::memory(CURRENT_ORDERS),
+                ViewSpec {
+                    name: ORDER_HISTORY.to_string(),
+                    backend: ViewBackend::Overflow { max_entries: 500 },
+                },
+            ]
+        );
+        assert!("stock_levels:disk".parse::<ViewSpec>().is_err());
+        assert!("order_history:overflow=0".parse::<ViewSpec>().is_err());
+        assert!(":memory".parse::<ViewSpec>().is_err());
+    }
+
+    #[test]
+    fn test_build_selected_views() {
+        let registry = ProjectionRegistry::with_builtin_views();
+        let set = registry
+            .build(&[
+                ViewSpec::memory(STOCK_LEVELS),
+                "order_history:overflow=100".parse().unwrap(),
+            ])
+            .unwrap();
+
+        let names: Vec<_> = set.projections.iter().map(|p| p.name()).collect();
+        assert_eq!(names, ["StockLevelsView", "OrderHistoryView"]);
+        assert!(set.views.get::<StockLevelsView>().is_some());
+        assert!(set.views.get::<CurrentOrdersView>().is_none());
+        assert_eq!(set.views.read_models().len(), 2);
+    }
+
+    #[test]
+    fn test_build_rejects_invalid_sets() {
+        let registry = ProjectionRegistry::with_builtin_views();
+        let build = |specs: &str| registry.build(&ViewSpec::parse_list(specs).unwrap());
+
+        assert!(matches!(
+            build("search_index"),
+            Err(ProjectionError::NotRegistered(_))
+        ));
+        assert!(matches!(
+            build("inventory,inventory"),
+            Err(ProjectionError::AlreadyRegistered(_))
+        ));
+        assert!(matches!(
+            build("current_orders:overflow=10"),
+            Err(ProjectionError::Projection(_))
+        ));
+    }
+
+    #[test]
+    fn test_build_shadow_alongside_its_view() {
+        let registry = ProjectionRegistry::with_builtin_views();
+        let mut set = registry
+            .build(&[ViewSpec::memory(ORDER_HISTORY), ViewSpec::memory(INVENTORY)])
+            .unwrap();
+
+        registry
+            .build_shadow(&"order_history:overflow=10".parse().unwrap(), &mut set, 1.0)
+            .unwrap();
+        let names: Vec<_> = set.projections.iter().map(|p| p.name()).collect();
+        assert_eq!(names, ["OrderHistoryView", "InventoryView", ORDER_HISTORY]);
+        // Only the authoritative view serves queries
+        assert_eq!(set.views.read_models().len(), 2);
+
+        // Shadows need a built, comparable view of the same name
+        assert!(
+            registry
+                .build_shadow(&ViewSpec::memory(CURRENT_ORDERS), &mut set, 1.0)
+                .is_err()
+        );
+        assert!(
+            registry
+                .build_shadow(&ViewSpec::memory(INVENTORY), &mut set, 1.0)
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,242 @@
+//! Retention of completed entries in in-memory views.
+//!
+//! Views that keep finished orders (or customers with nothing in flight)
+//! would otherwise grow for as long as the process runs. A
+//! [`RetentionPolicy`] bounds them by age and by entry count, evicting the
+//! least recently active entries first. Evicted entries can be handed to an
+//! [`OverflowStore`], from which lookups and late events read them back.
+
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use tokio::sync::RwLock;
+
+use crate::Result;
+
+/// Limits on how many completed entries a view keeps in memory.
+///
+/// Ages are measured against event timestamps rather than the wall clock,
+/// so a rebuild evicts the same entries as the original run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// Entries with no activity for longer than this are evicted.
+    pub max_age: Option<Duration>,
+    /// Past this many entries, the least recently active are evicted.
+    pub max_entries: Option<usize>,
+}
+
+impl RetentionPolicy {
+    /// Keeps every entry.
+    pub fn unbounded() -> Self {
+        Self::default()
+    }
+
+    /// Evicts entries with no activity for longer than `max_age`.
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Keeps at most `max_entries` entries.
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = Some(max_entries);
+        self
+    }
+
+    /// Returns true if the policy never evicts.
+    pub fn is_unbounded(&self) -> bool {
+        self.max_age.is_none() && self.max_entries.is_none()
+    }
+}
+
+/// Destination for entries evicted from an in-memory view.
+#[async_trait]
+pub trait OverflowStore<K, V>: Send + Sync {
+    /// Stores evicted entries, replacing any earlier copies.
+    async fn put(&self, entries: Vec<(K, V)>) -> Result<()>;
+
+    /// Returns an evicted entry, if one was stored under `key`.
+    async fn get(&self, key: &K) -> Result<Option<V>>;
+
+    /// Removes every entry, e.g. when the view is rebuilt.
+    async fn clear(&self) -> Result<()>;
+}
+
+/// In-memory overflow store, for testing and single-process deployments.
+pub struct InMemoryOverflow<K, V> {
+    entries: Arc<RwLock<HashMap<K, V>>>,
+}
+
+impl<K, V> InMemoryOverflow<K, V> {
+    /// Creates an empty overflow store.
+    pub fn new() -> Self {
+        Self {
+            entries: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Returns the number of stored entries.
+    pub async fn entry_count(&self) -> usize {
+        self.entries.read().await.len()
+    }
+}
+
+impl<K, V> Clone for InMemoryOverflow<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            entries: self.entries.clone(),
+        }
+    }
+}
+
+impl<K, V> Default for InMemoryOverflow<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl<K, V> OverflowStore<K, V> for InMemoryOverflow<K, V>
+where
+    K: Eq + Hash + Send + Sync,
+    V: Clone + Send + Sync,
+{
+    async fn put(&self, entries: Vec<(K, V)>) -> Result<()> {
+        self.entries.write().await.extend(entries);
+        Ok(())
+    }
+
+    async fn get(&self, key: &K) -> Result<Option<V>> {
+        Ok(self.entries.read().await.get(key).cloned())
+    }
+
+    async fn clear(&self) -> Result<()> {
+        self.entries.write().await.clear();
+        Ok(())
+    }
+}
+
+/// Counts entries a view evicted under its retention policy.
+pub(crate) fn record_evictions(projection: &'static str, count: usize) {
+    metrics::counter!("projection_evictions", "projection" => projection).increment(count as u64);
+    tracing::debug!(projection, count, "evicted read model entries");
+}
+
+/// Tracks when each evictable entry of a view was last active.
+#[derive(Debug, Clone)]
+pub(crate) struct RetentionIndex<K> {
+    /// Last activity per entry: its recency tick and event timestamp.
+    touched: HashMap<K, (u64, DateTime<Utc>)>,
+    /// Entries by recency tick, least recently active first.
+    by_recency: BTreeMap<u64, K>,
+    next_tick: u64,
+}
+
+impl<K: Eq + Hash + Clone> RetentionIndex<K> {
+    pub(crate) fn new() -> Self {
+        Self {
+            touched: HashMap::new(),
+            by_recency: BTreeMap::new(),
+            next_tick: 0,
+        }
+    }
+
+    /// Records activity on an entry at `at`, making it the most recent.
+    pub(crate) fn touch(&mut self, key: K, at: DateTime<Utc>) {
+        let tick = self.next_tick;
+        self.next_tick += 1;
+        if let Some((old_tick, _)) = self.touched.insert(key.clone(), (tick, at)) {
+            self.by_recency.remove(&old_tick);
+        }
+        self.by_recency.insert(tick, key);
+    }
+
+    /// Stops tracking an entry.
+    pub(crate) fn remove(&mut self, key: &K) {
+        if let Some((tick, _)) = self.touched.remove(key) {
+            self.by_recency.remove(&tick);
+        }
+    }
+
+    /// Removes and returns the entries `policy` evicts as of `now`, least
+    /// recently active first.
+    pub(crate) fn evict(&mut self, policy: &RetentionPolicy, now: DateTime<Utc>) -> Vec<K> {
+        let mut evicted = Vec::new();
+        while let Some((&tick, key)) = self.by_recency.first_key_value() {
+            let (_, at) = self.touched[key];
+            let too_many = policy
+                .max_entries
+                .is_some_and(|max| self.by_recency.len() > max);
+            let too_old = policy.max_age.is_some_and(|max_age| now - at > max_age);
+            if !too_many && !too_old {
+                break;
+            }
+            let key = self.by_recency.remove(&tick).expect("first key exists");
+            self.touched.remove(&key);
+            evicted.push(key);
+        }
+        evicted
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.touched.clear();
+        self.by_recency.clear();
+        self.next_tick = 0;
+    }
+
+    /// Estimates the bytes held by the index.
+    pub(crate) fn memory_usage(&self) -> usize {
+        crate::read_model::map_memory_usage(&self.touched)
+            + self.by_recency.len() * (size_of::<u64>() + size_of::<K>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(minutes: i64) -> DateTime<Utc> {
+        DateTime::<Utc>::UNIX_EPOCH + Duration::minutes(minutes)
+    }
+
+    #[test]
+    fn test_evicts_least_recently_touched_over_capacity() {
+        let policy = RetentionPolicy::unbounded().with_max_entries(2);
+        let mut index = RetentionIndex::new();
+        index.touch("a", at(0));
+        index.touch("b", at(1));
+        index.touch("c", at(2));
+        index.touch("a", at(3));
+
+        assert_eq!(index.evict(&policy, at(3)), vec!["b"]);
+        assert!(index.evict(&policy, at(3)).is_empty());
+    }
+
+    #[test]
+    fn test_evicts_entries_past_max_age() {
+        let policy = RetentionPolicy::unbounded().with_max_age(Duration::minutes(10));
+        let mut index = RetentionIndex::new();
+        index.touch("a", at(0));
+        index.touch("b", at(5));
+
+        assert!(index.evict(&policy, at(10)).is_empty());
+        assert_eq!(index.evict(&policy, at(11)), vec!["a"]);
+    }
+
+    #[test]
+    fn test_unbounded_policy_keeps_everything() {
+        let mut index = RetentionIndex::new();
+        for minute in 0..100 {
+            index.touch(minute, at(minute));
+        }
+
+        assert!(
+            index
+                .evict(&RetentionPolicy::unbounded(), at(1_000_000))
+                .is_empty()
+        );
+    }
+}
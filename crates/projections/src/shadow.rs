@@ -0,0 +1,303 @@
+//! Shadow-mode projections for rolling out rewritten views.
+//!
+//! A shadow view consumes the same events as the view it is meant to
+//! replace and builds its own state, but it is never handed to queries.
+//! After it handles a sampled event, the answers both views give for the
+//! event's aggregate are compared and any divergence is counted and logged.
+//! Once the shadow has run clean for long enough it can be promoted.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use async_trait::async_trait;
+use common::AggregateId;
+use event_store::EventEnvelope;
+
+use crate::Result;
+use crate::projection::{Projection, ProjectionPosition};
+use crate::sampling::EventSampler;
+
+/// Fraction of events compared when no rate is set.
+pub const DEFAULT_SHADOW_SAMPLE_RATE: f64 = 0.1;
+
+/// Query access used to compare two implementations of a view.
+#[async_trait]
+pub trait ShadowProbe: Send + Sync {
+    /// Returns what the view reports for one aggregate, in a shape every
+    /// implementation of the view agrees on. `null` if it has nothing.
+    async fn probe(&self, aggregate_id: AggregateId) -> Result<serde_json::Value>;
+}
+
+/// A view that can take part in a shadow comparison, on either side.
+pub trait ComparableView: Projection + ShadowProbe {}
+
+impl<T: Projection + ShadowProbe> ComparableView for T {}
+
+/// Comparison counts for one shadow view.
+#[derive(Debug, Default)]
+pub struct ShadowStats {
+    matched: AtomicU64,
+    diverged: AtomicU64,
+    skipped: AtomicU64,
+    errors: AtomicU64,
+}
+
+impl ShadowStats {
+    /// Comparisons where both views agreed.
+    pub fn matched(&self) -> u64 {
+        self.matched.load(Ordering::Relaxed)
+    }
+
+    /// Comparisons where the views disagreed.
+    pub fn diverged(&self) -> u64 {
+        self.diverged.load(Ordering::Relaxed)
+    }
+
+    /// Sampled events not compared because the views were at different
+    /// positions.
+    pub fn skipped(&self) -> u64 {
+        self.skipped.load(Ordering::Relaxed)
+    }
+
+    /// Events the shadow failed to handle or probe.
+    pub fn errors(&self) -> u64 {
+        self.errors.load(Ordering::Relaxed)
+    }
+}
+
+/// Runs a view in shadow mode against an authoritative one.
+///
+/// Register it with the processor after the authoritative view, so both
+/// have handled an event by the time they are compared. Failures in the
+/// shadow are logged and counted but never returned, so a broken shadow
+/// can't hold up the views that serve traffic.
+pub struct ShadowProjection {
+    name: &'static str,
+    shadow: Arc<dyn ComparableView>,
+    authoritative: Arc<dyn ComparableView>,
+    sampler: EventSampler,
+    stats: Arc<ShadowStats>,
+}
+
+impl ShadowProjection {
+    /// Shadows `authoritative` with `shadow`, registered under `name`.
+    ///
+    /// The name must differ from the authoritative view's, since the
+    /// processor tracks projections by name.
+    pub fn new(
+        name: &'static str,
+        shadow: Arc<dyn ComparableView>,
+        authoritative: Arc<dyn ComparableView>,
+    ) -> Self {
+        let sampler = EventSampler::new();
+        sampler.configure(true, DEFAULT_SHADOW_SAMPLE_RATE);
+        Self {
+            name,
+            shadow,
+            authoritative,
+            sampler,
+            stats: Arc::default(),
+        }
+    }
+
+    /// Sets the fraction of events compared, between 0.0 and 1.0.
+    pub fn with_sample_rate(self, rate: f64) -> Self {
+        self.sampler.configure(true, rate);
+        self
+    }
+
+    /// Returns the comparison counts, shared with this projection.
+    pub fn stats(&self) -> Arc<ShadowStats> {
+        self.stats.clone()
+    }
+
+    /// Compares both views' answers for the aggregate `event` belongs to.
+    async fn compare(&self, event: &EventEnvelope) -> Result<()> {
+        let shadow_at = self.shadow.position().await.last_sequence;
+        let authoritative_at = self.authoritative.position().await.last_sequence;
+        if shadow_at != authoritative_at {
+            self.record("skipped", &self.stats.skipped);
+            return Ok(());
+        }
+
+        let expected = self.authoritative.probe(event.aggregate_id).await?;
+        let actual = self.shadow.probe(event.aggregate_id).await?;
+        if expected == actual {
+            self.record("matched", &self.stats.matched);
+        } else {
+            self.record("diverged", &self.stats.diverged);
+            tracing::warn!(
+                shadow = self.name,
+                authoritative = self.authoritative.name(),
+                aggregate_id = %event.aggregate_id,
+                event_id = %event.event_id,
+                expected = %expected,
+                actual = %actual,
+                "shadow view diverged"
+            );
+        }
+        Ok(())
+    }
+
+    fn record(&self, outcome: &'static str, count: &AtomicU64) {
+        count.fetch_add(1, Ordering::Relaxed);
+        metrics::counter!(
+            "shadow_comparisons",
+            "shadow" => self.name,
+            "outcome" => outcome
+        )
+        .increment(1);
+    }
+
+    fn record_error(&self, error: &crate::ProjectionError) {
+        self.record("error", &self.stats.errors);
+        tracing::warn!(shadow = self.name, error = %error, "shadow view failed");
+    }
+}
+
+#[async_trait]
+impl Projection for ShadowProjection {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    async fn handle(&self, event: &EventEnvelope) -> Result<()> {
+        self.handle_group(std::slice::from_ref(event)).await
+    }
+
+    async fn handle_group(&self, events: &[EventEnvelope]) -> Result<()> {
+        if let Err(e) = self.shadow.handle_group(events).await {
+            self.record_error(&e);
+            return Ok(());
+        }
+        if let Some(event) = events.iter().find(|e| self.sampler.should_sample(e))
+            && let Err(e) = self.compare(event).await
+        {
+            self.record_error(&e);
+        }
+        Ok(())
+    }
+
+    async fn position(&self) -> ProjectionPosition {
+        self.shadow.position().await
+    }
+
+    async fn reset(&self) -> Result<()> {
+        self.shadow.reset().await
+    }
+
+    async fn summary(&self) -> serde_json::Value {
+        serde_json::json!({
+            "shadow": self.shadow.summary().await,
+            "matched": self.stats.matched(),
+            "diverged": self.stats.diverged(),
+            "skipped": self.stats.skipped(),
+            "errors": self.stats.errors(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CurrentOrdersView;
+    use domain::{CustomerId, DomainEvent, Money, OrderEvent, OrderItem};
+    use event_store::Version;
+
+    fn order_event(aggregate_id: AggregateId, version: i64, event: OrderEvent) -> EventEnvelope {
+        let mut envelope = EventEnvelope::builder()
+            .aggregate_id(aggregate_id)
+            .aggregate_type("Order")
+            .event_type(event.event_type())
+            .version(Version::new(version))
+            .payload(&event)
+            .unwrap()
+            .build();
+        envelope.sequence = Some(version as u64);
+        envelope
+    }
+
+    async fn feed(projections: &[&dyn Projection], events: &[EventEnvelope]) {
+        for event in events {
+            for projection in projections {
+                projection.handle(event).await.unwrap();
+            }
+        }
+    }
+
+    fn order_events() -> Vec<EventEnvelope> {
+        let order_id = AggregateId::new();
+        let item = OrderItem::new("SKU-001", "Widget", 2, Money::from_cents(500));
+        vec![
+            order_event(
+                order_id,
+                1,
+                OrderEvent::order_created(order_id, CustomerId::new()),
+            ),
+            order_event(order_id, 2, OrderEvent::item_added(&item)),
+        ]
+    }
+
+    #[tokio::test]
+    async fn test_identical_views_match() {
+        let authoritative = CurrentOrdersView::new();
+        let shadow = ShadowProjection::new(
+            "current_orders_shadow",
+            Arc::new(CurrentOrdersView::new()),
+            Arc::new(authoritative.clone()),
+        )
+        .with_sample_rate(1.0);
+        let stats = shadow.stats();
+        let events = order_events();
+
+        feed(&[&authoritative, &shadow], &events).await;
+
+        assert_eq!(stats.matched(), 2);
+        assert_eq!(stats.diverged(), 0);
+        assert_eq!(shadow.position().await.last_sequence, 2);
+    }
+
+    #[tokio::test]
+    async fn test_divergence_counted_without_failing() {
+        let authoritative = CurrentOrdersView::new();
+        let shadow = ShadowProjection::new(
+            "current_orders_shadow",
+            Arc::new(CurrentOrdersView::new()),
+            Arc::new(authoritative.clone()),
+        )
+        .with_sample_rate(1.0);
+        let stats = shadow.stats();
+
+        // The shadow reads a different quantity from the same event, as a
+        // buggy rewrite might
+        let events = order_events();
+        let mut misread = events.clone();
+        misread[1].payload["data"]["quantity"] = 3.into();
+        for (event, misread) in events.iter().zip(&misread) {
+            authoritative.handle(event).await.unwrap();
+            shadow.handle(misread).await.unwrap();
+        }
+
+        assert_eq!(stats.matched(), 1);
+        assert_eq!(stats.diverged(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_views_at_different_positions_are_skipped() {
+        let authoritative = CurrentOrdersView::new();
+        let shadow = ShadowProjection::new(
+            "current_orders_shadow",
+            Arc::new(CurrentOrdersView::new()),
+            Arc::new(authoritative.clone()),
+        )
+        .with_sample_rate(1.0);
+        let stats = shadow.stats();
+
+        // The authoritative view never sees the events
+        let events = order_events();
+        feed(&[&shadow], &events).await;
+
+        assert_eq!(stats.skipped(), 2);
+        assert_eq!(stats.matched(), 0);
+    }
+}
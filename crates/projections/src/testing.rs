@@ -0,0 +1,302 @@
+//! Golden-state tests for projections.
+//!
+//! An [`EventFixture`] is a recorded set of events, stored as a JSON file,
+//! that can be fed into any [`Projection`]. The state the view ends up in is
+//! then snapshotted as JSON and compared with a golden file by
+//! [`check_golden`], so a change to a view's arithmetic (a total, a count)
+//! shows up as a diff of the snapshot rather than as a scattered
+//! `assert_eq!` failure. Snapshots are written with object keys sorted, so
+//! the same state always renders to the same text; arrays keep the order
+//! the caller gives them.
+//!
+//! For views that answer per aggregate, [`probe_state`] builds the snapshot
+//! from their [`ShadowProbe`] answers for every aggregate in the fixture.
+//!
+//! Set `UPDATE_GOLDEN_STATE=1` to write the current state as the new golden
+//! file after an intended change. Meant for tests; nothing here is used by
+//! the views themselves.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use common::AggregateId;
+use event_store::{EventEnvelope, EventStore};
+use futures_util::TryStreamExt;
+use serde_json::Value;
+
+use crate::error::ProjectionError;
+use crate::projection::Projection;
+use crate::shadow::ShadowProbe;
+
+/// Environment variable that makes [`check_golden`] rewrite golden files.
+pub const UPDATE_GOLDEN_ENV: &str = "UPDATE_GOLDEN_STATE";
+
+/// Errors reading fixtures or checking golden files.
+#[derive(Debug, thiserror::Error)]
+pub enum GoldenError {
+    #[error("Failed to access {}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("Invalid JSON in {}: {source}", path.display())]
+    Json {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+
+    #[error("Projection error: {0}")]
+    Projection(#[from] ProjectionError),
+
+    #[error("State differs from {} (rerun with UPDATE_GOLDEN_STATE=1 to accept):\n{diff}", path.display())]
+    Mismatch { path: PathBuf, diff: String },
+}
+
+type GoldenResult<T> = std::result::Result<T, GoldenError>;
+
+/// A recorded set of events, in the order they were stored.
+#[derive(Debug, Clone, Default)]
+pub struct EventFixture {
+    events: Vec<EventEnvelope>,
+}
+
+impl EventFixture {
+    /// Creates a fixture from `events`, numbering any without a global
+    /// sequence after the one before.
+    pub fn new(events: Vec<EventEnvelope>) -> Self {
+        let mut sequence = 0;
+        let events = events
+            .into_iter()
+            .map(|mut event| {
+                sequence = event.sequence.unwrap_or(sequence + 1);
+                event.sequence = Some(sequence);
+                event
+            })
+            .collect();
+        Self { events }
+    }
+
+    /// Records every event in `store`.
+    pub async fn record<S: EventStore>(store: &S) -> crate::Result<Self> {
+        let events: Vec<EventEnvelope> = store.stream_all_events().await?.try_collect().await?;
+        Ok(Self::new(events))
+    }
+
+    /// Reads a fixture saved by [`save`](Self::save).
+    pub fn load(path: impl AsRef<Path>) -> GoldenResult<Self> {
+        let path = path.as_ref();
+        let text = read(path)?;
+        let events = serde_json::from_str(&text).map_err(|source| GoldenError::Json {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Ok(Self::new(events))
+    }
+
+    /// Writes the fixture as a JSON array of events.
+    pub fn save(&self, path: impl AsRef<Path>) -> GoldenResult<()> {
+        let path = path.as_ref();
+        let value = serde_json::to_value(&self.events).map_err(|source| GoldenError::Json {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        write(path, &render(&value))
+    }
+
+    /// Returns the events in order.
+    pub fn events(&self) -> &[EventEnvelope] {
+        &self.events
+    }
+
+    /// Returns the aggregates the events belong to, in order of first
+    /// appearance.
+    pub fn aggregate_ids(&self) -> Vec<AggregateId> {
+        let mut ids = Vec::new();
+        for event in &self.events {
+            if !ids.contains(&event.aggregate_id) {
+                ids.push(event.aggregate_id);
+            }
+        }
+        ids
+    }
+
+    /// Hands every event to `projection`, in order.
+    pub async fn feed<P: Projection + ?Sized>(&self, projection: &P) -> crate::Result<()> {
+        for event in &self.events {
+            projection.handle(event).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns what `view` reports for each of the fixture's aggregates, keyed
+/// by aggregate ID. Aggregates the view has nothing for are left out.
+pub async fn probe_state<V: ShadowProbe + ?Sized>(
+    view: &V,
+    fixture: &EventFixture,
+) -> crate::Result<Value> {
+    let mut state = BTreeMap::new();
+    for aggregate_id in fixture.aggregate_ids() {
+        let answer = view.probe(aggregate_id).await?;
+        if !answer.is_null() {
+            state.insert(aggregate_id.to_string(), answer);
+        }
+    }
+    Ok(serde_json::json!(state))
+}
+
+/// Renders `state` as pretty-printed JSON with object keys sorted.
+pub fn render(state: &Value) -> String {
+    // serde_json's maps are ordered by key unless `preserve_order` is on, so
+    // rebuild them through a BTreeMap to be sure
+    fn sorted(value: &Value) -> Value {
+        match value {
+            Value::Object(map) => {
+                let map: BTreeMap<_, _> = map.iter().map(|(k, v)| (k.clone(), sorted(v))).collect();
+                serde_json::json!(map)
+            }
+            Value::Array(items) => Value::Array(items.iter().map(sorted).collect()),
+            other => other.clone(),
+        }
+    }
+    let mut text = serde_json::to_string_pretty(&sorted(state)).unwrap_or_default();
+    text.push('\n');
+    text
+}
+
+/// Compares `state` with the golden file at `path`.
+///
+/// Writes the file instead when it doesn't exist yet or
+/// [`UPDATE_GOLDEN_ENV`] is set. A mismatch is returned with a line diff,
+/// `-` for the golden file and `+` for the current state.
+pub fn check_golden(path: impl AsRef<Path>, state: &Value) -> GoldenResult<()> {
+    let path = path.as_ref();
+    let actual = render(state);
+    if std::env::var_os(UPDATE_GOLDEN_ENV).is_some() || !path.exists() {
+        tracing::info!(path = %path.display(), "writing golden state");
+        return write(path, &actual);
+    }
+
+    let expected = read(path)?;
+    if expected == actual {
+        return Ok(());
+    }
+    Err(GoldenError::Mismatch {
+        path: path.to_path_buf(),
+        diff: line_diff(&expected, &actual),
+    })
+}
+
+/// Lists the lines only in `expected` (`-`) or only in `actual` (`+`), by
+/// longest common subsequence, with the line number in `expected` or
+/// `actual` respectively.
+fn line_diff(expected: &str, actual: &str) -> String {
+    let old: Vec<&str> = expected.lines().collect();
+    let new: Vec<&str> = actual.lines().collect();
+
+    // common[i][j]: length of the LCS of old[i..] and new[j..]
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || common[i + 1][j] >= common[i][j + 1]) {
+            diff.push_str(&format!("-{:>4} {}\n", i + 1, old[i]));
+            i += 1;
+        } else {
+            diff.push_str(&format!("+{:>4} {}\n", j + 1, new[j]));
+            j += 1;
+        }
+    }
+    diff
+}
+
+fn read(path: &Path) -> GoldenResult<String> {
+    std::fs::read_to_string(path).map_err(|source| GoldenError::Io {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+fn write(path: &Path, text: &str) -> GoldenResult<()> {
+    let io = |source| GoldenError::Io {
+        path: path.to_path_buf(),
+        source,
+    };
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(io)?;
+    }
+    std::fs::write(path, text).map_err(io)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("golden-{}", AggregateId::new()))
+            .join(name)
+    }
+
+    #[test]
+    fn test_render_sorts_keys() {
+        let state = serde_json::json!({ "b": 1, "a": { "d": [2, 1], "c": null } });
+        assert_eq!(
+            render(&state),
+            "{\n  \"a\": {\n    \"c\": null,\n    \"d\": [\n      2,\n      1\n    ]\n  },\n  \"b\": 1\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_mismatch_reports_changed_lines() {
+        let path = temp_path("state.json");
+        let golden = serde_json::json!({ "count": 2, "name": "a", "total": 300 });
+        // The first check writes the missing golden file
+        check_golden(&path, &golden).unwrap();
+        check_golden(&path, &golden).unwrap();
+
+        let changed = serde_json::json!({ "count": 2, "name": "a", "total": 350 });
+        match check_golden(&path, &changed) {
+            Err(GoldenError::Mismatch { diff, .. }) => {
+                assert_eq!(diff, "-   4   \"total\": 300\n+   4   \"total\": 350\n");
+            }
+            other => panic!("expected a mismatch, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_fixture_numbers_events_and_lists_aggregates() {
+        let (a, b) = (AggregateId::new(), AggregateId::new());
+        let event = |aggregate_id, version| {
+            EventEnvelope::builder()
+                .aggregate_id(aggregate_id)
+                .aggregate_type("Order")
+                .event_type("TestEvent")
+                .version(event_store::Version::new(version))
+                .payload_raw(serde_json::json!({}))
+                .build()
+        };
+        let fixture = EventFixture::new(vec![event(a, 1), event(b, 1), event(a, 2)]);
+        assert_eq!(fixture.aggregate_ids(), [a, b]);
+
+        let path = temp_path("events.json");
+        fixture.save(&path).unwrap();
+        let loaded = EventFixture::load(&path).unwrap();
+        let sequences: Vec<_> = loaded.events().iter().map(|e| e.sequence).collect();
+        assert_eq!(sequences, [Some(1), Some(2), Some(3)]);
+    }
+}
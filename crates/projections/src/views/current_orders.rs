@@ -1,18 +1,20 @@
 //! Current orders read model — active (non-terminal) orders.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::sync::Arc;
 
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use common::AggregateId;
-use domain::{CustomerId, Money, OrderEvent, OrderState, ProductId};
+use domain::{Currency, CustomerId, Money, OrderEvent, OrderState, ProductId};
 use event_store::EventEnvelope;
 use tokio::sync::RwLock;
 
 use crate::Result;
-use crate::projection::{Projection, ProjectionPosition};
-use crate::read_model::ReadModel;
+use crate::access::{RowPolicy, Unrestricted, visible_rows};
+use crate::projection::{Projection, ProjectionPosition, record_rollback};
+use crate::read_model::{ReadModel, map_memory_usage, read_state};
+use crate::shadow::ShadowProbe;
 
 /// Summary of an active order item.
 #[derive(Debug, Clone)]
@@ -31,17 +33,30 @@ pub struct CurrentOrderSummary {
     pub state: OrderState,
     pub item_count: usize,
     pub total_amount: Money,
+    pub currency: Currency,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub items: HashMap<ProductId, OrderItemSummary>,
+    pub tags: BTreeSet<String>,
+    /// Attachments such as a gift message or PO number.
+    pub metadata: BTreeMap<String, String>,
 }
 
 impl CurrentOrderSummary {
     fn recalculate_totals(&mut self) {
         self.item_count = self.items.len();
-        self.total_amount = self.items.values().fold(Money::zero(), |acc, item| {
-            acc + item.unit_price.multiply(item.quantity)
-        });
+        // Prices recorded before amounts carried a currency read as the
+        // default one
+        let currency = self.currency;
+        self.total_amount = self
+            .items
+            .values()
+            .fold(Money::zero_in(currency), |acc, item| {
+                acc + item
+                    .unit_price
+                    .in_currency(currency)
+                    .multiply(item.quantity)
+            });
     }
 }
 
@@ -71,7 +86,15 @@ impl CurrentOrdersView {
 
     /// Gets all active orders.
     pub async fn get_all_orders(&self) -> Vec<CurrentOrderSummary> {
-        self.orders.read().await.values().cloned().collect()
+        self.get_all_orders_for(&Unrestricted).await
+    }
+
+    /// Gets the active orders `policy` permits, masked by it.
+    pub async fn get_all_orders_for(
+        &self,
+        policy: &dyn RowPolicy<CurrentOrderSummary>,
+    ) -> Vec<CurrentOrderSummary> {
+        visible_rows(policy, self.orders.read().await.values())
     }
 
     /// Gets active orders filtered by state.
@@ -98,6 +121,29 @@ impl CurrentOrdersView {
             .cloned()
             .collect()
     }
+
+    /// Gets active orders carrying a tag.
+    pub async fn get_orders_by_tag(&self, tag: &str) -> Vec<CurrentOrderSummary> {
+        self.get_orders_by_tag_for(tag, &Unrestricted).await
+    }
+
+    /// Gets the active orders carrying a tag that `policy` permits, masked
+    /// by it.
+    pub async fn get_orders_by_tag_for(
+        &self,
+        tag: &str,
+        policy: &dyn RowPolicy<CurrentOrderSummary>,
+    ) -> Vec<CurrentOrderSummary> {
+        let tag = tag.trim().to_lowercase();
+        visible_rows(
+            policy,
+            self.orders
+                .read()
+                .await
+                .values()
+                .filter(|o| o.tags.contains(&tag)),
+        )
+    }
 }
 
 impl Default for CurrentOrdersView {
@@ -115,7 +161,7 @@ impl Projection for CurrentOrdersView {
     async fn handle(&self, event: &EventEnvelope) -> Result<()> {
         if event.aggregate_type != "Order" {
             let mut pos = self.position.write().await;
-            *pos = pos.advance();
+            *pos = pos.advance_to(event);
             return Ok(());
         }
 
@@ -133,10 +179,13 @@ impl Projection for CurrentOrdersView {
                         customer_id: data.customer_id,
                         state: OrderState::Draft,
                         item_count: 0,
-                        total_amount: Money::zero(),
+                        total_amount: Money::zero_in(data.currency),
+                        currency: data.currency,
                         created_at: data.created_at,
                         updated_at: data.created_at,
                         items: HashMap::new(),
+                        tags: BTreeSet::new(),
+                        metadata: BTreeMap::new(),
                     },
                 );
             }
@@ -171,6 +220,31 @@ impl Projection for CurrentOrdersView {
                     order.updated_at = event.timestamp;
                 }
             }
+            OrderEvent::ItemPriceAdjusted(data) => {
+                if let Some(order) = orders.get_mut(&order_id) {
+                    if let Some(item) = order.items.get_mut(&data.product_id) {
+                        item.unit_price = data.new_unit_price;
+                    }
+                    order.recalculate_totals();
+                    order.updated_at = event.timestamp;
+                }
+            }
+            OrderEvent::ItemSubstituted(data) => {
+                if let Some(order) = orders.get_mut(&order_id) {
+                    order.items.remove(&data.original_product_id);
+                    order.items.insert(
+                        data.substitute_product_id.clone(),
+                        OrderItemSummary {
+                            product_id: data.substitute_product_id,
+                            product_name: data.substitute_product_name,
+                            quantity: data.quantity,
+                            unit_price: data.unit_price,
+                        },
+                    );
+                    order.recalculate_totals();
+                    order.updated_at = data.substituted_at;
+                }
+            }
             OrderEvent::OrderSubmitted(data) => {
                 if let Some(order) = orders.get_mut(&order_id) {
                     order.state = OrderState::Draft; // Submitted is still pre-Reserved
@@ -183,6 +257,11 @@ impl Projection for CurrentOrdersView {
                     order.updated_at = data.reserved_at;
                 }
             }
+            OrderEvent::ReservationRenewed(data) => {
+                if let Some(order) = orders.get_mut(&order_id) {
+                    order.updated_at = data.renewed_at;
+                }
+            }
             OrderEvent::OrderProcessing(data) => {
                 if let Some(order) = orders.get_mut(&order_id) {
                     order.state = OrderState::Processing;
@@ -192,14 +271,68 @@ impl Projection for CurrentOrdersView {
             OrderEvent::OrderCompleted(_) | OrderEvent::OrderCancelled(_) => {
                 orders.remove(&order_id);
             }
+            // Carrier updates arrive after the order has left this view
+            OrderEvent::OrderShipped(_)
+            | OrderEvent::OrderInTransit(_)
+            | OrderEvent::OrderDelivered(_)
+            | OrderEvent::DeliveryFailed(_) => {}
+            OrderEvent::TagAdded(data) => {
+                if let Some(order) = orders.get_mut(&order_id) {
+                    order.tags.insert(data.tag);
+                    order.updated_at = data.added_at;
+                }
+            }
+            OrderEvent::TagRemoved(data) => {
+                if let Some(order) = orders.get_mut(&order_id) {
+                    order.tags.remove(&data.tag);
+                    order.updated_at = data.removed_at;
+                }
+            }
+            OrderEvent::OrderMetadataSet(data) => {
+                if let Some(order) = orders.get_mut(&order_id) {
+                    match data.value {
+                        Some(value) => order.metadata.insert(data.key, value),
+                        None => order.metadata.remove(&data.key),
+                    };
+                    order.updated_at = data.set_at;
+                }
+            }
+            OrderEvent::ShippingAddressSet(data) => {
+                if let Some(order) = orders.get_mut(&order_id) {
+                    order.updated_at = data.set_at;
+                }
+            }
+            OrderEvent::ShippingAddressUpdated(data) => {
+                if let Some(order) = orders.get_mut(&order_id) {
+                    order.updated_at = data.updated_at;
+                }
+            }
         }
 
         let mut pos = self.position.write().await;
-        *pos = pos.advance();
+        *pos = pos.advance_to(event);
 
         Ok(())
     }
 
+    async fn handle_group(&self, events: &[EventEnvelope]) -> Result<()> {
+        if let [event] = events {
+            return self.handle(event).await;
+        }
+
+        let saved_orders = self.orders.read().await.clone();
+        let saved_position = *self.position.read().await;
+        for event in events {
+            if let Err(e) = self.handle(event).await {
+                *self.orders.write().await = saved_orders;
+                *self.position.write().await = saved_position;
+                record_rollback(Projection::name(self), event, events.len());
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
     async fn position(&self) -> ProjectionPosition {
         *self.position.read().await
     }
@@ -209,16 +342,62 @@ impl Projection for CurrentOrdersView {
         *self.position.write().await = ProjectionPosition::zero();
         Ok(())
     }
+
+    async fn summary(&self) -> serde_json::Value {
+        serde_json::json!({
+            "events_processed": self.position.read().await.events_processed,
+            "orders": self.orders.read().await.len(),
+        })
+    }
 }
 
+#[async_trait]
 impl ReadModel for CurrentOrdersView {
     fn name(&self) -> &'static str {
         "CurrentOrdersView"
     }
 
-    fn count(&self) -> usize {
-        // Use try_read to avoid blocking; returns 0 if lock is held
-        self.orders.try_read().map(|o| o.len()).unwrap_or(0)
+    async fn count(&self) -> Result<usize> {
+        let orders = read_state(ReadModel::name(self), &self.orders).await?;
+        Ok(orders.len())
+    }
+
+    async fn memory_usage(&self) -> Result<usize> {
+        let orders = read_state(ReadModel::name(self), &self.orders).await?;
+        Ok(map_memory_usage(&orders)
+            + orders
+                .values()
+                .map(|order| map_memory_usage(&order.items))
+                .sum::<usize>())
+    }
+}
+
+#[async_trait]
+impl ShadowProbe for CurrentOrdersView {
+    async fn probe(&self, aggregate_id: AggregateId) -> Result<serde_json::Value> {
+        let orders = read_state(ReadModel::name(self), &self.orders).await?;
+        let Some(order) = orders.get(&aggregate_id) else {
+            return Ok(serde_json::Value::Null);
+        };
+        let mut items: Vec<_> = order.items.values().collect();
+        items.sort_by(|a, b| a.product_id.cmp(&b.product_id));
+        Ok(serde_json::json!({
+            "customer_id": order.customer_id.to_string(),
+            "state": order.state,
+            "total_cents": order.total_amount.cents(),
+            "updated_at": order.updated_at,
+            "items": items
+                .iter()
+                .map(|item| serde_json::json!({
+                    "product_id": item.product_id.as_str(),
+                    "product_name": item.product_name,
+                    "quantity": item.quantity,
+                    "unit_price_cents": item.unit_price.cents(),
+                }))
+                .collect::<Vec<_>>(),
+            "tags": order.tags,
+            "metadata": order.metadata,
+        }))
     }
 }
 
@@ -315,6 +494,35 @@ mod tests {
         assert_eq!(order.total_amount.cents(), 5000);
     }
 
+    #[tokio::test]
+    async fn test_price_adjustment_updates_total() {
+        let view = CurrentOrdersView::new();
+        let order_id = AggregateId::new();
+
+        let event = OrderEvent::order_created(order_id, CustomerId::new());
+        view.handle(&make_envelope(order_id, 1, &event))
+            .await
+            .unwrap();
+
+        let item = domain::OrderItem::new("SKU-001", "Widget", 2, Money::from_cents(1000));
+        let event = OrderEvent::item_added(&item);
+        view.handle(&make_envelope(order_id, 2, &event))
+            .await
+            .unwrap();
+
+        let event = OrderEvent::item_price_adjusted(
+            ProductId::new("SKU-001"),
+            Money::from_cents(1000),
+            Money::from_cents(1200),
+        );
+        view.handle(&make_envelope(order_id, 3, &event))
+            .await
+            .unwrap();
+
+        let order = view.get_order(order_id).await.unwrap();
+        assert_eq!(order.total_amount.cents(), 2400);
+    }
+
     #[tokio::test]
     async fn test_terminal_states_remove_order() {
         let view = CurrentOrdersView::new();
@@ -375,7 +583,7 @@ mod tests {
             .unwrap();
 
         // Reserve order2
-        let event = OrderEvent::order_reserved(None);
+        let event = OrderEvent::order_reserved(Default::default());
         view.handle(&make_envelope(order2, 2, &event))
             .await
             .unwrap();
@@ -389,6 +597,34 @@ mod tests {
         assert_eq!(reserved[0].order_id, order2);
     }
 
+    #[tokio::test]
+    async fn test_filter_by_tag() {
+        let view = CurrentOrdersView::new();
+        let order1 = AggregateId::new();
+        let order2 = AggregateId::new();
+
+        for order_id in [order1, order2] {
+            let event = OrderEvent::order_created(order_id, CustomerId::new());
+            view.handle(&make_envelope(order_id, 1, &event))
+                .await
+                .unwrap();
+        }
+        for (version, event) in [
+            (2, OrderEvent::tag_added("wholesale")),
+            (3, OrderEvent::tag_added("priority-review")),
+            (4, OrderEvent::tag_removed("priority-review")),
+        ] {
+            view.handle(&make_envelope(order1, version, &event))
+                .await
+                .unwrap();
+        }
+
+        let wholesale = view.get_orders_by_tag("Wholesale").await;
+        assert_eq!(wholesale.len(), 1);
+        assert_eq!(wholesale[0].order_id, order1);
+        assert!(view.get_orders_by_tag("priority-review").await.is_empty());
+    }
+
     #[tokio::test]
     async fn test_filter_by_customer() {
         let view = CurrentOrdersView::new();
@@ -413,6 +649,44 @@ mod tests {
         assert_eq!(c1_orders[0].order_id, order1);
     }
 
+    #[tokio::test]
+    async fn test_row_policy_filters_and_masks() {
+        struct OwnOrdersTagged(CustomerId);
+
+        impl RowPolicy<CurrentOrderSummary> for OwnOrdersTagged {
+            fn permits(&self, row: &CurrentOrderSummary) -> bool {
+                row.customer_id == self.0
+            }
+
+            fn mask(&self, row: &mut CurrentOrderSummary) {
+                row.metadata
+                    .insert("seen_by".to_string(), "policy".to_string());
+            }
+        }
+
+        let view = CurrentOrdersView::new();
+        let customer = CustomerId::new();
+        for owner in [customer, CustomerId::new()] {
+            let order_id = AggregateId::new();
+            let event = OrderEvent::order_created(order_id, owner);
+            view.handle(&make_envelope(order_id, 1, &event))
+                .await
+                .unwrap();
+        }
+
+        let orders = view.get_all_orders_for(&OwnOrdersTagged(customer)).await;
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].customer_id, customer);
+        assert_eq!(orders[0].metadata["seen_by"], "policy");
+        // The view itself is untouched
+        assert!(
+            view.get_all_orders()
+                .await
+                .iter()
+                .all(|o| o.metadata.is_empty())
+        );
+    }
+
     #[tokio::test]
     async fn test_skips_non_order_events() {
         let view = CurrentOrdersView::new();
@@ -1,17 +1,19 @@
 //! Customer orders read model — per-customer order statistics.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use common::AggregateId;
-use domain::{CustomerId, Money, OrderEvent, ProductId};
+use domain::{Currency, CustomerEvent, CustomerId, Money, OrderEvent, ProductId};
 use event_store::EventEnvelope;
 use tokio::sync::RwLock;
 
 use crate::Result;
-use crate::projection::{Projection, ProjectionPosition};
-use crate::read_model::ReadModel;
+use crate::projection::{Projection, ProjectionPosition, apply_or_rollback};
+use crate::read_model::{ReadModel, map_memory_usage, read_state};
+use crate::retention::{OverflowStore, RetentionIndex, RetentionPolicy, record_evictions};
 
 /// Per-customer order statistics.
 #[derive(Debug, Clone)]
@@ -20,49 +22,176 @@ pub struct CustomerOrdersSummary {
     pub total_orders: u64,
     pub active_orders: u64,
     pub completed_orders: u64,
+    /// Completed orders the carrier has since delivered.
+    pub delivered_orders: u64,
     pub cancelled_orders: u64,
-    pub total_spent: Money,
+    /// Spend on completed orders bucketed per order currency, never
+    /// converted.
+    pub spent_by_currency: BTreeMap<Currency, Money>,
     pub order_ids: Vec<AggregateId>,
 }
 
-/// Tracks per-order item totals for computing total_spent on completion.
+impl CustomerOrdersSummary {
+    fn new(customer_id: CustomerId) -> Self {
+        Self {
+            customer_id,
+            total_orders: 0,
+            active_orders: 0,
+            completed_orders: 0,
+            delivered_orders: 0,
+            cancelled_orders: 0,
+            spent_by_currency: BTreeMap::new(),
+            order_ids: Vec::new(),
+        }
+    }
+
+    /// Returns the amount spent in a single currency.
+    pub fn spent_in(&self, currency: Currency) -> Money {
+        self.spent_by_currency
+            .get(&currency)
+            .copied()
+            .unwrap_or_else(|| Money::zero_in(currency))
+    }
+
+    fn record_spend(&mut self, amount: Money) {
+        if amount.is_zero() {
+            return;
+        }
+        let currency = amount.currency();
+        *self
+            .spent_by_currency
+            .entry(currency)
+            .or_insert_with(|| Money::zero_in(currency)) += amount;
+    }
+}
+
+/// Tracks per-order item totals for computing spend on completion.
 #[derive(Debug, Clone)]
 struct OrderItemTracker {
+    currency: Currency,
     items: HashMap<ProductId, (u32, Money)>, // (quantity, unit_price)
 }
 
 impl OrderItemTracker {
-    fn new() -> Self {
+    fn new(currency: Currency) -> Self {
         Self {
+            currency,
             items: HashMap::new(),
         }
     }
 
+    /// Totals the items in the order's currency. Prices recorded before
+    /// amounts carried a currency read as the default one.
     fn total(&self) -> Money {
         self.items
             .values()
-            .fold(Money::zero(), |acc, (qty, price)| {
-                acc + price.multiply(*qty)
+            .fold(Money::zero_in(self.currency), |acc, (qty, price)| {
+                acc + price.in_currency(self.currency).multiply(*qty)
             })
     }
 }
 
 /// Internal state for the customer orders view.
+#[derive(Clone)]
 struct CustomerOrdersState {
     customers: HashMap<CustomerId, CustomerOrdersSummary>,
     /// Maps order_id -> customer_id for lookups.
     order_to_customer: HashMap<AggregateId, CustomerId>,
     /// Tracks items per order for computing totals.
     order_items: HashMap<AggregateId, OrderItemTracker>,
+    /// Maps merged-away customer IDs to the surviving customer.
+    merged_into: HashMap<CustomerId, CustomerId>,
+    /// Last activity of each customer with no orders in flight, for eviction.
+    retention: RetentionIndex<CustomerId>,
     position: ProjectionPosition,
 }
 
+impl CustomerOrdersState {
+    /// Returns the surviving customer for a customer ID.
+    ///
+    /// Links are flattened on merge, so a single lookup is enough.
+    fn resolve(&self, customer_id: CustomerId) -> CustomerId {
+        self.merged_into
+            .get(&customer_id)
+            .copied()
+            .unwrap_or(customer_id)
+    }
+
+    /// Consolidates a merged-away customer's stats under the survivor.
+    fn merge(&mut self, source: CustomerId, target: CustomerId) {
+        let target = self.resolve(target);
+        if target == source {
+            return;
+        }
+
+        self.merged_into.insert(source, target);
+        for survivor in self.merged_into.values_mut() {
+            if *survivor == source {
+                *survivor = target;
+            }
+        }
+        for owner in self.order_to_customer.values_mut() {
+            if *owner == source {
+                *owner = target;
+            }
+        }
+
+        if let Some(merged) = self.customers.remove(&source) {
+            let survivor = self
+                .customers
+                .entry(target)
+                .or_insert_with(|| CustomerOrdersSummary::new(target));
+            survivor.total_orders += merged.total_orders;
+            survivor.active_orders += merged.active_orders;
+            survivor.completed_orders += merged.completed_orders;
+            survivor.delivered_orders += merged.delivered_orders;
+            survivor.cancelled_orders += merged.cancelled_orders;
+            for amount in merged.spent_by_currency.into_values() {
+                survivor.record_spend(amount);
+            }
+            survivor.order_ids.extend(merged.order_ids);
+        }
+        self.retention.remove(&source);
+    }
+
+    /// Makes a customer evictable while none of its orders are in flight:
+    /// every order is cancelled or delivered.
+    fn track_retention(&mut self, customer_id: CustomerId, at: DateTime<Utc>) {
+        match self.customers.get(&customer_id) {
+            Some(customer)
+                if customer.active_orders == 0
+                    && customer.delivered_orders >= customer.completed_orders =>
+            {
+                self.retention.touch(customer_id, at);
+            }
+            _ => self.retention.remove(&customer_id),
+        }
+    }
+
+    /// Removes a customer and the per-order tracking of its orders.
+    fn remove_customer(&mut self, customer_id: CustomerId) -> Option<CustomerOrdersSummary> {
+        let customer = self.customers.remove(&customer_id)?;
+        for order_id in &customer.order_ids {
+            self.order_to_customer.remove(order_id);
+            self.order_items.remove(order_id);
+        }
+        Some(customer)
+    }
+}
+
 /// Read model view for per-customer order statistics.
 ///
 /// Tracks order counts, spending, and order IDs per customer.
+///
+/// With a [`RetentionPolicy`], customers with no orders in flight are
+/// evicted past its limits and, if an overflow store is attached, moved
+/// there. A new order or merge for an evicted customer reads it back.
+/// Listings cover only the customers held in memory.
 #[derive(Clone)]
 pub struct CustomerOrdersView {
     state: Arc<RwLock<CustomerOrdersState>>,
+    retention: RetentionPolicy,
+    overflow: Option<Arc<dyn OverflowStore<CustomerId, CustomerOrdersSummary>>>,
 }
 
 impl CustomerOrdersView {
@@ -73,14 +202,66 @@ impl CustomerOrdersView {
                 customers: HashMap::new(),
                 order_to_customer: HashMap::new(),
                 order_items: HashMap::new(),
+                merged_into: HashMap::new(),
+                retention: RetentionIndex::new(),
                 position: ProjectionPosition::zero(),
             })),
+            retention: RetentionPolicy::unbounded(),
+            overflow: None,
         }
     }
 
-    /// Gets statistics for a specific customer.
+    /// Limits how many idle customers are kept in memory.
+    pub fn with_retention(mut self, retention: RetentionPolicy) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    /// Moves evicted customers to `overflow` instead of dropping them.
+    pub fn with_overflow(
+        mut self,
+        overflow: Arc<dyn OverflowStore<CustomerId, CustomerOrdersSummary>>,
+    ) -> Self {
+        self.overflow = Some(overflow);
+        self
+    }
+
+    /// Gets statistics for a specific customer, if held in memory.
+    ///
+    /// Merged-away customer IDs resolve to the surviving customer.
     pub async fn get_customer(&self, customer_id: CustomerId) -> Option<CustomerOrdersSummary> {
-        self.state.read().await.customers.get(&customer_id).cloned()
+        let state = self.state.read().await;
+        state.customers.get(&state.resolve(customer_id)).cloned()
+    }
+
+    /// Gets statistics for a specific customer, reading the overflow store
+    /// if it was evicted.
+    pub async fn load_customer(
+        &self,
+        customer_id: CustomerId,
+    ) -> Result<Option<CustomerOrdersSummary>> {
+        let customer_id = {
+            let state = self.state.read().await;
+            let customer_id = state.resolve(customer_id);
+            if let Some(customer) = state.customers.get(&customer_id) {
+                return Ok(Some(customer.clone()));
+            }
+            customer_id
+        };
+        match &self.overflow {
+            Some(overflow) => overflow.get(&customer_id).await,
+            None => Ok(None),
+        }
+    }
+
+    /// Resolves a customer ID to the surviving customer after any merges.
+    pub async fn resolve_customer(&self, customer_id: CustomerId) -> CustomerId {
+        self.state.read().await.resolve(customer_id)
+    }
+
+    /// Gets the mapping of merged-away customer IDs to surviving customers.
+    pub async fn get_merged_customers(&self) -> HashMap<CustomerId, CustomerId> {
+        self.state.read().await.merged_into.clone()
     }
 
     /// Gets all customer statistics.
@@ -94,16 +275,65 @@ impl CustomerOrdersView {
             .collect()
     }
 
-    /// Gets the top customers by total spent, limited to `limit` results.
-    pub async fn get_top_customers(&self, limit: usize) -> Vec<CustomerOrdersSummary> {
+    /// Gets the top customers by amount spent in the given currency,
+    /// limited to `limit` results.
+    pub async fn get_top_customers(
+        &self,
+        currency: Currency,
+        limit: usize,
+    ) -> Vec<CustomerOrdersSummary> {
         let state = self.state.read().await;
         let mut customers: Vec<_> = state.customers.values().cloned().collect();
-        customers.sort_by(|a, b| b.total_spent.cents().cmp(&a.total_spent.cents()));
+        customers.sort_by_key(|c| std::cmp::Reverse(c.spent_in(currency).cents()));
         customers.truncate(limit);
         customers
     }
 }
 
+impl CustomerOrdersView {
+    /// Reads an evicted customer back from the overflow store.
+    async fn rehydrate(
+        &self,
+        state: &mut CustomerOrdersState,
+        customer_id: CustomerId,
+    ) -> Result<()> {
+        if state.customers.contains_key(&customer_id) {
+            return Ok(());
+        }
+        if let Some(overflow) = &self.overflow
+            && let Some(customer) = overflow.get(&customer_id).await?
+        {
+            state.customers.insert(customer_id, customer);
+        }
+        Ok(())
+    }
+
+    /// Evicts the customers the retention policy no longer keeps.
+    async fn evict(&self, state: &mut CustomerOrdersState, now: DateTime<Utc>) -> Result<()> {
+        if self.retention.is_unbounded() {
+            return Ok(());
+        }
+        let evicted: Vec<_> = state
+            .retention
+            .evict(&self.retention, now)
+            .into_iter()
+            .filter_map(|customer_id| {
+                state
+                    .remove_customer(customer_id)
+                    .map(|customer| (customer_id, customer))
+            })
+            .collect();
+        if evicted.is_empty() {
+            return Ok(());
+        }
+        record_evictions(Projection::name(self), evicted.len());
+        if let Some(overflow) = &self.overflow {
+            overflow.put(evicted).await?;
+        }
+        Ok(())
+    }
+}
+
 impl Default for CustomerOrdersView {
     fn default() -> Self {
         Self::new()
@@ -117,9 +347,30 @@ impl Projection for CustomerOrdersView {
     }
 
     async fn handle(&self, event: &EventEnvelope) -> Result<()> {
+        if event.aggregate_type == "Customer" {
+            let customer_event: CustomerEvent = serde_json::from_value(event.payload.clone())?;
+            let mut state = self.state.write().await;
+            match customer_event {
+                CustomerEvent::CustomerMerged(data) => {
+                    let source = state.resolve(data.customer_id);
+                    let target = state.resolve(data.merged_into);
+                    self.rehydrate(&mut state, source).await?;
+                    self.rehydrate(&mut state, target).await?;
+                    state.merge(data.customer_id, data.merged_into);
+                    state.track_retention(target, event.timestamp);
+                }
+                CustomerEvent::CustomerRegistered(_)
+                | CustomerEvent::ContactInfoUpdated(_)
+                | CustomerEvent::CustomerDeactivated(_) => {}
+            }
+            self.evict(&mut state, event.timestamp).await?;
+            state.position = state.position.advance_to(event);
+            return Ok(());
+        }
+
         if event.aggregate_type != "Order" {
             let mut state = self.state.write().await;
-            state.position = state.position.advance();
+            state.position = state.position.advance_to(event);
             return Ok(());
         }
 
@@ -130,22 +381,17 @@ impl Projection for CustomerOrdersView {
 
         match order_event {
             OrderEvent::OrderCreated(data) => {
-                let customer_id = data.customer_id;
+                let customer_id = state.resolve(data.customer_id);
+                self.rehydrate(&mut state, customer_id).await?;
                 state.order_to_customer.insert(order_id, customer_id);
-                state.order_items.insert(order_id, OrderItemTracker::new());
+                state
+                    .order_items
+                    .insert(order_id, OrderItemTracker::new(data.currency));
 
                 let entry = state
                     .customers
                     .entry(customer_id)
-                    .or_insert(CustomerOrdersSummary {
-                        customer_id,
-                        total_orders: 0,
-                        active_orders: 0,
-                        completed_orders: 0,
-                        cancelled_orders: 0,
-                        total_spent: Money::zero(),
-                        order_ids: Vec::new(),
-                    });
+                    .or_insert_with(|| CustomerOrdersSummary::new(customer_id));
                 entry.total_orders += 1;
                 entry.active_orders += 1;
                 entry.order_ids.push(order_id);
@@ -169,18 +415,33 @@ impl Projection for CustomerOrdersView {
                     entry.0 = data.new_quantity;
                 }
             }
+            OrderEvent::ItemPriceAdjusted(data) => {
+                if let Some(tracker) = state.order_items.get_mut(&order_id)
+                    && let Some(entry) = tracker.items.get_mut(&data.product_id)
+                {
+                    entry.1 = data.new_unit_price;
+                }
+            }
+            OrderEvent::ItemSubstituted(data) => {
+                if let Some(tracker) = state.order_items.get_mut(&order_id) {
+                    tracker.items.remove(&data.original_product_id);
+                    tracker
+                        .items
+                        .insert(data.substitute_product_id, (data.quantity, data.unit_price));
+                }
+            }
             OrderEvent::OrderCompleted(_) => {
                 if let Some(&customer_id) = state.order_to_customer.get(&order_id) {
                     let order_total = state
                         .order_items
                         .get(&order_id)
                         .map(|t| t.total())
-                        .unwrap_or(Money::zero());
+                        .unwrap_or_default();
 
                     if let Some(customer) = state.customers.get_mut(&customer_id) {
                         customer.active_orders = customer.active_orders.saturating_sub(1);
                         customer.completed_orders += 1;
-                        customer.total_spent = customer.total_spent.add(order_total);
+                        customer.record_spend(order_total);
                     }
                 }
             }
@@ -192,16 +453,41 @@ impl Projection for CustomerOrdersView {
                     customer.cancelled_orders += 1;
                 }
             }
-            // State transitions don't affect customer stats
+            OrderEvent::OrderDelivered(_) => {
+                if let Some(&customer_id) = state.order_to_customer.get(&order_id)
+                    && let Some(customer) = state.customers.get_mut(&customer_id)
+                {
+                    customer.delivered_orders += 1;
+                }
+            }
+            // State transitions and tags don't affect customer stats
             OrderEvent::OrderSubmitted(_)
             | OrderEvent::OrderReserved(_)
-            | OrderEvent::OrderProcessing(_) => {}
+            | OrderEvent::OrderProcessing(_)
+            | OrderEvent::OrderShipped(_)
+            | OrderEvent::OrderInTransit(_)
+            | OrderEvent::DeliveryFailed(_)
+            | OrderEvent::TagAdded(_)
+            | OrderEvent::TagRemoved(_)
+            | OrderEvent::OrderMetadataSet(_)
+            | OrderEvent::ShippingAddressSet(_)
+            | OrderEvent::ShippingAddressUpdated(_)
+            | OrderEvent::ReservationRenewed(_) => {}
+        }
+
+        if let Some(&customer_id) = state.order_to_customer.get(&order_id) {
+            state.track_retention(customer_id, event.timestamp);
         }
+        self.evict(&mut state, event.timestamp).await?;
 
-        state.position = state.position.advance();
+        state.position = state.position.advance_to(event);
         Ok(())
     }
 
+    async fn handle_group(&self, events: &[EventEnvelope]) -> Result<()> {
+        apply_or_rollback(self, &self.state, events).await
+    }
+
     async fn position(&self) -> ProjectionPosition {
         self.state.read().await.position
     }
@@ -211,21 +497,52 @@ impl Projection for CustomerOrdersView {
         state.customers.clear();
         state.order_to_customer.clear();
         state.order_items.clear();
+        state.merged_into.clear();
+        state.retention.clear();
         state.position = ProjectionPosition::zero();
+        if let Some(overflow) = &self.overflow {
+            overflow.clear().await?;
+        }
         Ok(())
     }
+
+    async fn summary(&self) -> serde_json::Value {
+        let state = self.state.read().await;
+        serde_json::json!({
+            "events_processed": state.position.events_processed,
+            "customers": state.customers.len(),
+            "tracked_orders": state.order_to_customer.len(),
+            "merged_customers": state.merged_into.len(),
+        })
+    }
 }
 
+#[async_trait]
 impl ReadModel for CustomerOrdersView {
     fn name(&self) -> &'static str {
         "CustomerOrdersView"
     }
 
-    fn count(&self) -> usize {
-        self.state
-            .try_read()
-            .map(|s| s.customers.len())
-            .unwrap_or(0)
+    async fn count(&self) -> Result<usize> {
+        let s = read_state(ReadModel::name(self), &self.state).await?;
+        Ok(s.customers.len())
+    }
+
+    async fn memory_usage(&self) -> Result<usize> {
+        let s = read_state(ReadModel::name(self), &self.state).await?;
+        Ok(map_memory_usage(&s.customers)
+            + s.customers
+                .values()
+                .map(|customer| customer.order_ids.capacity() * size_of::<AggregateId>())
+                .sum::<usize>()
+            + map_memory_usage(&s.order_to_customer)
+            + map_memory_usage(&s.order_items)
+            + s.order_items
+                .values()
+                .map(|tracker| map_memory_usage(&tracker.items))
+                .sum::<usize>()
+            + map_memory_usage(&s.merged_into)
+            + s.retention.memory_usage())
     }
 }
 
@@ -278,7 +595,7 @@ mod tests {
         assert_eq!(summary.active_orders, 1);
         assert_eq!(summary.completed_orders, 0);
         assert_eq!(summary.cancelled_orders, 0);
-        assert_eq!(summary.total_spent, Money::zero());
+        assert!(summary.spent_by_currency.is_empty());
         assert_eq!(summary.order_ids.len(), 1);
     }
 
@@ -315,7 +632,16 @@ mod tests {
         let summary = view.get_customer(customer_id).await.unwrap();
         assert_eq!(summary.active_orders, 0);
         assert_eq!(summary.completed_orders, 1);
-        assert_eq!(summary.total_spent.cents(), 2000); // 2 x $10
+        assert_eq!(summary.spent_in(Currency::USD).cents(), 2000); // 2 x $10
+        assert_eq!(summary.delivered_orders, 0);
+
+        let event = OrderEvent::order_delivered(None, chrono::Utc::now());
+        view.handle(&make_envelope(order_id, 4, &event))
+            .await
+            .unwrap();
+        let summary = view.get_customer(customer_id).await.unwrap();
+        assert_eq!(summary.completed_orders, 1);
+        assert_eq!(summary.delivered_orders, 1);
     }
 
     #[tokio::test]
@@ -334,7 +660,7 @@ mod tests {
         let summary = view.get_customer(customer_id).await.unwrap();
         assert_eq!(summary.active_orders, 0);
         assert_eq!(summary.cancelled_orders, 1);
-        assert_eq!(summary.total_spent, Money::zero()); // Not spent
+        assert!(summary.spent_by_currency.is_empty()); // Not spent
     }
 
     #[tokio::test]
@@ -379,10 +705,10 @@ mod tests {
             .await
             .unwrap();
 
-        let top = view.get_top_customers(1).await;
+        let top = view.get_top_customers(Currency::USD, 1).await;
         assert_eq!(top.len(), 1);
         assert_eq!(top[0].customer_id, customer2);
-        assert_eq!(top[0].total_spent.cents(), 5000);
+        assert_eq!(top[0].spent_in(Currency::USD).cents(), 5000);
     }
 
     #[tokio::test]
@@ -405,7 +731,34 @@ mod tests {
             .unwrap();
 
         let summary = view.get_customer(customer_id).await.unwrap();
-        assert_eq!(summary.total_spent.cents(), 5000); // 5 x $10
+        assert_eq!(summary.spent_in(Currency::USD).cents(), 5000); // 5 x $10
+    }
+
+    #[tokio::test]
+    async fn test_item_price_adjustment_affects_total_spent() {
+        let view = CustomerOrdersView::new();
+        let customer_id = CustomerId::new();
+        let order_id = AggregateId::new();
+
+        create_order_with_items(&view, order_id, customer_id).await;
+
+        // Change unit price from $10 to $7.50
+        let event = OrderEvent::item_price_adjusted(
+            ProductId::new("SKU-001"),
+            Money::from_cents(1000),
+            Money::from_cents(750),
+        );
+        view.handle(&make_envelope(order_id, 3, &event))
+            .await
+            .unwrap();
+
+        let event = OrderEvent::order_completed(None);
+        view.handle(&make_envelope(order_id, 4, &event))
+            .await
+            .unwrap();
+
+        let summary = view.get_customer(customer_id).await.unwrap();
+        assert_eq!(summary.spent_in(Currency::USD).cents(), 1500); // 2 x $7.50
     }
 
     #[tokio::test]
@@ -428,7 +781,125 @@ mod tests {
             .unwrap();
 
         let summary = view.get_customer(customer_id).await.unwrap();
-        assert_eq!(summary.total_spent, Money::zero());
+        assert!(summary.spent_by_currency.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_spend_bucketed_per_currency() {
+        let view = CustomerOrdersView::new();
+        let customer_id = CustomerId::new();
+
+        let usd_order = AggregateId::new();
+        create_order_with_items(&view, usd_order, customer_id).await;
+        let event = OrderEvent::order_completed(None);
+        view.handle(&make_envelope(usd_order, 3, &event))
+            .await
+            .unwrap();
+
+        let eur_order = AggregateId::new();
+        let item = OrderItem::new(
+            "SKU-002",
+            "Widget",
+            3,
+            Money::from_cents_in(500, Currency::EUR),
+        );
+        let events = [
+            OrderEvent::order_created_in(eur_order, customer_id, Currency::EUR),
+            OrderEvent::item_added(&item),
+            OrderEvent::order_completed(None),
+        ];
+        for (version, event) in events.iter().enumerate() {
+            view.handle(&make_envelope(eur_order, version as i64 + 1, event))
+                .await
+                .unwrap();
+        }
+
+        let summary = view.get_customer(customer_id).await.unwrap();
+        assert_eq!(summary.spent_in(Currency::USD), Money::from_cents(2000));
+        assert_eq!(
+            summary.spent_in(Currency::EUR),
+            Money::from_cents_in(1500, Currency::EUR)
+        );
+        assert_eq!(
+            summary.spent_in(Currency::GBP),
+            Money::zero_in(Currency::GBP)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_customer_merge_consolidates_stats() {
+        let view = CustomerOrdersView::new();
+        let duplicate = CustomerId::new();
+        let survivor = CustomerId::new();
+
+        // One completed order for the duplicate, one active for the survivor
+        let order1 = AggregateId::new();
+        let event = OrderEvent::order_created(order1, duplicate);
+        view.handle(&make_envelope(order1, 1, &event))
+            .await
+            .unwrap();
+        let item = OrderItem::new("SKU-001", "Widget", 2, Money::from_cents(1000));
+        view.handle(&make_envelope(order1, 2, &OrderEvent::item_added(&item)))
+            .await
+            .unwrap();
+        view.handle(&make_envelope(
+            order1,
+            3,
+            &OrderEvent::order_completed(None),
+        ))
+        .await
+        .unwrap();
+
+        let order2 = AggregateId::new();
+        let event = OrderEvent::order_created(order2, survivor);
+        view.handle(&make_envelope(order2, 1, &event))
+            .await
+            .unwrap();
+
+        let merged = CustomerEvent::customer_merged(duplicate, survivor, None);
+        let envelope = EventEnvelope::builder()
+            .aggregate_id(AggregateId::from_uuid(duplicate.as_uuid()))
+            .aggregate_type("Customer")
+            .event_type(merged.event_type())
+            .version(event_store::Version::first())
+            .payload(&merged)
+            .unwrap()
+            .build();
+        view.handle(&envelope).await.unwrap();
+
+        assert_eq!(view.get_all_customers().await.len(), 1);
+        assert_eq!(view.resolve_customer(duplicate).await, survivor);
+        assert_eq!(
+            view.get_merged_customers().await.get(&duplicate),
+            Some(&survivor)
+        );
+
+        // Old ID resolves to the merged customer
+        let summary = view.get_customer(duplicate).await.unwrap();
+        assert_eq!(summary.customer_id, survivor);
+        assert_eq!(summary.total_orders, 2);
+        assert_eq!(summary.active_orders, 1);
+        assert_eq!(summary.completed_orders, 1);
+        assert_eq!(summary.spent_in(Currency::USD).cents(), 2000);
+
+        // Later orders under the old ID count toward the survivor
+        let order3 = AggregateId::new();
+        let event = OrderEvent::order_created(order3, duplicate);
+        view.handle(&make_envelope(order3, 1, &event))
+            .await
+            .unwrap();
+        view.handle(&make_envelope(
+            order3,
+            2,
+            &OrderEvent::order_cancelled("dup", None),
+        ))
+        .await
+        .unwrap();
+
+        let summary = view.get_customer(survivor).await.unwrap();
+        assert_eq!(summary.total_orders, 3);
+        assert_eq!(summary.cancelled_orders, 1);
+        assert_eq!(summary.order_ids.len(), 3);
     }
 
     #[tokio::test]
@@ -444,4 +915,52 @@ mod tests {
         assert_eq!(view.get_all_customers().await.len(), 0);
         assert_eq!(view.position().await.events_processed, 0);
     }
+
+    #[tokio::test]
+    async fn test_retention_evicts_idle_customers_only() {
+        let overflow = Arc::new(crate::InMemoryOverflow::new());
+        let view = CustomerOrdersView::new()
+            .with_retention(RetentionPolicy::unbounded().with_max_age(chrono::Duration::days(30)))
+            .with_overflow(overflow.clone());
+        let start = Utc::now();
+        let at = |days| start + chrono::Duration::days(days);
+
+        // An idle customer whose only order was cancelled, and a customer
+        // with an order still in flight
+        let idle = CustomerId::new();
+        let idle_order = AggregateId::new();
+        create_order_with_items(&view, idle_order, idle).await;
+        let mut envelope = make_envelope(idle_order, 3, &OrderEvent::order_cancelled("x", None));
+        envelope.timestamp = at(0);
+        view.handle(&envelope).await.unwrap();
+
+        let busy = CustomerId::new();
+        create_order_with_items(&view, AggregateId::new(), busy).await;
+
+        // Any event 31 days later evicts the idle customer
+        let later_order = AggregateId::new();
+        let mut envelope = make_envelope(
+            later_order,
+            1,
+            &OrderEvent::order_created(later_order, CustomerId::new()),
+        );
+        envelope.timestamp = at(31);
+        view.handle(&envelope).await.unwrap();
+
+        assert!(view.get_customer(idle).await.is_none());
+        assert!(view.get_customer(busy).await.is_some());
+        let evicted = view.load_customer(idle).await.unwrap().unwrap();
+        assert_eq!(evicted.cancelled_orders, 1);
+        assert_eq!(overflow.entry_count().await, 1);
+
+        // A new order brings the customer back with its history
+        let new_order = AggregateId::new();
+        let mut envelope = make_envelope(new_order, 1, &OrderEvent::order_created(new_order, idle));
+        envelope.timestamp = at(32);
+        view.handle(&envelope).await.unwrap();
+        let customer = view.get_customer(idle).await.unwrap();
+        assert_eq!(customer.total_orders, 2);
+        assert_eq!(customer.cancelled_orders, 1);
+        assert!(ReadModel::memory_usage(&view).await.unwrap() > 0);
+    }
 }
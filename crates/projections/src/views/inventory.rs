@@ -1,17 +1,18 @@
 //! Inventory read model — product demand aggregated across orders.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 
 use async_trait::async_trait;
 use common::AggregateId;
-use domain::{Money, OrderEvent, ProductId};
+use domain::{Currency, Money, OrderEvent, ProductId};
 use event_store::EventEnvelope;
 use tokio::sync::RwLock;
 
 use crate::Result;
-use crate::projection::{Projection, ProjectionPosition};
-use crate::read_model::ReadModel;
+use crate::exchange::ExchangeRateProvider;
+use crate::projection::{Projection, ProjectionPosition, apply_or_rollback};
+use crate::read_model::{ReadModel, map_memory_usage, read_state};
 
 /// Product demand summary aggregated across all orders.
 #[derive(Debug, Clone)]
@@ -22,10 +23,35 @@ pub struct ProductDemand {
     pub quantity_in_active_orders: u64,
     pub quantity_reserved: u64,
     pub quantity_completed: u64,
-    pub total_revenue: Money,
+    /// Completed revenue bucketed per order currency, never converted.
+    pub revenue_by_currency: BTreeMap<Currency, Money>,
     pub order_count: u64,
 }
 
+impl ProductDemand {
+    /// Returns the revenue earned in a single currency.
+    pub fn revenue_in(&self, currency: Currency) -> Money {
+        self.revenue_by_currency
+            .get(&currency)
+            .copied()
+            .unwrap_or_else(|| Money::zero_in(currency))
+    }
+
+    /// Returns the revenue across all currencies, converted into `target`.
+    pub fn consolidated_revenue(
+        &self,
+        target: Currency,
+        rates: &dyn ExchangeRateProvider,
+    ) -> Result<Money> {
+        self.revenue_by_currency.iter().try_fold(
+            Money::zero_in(target),
+            |total, (currency, amount)| {
+                Ok(total.checked_add(rates.convert(*amount, *currency, target)?)?)
+            },
+        )
+    }
+}
+
 /// Tracks the state of each order for proper accounting on terminal events.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum OrderStatus {
@@ -36,6 +62,7 @@ enum OrderStatus {
 }
 
 /// Internal state for the inventory view.
+#[derive(Clone)]
 struct InventoryState {
     products: HashMap<ProductId, ProductDemand>,
     /// Per-order, per-product tracking: (quantity, unit_price).
@@ -44,6 +71,10 @@ struct InventoryState {
     order_product_sets: HashMap<AggregateId, Vec<ProductId>>,
     /// Tracks order status for state transitions.
     order_status: HashMap<AggregateId, OrderStatus>,
+    /// Currency each order is denominated in.
+    order_currency: HashMap<AggregateId, Currency>,
+    /// Reservation ID held per line item, for orders still reserved.
+    order_reservations: HashMap<AggregateId, BTreeMap<ProductId, String>>,
     position: ProjectionPosition,
 }
 
@@ -65,6 +96,8 @@ impl InventoryView {
                 order_products: HashMap::new(),
                 order_product_sets: HashMap::new(),
                 order_status: HashMap::new(),
+                order_currency: HashMap::new(),
+                order_reservations: HashMap::new(),
                 position: ProjectionPosition::zero(),
             })),
         }
@@ -75,6 +108,23 @@ impl InventoryView {
         self.state.read().await.products.get(product_id).cloned()
     }
 
+    /// Gets the reservation ID held for each line item of an order.
+    ///
+    /// Empty once the order completes or is cancelled, since its
+    /// reservations are no longer outstanding.
+    pub async fn get_order_reservations(
+        &self,
+        order_id: AggregateId,
+    ) -> BTreeMap<ProductId, String> {
+        self.state
+            .read()
+            .await
+            .order_reservations
+            .get(&order_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
     /// Gets all products.
     pub async fn get_all_products(&self) -> Vec<ProductDemand> {
         self.state.read().await.products.values().cloned().collect()
@@ -84,19 +134,52 @@ impl InventoryView {
     pub async fn get_top_products_by_demand(&self, limit: usize) -> Vec<ProductDemand> {
         let state = self.state.read().await;
         let mut products: Vec<_> = state.products.values().cloned().collect();
-        products.sort_by(|a, b| b.total_quantity_ordered.cmp(&a.total_quantity_ordered));
+        products.sort_by_key(|p| std::cmp::Reverse(p.total_quantity_ordered));
         products.truncate(limit);
         products
     }
 
-    /// Gets top products by total revenue.
-    pub async fn get_top_products_by_revenue(&self, limit: usize) -> Vec<ProductDemand> {
+    /// Gets top products by revenue earned in the given currency.
+    pub async fn get_top_products_by_revenue(
+        &self,
+        currency: Currency,
+        limit: usize,
+    ) -> Vec<ProductDemand> {
         let state = self.state.read().await;
         let mut products: Vec<_> = state.products.values().cloned().collect();
-        products.sort_by(|a, b| b.total_revenue.cents().cmp(&a.total_revenue.cents()));
+        products.sort_by_key(|p| std::cmp::Reverse(p.revenue_in(currency).cents()));
         products.truncate(limit);
         products
     }
+
+    /// Gets total revenue per currency across all products.
+    pub async fn get_revenue_by_currency(&self) -> BTreeMap<Currency, Money> {
+        let state = self.state.read().await;
+        let mut totals = BTreeMap::new();
+        for product in state.products.values() {
+            for (currency, amount) in &product.revenue_by_currency {
+                *totals
+                    .entry(*currency)
+                    .or_insert_with(|| Money::zero_in(*currency)) += *amount;
+            }
+        }
+        totals
+    }
+
+    /// Gets total revenue across all currencies, converted into `target`
+    /// at query time.
+    pub async fn get_consolidated_revenue(
+        &self,
+        target: Currency,
+        rates: &dyn ExchangeRateProvider,
+    ) -> Result<Money> {
+        self.get_revenue_by_currency().await.into_iter().try_fold(
+            Money::zero_in(target),
+            |total, (currency, amount)| {
+                Ok(total.checked_add(rates.convert(amount, currency, target)?)?)
+            },
+        )
+    }
 }
 
 impl Default for InventoryView {
@@ -114,7 +197,7 @@ impl Projection for InventoryView {
     async fn handle(&self, event: &EventEnvelope) -> Result<()> {
         if event.aggregate_type != "Order" {
             let mut state = self.state.write().await;
-            state.position = state.position.advance();
+            state.position = state.position.advance_to(event);
             return Ok(());
         }
 
@@ -124,7 +207,8 @@ impl Projection for InventoryView {
         let mut state = self.state.write().await;
 
         match order_event {
-            OrderEvent::OrderCreated(_) => {
+            OrderEvent::OrderCreated(data) => {
+                state.order_currency.insert(order_id, data.currency);
                 state.order_products.insert(order_id, HashMap::new());
                 state.order_product_sets.insert(order_id, Vec::new());
                 state.order_status.insert(order_id, OrderStatus::Active);
@@ -157,7 +241,7 @@ impl Projection for InventoryView {
                         quantity_in_active_orders: 0,
                         quantity_reserved: 0,
                         quantity_completed: 0,
-                        total_revenue: Money::zero(),
+                        revenue_by_currency: BTreeMap::new(),
                         order_count: 0,
                     });
                 demand.total_quantity_ordered += data.quantity as u64;
@@ -199,6 +283,9 @@ impl Projection for InventoryView {
                 if let Some(set) = state.order_product_sets.get_mut(&order_id) {
                     set.retain(|p| *p != data.product_id);
                 }
+                if let Some(reservations) = state.order_reservations.get_mut(&order_id) {
+                    reservations.remove(&data.product_id);
+                }
             }
             OrderEvent::ItemQuantityUpdated(data) => {
                 let order_status = state
@@ -237,8 +324,92 @@ impl Projection for InventoryView {
                     }
                 }
             }
-            OrderEvent::OrderReserved(_) => {
+            OrderEvent::ItemPriceAdjusted(data) => {
+                // Revenue is booked at completion from the adjusted price
+                if let Some(entry) = state
+                    .order_products
+                    .get_mut(&order_id)
+                    .and_then(|m| m.get_mut(&data.product_id))
+                {
+                    entry.1 = data.new_unit_price;
+                }
+            }
+            OrderEvent::ItemSubstituted(data) => {
+                let order_status = state
+                    .order_status
+                    .get(&order_id)
+                    .copied()
+                    .unwrap_or(OrderStatus::Active);
+                let qty = data.quantity as u64;
+
+                // The original's demand moves to the substitute, in the same
+                // bucket, since the substitute ships in its place
+                let removed = state
+                    .order_products
+                    .get_mut(&order_id)
+                    .and_then(|m| m.remove(&data.original_product_id));
+                if let Some((old_qty, _price)) = removed
+                    && let Some(demand) = state.products.get_mut(&data.original_product_id)
+                {
+                    demand.total_quantity_ordered =
+                        demand.total_quantity_ordered.saturating_sub(old_qty as u64);
+                    match order_status {
+                        OrderStatus::Active => {
+                            demand.quantity_in_active_orders = demand
+                                .quantity_in_active_orders
+                                .saturating_sub(old_qty as u64);
+                        }
+                        OrderStatus::Reserved => {
+                            demand.quantity_reserved =
+                                demand.quantity_reserved.saturating_sub(old_qty as u64);
+                        }
+                        _ => {}
+                    }
+                    demand.order_count = demand.order_count.saturating_sub(1);
+                }
+
+                state.order_products.entry(order_id).or_default().insert(
+                    data.substitute_product_id.clone(),
+                    (data.quantity, data.unit_price),
+                );
+                if let Some(set) = state.order_product_sets.get_mut(&order_id) {
+                    set.retain(|p| *p != data.original_product_id);
+                    set.push(data.substitute_product_id.clone());
+                }
+
+                let demand = state
+                    .products
+                    .entry(data.substitute_product_id.clone())
+                    .or_insert(ProductDemand {
+                        product_id: data.substitute_product_id.clone(),
+                        product_name: data.substitute_product_name.clone(),
+                        total_quantity_ordered: 0,
+                        quantity_in_active_orders: 0,
+                        quantity_reserved: 0,
+                        quantity_completed: 0,
+                        revenue_by_currency: BTreeMap::new(),
+                        order_count: 0,
+                    });
+                demand.total_quantity_ordered += qty;
+                match order_status {
+                    OrderStatus::Active => demand.quantity_in_active_orders += qty,
+                    OrderStatus::Reserved => demand.quantity_reserved += qty,
+                    _ => {}
+                }
+                demand.order_count += 1;
+
+                if let Some(reservations) = state.order_reservations.get_mut(&order_id) {
+                    reservations.remove(&data.original_product_id);
+                    if let Some(reservation_id) = data.reservation_id {
+                        reservations.insert(data.substitute_product_id, reservation_id);
+                    }
+                }
+            }
+            OrderEvent::OrderReserved(data) => {
                 state.order_status.insert(order_id, OrderStatus::Reserved);
+                if !data.reservations.is_empty() {
+                    state.order_reservations.insert(order_id, data.reservations);
+                }
 
                 // Clone the order products to avoid borrow conflict
                 let order_items: Vec<_> = state
@@ -255,6 +426,13 @@ impl Projection for InventoryView {
                     }
                 }
             }
+            OrderEvent::ReservationRenewed(data) => {
+                state
+                    .order_reservations
+                    .entry(order_id)
+                    .or_default()
+                    .insert(data.product_id, data.reservation_id);
+            }
             OrderEvent::OrderCompleted(_) => {
                 let prev_status = state
                     .order_status
@@ -262,6 +440,12 @@ impl Projection for InventoryView {
                     .copied()
                     .unwrap_or(OrderStatus::Active);
                 state.order_status.insert(order_id, OrderStatus::Completed);
+                state.order_reservations.remove(&order_id);
+                let currency = state
+                    .order_currency
+                    .get(&order_id)
+                    .copied()
+                    .unwrap_or_default();
 
                 let order_items: Vec<_> = state
                     .order_products
@@ -283,7 +467,11 @@ impl Projection for InventoryView {
                             _ => {}
                         }
                         demand.quantity_completed += qty as u64;
-                        demand.total_revenue = demand.total_revenue.add(unit_price.multiply(qty));
+                        *demand
+                            .revenue_by_currency
+                            .entry(currency)
+                            .or_insert_with(|| Money::zero_in(currency)) +=
+                            unit_price.in_currency(currency).multiply(qty);
                     }
                 }
             }
@@ -294,6 +482,7 @@ impl Projection for InventoryView {
                     .copied()
                     .unwrap_or(OrderStatus::Active);
                 state.order_status.insert(order_id, OrderStatus::Cancelled);
+                state.order_reservations.remove(&order_id);
 
                 let order_items: Vec<_> = state
                     .order_products
@@ -321,13 +510,27 @@ impl Projection for InventoryView {
                 }
             }
             // Submitted and Processing don't change inventory
-            OrderEvent::OrderSubmitted(_) | OrderEvent::OrderProcessing(_) => {}
+            OrderEvent::OrderSubmitted(_)
+            | OrderEvent::OrderProcessing(_)
+            | OrderEvent::OrderShipped(_)
+            | OrderEvent::OrderInTransit(_)
+            | OrderEvent::OrderDelivered(_)
+            | OrderEvent::DeliveryFailed(_)
+            | OrderEvent::TagAdded(_)
+            | OrderEvent::TagRemoved(_)
+            | OrderEvent::OrderMetadataSet(_)
+            | OrderEvent::ShippingAddressSet(_)
+            | OrderEvent::ShippingAddressUpdated(_) => {}
         }
 
-        state.position = state.position.advance();
+        state.position = state.position.advance_to(event);
         Ok(())
     }
 
+    async fn handle_group(&self, events: &[EventEnvelope]) -> Result<()> {
+        apply_or_rollback(self, &self.state, events).await
+    }
+
     async fn position(&self) -> ProjectionPosition {
         self.state.read().await.position
     }
@@ -338,24 +541,60 @@ impl Projection for InventoryView {
         state.order_products.clear();
         state.order_product_sets.clear();
         state.order_status.clear();
+        state.order_currency.clear();
+        state.order_reservations.clear();
         state.position = ProjectionPosition::zero();
         Ok(())
     }
+
+    async fn summary(&self) -> serde_json::Value {
+        let state = self.state.read().await;
+        serde_json::json!({
+            "events_processed": state.position.events_processed,
+            "products": state.products.len(),
+            "tracked_orders": state.order_status.len(),
+        })
+    }
 }
 
+#[async_trait]
 impl ReadModel for InventoryView {
     fn name(&self) -> &'static str {
         "InventoryView"
     }
 
-    fn count(&self) -> usize {
-        self.state.try_read().map(|s| s.products.len()).unwrap_or(0)
+    async fn count(&self) -> Result<usize> {
+        let s = read_state(ReadModel::name(self), &self.state).await?;
+        Ok(s.products.len())
+    }
+
+    async fn memory_usage(&self) -> Result<usize> {
+        let s = read_state(ReadModel::name(self), &self.state).await?;
+        Ok(map_memory_usage(&s.products)
+            + map_memory_usage(&s.order_products)
+            + s.order_products
+                .values()
+                .map(map_memory_usage)
+                .sum::<usize>()
+            + map_memory_usage(&s.order_product_sets)
+            + s.order_product_sets
+                .values()
+                .map(|products| products.capacity() * size_of::<ProductId>())
+                .sum::<usize>()
+            + map_memory_usage(&s.order_status)
+            + map_memory_usage(&s.order_currency)
+            + map_memory_usage(&s.order_reservations)
+            + s.order_reservations
+                .values()
+                .map(|lines| lines.len() * (size_of::<ProductId>() + size_of::<String>()))
+                .sum::<usize>())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::exchange::FixedExchangeRates;
     use domain::{CustomerId, DomainEvent, OrderItem};
 
     fn make_envelope(aggregate_id: AggregateId, version: i64, event: &OrderEvent) -> EventEnvelope {
@@ -396,7 +635,7 @@ mod tests {
         assert_eq!(demand.quantity_reserved, 0);
         assert_eq!(demand.quantity_completed, 0);
         assert_eq!(demand.order_count, 1);
-        assert_eq!(demand.total_revenue, Money::zero());
+        assert!(demand.revenue_by_currency.is_empty());
     }
 
     #[tokio::test]
@@ -422,7 +661,7 @@ mod tests {
 
         create_order_with_items(&view, order_id).await;
 
-        let event = OrderEvent::order_reserved(None);
+        let event = OrderEvent::order_reserved(BTreeMap::new());
         view.handle(&make_envelope(order_id, 3, &event))
             .await
             .unwrap();
@@ -433,6 +672,86 @@ mod tests {
         assert_eq!(demand.total_quantity_ordered, 2);
     }
 
+    #[tokio::test]
+    async fn test_substitution_moves_reserved_demand() {
+        let view = InventoryView::new();
+        let order_id = AggregateId::new();
+
+        create_order_with_items(&view, order_id).await;
+        let reservations = BTreeMap::from([(ProductId::new("SKU-001"), "RES-0001".to_string())]);
+        let event = OrderEvent::order_reserved(reservations);
+        view.handle(&make_envelope(order_id, 3, &event))
+            .await
+            .unwrap();
+
+        let original = OrderItem::new("SKU-001", "Widget", 2, Money::from_cents(1000));
+        let substitute = OrderItem::new("SKU-003", "Widget Plus", 2, Money::from_cents(1200));
+        let event =
+            OrderEvent::item_substituted(&original, &substitute, Some("RES-0002".to_string()));
+        view.handle(&make_envelope(order_id, 4, &event))
+            .await
+            .unwrap();
+
+        let demand = view.get_product(&ProductId::new("SKU-001")).await.unwrap();
+        assert_eq!(demand.total_quantity_ordered, 0);
+        assert_eq!(demand.quantity_reserved, 0);
+        assert_eq!(demand.order_count, 0);
+        let demand = view.get_product(&ProductId::new("SKU-003")).await.unwrap();
+        assert_eq!(demand.product_name, "Widget Plus");
+        assert_eq!(demand.total_quantity_ordered, 2);
+        assert_eq!(demand.quantity_reserved, 2);
+        assert_eq!(
+            view.get_order_reservations(order_id).await,
+            BTreeMap::from([(ProductId::new("SKU-003"), "RES-0002".to_string())])
+        );
+
+        // Revenue is booked at the substitute's price
+        let event = OrderEvent::order_completed(None);
+        view.handle(&make_envelope(order_id, 5, &event))
+            .await
+            .unwrap();
+        let demand = view.get_product(&ProductId::new("SKU-003")).await.unwrap();
+        assert_eq!(demand.quantity_completed, 2);
+        assert_eq!(
+            demand.revenue_in(Currency::default()),
+            Money::from_cents(2400)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reserved_tracks_line_item_reservations() {
+        let view = InventoryView::new();
+        let order_id = AggregateId::new();
+
+        create_order_with_items(&view, order_id).await;
+
+        let reservations = BTreeMap::from([(ProductId::new("SKU-001"), "RES-0001".to_string())]);
+        let event = OrderEvent::order_reserved(reservations.clone());
+        view.handle(&make_envelope(order_id, 3, &event))
+            .await
+            .unwrap();
+        assert_eq!(view.get_order_reservations(order_id).await, reservations);
+
+        let event = OrderEvent::reservation_renewed(
+            ProductId::new("SKU-001"),
+            "RES-0002",
+            Some("RES-0001".to_string()),
+        );
+        view.handle(&make_envelope(order_id, 4, &event))
+            .await
+            .unwrap();
+        assert_eq!(
+            view.get_order_reservations(order_id).await,
+            BTreeMap::from([(ProductId::new("SKU-001"), "RES-0002".to_string())])
+        );
+
+        let event = OrderEvent::order_cancelled("Payment failed", None);
+        view.handle(&make_envelope(order_id, 5, &event))
+            .await
+            .unwrap();
+        assert!(view.get_order_reservations(order_id).await.is_empty());
+    }
+
     #[tokio::test]
     async fn test_completed_updates_revenue() {
         let view = InventoryView::new();
@@ -441,7 +760,7 @@ mod tests {
         create_order_with_items(&view, order_id).await;
 
         // Reserve then complete
-        let event = OrderEvent::order_reserved(None);
+        let event = OrderEvent::order_reserved(BTreeMap::new());
         view.handle(&make_envelope(order_id, 3, &event))
             .await
             .unwrap();
@@ -454,10 +773,35 @@ mod tests {
         let demand = view.get_product(&ProductId::new("SKU-001")).await.unwrap();
         assert_eq!(demand.quantity_reserved, 0);
         assert_eq!(demand.quantity_completed, 2);
-        assert_eq!(demand.total_revenue.cents(), 2000);
+        assert_eq!(demand.revenue_in(Currency::USD).cents(), 2000);
         assert_eq!(demand.total_quantity_ordered, 2);
     }
 
+    #[tokio::test]
+    async fn test_price_adjustment_updates_revenue() {
+        let view = InventoryView::new();
+        let order_id = AggregateId::new();
+
+        create_order_with_items(&view, order_id).await;
+
+        let event = OrderEvent::item_price_adjusted(
+            ProductId::new("SKU-001"),
+            Money::from_cents(1000),
+            Money::from_cents(1500),
+        );
+        view.handle(&make_envelope(order_id, 3, &event))
+            .await
+            .unwrap();
+
+        let event = OrderEvent::order_completed(None);
+        view.handle(&make_envelope(order_id, 4, &event))
+            .await
+            .unwrap();
+
+        let demand = view.get_product(&ProductId::new("SKU-001")).await.unwrap();
+        assert_eq!(demand.revenue_in(Currency::USD).cents(), 3000);
+    }
+
     #[tokio::test]
     async fn test_cancelled_removes_demand() {
         let view = InventoryView::new();
@@ -569,10 +913,65 @@ mod tests {
             .await
             .unwrap();
 
-        let top = view.get_top_products_by_revenue(1).await;
+        let top = view.get_top_products_by_revenue(Currency::USD, 1).await;
         assert_eq!(top.len(), 1);
         assert_eq!(top[0].product_id, ProductId::new("SKU-002"));
-        assert_eq!(top[0].total_revenue.cents(), 15000);
+        assert_eq!(top[0].revenue_in(Currency::USD).cents(), 15000);
+    }
+
+    #[tokio::test]
+    async fn test_revenue_bucketed_per_currency() {
+        let view = InventoryView::new();
+        let usd_order = AggregateId::new();
+        let eur_order = AggregateId::new();
+
+        create_order_with_items(&view, usd_order).await;
+
+        let event = OrderEvent::order_created_in(eur_order, CustomerId::new(), Currency::EUR);
+        view.handle(&make_envelope(eur_order, 1, &event))
+            .await
+            .unwrap();
+        let item = OrderItem::new("SKU-001", "Widget", 3, Money::from_cents(800));
+        let event = OrderEvent::item_added(&item);
+        view.handle(&make_envelope(eur_order, 2, &event))
+            .await
+            .unwrap();
+
+        for order_id in [usd_order, eur_order] {
+            let event = OrderEvent::order_completed(None);
+            view.handle(&make_envelope(order_id, 3, &event))
+                .await
+                .unwrap();
+        }
+
+        let demand = view.get_product(&ProductId::new("SKU-001")).await.unwrap();
+        assert_eq!(demand.revenue_in(Currency::USD).cents(), 2000);
+        assert_eq!(demand.revenue_in(Currency::EUR).cents(), 2400);
+        assert_eq!(demand.revenue_in(Currency::GBP).cents(), 0);
+
+        let totals = view.get_revenue_by_currency().await;
+        assert_eq!(totals.len(), 2);
+
+        // Conversion only happens at query time
+        let rates = FixedExchangeRates::new().with_rate(Currency::EUR, Currency::USD, 1.5);
+        let consolidated = view
+            .get_consolidated_revenue(Currency::USD, &rates)
+            .await
+            .unwrap();
+        assert_eq!(consolidated.cents(), 2000 + 3600);
+        assert_eq!(
+            demand
+                .consolidated_revenue(Currency::USD, &rates)
+                .unwrap()
+                .cents(),
+            5600
+        );
+
+        let result = view.get_consolidated_revenue(Currency::GBP, &rates).await;
+        assert!(matches!(
+            result,
+            Err(crate::ProjectionError::MissingExchangeRate { .. })
+        ));
     }
 
     #[tokio::test]
@@ -4,8 +4,14 @@ pub mod current_orders;
 pub mod customer_orders;
 pub mod inventory;
 pub mod order_history;
+pub mod oversell;
+pub mod stock_levels;
 
 pub use current_orders::CurrentOrdersView;
 pub use customer_orders::CustomerOrdersView;
 pub use inventory::InventoryView;
-pub use order_history::OrderHistoryView;
+pub use order_history::{HistoryPage, HistoryQuery, OrderHistoryView};
+pub use oversell::{
+    InMemoryOversellAlerter, Oversell, OversellAlert, OversellAlerter, OversellDetectionView,
+};
+pub use stock_levels::{StockLevel, StockLevelsView};
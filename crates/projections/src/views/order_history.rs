@@ -1,18 +1,22 @@
 //! Order history read model — completed and cancelled orders.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ops::Bound;
 use std::sync::Arc;
 
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use common::AggregateId;
-use domain::{CustomerId, Money, OrderEvent, OrderState, ProductId};
+use domain::{Address, Currency, CustomerId, Money, OrderEvent, OrderState, ProductId};
 use event_store::EventEnvelope;
 use tokio::sync::RwLock;
 
 use crate::Result;
-use crate::projection::{Projection, ProjectionPosition};
-use crate::read_model::ReadModel;
+use crate::access::{RowPolicy, Unrestricted, masked};
+use crate::projection::{Projection, ProjectionPosition, apply_or_rollback};
+use crate::read_model::{ReadModel, map_memory_usage, read_state};
+use crate::retention::{OverflowStore, RetentionIndex, RetentionPolicy, record_evictions};
+use crate::shadow::ShadowProbe;
 
 /// An item in a historical order.
 #[derive(Debug, Clone)]
@@ -21,6 +25,10 @@ pub struct HistoryItemSummary {
     pub product_name: String,
     pub quantity: u32,
     pub unit_price: Money,
+    /// Serial numbers of the units shipped, recorded at completion.
+    pub serial_numbers: Vec<String>,
+    /// Lot the units shipped from, recorded at completion.
+    pub lot_number: Option<String>,
 }
 
 /// Summary of a completed or cancelled order.
@@ -31,44 +39,178 @@ pub struct OrderHistorySummary {
     pub state: OrderState,
     pub item_count: usize,
     pub total_amount: Money,
+    pub currency: Currency,
     pub created_at: DateTime<Utc>,
     pub completed_at: Option<DateTime<Utc>>,
     pub cancelled_at: Option<DateTime<Utc>>,
     pub tracking_number: Option<String>,
     pub cancellation_reason: Option<String>,
+    pub carrier: Option<String>,
+    /// Last location reported by the carrier.
+    pub last_location: Option<String>,
+    pub delivered_at: Option<DateTime<Utc>>,
+    /// Reason for the most recent failed delivery attempt.
+    pub delivery_failure_reason: Option<String>,
+    /// Address the order shipped, or was to ship, to.
+    pub shipping_address: Option<Address>,
     pub items: HashMap<ProductId, HistoryItemSummary>,
+    pub tags: BTreeSet<String>,
+    /// Attachments such as a gift message or PO number.
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl OrderHistorySummary {
+    /// Returns when the order reached history: its completion or
+    /// cancellation time.
+    pub fn finished_at(&self) -> DateTime<Utc> {
+        self.completed_at
+            .or(self.cancelled_at)
+            .unwrap_or(self.created_at)
+    }
+}
+
+/// Filters and paging for [`OrderHistoryView::query_customer_history`].
+#[derive(Debug, Clone, Default)]
+pub struct HistoryQuery {
+    /// Earliest finish time to include.
+    pub from: Option<DateTime<Utc>>,
+    /// Finish time to stop before.
+    pub to: Option<DateTime<Utc>>,
+    /// Only orders currently in this state.
+    pub state: Option<OrderState>,
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+/// A page of historical orders, oldest finish first.
+#[derive(Debug, Clone)]
+pub struct HistoryPage {
+    pub orders: Vec<OrderHistorySummary>,
+    /// Matching orders across all pages.
+    pub total: usize,
 }
 
 /// Staging data for an order being built up before it reaches terminal state.
 #[derive(Debug, Clone)]
 struct StagingOrder {
     customer_id: CustomerId,
+    currency: Currency,
     created_at: DateTime<Utc>,
+    shipping_address: Option<Address>,
     items: HashMap<ProductId, HistoryItemSummary>,
+    tags: BTreeSet<String>,
+    metadata: BTreeMap<String, String>,
 }
 
 impl StagingOrder {
     fn total_amount(&self) -> Money {
-        self.items.values().fold(Money::zero(), |acc, item| {
-            acc + item.unit_price.multiply(item.quantity)
-        })
+        // Prices recorded before amounts carried a currency read as the
+        // default one
+        self.items
+            .values()
+            .fold(Money::zero_in(self.currency), |acc, item| {
+                acc + item
+                    .unit_price
+                    .in_currency(self.currency)
+                    .multiply(item.quantity)
+            })
+    }
+}
+
+/// History entries ordered by finish time.
+#[derive(Debug, Clone, Default)]
+struct FinishedIndex {
+    entries: BTreeMap<DateTime<Utc>, Vec<AggregateId>>,
+}
+
+impl FinishedIndex {
+    fn insert(&mut self, finished_at: DateTime<Utc>, order_id: AggregateId) {
+        self.entries.entry(finished_at).or_default().push(order_id);
+    }
+
+    fn remove(&mut self, finished_at: DateTime<Utc>, order_id: AggregateId) {
+        if let Some(ids) = self.entries.get_mut(&finished_at) {
+            ids.retain(|id| *id != order_id);
+            if ids.is_empty() {
+                self.entries.remove(&finished_at);
+            }
+        }
+    }
+
+    /// Iterates the orders finished in `[from, to)`, oldest first.
+    fn range(
+        &self,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> impl Iterator<Item = AggregateId> + '_ {
+        let from = from.map_or(Bound::Unbounded, Bound::Included);
+        let to = to.map_or(Bound::Unbounded, Bound::Excluded);
+        // BTreeMap::range panics on an inverted range
+        let empty = matches!((from, to), (Bound::Included(f), Bound::Excluded(t)) if f >= t);
+        (!empty)
+            .then(|| self.entries.range((from, to)))
+            .into_iter()
+            .flatten()
+            .flat_map(|(_, ids)| ids.iter().copied())
     }
 }
 
 /// Internal state for the order history view.
+#[derive(Clone)]
 struct OrderHistoryState {
     staging: HashMap<AggregateId, StagingOrder>,
     history: HashMap<AggregateId, OrderHistorySummary>,
+    /// Each customer's history entries by finish time.
+    by_customer: HashMap<CustomerId, FinishedIndex>,
+    /// Last activity of each history entry, for eviction.
+    retention: RetentionIndex<AggregateId>,
     position: ProjectionPosition,
 }
 
+impl OrderHistoryState {
+    /// Adds an entry to history and its customer's index.
+    fn insert_history(&mut self, summary: OrderHistorySummary) {
+        let order_id = summary.order_id;
+        if let Some(old) = self.history.remove(&order_id) {
+            self.unindex(&old);
+        }
+        self.by_customer
+            .entry(summary.customer_id)
+            .or_default()
+            .insert(summary.finished_at(), order_id);
+        self.history.insert(order_id, summary);
+    }
+
+    /// Removes an entry from history and its customer's index.
+    fn remove_history(&mut self, order_id: AggregateId) -> Option<OrderHistorySummary> {
+        let summary = self.history.remove(&order_id)?;
+        self.unindex(&summary);
+        Some(summary)
+    }
+
+    fn unindex(&mut self, summary: &OrderHistorySummary) {
+        if let Some(index) = self.by_customer.get_mut(&summary.customer_id) {
+            index.remove(summary.finished_at(), summary.order_id);
+            if index.entries.is_empty() {
+                self.by_customer.remove(&summary.customer_id);
+            }
+        }
+    }
+}
+
 /// Read model view for completed and cancelled orders.
 ///
 /// Orders are staged while in progress and moved to history when they
 /// reach a terminal state (Completed or Cancelled).
+///
+/// With a [`RetentionPolicy`], history entries past its limits are evicted
+/// and, if an overflow store is attached, moved there. Shipping events for
+/// an evicted order read it back into memory.
 #[derive(Clone)]
 pub struct OrderHistoryView {
     state: Arc<RwLock<OrderHistoryState>>,
+    retention: RetentionPolicy,
+    overflow: Option<Arc<dyn OverflowStore<AggregateId, OrderHistorySummary>>>,
 }
 
 impl OrderHistoryView {
@@ -78,33 +220,83 @@ impl OrderHistoryView {
             state: Arc::new(RwLock::new(OrderHistoryState {
                 staging: HashMap::new(),
                 history: HashMap::new(),
+                by_customer: HashMap::new(),
+                retention: RetentionIndex::new(),
                 position: ProjectionPosition::zero(),
             })),
+            retention: RetentionPolicy::unbounded(),
+            overflow: None,
         }
     }
 
-    /// Gets a specific historical order.
+    /// Limits how many history entries are kept in memory.
+    pub fn with_retention(mut self, retention: RetentionPolicy) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    /// Moves evicted history entries to `overflow` instead of dropping them.
+    pub fn with_overflow(
+        mut self,
+        overflow: Arc<dyn OverflowStore<AggregateId, OrderHistorySummary>>,
+    ) -> Self {
+        self.overflow = Some(overflow);
+        self
+    }
+
+    /// Gets a specific historical order, if it is still held in memory.
     pub async fn get_order(&self, order_id: AggregateId) -> Option<OrderHistorySummary> {
         self.state.read().await.history.get(&order_id).cloned()
     }
 
+    /// Gets a specific historical order, reading the overflow store if it
+    /// was evicted.
+    pub async fn load_order(&self, order_id: AggregateId) -> Result<Option<OrderHistorySummary>> {
+        if let Some(summary) = self.get_order(order_id).await {
+            return Ok(Some(summary));
+        }
+        match &self.overflow {
+            Some(overflow) => overflow.get(&order_id).await,
+            None => Ok(None),
+        }
+    }
+
     /// Gets all historical orders.
     pub async fn get_all_history(&self) -> Vec<OrderHistorySummary> {
         self.state.read().await.history.values().cloned().collect()
     }
 
-    /// Gets all completed orders.
+    /// Gets all completed orders, including those since shipped or delivered.
     pub async fn get_completed_orders(&self) -> Vec<OrderHistorySummary> {
         self.state
             .read()
             .await
             .history
             .values()
-            .filter(|o| o.state == OrderState::Completed)
+            .filter(|o| o.completed_at.is_some())
             .cloned()
             .collect()
     }
 
+    /// Sums the totals of orders completed at or after `since`, per order
+    /// currency. Entries moved to an overflow store aren't counted.
+    pub async fn completed_revenue_since(
+        &self,
+        since: DateTime<Utc>,
+    ) -> Result<BTreeMap<Currency, Money>> {
+        let state = read_state(ReadModel::name(self), &self.state).await?;
+        let mut totals = BTreeMap::new();
+        for order in state.history.values() {
+            if order.completed_at.is_some_and(|at| at >= since) {
+                let total = totals
+                    .entry(order.currency)
+                    .or_insert_with(|| Money::zero_in(order.currency));
+                *total = total.checked_add(order.total_amount)?;
+            }
+        }
+        Ok(totals)
+    }
+
     /// Gets all cancelled orders.
     pub async fn get_cancelled_orders(&self) -> Vec<OrderHistorySummary> {
         self.state
@@ -117,22 +309,104 @@ impl OrderHistoryView {
             .collect()
     }
 
-    /// Gets historical orders for a specific customer.
+    /// Gets historical orders for a specific customer, oldest finish first.
     pub async fn get_history_by_customer(
         &self,
         customer_id: CustomerId,
     ) -> Vec<OrderHistorySummary> {
+        self.query_customer_history(customer_id, &HistoryQuery::default())
+            .await
+            .orders
+    }
+
+    /// Gets a page of a customer's historical orders that finished within
+    /// the query's date range, oldest finish first.
+    ///
+    /// Walks the customer's finish-time index, so only that customer's
+    /// entries in the range are visited. Entries evicted to an overflow
+    /// store are not included.
+    pub async fn query_customer_history(
+        &self,
+        customer_id: CustomerId,
+        query: &HistoryQuery,
+    ) -> HistoryPage {
+        self.query_customer_history_for(customer_id, query, &Unrestricted)
+            .await
+    }
+
+    /// Like [`query_customer_history`](Self::query_customer_history), but
+    /// only over the orders `policy` permits, masked by it.
+    pub async fn query_customer_history_for(
+        &self,
+        customer_id: CustomerId,
+        query: &HistoryQuery,
+        policy: &dyn RowPolicy<OrderHistorySummary>,
+    ) -> HistoryPage {
+        let state = self.state.read().await;
+        let Some(index) = state.by_customer.get(&customer_id) else {
+            return HistoryPage {
+                orders: Vec::new(),
+                total: 0,
+            };
+        };
+        let matching: Vec<_> = index
+            .range(query.from, query.to)
+            .filter_map(|order_id| state.history.get(&order_id))
+            .filter(|o| query.state.is_none_or(|s| o.state == s))
+            .filter(|o| policy.permits(o))
+            .collect();
+
+        let total = matching.len();
+        let orders = matching
+            .into_iter()
+            .skip(query.offset)
+            .take(query.limit.unwrap_or(usize::MAX))
+            .map(|o| masked(policy, o))
+            .collect();
+        HistoryPage { orders, total }
+    }
+
+    /// Gets historical orders carrying a tag.
+    pub async fn get_history_by_tag(&self, tag: &str) -> Vec<OrderHistorySummary> {
+        let tag = tag.trim().to_lowercase();
         self.state
             .read()
             .await
             .history
             .values()
-            .filter(|o| o.customer_id == customer_id)
+            .filter(|o| o.tags.contains(&tag))
             .cloned()
             .collect()
     }
 }
 
+impl OrderHistoryView {
+    /// Evicts the history entries the retention policy no longer keeps.
+    async fn evict(&self, state: &mut OrderHistoryState, now: DateTime<Utc>) -> Result<()> {
+        if self.retention.is_unbounded() {
+            return Ok(());
+        }
+        let evicted: Vec<_> = state
+            .retention
+            .evict(&self.retention, now)
+            .into_iter()
+            .filter_map(|order_id| {
+                state
+                    .remove_history(order_id)
+                    .map(|summary| (order_id, summary))
+            })
+            .collect();
+        if evicted.is_empty() {
+            return Ok(());
+        }
+        record_evictions(Projection::name(self), evicted.len());
+        if let Some(overflow) = &self.overflow {
+            overflow.put(evicted).await?;
+        }
+        Ok(())
+    }
+}
+
 impl Default for OrderHistoryView {
     fn default() -> Self {
         Self::new()
@@ -148,7 +422,7 @@ impl Projection for OrderHistoryView {
     async fn handle(&self, event: &EventEnvelope) -> Result<()> {
         if event.aggregate_type != "Order" {
             let mut state = self.state.write().await;
-            state.position = state.position.advance();
+            state.position = state.position.advance_to(event);
             return Ok(());
         }
 
@@ -157,14 +431,37 @@ impl Projection for OrderHistoryView {
 
         let mut state = self.state.write().await;
 
+        let updates_history = match order_event {
+            OrderEvent::OrderShipped(_)
+            | OrderEvent::OrderInTransit(_)
+            | OrderEvent::OrderDelivered(_)
+            | OrderEvent::DeliveryFailed(_) => true,
+            // Tags can change at any point; only finished orders are in history
+            OrderEvent::TagAdded(_) | OrderEvent::TagRemoved(_) => {
+                !state.staging.contains_key(&order_id)
+            }
+            _ => false,
+        };
+        if updates_history
+            && !state.history.contains_key(&order_id)
+            && let Some(overflow) = &self.overflow
+            && let Some(summary) = overflow.get(&order_id).await?
+        {
+            state.insert_history(summary);
+        }
+
         match order_event {
             OrderEvent::OrderCreated(data) => {
                 state.staging.insert(
                     order_id,
                     StagingOrder {
                         customer_id: data.customer_id,
+                        currency: data.currency,
                         created_at: data.created_at,
+                        shipping_address: None,
                         items: HashMap::new(),
+                        tags: BTreeSet::new(),
+                        metadata: BTreeMap::new(),
                     },
                 );
             }
@@ -177,6 +474,8 @@ impl Projection for OrderHistoryView {
                             product_name: data.product_name,
                             quantity: data.quantity,
                             unit_price: data.unit_price,
+                            serial_numbers: Vec::new(),
+                            lot_number: None,
                         },
                     );
                 }
@@ -193,58 +492,172 @@ impl Projection for OrderHistoryView {
                     item.quantity = data.new_quantity;
                 }
             }
+            OrderEvent::ItemPriceAdjusted(data) => {
+                if let Some(staging) = state.staging.get_mut(&order_id)
+                    && let Some(item) = staging.items.get_mut(&data.product_id)
+                {
+                    item.unit_price = data.new_unit_price;
+                }
+            }
+            OrderEvent::ItemSubstituted(data) => {
+                if let Some(staging) = state.staging.get_mut(&order_id) {
+                    staging.items.remove(&data.original_product_id);
+                    staging.items.insert(
+                        data.substitute_product_id.clone(),
+                        HistoryItemSummary {
+                            product_id: data.substitute_product_id,
+                            product_name: data.substitute_product_name,
+                            quantity: data.quantity,
+                            unit_price: data.unit_price,
+                            serial_numbers: Vec::new(),
+                            lot_number: None,
+                        },
+                    );
+                }
+            }
             OrderEvent::OrderCompleted(data) => {
-                if let Some(staging) = state.staging.remove(&order_id) {
+                if let Some(mut staging) = state.staging.remove(&order_id) {
+                    for shipped in data.shipped_items {
+                        if let Some(item) = staging.items.get_mut(&shipped.product_id) {
+                            item.serial_numbers = shipped.serial_numbers;
+                            item.lot_number = shipped.lot_number;
+                        }
+                    }
                     let total_amount = staging.total_amount();
-                    state.history.insert(
+                    state.insert_history(OrderHistorySummary {
                         order_id,
-                        OrderHistorySummary {
-                            order_id,
-                            customer_id: staging.customer_id,
-                            state: OrderState::Completed,
-                            item_count: staging.items.len(),
-                            total_amount,
-                            created_at: staging.created_at,
-                            completed_at: Some(data.completed_at),
-                            cancelled_at: None,
-                            tracking_number: data.tracking_number,
-                            cancellation_reason: None,
-                            items: staging.items,
-                        },
-                    );
+                        customer_id: staging.customer_id,
+                        state: OrderState::Completed,
+                        item_count: staging.items.len(),
+                        total_amount,
+                        currency: staging.currency,
+                        created_at: staging.created_at,
+                        completed_at: Some(data.completed_at),
+                        cancelled_at: None,
+                        tracking_number: data.tracking_number,
+                        cancellation_reason: None,
+                        carrier: None,
+                        last_location: None,
+                        delivered_at: None,
+                        delivery_failure_reason: None,
+                        shipping_address: staging.shipping_address,
+                        items: staging.items,
+                        tags: staging.tags,
+                        metadata: staging.metadata,
+                    });
                 }
             }
             OrderEvent::OrderCancelled(data) => {
                 if let Some(staging) = state.staging.remove(&order_id) {
                     let total_amount = staging.total_amount();
-                    state.history.insert(
+                    state.insert_history(OrderHistorySummary {
                         order_id,
-                        OrderHistorySummary {
-                            order_id,
-                            customer_id: staging.customer_id,
-                            state: OrderState::Cancelled,
-                            item_count: staging.items.len(),
-                            total_amount,
-                            created_at: staging.created_at,
-                            completed_at: None,
-                            cancelled_at: Some(data.cancelled_at),
-                            tracking_number: None,
-                            cancellation_reason: Some(data.reason),
-                            items: staging.items,
-                        },
-                    );
+                        customer_id: staging.customer_id,
+                        state: OrderState::Cancelled,
+                        item_count: staging.items.len(),
+                        total_amount,
+                        currency: staging.currency,
+                        created_at: staging.created_at,
+                        completed_at: None,
+                        cancelled_at: Some(data.cancelled_at),
+                        tracking_number: None,
+                        cancellation_reason: Some(data.reason),
+                        carrier: None,
+                        last_location: None,
+                        delivered_at: None,
+                        delivery_failure_reason: None,
+                        shipping_address: staging.shipping_address,
+                        items: staging.items,
+                        tags: staging.tags,
+                        metadata: staging.metadata,
+                    });
+                }
+            }
+            OrderEvent::OrderShipped(data) => {
+                if let Some(summary) = state.history.get_mut(&order_id) {
+                    summary.state = OrderState::Shipped;
+                    summary.carrier = Some(data.carrier);
+                    if data.tracking_number.is_some() {
+                        summary.tracking_number = data.tracking_number;
+                    }
+                }
+            }
+            OrderEvent::OrderInTransit(data) => {
+                if let Some(summary) = state.history.get_mut(&order_id) {
+                    summary.state = OrderState::InTransit;
+                    if data.location.is_some() {
+                        summary.last_location = data.location;
+                    }
+                }
+            }
+            OrderEvent::OrderDelivered(data) => {
+                if let Some(summary) = state.history.get_mut(&order_id) {
+                    summary.state = OrderState::Delivered;
+                    summary.delivered_at = Some(data.delivered_at);
+                }
+            }
+            OrderEvent::DeliveryFailed(data) => {
+                if let Some(summary) = state.history.get_mut(&order_id) {
+                    summary.state = OrderState::DeliveryFailed;
+                    summary.delivery_failure_reason = Some(data.reason);
+                }
+            }
+            OrderEvent::TagAdded(data) => {
+                if let Some(staging) = state.staging.get_mut(&order_id) {
+                    staging.tags.insert(data.tag);
+                } else if let Some(summary) = state.history.get_mut(&order_id) {
+                    summary.tags.insert(data.tag);
+                }
+            }
+            OrderEvent::TagRemoved(data) => {
+                if let Some(staging) = state.staging.get_mut(&order_id) {
+                    staging.tags.remove(&data.tag);
+                } else if let Some(summary) = state.history.get_mut(&order_id) {
+                    summary.tags.remove(&data.tag);
+                }
+            }
+            // Metadata is locked once the order is reserved, so it only
+            // ever changes while staged
+            OrderEvent::OrderMetadataSet(data) => {
+                if let Some(staging) = state.staging.get_mut(&order_id) {
+                    match data.value {
+                        Some(value) => staging.metadata.insert(data.key, value),
+                        None => staging.metadata.remove(&data.key),
+                    };
+                }
+            }
+            // Addresses are locked once the order is completed, so they
+            // only ever change while staged
+            OrderEvent::ShippingAddressSet(data) => {
+                if let Some(staging) = state.staging.get_mut(&order_id) {
+                    staging.shipping_address = Some(data.address);
+                }
+            }
+            OrderEvent::ShippingAddressUpdated(data) => {
+                if let Some(staging) = state.staging.get_mut(&order_id) {
+                    staging.shipping_address = Some(data.address);
                 }
             }
             // State transitions don't affect history staging
             OrderEvent::OrderSubmitted(_)
             | OrderEvent::OrderReserved(_)
+            | OrderEvent::ReservationRenewed(_)
             | OrderEvent::OrderProcessing(_) => {}
         }
 
-        state.position = state.position.advance();
+        if state.history.contains_key(&order_id) {
+            state.retention.touch(order_id, event.timestamp);
+        }
+        self.evict(&mut state, event.timestamp).await?;
+
+        state.position = state.position.advance_to(event);
         Ok(())
     }
 
+    async fn handle_group(&self, events: &[EventEnvelope]) -> Result<()> {
+        apply_or_rollback(self, &self.state, events).await
+    }
+
     async fn position(&self) -> ProjectionPosition {
         self.state.read().await.position
     }
@@ -253,25 +666,96 @@ impl Projection for OrderHistoryView {
         let mut state = self.state.write().await;
         state.staging.clear();
         state.history.clear();
+        state.by_customer.clear();
+        state.retention.clear();
         state.position = ProjectionPosition::zero();
+        if let Some(overflow) = &self.overflow {
+            overflow.clear().await?;
+        }
         Ok(())
     }
+
+    async fn summary(&self) -> serde_json::Value {
+        let state = self.state.read().await;
+        serde_json::json!({
+            "events_processed": state.position.events_processed,
+            "in_progress": state.staging.len(),
+            "history": state.history.len(),
+        })
+    }
 }
 
+#[async_trait]
 impl ReadModel for OrderHistoryView {
     fn name(&self) -> &'static str {
         "OrderHistoryView"
     }
 
-    fn count(&self) -> usize {
-        self.state.try_read().map(|s| s.history.len()).unwrap_or(0)
+    async fn count(&self) -> Result<usize> {
+        let s = read_state(ReadModel::name(self), &self.state).await?;
+        Ok(s.history.len())
+    }
+
+    async fn memory_usage(&self) -> Result<usize> {
+        let s = read_state(ReadModel::name(self), &self.state).await?;
+        Ok(map_memory_usage(&s.staging)
+            + s.staging
+                .values()
+                .map(|order| map_memory_usage(&order.items))
+                .sum::<usize>()
+            + map_memory_usage(&s.history)
+            + s.history
+                .values()
+                .map(|order| map_memory_usage(&order.items))
+                .sum::<usize>()
+            + map_memory_usage(&s.by_customer)
+            + s.retention.memory_usage())
+    }
+}
+
+/// Compares finished orders wherever they are held, so a view that moves
+/// them to overflow agrees with one that keeps them all in memory.
+#[async_trait]
+impl ShadowProbe for OrderHistoryView {
+    async fn probe(&self, aggregate_id: AggregateId) -> Result<serde_json::Value> {
+        let Some(order) = self.load_order(aggregate_id).await? else {
+            return Ok(serde_json::Value::Null);
+        };
+        let mut items: Vec<_> = order.items.values().collect();
+        items.sort_by(|a, b| a.product_id.cmp(&b.product_id));
+        Ok(serde_json::json!({
+            "customer_id": order.customer_id.to_string(),
+            "state": order.state,
+            "total_cents": order.total_amount.cents(),
+            "completed_at": order.completed_at,
+            "cancelled_at": order.cancelled_at,
+            "tracking_number": order.tracking_number,
+            "cancellation_reason": order.cancellation_reason,
+            "carrier": order.carrier,
+            "last_location": order.last_location,
+            "delivered_at": order.delivered_at,
+            "delivery_failure_reason": order.delivery_failure_reason,
+            "items": items
+                .iter()
+                .map(|item| serde_json::json!({
+                    "product_id": item.product_id.as_str(),
+                    "quantity": item.quantity,
+                    "unit_price_cents": item.unit_price.cents(),
+                    "serial_numbers": item.serial_numbers,
+                    "lot_number": item.lot_number,
+                }))
+                .collect::<Vec<_>>(),
+            "tags": order.tags,
+            "metadata": order.metadata,
+        }))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use domain::{DomainEvent, OrderItem};
+    use chrono::TimeZone;
+    use domain::{DomainEvent, OrderItem, ShippedItem};
 
     fn make_envelope(aggregate_id: AggregateId, version: i64, event: &OrderEvent) -> EventEnvelope {
         EventEnvelope::builder()
@@ -323,6 +807,135 @@ mod tests {
         assert_eq!(history.tracking_number, Some("TRACK-123".to_string()));
         assert!(history.completed_at.is_some());
         assert!(history.cancelled_at.is_none());
+        assert!(history.shipping_address.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_shipping_address_carried_into_history() {
+        let view = OrderHistoryView::new();
+        let order_id = AggregateId::new();
+
+        create_order_with_items(&view, order_id, CustomerId::new()).await;
+        let first = Address::new("1 Main St", "Springfield", "12345", "US");
+        let moved = Address::new("9 Elm St", "Springfield", "12345", "US");
+        let events = [
+            OrderEvent::shipping_address_set(first.clone()),
+            OrderEvent::shipping_address_updated(moved.clone(), first),
+            OrderEvent::order_completed(None),
+        ];
+        for (version, event) in events.iter().enumerate() {
+            view.handle(&make_envelope(order_id, version as i64 + 3, event))
+                .await
+                .unwrap();
+        }
+
+        let history = view.get_order(order_id).await.unwrap();
+        assert_eq!(history.shipping_address, Some(moved));
+    }
+
+    #[tokio::test]
+    async fn test_completed_revenue_is_summed_per_currency() {
+        let view = OrderHistoryView::new();
+        let since = Utc::now();
+        for currency in [Currency::USD, Currency::USD, Currency::EUR] {
+            let order_id = AggregateId::new();
+            let event = OrderEvent::order_created_in(order_id, CustomerId::new(), currency);
+            view.handle(&make_envelope(order_id, 1, &event))
+                .await
+                .unwrap();
+            let item = OrderItem::new("SKU-001", "Widget", 2, Money::from_cents(1000));
+            view.handle(&make_envelope(order_id, 2, &OrderEvent::item_added(&item)))
+                .await
+                .unwrap();
+            view.handle(&make_envelope(
+                order_id,
+                3,
+                &OrderEvent::order_completed(None),
+            ))
+            .await
+            .unwrap();
+        }
+        let cancelled = AggregateId::new();
+        create_order_with_items(&view, cancelled, CustomerId::new()).await;
+        let event = OrderEvent::order_cancelled("Changed mind", None);
+        view.handle(&make_envelope(cancelled, 3, &event))
+            .await
+            .unwrap();
+
+        let revenue = view.completed_revenue_since(since).await.unwrap();
+        assert_eq!(revenue[&Currency::USD].cents(), 4000);
+        assert_eq!(revenue[&Currency::EUR].cents(), 2000);
+        assert_eq!(revenue.len(), 2);
+
+        let later = Utc::now() + chrono::Duration::minutes(1);
+        assert!(
+            view.completed_revenue_since(later)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_completed_order_records_serial_and_lot_numbers() {
+        let view = OrderHistoryView::new();
+        let order_id = AggregateId::new();
+
+        create_order_with_items(&view, order_id, CustomerId::new()).await;
+        let event = OrderEvent::order_completed_with_items(
+            Some("TRACK-123".to_string()),
+            vec![
+                ShippedItem::new("SKU-001")
+                    .with_serial_numbers(["SN-1", "SN-2"])
+                    .with_lot_number("LOT-7"),
+            ],
+        );
+        view.handle(&make_envelope(order_id, 3, &event))
+            .await
+            .unwrap();
+
+        let history = view.get_order(order_id).await.unwrap();
+        let item = &history.items[&ProductId::new("SKU-001")];
+        assert_eq!(item.serial_numbers, ["SN-1", "SN-2"]);
+        assert_eq!(item.lot_number.as_deref(), Some("LOT-7"));
+    }
+
+    #[tokio::test]
+    async fn test_carrier_updates_advance_history_state() {
+        let view = OrderHistoryView::new();
+        let order_id = AggregateId::new();
+
+        create_order_with_items(&view, order_id, CustomerId::new()).await;
+        let event = OrderEvent::order_completed(None);
+        view.handle(&make_envelope(order_id, 3, &event))
+            .await
+            .unwrap();
+
+        let event = OrderEvent::order_shipped("UPS", Some("1Z999".to_string()), Utc::now());
+        view.handle(&make_envelope(order_id, 4, &event))
+            .await
+            .unwrap();
+        let history = view.get_order(order_id).await.unwrap();
+        assert_eq!(history.state, OrderState::Shipped);
+        assert_eq!(history.carrier, Some("UPS".to_string()));
+        assert_eq!(history.tracking_number, Some("1Z999".to_string()));
+
+        let event = OrderEvent::order_in_transit(Some("Memphis, TN".to_string()), Utc::now());
+        view.handle(&make_envelope(order_id, 5, &event))
+            .await
+            .unwrap();
+        let history = view.get_order(order_id).await.unwrap();
+        assert_eq!(history.state, OrderState::InTransit);
+        assert_eq!(history.last_location, Some("Memphis, TN".to_string()));
+
+        let event = OrderEvent::order_delivered(None, Utc::now());
+        view.handle(&make_envelope(order_id, 6, &event))
+            .await
+            .unwrap();
+        let history = view.get_order(order_id).await.unwrap();
+        assert_eq!(history.state, OrderState::Delivered);
+        assert!(history.delivered_at.is_some());
+        assert_eq!(view.get_completed_orders().await.len(), 1);
     }
 
     #[tokio::test]
@@ -386,6 +999,38 @@ mod tests {
         assert_eq!(view.get_all_history().await.len(), 2);
     }
 
+    #[tokio::test]
+    async fn test_tags_carried_into_history_and_updated_after() {
+        let view = OrderHistoryView::new();
+        let order_id = AggregateId::new();
+
+        create_order_with_items(&view, order_id, CustomerId::new()).await;
+        let event = OrderEvent::tag_added("wholesale");
+        view.handle(&make_envelope(order_id, 3, &event))
+            .await
+            .unwrap();
+        let event = OrderEvent::order_completed(None);
+        view.handle(&make_envelope(order_id, 4, &event))
+            .await
+            .unwrap();
+
+        assert_eq!(view.get_history_by_tag("wholesale").await.len(), 1);
+
+        // Tags can still change once the order is in history
+        let event = OrderEvent::tag_added("refund-audit");
+        view.handle(&make_envelope(order_id, 5, &event))
+            .await
+            .unwrap();
+        let event = OrderEvent::tag_removed("wholesale");
+        view.handle(&make_envelope(order_id, 6, &event))
+            .await
+            .unwrap();
+
+        assert!(view.get_history_by_tag("wholesale").await.is_empty());
+        let summary = view.get_order(order_id).await.unwrap();
+        assert!(summary.tags.contains("refund-audit"));
+    }
+
     #[tokio::test]
     async fn test_filter_by_customer() {
         let view = OrderHistoryView::new();
@@ -411,6 +1056,76 @@ mod tests {
         assert_eq!(c1_history[0].order_id, order1);
     }
 
+    #[tokio::test]
+    async fn test_query_customer_history_by_finish_date() {
+        let view = OrderHistoryView::new();
+        let customer_id = CustomerId::new();
+        let day = |d: u32| Utc.with_ymd_and_hms(2025, 3, d, 12, 0, 0).unwrap();
+
+        // Finished out of order, and one order for another customer
+        let mut ids = Vec::new();
+        for (finish_day, cancelled) in [(10, false), (2, false), (6, true), (8, false)] {
+            let order_id = AggregateId::new();
+            create_order_with_items(&view, order_id, customer_id).await;
+            let event = if cancelled {
+                OrderEvent::OrderCancelled(domain::order::OrderCancelledData {
+                    reason: "Changed mind".to_string(),
+                    cancelled_by: None,
+                    cancelled_at: day(finish_day),
+                    reason_code: None,
+                })
+            } else {
+                OrderEvent::OrderCompleted(domain::order::OrderCompletedData {
+                    completed_at: day(finish_day),
+                    tracking_number: None,
+                    shipped_items: Vec::new(),
+                })
+            };
+            view.handle(&make_envelope(order_id, 3, &event))
+                .await
+                .unwrap();
+            ids.push(order_id);
+        }
+        let other = AggregateId::new();
+        create_order_with_items(&view, other, CustomerId::new()).await;
+        let event = OrderEvent::order_completed(None);
+        view.handle(&make_envelope(other, 3, &event)).await.unwrap();
+
+        let all = view.get_history_by_customer(customer_id).await;
+        let order: Vec<_> = all.iter().map(|o| o.order_id).collect();
+        assert_eq!(order, [ids[1], ids[2], ids[3], ids[0]]);
+
+        let query = HistoryQuery {
+            from: Some(day(2)),
+            to: Some(day(10)),
+            state: Some(OrderState::Completed),
+            ..Default::default()
+        };
+        let page = view.query_customer_history(customer_id, &query).await;
+        assert_eq!(page.total, 2);
+        let order: Vec<_> = page.orders.iter().map(|o| o.order_id).collect();
+        assert_eq!(order, [ids[1], ids[3]]);
+
+        let query = HistoryQuery {
+            offset: 1,
+            limit: Some(2),
+            ..Default::default()
+        };
+        let page = view.query_customer_history(customer_id, &query).await;
+        assert_eq!(page.total, 4);
+        let order: Vec<_> = page.orders.iter().map(|o| o.order_id).collect();
+        assert_eq!(order, [ids[2], ids[3]]);
+
+        // An inverted range matches nothing
+        let query = HistoryQuery {
+            from: Some(day(9)),
+            to: Some(day(3)),
+            ..Default::default()
+        };
+        let page = view.query_customer_history(customer_id, &query).await;
+        assert_eq!(page.total, 0);
+    }
+
     #[tokio::test]
     async fn test_reset() {
         let view = OrderHistoryView::new();
@@ -461,4 +1176,74 @@ mod tests {
         let history = view.get_order(order_id).await.unwrap();
         assert_eq!(history.total_amount.cents(), 5000);
     }
+
+    #[tokio::test]
+    async fn test_item_price_adjustment_reflected_in_history() {
+        let view = OrderHistoryView::new();
+        let order_id = AggregateId::new();
+        let customer_id = CustomerId::new();
+
+        let event = OrderEvent::order_created(order_id, customer_id);
+        view.handle(&make_envelope(order_id, 1, &event))
+            .await
+            .unwrap();
+
+        let item = OrderItem::new("SKU-001", "Widget", 2, Money::from_cents(1000));
+        let event = OrderEvent::item_added(&item);
+        view.handle(&make_envelope(order_id, 2, &event))
+            .await
+            .unwrap();
+
+        let event = OrderEvent::item_price_adjusted(
+            ProductId::new("SKU-001"),
+            Money::from_cents(1000),
+            Money::from_cents(1200),
+        );
+        view.handle(&make_envelope(order_id, 3, &event))
+            .await
+            .unwrap();
+
+        let event = OrderEvent::order_completed(None);
+        view.handle(&make_envelope(order_id, 4, &event))
+            .await
+            .unwrap();
+
+        let history = view.get_order(order_id).await.unwrap();
+        assert_eq!(history.total_amount.cents(), 2400);
+    }
+
+    #[tokio::test]
+    async fn test_retention_moves_evicted_orders_to_overflow() {
+        let overflow = Arc::new(crate::InMemoryOverflow::new());
+        let view = OrderHistoryView::new()
+            .with_retention(RetentionPolicy::unbounded().with_max_entries(1))
+            .with_overflow(overflow.clone());
+        let first = AggregateId::new();
+        let second = AggregateId::new();
+
+        for order_id in [first, second] {
+            create_order_with_items(&view, order_id, CustomerId::new()).await;
+            let event = OrderEvent::order_completed(None);
+            view.handle(&make_envelope(order_id, 3, &event))
+                .await
+                .unwrap();
+        }
+
+        assert!(view.get_order(first).await.is_none());
+        assert_eq!(
+            view.load_order(first).await.unwrap().unwrap().state,
+            OrderState::Completed
+        );
+        assert_eq!(ReadModel::count(&view).await.unwrap(), 1);
+
+        // A late shipping event reads the order back and evicts the other
+        let event = OrderEvent::order_shipped("UPS", None, Utc::now());
+        view.handle(&make_envelope(first, 4, &event)).await.unwrap();
+        assert_eq!(
+            view.get_order(first).await.unwrap().state,
+            OrderState::Shipped
+        );
+        assert!(view.get_order(second).await.is_none());
+        assert_eq!(overflow.entry_count().await, 2);
+    }
 }
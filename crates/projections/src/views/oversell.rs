@@ -0,0 +1,371 @@
+//! Oversell detection read model — products with more units reserved than
+//! are in stock.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use domain::{InventoryEvent, ProductId};
+use event_store::EventEnvelope;
+use tokio::sync::RwLock;
+
+use super::stock_levels::StockLevelsState;
+use crate::Result;
+use crate::projection::{Projection, ProjectionPosition, apply_or_rollback};
+use crate::read_model::{ReadModel, map_memory_usage, read_state};
+
+/// A product whose reservations exceed its stock.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Oversell {
+    pub product_id: ProductId,
+    /// Units physically in stock.
+    pub on_hand: i64,
+    /// Units held by reserved orders that have not shipped yet.
+    pub reserved: u64,
+    /// Timestamp of the event that first pushed reservations past stock.
+    pub detected_at: DateTime<Utc>,
+}
+
+impl Oversell {
+    /// Returns the units reserved beyond what is in stock.
+    pub fn shortfall(&self) -> u64 {
+        (self.reserved as i64 - self.on_hand).max(0) as u64
+    }
+}
+
+/// A change in a product's oversell condition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OversellAlert {
+    /// Reservations went past stock.
+    Detected(Oversell),
+    /// Stock covers the reservations again.
+    Resolved(ProductId),
+}
+
+/// Hook told when a product becomes oversold or stops being oversold, such
+/// as a pager or a webhook sender.
+///
+/// Alerts are sent after the view has applied the event. A group of events
+/// that is rolled back and handled again can alert twice, so receivers
+/// should tolerate repeats.
+#[async_trait]
+pub trait OversellAlerter: Send + Sync {
+    /// Sends an alert.
+    async fn alert(&self, alert: &OversellAlert);
+}
+
+/// In-memory alerter for testing; records every alert sent.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryOversellAlerter {
+    sent: Arc<std::sync::RwLock<Vec<OversellAlert>>>,
+}
+
+impl InMemoryOversellAlerter {
+    /// Creates a new in-memory alerter.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the alerts sent so far, oldest first.
+    pub fn sent(&self) -> Vec<OversellAlert> {
+        self.sent.read().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl OversellAlerter for InMemoryOversellAlerter {
+    async fn alert(&self, alert: &OversellAlert) {
+        self.sent.write().unwrap().push(alert.clone());
+    }
+}
+
+/// Internal state for the oversell detection view.
+#[derive(Clone)]
+struct OversellState {
+    levels: StockLevelsState,
+    /// Products with inventory events; others have no known stock to
+    /// compare against.
+    stocked: HashSet<ProductId>,
+    oversold: HashMap<ProductId, Oversell>,
+}
+
+impl OversellState {
+    /// Brings `oversold` in line with the stock levels, returning what
+    /// changed.
+    fn check(&mut self, at: DateTime<Utc>) -> Vec<OversellAlert> {
+        let mut alerts = Vec::new();
+        for product_id in &self.stocked {
+            let Some(level) = self.levels.products.get(product_id) else {
+                continue;
+            };
+            if level.available() >= 0 {
+                if self.oversold.remove(product_id).is_some() {
+                    alerts.push(OversellAlert::Resolved(product_id.clone()));
+                }
+                continue;
+            }
+            match self.oversold.get_mut(product_id) {
+                Some(oversell) => {
+                    oversell.on_hand = level.on_hand;
+                    oversell.reserved = level.reserved;
+                }
+                None => {
+                    let oversell = Oversell {
+                        product_id: product_id.clone(),
+                        on_hand: level.on_hand,
+                        reserved: level.reserved,
+                        detected_at: at,
+                    };
+                    self.oversold.insert(product_id.clone(), oversell.clone());
+                    alerts.push(OversellAlert::Detected(oversell));
+                }
+            }
+        }
+        alerts
+    }
+}
+
+/// Read model view of oversold products.
+///
+/// Keeps the same stock levels as
+/// [`StockLevelsView`](super::StockLevelsView) and flags products with
+/// known stock whose reservations exceed it. Each new oversell counts
+/// towards `inventory_oversells_detected`, the `inventory_oversold_products`
+/// gauge tracks how many are open, and every change goes to the view's
+/// alerters.
+#[derive(Clone)]
+pub struct OversellDetectionView {
+    state: Arc<RwLock<OversellState>>,
+    alerters: Vec<Arc<dyn OversellAlerter>>,
+}
+
+impl OversellDetectionView {
+    /// Creates a new empty oversell detection view with no alerters.
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(RwLock::new(OversellState {
+                levels: StockLevelsState::new(),
+                stocked: HashSet::new(),
+                oversold: HashMap::new(),
+            })),
+            alerters: Vec::new(),
+        }
+    }
+
+    /// Adds a hook told about oversells as they open and close.
+    pub fn with_alerter(mut self, alerter: Arc<dyn OversellAlerter>) -> Self {
+        self.alerters.push(alerter);
+        self
+    }
+
+    /// Gets the open oversell for a product, if any.
+    pub async fn get_product(&self, product_id: &ProductId) -> Option<Oversell> {
+        self.state.read().await.oversold.get(product_id).cloned()
+    }
+
+    /// Gets all open oversells, largest shortfall first.
+    pub async fn get_all(&self) -> Vec<Oversell> {
+        let mut oversold: Vec<_> = self.state.read().await.oversold.values().cloned().collect();
+        oversold.sort_by(|a, b| {
+            b.shortfall()
+                .cmp(&a.shortfall())
+                .then_with(|| a.product_id.cmp(&b.product_id))
+        });
+        oversold
+    }
+}
+
+impl Default for OversellDetectionView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Projection for OversellDetectionView {
+    fn name(&self) -> &'static str {
+        "OversellDetectionView"
+    }
+
+    async fn handle(&self, event: &EventEnvelope) -> Result<()> {
+        let (alerts, open) = {
+            let mut state = self.state.write().await;
+            state.levels.apply(event)?;
+            if event.aggregate_type == "InventoryItem" {
+                let inventory_event: InventoryEvent =
+                    serde_json::from_value(event.payload.clone())?;
+                state.stocked.insert(inventory_event.product_id().clone());
+            }
+            let alerts = state.check(event.timestamp);
+            (alerts, state.oversold.len())
+        };
+        if alerts.is_empty() {
+            return Ok(());
+        }
+
+        metrics::gauge!("inventory_oversold_products").set(open as f64);
+        for alert in &alerts {
+            match alert {
+                OversellAlert::Detected(oversell) => {
+                    metrics::counter!("inventory_oversells_detected").increment(1);
+                    tracing::warn!(
+                        product_id = %oversell.product_id,
+                        on_hand = oversell.on_hand,
+                        reserved = oversell.reserved,
+                        "inventory oversold"
+                    );
+                }
+                OversellAlert::Resolved(product_id) => {
+                    tracing::info!(%product_id, "inventory oversell resolved");
+                }
+            }
+            for alerter in &self.alerters {
+                alerter.alert(alert).await;
+            }
+        }
+        Ok(())
+    }
+
+    async fn handle_group(&self, events: &[EventEnvelope]) -> Result<()> {
+        apply_or_rollback(self, &self.state, events).await
+    }
+
+    async fn position(&self) -> ProjectionPosition {
+        self.state.read().await.levels.position
+    }
+
+    async fn reset(&self) -> Result<()> {
+        let mut state = self.state.write().await;
+        state.levels = StockLevelsState::new();
+        state.stocked.clear();
+        state.oversold.clear();
+        Ok(())
+    }
+
+    async fn summary(&self) -> serde_json::Value {
+        let state = self.state.read().await;
+        serde_json::json!({
+            "events_processed": state.levels.position.events_processed,
+            "stocked_products": state.stocked.len(),
+            "oversold": state.oversold.len(),
+        })
+    }
+}
+
+#[async_trait]
+impl ReadModel for OversellDetectionView {
+    fn name(&self) -> &'static str {
+        "OversellDetectionView"
+    }
+
+    async fn count(&self) -> Result<usize> {
+        let s = read_state(ReadModel::name(self), &self.state).await?;
+        Ok(s.oversold.len())
+    }
+
+    async fn memory_usage(&self) -> Result<usize> {
+        let s = read_state(ReadModel::name(self), &self.state).await?;
+        Ok(map_memory_usage(&s.levels.products)
+            + map_memory_usage(&s.oversold)
+            + s.stocked.capacity() * size_of::<ProductId>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::AggregateId;
+    use domain::{CustomerId, DomainEvent, InventoryItem, Money, OrderEvent, OrderItem};
+
+    fn order_envelope(order_id: AggregateId, version: i64, event: &OrderEvent) -> EventEnvelope {
+        EventEnvelope::builder()
+            .aggregate_id(order_id)
+            .aggregate_type("Order")
+            .event_type(event.event_type())
+            .version(event_store::Version::new(version))
+            .payload(event)
+            .unwrap()
+            .build()
+    }
+
+    async fn restock(view: &OversellDetectionView, sku: &str, quantity: u32, version: i64) {
+        let product_id = ProductId::new(sku);
+        let event = InventoryEvent::stock_restocked(product_id.clone(), quantity, None);
+        let envelope = EventEnvelope::builder()
+            .aggregate_id(InventoryItem::stream_id(&product_id))
+            .aggregate_type("InventoryItem")
+            .event_type(event.event_type())
+            .version(event_store::Version::new(version))
+            .payload(&event)
+            .unwrap()
+            .build();
+        view.handle(&envelope).await.unwrap();
+    }
+
+    /// Creates and reserves an order for `quantity` units of `sku`.
+    async fn reserve_order(view: &OversellDetectionView, sku: &str, quantity: u32) -> AggregateId {
+        let order_id = AggregateId::new();
+        let item = OrderItem::new(sku, "Widget", quantity, Money::from_cents(1000));
+        let events = [
+            OrderEvent::order_created(order_id, CustomerId::new()),
+            OrderEvent::item_added(&item),
+            OrderEvent::order_reserved(Default::default()),
+        ];
+        for (version, event) in events.iter().enumerate() {
+            view.handle(&order_envelope(order_id, version as i64 + 1, event))
+                .await
+                .unwrap();
+        }
+        order_id
+    }
+
+    #[tokio::test]
+    async fn test_detects_and_resolves_oversell() {
+        let alerter = InMemoryOversellAlerter::new();
+        let view = OversellDetectionView::new().with_alerter(Arc::new(alerter.clone()));
+        restock(&view, "SKU-001", 5, 1).await;
+
+        reserve_order(&view, "SKU-001", 3).await;
+        assert!(view.get_all().await.is_empty());
+
+        let order_id = reserve_order(&view, "SKU-001", 4).await;
+        let oversell = view.get_product(&ProductId::new("SKU-001")).await.unwrap();
+        assert_eq!(oversell.on_hand, 5);
+        assert_eq!(oversell.reserved, 7);
+        assert_eq!(oversell.shortfall(), 2);
+        assert_eq!(view.count().await.unwrap(), 1);
+
+        // Cancelling the second order brings reservations back under stock
+        let event = OrderEvent::order_cancelled("Out of stock", None);
+        view.handle(&order_envelope(order_id, 4, &event))
+            .await
+            .unwrap();
+        assert!(view.get_all().await.is_empty());
+
+        assert_eq!(
+            alerter.sent(),
+            [
+                OversellAlert::Detected(oversell),
+                OversellAlert::Resolved(ProductId::new("SKU-001")),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ignores_products_without_known_stock() {
+        let view = OversellDetectionView::new();
+        reserve_order(&view, "SKU-001", 3).await;
+        assert!(view.get_all().await.is_empty());
+
+        // Once stock is known, reservations beyond it are flagged
+        restock(&view, "SKU-001", 1, 1).await;
+        let oversold = view.get_all().await;
+        assert_eq!(oversold.len(), 1);
+        assert_eq!(oversold[0].shortfall(), 2);
+
+        view.reset().await.unwrap();
+        assert!(view.get_all().await.is_empty());
+        assert_eq!(view.position().await, ProjectionPosition::zero());
+    }
+}
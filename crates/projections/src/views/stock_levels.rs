@@ -0,0 +1,437 @@
+//! Stock levels read model — on-hand, reserved, and available units per product.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use common::AggregateId;
+use domain::{InventoryEvent, OrderEvent, ProductId};
+use event_store::EventEnvelope;
+use tokio::sync::RwLock;
+
+use crate::Result;
+use crate::projection::{Projection, ProjectionPosition, apply_or_rollback};
+use crate::read_model::{ReadModel, map_memory_usage, read_state};
+
+/// Stock position for a single product.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StockLevel {
+    pub product_id: ProductId,
+    /// Units physically in stock: received minus shipped.
+    ///
+    /// Negative if more was shipped than was ever received.
+    pub on_hand: i64,
+    /// Units held by reserved orders that have not shipped yet.
+    pub reserved: u64,
+}
+
+impl StockLevel {
+    fn new(product_id: ProductId) -> Self {
+        Self {
+            product_id,
+            on_hand: 0,
+            reserved: 0,
+        }
+    }
+
+    /// Returns the units that can still be promised to new orders.
+    pub fn available(&self) -> i64 {
+        self.on_hand - self.reserved as i64
+    }
+}
+
+/// Per-order tracking needed to move quantities on state changes.
+#[derive(Clone, Default)]
+struct OrderLines {
+    quantities: HashMap<ProductId, u32>,
+    reserved: bool,
+}
+
+/// Internal state for the stock levels view, shared with views built on it.
+#[derive(Clone)]
+pub(super) struct StockLevelsState {
+    pub(super) products: HashMap<ProductId, StockLevel>,
+    orders: HashMap<AggregateId, OrderLines>,
+    pub(super) position: ProjectionPosition,
+}
+
+impl StockLevelsState {
+    pub(super) fn new() -> Self {
+        Self {
+            products: HashMap::new(),
+            orders: HashMap::new(),
+            position: ProjectionPosition::zero(),
+        }
+    }
+
+    /// Applies an event and advances the position past it.
+    pub(super) fn apply(&mut self, event: &EventEnvelope) -> Result<()> {
+        match event.aggregate_type.as_str() {
+            "InventoryItem" => {
+                let inventory_event: InventoryEvent =
+                    serde_json::from_value(event.payload.clone())?;
+                StockLevelsView::handle_inventory_event(self, inventory_event);
+            }
+            "Order" => {
+                let order_event: OrderEvent = serde_json::from_value(event.payload.clone())?;
+                StockLevelsView::handle_order_event(self, event.aggregate_id, order_event);
+            }
+            _ => {}
+        }
+
+        self.position = self.position.advance_to(event);
+        Ok(())
+    }
+
+    fn level_mut(&mut self, product_id: &ProductId) -> &mut StockLevel {
+        self.products
+            .entry(product_id.clone())
+            .or_insert_with(|| StockLevel::new(product_id.clone()))
+    }
+
+    /// Adjusts reserved units for a line of an order that is already reserved.
+    fn adjust_reserved(&mut self, order_id: AggregateId, product_id: &ProductId, delta: i64) {
+        if self.orders.get(&order_id).is_some_and(|o| o.reserved) {
+            let level = self.level_mut(product_id);
+            level.reserved = (level.reserved as i64 + delta).max(0) as u64;
+        }
+    }
+}
+
+/// Read model view for stock levels.
+///
+/// Supply comes from inventory restocks and adjustments; reservations and
+/// shipments are derived from order events.
+#[derive(Clone)]
+pub struct StockLevelsView {
+    state: Arc<RwLock<StockLevelsState>>,
+}
+
+impl StockLevelsView {
+    /// Creates a new empty stock levels view.
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(RwLock::new(StockLevelsState::new())),
+        }
+    }
+
+    /// Gets the stock level for a product.
+    pub async fn get_product(&self, product_id: &ProductId) -> Option<StockLevel> {
+        self.state.read().await.products.get(product_id).cloned()
+    }
+
+    /// Gets stock levels for all known products.
+    pub async fn get_all_products(&self) -> Vec<StockLevel> {
+        self.state.read().await.products.values().cloned().collect()
+    }
+
+    fn handle_inventory_event(state: &mut StockLevelsState, event: InventoryEvent) {
+        match event {
+            InventoryEvent::StockRestocked(data) => {
+                state.level_mut(&data.product_id).on_hand += data.quantity as i64;
+            }
+            InventoryEvent::StockAdjusted(data) => {
+                state.level_mut(&data.product_id).on_hand += data.delta;
+            }
+            // Reservations are counted from the orders they're made for
+            InventoryEvent::StockReserved(_) | InventoryEvent::StockReleased(_) => {}
+        }
+    }
+
+    fn handle_order_event(state: &mut StockLevelsState, order_id: AggregateId, event: OrderEvent) {
+        match event {
+            OrderEvent::OrderCreated(_) => {
+                state.orders.insert(order_id, OrderLines::default());
+            }
+            OrderEvent::ItemAdded(data) => {
+                state
+                    .orders
+                    .entry(order_id)
+                    .or_default()
+                    .quantities
+                    .insert(data.product_id.clone(), data.quantity);
+                state.adjust_reserved(order_id, &data.product_id, data.quantity as i64);
+            }
+            OrderEvent::ItemRemoved(data) => {
+                let removed = state
+                    .orders
+                    .get_mut(&order_id)
+                    .and_then(|o| o.quantities.remove(&data.product_id));
+                if let Some(qty) = removed {
+                    state.adjust_reserved(order_id, &data.product_id, -(qty as i64));
+                }
+            }
+            OrderEvent::ItemQuantityUpdated(data) => {
+                if let Some(qty) = state
+                    .orders
+                    .get_mut(&order_id)
+                    .and_then(|o| o.quantities.get_mut(&data.product_id))
+                {
+                    *qty = data.new_quantity;
+                }
+                let delta = data.new_quantity as i64 - data.old_quantity as i64;
+                state.adjust_reserved(order_id, &data.product_id, delta);
+            }
+            OrderEvent::ItemSubstituted(data) => {
+                let removed = state.orders.get_mut(&order_id).and_then(|o| {
+                    let removed = o.quantities.remove(&data.original_product_id);
+                    o.quantities
+                        .insert(data.substitute_product_id.clone(), data.quantity);
+                    removed
+                });
+                if let Some(qty) = removed {
+                    state.adjust_reserved(order_id, &data.original_product_id, -(qty as i64));
+                }
+                state.adjust_reserved(order_id, &data.substitute_product_id, data.quantity as i64);
+            }
+            OrderEvent::OrderReserved(_) => {
+                let Some(order) = state.orders.get_mut(&order_id) else {
+                    return;
+                };
+                order.reserved = true;
+                let lines: Vec<_> = order
+                    .quantities
+                    .iter()
+                    .map(|(p, q)| (p.clone(), *q))
+                    .collect();
+                for (product_id, qty) in lines {
+                    state.level_mut(&product_id).reserved += qty as u64;
+                }
+            }
+            OrderEvent::OrderCompleted(_) => {
+                let Some(order) = state.orders.remove(&order_id) else {
+                    return;
+                };
+                for (product_id, qty) in order.quantities {
+                    let level = state.level_mut(&product_id);
+                    if order.reserved {
+                        level.reserved = level.reserved.saturating_sub(qty as u64);
+                    }
+                    level.on_hand -= qty as i64;
+                }
+            }
+            OrderEvent::OrderCancelled(_) => {
+                let Some(order) = state.orders.remove(&order_id) else {
+                    return;
+                };
+                if order.reserved {
+                    for (product_id, qty) in order.quantities {
+                        let level = state.level_mut(&product_id);
+                        level.reserved = level.reserved.saturating_sub(qty as u64);
+                    }
+                }
+            }
+            // Stock leaves on completion; later carrier updates don't move it
+            OrderEvent::ItemPriceAdjusted(_)
+            | OrderEvent::OrderSubmitted(_)
+            | OrderEvent::OrderProcessing(_)
+            | OrderEvent::OrderShipped(_)
+            | OrderEvent::OrderInTransit(_)
+            | OrderEvent::OrderDelivered(_)
+            | OrderEvent::DeliveryFailed(_)
+            | OrderEvent::TagAdded(_)
+            | OrderEvent::TagRemoved(_)
+            | OrderEvent::OrderMetadataSet(_)
+            | OrderEvent::ShippingAddressSet(_)
+            | OrderEvent::ShippingAddressUpdated(_)
+            | OrderEvent::ReservationRenewed(_) => {}
+        }
+    }
+}
+
+impl Default for StockLevelsView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Projection for StockLevelsView {
+    fn name(&self) -> &'static str {
+        "StockLevelsView"
+    }
+
+    async fn handle(&self, event: &EventEnvelope) -> Result<()> {
+        self.state.write().await.apply(event)
+    }
+
+    async fn handle_group(&self, events: &[EventEnvelope]) -> Result<()> {
+        apply_or_rollback(self, &self.state, events).await
+    }
+
+    async fn position(&self) -> ProjectionPosition {
+        self.state.read().await.position
+    }
+
+    async fn reset(&self) -> Result<()> {
+        *self.state.write().await = StockLevelsState::new();
+        Ok(())
+    }
+
+    async fn summary(&self) -> serde_json::Value {
+        let state = self.state.read().await;
+        serde_json::json!({
+            "events_processed": state.position.events_processed,
+            "products": state.products.len(),
+            "open_orders": state.orders.len(),
+        })
+    }
+}
+
+#[async_trait]
+impl ReadModel for StockLevelsView {
+    fn name(&self) -> &'static str {
+        "StockLevelsView"
+    }
+
+    async fn count(&self) -> Result<usize> {
+        let s = read_state(ReadModel::name(self), &self.state).await?;
+        Ok(s.products.len())
+    }
+
+    async fn memory_usage(&self) -> Result<usize> {
+        let s = read_state(ReadModel::name(self), &self.state).await?;
+        Ok(map_memory_usage(&s.products)
+            + map_memory_usage(&s.orders)
+            + s.orders
+                .values()
+                .map(|lines| map_memory_usage(&lines.quantities))
+                .sum::<usize>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use domain::{CustomerId, DomainEvent, InventoryItem, Money, OrderItem};
+
+    fn order_envelope(order_id: AggregateId, version: i64, event: &OrderEvent) -> EventEnvelope {
+        EventEnvelope::builder()
+            .aggregate_id(order_id)
+            .aggregate_type("Order")
+            .event_type(event.event_type())
+            .version(event_store::Version::new(version))
+            .payload(event)
+            .unwrap()
+            .build()
+    }
+
+    async fn restock(view: &StockLevelsView, sku: &str, quantity: u32, version: i64) {
+        let product_id = ProductId::new(sku);
+        let event = InventoryEvent::stock_restocked(product_id.clone(), quantity, None);
+        inventory_event(view, &product_id, &event, version).await;
+    }
+
+    async fn inventory_event(
+        view: &StockLevelsView,
+        product_id: &ProductId,
+        event: &InventoryEvent,
+        version: i64,
+    ) {
+        let envelope = EventEnvelope::builder()
+            .aggregate_id(InventoryItem::stream_id(product_id))
+            .aggregate_type("InventoryItem")
+            .event_type(event.event_type())
+            .version(event_store::Version::new(version))
+            .payload(event)
+            .unwrap()
+            .build();
+        view.handle(&envelope).await.unwrap();
+    }
+
+    async fn create_order(view: &StockLevelsView, sku: &str, quantity: u32) -> AggregateId {
+        let order_id = AggregateId::new();
+        let event = OrderEvent::order_created(order_id, CustomerId::new());
+        view.handle(&order_envelope(order_id, 1, &event))
+            .await
+            .unwrap();
+        let item = OrderItem::new(sku, "Widget", quantity, Money::from_cents(1000));
+        let event = OrderEvent::item_added(&item);
+        view.handle(&order_envelope(order_id, 2, &event))
+            .await
+            .unwrap();
+        order_id
+    }
+
+    #[tokio::test]
+    async fn test_restock_increases_on_hand() {
+        let view = StockLevelsView::new();
+        restock(&view, "SKU-001", 10, 1).await;
+        restock(&view, "SKU-001", 5, 2).await;
+
+        let level = view.get_product(&ProductId::new("SKU-001")).await.unwrap();
+        assert_eq!(level.on_hand, 15);
+        assert_eq!(level.reserved, 0);
+        assert_eq!(level.available(), 15);
+
+        // Adjustments change on-hand stock; item reservations are left to
+        // the order events
+        let product_id = ProductId::new("SKU-001");
+        let adjusted = InventoryEvent::stock_adjusted(product_id.clone(), -3, "damaged");
+        inventory_event(&view, &product_id, &adjusted, 3).await;
+        let reserved =
+            InventoryEvent::stock_reserved(product_id.clone(), "R-1", AggregateId::new(), 2);
+        inventory_event(&view, &product_id, &reserved, 4).await;
+        let level = view.get_product(&product_id).await.unwrap();
+        assert_eq!(level.on_hand, 12);
+        assert_eq!(level.reserved, 0);
+    }
+
+    #[tokio::test]
+    async fn test_reservation_then_completion_ships_stock() {
+        let view = StockLevelsView::new();
+        restock(&view, "SKU-001", 10, 1).await;
+        let order_id = create_order(&view, "SKU-001", 3).await;
+
+        // Draft orders don't hold stock
+        let level = view.get_product(&ProductId::new("SKU-001")).await.unwrap();
+        assert_eq!(level.available(), 10);
+
+        let event = OrderEvent::order_reserved(Default::default());
+        view.handle(&order_envelope(order_id, 3, &event))
+            .await
+            .unwrap();
+        let level = view.get_product(&ProductId::new("SKU-001")).await.unwrap();
+        assert_eq!(level.reserved, 3);
+        assert_eq!(level.available(), 7);
+
+        let event = OrderEvent::order_completed(None);
+        view.handle(&order_envelope(order_id, 4, &event))
+            .await
+            .unwrap();
+        let level = view.get_product(&ProductId::new("SKU-001")).await.unwrap();
+        assert_eq!(level.on_hand, 7);
+        assert_eq!(level.reserved, 0);
+        assert_eq!(level.available(), 7);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_reservation_releases_stock() {
+        let view = StockLevelsView::new();
+        restock(&view, "SKU-001", 10, 1).await;
+        let order_id = create_order(&view, "SKU-001", 4).await;
+
+        let event = OrderEvent::order_reserved(Default::default());
+        view.handle(&order_envelope(order_id, 3, &event))
+            .await
+            .unwrap();
+        let event = OrderEvent::order_cancelled("Payment failed", None);
+        view.handle(&order_envelope(order_id, 4, &event))
+            .await
+            .unwrap();
+
+        let level = view.get_product(&ProductId::new("SKU-001")).await.unwrap();
+        assert_eq!(level.on_hand, 10);
+        assert_eq!(level.reserved, 0);
+    }
+
+    #[tokio::test]
+    async fn test_reset_clears_state() {
+        let view = StockLevelsView::new();
+        restock(&view, "SKU-001", 10, 1).await;
+        view.reset().await.unwrap();
+
+        assert!(view.get_all_products().await.is_empty());
+        assert_eq!(view.position().await, ProjectionPosition::zero());
+    }
+}
@@ -1,13 +1,17 @@
 //! Integration tests: OrderService commands → ProjectionProcessor → all four views.
 
+use std::collections::BTreeMap;
+
 use common::AggregateId;
 use domain::{
-    AddItem, CancelOrder, CompleteOrder, CreateOrder, CustomerId, MarkReserved, Money,
-    OrderService, OrderState, ProductId, StartProcessing, SubmitOrder,
+    AddItem, CancelOrder, CompleteOrder, CreateOrder, Currency, CustomerId, MarkReserved, Money,
+    OrderService, OrderState, ProductId, StartProcessing, SubmitOrder, UpdateItemQuantity,
 };
 use event_store::InMemoryEventStore;
+use projections::registry::ORDER_HISTORY;
 use projections::{
-    CurrentOrdersView, CustomerOrdersView, InventoryView, OrderHistoryView, ProjectionProcessor,
+    CurrentOrdersView, CustomerOrdersView, EventFixture, InventoryView, OrderHistoryView,
+    ProjectionProcessor, ProjectionRegistry, ViewSpec, check_golden, probe_state,
 };
 
 /// Helper to set up service, processor, and all views.
@@ -75,7 +79,12 @@ async fn test_full_order_lifecycle_across_all_views() {
         .await
         .unwrap();
     service
-        .mark_reserved(MarkReserved::new(order_id, Some("RES-100".to_string())))
+        .mark_reserved(
+            MarkReserved::new(order_id).with_reservations(BTreeMap::from([
+                (ProductId::new("SKU-001"), "RES-100".to_string()),
+                (ProductId::new("SKU-002"), "RES-101".to_string()),
+            ])),
+        )
         .await
         .unwrap();
     service
@@ -108,7 +117,7 @@ async fn test_full_order_lifecycle_across_all_views() {
     assert_eq!(cust.total_orders, 1);
     assert_eq!(cust.completed_orders, 1);
     assert_eq!(cust.active_orders, 0);
-    assert_eq!(cust.total_spent.cents(), 5500);
+    assert_eq!(cust.spent_in(Currency::USD).cents(), 5500);
 
     // -- InventoryView
     let widget = inventory
@@ -116,14 +125,14 @@ async fn test_full_order_lifecycle_across_all_views() {
         .await
         .unwrap();
     assert_eq!(widget.quantity_completed, 3);
-    assert_eq!(widget.total_revenue.cents(), 3000);
+    assert_eq!(widget.revenue_in(Currency::USD).cents(), 3000);
 
     let gadget = inventory
         .get_product(&ProductId::new("SKU-002"))
         .await
         .unwrap();
     assert_eq!(gadget.quantity_completed, 1);
-    assert_eq!(gadget.total_revenue.cents(), 2500);
+    assert_eq!(gadget.revenue_in(Currency::USD).cents(), 2500);
 }
 
 #[tokio::test]
@@ -168,7 +177,7 @@ async fn test_cancelled_order_across_views() {
     let cust = customers.get_customer(customer_id).await.unwrap();
     assert_eq!(cust.cancelled_orders, 1);
     assert_eq!(cust.active_orders, 0);
-    assert_eq!(cust.total_spent, Money::zero());
+    assert!(cust.spent_by_currency.is_empty());
 
     // Inventory: demand removed
     let widget = inventory
@@ -279,7 +288,7 @@ async fn test_rebuild_produces_same_state() {
         .await
         .unwrap();
     service
-        .mark_reserved(MarkReserved::new(order_id, None))
+        .mark_reserved(MarkReserved::new(order_id))
         .await
         .unwrap();
     service
@@ -330,7 +339,10 @@ async fn test_rebuild_produces_same_state() {
         customer_stats.completed_orders
     );
     assert_eq!(rebuilt_stats.active_orders, customer_stats.active_orders);
-    assert_eq!(rebuilt_stats.total_spent, customer_stats.total_spent);
+    assert_eq!(
+        rebuilt_stats.spent_by_currency,
+        customer_stats.spent_by_currency
+    );
 
     assert_eq!(
         inventory
@@ -388,5 +400,330 @@ async fn test_process_event_delivers_to_all_projections() {
     assert_eq!(inventory.position().await.events_processed, 1);
 }
 
-use event_store::EventStore;
+#[tokio::test]
+async fn test_failed_event_rolls_back_its_append_group() {
+    let (service, processor, current, history, customers, inventory) = setup();
+    let customer_id = CustomerId::new();
+    let healthy = CreateOrder::for_customer(customer_id);
+    let healthy_id = healthy.order_id;
+    service.create_order(healthy).await.unwrap();
+    processor.run_catch_up().await.unwrap();
+
+    // One append: a valid OrderCreated followed by an ItemAdded no view can read
+    let store = InMemoryEventStore::new();
+    let order_id = AggregateId::new();
+    let created = domain::OrderEvent::order_created(order_id, customer_id);
+    let events = vec![
+        EventEnvelope::builder()
+            .aggregate_id(order_id)
+            .aggregate_type("Order")
+            .event_type("OrderCreated")
+            .version(Version::new(1))
+            .payload(&created)
+            .unwrap()
+            .build(),
+        EventEnvelope::builder()
+            .aggregate_id(order_id)
+            .aggregate_type("Order")
+            .event_type("ItemAdded")
+            .version(Version::new(2))
+            .payload_raw(serde_json::json!({"type": "ItemAdded", "data": {"quantity": "two"}}))
+            .build(),
+    ];
+    store
+        .append(events.clone(), AppendOptions::new())
+        .await
+        .unwrap();
+    let events = store.get_events_for_aggregate(order_id).await.unwrap();
+    assert!(processor.process_group(&events).await.is_err());
+
+    // Neither event of the group is visible, and earlier state is intact
+    assert!(current.get_order(order_id).await.is_none());
+    assert!(current.get_order(healthy_id).await.is_some());
+    assert_eq!(
+        customers
+            .get_customer(customer_id)
+            .await
+            .unwrap()
+            .total_orders,
+        1
+    );
+    assert_eq!(current.position().await.last_sequence, 1);
+    assert_eq!(history.position().await.last_sequence, 1);
+    assert_eq!(customers.position().await.last_sequence, 1);
+    assert_eq!(inventory.position().await.last_sequence, 1);
+}
+
+#[tokio::test]
+async fn test_time_travel_reproduces_historical_state() {
+    let store = InMemoryEventStore::new();
+    let service = OrderService::new(store.clone());
+
+    let cmd = CreateOrder::for_customer(CustomerId::new());
+    let order_id = cmd.order_id;
+    service.create_order(cmd).await.unwrap();
+    service
+        .add_item(AddItem::with_details(
+            order_id,
+            "SKU-001",
+            "Widget",
+            2,
+            Money::from_cents(1000),
+        ))
+        .await
+        .unwrap();
+
+    // The "incident" happens here; later events must not leak into replays
+    let incident = store.head_sequence().await.unwrap();
+    service
+        .cancel_order(CancelOrder::new(order_id, "Changed mind", None))
+        .await
+        .unwrap();
+
+    let past = TimeTravelEventStore::at_sequence(store, incident);
+
+    let current = CurrentOrdersView::new();
+    let inventory = InventoryView::new();
+    let mut processor = ProjectionProcessor::new(past.clone());
+    processor.register(Box::new(current.clone()));
+    processor.register(Box::new(inventory.clone()));
+    processor.run_catch_up().await.unwrap();
+
+    let summary = current.get_order(order_id).await.unwrap();
+    assert_eq!(summary.state, OrderState::Draft);
+    let widget = inventory
+        .get_product(&ProductId::new("SKU-001"))
+        .await
+        .unwrap();
+    assert_eq!(widget.quantity_in_active_orders, 2);
+
+    let order = OrderService::new(past)
+        .get_order(order_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(order.state(), OrderState::Draft);
+    assert_eq!(order.item_count(), 1);
+}
+
+use event_store::{AppendOptions, EventEnvelope, EventStore, TimeTravelEventStore, Version};
 use projections::Projection;
+
+#[tokio::test]
+async fn test_shadow_overflow_history_agrees_with_memory_view() {
+    let store = InMemoryEventStore::new();
+    let service = OrderService::new(store.clone());
+    let registry = ProjectionRegistry::with_builtin_views();
+    let mut set = registry.build(&[ViewSpec::memory(ORDER_HISTORY)]).unwrap();
+    let stats = registry
+        .build_shadow(&"order_history:overflow=1".parse().unwrap(), &mut set, 1.0)
+        .unwrap();
+    let mut processor = ProjectionProcessor::new(store);
+    for projection in set.projections {
+        processor.register(projection);
+    }
+
+    for _ in 0..3 {
+        let cmd = CreateOrder::for_customer(CustomerId::new());
+        let order_id = cmd.order_id;
+        service.create_order(cmd).await.unwrap();
+        service
+            .cancel_order(CancelOrder::new(order_id, "Customer changed mind", None))
+            .await
+            .unwrap();
+    }
+    processor.run_catch_up().await.unwrap();
+
+    // The shadow evicted older orders to overflow but still answers the
+    // same; each order's events arrive as one group, compared once
+    assert_eq!(stats.matched(), 3);
+    assert_eq!(stats.diverged(), 0);
+    assert_eq!(stats.errors(), 0);
+    let history = set.views.get::<OrderHistoryView>().unwrap();
+    assert_eq!(history.get_all_history().await.len(), 3);
+}
+
+/// Recorded events fed to the golden-state tests.
+const FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/orders.json");
+
+/// Golden snapshots of each view's state after the fixture.
+const GOLDEN_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden");
+
+/// Runs the order scenario the fixture was recorded from: a completed order
+/// with a quantity change, a cancelled one, and an open one, across two
+/// customers.
+async fn record_fixture() -> EventFixture {
+    let store = InMemoryEventStore::new();
+    let service = OrderService::new(store.clone());
+    let (alice, bob) = (CustomerId::new(), CustomerId::new());
+
+    let cmd = CreateOrder::for_customer(alice);
+    let completed = cmd.order_id;
+    service.create_order(cmd).await.unwrap();
+    for (sku, name, quantity, cents) in [
+        ("SKU-001", "Widget", 3, 1000),
+        ("SKU-002", "Gadget", 1, 2500),
+    ] {
+        service
+            .add_item(AddItem::with_details(
+                completed,
+                sku,
+                name,
+                quantity,
+                Money::from_cents(cents),
+            ))
+            .await
+            .unwrap();
+    }
+    service
+        .update_item_quantity(UpdateItemQuantity::new(completed, "SKU-001", 2))
+        .await
+        .unwrap();
+    service
+        .submit_order(SubmitOrder::new(completed))
+        .await
+        .unwrap();
+    service
+        .mark_reserved(
+            MarkReserved::new(completed).with_reservations(BTreeMap::from([
+                (ProductId::new("SKU-001"), "RES-1".to_string()),
+                (ProductId::new("SKU-002"), "RES-2".to_string()),
+            ])),
+        )
+        .await
+        .unwrap();
+    service
+        .start_processing(StartProcessing::new(completed, Some("PAY-1".to_string())))
+        .await
+        .unwrap();
+    service
+        .complete_order(CompleteOrder::new(completed, Some("TRACK-1".to_string())))
+        .await
+        .unwrap();
+
+    let cmd = CreateOrder::for_customer(alice);
+    let cancelled = cmd.order_id;
+    service.create_order(cmd).await.unwrap();
+    service
+        .add_item(AddItem::with_details(
+            cancelled,
+            "SKU-002",
+            "Gadget",
+            4,
+            Money::from_cents(2500),
+        ))
+        .await
+        .unwrap();
+    service
+        .cancel_order(CancelOrder::new(cancelled, "Found it cheaper", None))
+        .await
+        .unwrap();
+
+    let cmd = CreateOrder::for_customer(bob);
+    let open = cmd.order_id;
+    service.create_order(cmd).await.unwrap();
+    service
+        .add_item(AddItem::with_details(
+            open,
+            "SKU-001",
+            "Widget",
+            5,
+            Money::from_cents(1000),
+        ))
+        .await
+        .unwrap();
+    service.submit_order(SubmitOrder::new(open)).await.unwrap();
+
+    EventFixture::record(&store).await.unwrap()
+}
+
+/// Records the fixture afresh. Run with `--ignored`, then rerun the
+/// golden-state tests with `UPDATE_GOLDEN_STATE=1` to update the golden
+/// files to match.
+#[tokio::test]
+#[ignore = "rewrites the recorded fixture"]
+async fn record_golden_fixture() {
+    record_fixture().await.save(FIXTURE).unwrap();
+}
+
+async fn load_fixture() -> EventFixture {
+    EventFixture::load(FIXTURE).unwrap()
+}
+
+fn assert_golden(name: &str, state: &serde_json::Value) {
+    let path = std::path::Path::new(GOLDEN_DIR).join(name);
+    if let Err(e) = check_golden(&path, state) {
+        panic!("{e}");
+    }
+}
+
+#[tokio::test]
+async fn test_order_views_match_golden_state() {
+    let fixture = load_fixture().await;
+    let current = CurrentOrdersView::new();
+    let history = OrderHistoryView::new();
+    fixture.feed(&current).await.unwrap();
+    fixture.feed(&history).await.unwrap();
+
+    assert_golden(
+        "current_orders.json",
+        &probe_state(&current, &fixture).await.unwrap(),
+    );
+    assert_golden(
+        "order_history.json",
+        &probe_state(&history, &fixture).await.unwrap(),
+    );
+}
+
+#[tokio::test]
+async fn test_summary_views_match_golden_state() {
+    let fixture = load_fixture().await;
+    let customers = CustomerOrdersView::new();
+    let inventory = InventoryView::new();
+    fixture.feed(&customers).await.unwrap();
+    fixture.feed(&inventory).await.unwrap();
+
+    let cents = |amounts: &BTreeMap<Currency, Money>| -> BTreeMap<String, i64> {
+        amounts
+            .iter()
+            .map(|(currency, amount)| (currency.to_string(), amount.cents()))
+            .collect()
+    };
+
+    let customers: BTreeMap<String, serde_json::Value> = customers
+        .get_all_customers()
+        .await
+        .into_iter()
+        .map(|c| {
+            let state = serde_json::json!({
+                "total_orders": c.total_orders,
+                "active_orders": c.active_orders,
+                "completed_orders": c.completed_orders,
+                "delivered_orders": c.delivered_orders,
+                "cancelled_orders": c.cancelled_orders,
+                "spent_cents": cents(&c.spent_by_currency),
+            });
+            (c.customer_id.to_string(), state)
+        })
+        .collect();
+    assert_golden("customer_orders.json", &serde_json::json!(customers));
+
+    let products: BTreeMap<String, serde_json::Value> = inventory
+        .get_all_products()
+        .await
+        .into_iter()
+        .map(|p| {
+            let state = serde_json::json!({
+                "product_name": p.product_name,
+                "total_quantity_ordered": p.total_quantity_ordered,
+                "quantity_in_active_orders": p.quantity_in_active_orders,
+                "quantity_reserved": p.quantity_reserved,
+                "quantity_completed": p.quantity_completed,
+                "revenue_cents": cents(&p.revenue_by_currency),
+                "order_count": p.order_count,
+            });
+            (p.product_id.as_str().to_string(), state)
+        })
+        .collect();
+    assert_golden("inventory.json", &serde_json::json!(products));
+}
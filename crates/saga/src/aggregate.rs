@@ -1,14 +1,49 @@
 //! Saga instance aggregate.
 
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
 use common::AggregateId;
-use domain::Aggregate;
+use domain::{Aggregate, ProductId};
 use event_store::Version;
 use serde::{Deserialize, Serialize};
 
 use crate::error::SagaError;
-use crate::events::SagaEvent;
+use crate::events::{SagaEvent, StepTiming};
+use crate::failure::{FailureKind, ServiceFailure};
 use crate::state::SagaState;
 
+/// One attempt at a saga step, as recorded by the step's events.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StepAttempt {
+    pub step_name: String,
+    /// Which attempt at the step this was, starting at 1.
+    pub attempt: u32,
+    /// Unset on events written before steps were timed.
+    pub started_at: Option<DateTime<Utc>>,
+    /// Unset while the attempt is running, or if it wasn't timed.
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Time spent waiting on the external service call.
+    pub service_latency_ms: Option<u64>,
+    /// Error the attempt failed with, if it failed.
+    pub error: Option<String>,
+}
+
+impl StepAttempt {
+    /// Returns the time between the attempt starting and finishing.
+    pub fn duration_ms(&self) -> Option<i64> {
+        Some((self.finished_at? - self.started_at?).num_milliseconds())
+    }
+
+    fn finish(&mut self, timing: Option<StepTiming>) {
+        if let Some(timing) = timing {
+            self.started_at = Some(timing.started_at);
+            self.finished_at = Some(timing.finished_at);
+            self.service_latency_ms = Some(timing.service_latency_ms);
+        }
+    }
+}
+
 /// An event-sourced saga instance.
 ///
 /// Tracks the state of a saga execution including completed steps
@@ -18,11 +53,22 @@ pub struct SagaInstance {
     id: Option<AggregateId>,
     version: Version,
     saga_type: String,
+    /// Version of the saga definition recorded when the saga started.
+    #[serde(default = "crate::events::initial_definition_version")]
+    definition_version: u32,
     order_id: Option<AggregateId>,
     state: SagaState,
     current_step: usize,
     completed_steps: Vec<String>,
-    /// Reservation ID from inventory service.
+    /// Steps whose effects were undone by compensation.
+    #[serde(default)]
+    compensated_steps: Vec<String>,
+    /// Reservation ID per line item from inventory service.
+    #[serde(default)]
+    reservations: BTreeMap<ProductId, String>,
+    /// Whole-order reservation ID from sagas started before reservations
+    /// were tracked per line item.
+    #[serde(default)]
     reservation_id: Option<String>,
     /// Payment ID from payment service.
     payment_id: Option<String>,
@@ -30,6 +76,20 @@ pub struct SagaInstance {
     tracking_number: Option<String>,
     /// Reason for failure, if any.
     failure_reason: Option<String>,
+    /// Customer-facing classification of the step failure, if a step failed.
+    #[serde(default)]
+    failure_kind: Option<FailureKind>,
+    /// Typed service error the saga's step failed with, if a service
+    /// reported one.
+    #[serde(default)]
+    failure_cause: Option<ServiceFailure>,
+    /// Every step attempt in the order it started.
+    #[serde(default)]
+    step_attempts: Vec<StepAttempt>,
+    /// Step put off by its scheduling policy and when it may run, until
+    /// the step starts.
+    #[serde(default)]
+    deferred: Option<(String, DateTime<Utc>)>,
 }
 
 impl Aggregate for SagaInstance {
@@ -58,13 +118,33 @@ impl Aggregate for SagaInstance {
                 self.id = Some(data.saga_id);
                 self.order_id = Some(data.order_id);
                 self.saga_type = data.saga_type;
+                self.definition_version = data.definition_version;
                 self.state = SagaState::Running;
             }
-            SagaEvent::StepStarted(_) => {
-                self.current_step += 1;
+            SagaEvent::StepDeferred(data) => {
+                self.deferred = Some((data.step_name, data.until));
+            }
+            SagaEvent::StepStarted(data) => {
+                self.deferred = None;
+                // Retries stay on the same step
+                if data.attempt <= 1 {
+                    self.current_step += 1;
+                }
+                self.step_attempts.push(StepAttempt {
+                    step_name: data.step_name,
+                    attempt: data.attempt,
+                    started_at: data.started_at,
+                    finished_at: None,
+                    service_latency_ms: None,
+                    error: None,
+                });
             }
             SagaEvent::StepCompleted(data) => {
+                if let Some(attempt) = self.running_attempt_mut(&data.step_name) {
+                    attempt.finish(data.timing);
+                }
                 self.completed_steps.push(data.step_name);
+                self.reservations.extend(data.reservations);
                 if let Some(rid) = data.reservation_id {
                     self.reservation_id = Some(rid);
                 }
@@ -76,17 +156,31 @@ impl Aggregate for SagaInstance {
                 }
             }
             SagaEvent::StepFailed(data) => {
-                self.failure_reason = Some(data.error);
+                if let Some(attempt) = self.running_attempt_mut(&data.step_name) {
+                    attempt.finish(data.timing);
+                    attempt.error = Some(data.error.clone());
+                }
+                // A failure that was retried isn't the saga's failure
+                if !data.retrying {
+                    self.failure_reason = Some(data.error);
+                    self.failure_kind = Some(data.kind);
+                    self.failure_cause = data.cause;
+                }
             }
             SagaEvent::CompensationStarted(_) => {
                 self.state = SagaState::Compensating;
             }
-            SagaEvent::CompensationStepCompleted(_) => {
-                // Compensation step tracked but no state change needed
+            SagaEvent::CompensationStepCompleted(data) => {
+                self.compensated_steps.push(data.step_name);
             }
             SagaEvent::CompensationStepFailed(_) => {
                 // Compensation failures are logged but don't stop the chain
             }
+            SagaEvent::ReservationReplaced(data) => {
+                self.reservations.remove(&data.original_product_id);
+                self.reservations
+                    .insert(data.substitute_product_id, data.reservation_id);
+            }
             SagaEvent::SagaCompleted(_) => {
                 self.state = SagaState::Completed;
             }
@@ -115,16 +209,40 @@ impl SagaInstance {
         &self.saga_type
     }
 
+    /// Returns the version of the saga definition the saga started with.
+    pub fn definition_version(&self) -> u32 {
+        self.definition_version
+    }
+
     /// Returns the list of completed step names.
     pub fn completed_steps(&self) -> &[String] {
         &self.completed_steps
     }
 
-    /// Returns the reservation ID, if set.
+    /// Returns the steps that were undone by compensation.
+    pub fn compensated_steps(&self) -> &[String] {
+        &self.compensated_steps
+    }
+
+    /// Returns the reservation ID held for each line item.
+    pub fn reservations(&self) -> &BTreeMap<ProductId, String> {
+        &self.reservations
+    }
+
+    /// Returns the whole-order reservation ID recorded by older sagas.
     pub fn reservation_id(&self) -> Option<&str> {
         self.reservation_id.as_deref()
     }
 
+    /// Returns every reservation ID compensation must release: one per line
+    /// item, plus the whole-order reservation of older sagas.
+    pub fn reservation_ids(&self) -> impl Iterator<Item = &str> {
+        self.reservations
+            .values()
+            .map(String::as_str)
+            .chain(self.reservation_id.as_deref())
+    }
+
     /// Returns the payment ID, if set.
     pub fn payment_id(&self) -> Option<&str> {
         self.payment_id.as_deref()
@@ -139,6 +257,57 @@ impl SagaInstance {
     pub fn failure_reason(&self) -> Option<&str> {
         self.failure_reason.as_deref()
     }
+
+    /// Returns the customer-facing classification of the step failure, if a
+    /// step failed.
+    pub fn failure_kind(&self) -> Option<FailureKind> {
+        self.failure_kind
+    }
+
+    /// Returns the typed service error the saga's step failed with, if a
+    /// service reported one.
+    pub fn failure_cause(&self) -> Option<&ServiceFailure> {
+        self.failure_cause.as_ref()
+    }
+
+    /// Returns every step attempt with its timing, in the order they started.
+    pub fn step_attempts(&self) -> &[StepAttempt] {
+        &self.step_attempts
+    }
+
+    /// Returns how many attempts have been started at a step.
+    pub fn attempts(&self, step_name: &str) -> u32 {
+        self.step_attempts
+            .iter()
+            .filter(|a| a.step_name == step_name)
+            .count() as u32
+    }
+
+    /// Returns the latest step attempt if it started but neither completed
+    /// nor failed, as when the process stopped during the service call.
+    pub fn attempt_in_flight(&self) -> Option<&StepAttempt> {
+        self.step_attempts
+            .last()
+            .filter(|a| a.error.is_none() && !self.completed_steps.contains(&a.step_name))
+    }
+
+    /// Returns when `step_name` may run, if its scheduling policy put it
+    /// off and it hasn't started since.
+    pub fn deferred_until(&self, step_name: &str) -> Option<DateTime<Utc>> {
+        self.deferred
+            .as_ref()
+            .filter(|(step, _)| step == step_name)
+            .map(|(_, until)| *until)
+    }
+
+    /// Returns the latest attempt at a step, if it hasn't finished.
+    fn running_attempt_mut(&mut self, step_name: &str) -> Option<&mut StepAttempt> {
+        self.step_attempts
+            .iter_mut()
+            .rev()
+            .find(|a| a.step_name == step_name)
+            .filter(|a| a.finished_at.is_none() && a.error.is_none())
+    }
 }
 
 #[cfg(test)]
@@ -200,12 +369,22 @@ mod tests {
 
         saga.apply(SagaEvent::step_completed(
             order_fulfillment::STEP_RESERVE_INVENTORY,
-            Some("RES-123".to_string()),
+            BTreeMap::from([
+                (ProductId::new("SKU-001"), "RES-123".to_string()),
+                (ProductId::new("SKU-002"), "RES-124".to_string()),
+            ]),
             None,
             None,
         ));
         assert_eq!(saga.completed_steps(), &["reserve_inventory"]);
-        assert_eq!(saga.reservation_id(), Some("RES-123"));
+        assert_eq!(
+            saga.reservations().get(&ProductId::new("SKU-002")),
+            Some(&"RES-124".to_string())
+        );
+        assert_eq!(
+            saga.reservation_ids().collect::<Vec<_>>(),
+            ["RES-123", "RES-124"]
+        );
 
         // Step 2: Process payment
         saga.apply(SagaEvent::step_started(
@@ -215,7 +394,7 @@ mod tests {
 
         saga.apply(SagaEvent::step_completed(
             order_fulfillment::STEP_PROCESS_PAYMENT,
-            None,
+            BTreeMap::new(),
             Some("PAY-456".to_string()),
             None,
         ));
@@ -230,7 +409,7 @@ mod tests {
 
         saga.apply(SagaEvent::step_completed(
             order_fulfillment::STEP_CREATE_SHIPMENT,
-            None,
+            BTreeMap::new(),
             None,
             Some("TRACK-789".to_string()),
         ));
@@ -261,7 +440,7 @@ mod tests {
         ));
         saga.apply(SagaEvent::step_completed(
             order_fulfillment::STEP_RESERVE_INVENTORY,
-            Some("RES-123".to_string()),
+            BTreeMap::from([(ProductId::new("SKU-001"), "RES-123".to_string())]),
             None,
             None,
         ));
@@ -285,6 +464,10 @@ mod tests {
         saga.apply(SagaEvent::compensation_step_completed(
             order_fulfillment::STEP_RESERVE_INVENTORY,
         ));
+        assert_eq!(
+            saga.compensated_steps(),
+            [order_fulfillment::STEP_RESERVE_INVENTORY]
+        );
 
         // Saga failed
         saga.apply(SagaEvent::saga_failed("Payment failed: insufficient funds"));
@@ -329,6 +512,40 @@ mod tests {
         assert_eq!(saga.state(), SagaState::Compensating);
     }
 
+    #[test]
+    fn test_step_attempts_record_timing() {
+        let mut saga = SagaInstance::default();
+        saga.apply(SagaEvent::saga_started(
+            make_saga_id(),
+            make_order_id(),
+            order_fulfillment::SAGA_TYPE,
+        ));
+
+        let started_at = Utc::now();
+        saga.apply(SagaEvent::step_attempt_started(
+            order_fulfillment::STEP_PROCESS_PAYMENT,
+            1,
+            started_at,
+        ));
+        assert_eq!(saga.attempts(order_fulfillment::STEP_PROCESS_PAYMENT), 1);
+        assert!(saga.step_attempts()[0].finished_at.is_none());
+
+        saga.apply(
+            SagaEvent::step_failed(order_fulfillment::STEP_PROCESS_PAYMENT, "card declined")
+                .with_timing(StepTiming {
+                    attempt: 1,
+                    started_at,
+                    finished_at: started_at + chrono::Duration::milliseconds(250),
+                    service_latency_ms: 200,
+                }),
+        );
+
+        let attempt = &saga.step_attempts()[0];
+        assert_eq!(attempt.duration_ms(), Some(250));
+        assert_eq!(attempt.service_latency_ms, Some(200));
+        assert_eq!(attempt.error.as_deref(), Some("card declined"));
+    }
+
     #[test]
     fn test_aggregate_type() {
         assert_eq!(SagaInstance::aggregate_type(), "OrderFulfillmentSaga");
@@ -350,7 +567,7 @@ mod tests {
         ));
         saga.apply(SagaEvent::step_completed(
             order_fulfillment::STEP_RESERVE_INVENTORY,
-            Some("RES-1".into()),
+            BTreeMap::from([(ProductId::new("SKU-001"), "RES-1".to_string())]),
             None,
             None,
         ));
@@ -360,6 +577,29 @@ mod tests {
 
         assert_eq!(deserialized.id(), Some(saga_id));
         assert_eq!(deserialized.state(), SagaState::Running);
-        assert_eq!(deserialized.reservation_id(), Some("RES-1"));
+        assert_eq!(deserialized.reservations(), saga.reservations());
+    }
+
+    #[test]
+    fn test_whole_order_reservation_is_released_with_lines() {
+        let mut saga = SagaInstance::default();
+        saga.apply(SagaEvent::saga_started(
+            make_saga_id(),
+            make_order_id(),
+            order_fulfillment::SAGA_TYPE,
+        ));
+        let legacy = serde_json::json!({
+            "type": "StepCompleted",
+            "data": {
+                "step_name": order_fulfillment::STEP_RESERVE_INVENTORY,
+                "reservation_id": "RES-0007",
+                "payment_id": null,
+                "tracking_number": null,
+            }
+        });
+        saga.apply(serde_json::from_value(legacy).unwrap());
+
+        assert!(saga.reservations().is_empty());
+        assert_eq!(saga.reservation_ids().collect::<Vec<_>>(), ["RES-0007"]);
     }
 }
@@ -0,0 +1,85 @@
+//! Causation metadata linking order events to the saga steps behind them.
+//!
+//! Order transitions the saga drives (reserved, processing, completed, and
+//! cancelled on failure) carry the saga, the step, and the saga event that
+//! decided them, so audit tooling can walk from an order event back to the
+//! saga decision.
+
+use std::collections::HashMap;
+
+use common::AggregateId;
+use domain::CommandMetadata;
+use event_store::EventId;
+use uuid::Uuid;
+
+/// Metadata key holding the ID of the saga that caused an event.
+pub const SAGA_ID_KEY: &str = "saga_id";
+
+/// Metadata key holding the saga step that caused an event.
+pub const SAGA_STEP_KEY: &str = "saga_step";
+
+/// Metadata key holding the ID of the saga event that caused an event.
+pub const CAUSATION_ID_KEY: &str = "causation_id";
+
+/// The saga decision that caused an order event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SagaCausation {
+    pub saga_id: AggregateId,
+    /// The step the decision belongs to; for a cancellation, the step whose
+    /// failure started compensation.
+    pub step: String,
+    /// The saga event recording the decision.
+    pub event_id: EventId,
+}
+
+impl SagaCausation {
+    /// Creates a causation for the saga event `event_id` of `step`.
+    pub fn new(saga_id: AggregateId, step: impl Into<String>, event_id: EventId) -> Self {
+        Self {
+            saga_id,
+            step: step.into(),
+            event_id,
+        }
+    }
+
+    /// Returns the metadata recorded on caused events.
+    pub fn to_metadata(&self) -> CommandMetadata {
+        CommandMetadata::from([
+            (
+                SAGA_ID_KEY.to_string(),
+                serde_json::json!(self.saga_id.to_string()),
+            ),
+            (SAGA_STEP_KEY.to_string(), serde_json::json!(self.step)),
+            (
+                CAUSATION_ID_KEY.to_string(),
+                serde_json::json!(self.event_id.to_string()),
+            ),
+        ])
+    }
+
+    /// Reads the causation back from event metadata, or None if the event
+    /// wasn't caused by a saga.
+    pub fn from_metadata(metadata: &HashMap<String, serde_json::Value>) -> Option<Self> {
+        let field = |key| metadata.get(key).and_then(|value| value.as_str());
+        Some(Self {
+            saga_id: AggregateId::from(Uuid::parse_str(field(SAGA_ID_KEY)?).ok()?),
+            step: field(SAGA_STEP_KEY)?.to_string(),
+            event_id: EventId::from(Uuid::parse_str(field(CAUSATION_ID_KEY)?).ok()?),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metadata_round_trip() {
+        let causation = SagaCausation::new(AggregateId::new(), "process_payment", EventId::new());
+
+        let metadata = causation.to_metadata();
+
+        assert_eq!(SagaCausation::from_metadata(&metadata), Some(causation));
+        assert_eq!(SagaCausation::from_metadata(&HashMap::new()), None);
+    }
+}
@@ -1,24 +1,62 @@
 //! Saga coordinator for orchestrating multi-step sagas.
 
-use common::AggregateId;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Instant;
+
+use chrono::{DateTime, Utc};
+use common::{AggregateId, Clock, SystemClock, Tunable};
 use domain::{
-    Aggregate, CancelOrder, CompleteOrder, DomainEvent, MarkReserved, OrderService, OrderState,
-    StartProcessing, SubmitOrder,
+    Address, Aggregate, CancelOrder, CommandMetadata, CompleteOrder, CustomerId, DomainError,
+    DomainEvent, MarkReserved, Money, Order, OrderEvent, OrderService, OrderState, ProductId,
+    RenewReservations, ShippedItem, StartProcessing, SubmitOrder, SubstituteItem,
 };
-use event_store::{AppendOptions, EventEnvelope, EventStore, Version};
+use event_store::{AppendOptions, EnvelopeFactory, EventId, EventStore, Version};
+use futures_util::{Stream, StreamExt};
+use tokio_util::sync::CancellationToken;
 
 use crate::aggregate::SagaInstance;
+use crate::causation::SagaCausation;
+use crate::definition::{SagaDefinition, SagaDefinitions};
+use crate::details::latest_saga_id;
+use crate::dry_run::{DryRunReport, DryRunStep, StepCheck};
 use crate::error::SagaError;
-use crate::events::SagaEvent;
+use crate::events::{SagaEvent, StepTiming};
+use crate::graph::SagaGraph;
 use crate::order_fulfillment;
-use crate::services::inventory::{InventoryService, ReservationItem};
+use crate::reconcile::{
+    self, Correction, ReconciliationOutcome, ReconciliationReport, ReservationCheck,
+    ReservationReport,
+};
+use crate::schedule::{StepSchedule, StepSchedulingPolicy};
+use crate::services::inventory::{
+    InventoryError, InventoryService, ReservationItem, ReservationStatus,
+};
+use crate::services::notification::{CustomerNotification, CustomerNotifier};
 use crate::services::payment::PaymentService;
 use crate::services::shipping::ShippingService;
+use crate::state::SagaState;
+use crate::trace::{SagaTrace, TraceContext};
+
+/// Attempts made at a step or compensation when no limit is set.
+pub const DEFAULT_MAX_STEP_ATTEMPTS: u32 = 3;
+
+/// IDs of sagas as they start, from
+/// [`SagaCoordinator::follow_running_sagas`].
+pub type SagaStarts = Pin<Box<dyn Stream<Item = Result<AggregateId, SagaError>> + Send>>;
 
 /// Orchestrates the execution of order fulfillment sagas.
 ///
 /// The coordinator drives a 3-step saga (inventory → payment → shipping)
 /// with compensating transactions on failure. The saga itself is event-sourced.
+///
+/// A step that fails with a retryable service error, such as the service
+/// being unavailable, is attempted again right away, up to the attempt
+/// limit. Any other failure, like a declined payment or missing stock,
+/// compensates at once since repeating the call won't change the answer.
+/// Compensations are retried the same way.
 pub struct SagaCoordinator<S, I, P, Sh>
 where
     S: EventStore,
@@ -31,6 +69,13 @@ where
     inventory: I,
     payment: P,
     shipping: Sh,
+    definitions: SagaDefinitions,
+    max_step_attempts: Tunable<u32>,
+    notifiers: Vec<Arc<dyn CustomerNotifier>>,
+    resumable: bool,
+    envelopes: EnvelopeFactory,
+    step_policies: HashMap<String, Arc<dyn StepSchedulingPolicy>>,
+    clock: Arc<dyn Clock>,
 }
 
 impl<S, I, P, Sh> SagaCoordinator<S, I, P, Sh>
@@ -49,6 +94,198 @@ where
             inventory,
             payment,
             shipping,
+            definitions: SagaDefinitions::builtin(),
+            max_step_attempts: Tunable::new(DEFAULT_MAX_STEP_ATTEMPTS),
+            notifiers: Vec::new(),
+            resumable: false,
+            envelopes: EnvelopeFactory::new().for_aggregate(SagaInstance::aggregate_type()),
+            step_policies: HashMap::new(),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Sets how many times a step or compensation is attempted before it
+    /// counts as failed, at least once.
+    ///
+    /// Pass a shared [`Tunable`] to change the limit while sagas run; each
+    /// attempt reads the current value.
+    pub fn with_max_step_attempts(mut self, attempts: impl Into<Tunable<u32>>) -> Self {
+        self.max_step_attempts = attempts.into();
+        self
+    }
+
+    /// Returns how many times a step or compensation is attempted.
+    pub fn max_step_attempts(&self) -> u32 {
+        self.max_step_attempts.value().max(1)
+    }
+
+    /// Uses `definitions` in place of the built-in saga definitions.
+    ///
+    /// The registry is shared, so versions registered on it later are used
+    /// by this coordinator without a restart.
+    pub fn with_definitions(mut self, definitions: SagaDefinitions) -> Self {
+        self.definitions = definitions;
+        self
+    }
+
+    /// Returns the saga definitions this coordinator runs.
+    pub fn definitions(&self) -> &SagaDefinitions {
+        &self.definitions
+    }
+
+    /// Returns the inventory service sagas reserve stock with.
+    pub fn inventory(&self) -> &I {
+        &self.inventory
+    }
+
+    /// Leaves sagas stopped between steps running when they're reconciled,
+    /// for a [`SagaRunner`](crate::SagaRunner) to resume, instead of
+    /// compensating them.
+    pub fn with_resumable_sagas(mut self) -> Self {
+        self.resumable = true;
+        self
+    }
+
+    /// Builds saga envelopes, and those of the order events sagas cause,
+    /// with `factory`.
+    pub fn with_envelope_factory(mut self, factory: EnvelopeFactory) -> Self {
+        self.envelopes = factory.for_aggregate(SagaInstance::aggregate_type());
+        self.order_service = self.order_service.with_envelope_factory(factory);
+        self
+    }
+
+    /// Asks `policy` when `step` may run before each saga starts it.
+    ///
+    /// A deferred step is recorded and the saga stops before it, still
+    /// running, for a [`SagaRunner`](crate::SagaRunner) to resume once the
+    /// deferral is up. Steps without a policy run immediately.
+    pub fn with_step_policy(
+        mut self,
+        step: impl Into<String>,
+        policy: Arc<dyn StepSchedulingPolicy>,
+    ) -> Self {
+        self.step_policies.insert(step.into(), policy);
+        self
+    }
+
+    /// Reads the time steps are scheduled against from `clock`.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Adds a hook told about fulfillment changes the customer should hear
+    /// about, such as item substitutions.
+    pub fn with_notifier(mut self, notifier: Arc<dyn CustomerNotifier>) -> Self {
+        self.notifiers.push(notifier);
+        self
+    }
+
+    /// Replaces an item of a reserved or processing order with a substitute.
+    ///
+    /// The substitute is reserved before the order changes, the reservation
+    /// is recorded on the order and on its latest saga so compensation
+    /// releases it instead of the original's, and the original's
+    /// reservation is then released. Notification hooks are told once the
+    /// substitution is recorded.
+    #[tracing::instrument(skip(self))]
+    pub async fn substitute_item(&self, cmd: SubstituteItem) -> Result<Order, SagaError> {
+        let order_id = cmd.order_id;
+        let order = self
+            .order_service
+            .get_order(order_id)
+            .await?
+            .ok_or(SagaError::OrderNotFound(order_id))?;
+        // Checked up front so a refused substitution reserves nothing
+        order
+            .substitute_item(&cmd.original_product_id, cmd.substitute.clone(), None)
+            .map_err(DomainError::from)?;
+        let original_reservation = order
+            .reservation(&cmd.original_product_id)
+            .map(String::from);
+        let quantity = order
+            .get_item(&cmd.original_product_id)
+            .map_or(0, |item| item.quantity);
+
+        let substitute_id = cmd.substitute.product_id.clone();
+        let reserved = self
+            .inventory
+            .reserve(
+                order_id,
+                vec![ReservationItem {
+                    product_id: substitute_id.clone(),
+                    product_name: cmd.substitute.product_name.clone(),
+                    quantity,
+                }],
+            )
+            .await?;
+        let reservation_id = reserved.reservations.get(&substitute_id).cloned();
+
+        let original_product_id = cmd.original_product_id.clone();
+        let cmd = match &reservation_id {
+            Some(rid) => cmd.with_reservation(rid.clone()),
+            None => cmd,
+        };
+        let result = match self.order_service.substitute_item(cmd).await {
+            Ok(result) => result,
+            Err(e) => {
+                if let Some(rid) = &reservation_id {
+                    self.release_reservation(rid).await;
+                }
+                return Err(e.into());
+            }
+        };
+
+        if let Some(rid) = &reservation_id
+            && let Some(saga_id) = latest_saga_id(&self.store, order_id).await?
+        {
+            let version = self
+                .store
+                .get_events_for_aggregate(saga_id)
+                .await?
+                .last()
+                .map_or(Version::initial(), |envelope| envelope.version);
+            let replaced = SagaEvent::reservation_replaced(
+                original_product_id.clone(),
+                substitute_id.clone(),
+                rid,
+            );
+            self.append_saga_event(saga_id, version, &replaced).await?;
+        }
+        if let Some(rid) = &original_reservation {
+            self.release_reservation(rid).await;
+        }
+
+        if let Some(OrderEvent::ItemSubstituted(data)) = result.events.first()
+            && let Some(customer_id) = result.aggregate.customer_id()
+        {
+            let notification = CustomerNotification::ItemSubstituted {
+                order_id,
+                customer_id,
+                original_product_id,
+                substitute_product_id: substitute_id,
+                substitute_product_name: data.substitute_product_name.clone(),
+                price_delta: data.price_delta,
+            };
+            for notifier in &self.notifiers {
+                if let Err(e) = notifier.notify(&notification).await {
+                    tracing::warn!(%order_id, error = %e, "substitution notification failed");
+                }
+            }
+        }
+        metrics::counter!("order_item_substitutions_total").increment(1);
+
+        Ok(result.aggregate)
+    }
+
+    /// Releases a reservation that's no longer needed, logging a failure
+    /// instead of returning it.
+    async fn release_reservation(&self, reservation_id: &str) {
+        let released = self
+            .with_retries(|| async { Ok(self.inventory.release(reservation_id).await?) })
+            .await;
+        if let Err(e) = released {
+            tracing::warn!(reservation_id, error = %e, "failed to release reservation");
         }
     }
 
@@ -56,214 +293,590 @@ where
     ///
     /// The order must be in Draft state with at least one item.
     /// Returns the saga instance ID on success.
-    #[tracing::instrument(skip(self), fields(saga_type = "OrderFulfillment"))]
     pub async fn execute_saga(&self, order_id: AggregateId) -> Result<AggregateId, SagaError> {
+        self.execute_saga_cancellable(order_id, CancellationToken::new())
+            .await
+    }
+
+    /// Executes an order fulfillment saga that the caller can cancel.
+    ///
+    /// The token is checked before the saga starts and between steps. A
+    /// cancellation before the saga starts returns `SagaError::Cancelled`;
+    /// after that, the next step is recorded as failed and completed steps
+    /// are compensated, so no step is ever left half-applied.
+    pub async fn execute_saga_cancellable(
+        &self,
+        order_id: AggregateId,
+        cancel: CancellationToken,
+    ) -> Result<AggregateId, SagaError> {
+        self.execute_saga_traced(order_id, cancel, TraceContext::new_root())
+            .await
+    }
+
+    /// Executes an order fulfillment saga that the caller can cancel, as
+    /// part of `trace`.
+    ///
+    /// The saga's events are recorded under `trace`'s span and each step
+    /// attempt's under a child span; see [`saga_trace`](Self::saga_trace).
+    #[tracing::instrument(
+        skip(self, cancel, trace),
+        fields(saga_type = "OrderFulfillment", trace_id = %trace.trace_id)
+    )]
+    pub async fn execute_saga_traced(
+        &self,
+        order_id: AggregateId,
+        cancel: CancellationToken,
+        trace: TraceContext,
+    ) -> Result<AggregateId, SagaError> {
         metrics::counter!("saga_executions_total").increment(1);
-        let saga_start = std::time::Instant::now();
+        let saga_start = Instant::now();
         // 1. Load and validate the order
+        let input = self.fulfillment_input(order_id).await?;
+
+        if cancel.is_cancelled() {
+            return Err(SagaError::Cancelled);
+        }
+
+        // 2-3. Submit the order and create the saga
+        let mut run = self.begin_saga(order_id, input, trace).await?;
+        run.started = saga_start;
+
+        // 4-5. Run the steps and complete the saga
+        self.run_steps(&mut run, &cancel, OnCancel::Compensate)
+            .await?;
+        Ok(run.saga_id)
+    }
+
+    /// Validates and submits an order and records a new saga for it,
+    /// without running any steps.
+    ///
+    /// Returns the saga ID at once; a [`SagaRunner`](crate::SagaRunner)
+    /// picks the saga up and drives it with
+    /// [`resume_saga`](Self::resume_saga).
+    #[tracing::instrument(
+        skip(self, trace),
+        fields(saga_type = "OrderFulfillment", trace_id = %trace.trace_id)
+    )]
+    pub async fn start_saga(
+        &self,
+        order_id: AggregateId,
+        trace: TraceContext,
+    ) -> Result<AggregateId, SagaError> {
+        metrics::counter!("saga_executions_total").increment(1);
+        let input = self.fulfillment_input(order_id).await?;
+        let run = self.begin_saga(order_id, input, trace).await?;
+        tracing::info!(saga_id = %run.saga_id, "saga started");
+        Ok(run.saga_id)
+    }
+
+    /// Drives a running saga from where its events leave off, returning
+    /// the state it ends in, or None if it doesn't exist.
+    ///
+    /// Sagas that already finished are left alone. If the saga stopped
+    /// between steps, the order is first caught up with the completed
+    /// steps and the remaining steps then run. If it stopped during a
+    /// step's service call, that call may or may not have taken effect, so
+    /// the step is failed and the saga compensated, as
+    /// [`reconcile`](Self::reconcile) would. Cancelling `cancel` pauses
+    /// the saga at the next step boundary, leaving it running to be resumed
+    /// again later.
+    #[tracing::instrument(skip(self, cancel))]
+    pub async fn resume_saga(
+        &self,
+        saga_id: AggregateId,
+        cancel: CancellationToken,
+    ) -> Result<Option<SagaState>, SagaError> {
+        let envelopes = self.store.get_events_for_aggregate(saga_id).await?;
+        let Some(mut version) = envelopes.last().map(|envelope| envelope.version) else {
+            return Ok(None);
+        };
+        let trace = envelopes
+            .first()
+            .and_then(|envelope| TraceContext::from_metadata(&envelope.metadata))
+            .unwrap_or_else(TraceContext::new_root);
+        let mut saga = SagaInstance::default();
+        let mut decisions = HashMap::new();
+        for envelope in envelopes {
+            let event: SagaEvent = serde_json::from_value(envelope.payload)?;
+            if let SagaEvent::StepCompleted(data) = &event {
+                decisions.insert(data.step_name.clone(), envelope.event_id);
+            }
+            saga.apply(event);
+        }
+        if saga.state() != SagaState::Running {
+            return Ok(Some(saga.state()));
+        }
+        let Some(order_id) = saga.order_id() else {
+            return Ok(None);
+        };
+        let definition = self.definition_of(&saga)?;
+        if self.deferred_until(&saga, &definition).is_some() {
+            return Ok(Some(saga.state()));
+        }
+
+        if let Some(attempt) = saga.attempt_in_flight() {
+            let step = attempt.step_name.clone();
+            tracing::warn!(%saga_id, step, "saga interrupted mid-step, compensating");
+            let failed = SagaEvent::step_failed(&step, "interrupted");
+            version = self.append_saga_event(saga_id, version, &failed).await?;
+            saga.apply(failed);
+            self.compensate(&mut saga, &definition, saga_id, &mut version, order_id)
+                .await?;
+            return Ok(Some(saga.state()));
+        }
+
         let order = self
             .order_service
             .get_order(order_id)
             .await?
             .ok_or(SagaError::OrderNotFound(order_id))?;
+        let order_state = self
+            .advance_order(
+                &saga,
+                &definition,
+                saga_id,
+                order_id,
+                order.state(),
+                &decisions,
+            )
+            .await?
+            .unwrap_or(order.state());
+        let mut run = SagaRun {
+            saga_id,
+            order_id,
+            input: FulfillmentInput::from_order(&order)?,
+            definition,
+            saga,
+            version,
+            order_state,
+            trace,
+            started: Instant::now(),
+        };
+        tracing::info!(%saga_id, completed = run.saga.completed_steps().len(), "saga resumed");
+        metrics::counter!("saga_resumed").increment(1);
+        self.run_steps(&mut run, &cancel, OnCancel::Pause).await?;
+        Ok(Some(run.saga.state()))
+    }
 
-        if order.state() != OrderState::Draft {
-            return Err(SagaError::OrderNotReady(format!(
-                "Order is in {} state, expected Draft",
-                order.state()
-            )));
-        }
-
-        if !order.has_items() {
-            return Err(SagaError::OrderNotReady("Order has no items".to_string()));
+    /// Returns the sagas that are still running, for a
+    /// [`SagaRunner`](crate::SagaRunner) to resume.
+    pub async fn running_sagas(&self) -> Result<Vec<AggregateId>, SagaError> {
+        let started = self.store.get_events_by_type("SagaStarted").await?;
+        let mut running = Vec::new();
+        for envelope in started {
+            if envelope.aggregate_type != SagaInstance::aggregate_type() {
+                continue;
+            }
+            if let Some(saga) = self.get_saga(envelope.aggregate_id).await?
+                && saga.state() == SagaState::Running
+            {
+                running.push(envelope.aggregate_id);
+            }
         }
+        Ok(running)
+    }
 
-        let customer_id = order
-            .customer_id()
-            .ok_or_else(|| SagaError::OrderNotReady("Order has no customer ID".to_string()))?;
-        let total_amount = order.total_amount();
-        let items: Vec<ReservationItem> = order
-            .items()
-            .map(|item| ReservationItem {
-                product_id: item.product_id.clone(),
-                product_name: item.product_name.clone(),
-                quantity: item.quantity,
-            })
-            .collect();
+    /// Returns the sagas still running, like
+    /// [`running_sagas`](Self::running_sagas), along with the IDs of sagas
+    /// started after that scan as they start.
+    ///
+    /// The [`SagaRunner`](crate::SagaRunner) scans once and then follows
+    /// the stream, so its later passes don't reload every saga ever started.
+    pub async fn follow_running_sagas(&self) -> Result<(Vec<AggregateId>, SagaStarts), SagaError> {
+        // Subscribed before the scan, so a saga starting during it is in
+        // one or both
+        let head = self.store.head_sequence().await?;
+        let events = self.store.subscribe(head).await?;
+        let running = self.running_sagas().await?;
+        let starts = events.filter_map(|event| async move {
+            match event {
+                Ok(event)
+                    if event.event_type == "SagaStarted"
+                        && event.aggregate_type == SagaInstance::aggregate_type() =>
+                {
+                    Some(Ok(event.aggregate_id))
+                }
+                Ok(_) => None,
+                Err(e) => Some(Err(e.into())),
+            }
+        });
+        Ok((running, Box::pin(starts)))
+    }
 
-        // 2. Submit the order (stays Draft, records OrderSubmitted)
+    /// Submits a validated order and records the start of its saga on the
+    /// current definition.
+    async fn begin_saga(
+        &self,
+        order_id: AggregateId,
+        input: FulfillmentInput,
+        trace: TraceContext,
+    ) -> Result<SagaRun, SagaError> {
+        // Submit the order (stays Draft, records OrderSubmitted)
         self.order_service
             .submit_order(SubmitOrder::new(order_id))
             .await?;
 
-        // 3. Create the saga
+        // Create the saga on the current definition
+        let definition = self.current_definition()?;
         let saga_id = AggregateId::new();
-        let mut version = Version::initial();
 
         let started_event =
-            SagaEvent::saga_started(saga_id, order_id, order_fulfillment::SAGA_TYPE);
-        version = self
-            .append_saga_event(saga_id, version, &started_event)
+            SagaEvent::saga_started(saga_id, order_id, order_fulfillment::SAGA_TYPE)
+                .with_definition_version(definition.version);
+        let (version, _) = self
+            .append_saga_event_in(saga_id, Version::initial(), &started_event, Some(&trace))
             .await?;
 
         // Build saga state for compensation tracking
         let mut saga = SagaInstance::default();
         saga.apply(started_event);
 
-        // 4. Step 1: Reserve Inventory
-        tracing::info!(
-            step = order_fulfillment::STEP_RESERVE_INVENTORY,
-            "saga step started"
-        );
-        let step1_started = SagaEvent::step_started(order_fulfillment::STEP_RESERVE_INVENTORY);
-        version = self
-            .append_saga_event(saga_id, version, &step1_started)
-            .await?;
-        saga.apply(step1_started);
-
-        match self.inventory.reserve(order_id, items).await {
-            Ok(result) => {
-                let reservation_id = result.reservation_id.clone();
-                let step1_completed = SagaEvent::step_completed(
-                    order_fulfillment::STEP_RESERVE_INVENTORY,
-                    Some(reservation_id.clone()),
-                    None,
-                    None,
-                );
-                version = self
-                    .append_saga_event(saga_id, version, &step1_completed)
-                    .await?;
-                saga.apply(step1_completed);
+        Ok(SagaRun {
+            saga_id,
+            order_id,
+            input,
+            definition,
+            saga,
+            version,
+            order_state: OrderState::Draft,
+            trace,
+            started: Instant::now(),
+        })
+    }
 
-                // Advance order state to Reserved
-                self.order_service
-                    .mark_reserved(MarkReserved::new(order_id, Some(reservation_id)))
-                    .await?;
+    /// Runs the steps of the saga's definition it hasn't completed, then
+    /// completes it, or compensates on the first step that fails.
+    async fn run_steps(
+        &self,
+        run: &mut SagaRun,
+        cancel: &CancellationToken,
+        on_cancel: OnCancel,
+    ) -> Result<(), SagaError> {
+        let SagaRun {
+            saga_id,
+            order_id,
+            input,
+            definition,
+            saga,
+            version,
+            order_state,
+            trace,
+            started,
+        } = run;
+        let (saga_id, order_id, saga_start) = (*saga_id, *order_id, *started);
+        let mut shipped_items = Vec::new();
+        while let Some(step) = definition.next_step(saga.completed_steps()) {
+            let step = step.to_string();
+            if cancel.is_cancelled() {
+                if on_cancel == OnCancel::Pause {
+                    tracing::info!(%saga_id, step, "saga paused before step");
+                    return Ok(());
+                }
+                self.abort_cancelled(
+                    saga, definition, saga_id, version, order_id, &step, saga_start,
+                )
+                .await?;
+                return Ok(());
             }
-            Err(e) => {
-                let step1_failed = SagaEvent::step_failed(
-                    order_fulfillment::STEP_RESERVE_INVENTORY,
-                    e.to_string(),
-                );
-                version = self
-                    .append_saga_event(saga_id, version, &step1_failed)
-                    .await?;
-                saga.apply(step1_failed);
-
-                self.compensate(&mut saga, saga_id, &mut version, order_id)
+            if let Some(until) = self.defer_step(saga, &step) {
+                if saga.deferred_until(&step) != Some(until) {
+                    let deferred = SagaEvent::step_deferred(&step, until);
+                    (*version, _) = self
+                        .append_saga_event_in(saga_id, *version, &deferred, Some(trace))
+                        .await?;
+                    saga.apply(deferred);
+                    metrics::counter!("saga_steps_deferred", "step" => step.clone()).increment(1);
+                }
+                tracing::info!(%saga_id, step, %until, "saga step deferred");
+                return Ok(());
+            }
+            tracing::info!(step, "saga step started");
+            let (output, timing, span) = loop {
+                let attempt = saga.attempts(&step) + 1;
+                let span = trace.child();
+                let started_at = Utc::now();
+                let step_started = SagaEvent::step_attempt_started(&step, attempt, started_at);
+                (*version, _) = self
+                    .append_saga_event_in(saga_id, *version, &step_started, Some(&span))
                     .await?;
-                metrics::histogram!("saga_duration_seconds")
-                    .record(saga_start.elapsed().as_secs_f64());
-                return Ok(saga_id);
+                saga.apply(step_started);
+
+                let call_start = Instant::now();
+                let output = self.call_step(&step, order_id, input).await;
+                let timing = step_timing(attempt, started_at, call_start);
+                match output {
+                    Err(e) if e.is_retryable() && attempt < self.max_step_attempts() => {
+                        tracing::warn!(step, attempt, error = %e, "saga step failed, retrying");
+                        metrics::counter!("saga_step_retries", "step" => step.clone()).increment(1);
+                        let step_failed = SagaEvent::step_failed_with(&step, &e)
+                            .with_timing(timing)
+                            .retrying();
+                        (*version, _) = self
+                            .append_saga_event_in(saga_id, *version, &step_failed, Some(&span))
+                            .await?;
+                        saga.apply(step_failed);
+                    }
+                    output => break (output, timing, span),
+                }
+            };
+            match output {
+                Ok(output) => {
+                    shipped_items.extend(output.shipped_items);
+                    let step_completed = SagaEvent::step_completed(
+                        &step,
+                        output.reservations,
+                        output.payment_id,
+                        output.tracking_number,
+                    )
+                    .with_timing(timing);
+                    let event_id;
+                    (*version, event_id) = self
+                        .append_saga_event_in(saga_id, *version, &step_completed, Some(&span))
+                        .await?;
+                    saga.apply(step_completed);
+
+                    // Move the order one state forward per completed step
+                    let causation = SagaCausation::new(saga_id, &step, event_id);
+                    let shipped = if *order_state == OrderState::Processing {
+                        std::mem::take(&mut shipped_items)
+                    } else {
+                        Vec::new()
+                    };
+                    *order_state = self
+                        .advance_order_once(
+                            saga,
+                            order_id,
+                            *order_state,
+                            causation.to_metadata(),
+                            shipped,
+                        )
+                        .await?;
+                }
+                Err(e) => {
+                    let step_failed = SagaEvent::step_failed_with(&step, &e).with_timing(timing);
+                    (*version, _) = self
+                        .append_saga_event_in(saga_id, *version, &step_failed, Some(&span))
+                        .await?;
+                    saga.apply(step_failed);
+
+                    self.compensate(saga, definition, saga_id, version, order_id)
+                        .await?;
+                    metrics::histogram!("saga_duration_seconds")
+                        .record(saga_start.elapsed().as_secs_f64());
+                    return Ok(());
+                }
             }
         }
 
-        // 5. Step 2: Process Payment
-        tracing::info!(
-            step = order_fulfillment::STEP_PROCESS_PAYMENT,
-            "saga step started"
-        );
-        let step2_started = SagaEvent::step_started(order_fulfillment::STEP_PROCESS_PAYMENT);
-        version = self
-            .append_saga_event(saga_id, version, &step2_started)
+        // Saga completed
+        let completed_event = SagaEvent::saga_completed();
+        (*version, _) = self
+            .append_saga_event_in(saga_id, *version, &completed_event, Some(trace))
             .await?;
-        saga.apply(step2_started);
+        saga.apply(completed_event);
 
-        match self
-            .payment
-            .charge(order_id, customer_id, total_amount)
-            .await
-        {
-            Ok(result) => {
-                let payment_id = result.payment_id.clone();
-                let step2_completed = SagaEvent::step_completed(
-                    order_fulfillment::STEP_PROCESS_PAYMENT,
-                    None,
-                    Some(payment_id.clone()),
-                    None,
-                );
-                version = self
-                    .append_saga_event(saga_id, version, &step2_completed)
-                    .await?;
-                saga.apply(step2_completed);
+        let duration = saga_start.elapsed().as_secs_f64();
+        metrics::histogram!("saga_duration_seconds").record(duration);
+        metrics::counter!("saga_completed").increment(1);
+        tracing::info!(%saga_id, duration, "saga completed successfully");
 
-                // Advance order state to Processing
-                self.order_service
-                    .start_processing(StartProcessing::new(order_id, Some(payment_id)))
-                    .await?;
-            }
-            Err(e) => {
-                let step2_failed =
-                    SagaEvent::step_failed(order_fulfillment::STEP_PROCESS_PAYMENT, e.to_string());
-                version = self
-                    .append_saga_event(saga_id, version, &step2_failed)
-                    .await?;
-                saga.apply(step2_failed);
+        Ok(())
+    }
 
-                self.compensate(&mut saga, saga_id, &mut version, order_id)
-                    .await?;
-                metrics::histogram!("saga_duration_seconds")
-                    .record(saga_start.elapsed().as_secs_f64());
-                return Ok(saga_id);
-            }
+    /// Returns when `step` may run if it can't run now, recorded or newly
+    /// decided by the step's scheduling policy. A deferral that is up isn't
+    /// asked about again.
+    fn defer_step(&self, saga: &SagaInstance, step: &str) -> Option<DateTime<Utc>> {
+        let now = self.clock.now();
+        if let Some(until) = saga.deferred_until(step) {
+            return (until > now).then_some(until);
+        }
+        match self.step_policies.get(step)?.schedule(step, now) {
+            StepSchedule::Defer(until) if until > now => Some(until),
+            _ => None,
+        }
+    }
+
+    /// Returns when the saga's next step may run, if it was deferred and
+    /// the deferral isn't up yet.
+    fn deferred_until(
+        &self,
+        saga: &SagaInstance,
+        definition: &SagaDefinition,
+    ) -> Option<DateTime<Utc>> {
+        let step = definition.next_step(saga.completed_steps())?;
+        saga.deferred_until(step)
+            .filter(|until| *until > self.clock.now())
+    }
+
+    /// Validates an order the way [`execute_saga`](Self::execute_saga) does
+    /// and checks each step with its service, without side effects.
+    ///
+    /// Inventory and payment are asked through `can_reserve` and
+    /// `can_charge`; shipping has no such check and is reported unchecked.
+    /// Every step is checked even after one fails, so all expected failures
+    /// show up at once.
+    #[tracing::instrument(skip(self))]
+    pub async fn dry_run(&self, order_id: AggregateId) -> Result<DryRunReport, SagaError> {
+        let input = self.fulfillment_input(order_id).await?;
+        let definition = self.current_definition()?;
+
+        let mut steps = Vec::with_capacity(definition.steps.len());
+        for step in &definition.steps {
+            let checked = match step.as_str() {
+                order_fulfillment::STEP_RESERVE_INVENTORY => Some(
+                    self.inventory
+                        .can_reserve(&input.items)
+                        .await
+                        .map_err(SagaError::from),
+                ),
+                order_fulfillment::STEP_PROCESS_PAYMENT => Some(
+                    self.payment
+                        .can_charge(input.customer_id, input.total_amount)
+                        .await
+                        .map_err(SagaError::from),
+                ),
+                _ => None,
+            };
+            let check = match checked {
+                Some(Ok(())) => StepCheck::Pass,
+                Some(Err(e)) => StepCheck::Fail {
+                    reason: e.to_string(),
+                },
+                None => StepCheck::Unchecked,
+            };
+            steps.push(DryRunStep {
+                step: step.clone(),
+                check,
+            });
         }
 
-        // 6. Step 3: Create Shipment
+        let report = DryRunReport {
+            order_id,
+            definition_version: definition.version,
+            steps,
+        };
         tracing::info!(
-            step = order_fulfillment::STEP_CREATE_SHIPMENT,
-            "saga step started"
+            would_succeed = report.would_succeed(),
+            "fulfillment dry run"
         );
-        let step3_started = SagaEvent::step_started(order_fulfillment::STEP_CREATE_SHIPMENT);
-        version = self
-            .append_saga_event(saga_id, version, &step3_started)
-            .await?;
-        saga.apply(step3_started);
+        Ok(report)
+    }
 
-        match self.shipping.create_shipment(order_id).await {
-            Ok(result) => {
-                let tracking_number = result.tracking_number.clone();
-                let step3_completed = SagaEvent::step_completed(
-                    order_fulfillment::STEP_CREATE_SHIPMENT,
-                    None,
-                    None,
-                    Some(tracking_number.clone()),
-                );
-                version = self
-                    .append_saga_event(saga_id, version, &step3_completed)
-                    .await?;
-                saga.apply(step3_completed);
+    /// Loads an order and checks it can be fulfilled, returning what the
+    /// steps need from it.
+    async fn fulfillment_input(
+        &self,
+        order_id: AggregateId,
+    ) -> Result<FulfillmentInput, SagaError> {
+        let order = self
+            .order_service
+            .get_order(order_id)
+            .await?
+            .ok_or(SagaError::OrderNotFound(order_id))?;
 
-                // Advance order state to Completed
-                self.order_service
-                    .complete_order(CompleteOrder::new(order_id, Some(tracking_number)))
+        if order.state() != OrderState::Draft {
+            return Err(SagaError::OrderNotReady(format!(
+                "Order is in {} state, expected Draft",
+                order.state()
+            )));
+        }
+
+        if !order.has_items() {
+            return Err(SagaError::OrderNotReady("Order has no items".to_string()));
+        }
+
+        FulfillmentInput::from_order(&order)
+    }
+
+    /// Calls the external service behind `step`.
+    async fn call_step(
+        &self,
+        step: &str,
+        order_id: AggregateId,
+        input: &FulfillmentInput,
+    ) -> Result<StepOutput, SagaError> {
+        match step {
+            order_fulfillment::STEP_RESERVE_INVENTORY => {
+                let result = self
+                    .inventory
+                    .reserve(order_id, input.items.clone())
                     .await?;
+                Ok(StepOutput {
+                    reservations: result.reservations,
+                    ..Default::default()
+                })
             }
-            Err(e) => {
-                let step3_failed =
-                    SagaEvent::step_failed(order_fulfillment::STEP_CREATE_SHIPMENT, e.to_string());
-                version = self
-                    .append_saga_event(saga_id, version, &step3_failed)
+            order_fulfillment::STEP_PROCESS_PAYMENT => {
+                let result = self
+                    .payment
+                    .charge(order_id, input.customer_id, input.total_amount)
                     .await?;
-                saga.apply(step3_failed);
-
-                self.compensate(&mut saga, saga_id, &mut version, order_id)
+                Ok(StepOutput {
+                    payment_id: Some(result.payment_id),
+                    ..Default::default()
+                })
+            }
+            order_fulfillment::STEP_CREATE_SHIPMENT => {
+                let result = self
+                    .shipping
+                    .create_shipment(order_id, &input.items, input.shipping_address.as_ref())
                     .await?;
-                metrics::histogram!("saga_duration_seconds")
-                    .record(saga_start.elapsed().as_secs_f64());
-                return Ok(saga_id);
+                Ok(StepOutput {
+                    tracking_number: Some(result.tracking_number),
+                    shipped_items: result.shipped_items,
+                    ..Default::default()
+                })
             }
+            other => Err(SagaError::StepFailed {
+                step: other.to_string(),
+                reason: "no handler for this step".to_string(),
+            }),
         }
+    }
 
-        // 7. Saga completed
-        let completed_event = SagaEvent::saga_completed();
-        self.append_saga_event(saga_id, version, &completed_event)
-            .await?;
+    /// Returns the definition new sagas start on.
+    fn current_definition(&self) -> Result<Arc<SagaDefinition>, SagaError> {
+        self.definitions
+            .current(order_fulfillment::SAGA_TYPE)
+            .ok_or_else(|| SagaError::UnknownDefinition {
+                saga_type: order_fulfillment::SAGA_TYPE.to_string(),
+                version: 0,
+            })
+    }
 
-        let duration = saga_start.elapsed().as_secs_f64();
-        metrics::histogram!("saga_duration_seconds").record(duration);
-        metrics::counter!("saga_completed").increment(1);
-        tracing::info!(%saga_id, duration, "saga completed successfully");
+    /// Returns the definition `saga` started with.
+    fn definition_of(&self, saga: &SagaInstance) -> Result<Arc<SagaDefinition>, SagaError> {
+        self.definitions
+            .get(saga.saga_type(), saga.definition_version())
+            .ok_or_else(|| SagaError::UnknownDefinition {
+                saga_type: saga.saga_type().to_string(),
+                version: saga.definition_version(),
+            })
+    }
+
+    /// Records a cancellation as a failure of the next step and compensates.
+    #[allow(clippy::too_many_arguments)]
+    async fn abort_cancelled(
+        &self,
+        saga: &mut SagaInstance,
+        definition: &SagaDefinition,
+        saga_id: AggregateId,
+        version: &mut Version,
+        order_id: AggregateId,
+        next_step: &str,
+        saga_start: Instant,
+    ) -> Result<AggregateId, SagaError> {
+        tracing::warn!(%saga_id, step = next_step, "saga cancelled by caller");
+        metrics::counter!("saga_cancelled").increment(1);
+
+        let failed = SagaEvent::step_failed(next_step, "cancelled");
+        *version = self.append_saga_event(saga_id, *version, &failed).await?;
+        saga.apply(failed);
 
+        self.compensate(saga, definition, saga_id, version, order_id)
+            .await?;
+        metrics::histogram!("saga_duration_seconds").record(saga_start.elapsed().as_secs_f64());
         Ok(saga_id)
     }
 
@@ -272,6 +885,7 @@ where
     async fn compensate(
         &self,
         saga: &mut SagaInstance,
+        definition: &SagaDefinition,
         saga_id: AggregateId,
         version: &mut Version,
         order_id: AggregateId,
@@ -279,8 +893,9 @@ where
         let failed_step = saga.failure_reason().unwrap_or("unknown").to_string();
 
         let comp_started = SagaEvent::compensation_started(&failed_step);
-        *version = self
-            .append_saga_event(saga_id, *version, &comp_started)
+        let comp_started_id;
+        (*version, comp_started_id) = self
+            .append_saga_event_with_id(saga_id, *version, &comp_started)
             .await?;
         saga.apply(comp_started);
 
@@ -291,7 +906,12 @@ where
                 order_fulfillment::STEP_CREATE_SHIPMENT => {
                     if let Some(tracking_number) = saga.tracking_number() {
                         let tn = tracking_number.to_string();
-                        match self.shipping.cancel_shipment(&tn).await {
+                        let cancelled = self
+                            .with_retries(|| async {
+                                Ok(self.shipping.cancel_shipment(&tn).await?)
+                            })
+                            .await;
+                        match cancelled {
                             Ok(()) => {
                                 let event = SagaEvent::compensation_step_completed(step);
                                 *version =
@@ -311,7 +931,10 @@ where
                 order_fulfillment::STEP_PROCESS_PAYMENT => {
                     if let Some(payment_id) = saga.payment_id() {
                         let pid = payment_id.to_string();
-                        match self.payment.refund(&pid).await {
+                        let refunded = self
+                            .with_retries(|| async { Ok(self.payment.refund(&pid).await?) })
+                            .await;
+                        match refunded {
                             Ok(()) => {
                                 let event = SagaEvent::compensation_step_completed(step);
                                 *version =
@@ -329,37 +952,54 @@ where
                     }
                 }
                 order_fulfillment::STEP_RESERVE_INVENTORY => {
-                    if let Some(reservation_id) = saga.reservation_id() {
-                        let rid = reservation_id.to_string();
-                        match self.inventory.release(&rid).await {
-                            Ok(()) => {
-                                let event = SagaEvent::compensation_step_completed(step);
-                                *version =
-                                    self.append_saga_event(saga_id, *version, &event).await?;
-                                saga.apply(event);
-                            }
-                            Err(e) => {
-                                let event =
-                                    SagaEvent::compensation_step_failed(step, e.to_string());
-                                *version =
-                                    self.append_saga_event(saga_id, *version, &event).await?;
-                                saga.apply(event);
+                    let reservation_ids: Vec<String> =
+                        saga.reservation_ids().map(String::from).collect();
+                    if !reservation_ids.is_empty() {
+                        // Release every line, even after one fails, so a
+                        // single stuck reservation doesn't strand the rest.
+                        let mut errors = Vec::new();
+                        for rid in &reservation_ids {
+                            let released = self
+                                .with_retries(|| async { Ok(self.inventory.release(rid).await?) })
+                                .await;
+                            if let Err(e) = released {
+                                errors.push(format!("{rid}: {e}"));
                             }
                         }
+                        let event = if errors.is_empty() {
+                            SagaEvent::compensation_step_completed(step)
+                        } else {
+                            SagaEvent::compensation_step_failed(step, errors.join("; "))
+                        };
+                        *version = self.append_saga_event(saga_id, *version, &event).await?;
+                        saga.apply(event);
                     }
                 }
                 _ => {}
             }
         }
 
-        // Cancel the order
-        self.order_service
-            .cancel_order(CancelOrder::new(
-                order_id,
-                format!("Saga failed: {}", failed_step),
-                Some("saga_coordinator".to_string()),
-            ))
-            .await?;
+        // Cancel the order, unless it was cancelled before a crash
+        let cancelled = self
+            .order_service
+            .get_order(order_id)
+            .await?
+            .is_some_and(|order| order.state() == OrderState::Cancelled);
+        if !cancelled {
+            let causation =
+                SagaCausation::new(saga_id, failed_step_of(saga, definition), comp_started_id);
+            self.order_service
+                .cancel_order(
+                    CancelOrder::new(
+                        order_id,
+                        format!("Saga failed: {}", failed_step),
+                        Some("saga_coordinator".to_string()),
+                    )
+                    .with_reason_code(saga.failure_kind().unwrap_or_default().code())
+                    .with_metadata(causation.to_metadata()),
+                )
+                .await?;
+        }
 
         // Record saga failure
         let failed_event = SagaEvent::saga_failed(format!("Step failed: {}", failed_step));
@@ -374,20 +1014,537 @@ where
         Ok(())
     }
 
+    /// Calls `undo` until it succeeds, fails with an error that isn't
+    /// retryable, or runs out of attempts.
+    async fn with_retries<F, Fut>(&self, mut undo: F) -> Result<(), SagaError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<(), SagaError>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match undo().await {
+                Err(e) if e.is_retryable() && attempt < self.max_step_attempts() => {
+                    tracing::warn!(attempt, error = %e, "compensation failed, retrying");
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
     /// Loads a saga instance by ID from the event store.
     pub async fn get_saga(&self, saga_id: AggregateId) -> Result<Option<SagaInstance>, SagaError> {
-        let events = self.store.get_events_for_aggregate(saga_id).await?;
+        load_saga(&self.store, saga_id).await
+    }
+
+    /// Returns the trace a saga ran under, with the span of each step
+    /// attempt, or None if the saga doesn't exist or wasn't traced.
+    pub async fn saga_trace(&self, saga_id: AggregateId) -> Result<Option<SagaTrace>, SagaError> {
+        let envelopes = self.store.get_events_for_aggregate(saga_id).await?;
+        let events = envelopes
+            .iter()
+            .map(|envelope| serde_json::from_value(envelope.payload.clone()))
+            .collect::<Result<Vec<SagaEvent>, _>>()?;
+        Ok(SagaTrace::from_events(
+            events
+                .iter()
+                .zip(envelopes.iter().map(|envelope| &envelope.metadata)),
+        ))
+    }
 
-        if events.is_empty() {
+    /// Builds the step graph of a saga, with per-step status and timings.
+    pub async fn saga_graph(&self, saga_id: AggregateId) -> Result<Option<SagaGraph>, SagaError> {
+        let envelopes = self.store.get_events_for_aggregate(saga_id).await?;
+        if envelopes.is_empty() {
             return Ok(None);
         }
 
         let mut saga = SagaInstance::default();
-        for envelope in events {
+        let mut events = Vec::with_capacity(envelopes.len());
+        for envelope in envelopes {
+            let event: SagaEvent = serde_json::from_value(envelope.payload)?;
+            saga.apply(event.clone());
+            events.push((envelope.timestamp, event));
+        }
+        // Steps outside a known definition still appear, in the order seen
+        let definition = self.definition_of(&saga).ok();
+        let steps = definition
+            .as_deref()
+            .map(SagaDefinition::step_names)
+            .unwrap_or_default();
+        Ok(Some(SagaGraph::build(&saga, &steps, events)))
+    }
+
+    /// Reconciles every saga with its order, e.g. on startup after a crash.
+    ///
+    /// Returns a report for each pair that was repaired or flagged for
+    /// review; pairs that already agree are left out.
+    pub async fn reconcile_all(&self) -> Result<Vec<ReconciliationReport>, SagaError> {
+        let started = self.store.get_events_by_type("SagaStarted").await?;
+        let mut reports = Vec::new();
+        for envelope in started {
+            if envelope.aggregate_type != SagaInstance::aggregate_type() {
+                continue;
+            }
+            if let Some(report) = self.reconcile(envelope.aggregate_id).await?
+                && report.outcome != ReconciliationOutcome::InSync
+            {
+                reports.push(report);
+            }
+        }
+        Ok(reports)
+    }
+
+    /// Compares a saga with its order and appends whatever events are needed
+    /// for them to agree.
+    ///
+    /// A saga interrupted during a step is failed and compensated rather
+    /// than resumed, since the interrupted service call may or may not have
+    /// taken effect. So is one stopped between steps, unless sagas are
+    /// [resumable](Self::with_resumable_sagas). An interrupted compensation, or an order that moved past
+    /// its saga, is flagged for review. Returns None if the saga doesn't
+    /// exist.
+    #[tracing::instrument(skip(self))]
+    pub async fn reconcile(
+        &self,
+        saga_id: AggregateId,
+    ) -> Result<Option<ReconciliationReport>, SagaError> {
+        let envelopes = self.store.get_events_for_aggregate(saga_id).await?;
+        let Some(mut version) = envelopes.last().map(|envelope| envelope.version) else {
+            return Ok(None);
+        };
+        let mut saga = SagaInstance::default();
+        // The saga event deciding each step's order transition: its
+        // completion, or the compensation its failure started
+        let mut decisions = HashMap::new();
+        let mut compensation_started = None;
+        for envelope in envelopes {
             let event: SagaEvent = serde_json::from_value(envelope.payload)?;
+            match &event {
+                SagaEvent::StepCompleted(data) => {
+                    decisions.insert(data.step_name.clone(), envelope.event_id);
+                }
+                SagaEvent::CompensationStarted(_) => {
+                    compensation_started = Some(envelope.event_id);
+                }
+                _ => {}
+            }
             saga.apply(event);
         }
-        Ok(Some(saga))
+        let Some(order_id) = saga.order_id() else {
+            return Ok(None);
+        };
+
+        // Resume with the step order the saga started with, not today's
+        let outcome = match self.definition_of(&saga) {
+            Ok(definition) => {
+                if let Some(event_id) = compensation_started {
+                    decisions.insert(failed_step_of(&saga, &definition).to_string(), event_id);
+                }
+                match self.order_service.get_order(order_id).await? {
+                    Some(order) => {
+                        self.converge(
+                            &mut saga,
+                            &definition,
+                            saga_id,
+                            &mut version,
+                            order_id,
+                            order.state(),
+                            &decisions,
+                        )
+                        .await?
+                    }
+                    None => ReconciliationOutcome::NeedsReview {
+                        reason: format!("order {order_id} not found"),
+                    },
+                }
+            }
+            Err(e) => ReconciliationOutcome::NeedsReview {
+                reason: e.to_string(),
+            },
+        };
+
+        metrics::counter!("saga_reconciliations", "outcome" => outcome.as_str()).increment(1);
+        match &outcome {
+            ReconciliationOutcome::InSync => {}
+            ReconciliationOutcome::Repaired(corrections) => {
+                tracing::info!(%saga_id, %order_id, ?corrections, "saga reconciled with order");
+            }
+            ReconciliationOutcome::NeedsReview { reason } => {
+                tracing::warn!(%saga_id, %order_id, %reason, "saga flagged for manual review");
+            }
+        }
+
+        Ok(Some(ReconciliationReport {
+            saga_id,
+            order_id,
+            outcome,
+        }))
+    }
+
+    /// Decides and applies the corrections for a saga and an order in
+    /// `order_state`.
+    #[allow(clippy::too_many_arguments)]
+    async fn converge(
+        &self,
+        saga: &mut SagaInstance,
+        definition: &SagaDefinition,
+        saga_id: AggregateId,
+        version: &mut Version,
+        order_id: AggregateId,
+        order_state: OrderState,
+        decisions: &HashMap<String, EventId>,
+    ) -> Result<ReconciliationOutcome, SagaError> {
+        let needs_review = |reason: String| Ok(ReconciliationOutcome::NeedsReview { reason });
+        let next_step = definition.next_step(saga.completed_steps());
+        let mut corrections = Vec::new();
+
+        match (saga.state(), next_step) {
+            (SagaState::Completed, _) | (SagaState::Running, None) => {
+                if order_state == OrderState::Cancelled {
+                    return needs_review("order cancelled after every saga step completed".into());
+                }
+                if let Some(to) = self
+                    .advance_order(saga, definition, saga_id, order_id, order_state, decisions)
+                    .await?
+                {
+                    corrections.push(Correction::OrderAdvanced { to });
+                }
+                if saga.state() == SagaState::Running {
+                    let completed = SagaEvent::saga_completed();
+                    *version = self
+                        .append_saga_event(saga_id, *version, &completed)
+                        .await?;
+                    saga.apply(completed);
+                    corrections.push(Correction::SagaCompleted);
+                }
+            }
+            (SagaState::Running, Some(step)) => {
+                if reconcile::order_progress(order_state)
+                    .is_some_and(|progress| progress > saga.completed_steps().len())
+                {
+                    return needs_review(format!(
+                        "order is {order_state} but the saga stopped before {step}"
+                    ));
+                }
+                if (self.resumable || saga.deferred_until(step).is_some())
+                    && saga.attempt_in_flight().is_none()
+                {
+                    // Left for the runner, which catches the order up first
+                    return Ok(ReconciliationOutcome::InSync);
+                }
+                let failed = SagaEvent::step_failed(step, "interrupted");
+                *version = self.append_saga_event(saga_id, *version, &failed).await?;
+                saga.apply(failed);
+                self.compensate(saga, definition, saga_id, version, order_id)
+                    .await?;
+                corrections.push(Correction::SagaCompensated {
+                    interrupted_step: step.to_string(),
+                });
+            }
+            (SagaState::Failed, _) => match order_state {
+                OrderState::Cancelled => {}
+                state if state.can_cancel() => {
+                    let mut cancel = CancelOrder::new(
+                        order_id,
+                        "Saga failed",
+                        Some("saga_coordinator".to_string()),
+                    )
+                    .with_reason_code(saga.failure_kind().unwrap_or_default().code());
+                    if let Some(metadata) =
+                        caused_by(saga_id, failed_step_of(saga, definition), decisions)
+                    {
+                        cancel = cancel.with_metadata(metadata);
+                    }
+                    self.order_service.cancel_order(cancel).await?;
+                    corrections.push(Correction::OrderCancelled);
+                }
+                _ => {
+                    return needs_review(format!("order is {order_state} but its saga failed"));
+                }
+            },
+            (SagaState::Compensating, _) => {
+                return needs_review(
+                    "compensation was interrupted; completed steps may not have been undone".into(),
+                );
+            }
+            (SagaState::NotStarted, _) => {}
+        }
+
+        Ok(if corrections.is_empty() {
+            ReconciliationOutcome::InSync
+        } else {
+            ReconciliationOutcome::Repaired(corrections)
+        })
+    }
+
+    /// Moves an order forward through the transitions its completed saga
+    /// steps imply, returning the state it ends in if it moved.
+    async fn advance_order(
+        &self,
+        saga: &SagaInstance,
+        definition: &SagaDefinition,
+        saga_id: AggregateId,
+        order_id: AggregateId,
+        from: OrderState,
+        decisions: &HashMap<String, EventId>,
+    ) -> Result<Option<OrderState>, SagaError> {
+        let mut state = from;
+        // The n-th transition is caused by the n-th step of the definition
+        while let Some(step) = reconcile::order_progress(state)
+            .filter(|progress| *progress < saga.completed_steps().len())
+            .and_then(|progress| definition.steps.get(progress))
+        {
+            let metadata = caused_by(saga_id, step, decisions).unwrap_or_default();
+            let next = self
+                .advance_order_once(saga, order_id, state, metadata, Vec::new())
+                .await?;
+            if next == state {
+                break;
+            }
+            state = next;
+        }
+        Ok((state != from).then_some(state))
+    }
+
+    /// Moves an order one fulfillment state forward, carrying the context
+    /// the saga has gathered so far. Returns the order's new state, or
+    /// `state` unchanged if it's past fulfillment.
+    async fn advance_order_once(
+        &self,
+        saga: &SagaInstance,
+        order_id: AggregateId,
+        state: OrderState,
+        metadata: CommandMetadata,
+        shipped_items: Vec<ShippedItem>,
+    ) -> Result<OrderState, SagaError> {
+        match state {
+            OrderState::Draft => {
+                self.order_service
+                    .mark_reserved(
+                        MarkReserved::new(order_id)
+                            .with_reservations(saga.reservations().clone())
+                            .with_metadata(metadata),
+                    )
+                    .await?;
+                Ok(OrderState::Reserved)
+            }
+            OrderState::Reserved => {
+                self.order_service
+                    .start_processing(
+                        StartProcessing::new(order_id, saga.payment_id().map(String::from))
+                            .with_metadata(metadata),
+                    )
+                    .await?;
+                Ok(OrderState::Processing)
+            }
+            OrderState::Processing => {
+                self.order_service
+                    .complete_order(
+                        CompleteOrder::new(order_id, saga.tracking_number().map(String::from))
+                            .with_shipped_items(shipped_items)
+                            .with_metadata(metadata),
+                    )
+                    .await?;
+                Ok(OrderState::Completed)
+            }
+            state => Ok(state),
+        }
+    }
+
+    /// Checks the reservations of every reserved order with the inventory
+    /// service, e.g. periodically, catching those the warehouse let lapse.
+    ///
+    /// Returns a report for each order whose reservations were renewed or
+    /// that was cancelled; orders whose reservations are all held are left
+    /// out.
+    pub async fn reconcile_all_reservations(&self) -> Result<Vec<ReservationReport>, SagaError> {
+        let reserved = self.store.get_events_by_type("OrderReserved").await?;
+        let mut checked = HashSet::new();
+        let mut reports = Vec::new();
+        for envelope in reserved {
+            if envelope.aggregate_type != Order::aggregate_type()
+                || !checked.insert(envelope.aggregate_id)
+            {
+                continue;
+            }
+            let order_id = envelope.aggregate_id;
+            let Some(order) = self.order_service.get_order(order_id).await? else {
+                continue;
+            };
+            if order.state() != OrderState::Reserved {
+                continue;
+            }
+            let report = self.renew_lapsed_reservations(order_id, &order).await?;
+            if report.check != ReservationCheck::Held {
+                reports.push(report);
+            }
+        }
+        Ok(reports)
+    }
+
+    /// Checks a reserved order's reservations with the inventory service,
+    /// e.g. when the warehouse reports that one lapsed.
+    ///
+    /// Lapsed line items are reserved again and the new reservations
+    /// recorded on the order and its running saga. If one can't be, the
+    /// saga fails at its next step and compensates, cancelling the order;
+    /// an order without a running saga is cancelled directly. Fails with
+    /// [`SagaError::OrderNotReady`] if the order isn't reserved.
+    #[tracing::instrument(skip(self))]
+    pub async fn reconcile_reservations(
+        &self,
+        order_id: AggregateId,
+    ) -> Result<ReservationReport, SagaError> {
+        let order = self
+            .order_service
+            .get_order(order_id)
+            .await?
+            .ok_or(SagaError::OrderNotFound(order_id))?;
+        if order.state() != OrderState::Reserved {
+            return Err(SagaError::OrderNotReady(format!(
+                "Order is {}, not reserved",
+                order.state()
+            )));
+        }
+        self.renew_lapsed_reservations(order_id, &order).await
+    }
+
+    /// Re-reserves a reserved order's lapsed line items, or cancels the
+    /// order if they're out of stock.
+    async fn renew_lapsed_reservations(
+        &self,
+        order_id: AggregateId,
+        order: &Order,
+    ) -> Result<ReservationReport, SagaError> {
+        let mut lapsed = Vec::new();
+        for (product_id, reservation_id) in order.reservations() {
+            let status = self
+                .inventory
+                .get_reservation_status(reservation_id)
+                .await?;
+            if let Some(item) = order.get_item(product_id)
+                && status != ReservationStatus::Held
+            {
+                lapsed.push(ReservationItem {
+                    product_id: item.product_id.clone(),
+                    product_name: item.product_name.clone(),
+                    quantity: item.quantity,
+                });
+            }
+        }
+
+        let check = if lapsed.is_empty() {
+            ReservationCheck::Held
+        } else {
+            match self.inventory.reserve(order_id, lapsed).await {
+                Ok(reserved) => {
+                    let products = reserved.reservations.keys().cloned().collect();
+                    self.record_renewals(order_id, reserved.reservations)
+                        .await?;
+                    ReservationCheck::Renewed { products }
+                }
+                Err(InventoryError::OutOfStock { product_id }) => {
+                    let error = SagaError::Inventory(InventoryError::OutOfStock {
+                        product_id: product_id.clone(),
+                    });
+                    self.cancel_for_lapsed_reservation(order_id, order, &error)
+                        .await?;
+                    ReservationCheck::Cancelled { product_id }
+                }
+                Err(e) => return Err(e.into()),
+            }
+        };
+
+        metrics::counter!("order_reservation_checks", "outcome" => check.as_str()).increment(1);
+        match &check {
+            ReservationCheck::Held => {}
+            ReservationCheck::Renewed { products } => {
+                tracing::info!(%order_id, ?products, "lapsed reservations renewed");
+            }
+            ReservationCheck::Cancelled { product_id } => {
+                tracing::warn!(%order_id, %product_id, "order cancelled after its reservation lapsed");
+            }
+        }
+        Ok(ReservationReport { order_id, check })
+    }
+
+    /// Records renewed reservations on the order and, so compensation
+    /// releases them, on its running saga. The reservations are released
+    /// again if the order refuses them.
+    async fn record_renewals(
+        &self,
+        order_id: AggregateId,
+        reservations: BTreeMap<ProductId, String>,
+    ) -> Result<(), SagaError> {
+        let renew = RenewReservations::new(order_id, reservations.clone());
+        if let Err(e) = self.order_service.renew_reservations(renew).await {
+            for rid in reservations.values() {
+                self.release_reservation(rid).await;
+            }
+            return Err(e.into());
+        }
+
+        if let Some((saga_id, _, mut version)) = self.running_saga_of(order_id).await? {
+            for (product_id, rid) in reservations {
+                let replaced = SagaEvent::reservation_replaced(product_id.clone(), product_id, rid);
+                version = self.append_saga_event(saga_id, version, &replaced).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Cancels a reserved order whose lapsed reservation couldn't be
+    /// renewed, through its running saga's compensation if it has one.
+    async fn cancel_for_lapsed_reservation(
+        &self,
+        order_id: AggregateId,
+        order: &Order,
+        error: &SagaError,
+    ) -> Result<(), SagaError> {
+        if let Some((saga_id, mut saga, mut version)) = self.running_saga_of(order_id).await? {
+            let definition = self.definition_of(&saga)?;
+            let failed = SagaEvent::step_failed_with(failed_step_of(&saga, &definition), error);
+            version = self.append_saga_event(saga_id, version, &failed).await?;
+            saga.apply(failed);
+            return self
+                .compensate(&mut saga, &definition, saga_id, &mut version, order_id)
+                .await;
+        }
+
+        for rid in order.reservations().values() {
+            self.release_reservation(rid).await;
+        }
+        self.order_service
+            .cancel_order(
+                CancelOrder::new(
+                    order_id,
+                    format!("Reservation lapsed: {error}"),
+                    Some("saga_coordinator".to_string()),
+                )
+                .with_reason_code(error.failure_kind().code()),
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Returns the order's latest saga with its version, if that saga is
+    /// still running.
+    async fn running_saga_of(
+        &self,
+        order_id: AggregateId,
+    ) -> Result<Option<(AggregateId, SagaInstance, Version)>, SagaError> {
+        let Some(saga_id) = latest_saga_id(&self.store, order_id).await? else {
+            return Ok(None);
+        };
+        let mut saga = SagaInstance::default();
+        let mut version = Version::initial();
+        for envelope in self.store.get_events_for_aggregate(saga_id).await? {
+            version = envelope.version;
+            saga.apply(serde_json::from_value(envelope.payload)?);
+        }
+        Ok((saga.state() == SagaState::Running).then_some((saga_id, saga, version)))
     }
 
     /// Appends a single saga event to the event store.
@@ -397,15 +1554,41 @@ where
         current_version: Version,
         event: &SagaEvent,
     ) -> Result<Version, SagaError> {
-        let next_version = current_version.next();
+        let (version, _) = self
+            .append_saga_event_with_id(saga_id, current_version, event)
+            .await?;
+        Ok(version)
+    }
+
+    /// Appends a single saga event, also returning its event ID for order
+    /// events it causes to reference.
+    async fn append_saga_event_with_id(
+        &self,
+        saga_id: AggregateId,
+        current_version: Version,
+        event: &SagaEvent,
+    ) -> Result<(Version, EventId), SagaError> {
+        self.append_saga_event_in(saga_id, current_version, event, None)
+            .await
+    }
 
-        let envelope = EventEnvelope::builder()
-            .event_type(event.event_type())
-            .aggregate_id(saga_id)
-            .aggregate_type(SagaInstance::aggregate_type())
-            .version(next_version)
-            .payload(event)?
-            .build();
+    /// Appends a single saga event recorded under `trace`, if given,
+    /// returning its version and event ID.
+    async fn append_saga_event_in(
+        &self,
+        saga_id: AggregateId,
+        current_version: Version,
+        event: &SagaEvent,
+        trace: Option<&TraceContext>,
+    ) -> Result<(Version, EventId), SagaError> {
+        let envelope = self.envelopes.build(
+            saga_id,
+            current_version.next(),
+            event.event_type(),
+            event,
+            trace.map(TraceContext::to_metadata).unwrap_or_default(),
+        )?;
+        let event_id = envelope.event_id;
 
         let new_version = self
             .store
@@ -415,15 +1598,127 @@ where
             )
             .await?;
 
-        Ok(new_version)
+        Ok((new_version, event_id))
+    }
+}
+
+/// What the fulfillment steps need from the order.
+struct FulfillmentInput {
+    customer_id: CustomerId,
+    total_amount: Money,
+    items: Vec<ReservationItem>,
+    shipping_address: Option<Address>,
+}
+
+impl FulfillmentInput {
+    fn from_order(order: &Order) -> Result<Self, SagaError> {
+        let customer_id = order
+            .customer_id()
+            .ok_or_else(|| SagaError::OrderNotReady("Order has no customer ID".to_string()))?;
+        let items = order
+            .items()
+            .map(|item| ReservationItem {
+                product_id: item.product_id.clone(),
+                product_name: item.product_name.clone(),
+                quantity: item.quantity,
+            })
+            .collect();
+        Ok(Self {
+            customer_id,
+            total_amount: order.total_amount(),
+            items,
+            shipping_address: order.shipping_address().cloned(),
+        })
+    }
+}
+
+/// A saga being driven through its steps.
+struct SagaRun {
+    saga_id: AggregateId,
+    order_id: AggregateId,
+    input: FulfillmentInput,
+    definition: Arc<SagaDefinition>,
+    saga: SagaInstance,
+    version: Version,
+    order_state: OrderState,
+    trace: TraceContext,
+    started: Instant,
+}
+
+/// What a cancelled saga does at its next step boundary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OnCancel {
+    /// Fails the next step and compensates, for callers giving up on it.
+    Compensate,
+    /// Stops, leaving the saga running to be resumed, e.g. on shutdown.
+    Pause,
+}
+
+/// What a successful step call produced, for its StepCompleted event.
+#[derive(Default)]
+struct StepOutput {
+    reservations: BTreeMap<ProductId, String>,
+    payment_id: Option<String>,
+    tracking_number: Option<String>,
+    shipped_items: Vec<ShippedItem>,
+}
+
+/// Returns the step whose failure stops a saga: the first one in its
+/// definition it hasn't completed.
+fn failed_step_of<'a>(saga: &SagaInstance, definition: &'a SagaDefinition) -> &'a str {
+    definition
+        .next_step(saga.completed_steps())
+        .unwrap_or("unknown")
+}
+
+/// Causation metadata for an order transition decided by `step`, if the
+/// saga recorded that decision.
+fn caused_by(
+    saga_id: AggregateId,
+    step: &str,
+    decisions: &HashMap<String, EventId>,
+) -> Option<domain::CommandMetadata> {
+    decisions
+        .get(step)
+        .map(|event_id| SagaCausation::new(saga_id, step, *event_id).to_metadata())
+}
+
+/// Times a step attempt that started at `started_at` and whose service call
+/// began at `call_start` and has just returned.
+fn step_timing(attempt: u32, started_at: DateTime<Utc>, call_start: Instant) -> StepTiming {
+    StepTiming {
+        attempt,
+        started_at,
+        finished_at: Utc::now(),
+        service_latency_ms: call_start.elapsed().as_millis() as u64,
     }
 }
 
+/// Replays a saga instance from its events, or None if it has none.
+pub(crate) async fn load_saga<S: EventStore>(
+    store: &S,
+    saga_id: AggregateId,
+) -> Result<Option<SagaInstance>, SagaError> {
+    let events = store.get_events_for_aggregate(saga_id).await?;
+
+    if events.is_empty() {
+        return Ok(None);
+    }
+
+    let mut saga = SagaInstance::default();
+    for envelope in events {
+        let event: SagaEvent = serde_json::from_value(envelope.payload)?;
+        saga.apply(event);
+    }
+    Ok(Some(saga))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::failure::{FailureKind, ServiceFailure};
     use crate::services::inventory::InMemoryInventoryService;
-    use crate::services::payment::InMemoryPaymentService;
+    use crate::services::payment::{InMemoryPaymentService, PaymentError};
     use crate::services::shipping::InMemoryShippingService;
     use domain::{AddItem, CreateOrder, CustomerId, Money, OrderItem};
     use event_store::InMemoryEventStore;
@@ -481,6 +1776,51 @@ mod tests {
         order_id
     }
 
+    #[tokio::test]
+    async fn test_envelope_factory_applies_to_saga_and_order_events() {
+        let (coordinator, order_service, _, _, _) = setup().await;
+        let coordinator = coordinator.with_envelope_factory(EnvelopeFactory::new().with_enricher(
+            Arc::new(|envelope: &mut event_store::EventEnvelope| {
+                envelope
+                    .metadata
+                    .insert("service".to_string(), serde_json::json!("fulfillment"));
+            }),
+        ));
+        let order_id = create_order_with_items(&order_service).await;
+
+        let saga_id = coordinator.execute_saga(order_id).await.unwrap();
+
+        let saga_events = coordinator
+            .store
+            .get_events_for_aggregate(saga_id)
+            .await
+            .unwrap();
+        assert!(saga_events.iter().all(|e| {
+            e.aggregate_type == SagaInstance::aggregate_type()
+                && e.metadata["service"] == "fulfillment"
+        }));
+        // Order events the saga appended, but not those appended before it
+        let order_events = coordinator
+            .store
+            .get_events_for_aggregate(order_id)
+            .await
+            .unwrap();
+        let enriched: Vec<_> = order_events
+            .iter()
+            .filter(|e| e.metadata.contains_key("service"))
+            .map(|e| e.event_type.as_str())
+            .collect();
+        assert_eq!(
+            enriched,
+            [
+                "OrderSubmitted",
+                "OrderReserved",
+                "OrderProcessing",
+                "OrderCompleted"
+            ]
+        );
+    }
+
     #[tokio::test]
     async fn test_happy_path() {
         let (coordinator, order_service, inventory, payment, shipping) = setup().await;
@@ -492,68 +1832,302 @@ mod tests {
         let saga = coordinator.get_saga(saga_id).await.unwrap().unwrap();
         assert_eq!(saga.state(), crate::state::SagaState::Completed);
         assert_eq!(saga.completed_steps().len(), 3);
-        assert!(saga.reservation_id().is_some());
+        assert_eq!(saga.reservations().len(), 2);
         assert!(saga.payment_id().is_some());
         assert!(saga.tracking_number().is_some());
 
+        // Every step was timed on its first attempt
+        assert_eq!(saga.step_attempts().len(), 3);
+        for attempt in saga.step_attempts() {
+            assert_eq!(attempt.attempt, 1);
+            assert!(attempt.duration_ms().is_some());
+            assert!(attempt.service_latency_ms.is_some());
+            assert!(attempt.error.is_none());
+        }
+
         // Verify order state
         let order = order_service.get_order(order_id).await.unwrap().unwrap();
         assert_eq!(order.state(), OrderState::Completed);
+        assert_eq!(order.reservations(), saga.reservations());
 
-        // Verify external services
-        assert_eq!(inventory.reservation_count(), 1);
+        // Verify external services: one reservation per line item
+        assert_eq!(inventory.reservation_count(), 2);
         assert_eq!(payment.payment_count(), 1);
         assert_eq!(shipping.shipment_count(), 1);
     }
 
+    /// Returns the causation recorded on the order's `event_type` event,
+    /// checking that it names a saga event of `saga_event_type`.
+    async fn order_event_causation(
+        coordinator: &SagaCoordinator<
+            InMemoryEventStore,
+            InMemoryInventoryService,
+            InMemoryPaymentService,
+            InMemoryShippingService,
+        >,
+        order_id: AggregateId,
+        event_type: &str,
+        saga_event_type: &str,
+    ) -> SagaCausation {
+        let events = coordinator
+            .store
+            .get_events_for_aggregate(order_id)
+            .await
+            .unwrap();
+        let event = events.iter().find(|e| e.event_type == event_type).unwrap();
+        let causation = SagaCausation::from_metadata(&event.metadata).unwrap();
+        let saga_events = coordinator
+            .store
+            .get_events_for_aggregate(causation.saga_id)
+            .await
+            .unwrap();
+        let cause = saga_events
+            .iter()
+            .find(|e| e.event_id == causation.event_id)
+            .unwrap();
+        assert_eq!(cause.event_type, saga_event_type);
+        causation
+    }
+
     #[tokio::test]
-    async fn test_inventory_failure() {
-        let (coordinator, order_service, inventory, payment, shipping) = setup().await;
+    async fn test_order_transitions_reference_saga_steps() {
+        let (coordinator, order_service, _, _, _) = setup().await;
         let order_id = create_order_with_items(&order_service).await;
 
-        inventory.set_fail_on_reserve(true);
-
         let saga_id = coordinator.execute_saga(order_id).await.unwrap();
 
-        // Verify saga failed
-        let saga = coordinator.get_saga(saga_id).await.unwrap().unwrap();
-        assert_eq!(saga.state(), crate::state::SagaState::Failed);
-        assert!(saga.completed_steps().is_empty());
-
-        // Verify order cancelled
-        let order = order_service.get_order(order_id).await.unwrap().unwrap();
-        assert_eq!(order.state(), OrderState::Cancelled);
-
-        // No external service calls should remain
-        assert_eq!(inventory.reservation_count(), 0);
-        assert_eq!(payment.payment_count(), 0);
-        assert_eq!(shipping.shipment_count(), 0);
+        for (event_type, step) in [
+            ("OrderReserved", order_fulfillment::STEP_RESERVE_INVENTORY),
+            ("OrderProcessing", order_fulfillment::STEP_PROCESS_PAYMENT),
+            ("OrderCompleted", order_fulfillment::STEP_CREATE_SHIPMENT),
+        ] {
+            let causation =
+                order_event_causation(&coordinator, order_id, event_type, "StepCompleted").await;
+            assert_eq!(causation.saga_id, saga_id);
+            assert_eq!(causation.step, step);
+        }
     }
 
     #[tokio::test]
-    async fn test_payment_failure() {
-        let (coordinator, order_service, inventory, payment, shipping) = setup().await;
+    async fn test_cancellation_references_compensation() {
+        let (coordinator, order_service, _, payment, _) = setup().await;
         let order_id = create_order_with_items(&order_service).await;
-
         payment.set_fail_on_charge(true);
 
         let saga_id = coordinator.execute_saga(order_id).await.unwrap();
 
-        // Verify saga failed
-        let saga = coordinator.get_saga(saga_id).await.unwrap().unwrap();
+        let causation = order_event_causation(
+            &coordinator,
+            order_id,
+            "OrderCancelled",
+            "CompensationStarted",
+        )
+        .await;
+        assert_eq!(causation.saga_id, saga_id);
+        assert_eq!(causation.step, order_fulfillment::STEP_PROCESS_PAYMENT);
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_reports_failing_steps_without_side_effects() {
+        let (coordinator, order_service, inventory, payment, shipping) = setup().await;
+        let order_id = create_order_with_items(&order_service).await;
+
+        let report = coordinator.dry_run(order_id).await.unwrap();
+        assert!(report.would_succeed());
+        let checks: Vec<_> = report.steps.iter().map(|s| s.check.clone()).collect();
+        assert_eq!(
+            checks,
+            [StepCheck::Pass, StepCheck::Pass, StepCheck::Unchecked]
+        );
+
+        payment.set_fail_on_charge(true);
+        let report = coordinator.dry_run(order_id).await.unwrap();
+        assert!(!report.would_succeed());
+        assert_eq!(
+            report.steps[1].step,
+            order_fulfillment::STEP_PROCESS_PAYMENT
+        );
+        assert!(matches!(
+            &report.steps[1].check,
+            StepCheck::Fail { reason } if reason.contains("declined")
+        ));
+
+        // Nothing was reserved, charged, or recorded
+        assert_eq!(inventory.reservation_count(), 0);
+        assert_eq!(payment.payment_count(), 0);
+        assert_eq!(shipping.shipment_count(), 0);
+        let order = order_service.get_order(order_id).await.unwrap().unwrap();
+        assert_eq!(order.state(), OrderState::Draft);
+        assert_eq!(order.version(), Version::new(3));
+    }
+
+    #[tokio::test]
+    async fn test_inventory_failure() {
+        let (coordinator, order_service, inventory, payment, shipping) = setup().await;
+        let order_id = create_order_with_items(&order_service).await;
+
+        inventory.set_fail_on_reserve(true);
+
+        let saga_id = coordinator.execute_saga(order_id).await.unwrap();
+
+        // Verify saga failed
+        let saga = coordinator.get_saga(saga_id).await.unwrap().unwrap();
         assert_eq!(saga.state(), crate::state::SagaState::Failed);
-        assert_eq!(saga.completed_steps(), &["reserve_inventory"]);
+        assert!(saga.completed_steps().is_empty());
+        assert_eq!(saga.failure_kind(), Some(FailureKind::InsufficientStock));
 
         // Verify order cancelled
         let order = order_service.get_order(order_id).await.unwrap().unwrap();
         assert_eq!(order.state(), OrderState::Cancelled);
 
+        // No external service calls should remain
+        assert_eq!(inventory.reservation_count(), 0);
+        assert_eq!(payment.payment_count(), 0);
+        assert_eq!(shipping.shipment_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_payment_failure() {
+        let (coordinator, order_service, inventory, payment, shipping) = setup().await;
+        let order_id = create_order_with_items(&order_service).await;
+
+        payment.set_fail_on_charge(true);
+
+        let saga_id = coordinator.execute_saga(order_id).await.unwrap();
+
+        // Verify saga failed
+        let saga = coordinator.get_saga(saga_id).await.unwrap().unwrap();
+        assert_eq!(saga.state(), crate::state::SagaState::Failed);
+        assert_eq!(saga.completed_steps(), &["reserve_inventory"]);
+        assert_eq!(saga.failure_kind(), Some(FailureKind::PaymentDeclined));
+        assert!(matches!(
+            saga.failure_cause(),
+            Some(ServiceFailure::Payment(PaymentError::Declined { .. }))
+        ));
+        // A declined charge is not retried
+        assert_eq!(saga.attempts(order_fulfillment::STEP_PROCESS_PAYMENT), 1);
+
+        // Verify order cancelled, with the classified cause
+        let order = order_service.get_order(order_id).await.unwrap().unwrap();
+        assert_eq!(order.state(), OrderState::Cancelled);
+        let events = coordinator
+            .store
+            .get_events_for_aggregate(order_id)
+            .await
+            .unwrap();
+        let cancelled = events
+            .iter()
+            .find(|e| e.event_type == "OrderCancelled")
+            .unwrap();
+        assert_eq!(cancelled.payload["data"]["reason_code"], "payment_declined");
+
         // Inventory reservation should be released
         assert_eq!(inventory.reservation_count(), 0);
         assert_eq!(payment.payment_count(), 0);
         assert_eq!(shipping.shipment_count(), 0);
     }
 
+    #[tokio::test]
+    async fn test_unavailable_service_is_retried() {
+        let (coordinator, order_service, _, payment, _) = setup().await;
+        let order_id = create_order_with_items(&order_service).await;
+
+        payment.set_unavailable_charges(2);
+
+        let saga_id = coordinator.execute_saga(order_id).await.unwrap();
+
+        let saga = coordinator.get_saga(saga_id).await.unwrap().unwrap();
+        assert_eq!(saga.state(), crate::state::SagaState::Completed);
+        assert_eq!(saga.failure_kind(), None);
+        assert_eq!(saga.attempts(order_fulfillment::STEP_PROCESS_PAYMENT), 3);
+        let attempts: Vec<_> = saga
+            .step_attempts()
+            .iter()
+            .filter(|a| a.step_name == order_fulfillment::STEP_PROCESS_PAYMENT)
+            .map(|a| (a.attempt, a.error.is_some()))
+            .collect();
+        assert_eq!(attempts, [(1, true), (2, true), (3, false)]);
+        assert_eq!(payment.payment_count(), 1);
+
+        let events = coordinator
+            .store
+            .get_events_for_aggregate(saga_id)
+            .await
+            .unwrap();
+        let retried = events
+            .iter()
+            .find(|e| e.event_type == "StepFailed")
+            .unwrap();
+        assert_eq!(retried.payload["data"]["retrying"], true);
+        assert_eq!(retried.payload["data"]["cause"]["service"], "payment");
+        assert_eq!(
+            retried.payload["data"]["cause"]["error"]["type"],
+            "unavailable"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_each_step_attempt_gets_its_own_span() {
+        let (coordinator, order_service, _, payment, _) = setup().await;
+        let order_id = create_order_with_items(&order_service).await;
+        payment.set_unavailable_charges(1);
+
+        let saga_id = coordinator.execute_saga(order_id).await.unwrap();
+
+        let trace = coordinator.saga_trace(saga_id).await.unwrap().unwrap();
+        assert_eq!(trace.parent_span_id, None);
+        let spans: Vec<_> = trace
+            .steps
+            .iter()
+            .map(|s| (s.step_name.as_str(), s.attempt))
+            .collect();
+        assert_eq!(
+            spans,
+            [
+                (order_fulfillment::STEP_RESERVE_INVENTORY, 1),
+                (order_fulfillment::STEP_PROCESS_PAYMENT, 1),
+                (order_fulfillment::STEP_PROCESS_PAYMENT, 2),
+                (order_fulfillment::STEP_CREATE_SHIPMENT, 1),
+            ]
+        );
+        assert_ne!(trace.steps[1].span_id, trace.steps[2].span_id);
+
+        // A step's outcome is recorded under the span of its attempt
+        let events = coordinator
+            .store
+            .get_events_for_aggregate(saga_id)
+            .await
+            .unwrap();
+        let span_of = |event_type: &str| {
+            let event = events.iter().find(|e| e.event_type == event_type).unwrap();
+            TraceContext::from_metadata(&event.metadata).unwrap()
+        };
+        assert_eq!(span_of("StepFailed").span_id, trace.steps[1].span_id);
+        assert_eq!(span_of("SagaCompleted").span_id, trace.span_id);
+        assert_eq!(span_of("SagaCompleted").trace_id, trace.trace_id);
+    }
+
+    #[tokio::test]
+    async fn test_unavailable_service_fails_after_max_attempts() {
+        let (coordinator, order_service, inventory, payment, _) = setup().await;
+        let coordinator = coordinator.with_max_step_attempts(2);
+        let order_id = create_order_with_items(&order_service).await;
+
+        payment.set_unavailable_charges(2);
+
+        let saga_id = coordinator.execute_saga(order_id).await.unwrap();
+
+        let saga = coordinator.get_saga(saga_id).await.unwrap().unwrap();
+        assert_eq!(saga.state(), crate::state::SagaState::Failed);
+        assert_eq!(saga.failure_kind(), Some(FailureKind::Unknown));
+        assert!(matches!(
+            saga.failure_cause(),
+            Some(ServiceFailure::Payment(PaymentError::Unavailable { .. }))
+        ));
+        assert_eq!(saga.attempts(order_fulfillment::STEP_PROCESS_PAYMENT), 2);
+        assert_eq!(inventory.reservation_count(), 0);
+    }
+
     #[tokio::test]
     async fn test_shipping_failure() {
         let (coordinator, order_service, inventory, payment, shipping) = setup().await;
@@ -566,6 +2140,7 @@ mod tests {
         // Verify saga failed
         let saga = coordinator.get_saga(saga_id).await.unwrap().unwrap();
         assert_eq!(saga.state(), crate::state::SagaState::Failed);
+        assert_eq!(saga.failure_kind(), Some(FailureKind::CarrierUnavailable));
         assert_eq!(
             saga.completed_steps(),
             &["reserve_inventory", "process_payment"]
@@ -625,4 +2200,638 @@ mod tests {
         let result = coordinator.get_saga(AggregateId::new()).await.unwrap();
         assert!(result.is_none());
     }
+
+    #[tokio::test]
+    async fn test_cancelled_before_start() {
+        let (coordinator, order_service, inventory, _, _) = setup().await;
+        let order_id = create_order_with_items(&order_service).await;
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let result = coordinator.execute_saga_cancellable(order_id, cancel).await;
+
+        assert!(matches!(result, Err(SagaError::Cancelled)));
+        assert_eq!(inventory.reservation_count(), 0);
+        let order = order_service.get_order(order_id).await.unwrap().unwrap();
+        assert_eq!(order.state(), OrderState::Draft);
+    }
+
+    /// Payment service that cancels the saga once the charge succeeds.
+    struct CancellingPayment {
+        inner: InMemoryPaymentService,
+        cancel: CancellationToken,
+    }
+
+    #[async_trait::async_trait]
+    impl PaymentService for CancellingPayment {
+        async fn charge(
+            &self,
+            order_id: AggregateId,
+            customer_id: CustomerId,
+            amount: Money,
+        ) -> Result<crate::services::payment::PaymentResult, PaymentError> {
+            let result = self.inner.charge(order_id, customer_id, amount).await;
+            self.cancel.cancel();
+            result
+        }
+
+        async fn can_charge(
+            &self,
+            customer_id: CustomerId,
+            amount: Money,
+        ) -> Result<(), PaymentError> {
+            self.inner.can_charge(customer_id, amount).await
+        }
+
+        async fn refund(&self, payment_id: &str) -> Result<(), PaymentError> {
+            self.inner.refund(payment_id).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_between_steps_compensates() {
+        let store = InMemoryEventStore::new();
+        let inventory = InMemoryInventoryService::new();
+        let payment = InMemoryPaymentService::new();
+        let shipping = InMemoryShippingService::new();
+        let cancel = CancellationToken::new();
+
+        let coordinator = SagaCoordinator::new(
+            store.clone(),
+            inventory.clone(),
+            CancellingPayment {
+                inner: payment.clone(),
+                cancel: cancel.clone(),
+            },
+            shipping.clone(),
+        );
+        let order_service = OrderService::new(store);
+        let order_id = create_order_with_items(&order_service).await;
+
+        let saga_id = coordinator
+            .execute_saga_cancellable(order_id, cancel)
+            .await
+            .unwrap();
+
+        let saga = coordinator.get_saga(saga_id).await.unwrap().unwrap();
+        assert_eq!(saga.state(), crate::state::SagaState::Failed);
+        assert_eq!(saga.failure_reason(), Some("Step failed: cancelled"));
+
+        // Shipping never ran; payment and reservation were compensated
+        assert_eq!(shipping.shipment_count(), 0);
+        assert_eq!(payment.payment_count(), 0);
+        assert_eq!(inventory.reservation_count(), 0);
+        let order = order_service.get_order(order_id).await.unwrap().unwrap();
+        assert_eq!(order.state(), OrderState::Cancelled);
+    }
+
+    /// Starts a saga for `order_id` by hand and records `steps` as
+    /// completed, the way a coordinator that crashed after them would have.
+    async fn partial_saga(
+        coordinator: &SagaCoordinator<
+            InMemoryEventStore,
+            InMemoryInventoryService,
+            InMemoryPaymentService,
+            InMemoryShippingService,
+        >,
+        order_id: AggregateId,
+        steps: Vec<SagaEvent>,
+    ) -> AggregateId {
+        let saga_id = AggregateId::new();
+        let started = SagaEvent::saga_started(saga_id, order_id, order_fulfillment::SAGA_TYPE);
+        let mut version = coordinator
+            .append_saga_event(saga_id, Version::initial(), &started)
+            .await
+            .unwrap();
+        for event in steps {
+            version = coordinator
+                .append_saga_event(saga_id, version, &event)
+                .await
+                .unwrap();
+        }
+        saga_id
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_compensates_saga_interrupted_after_reservation() {
+        let (coordinator, order_service, inventory, _, _) = setup().await;
+        let order_id = create_order_with_items(&order_service).await;
+        order_service
+            .submit_order(SubmitOrder::new(order_id))
+            .await
+            .unwrap();
+
+        // Inventory reserved and the order moved, but the next saga append
+        // never happened
+        let reserved = inventory
+            .reserve(
+                order_id,
+                vec![ReservationItem {
+                    product_id: "SKU-001".into(),
+                    product_name: "Widget".to_string(),
+                    quantity: 2,
+                }],
+            )
+            .await
+            .unwrap();
+        let saga_id = partial_saga(
+            &coordinator,
+            order_id,
+            vec![SagaEvent::step_completed(
+                order_fulfillment::STEP_RESERVE_INVENTORY,
+                reserved.reservations.clone(),
+                None,
+                None,
+            )],
+        )
+        .await;
+        order_service
+            .mark_reserved(MarkReserved::new(order_id).with_reservations(reserved.reservations))
+            .await
+            .unwrap();
+
+        let report = coordinator.reconcile(saga_id).await.unwrap().unwrap();
+        assert_eq!(
+            report.outcome,
+            ReconciliationOutcome::Repaired(vec![Correction::SagaCompensated {
+                interrupted_step: order_fulfillment::STEP_PROCESS_PAYMENT.to_string(),
+            }])
+        );
+
+        let saga = coordinator.get_saga(saga_id).await.unwrap().unwrap();
+        assert_eq!(saga.state(), SagaState::Failed);
+        let order = order_service.get_order(order_id).await.unwrap().unwrap();
+        assert_eq!(order.state(), OrderState::Cancelled);
+        assert_eq!(inventory.reservation_count(), 0);
+
+        // A second pass finds nothing left to do
+        let report = coordinator.reconcile(saga_id).await.unwrap().unwrap();
+        assert_eq!(report.outcome, ReconciliationOutcome::InSync);
+    }
+
+    fn reordered_definition() -> SagaDefinition {
+        SagaDefinition::new(
+            order_fulfillment::SAGA_TYPE,
+            2,
+            [
+                order_fulfillment::STEP_RESERVE_INVENTORY,
+                order_fulfillment::STEP_CREATE_SHIPMENT,
+                order_fulfillment::STEP_PROCESS_PAYMENT,
+            ],
+        )
+    }
+
+    #[tokio::test]
+    async fn test_new_sagas_run_the_latest_definition() {
+        let (coordinator, order_service, _, payment, shipping) = setup().await;
+        coordinator
+            .definitions()
+            .register(reordered_definition())
+            .unwrap();
+        let order_id = create_order_with_items(&order_service).await;
+
+        let saga_id = coordinator.execute_saga(order_id).await.unwrap();
+
+        let saga = coordinator.get_saga(saga_id).await.unwrap().unwrap();
+        assert_eq!(saga.state(), SagaState::Completed);
+        assert_eq!(saga.definition_version(), 2);
+        assert_eq!(
+            saga.completed_steps(),
+            reordered_definition().steps.as_slice()
+        );
+        let order = order_service.get_order(order_id).await.unwrap().unwrap();
+        assert_eq!(order.state(), OrderState::Completed);
+        assert_eq!(payment.payment_count(), 1);
+        assert_eq!(shipping.shipment_count(), 1);
+
+        let graph = coordinator.saga_graph(saga_id).await.unwrap().unwrap();
+        let steps: Vec<_> = graph.nodes.iter().map(|n| n.id.clone()).collect();
+        assert_eq!(steps, reordered_definition().steps);
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_resumes_with_the_definition_the_saga_started_with() {
+        let (coordinator, order_service, inventory, _, _) = setup().await;
+        let order_id = create_order_with_items(&order_service).await;
+        order_service
+            .submit_order(SubmitOrder::new(order_id))
+            .await
+            .unwrap();
+        let reserved = inventory
+            .reserve(
+                order_id,
+                vec![ReservationItem {
+                    product_id: "SKU-001".into(),
+                    product_name: "Widget".to_string(),
+                    quantity: 2,
+                }],
+            )
+            .await
+            .unwrap();
+        let saga_id = partial_saga(
+            &coordinator,
+            order_id,
+            vec![SagaEvent::step_completed(
+                order_fulfillment::STEP_RESERVE_INVENTORY,
+                reserved.reservations,
+                None,
+                None,
+            )],
+        )
+        .await;
+
+        // A deploy moved shipment ahead of payment after the saga started
+        coordinator
+            .definitions()
+            .register(reordered_definition())
+            .unwrap();
+
+        let report = coordinator.reconcile(saga_id).await.unwrap().unwrap();
+        assert_eq!(
+            report.outcome,
+            ReconciliationOutcome::Repaired(vec![Correction::SagaCompensated {
+                interrupted_step: order_fulfillment::STEP_PROCESS_PAYMENT.to_string(),
+            }])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_substitution_replaces_reservation_and_notifies() {
+        use crate::services::notification::InMemoryCustomerNotifier;
+
+        let (coordinator, order_service, inventory, _, _) = setup().await;
+        let notifier = InMemoryCustomerNotifier::new();
+        let coordinator = coordinator.with_notifier(Arc::new(notifier.clone()));
+        let order_id = create_order_with_items(&order_service).await;
+        order_service
+            .submit_order(SubmitOrder::new(order_id))
+            .await
+            .unwrap();
+
+        // Stopped after reserving, before payment
+        let items = ["SKU-001", "SKU-002"].map(|sku| ReservationItem {
+            product_id: sku.into(),
+            product_name: "Widget".to_string(),
+            quantity: 1,
+        });
+        let reserved = inventory.reserve(order_id, items.to_vec()).await.unwrap();
+        let original_reservation = reserved.reservations[&ProductId::new("SKU-001")].clone();
+        let saga_id = partial_saga(
+            &coordinator,
+            order_id,
+            vec![SagaEvent::step_completed(
+                order_fulfillment::STEP_RESERVE_INVENTORY,
+                reserved.reservations.clone(),
+                None,
+                None,
+            )],
+        )
+        .await;
+        order_service
+            .mark_reserved(MarkReserved::new(order_id).with_reservations(reserved.reservations))
+            .await
+            .unwrap();
+
+        let order = coordinator
+            .substitute_item(SubstituteItem::new(
+                order_id,
+                "SKU-001",
+                "SKU-003",
+                "Widget Plus",
+                Money::from_cents(1500),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(order.total_amount(), Money::from_cents(5500));
+        let reservation = order.reservation(&ProductId::new("SKU-003")).unwrap();
+
+        // The saga compensates the substitute's reservation, not the original's
+        let saga = coordinator.get_saga(saga_id).await.unwrap().unwrap();
+        assert_eq!(saga.reservations(), order.reservations());
+        assert!(!inventory.has_reservation(&original_reservation));
+        assert!(inventory.has_reservation(reservation));
+
+        assert_eq!(
+            notifier.sent(),
+            [CustomerNotification::ItemSubstituted {
+                order_id,
+                customer_id: order.customer_id().unwrap(),
+                original_product_id: ProductId::new("SKU-001"),
+                substitute_product_id: ProductId::new("SKU-003"),
+                substitute_product_name: "Widget Plus".to_string(),
+                price_delta: Money::from_cents(1000),
+            }]
+        );
+
+        coordinator.reconcile(saga_id).await.unwrap();
+        assert_eq!(inventory.reservation_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_substitution_refused_before_reservation() {
+        let (coordinator, order_service, inventory, _, _) = setup().await;
+        let order_id = create_order_with_items(&order_service).await;
+
+        let result = coordinator
+            .substitute_item(SubstituteItem::new(
+                order_id,
+                "SKU-001",
+                "SKU-003",
+                "Widget Plus",
+                Money::from_cents(1500),
+            ))
+            .await;
+        assert!(matches!(
+            result,
+            Err(SagaError::Domain(DomainError::Order(
+                domain::OrderError::InvalidStateTransition { .. }
+            )))
+        ));
+        assert_eq!(inventory.reservation_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_flags_saga_on_unknown_definition() {
+        let (coordinator, order_service, _, _, _) = setup().await;
+        let order_id = create_order_with_items(&order_service).await;
+        let saga_id = AggregateId::new();
+        let started = SagaEvent::saga_started(saga_id, order_id, order_fulfillment::SAGA_TYPE)
+            .with_definition_version(9);
+        coordinator
+            .append_saga_event(saga_id, Version::initial(), &started)
+            .await
+            .unwrap();
+
+        let report = coordinator.reconcile(saga_id).await.unwrap().unwrap();
+        assert!(matches!(
+            report.outcome,
+            ReconciliationOutcome::NeedsReview { reason } if reason.contains("version 9")
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_advances_order_behind_finished_saga() {
+        let (coordinator, order_service, _, _, _) = setup().await;
+        let order_id = create_order_with_items(&order_service).await;
+        order_service
+            .submit_order(SubmitOrder::new(order_id))
+            .await
+            .unwrap();
+        order_service
+            .mark_reserved(MarkReserved::new(order_id))
+            .await
+            .unwrap();
+
+        // Every step completed, but the order never left Reserved
+        let saga_id = partial_saga(
+            &coordinator,
+            order_id,
+            vec![
+                SagaEvent::step_completed(
+                    order_fulfillment::STEP_RESERVE_INVENTORY,
+                    BTreeMap::new(),
+                    None,
+                    None,
+                ),
+                SagaEvent::step_completed(
+                    order_fulfillment::STEP_PROCESS_PAYMENT,
+                    BTreeMap::new(),
+                    Some("PAY-1".to_string()),
+                    None,
+                ),
+                SagaEvent::step_completed(
+                    order_fulfillment::STEP_CREATE_SHIPMENT,
+                    BTreeMap::new(),
+                    None,
+                    Some("TRACK-1".to_string()),
+                ),
+            ],
+        )
+        .await;
+
+        let report = coordinator.reconcile(saga_id).await.unwrap().unwrap();
+        assert_eq!(
+            report.outcome,
+            ReconciliationOutcome::Repaired(vec![
+                Correction::OrderAdvanced {
+                    to: OrderState::Completed
+                },
+                Correction::SagaCompleted,
+            ])
+        );
+
+        let order = order_service.get_order(order_id).await.unwrap().unwrap();
+        assert_eq!(order.state(), OrderState::Completed);
+        assert_eq!(order.tracking_number(), Some("TRACK-1"));
+        let saga = coordinator.get_saga(saga_id).await.unwrap().unwrap();
+        assert_eq!(saga.state(), SagaState::Completed);
+        let causation =
+            order_event_causation(&coordinator, order_id, "OrderCompleted", "StepCompleted").await;
+        assert_eq!(causation.step, order_fulfillment::STEP_CREATE_SHIPMENT);
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_flags_interrupted_compensation() {
+        let (coordinator, order_service, _, _, _) = setup().await;
+        let order_id = create_order_with_items(&order_service).await;
+        let saga_id = partial_saga(
+            &coordinator,
+            order_id,
+            vec![
+                SagaEvent::step_failed(order_fulfillment::STEP_RESERVE_INVENTORY, "timeout"),
+                SagaEvent::compensation_started(order_fulfillment::STEP_RESERVE_INVENTORY),
+            ],
+        )
+        .await;
+
+        let reports = coordinator.reconcile_all().await.unwrap();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].saga_id, saga_id);
+        assert!(matches!(
+            reports[0].outcome,
+            ReconciliationOutcome::NeedsReview { .. }
+        ));
+
+        // Flagging appends nothing
+        let order = order_service.get_order(order_id).await.unwrap().unwrap();
+        assert_eq!(order.state(), OrderState::Draft);
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_all_skips_finished_sagas() {
+        let (coordinator, order_service, _, _, _) = setup().await;
+        let order_id = create_order_with_items(&order_service).await;
+        coordinator.execute_saga(order_id).await.unwrap();
+
+        assert!(coordinator.reconcile_all().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_deferred_step_resumes_when_due() {
+        let (coordinator, order_service, _, _, shipping) = setup().await;
+        // Friday evening, after the carrier has closed
+        let clock = Arc::new(common::VirtualClock::new(
+            "2026-10-16T20:00:00Z".parse().unwrap(),
+        ));
+        let hours = crate::BusinessHours::new(
+            chrono::FixedOffset::east_opt(0).unwrap(),
+            chrono::NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+            chrono::NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+        );
+        let coordinator = coordinator
+            .with_clock(clock.clone())
+            .with_step_policy(order_fulfillment::STEP_CREATE_SHIPMENT, Arc::new(hours));
+        let order_id = create_order_with_items(&order_service).await;
+
+        let saga_id = coordinator.execute_saga(order_id).await.unwrap();
+        let saga = coordinator.get_saga(saga_id).await.unwrap().unwrap();
+        assert_eq!(saga.state(), SagaState::Running);
+        assert_eq!(saga.completed_steps().len(), 2);
+        let monday: DateTime<Utc> = "2026-10-19T09:00:00Z".parse().unwrap();
+        assert_eq!(
+            saga.deferred_until(order_fulfillment::STEP_CREATE_SHIPMENT),
+            Some(monday)
+        );
+        assert_eq!(shipping.shipment_count(), 0);
+
+        // Left alone until the deferral is up, even without a runner
+        let report = coordinator.reconcile(saga_id).await.unwrap().unwrap();
+        assert_eq!(report.outcome, ReconciliationOutcome::InSync);
+        let version = coordinator
+            .store
+            .get_aggregate_version(saga_id)
+            .await
+            .unwrap();
+        let state = coordinator
+            .resume_saga(saga_id, CancellationToken::new())
+            .await
+            .unwrap();
+        assert_eq!(state, Some(SagaState::Running));
+        assert_eq!(
+            coordinator
+                .store
+                .get_aggregate_version(saga_id)
+                .await
+                .unwrap(),
+            version
+        );
+
+        clock.advance(std::time::Duration::from_secs(3 * 24 * 3600));
+        let state = coordinator
+            .resume_saga(saga_id, CancellationToken::new())
+            .await
+            .unwrap();
+        assert_eq!(state, Some(SagaState::Completed));
+        assert_eq!(shipping.shipment_count(), 1);
+        let order = order_service.get_order(order_id).await.unwrap().unwrap();
+        assert_eq!(order.state(), OrderState::Completed);
+    }
+
+    /// Reserves an order's items under a saga stopped before payment.
+    async fn reserved_before_payment(
+        coordinator: &SagaCoordinator<
+            InMemoryEventStore,
+            InMemoryInventoryService,
+            InMemoryPaymentService,
+            InMemoryShippingService,
+        >,
+        order_service: &OrderService<InMemoryEventStore>,
+        inventory: &InMemoryInventoryService,
+    ) -> (AggregateId, AggregateId, BTreeMap<ProductId, String>) {
+        let order_id = create_order_with_items(order_service).await;
+        order_service
+            .submit_order(SubmitOrder::new(order_id))
+            .await
+            .unwrap();
+        let items = ["SKU-001", "SKU-002"].map(|sku| ReservationItem {
+            product_id: sku.into(),
+            product_name: "Widget".to_string(),
+            quantity: 1,
+        });
+        let reserved = inventory.reserve(order_id, items.to_vec()).await.unwrap();
+        let saga_id = partial_saga(
+            coordinator,
+            order_id,
+            vec![SagaEvent::step_completed(
+                order_fulfillment::STEP_RESERVE_INVENTORY,
+                reserved.reservations.clone(),
+                None,
+                None,
+            )],
+        )
+        .await;
+        order_service
+            .mark_reserved(
+                MarkReserved::new(order_id).with_reservations(reserved.reservations.clone()),
+            )
+            .await
+            .unwrap();
+        (order_id, saga_id, reserved.reservations)
+    }
+
+    #[tokio::test]
+    async fn test_lapsed_reservation_is_renewed() {
+        let (coordinator, order_service, inventory, _, _) = setup().await;
+        let (order_id, saga_id, reservations) =
+            reserved_before_payment(&coordinator, &order_service, &inventory).await;
+        let report = coordinator.reconcile_reservations(order_id).await.unwrap();
+        assert_eq!(report.check, ReservationCheck::Held);
+
+        let lapsed = &reservations[&ProductId::new("SKU-001")];
+        inventory.expire_reservation(lapsed);
+        let report = coordinator.reconcile_reservations(order_id).await.unwrap();
+        assert_eq!(
+            report.check,
+            ReservationCheck::Renewed {
+                products: vec![ProductId::new("SKU-001")]
+            }
+        );
+
+        // The order and its saga both hold the new reservation
+        let order = order_service.get_order(order_id).await.unwrap().unwrap();
+        assert_eq!(order.state(), OrderState::Reserved);
+        let renewed = order.reservation(&ProductId::new("SKU-001")).unwrap();
+        assert_ne!(renewed, lapsed);
+        assert!(inventory.has_reservation(renewed));
+        let saga = coordinator.get_saga(saga_id).await.unwrap().unwrap();
+        assert_eq!(saga.reservations(), order.reservations());
+        assert!(
+            coordinator
+                .reconcile_all_reservations()
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_lapsed_reservation_out_of_stock_cancels_order() {
+        let (coordinator, order_service, inventory, _, _) = setup().await;
+        let (order_id, saga_id, reservations) =
+            reserved_before_payment(&coordinator, &order_service, &inventory).await;
+        inventory.expire_reservation(&reservations[&ProductId::new("SKU-001")]);
+        inventory.set_fail_on_reserve(true);
+
+        let reports = coordinator.reconcile_all_reservations().await.unwrap();
+        assert_eq!(
+            reports,
+            [ReservationReport {
+                order_id,
+                check: ReservationCheck::Cancelled {
+                    product_id: ProductId::new("SKU-001")
+                },
+            }]
+        );
+
+        // The saga compensated, releasing the line that was still held
+        let saga = coordinator.get_saga(saga_id).await.unwrap().unwrap();
+        assert_eq!(saga.state(), SagaState::Failed);
+        assert_eq!(saga.failure_kind(), Some(FailureKind::InsufficientStock));
+        assert_eq!(inventory.reservation_count(), 0);
+        let order = order_service.get_order(order_id).await.unwrap().unwrap();
+        assert_eq!(order.state(), OrderState::Cancelled);
+
+        let result = coordinator.reconcile_reservations(order_id).await;
+        assert!(matches!(result, Err(SagaError::OrderNotReady(_))));
+    }
 }
@@ -0,0 +1,250 @@
+//! Versioned saga definitions.
+//!
+//! A definition is the ordered list of steps a saga type runs. Each saga
+//! records the definition version it started with in `SagaStarted`, and the
+//! coordinator looks that version up whenever it resumes, reconciles, or
+//! renders the saga. Changing the step order therefore means registering a
+//! new version: sagas already in flight keep the order they started with.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, RwLock};
+
+use crate::error::SagaError;
+use crate::order_fulfillment;
+
+/// The definition version recorded by sagas started before versions were
+/// tracked.
+pub const INITIAL_VERSION: u32 = 1;
+
+/// One version of a saga type's step order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SagaDefinition {
+    pub saga_type: String,
+    pub version: u32,
+    /// Steps in execution order.
+    pub steps: Vec<String>,
+}
+
+impl SagaDefinition {
+    /// Creates a definition from its steps in execution order.
+    pub fn new<I, T>(saga_type: impl Into<String>, version: u32, steps: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        Self {
+            saga_type: saga_type.into(),
+            version,
+            steps: steps.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// The order fulfillment steps as first released.
+    pub fn order_fulfillment_v1() -> Self {
+        Self::new(
+            order_fulfillment::SAGA_TYPE,
+            INITIAL_VERSION,
+            order_fulfillment::STEPS,
+        )
+    }
+
+    /// Returns the step names as string slices, in execution order.
+    pub fn step_names(&self) -> Vec<&str> {
+        self.steps.iter().map(String::as_str).collect()
+    }
+
+    /// Returns the first step in this definition the saga hasn't completed.
+    pub fn next_step<'a>(&'a self, completed: &[String]) -> Option<&'a str> {
+        self.steps
+            .iter()
+            .find(|step| !completed.contains(step))
+            .map(String::as_str)
+    }
+
+    fn validate(&self) -> Result<(), SagaError> {
+        let invalid = |reason: String| {
+            Err(SagaError::InvalidDefinition {
+                saga_type: self.saga_type.clone(),
+                version: self.version,
+                reason,
+            })
+        };
+        if self.version == 0 {
+            return invalid("versions start at 1".to_string());
+        }
+        if self.steps.is_empty() {
+            return invalid("no steps".to_string());
+        }
+        if let Some((i, step)) = self
+            .steps
+            .iter()
+            .enumerate()
+            .find(|(i, step)| self.steps[..*i].contains(step))
+        {
+            return invalid(format!("step {step} listed twice (position {})", i + 1));
+        }
+        // The coordinator only knows how to run the fulfillment steps, and
+        // the order reaches Completed only after all of them
+        if self.saga_type == order_fulfillment::SAGA_TYPE
+            && (self.steps.len() != order_fulfillment::STEPS.len()
+                || self
+                    .steps
+                    .iter()
+                    .any(|step| !order_fulfillment::STEPS.contains(&step.as_str())))
+        {
+            return invalid(format!(
+                "steps must be an ordering of {}",
+                order_fulfillment::STEPS.join(", ")
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A saga type's definitions by version.
+type Versions = BTreeMap<u32, Arc<SagaDefinition>>;
+
+/// Registry of saga definitions by type and version.
+///
+/// Clones share the registry, so a version registered at runtime is picked
+/// up by every coordinator holding a clone: new sagas start on the latest
+/// version, while running ones keep theirs. Registered versions are
+/// immutable, since sagas recorded against them must replay the same way.
+#[derive(Debug, Clone, Default)]
+pub struct SagaDefinitions {
+    inner: Arc<RwLock<HashMap<String, Versions>>>,
+}
+
+impl SagaDefinitions {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a registry holding the built-in definitions.
+    pub fn builtin() -> Self {
+        let definitions = Self::new();
+        definitions
+            .register(SagaDefinition::order_fulfillment_v1())
+            .expect("built-in definition is valid");
+        definitions
+    }
+
+    /// Registers a definition version.
+    ///
+    /// Registering an identical definition again is a no-op; registering a
+    /// different one under an existing version fails.
+    pub fn register(&self, definition: SagaDefinition) -> Result<(), SagaError> {
+        definition.validate()?;
+        let mut inner = self.inner.write().expect("saga definitions poisoned");
+        let versions = inner.entry(definition.saga_type.clone()).or_default();
+        match versions.get(&definition.version) {
+            Some(existing) if **existing == definition => Ok(()),
+            Some(_) => Err(SagaError::InvalidDefinition {
+                saga_type: definition.saga_type,
+                version: definition.version,
+                reason: "a different definition is already registered under this version"
+                    .to_string(),
+            }),
+            None => {
+                tracing::info!(
+                    saga_type = %definition.saga_type,
+                    version = definition.version,
+                    steps = ?definition.steps,
+                    "saga definition registered"
+                );
+                versions.insert(definition.version, Arc::new(definition));
+                Ok(())
+            }
+        }
+    }
+
+    /// Returns a specific version of a saga type's definition.
+    pub fn get(&self, saga_type: &str, version: u32) -> Option<Arc<SagaDefinition>> {
+        let inner = self.inner.read().expect("saga definitions poisoned");
+        inner.get(saga_type)?.get(&version).cloned()
+    }
+
+    /// Returns the latest version of a saga type's definition, which new
+    /// sagas start on.
+    pub fn current(&self, saga_type: &str) -> Option<Arc<SagaDefinition>> {
+        let inner = self.inner.read().expect("saga definitions poisoned");
+        inner
+            .get(saga_type)?
+            .last_key_value()
+            .map(|(_, definition)| definition.clone())
+    }
+
+    /// Returns the registered versions of a saga type, oldest first.
+    pub fn versions(&self, saga_type: &str) -> Vec<u32> {
+        let inner = self.inner.read().expect("saga definitions poisoned");
+        inner
+            .get(saga_type)
+            .map(|versions| versions.keys().copied().collect())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::order_fulfillment::{
+        SAGA_TYPE, STEP_CREATE_SHIPMENT, STEP_PROCESS_PAYMENT, STEP_RESERVE_INVENTORY,
+    };
+
+    fn v2() -> SagaDefinition {
+        SagaDefinition::new(
+            SAGA_TYPE,
+            2,
+            [
+                STEP_RESERVE_INVENTORY,
+                STEP_CREATE_SHIPMENT,
+                STEP_PROCESS_PAYMENT,
+            ],
+        )
+    }
+
+    #[test]
+    fn test_new_versions_become_current_and_old_ones_stay() {
+        let definitions = SagaDefinitions::builtin();
+        let handle = definitions.clone();
+        handle.register(v2()).unwrap();
+
+        assert_eq!(definitions.current(SAGA_TYPE).unwrap().version, 2);
+        assert_eq!(definitions.versions(SAGA_TYPE), [1, 2]);
+        let v1 = definitions.get(SAGA_TYPE, 1).unwrap();
+        assert_eq!(
+            v1.next_step(&[STEP_RESERVE_INVENTORY.to_string()]),
+            Some(STEP_PROCESS_PAYMENT)
+        );
+        assert!(definitions.get(SAGA_TYPE, 3).is_none());
+    }
+
+    #[test]
+    fn test_registered_versions_are_immutable() {
+        let definitions = SagaDefinitions::builtin();
+        definitions.register(v2()).unwrap();
+        definitions.register(v2()).unwrap();
+
+        let mut changed = v2();
+        changed.steps.pop();
+        assert!(matches!(
+            definitions.register(changed),
+            Err(SagaError::InvalidDefinition { version: 2, .. })
+        ));
+
+        let duplicate_step = SagaDefinition::new(SAGA_TYPE, 3, ["a", "b", "a"]);
+        assert!(definitions.register(duplicate_step).is_err());
+        let unknown_step = SagaDefinition::new(
+            SAGA_TYPE,
+            3,
+            [STEP_RESERVE_INVENTORY, STEP_PROCESS_PAYMENT, "notify"],
+        );
+        assert!(definitions.register(unknown_step).is_err());
+        assert!(
+            definitions
+                .register(SagaDefinition::new(SAGA_TYPE, 4, Vec::<String>::new()))
+                .is_err()
+        );
+    }
+}
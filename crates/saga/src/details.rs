@@ -0,0 +1,250 @@
+//! Composed view of an order and its fulfillment.
+//!
+//! An order page needs the order itself, the saga fulfilling it, and the
+//! payment and shipment that saga produced. [`OrderDetailsQuery`] loads them
+//! together so callers make one request instead of several.
+
+use common::AggregateId;
+use domain::{Money, Order, OrderService};
+use event_store::EventStore;
+
+use crate::aggregate::SagaInstance;
+use crate::coordinator::load_saga;
+use crate::error::SagaError;
+use crate::events::SagaEvent;
+use crate::order_fulfillment;
+
+/// Payment taken by an order's fulfillment saga.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaymentDetails {
+    pub payment_id: String,
+    pub amount: Money,
+    /// True if the payment was refunded when the saga compensated.
+    pub refunded: bool,
+}
+
+/// Shipment created for an order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShipmentDetails {
+    pub carrier: Option<String>,
+    pub tracking_number: Option<String>,
+    pub last_location: Option<String>,
+    /// True if the shipment was cancelled when the saga compensated.
+    pub cancelled: bool,
+}
+
+/// An order with its latest fulfillment saga, payment, and shipment.
+#[derive(Debug, Clone)]
+pub struct OrderDetails {
+    pub order: Order,
+    /// The most recently started saga for the order, if any.
+    pub saga: Option<SagaInstance>,
+    pub payment: Option<PaymentDetails>,
+    pub shipment: Option<ShipmentDetails>,
+}
+
+/// Loads an order together with its fulfillment state.
+pub struct OrderDetailsQuery<S: EventStore> {
+    store: S,
+    order_service: OrderService<S>,
+}
+
+impl<S: EventStore + Clone> OrderDetailsQuery<S> {
+    /// Creates a query reading from the given event store.
+    pub fn new(store: S) -> Self {
+        let order_service = OrderService::new(store.clone());
+        Self {
+            store,
+            order_service,
+        }
+    }
+
+    /// Returns the order's details, or None if the order doesn't exist.
+    pub async fn get(&self, order_id: AggregateId) -> Result<Option<OrderDetails>, SagaError> {
+        let Some(order) = self.order_service.get_order(order_id).await? else {
+            return Ok(None);
+        };
+        let saga = self.latest_saga(order_id).await?;
+
+        let compensated = |step: &str| {
+            saga.as_ref()
+                .is_some_and(|s| s.compensated_steps().iter().any(|c| c == step))
+        };
+        let payment = saga
+            .as_ref()
+            .and_then(|s| s.payment_id())
+            .map(|payment_id| PaymentDetails {
+                payment_id: payment_id.to_string(),
+                amount: order.total_amount(),
+                refunded: compensated(order_fulfillment::STEP_PROCESS_PAYMENT),
+            });
+
+        // The order records the carrier's view; the saga only knows the
+        // tracking number it was issued
+        let tracking_number = order
+            .tracking_number()
+            .or_else(|| saga.as_ref().and_then(|s| s.tracking_number()))
+            .map(String::from);
+        let shipment =
+            (tracking_number.is_some() || order.carrier().is_some()).then(|| ShipmentDetails {
+                carrier: order.carrier().map(String::from),
+                tracking_number,
+                last_location: order.last_location().map(String::from),
+                cancelled: compensated(order_fulfillment::STEP_CREATE_SHIPMENT),
+            });
+
+        Ok(Some(OrderDetails {
+            order,
+            saga,
+            payment,
+            shipment,
+        }))
+    }
+
+    /// Returns the most recently started saga for an order.
+    ///
+    /// Sagas are found by scanning `SagaStarted` events, so the cost grows
+    /// with the total number of sagas.
+    pub async fn latest_saga(
+        &self,
+        order_id: AggregateId,
+    ) -> Result<Option<SagaInstance>, SagaError> {
+        match latest_saga_id(&self.store, order_id).await? {
+            Some(saga_id) => load_saga(&self.store, saga_id).await,
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the IDs of every saga started for an order, oldest first.
+    ///
+    /// Scans `SagaStarted` events like [`latest_saga`](Self::latest_saga).
+    pub async fn saga_ids(&self, order_id: AggregateId) -> Result<Vec<AggregateId>, SagaError> {
+        saga_ids(&self.store, order_id).await
+    }
+}
+
+/// Returns the IDs of every saga started for an order, oldest first.
+async fn saga_ids<S: EventStore>(
+    store: &S,
+    order_id: AggregateId,
+) -> Result<Vec<AggregateId>, SagaError> {
+    let started = store.get_events_by_type("SagaStarted").await?;
+
+    let mut ids = Vec::new();
+    for envelope in started {
+        if let SagaEvent::SagaStarted(data) = serde_json::from_value(envelope.payload)?
+            && data.order_id == order_id
+        {
+            ids.push(data.saga_id);
+        }
+    }
+    Ok(ids)
+}
+
+/// Returns the ID of the most recently started saga for an order.
+pub(crate) async fn latest_saga_id<S: EventStore>(
+    store: &S,
+    order_id: AggregateId,
+) -> Result<Option<AggregateId>, SagaError> {
+    Ok(saga_ids(store, order_id).await?.pop())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        InMemoryInventoryService, InMemoryPaymentService, InMemoryShippingService, SagaCoordinator,
+        SagaState,
+    };
+    use domain::{AddItem, CreateOrder, CustomerId, OrderItem, OrderState};
+    use event_store::InMemoryEventStore;
+
+    async fn create_order(store: &InMemoryEventStore) -> AggregateId {
+        let service = OrderService::new(store.clone());
+        let cmd = CreateOrder::for_customer(CustomerId::new());
+        let order_id = cmd.order_id;
+        service.create_order(cmd).await.unwrap();
+        service
+            .add_item(AddItem::new(
+                order_id,
+                OrderItem::new("SKU-001", "Widget", 2, Money::from_cents(1000)),
+            ))
+            .await
+            .unwrap();
+        order_id
+    }
+
+    #[tokio::test]
+    async fn test_details_of_fulfilled_order() {
+        let store = InMemoryEventStore::new();
+        let order_id = create_order(&store).await;
+        let coordinator = SagaCoordinator::new(
+            store.clone(),
+            InMemoryInventoryService::new(),
+            InMemoryPaymentService::new(),
+            InMemoryShippingService::new(),
+        );
+        coordinator.execute_saga(order_id).await.unwrap();
+
+        let details = OrderDetailsQuery::new(store)
+            .get(order_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(details.order.state(), OrderState::Completed);
+        assert_eq!(details.saga.unwrap().state(), SagaState::Completed);
+
+        let payment = details.payment.unwrap();
+        assert_eq!(payment.amount, Money::from_cents(2000));
+        assert!(!payment.refunded);
+
+        let shipment = details.shipment.unwrap();
+        assert!(shipment.tracking_number.is_some());
+        assert!(!shipment.cancelled);
+    }
+
+    #[tokio::test]
+    async fn test_details_report_refunded_payment() {
+        let store = InMemoryEventStore::new();
+        let order_id = create_order(&store).await;
+        let shipping = InMemoryShippingService::new();
+        shipping.set_fail_on_create(true);
+        let coordinator = SagaCoordinator::new(
+            store.clone(),
+            InMemoryInventoryService::new(),
+            InMemoryPaymentService::new(),
+            shipping,
+        );
+        let _ = coordinator.execute_saga(order_id).await;
+
+        let details = OrderDetailsQuery::new(store)
+            .get(order_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(details.saga.unwrap().state(), SagaState::Failed);
+        assert!(details.payment.unwrap().refunded);
+        assert!(details.shipment.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_details_of_order_without_saga() {
+        let store = InMemoryEventStore::new();
+        let order_id = create_order(&store).await;
+
+        let details = OrderDetailsQuery::new(store.clone())
+            .get(order_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(details.saga.is_none());
+        assert!(details.payment.is_none());
+        assert!(details.shipment.is_none());
+
+        let missing = OrderDetailsQuery::new(store)
+            .get(AggregateId::new())
+            .await
+            .unwrap();
+        assert!(missing.is_none());
+    }
+}
@@ -0,0 +1,49 @@
+//! Fulfillment dry runs.
+//!
+//! A dry run validates an order the way a saga would and asks each external
+//! service whether its step would go through, without reserving, charging,
+//! or shipping anything and without recording a saga.
+
+use common::AggregateId;
+use serde::Serialize;
+
+/// What a dry run found for one step.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum StepCheck {
+    /// The service reports the step would succeed.
+    Pass,
+    /// The service reports the step would fail.
+    Fail { reason: String },
+    /// The service has no side-effect-free check for this step.
+    Unchecked,
+}
+
+/// A step and its dry-run result.
+#[derive(Debug, Clone, Serialize)]
+pub struct DryRunStep {
+    pub step: String,
+    #[serde(flatten)]
+    pub check: StepCheck,
+}
+
+/// The result of a fulfillment dry run.
+#[derive(Debug, Clone, Serialize)]
+pub struct DryRunReport {
+    pub order_id: AggregateId,
+    /// Definition version a saga started now would run.
+    pub definition_version: u32,
+    /// Every step of that definition, in execution order.
+    pub steps: Vec<DryRunStep>,
+}
+
+impl DryRunReport {
+    /// Returns true if no step is expected to fail. Unchecked steps may
+    /// still fail when the saga runs.
+    pub fn would_succeed(&self) -> bool {
+        !self
+            .steps
+            .iter()
+            .any(|s| matches!(s.check, StepCheck::Fail { .. }))
+    }
+}
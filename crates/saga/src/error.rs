@@ -5,6 +5,8 @@ use domain::DomainError;
 use event_store::EventStoreError;
 use thiserror::Error;
 
+use crate::failure::{FailureKind, ServiceFailure};
+use crate::services::{InventoryError, PaymentError, ShippingError};
 use crate::state::SagaState;
 
 /// Errors that can occur during saga operations.
@@ -24,15 +26,15 @@ pub enum SagaError {
 
     /// Inventory service error.
     #[error("Inventory service error: {0}")]
-    InventoryService(String),
+    Inventory(#[from] InventoryError),
 
     /// Payment service error.
     #[error("Payment service error: {0}")]
-    PaymentService(String),
+    Payment(#[from] PaymentError),
 
     /// Shipping service error.
     #[error("Shipping service error: {0}")]
-    ShippingService(String),
+    Shipping(#[from] ShippingError),
 
     /// Domain error.
     #[error("Domain error: {0}")]
@@ -57,6 +59,48 @@ pub enum SagaError {
     /// Order is not in the expected state for saga execution.
     #[error("Order not ready: {0}")]
     OrderNotReady(String),
+
+    /// A saga definition can't be registered.
+    #[error("Invalid {saga_type} definition v{version}: {reason}")]
+    InvalidDefinition {
+        saga_type: String,
+        version: u32,
+        reason: String,
+    },
+
+    /// No definition is registered for a saga type and version.
+    #[error("No {saga_type} definition registered for version {version}")]
+    UnknownDefinition { saga_type: String, version: u32 },
+
+    /// The caller cancelled the saga before it started.
+    #[error("Saga cancelled")]
+    Cancelled,
+}
+
+impl SagaError {
+    /// Classifies the error for customers. Errors that didn't come from a
+    /// service are [`FailureKind::Unknown`].
+    pub fn failure_kind(&self) -> FailureKind {
+        self.service_failure()
+            .map_or(FailureKind::Unknown, |failure| failure.kind())
+    }
+
+    /// Returns true if the service reported a failure that may not recur,
+    /// so the call is worth repeating.
+    pub fn is_retryable(&self) -> bool {
+        self.service_failure()
+            .is_some_and(|failure| failure.is_retryable())
+    }
+
+    /// Returns the service error, if a service call failed.
+    pub fn service_failure(&self) -> Option<ServiceFailure> {
+        match self {
+            SagaError::Inventory(e) => Some(ServiceFailure::Inventory(e.clone())),
+            SagaError::Payment(e) => Some(ServiceFailure::Payment(e.clone())),
+            SagaError::Shipping(e) => Some(ServiceFailure::Shipping(e.clone())),
+            _ => None,
+        }
+    }
 }
 
 /// Convenience type alias for saga results.
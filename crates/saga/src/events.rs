@@ -1,10 +1,16 @@
 //! Saga domain events.
 
+use std::collections::BTreeMap;
+
 use chrono::{DateTime, Utc};
 use common::AggregateId;
-use domain::DomainEvent;
+use domain::{DomainEvent, ProductId};
 use serde::{Deserialize, Serialize};
 
+use crate::definition::INITIAL_VERSION;
+use crate::error::SagaError;
+use crate::failure::{FailureKind, ServiceFailure};
+
 /// Events that can occur during saga execution.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", content = "data")]
@@ -12,8 +18,12 @@ pub enum SagaEvent {
     /// Saga execution started.
     SagaStarted(SagaStartedData),
 
+    /// A saga step was put off until a later time by its scheduling
+    /// policy.
+    StepDeferred(StepDeferredData),
+
     /// A saga step started execution.
-    StepStarted(StepData),
+    StepStarted(StepStartedData),
 
     /// A saga step completed successfully.
     StepCompleted(StepCompletedData),
@@ -30,6 +40,10 @@ pub enum SagaEvent {
     /// A compensation step failed (logged, compensation continues).
     CompensationStepFailed(StepFailedData),
 
+    /// A line item's reservation was replaced when the item was
+    /// substituted.
+    ReservationReplaced(ReservationReplacedData),
+
     /// Saga completed successfully.
     SagaCompleted(SagaCompletedData),
 
@@ -41,12 +55,14 @@ impl DomainEvent for SagaEvent {
     fn event_type(&self) -> &'static str {
         match self {
             SagaEvent::SagaStarted(_) => "SagaStarted",
+            SagaEvent::StepDeferred(_) => "StepDeferred",
             SagaEvent::StepStarted(_) => "StepStarted",
             SagaEvent::StepCompleted(_) => "StepCompleted",
             SagaEvent::StepFailed(_) => "StepFailed",
             SagaEvent::CompensationStarted(_) => "CompensationStarted",
             SagaEvent::CompensationStepCompleted(_) => "CompensationStepCompleted",
             SagaEvent::CompensationStepFailed(_) => "CompensationStepFailed",
+            SagaEvent::ReservationReplaced(_) => "ReservationReplaced",
             SagaEvent::SagaCompleted(_) => "SagaCompleted",
             SagaEvent::SagaFailed(_) => "SagaFailed",
         }
@@ -64,26 +80,88 @@ pub struct SagaStartedData {
     pub saga_type: String,
     /// When the saga started.
     pub started_at: DateTime<Utc>,
+    /// Version of the saga definition the saga runs. Sagas started before
+    /// versions were recorded ran the first one.
+    #[serde(default = "initial_definition_version")]
+    pub definition_version: u32,
 }
 
-/// Data for step started/completed events (just the step name).
+pub(crate) fn initial_definition_version() -> u32 {
+    INITIAL_VERSION
+}
+
+/// Data for compensation step events (just the step name).
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StepData {
     /// The step name.
     pub step_name: String,
 }
 
+/// Data for StepStarted event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepStartedData {
+    /// The step name.
+    pub step_name: String,
+    /// Which attempt at the step this is, starting at 1.
+    #[serde(default = "first_attempt")]
+    pub attempt: u32,
+    /// When the attempt started. Missing on events written before steps
+    /// were timed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<DateTime<Utc>>,
+}
+
+/// Data for StepDeferred event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepDeferredData {
+    /// The step put off.
+    pub step_name: String,
+    /// When the step may run.
+    pub until: DateTime<Utc>,
+}
+
+fn first_attempt() -> u32 {
+    1
+}
+
+/// How long one attempt at a step took.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StepTiming {
+    /// Which attempt at the step this was, starting at 1.
+    pub attempt: u32,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    /// Time spent waiting on the external service call.
+    pub service_latency_ms: u64,
+}
+
+impl StepTiming {
+    /// Returns the time between the attempt starting and finishing.
+    pub fn duration_ms(&self) -> i64 {
+        (self.finished_at - self.started_at).num_milliseconds()
+    }
+}
+
 /// Data for StepCompleted event.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StepCompletedData {
     /// The step name.
     pub step_name: String,
-    /// Reservation ID (set after reserve_inventory step).
+    /// Reservation ID per line item (set after reserve_inventory step).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub reservations: BTreeMap<ProductId, String>,
+    /// Whole-order reservation ID recorded by events written before
+    /// reservations were tracked per line item.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub reservation_id: Option<String>,
     /// Payment ID (set after process_payment step).
     pub payment_id: Option<String>,
     /// Tracking number (set after create_shipment step).
     pub tracking_number: Option<String>,
+    /// Timing of the attempt. Missing on events written before steps were
+    /// timed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timing: Option<StepTiming>,
 }
 
 /// Data for StepFailed event.
@@ -93,6 +171,24 @@ pub struct StepFailedData {
     pub step_name: String,
     /// Error message describing the failure.
     pub error: String,
+    /// Customer-facing classification of the failure. Unknown on
+    /// compensation failures and on events written before failures were
+    /// classified.
+    #[serde(default)]
+    pub kind: FailureKind,
+    /// The typed error the service reported. Missing when the failure
+    /// didn't come from a service and on events written before service
+    /// errors were typed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cause: Option<ServiceFailure>,
+    /// Whether the step was attempted again after this failure, rather
+    /// than failing the saga.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub retrying: bool,
+    /// Timing of the failed attempt. Missing on compensation failures and
+    /// on events written before steps were timed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timing: Option<StepTiming>,
 }
 
 /// Data for CompensationStarted event.
@@ -102,6 +198,17 @@ pub struct CompensationData {
     pub from_step: String,
 }
 
+/// Data for ReservationReplaced event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReservationReplacedData {
+    /// The product that was substituted.
+    pub original_product_id: ProductId,
+    /// The product now reserved in its place.
+    pub substitute_product_id: ProductId,
+    /// Reservation held for the substitute.
+    pub reservation_id: String,
+}
+
 /// Data for SagaCompleted event.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SagaCompletedData {
@@ -131,28 +238,59 @@ impl SagaEvent {
             order_id,
             saga_type: saga_type.into(),
             started_at: Utc::now(),
+            definition_version: INITIAL_VERSION,
         })
     }
 
-    /// Creates a StepStarted event.
+    /// Records the definition version on a SagaStarted event. Other events
+    /// are returned unchanged.
+    pub fn with_definition_version(mut self, version: u32) -> Self {
+        if let SagaEvent::SagaStarted(data) = &mut self {
+            data.definition_version = version;
+        }
+        self
+    }
+
+    /// Creates a StepStarted event for the first attempt at a step.
     pub fn step_started(step_name: impl Into<String>) -> Self {
-        SagaEvent::StepStarted(StepData {
+        Self::step_attempt_started(step_name, 1, Utc::now())
+    }
+
+    /// Creates a StepStarted event for the given attempt at a step.
+    pub fn step_attempt_started(
+        step_name: impl Into<String>,
+        attempt: u32,
+        started_at: DateTime<Utc>,
+    ) -> Self {
+        SagaEvent::StepStarted(StepStartedData {
+            step_name: step_name.into(),
+            attempt,
+            started_at: Some(started_at),
+        })
+    }
+
+    /// Creates a StepDeferred event putting a step off until `until`.
+    pub fn step_deferred(step_name: impl Into<String>, until: DateTime<Utc>) -> Self {
+        SagaEvent::StepDeferred(StepDeferredData {
             step_name: step_name.into(),
+            until,
         })
     }
 
     /// Creates a StepCompleted event.
     pub fn step_completed(
         step_name: impl Into<String>,
-        reservation_id: Option<String>,
+        reservations: BTreeMap<ProductId, String>,
         payment_id: Option<String>,
         tracking_number: Option<String>,
     ) -> Self {
         SagaEvent::StepCompleted(StepCompletedData {
             step_name: step_name.into(),
-            reservation_id,
+            reservations,
+            reservation_id: None,
             payment_id,
             tracking_number,
+            timing: None,
         })
     }
 
@@ -161,9 +299,46 @@ impl SagaEvent {
         SagaEvent::StepFailed(StepFailedData {
             step_name: step_name.into(),
             error: error.into(),
+            kind: FailureKind::Unknown,
+            cause: None,
+            retrying: false,
+            timing: None,
         })
     }
 
+    /// Creates a StepFailed event classified from the service error, with
+    /// the typed error as its cause.
+    pub fn step_failed_with(step_name: impl Into<String>, error: &SagaError) -> Self {
+        SagaEvent::StepFailed(StepFailedData {
+            step_name: step_name.into(),
+            error: error.to_string(),
+            kind: error.failure_kind(),
+            cause: error.service_failure(),
+            retrying: false,
+            timing: None,
+        })
+    }
+
+    /// Marks a StepFailed event as followed by another attempt. Other
+    /// events are returned unchanged.
+    pub fn retrying(mut self) -> Self {
+        if let SagaEvent::StepFailed(data) = &mut self {
+            data.retrying = true;
+        }
+        self
+    }
+
+    /// Attaches the attempt's timing to a StepCompleted or StepFailed
+    /// event. Other events are returned unchanged.
+    pub fn with_timing(mut self, timing: StepTiming) -> Self {
+        match &mut self {
+            SagaEvent::StepCompleted(data) => data.timing = Some(timing),
+            SagaEvent::StepFailed(data) => data.timing = Some(timing),
+            _ => {}
+        }
+        self
+    }
+
     /// Creates a CompensationStarted event.
     pub fn compensation_started(from_step: impl Into<String>) -> Self {
         SagaEvent::CompensationStarted(CompensationData {
@@ -186,6 +361,23 @@ impl SagaEvent {
         SagaEvent::CompensationStepFailed(StepFailedData {
             step_name: step_name.into(),
             error: error.into(),
+            kind: FailureKind::Unknown,
+            cause: None,
+            retrying: false,
+            timing: None,
+        })
+    }
+
+    /// Creates a ReservationReplaced event.
+    pub fn reservation_replaced(
+        original_product_id: ProductId,
+        substitute_product_id: ProductId,
+        reservation_id: impl Into<String>,
+    ) -> Self {
+        SagaEvent::ReservationReplaced(ReservationReplacedData {
+            original_product_id,
+            substitute_product_id,
+            reservation_id: reservation_id.into(),
         })
     }
 
@@ -208,6 +400,11 @@ impl SagaEvent {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::services::PaymentError;
+
+    fn reservations() -> BTreeMap<ProductId, String> {
+        BTreeMap::from([(ProductId::new("SKU-001"), "RES-1".to_string())])
+    }
 
     #[test]
     fn test_event_type() {
@@ -223,10 +420,13 @@ mod tests {
             "StepStarted"
         );
         assert_eq!(
-            SagaEvent::step_completed("reserve_inventory", Some("RES-1".into()), None, None)
-                .event_type(),
+            SagaEvent::step_completed("reserve_inventory", reservations(), None, None).event_type(),
             "StepCompleted"
         );
+        assert_eq!(
+            SagaEvent::step_deferred("create_shipment", Utc::now()).event_type(),
+            "StepDeferred"
+        );
         assert_eq!(
             SagaEvent::step_failed("reserve_inventory", "out of stock").event_type(),
             "StepFailed"
@@ -243,6 +443,15 @@ mod tests {
             SagaEvent::compensation_step_failed("reserve_inventory", "service down").event_type(),
             "CompensationStepFailed"
         );
+        assert_eq!(
+            SagaEvent::reservation_replaced(
+                ProductId::new("SKU-001"),
+                ProductId::new("SKU-003"),
+                "RES-0003"
+            )
+            .event_type(),
+            "ReservationReplaced"
+        );
         assert_eq!(SagaEvent::saga_completed().event_type(), "SagaCompleted");
         assert_eq!(
             SagaEvent::saga_failed("step failed").event_type(),
@@ -258,7 +467,7 @@ mod tests {
         let events = vec![
             SagaEvent::saga_started(saga_id, order_id, "OrderFulfillment"),
             SagaEvent::step_started("reserve_inventory"),
-            SagaEvent::step_completed("reserve_inventory", Some("RES-1".into()), None, None),
+            SagaEvent::step_completed("reserve_inventory", reservations(), None, None),
             SagaEvent::step_failed("process_payment", "insufficient funds"),
             SagaEvent::compensation_started("process_payment"),
             SagaEvent::compensation_step_completed("reserve_inventory"),
@@ -294,8 +503,12 @@ mod tests {
 
     #[test]
     fn test_step_completed_data() {
-        let event =
-            SagaEvent::step_completed("process_payment", None, Some("PAY-123".to_string()), None);
+        let event = SagaEvent::step_completed(
+            "process_payment",
+            BTreeMap::new(),
+            Some("PAY-123".to_string()),
+            None,
+        );
 
         let json = serde_json::to_string(&event).unwrap();
         let deserialized: SagaEvent = serde_json::from_str(&json).unwrap();
@@ -303,10 +516,110 @@ mod tests {
         if let SagaEvent::StepCompleted(data) = deserialized {
             assert_eq!(data.step_name, "process_payment");
             assert_eq!(data.payment_id, Some("PAY-123".to_string()));
-            assert!(data.reservation_id.is_none());
+            assert!(data.reservations.is_empty());
             assert!(data.tracking_number.is_none());
         } else {
             panic!("Expected StepCompleted event");
         }
     }
+
+    #[test]
+    fn test_step_timing_roundtrip() {
+        let started_at = Utc::now();
+        let timing = StepTiming {
+            attempt: 2,
+            started_at,
+            finished_at: started_at + chrono::Duration::milliseconds(120),
+            service_latency_ms: 100,
+        };
+        let event = SagaEvent::step_failed("process_payment", "card declined").with_timing(timing);
+
+        let json = serde_json::to_string(&event).unwrap();
+        let deserialized: SagaEvent = serde_json::from_str(&json).unwrap();
+
+        if let SagaEvent::StepFailed(data) = deserialized {
+            assert_eq!(data.timing, Some(timing));
+            assert_eq!(timing.duration_ms(), 120);
+        } else {
+            panic!("Expected StepFailed event");
+        }
+    }
+
+    #[test]
+    fn test_step_failure_is_classified_from_the_service_error() {
+        let declined = PaymentError::Declined {
+            code: "card_declined".to_string(),
+        };
+        let error = SagaError::from(declined.clone());
+        let event = SagaEvent::step_failed_with("process_payment", &error);
+        let json = serde_json::to_string(&event).unwrap();
+        match serde_json::from_str(&json).unwrap() {
+            SagaEvent::StepFailed(data) => {
+                assert_eq!(data.kind, FailureKind::PaymentDeclined);
+                assert_eq!(
+                    data.error,
+                    "Payment service error: Payment declined: card_declined"
+                );
+                assert_eq!(data.cause, Some(ServiceFailure::Payment(declined)));
+                assert!(!data.retrying);
+            }
+            _ => panic!("Expected StepFailed event"),
+        }
+
+        let legacy = r#"{"type":"StepFailed","data":{"step_name":"process_payment","error":"Payment service error: Payment declined"}}"#;
+        match serde_json::from_str(legacy).unwrap() {
+            SagaEvent::StepFailed(data) => {
+                assert_eq!(data.kind, FailureKind::Unknown);
+                assert!(data.cause.is_none());
+            }
+            _ => panic!("Expected StepFailed event"),
+        }
+    }
+
+    #[test]
+    fn test_untimed_step_events_still_deserialize() {
+        let json = r#"{"type":"StepStarted","data":{"step_name":"reserve_inventory"}}"#;
+        let event: SagaEvent = serde_json::from_str(json).unwrap();
+
+        if let SagaEvent::StepStarted(data) = event {
+            assert_eq!(data.attempt, 1);
+            assert!(data.started_at.is_none());
+        } else {
+            panic!("Expected StepStarted event");
+        }
+    }
+
+    #[test]
+    fn test_step_completed_reads_whole_order_reservation() {
+        let json = r#"{"type":"StepCompleted","data":{"step_name":"reserve_inventory","reservation_id":"RES-1","payment_id":null,"tracking_number":null}}"#;
+        let event: SagaEvent = serde_json::from_str(json).unwrap();
+
+        if let SagaEvent::StepCompleted(data) = event {
+            assert!(data.reservations.is_empty());
+            assert_eq!(data.reservation_id.as_deref(), Some("RES-1"));
+        } else {
+            panic!("Expected StepCompleted event");
+        }
+    }
+
+    #[test]
+    fn test_saga_started_without_version_ran_first_definition() {
+        let json = r#"{"type":"SagaStarted","data":{"saga_id":"00000000-0000-0000-0000-000000000001","order_id":"00000000-0000-0000-0000-000000000002","saga_type":"OrderFulfillment","started_at":"2024-01-01T00:00:00Z"}}"#;
+        let event: SagaEvent = serde_json::from_str(json).unwrap();
+
+        if let SagaEvent::SagaStarted(data) = event {
+            assert_eq!(data.definition_version, INITIAL_VERSION);
+        } else {
+            panic!("Expected SagaStarted event");
+        }
+
+        let versioned =
+            SagaEvent::saga_started(AggregateId::new(), AggregateId::new(), "OrderFulfillment")
+                .with_definition_version(3);
+        let json = serde_json::to_string(&versioned).unwrap();
+        match serde_json::from_str(&json).unwrap() {
+            SagaEvent::SagaStarted(data) => assert_eq!(data.definition_version, 3),
+            _ => panic!("Expected SagaStarted event"),
+        }
+    }
 }
@@ -0,0 +1,121 @@
+//! Saga failure classification.
+//!
+//! Service errors are classified into a small taxonomy so the reason a
+//! saga failed can be shown to customers without exposing raw errors.
+
+use serde::{Deserialize, Serialize};
+
+use crate::services::{InventoryError, PaymentError, ShippingError};
+
+/// A typed error from one of the services behind the saga steps, recorded
+/// on `StepFailed` events.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "service", content = "error", rename_all = "snake_case")]
+pub enum ServiceFailure {
+    Inventory(InventoryError),
+    Payment(PaymentError),
+    Shipping(ShippingError),
+}
+
+impl ServiceFailure {
+    /// Classifies the failure for customers.
+    pub fn kind(&self) -> FailureKind {
+        match self {
+            ServiceFailure::Inventory(e) => e.failure_kind(),
+            ServiceFailure::Payment(e) => e.failure_kind(),
+            ServiceFailure::Shipping(e) => e.failure_kind(),
+        }
+    }
+
+    /// Returns true if repeating the failed call may succeed.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ServiceFailure::Inventory(e) => e.is_retryable(),
+            ServiceFailure::Payment(e) => e.is_retryable(),
+            ServiceFailure::Shipping(e) => e.is_retryable(),
+        }
+    }
+}
+
+/// Why a saga step failed, as far as the customer is concerned.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureKind {
+    /// Inventory couldn't reserve the requested quantities.
+    InsufficientStock,
+    /// The payment provider refused the charge.
+    PaymentDeclined,
+    /// No carrier could take the shipment.
+    CarrierUnavailable,
+    /// Anything else, including failures recorded before classification.
+    #[default]
+    Unknown,
+}
+
+impl FailureKind {
+    /// Returns the stable code recorded as the order's cancellation
+    /// `reason_code`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            FailureKind::InsufficientStock => "insufficient_stock",
+            FailureKind::PaymentDeclined => "payment_declined",
+            FailureKind::CarrierUnavailable => "carrier_unavailable",
+            FailureKind::Unknown => "unknown",
+        }
+    }
+
+    /// Returns a message a frontend can show the customer as is.
+    pub fn customer_message(&self) -> &'static str {
+        match self {
+            FailureKind::InsufficientStock => {
+                "Some items in your order are out of stock. Remove them or reduce the quantity and try again."
+            }
+            FailureKind::PaymentDeclined => {
+                "Your payment was declined. Please use a different payment method."
+            }
+            FailureKind::CarrierUnavailable => {
+                "We couldn't arrange shipping for your order right now. Please try again later."
+            }
+            FailureKind::Unknown => {
+                "We couldn't complete your order. Please try again or contact support."
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_code_matches_serialized_form() {
+        for kind in [
+            FailureKind::InsufficientStock,
+            FailureKind::PaymentDeclined,
+            FailureKind::CarrierUnavailable,
+            FailureKind::Unknown,
+        ] {
+            let json = serde_json::to_value(kind).unwrap();
+            assert_eq!(json, kind.code());
+        }
+    }
+
+    #[test]
+    fn test_service_failure_serialized_form() {
+        let failure = ServiceFailure::Payment(PaymentError::Declined {
+            code: "insufficient_funds".to_string(),
+        });
+        let json = serde_json::to_value(&failure).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "service": "payment",
+                "error": {"type": "declined", "code": "insufficient_funds"}
+            })
+        );
+        let parsed: ServiceFailure = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, failure);
+        assert_eq!(parsed.kind(), FailureKind::PaymentDeclined);
+        assert!(!parsed.is_retryable());
+    }
+}
@@ -0,0 +1,364 @@
+//! Renderable step graph of a saga execution.
+//!
+//! Built from the saga definition's step list and the saga's recorded
+//! events, with per-step status and timings. Rendered as JSON nodes/edges
+//! or as Graphviz DOT.
+
+use std::fmt::Write;
+
+use chrono::{DateTime, Utc};
+use domain::Aggregate;
+use serde::Serialize;
+
+use crate::aggregate::SagaInstance;
+use crate::events::SagaEvent;
+use crate::state::SagaState;
+
+/// Status of a single saga step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    /// Not reached yet.
+    Pending,
+    /// Put off by its scheduling policy until a later time.
+    Deferred,
+    /// Started but not finished.
+    Running,
+    Completed,
+    Failed,
+    /// Completed, then undone by its compensating action.
+    Compensated,
+    /// Completed, but its compensating action failed.
+    CompensationFailed,
+}
+
+impl StepStatus {
+    /// Returns the status name as a string.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StepStatus::Pending => "pending",
+            StepStatus::Deferred => "deferred",
+            StepStatus::Running => "running",
+            StepStatus::Completed => "completed",
+            StepStatus::Failed => "failed",
+            StepStatus::Compensated => "compensated",
+            StepStatus::CompensationFailed => "compensation_failed",
+        }
+    }
+
+    fn dot_color(&self) -> &'static str {
+        match self {
+            StepStatus::Pending => "lightgrey",
+            StepStatus::Deferred => "lightyellow",
+            StepStatus::Running => "lightblue",
+            StepStatus::Completed => "palegreen",
+            StepStatus::Failed => "salmon",
+            StepStatus::Compensated => "khaki",
+            StepStatus::CompensationFailed => "orange",
+        }
+    }
+}
+
+/// A step in the saga graph.
+#[derive(Debug, Clone, Serialize)]
+pub struct GraphNode {
+    /// The step name.
+    pub id: String,
+    pub status: StepStatus,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Time between the step starting and finishing.
+    pub duration_ms: Option<i64>,
+    /// Number of attempts started at the step.
+    pub attempts: u32,
+    /// Time the latest attempt spent waiting on its external service.
+    pub service_latency_ms: Option<u64>,
+    /// Step or compensation error, if any.
+    pub error: Option<String>,
+}
+
+/// Kind of transition between steps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeKind {
+    /// Forward execution order from the saga definition.
+    Next,
+    /// Order in which compensating actions ran.
+    Compensation,
+}
+
+/// A transition between two steps.
+#[derive(Debug, Clone, Serialize)]
+pub struct GraphEdge {
+    pub from: String,
+    pub to: String,
+    pub kind: EdgeKind,
+}
+
+/// Step graph of one saga execution.
+#[derive(Debug, Clone, Serialize)]
+pub struct SagaGraph {
+    pub saga_id: String,
+    pub saga_type: String,
+    pub state: SagaState,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+impl SagaGraph {
+    /// Builds the graph for `saga` from its definition's `steps` and its
+    /// events, each paired with the time it was recorded.
+    pub fn build(
+        saga: &SagaInstance,
+        steps: &[&str],
+        events: impl IntoIterator<Item = (DateTime<Utc>, SagaEvent)>,
+    ) -> Self {
+        let mut nodes: Vec<GraphNode> = steps.iter().map(|step| GraphNode::new(step)).collect();
+        let mut started_at = None;
+        let mut finished_at = None;
+        let mut failed_step: Option<String> = None;
+        let mut compensated: Vec<String> = Vec::new();
+
+        for (at, event) in events {
+            match event {
+                SagaEvent::SagaStarted(_) => started_at = Some(at),
+                SagaEvent::StepDeferred(data) => {
+                    node_mut(&mut nodes, &data.step_name).status = StepStatus::Deferred;
+                }
+                SagaEvent::StepStarted(data) => {
+                    let node = node_mut(&mut nodes, &data.step_name);
+                    node.status = StepStatus::Running;
+                    node.started_at = Some(at);
+                    node.attempts = data.attempt;
+                }
+                SagaEvent::StepCompleted(data) => {
+                    let node = node_mut(&mut nodes, &data.step_name);
+                    node.finish(StepStatus::Completed, at);
+                    node.service_latency_ms = data.timing.map(|t| t.service_latency_ms);
+                }
+                SagaEvent::StepFailed(data) => {
+                    let node = node_mut(&mut nodes, &data.step_name);
+                    node.finish(StepStatus::Failed, at);
+                    node.service_latency_ms = data.timing.map(|t| t.service_latency_ms);
+                    node.error = Some(data.error);
+                    if !data.retrying {
+                        failed_step = Some(data.step_name);
+                    }
+                }
+                SagaEvent::CompensationStarted(_) | SagaEvent::ReservationReplaced(_) => {}
+                SagaEvent::CompensationStepCompleted(data) => {
+                    node_mut(&mut nodes, &data.step_name).status = StepStatus::Compensated;
+                    compensated.push(data.step_name);
+                }
+                SagaEvent::CompensationStepFailed(data) => {
+                    let node = node_mut(&mut nodes, &data.step_name);
+                    node.status = StepStatus::CompensationFailed;
+                    node.error = Some(data.error);
+                    compensated.push(data.step_name);
+                }
+                SagaEvent::SagaCompleted(_) | SagaEvent::SagaFailed(_) => finished_at = Some(at),
+            }
+        }
+
+        let mut edges: Vec<GraphEdge> = steps
+            .windows(2)
+            .map(|pair| GraphEdge::new(pair[0], pair[1], EdgeKind::Next))
+            .collect();
+        let compensation_path = failed_step.iter().chain(compensated.iter());
+        edges.extend(
+            compensation_path
+                .clone()
+                .zip(compensation_path.skip(1))
+                .map(|(from, to)| GraphEdge::new(from, to, EdgeKind::Compensation)),
+        );
+
+        Self {
+            saga_id: saga.id().map(|id| id.to_string()).unwrap_or_default(),
+            saga_type: saga.saga_type().to_string(),
+            state: saga.state(),
+            started_at,
+            finished_at,
+            nodes,
+            edges,
+        }
+    }
+
+    /// Renders the graph in Graphviz DOT format.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        let _ = writeln!(dot, "digraph \"{}\" {{", escape(&self.saga_id));
+        let _ = writeln!(dot, "  rankdir=LR;");
+        let _ = writeln!(
+            dot,
+            "  label=\"{} ({})\";",
+            escape(&self.saga_type),
+            self.state
+        );
+        let _ = writeln!(dot, "  node [shape=box, style=\"rounded,filled\"];");
+
+        for node in &self.nodes {
+            let mut label = format!("{}\\n{}", escape(&node.id), node.status.as_str());
+            if let Some(ms) = node.duration_ms {
+                let _ = write!(label, " ({ms} ms)");
+            }
+            if let Some(error) = &node.error {
+                let _ = write!(label, "\\n{}", escape(error));
+            }
+            let _ = writeln!(
+                dot,
+                "  \"{}\" [label=\"{label}\", fillcolor=\"{}\"];",
+                escape(&node.id),
+                node.status.dot_color()
+            );
+        }
+
+        for edge in &self.edges {
+            let style = match edge.kind {
+                EdgeKind::Next => "",
+                EdgeKind::Compensation => " [style=dashed, color=red, label=\"compensate\"]",
+            };
+            let _ = writeln!(
+                dot,
+                "  \"{}\" -> \"{}\"{style};",
+                escape(&edge.from),
+                escape(&edge.to)
+            );
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+impl GraphNode {
+    fn new(id: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            status: StepStatus::Pending,
+            started_at: None,
+            finished_at: None,
+            duration_ms: None,
+            attempts: 0,
+            service_latency_ms: None,
+            error: None,
+        }
+    }
+
+    fn finish(&mut self, status: StepStatus, at: DateTime<Utc>) {
+        self.status = status;
+        self.finished_at = Some(at);
+        self.duration_ms = self
+            .started_at
+            .map(|started| (at - started).num_milliseconds());
+    }
+}
+
+impl GraphEdge {
+    fn new(from: &str, to: &str, kind: EdgeKind) -> Self {
+        Self {
+            from: from.to_string(),
+            to: to.to_string(),
+            kind,
+        }
+    }
+}
+
+/// Returns the node for `step`, adding one for steps outside the definition.
+fn node_mut<'a>(nodes: &'a mut Vec<GraphNode>, step: &str) -> &'a mut GraphNode {
+    let index = match nodes.iter().position(|n| n.id == step) {
+        Some(index) => index,
+        None => {
+            nodes.push(GraphNode::new(step));
+            nodes.len() - 1
+        }
+    };
+    &mut nodes[index]
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::order_fulfillment::{
+        SAGA_TYPE, STEP_CREATE_SHIPMENT, STEP_PROCESS_PAYMENT, STEP_RESERVE_INVENTORY, STEPS,
+    };
+    use chrono::Duration;
+    use common::AggregateId;
+
+    fn timed(events: Vec<SagaEvent>) -> Vec<(DateTime<Utc>, SagaEvent)> {
+        let start = Utc::now();
+        events
+            .into_iter()
+            .enumerate()
+            .map(|(i, e)| (start + Duration::milliseconds(10 * i as i64), e))
+            .collect()
+    }
+
+    fn build(events: Vec<SagaEvent>) -> SagaGraph {
+        let mut saga = SagaInstance::default();
+        saga.apply_events(events.clone());
+        SagaGraph::build(&saga, &STEPS, timed(events))
+    }
+
+    #[test]
+    fn test_failed_saga_shows_compensation_path() {
+        let graph = build(vec![
+            SagaEvent::saga_started(AggregateId::new(), AggregateId::new(), SAGA_TYPE),
+            SagaEvent::step_started(STEP_RESERVE_INVENTORY),
+            SagaEvent::step_completed(STEP_RESERVE_INVENTORY, Default::default(), None, None),
+            SagaEvent::step_started(STEP_PROCESS_PAYMENT),
+            SagaEvent::step_failed(STEP_PROCESS_PAYMENT, "card declined"),
+            SagaEvent::compensation_started(STEP_PROCESS_PAYMENT),
+            SagaEvent::compensation_step_completed(STEP_RESERVE_INVENTORY),
+            SagaEvent::saga_failed("Step failed: process_payment"),
+        ]);
+
+        assert_eq!(graph.state, SagaState::Failed);
+        let statuses: Vec<_> = graph.nodes.iter().map(|n| n.status).collect();
+        assert_eq!(
+            statuses,
+            [
+                StepStatus::Compensated,
+                StepStatus::Failed,
+                StepStatus::Pending
+            ]
+        );
+        assert_eq!(graph.nodes[0].duration_ms, Some(10));
+        assert_eq!(graph.nodes[1].attempts, 1);
+        assert_eq!(graph.nodes[2].attempts, 0);
+        assert_eq!(graph.nodes[1].error.as_deref(), Some("card declined"));
+
+        let compensation: Vec<_> = graph
+            .edges
+            .iter()
+            .filter(|e| e.kind == EdgeKind::Compensation)
+            .map(|e| (e.from.as_str(), e.to.as_str()))
+            .collect();
+        assert_eq!(
+            compensation,
+            [(STEP_PROCESS_PAYMENT, STEP_RESERVE_INVENTORY)]
+        );
+        assert!(graph.finished_at.is_some());
+    }
+
+    #[test]
+    fn test_dot_output_lists_steps_and_edges() {
+        let graph = build(vec![
+            SagaEvent::saga_started(AggregateId::new(), AggregateId::new(), SAGA_TYPE),
+            SagaEvent::step_started(STEP_RESERVE_INVENTORY),
+        ]);
+
+        let dot = graph.to_dot();
+        assert!(dot.starts_with("digraph "));
+        assert!(dot.contains("\"reserve_inventory\" [label=\"reserve_inventory\\nrunning\""));
+        assert!(dot.contains(&format!(
+            "\"{STEP_PROCESS_PAYMENT}\" -> \"{STEP_CREATE_SHIPMENT}\";"
+        )));
+        assert!(dot.trim_end().ends_with('}'));
+    }
+}
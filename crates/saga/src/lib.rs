@@ -9,22 +9,50 @@
 //! 3. Create shipment
 //!
 //! If any step fails, previously completed steps are compensated in reverse order.
+//! Sagas run either within one call or, with a [`SagaRunner`], in the
+//! background, resuming after a restart from the last recorded step.
 
 pub mod aggregate;
+pub mod causation;
 pub mod coordinator;
+pub mod definition;
+pub mod details;
+pub mod dry_run;
 pub mod error;
 pub mod events;
+pub mod failure;
+pub mod graph;
 pub mod order_fulfillment;
+pub mod reconcile;
+pub mod runner;
+pub mod schedule;
 pub mod services;
 pub mod state;
+pub mod trace;
 
-pub use aggregate::SagaInstance;
-pub use coordinator::SagaCoordinator;
+pub use aggregate::{SagaInstance, StepAttempt};
+pub use causation::SagaCausation;
+pub use coordinator::{SagaCoordinator, SagaStarts};
+pub use definition::{SagaDefinition, SagaDefinitions};
+pub use details::{OrderDetails, OrderDetailsQuery, PaymentDetails, ShipmentDetails};
+pub use dry_run::{DryRunReport, DryRunStep, StepCheck};
 pub use error::SagaError;
-pub use events::SagaEvent;
+pub use events::{SagaEvent, StepTiming};
+pub use failure::{FailureKind, ServiceFailure};
+pub use graph::{EdgeKind, GraphEdge, GraphNode, SagaGraph, StepStatus};
+pub use reconcile::{
+    Correction, ReconciliationOutcome, ReconciliationReport, ReservationCheck, ReservationReport,
+};
+pub use runner::SagaRunner;
+pub use schedule::{
+    BusinessHours, InvalidBusinessHours, RunImmediately, StepSchedule, StepSchedulingPolicy,
+};
 pub use services::{
-    InMemoryInventoryService, InMemoryPaymentService, InMemoryShippingService, InventoryService,
-    PaymentResult, PaymentService, ReservationItem, ReservationResult, ShipmentResult,
-    ShippingService,
+    CustomerNotification, CustomerNotifier, EventSourcedInventoryService,
+    EventSourcedPaymentService, InMemoryCustomerNotifier, InMemoryInventoryService,
+    InMemoryPaymentService, InMemoryShippingService, InventoryError, InventoryService,
+    NotificationError, PaymentError, PaymentResult, PaymentService, ReservationItem,
+    ReservationResult, ReservationStatus, ShipmentResult, ShippingError, ShippingService,
 };
 pub use state::SagaState;
+pub use trace::{SagaTrace, StepSpan, TraceContext};
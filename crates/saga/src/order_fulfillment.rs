@@ -11,3 +11,10 @@ pub const STEP_PROCESS_PAYMENT: &str = "process_payment";
 
 /// Step name: Create shipment for the order.
 pub const STEP_CREATE_SHIPMENT: &str = "create_shipment";
+
+/// Steps in execution order.
+pub const STEPS: [&str; 3] = [
+    STEP_RESERVE_INVENTORY,
+    STEP_PROCESS_PAYMENT,
+    STEP_CREATE_SHIPMENT,
+];
@@ -0,0 +1,130 @@
+//! Reconciliation of sagas with the orders they drive.
+//!
+//! The coordinator appends a saga event and then moves the order, as two
+//! separate appends. A crash or failed append between them leaves the saga
+//! and order disagreeing. Reconciliation compares the two after a restart
+//! and either appends corrective events or flags the pair for manual review.
+//!
+//! Reserved orders are also reconciled with the inventory service, whose
+//! reservations can lapse while the order waits, for example when the
+//! warehouse's reservation TTL runs out before payment. Lapsed lines are
+//! reserved again, or the order is cancelled if the stock is gone.
+
+use common::AggregateId;
+use domain::{OrderState, ProductId};
+
+/// A corrective action taken to converge a saga and its order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Correction {
+    /// The order was moved forward to match the saga's completed steps.
+    ///
+    /// Serial and lot numbers reported by the warehouse are not kept on the
+    /// saga, so an order completed this way has no shipped items recorded.
+    OrderAdvanced { to: OrderState },
+
+    /// The saga was marked completed after all of its steps had completed.
+    SagaCompleted,
+
+    /// A saga interrupted mid-step was failed and its completed steps
+    /// compensated.
+    SagaCompensated { interrupted_step: String },
+
+    /// The order was cancelled to match its failed saga.
+    OrderCancelled,
+}
+
+/// How a saga and its order compared during reconciliation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReconciliationOutcome {
+    /// The saga and order already agreed.
+    InSync,
+
+    /// Corrective events were appended, in the order listed.
+    Repaired(Vec<Correction>),
+
+    /// The pair can't be converged automatically.
+    NeedsReview { reason: String },
+}
+
+/// The result of reconciling one saga with its order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReconciliationReport {
+    pub saga_id: AggregateId,
+    pub order_id: AggregateId,
+    pub outcome: ReconciliationOutcome,
+}
+
+impl ReconciliationOutcome {
+    /// Returns the outcome name used in logs and metrics.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReconciliationOutcome::InSync => "in_sync",
+            ReconciliationOutcome::Repaired(_) => "repaired",
+            ReconciliationOutcome::NeedsReview { .. } => "needs_review",
+        }
+    }
+}
+
+/// How a reserved order's reservations compared with the inventory
+/// service's.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReservationCheck {
+    /// Every line item's reservation is still held.
+    Held,
+
+    /// Lapsed reservations were replaced for these line items.
+    Renewed { products: Vec<ProductId> },
+
+    /// A lapsed line item couldn't be reserved again, so the order was
+    /// cancelled and its other reservations released.
+    Cancelled { product_id: ProductId },
+}
+
+impl ReservationCheck {
+    /// Returns the outcome name used in logs and metrics.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReservationCheck::Held => "held",
+            ReservationCheck::Renewed { .. } => "renewed",
+            ReservationCheck::Cancelled { .. } => "cancelled",
+        }
+    }
+}
+
+/// The result of checking one reserved order's reservations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReservationReport {
+    pub order_id: AggregateId,
+    pub check: ReservationCheck,
+}
+
+/// Returns how many fulfillment steps an order in `state` reflects, or
+/// None for a cancelled order.
+pub(crate) fn order_progress(state: OrderState) -> Option<usize> {
+    match state {
+        OrderState::Draft => Some(0),
+        OrderState::Reserved => Some(1),
+        OrderState::Processing => Some(2),
+        OrderState::Completed
+        | OrderState::Shipped
+        | OrderState::InTransit
+        | OrderState::Delivered
+        | OrderState::DeliveryFailed => Some(3),
+        OrderState::Cancelled => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_order_progress_follows_fulfillment_steps() {
+        assert_eq!(order_progress(OrderState::Draft), Some(0));
+        assert_eq!(order_progress(OrderState::Reserved), Some(1));
+        assert_eq!(order_progress(OrderState::Processing), Some(2));
+        assert_eq!(order_progress(OrderState::Completed), Some(3));
+        assert_eq!(order_progress(OrderState::Delivered), Some(3));
+        assert_eq!(order_progress(OrderState::Cancelled), None);
+    }
+}
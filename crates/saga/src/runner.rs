@@ -0,0 +1,399 @@
+//! Background execution of sagas.
+//!
+//! With a [`SagaRunner`], a saga is started with
+//! [`SagaCoordinator::start_saga`], which only records `SagaStarted`, and
+//! the runner drives its steps in the background. Every step is recorded
+//! in the saga's events, so a saga the process stopped running is picked up
+//! again by the next runner from where it left off.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use common::AggregateId;
+use futures_util::StreamExt;
+use futures_util::stream::{self, FuturesUnordered};
+use tokio::sync::Notify;
+use tokio_util::sync::CancellationToken;
+
+use event_store::EventStore;
+
+use crate::coordinator::{SagaCoordinator, SagaStarts};
+use crate::error::SagaError;
+use crate::services::inventory::InventoryService;
+use crate::services::payment::PaymentService;
+use crate::services::shipping::ShippingService;
+use crate::state::SagaState;
+
+/// How often the runner looks for running sagas when it isn't woken.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Drives running sagas to completion in the background.
+///
+/// On startup the runner scans the store once for the sagas still running,
+/// so sagas left running by a previous process are finished, and from then
+/// on follows the sagas that start. It keeps the sagas that haven't
+/// finished in memory, and each pass resumes those it isn't already
+/// driving, so a pass costs only the unfinished sagas rather than every
+/// saga ever started. Passes run when a saga starts or the runner is
+/// [woken](Self::wake), and every poll interval otherwise, which retries
+/// sagas that were deferred or failed to run. Only one runner should drive
+/// a store's sagas; a second one would race it and fail on concurrency
+/// conflicts.
+#[derive(Clone)]
+pub struct SagaRunner {
+    wake: Arc<Notify>,
+    poll_interval: Duration,
+}
+
+impl SagaRunner {
+    /// Creates a runner polling every [`DEFAULT_POLL_INTERVAL`].
+    pub fn new() -> Self {
+        Self {
+            wake: Arc::new(Notify::new()),
+            poll_interval: DEFAULT_POLL_INTERVAL,
+        }
+    }
+
+    /// Sets how often the runner looks for running sagas unprompted.
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Asks the runner to look for running sagas now, e.g. after starting
+    /// one. Clones share the wake-up.
+    pub fn wake(&self) {
+        self.wake.notify_one();
+    }
+
+    /// Resumes running sagas until `shutdown` is cancelled.
+    ///
+    /// On shutdown, each saga in progress stops at its next step boundary
+    /// and stays running for the next runner to resume. A failed pass or
+    /// saga is logged and retried on the next pass.
+    #[tracing::instrument(skip_all)]
+    pub async fn run<S, I, P, Sh>(
+        &self,
+        coordinator: &SagaCoordinator<S, I, P, Sh>,
+        shutdown: CancellationToken,
+    ) where
+        S: EventStore + Clone,
+        I: InventoryService,
+        P: PaymentService,
+        Sh: ShippingService,
+    {
+        let mut unfinished = HashSet::new();
+        let mut driving = HashSet::new();
+        let mut in_progress = FuturesUnordered::new();
+        let mut starts: SagaStarts = Box::pin(stream::pending());
+        let mut following = false;
+        let mut scan = true;
+        tracing::info!("saga runner started");
+
+        loop {
+            if scan {
+                // Scanned once; retried each pass until it succeeds
+                if !following {
+                    match coordinator.follow_running_sagas().await {
+                        Ok((sagas, started)) => {
+                            unfinished.extend(sagas);
+                            starts = started;
+                            following = true;
+                        }
+                        Err(e) => tracing::warn!(error = %e, "failed to list running sagas"),
+                    }
+                }
+                for &saga_id in &unfinished {
+                    if driving.insert(saga_id) {
+                        let cancel = shutdown.clone();
+                        in_progress.push(async move {
+                            (saga_id, coordinator.resume_saga(saga_id, cancel).await)
+                        });
+                    }
+                }
+                metrics::gauge!("saga_runner_in_progress").set(driving.len() as f64);
+                scan = false;
+            }
+
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = self.wake.notified() => scan = true,
+                _ = tokio::time::sleep(self.poll_interval) => scan = true,
+                Some(started) = starts.next() => match started {
+                    Ok(saga_id) => scan |= unfinished.insert(saga_id),
+                    Err(e) => tracing::warn!(error = %e, "failed to follow started sagas"),
+                },
+                Some((saga_id, result)) = in_progress.next(), if !in_progress.is_empty() => {
+                    finished(&mut unfinished, &mut driving, saga_id, result);
+                }
+            }
+        }
+
+        // Let each saga reach its next step boundary
+        while let Some((saga_id, result)) = in_progress.next().await {
+            finished(&mut unfinished, &mut driving, saga_id, result);
+        }
+        tracing::info!("saga runner stopped");
+    }
+}
+
+impl Default for SagaRunner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Records the end of a saga's run, forgetting the saga unless it is still
+/// running or its run failed.
+fn finished(
+    unfinished: &mut HashSet<AggregateId>,
+    driving: &mut HashSet<AggregateId>,
+    saga_id: AggregateId,
+    result: Result<Option<SagaState>, SagaError>,
+) {
+    driving.remove(&saga_id);
+    metrics::gauge!("saga_runner_in_progress").set(driving.len() as f64);
+    match result {
+        Ok(state) => {
+            if state != Some(SagaState::Running) {
+                unfinished.remove(&saga_id);
+            }
+            tracing::debug!(%saga_id, ?state, "saga run finished");
+        }
+        Err(e) => tracing::warn!(%saga_id, error = %e, "saga run failed, will retry"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aggregate::SagaInstance;
+    use crate::events::SagaEvent;
+    use crate::order_fulfillment;
+    use crate::services::inventory::InMemoryInventoryService;
+    use crate::services::payment::InMemoryPaymentService;
+    use crate::services::shipping::InMemoryShippingService;
+    use crate::trace::TraceContext;
+    use domain::DomainEvent;
+    use domain::{
+        AddItem, Aggregate, CreateOrder, CustomerId, Money, OrderItem, OrderService, OrderState,
+    };
+    use event_store::{
+        AppendOptions, EventEnvelope, EventStoreExt, FaultyEventStore, InMemoryEventStore,
+        StoreOperation,
+    };
+
+    type Coordinator = SagaCoordinator<
+        InMemoryEventStore,
+        InMemoryInventoryService,
+        InMemoryPaymentService,
+        InMemoryShippingService,
+    >;
+
+    fn coordinator(store: &InMemoryEventStore) -> Coordinator {
+        SagaCoordinator::new(
+            store.clone(),
+            InMemoryInventoryService::new(),
+            InMemoryPaymentService::new(),
+            InMemoryShippingService::new(),
+        )
+        .with_resumable_sagas()
+    }
+
+    async fn order(store: &InMemoryEventStore) -> AggregateId {
+        let service = OrderService::new(store.clone());
+        let cmd = CreateOrder::for_customer(CustomerId::new());
+        let order_id = cmd.order_id;
+        service.create_order(cmd).await.unwrap();
+        service
+            .add_item(AddItem::new(
+                order_id,
+                OrderItem::new("SKU-001", "Widget", 1, Money::from_cents(1000)),
+            ))
+            .await
+            .unwrap();
+        order_id
+    }
+
+    async fn order_state(store: &InMemoryEventStore, order_id: AggregateId) -> OrderState {
+        OrderService::new(store.clone())
+            .get_order(order_id)
+            .await
+            .unwrap()
+            .unwrap()
+            .state()
+    }
+
+    #[tokio::test]
+    async fn test_runner_drives_started_sagas() {
+        let store = InMemoryEventStore::new();
+        let coordinator = coordinator(&store);
+        let order_id = order(&store).await;
+
+        let saga_id = coordinator
+            .start_saga(order_id, TraceContext::new_root())
+            .await
+            .unwrap();
+        let saga = coordinator.get_saga(saga_id).await.unwrap().unwrap();
+        assert_eq!(saga.state(), SagaState::Running);
+        assert!(saga.completed_steps().is_empty());
+
+        let runner = SagaRunner::new().with_poll_interval(Duration::from_secs(3600));
+        let shutdown = CancellationToken::new();
+        let run = runner.run(&coordinator, shutdown.clone());
+        let check = async {
+            for _ in 0..200 {
+                let saga = coordinator.get_saga(saga_id).await.unwrap().unwrap();
+                if saga.state() == SagaState::Completed {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+            shutdown.cancel();
+        };
+        tokio::join!(run, check);
+
+        let saga = coordinator.get_saga(saga_id).await.unwrap().unwrap();
+        assert_eq!(saga.state(), SagaState::Completed);
+        assert_eq!(order_state(&store, order_id).await, OrderState::Completed);
+    }
+
+    #[tokio::test]
+    async fn test_runner_does_not_reload_finished_sagas() {
+        let store = FaultyEventStore::new(InMemoryEventStore::new());
+        let coordinator = SagaCoordinator::new(
+            store.clone(),
+            InMemoryInventoryService::new(),
+            InMemoryPaymentService::new(),
+            InMemoryShippingService::new(),
+        )
+        .with_resumable_sagas();
+        let orders = OrderService::new(store.clone());
+        let order =
+            |quantity| {
+                let orders = &orders;
+                async move {
+                    let cmd = CreateOrder::for_customer(CustomerId::new()).with_items(vec![
+                        OrderItem::new("SKU-001", "Widget", quantity, Money::from_cents(1000)),
+                    ]);
+                    let order_id = cmd.order_id;
+                    orders.create_order(cmd).await.unwrap();
+                    order_id
+                }
+            };
+        let finished = coordinator
+            .start_saga(order(1).await, TraceContext::new_root())
+            .await
+            .unwrap();
+
+        let runner = SagaRunner::new().with_poll_interval(Duration::from_millis(5));
+        let shutdown = CancellationToken::new();
+        let run = runner.run(&coordinator, shutdown.clone());
+        let check = async {
+            let completed = |saga_id| {
+                let coordinator = &coordinator;
+                async move {
+                    for _ in 0..200 {
+                        let saga = coordinator.get_saga(saga_id).await.unwrap().unwrap();
+                        if saga.state() == SagaState::Completed {
+                            return true;
+                        }
+                        tokio::time::sleep(Duration::from_millis(5)).await;
+                    }
+                    false
+                }
+            };
+            assert!(completed(finished).await);
+
+            // Later passes touch no saga; a rescan or reload would fail
+            store.set_error_rate(StoreOperation::Query, 1.0);
+            store.set_error_rate(StoreOperation::Load, 1.0);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            assert_eq!(store.injected(StoreOperation::Query), 0);
+            assert_eq!(store.injected(StoreOperation::Load), 0);
+            store.heal();
+
+            // A saga started afterwards is still picked up, without a wake
+            let started = coordinator
+                .start_saga(order(2).await, TraceContext::new_root())
+                .await
+                .unwrap();
+            assert!(completed(started).await);
+            shutdown.cancel();
+        };
+        tokio::join!(run, check);
+    }
+
+    #[tokio::test]
+    async fn test_resume_continues_after_restart_between_steps() {
+        let store = InMemoryEventStore::new();
+        let order_id = order(&store).await;
+
+        // The first process paused the saga after its first step, before
+        // the order moved
+        let first = coordinator(&store);
+        let saga_id = first
+            .start_saga(order_id, TraceContext::new_root())
+            .await
+            .unwrap();
+        let version = store.get_aggregate_version(saga_id).await.unwrap().unwrap();
+        let completed = SagaEvent::step_completed(
+            order_fulfillment::STEP_RESERVE_INVENTORY,
+            Default::default(),
+            None,
+            None,
+        );
+        store
+            .append_event(
+                EventEnvelope::builder()
+                    .aggregate_id(saga_id)
+                    .aggregate_type(SagaInstance::aggregate_type())
+                    .event_type(completed.event_type())
+                    .version(version.next())
+                    .payload(&completed)
+                    .unwrap()
+                    .build(),
+                AppendOptions::expect_version(version),
+            )
+            .await
+            .unwrap();
+        assert_eq!(order_state(&store, order_id).await, OrderState::Draft);
+
+        // Reconciling leaves it for the runner
+        let restarted = coordinator(&store);
+        assert_eq!(restarted.running_sagas().await.unwrap(), vec![saga_id]);
+        let report = restarted.reconcile(saga_id).await.unwrap().unwrap();
+        assert_eq!(report.outcome, crate::ReconciliationOutcome::InSync);
+
+        let state = restarted
+            .resume_saga(saga_id, CancellationToken::new())
+            .await
+            .unwrap();
+        assert_eq!(state, Some(SagaState::Completed));
+        assert_eq!(order_state(&store, order_id).await, OrderState::Completed);
+        let saga = restarted.get_saga(saga_id).await.unwrap().unwrap();
+        assert_eq!(saga.attempts(order_fulfillment::STEP_RESERVE_INVENTORY), 0);
+        assert!(restarted.running_sagas().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_pauses_instead_of_compensating() {
+        let store = InMemoryEventStore::new();
+        let coordinator = coordinator(&store);
+        let order_id = order(&store).await;
+        let saga_id = coordinator
+            .start_saga(order_id, TraceContext::new_root())
+            .await
+            .unwrap();
+
+        let cancelled = CancellationToken::new();
+        cancelled.cancel();
+        let state = coordinator.resume_saga(saga_id, cancelled).await.unwrap();
+        assert_eq!(state, Some(SagaState::Running));
+        assert_eq!(order_state(&store, order_id).await, OrderState::Draft);
+        let saga = coordinator.get_saga(saga_id).await.unwrap().unwrap();
+        assert!(saga.step_attempts().is_empty());
+        assert!(saga.failure_reason().is_none());
+    }
+}
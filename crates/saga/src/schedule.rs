@@ -0,0 +1,205 @@
+//! When saga steps may run.
+//!
+//! A [`StepSchedulingPolicy`] is asked before each step starts. A step it
+//! defers is recorded with a `StepDeferred` event and the saga stops there,
+//! still running; the [`SagaRunner`](crate::SagaRunner) resumes it on a
+//! pass after the deferral is up.
+
+use std::str::FromStr;
+
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveTime, TimeZone, Utc, Weekday};
+use thiserror::Error;
+
+/// What a scheduling policy decided for a step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepSchedule {
+    /// Run the step now.
+    RunImmediately,
+    /// Put the step off until the given time.
+    Defer(DateTime<Utc>),
+}
+
+/// Decides when a saga step may run.
+///
+/// Implemented for closures, so a one-off policy can be passed as
+/// `|step, now| ...`.
+pub trait StepSchedulingPolicy: Send + Sync {
+    /// Decides whether `step` runs at `now` or later.
+    fn schedule(&self, step: &str, now: DateTime<Utc>) -> StepSchedule;
+}
+
+impl<F> StepSchedulingPolicy for F
+where
+    F: Fn(&str, DateTime<Utc>) -> StepSchedule + Send + Sync,
+{
+    fn schedule(&self, step: &str, now: DateTime<Utc>) -> StepSchedule {
+        self(step, now)
+    }
+}
+
+/// Runs every step as soon as the saga reaches it, the default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RunImmediately;
+
+impl StepSchedulingPolicy for RunImmediately {
+    fn schedule(&self, _step: &str, _now: DateTime<Utc>) -> StepSchedule {
+        StepSchedule::RunImmediately
+    }
+}
+
+/// Runs steps only during opening hours, deferring them to the next
+/// opening otherwise.
+///
+/// Hours are in a fixed UTC offset and open and close on the same day.
+#[derive(Debug, Clone)]
+pub struct BusinessHours {
+    offset: FixedOffset,
+    opens: NaiveTime,
+    closes: NaiveTime,
+    days: Vec<Weekday>,
+}
+
+impl BusinessHours {
+    /// Creates hours from `opens` to `closes` at `offset`, Monday to
+    /// Friday.
+    pub fn new(offset: FixedOffset, opens: NaiveTime, closes: NaiveTime) -> Self {
+        Self {
+            offset,
+            opens,
+            closes,
+            days: vec![
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Thu,
+                Weekday::Fri,
+            ],
+        }
+    }
+
+    /// Opens on `days` instead of Monday to Friday.
+    pub fn with_days(mut self, days: impl IntoIterator<Item = Weekday>) -> Self {
+        self.days = days.into_iter().collect();
+        self
+    }
+
+    /// Returns true if `now` falls within opening hours.
+    pub fn is_open(&self, now: DateTime<Utc>) -> bool {
+        let local = now.with_timezone(&self.offset);
+        self.days.contains(&local.weekday())
+            && local.time() >= self.opens
+            && local.time() < self.closes
+    }
+
+    /// Returns `now` if open, otherwise the next time the hours open, or
+    /// None if they never do.
+    pub fn next_opening(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        if self.is_open(now) {
+            return Some(now);
+        }
+        let today = now.with_timezone(&self.offset).date_naive();
+        (0..=7)
+            .map(|days| today + Duration::days(days))
+            .filter(|date| self.days.contains(&date.weekday()))
+            .filter_map(|date| {
+                self.offset
+                    .from_local_datetime(&date.and_time(self.opens))
+                    .single()
+            })
+            .map(|opening| opening.with_timezone(&Utc))
+            .find(|opening| *opening > now)
+    }
+}
+
+/// A string that isn't a range of business hours.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("invalid business hours {0:?}, expected HH:MM-HH:MM")]
+pub struct InvalidBusinessHours(String);
+
+/// Parses `HH:MM-HH:MM` as UTC hours, Monday to Friday.
+impl FromStr for BusinessHours {
+    type Err = InvalidBusinessHours;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidBusinessHours(s.to_string());
+        let (opens, closes) = s.trim().split_once('-').ok_or_else(invalid)?;
+        let time = |t: &str| NaiveTime::parse_from_str(t.trim(), "%H:%M").map_err(|_| invalid());
+        let (opens, closes) = (time(opens)?, time(closes)?);
+        if opens >= closes {
+            return Err(invalid());
+        }
+        Ok(Self::new(FixedOffset::east_opt(0).unwrap(), opens, closes))
+    }
+}
+
+impl StepSchedulingPolicy for BusinessHours {
+    fn schedule(&self, _step: &str, now: DateTime<Utc>) -> StepSchedule {
+        // Hours that never open don't hold steps forever
+        match self.next_opening(now) {
+            Some(opening) if opening > now => StepSchedule::Defer(opening),
+            _ => StepSchedule::RunImmediately,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    fn nine_to_five() -> BusinessHours {
+        BusinessHours::new(
+            FixedOffset::east_opt(2 * 3600).unwrap(),
+            NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+            NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_business_hours_defer_to_the_next_opening() {
+        let hours = nine_to_five();
+
+        // Wednesday 10:00 local is open
+        let open = at("2026-10-14T08:00:00Z");
+        assert!(hours.is_open(open));
+        assert_eq!(hours.schedule("ship", open), StepSchedule::RunImmediately);
+
+        // Wednesday 18:00 local waits for Thursday 09:00
+        assert_eq!(
+            hours.schedule("ship", at("2026-10-14T16:00:00Z")),
+            StepSchedule::Defer(at("2026-10-15T07:00:00Z"))
+        );
+        // Early Wednesday waits for the same morning
+        assert_eq!(
+            hours.next_opening(at("2026-10-14T05:00:00Z")),
+            Some(at("2026-10-14T07:00:00Z"))
+        );
+        // Friday evening waits over the weekend
+        assert_eq!(
+            hours.next_opening(at("2026-10-16T20:00:00Z")),
+            Some(at("2026-10-19T07:00:00Z"))
+        );
+        // Closing time itself is closed
+        assert!(!hours.is_open(at("2026-10-14T15:00:00Z")));
+    }
+
+    #[test]
+    fn test_parses_hours() {
+        let hours: BusinessHours = "09:00-17:30".parse().unwrap();
+        assert!(hours.is_open(at("2026-10-14T17:29:00Z")));
+        assert!(!hours.is_open(at("2026-10-17T10:00:00Z")));
+        assert!("17:00-09:00".parse::<BusinessHours>().is_err());
+        assert!("9am-5pm".parse::<BusinessHours>().is_err());
+    }
+
+    #[test]
+    fn test_hours_without_open_days_never_open() {
+        let hours = nine_to_five().with_days([]);
+        let now = at("2026-10-14T08:00:00Z");
+        assert_eq!(hours.next_opening(now), None);
+        assert_eq!(hours.schedule("ship", now), StepSchedule::RunImmediately);
+    }
+}
@@ -1,19 +1,66 @@
-//! Inventory service trait and in-memory implementation.
+//! Inventory service trait with in-memory and event-sourced implementations.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::{Arc, RwLock};
+use std::time::Duration;
 
 use async_trait::async_trait;
 use common::AggregateId;
-use domain::ProductId;
+use domain::{
+    DomainError, InventoryItem, InventoryItemService, ProductId, ReleaseStock, ReserveStock,
+};
+use event_store::EventStore;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
-use crate::error::SagaError;
+use crate::failure::FailureKind;
+
+/// Errors from the inventory service.
+#[derive(Debug, Clone, PartialEq, Eq, Error, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum InventoryError {
+    /// Not enough stock of a product to reserve the requested quantity.
+    #[error("Out of stock: {product_id}")]
+    OutOfStock { product_id: ProductId },
+
+    /// The service couldn't be reached or timed out; the call may succeed
+    /// if repeated.
+    #[error("Inventory service unavailable: {reason}")]
+    Unavailable { reason: String },
+}
+
+impl InventoryError {
+    /// Classifies the error for customers.
+    pub fn failure_kind(&self) -> FailureKind {
+        match self {
+            InventoryError::OutOfStock { .. } => FailureKind::InsufficientStock,
+            InventoryError::Unavailable { .. } => FailureKind::Unknown,
+        }
+    }
+
+    /// Returns true if repeating the call may succeed.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, InventoryError::Unavailable { .. })
+    }
+}
 
 /// Result of a successful inventory reservation.
 #[derive(Debug, Clone)]
 pub struct ReservationResult {
-    /// The reservation ID assigned by the inventory service.
-    pub reservation_id: String,
+    /// The reservation ID assigned to each line item, keyed by product.
+    pub reservations: BTreeMap<ProductId, String>,
+}
+
+/// Whether a reservation still holds its units.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReservationStatus {
+    /// The units are still held.
+    Held,
+    /// The service let the reservation lapse, e.g. when its TTL ran out.
+    Expired,
+    /// The reservation was released, or was never made.
+    Released,
 }
 
 /// An item to reserve in inventory.
@@ -30,22 +77,38 @@ pub struct ReservationItem {
 /// Trait for inventory management operations.
 #[async_trait]
 pub trait InventoryService: Send + Sync {
-    /// Reserves inventory for the given order items.
+    /// Reserves inventory for the given order items, one reservation per
+    /// line item.
+    ///
+    /// Fails with [`InventoryError::OutOfStock`] when the quantities
+    /// aren't available, so the customer is told why.
     async fn reserve(
         &self,
         order_id: AggregateId,
         items: Vec<ReservationItem>,
-    ) -> Result<ReservationResult, SagaError>;
+    ) -> Result<ReservationResult, InventoryError>;
+
+    /// Checks whether `items` could be reserved right now, without
+    /// reserving anything. Returns the error `reserve` would fail with.
+    async fn can_reserve(&self, items: &[ReservationItem]) -> Result<(), InventoryError>;
+
+    /// Releases a previously made line-item reservation.
+    async fn release(&self, reservation_id: &str) -> Result<(), InventoryError>;
 
-    /// Releases a previously made reservation.
-    async fn release(&self, reservation_id: &str) -> Result<(), SagaError>;
+    /// Reports whether a line-item reservation still holds its units.
+    async fn get_reservation_status(
+        &self,
+        reservation_id: &str,
+    ) -> Result<ReservationStatus, InventoryError>;
 }
 
 #[derive(Debug, Default)]
 struct InMemoryInventoryState {
-    reservations: HashMap<String, (AggregateId, Vec<ReservationItem>)>,
+    reservations: HashMap<String, (AggregateId, ReservationItem)>,
+    expired: HashSet<String>,
     next_id: u32,
     fail_on_reserve: bool,
+    reserve_delay: Duration,
 }
 
 /// In-memory inventory service for testing.
@@ -65,7 +128,12 @@ impl InMemoryInventoryService {
         self.state.write().unwrap().fail_on_reserve = fail;
     }
 
-    /// Returns the number of active reservations.
+    /// Makes every reserve call take `delay`, like a slow warehouse.
+    pub fn set_reserve_delay(&self, delay: Duration) {
+        self.state.write().unwrap().reserve_delay = delay;
+    }
+
+    /// Returns the number of active line-item reservations.
     pub fn reservation_count(&self) -> usize {
         self.state.read().unwrap().reservations.len()
     }
@@ -78,6 +146,17 @@ impl InMemoryInventoryService {
             .reservations
             .contains_key(reservation_id)
     }
+
+    /// Lets a reservation lapse, as a warehouse does when its TTL runs
+    /// out. Returns false if no such reservation was held.
+    pub fn expire_reservation(&self, reservation_id: &str) -> bool {
+        let mut state = self.state.write().unwrap();
+        if state.reservations.remove(reservation_id).is_none() {
+            return false;
+        }
+        state.expired.insert(reservation_id.to_string());
+        true
+    }
 }
 
 #[async_trait]
@@ -86,51 +165,255 @@ impl InventoryService for InMemoryInventoryService {
         &self,
         order_id: AggregateId,
         items: Vec<ReservationItem>,
-    ) -> Result<ReservationResult, SagaError> {
+    ) -> Result<ReservationResult, InventoryError> {
+        let delay = self.state.read().unwrap().reserve_delay;
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
         let mut state = self.state.write().unwrap();
 
         if state.fail_on_reserve {
-            return Err(SagaError::InventoryService(
-                "Insufficient stock".to_string(),
-            ));
+            return Err(out_of_stock(&items));
         }
 
-        state.next_id += 1;
-        let reservation_id = format!("RES-{:04}", state.next_id);
-        state
-            .reservations
-            .insert(reservation_id.clone(), (order_id, items));
+        let mut reservations = BTreeMap::new();
+        for item in items {
+            state.next_id += 1;
+            let reservation_id = format!("RES-{:04}", state.next_id);
+            reservations.insert(item.product_id.clone(), reservation_id.clone());
+            state.reservations.insert(reservation_id, (order_id, item));
+        }
 
-        Ok(ReservationResult { reservation_id })
+        Ok(ReservationResult { reservations })
     }
 
-    async fn release(&self, reservation_id: &str) -> Result<(), SagaError> {
+    async fn can_reserve(&self, items: &[ReservationItem]) -> Result<(), InventoryError> {
+        if self.state.read().unwrap().fail_on_reserve {
+            return Err(out_of_stock(items));
+        }
+        Ok(())
+    }
+
+    async fn release(&self, reservation_id: &str) -> Result<(), InventoryError> {
         let mut state = self.state.write().unwrap();
         state.reservations.remove(reservation_id);
         Ok(())
     }
+
+    async fn get_reservation_status(
+        &self,
+        reservation_id: &str,
+    ) -> Result<ReservationStatus, InventoryError> {
+        let state = self.state.read().unwrap();
+        Ok(if state.reservations.contains_key(reservation_id) {
+            ReservationStatus::Held
+        } else if state.expired.contains(reservation_id) {
+            ReservationStatus::Expired
+        } else {
+            ReservationStatus::Released
+        })
+    }
+}
+
+/// Inventory service backed by `InventoryItem` streams in the event store.
+///
+/// Each line item is reserved on its product's stream, so stock is
+/// refused once the restocked and adjusted units are all held, and
+/// reservations survive restarts. If one line can't be reserved, those
+/// already held for the order are released before the error is returned.
+pub struct EventSourcedInventoryService<S: EventStore> {
+    items: Arc<InventoryItemService<S>>,
+}
+
+impl<S: EventStore> EventSourcedInventoryService<S> {
+    /// Creates a service reserving against `store`.
+    pub fn new(store: S) -> Self {
+        Self::with_items(InventoryItemService::new(store))
+    }
+
+    /// Creates a service reserving through an already configured item
+    /// service.
+    pub fn with_items(items: InventoryItemService<S>) -> Self {
+        Self {
+            items: Arc::new(items),
+        }
+    }
+
+    /// Returns the item service stock is reserved through.
+    pub fn items(&self) -> &InventoryItemService<S> {
+        &self.items
+    }
+
+    /// Returns the units of `product_id` that can still be reserved.
+    async fn available(&self, product_id: &ProductId) -> Result<i64, InventoryError> {
+        let item = self.items.get_item(product_id).await.map_err(unavailable)?;
+        Ok(item.map_or(0, |item| item.available()))
+    }
+}
+
+impl<S: EventStore> Clone for EventSourcedInventoryService<S> {
+    fn clone(&self) -> Self {
+        Self {
+            items: Arc::clone(&self.items),
+        }
+    }
+}
+
+#[async_trait]
+impl<S: EventStore + 'static> InventoryService for EventSourcedInventoryService<S> {
+    async fn reserve(
+        &self,
+        order_id: AggregateId,
+        items: Vec<ReservationItem>,
+    ) -> Result<ReservationResult, InventoryError> {
+        let mut reservations: BTreeMap<ProductId, String> = BTreeMap::new();
+        for item in items {
+            let cmd = ReserveStock::new(item.product_id.clone(), order_id, item.quantity);
+            let reservation_id = cmd.reservation_id.clone();
+            if let Err(e) = self.items.reserve(cmd).await {
+                for held in reservations.values() {
+                    if let Err(e) = self.release(held).await {
+                        tracing::warn!(reservation_id = %held, error = %e, "failed to release reservation");
+                    }
+                }
+                return Err(match e {
+                    DomainError::Inventory(_) => InventoryError::OutOfStock {
+                        product_id: item.product_id,
+                    },
+                    e => unavailable(e),
+                });
+            }
+            reservations.insert(item.product_id, reservation_id);
+        }
+
+        Ok(ReservationResult { reservations })
+    }
+
+    async fn can_reserve(&self, items: &[ReservationItem]) -> Result<(), InventoryError> {
+        let mut needed: BTreeMap<&ProductId, i64> = BTreeMap::new();
+        for item in items {
+            *needed.entry(&item.product_id).or_default() += item.quantity as i64;
+        }
+        for (product_id, quantity) in needed {
+            if self.available(product_id).await? < quantity {
+                return Err(InventoryError::OutOfStock {
+                    product_id: product_id.clone(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    async fn release(&self, reservation_id: &str) -> Result<(), InventoryError> {
+        // IDs that don't name an inventory stream were never held here
+        let Some(cmd) = ReleaseStock::new(reservation_id) else {
+            return Ok(());
+        };
+        self.items.release(cmd).await.map_err(unavailable)?;
+        Ok(())
+    }
+
+    /// Reservations on inventory streams don't expire, so they are either
+    /// held or released.
+    async fn get_reservation_status(
+        &self,
+        reservation_id: &str,
+    ) -> Result<ReservationStatus, InventoryError> {
+        let Some(stream) = InventoryItem::reservation_stream(reservation_id) else {
+            return Ok(ReservationStatus::Released);
+        };
+        let item = self
+            .items
+            .handler()
+            .load_existing(stream)
+            .await
+            .map_err(unavailable)?;
+        Ok(
+            if item.is_some_and(|item| item.reservation(reservation_id).is_some()) {
+                ReservationStatus::Held
+            } else {
+                ReservationStatus::Released
+            },
+        )
+    }
+}
+
+/// Reports a store failure as the service being unavailable.
+fn unavailable(e: DomainError) -> InventoryError {
+    InventoryError::Unavailable {
+        reason: e.to_string(),
+    }
+}
+
+/// Reports the first of `items` as out of stock.
+fn out_of_stock(items: &[ReservationItem]) -> InventoryError {
+    InventoryError::OutOfStock {
+        product_id: items
+            .first()
+            .map(|item| item.product_id.clone())
+            .unwrap_or_else(|| ProductId::new("")),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn item(sku: &str) -> ReservationItem {
+        ReservationItem {
+            product_id: ProductId::new(sku),
+            product_name: "Widget".to_string(),
+            quantity: 2,
+        }
+    }
+
     #[tokio::test]
     async fn test_reserve_and_release() {
         let service = InMemoryInventoryService::new();
         let order_id = AggregateId::new();
-        let items = vec![ReservationItem {
-            product_id: ProductId::new("SKU-001"),
-            product_name: "Widget".to_string(),
-            quantity: 2,
-        }];
 
-        let result = service.reserve(order_id, items).await.unwrap();
-        assert!(result.reservation_id.starts_with("RES-"));
+        let result = service
+            .reserve(order_id, vec![item("SKU-001"), item("SKU-002")])
+            .await
+            .unwrap();
+        assert_eq!(result.reservations.len(), 2);
+        assert_eq!(service.reservation_count(), 2);
+
+        let first = &result.reservations[&ProductId::new("SKU-001")];
+        let second = &result.reservations[&ProductId::new("SKU-002")];
+        assert!(first.starts_with("RES-"));
+
+        service.release(first).await.unwrap();
         assert_eq!(service.reservation_count(), 1);
-        assert!(service.has_reservation(&result.reservation_id));
+        assert!(!service.has_reservation(first));
+        assert!(service.has_reservation(second));
+    }
+
+    #[tokio::test]
+    async fn test_reservation_status() {
+        let service = InMemoryInventoryService::new();
+        let result = service
+            .reserve(AggregateId::new(), vec![item("SKU-001"), item("SKU-002")])
+            .await
+            .unwrap();
+        let first = &result.reservations[&ProductId::new("SKU-001")];
+        let second = &result.reservations[&ProductId::new("SKU-002")];
+        assert_eq!(
+            service.get_reservation_status(first).await.unwrap(),
+            ReservationStatus::Held
+        );
 
-        service.release(&result.reservation_id).await.unwrap();
+        assert!(service.expire_reservation(first));
+        assert!(!service.expire_reservation(first));
+        service.release(second).await.unwrap();
+        assert_eq!(
+            service.get_reservation_status(first).await.unwrap(),
+            ReservationStatus::Expired
+        );
+        assert_eq!(
+            service.get_reservation_status(second).await.unwrap(),
+            ReservationStatus::Released
+        );
         assert_eq!(service.reservation_count(), 0);
     }
 
@@ -140,26 +423,109 @@ mod tests {
         service.set_fail_on_reserve(true);
 
         let order_id = AggregateId::new();
-        let items = vec![ReservationItem {
-            product_id: ProductId::new("SKU-001"),
-            product_name: "Widget".to_string(),
-            quantity: 2,
-        }];
+        let result = service.reserve(order_id, vec![item("SKU-001")]).await;
+        assert_eq!(
+            result.unwrap_err(),
+            InventoryError::OutOfStock {
+                product_id: ProductId::new("SKU-001")
+            }
+        );
+        assert!(service.can_reserve(&[item("SKU-001")]).await.is_err());
+        assert_eq!(service.reservation_count(), 0);
 
-        let result = service.reserve(order_id, items).await;
-        assert!(result.is_err());
+        service.set_fail_on_reserve(false);
+        service.can_reserve(&[item("SKU-001")]).await.unwrap();
         assert_eq!(service.reservation_count(), 0);
     }
 
+    #[tokio::test]
+    async fn test_event_sourced_reservations_hold_stock() {
+        let store = event_store::InMemoryEventStore::new();
+        let service = EventSourcedInventoryService::new(store);
+        service
+            .items()
+            .restock(domain::RestockItem::new("SKU-001", 3, None))
+            .await
+            .unwrap();
+        service
+            .items()
+            .restock(domain::RestockItem::new("SKU-002", 1, None))
+            .await
+            .unwrap();
+        let order_id = AggregateId::new();
+
+        service.can_reserve(&[item("SKU-001")]).await.unwrap();
+        let held = service
+            .reserve(order_id, vec![item("SKU-001")])
+            .await
+            .unwrap();
+
+        // One unit of SKU-001 is left, and SKU-002 can't cover two
+        let result = service
+            .reserve(order_id, vec![item("SKU-001"), item("SKU-002")])
+            .await;
+        assert_eq!(
+            result.unwrap_err(),
+            InventoryError::OutOfStock {
+                product_id: ProductId::new("SKU-001")
+            }
+        );
+        let second = ReservationItem {
+            quantity: 1,
+            ..item("SKU-001")
+        };
+        let result = service
+            .reserve(order_id, vec![second, item("SKU-002")])
+            .await;
+        assert_eq!(
+            result.unwrap_err(),
+            InventoryError::OutOfStock {
+                product_id: ProductId::new("SKU-002")
+            }
+        );
+        // The partly reserved order was rolled back
+        assert_eq!(
+            service.available(&ProductId::new("SKU-001")).await.unwrap(),
+            1
+        );
+
+        let reservation = &held.reservations[&ProductId::new("SKU-001")];
+        assert_eq!(
+            service.get_reservation_status(reservation).await.unwrap(),
+            ReservationStatus::Held
+        );
+        service.release(reservation).await.unwrap();
+        assert_eq!(
+            service.get_reservation_status(reservation).await.unwrap(),
+            ReservationStatus::Released
+        );
+        service.release(reservation).await.unwrap();
+        service.release("RES-0001").await.unwrap();
+        assert_eq!(
+            service.available(&ProductId::new("SKU-001")).await.unwrap(),
+            3
+        );
+    }
+
     #[tokio::test]
     async fn test_sequential_reservation_ids() {
         let service = InMemoryInventoryService::new();
         let order_id = AggregateId::new();
 
-        let r1 = service.reserve(order_id, vec![]).await.unwrap();
-        let r2 = service.reserve(order_id, vec![]).await.unwrap();
+        let r1 = service
+            .reserve(order_id, vec![item("SKU-001"), item("SKU-002")])
+            .await
+            .unwrap();
+        let r2 = service
+            .reserve(order_id, vec![item("SKU-001")])
+            .await
+            .unwrap();
 
-        assert_eq!(r1.reservation_id, "RES-0001");
-        assert_eq!(r2.reservation_id, "RES-0002");
+        let ids: Vec<_> = r1
+            .reservations
+            .values()
+            .chain(r2.reservations.values())
+            .collect();
+        assert_eq!(ids, ["RES-0001", "RES-0002", "RES-0003"]);
     }
 }
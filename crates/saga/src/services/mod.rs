@@ -1,11 +1,18 @@
 //! External service traits and in-memory implementations for saga steps.
 
 pub mod inventory;
+pub mod notification;
 pub mod payment;
 pub mod shipping;
 
 pub use inventory::{
-    InMemoryInventoryService, InventoryService, ReservationItem, ReservationResult,
+    EventSourcedInventoryService, InMemoryInventoryService, InventoryError, InventoryService,
+    ReservationItem, ReservationResult, ReservationStatus,
 };
-pub use payment::{InMemoryPaymentService, PaymentResult, PaymentService};
-pub use shipping::{InMemoryShippingService, ShipmentResult, ShippingService};
+pub use notification::{
+    CustomerNotification, CustomerNotifier, InMemoryCustomerNotifier, NotificationError,
+};
+pub use payment::{
+    EventSourcedPaymentService, InMemoryPaymentService, PaymentError, PaymentResult, PaymentService,
+};
+pub use shipping::{InMemoryShippingService, ShipmentResult, ShippingError, ShippingService};
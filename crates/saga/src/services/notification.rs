@@ -0,0 +1,71 @@
+//! Customer notification hooks and in-memory implementation.
+
+use std::sync::{Arc, RwLock};
+
+use async_trait::async_trait;
+use common::AggregateId;
+use domain::{CustomerId, Money, ProductId};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Errors from a notification hook.
+#[derive(Debug, Clone, PartialEq, Eq, Error, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NotificationError {
+    /// The notification couldn't be delivered.
+    #[error("Notification undeliverable: {reason}")]
+    Undeliverable { reason: String },
+}
+
+/// A change to an order made during fulfillment that the customer should
+/// hear about.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CustomerNotification {
+    /// An item was replaced with a substitute product.
+    ItemSubstituted {
+        order_id: AggregateId,
+        customer_id: CustomerId,
+        original_product_id: ProductId,
+        substitute_product_id: ProductId,
+        substitute_product_name: String,
+        /// Change to the order total; negative when the customer pays less.
+        price_delta: Money,
+    },
+}
+
+/// Hook told about fulfillment changes to a customer's order, such as an
+/// email or push sender.
+///
+/// Notifications are sent after the change is recorded, so a failed
+/// notification is logged and doesn't undo the change.
+#[async_trait]
+pub trait CustomerNotifier: Send + Sync {
+    /// Sends a notification to the order's customer.
+    async fn notify(&self, notification: &CustomerNotification) -> Result<(), NotificationError>;
+}
+
+/// In-memory notifier for testing; records every notification sent.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryCustomerNotifier {
+    sent: Arc<RwLock<Vec<CustomerNotification>>>,
+}
+
+impl InMemoryCustomerNotifier {
+    /// Creates a new in-memory notifier.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the notifications sent so far, oldest first.
+    pub fn sent(&self) -> Vec<CustomerNotification> {
+        self.sent.read().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl CustomerNotifier for InMemoryCustomerNotifier {
+    async fn notify(&self, notification: &CustomerNotification) -> Result<(), NotificationError> {
+        self.sent.write().unwrap().push(notification.clone());
+        Ok(())
+    }
+}
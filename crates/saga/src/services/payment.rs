@@ -1,13 +1,49 @@
-//! Payment service trait and in-memory implementation.
+//! Payment service trait with in-memory and event-sourced implementations.
 
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
 
 use async_trait::async_trait;
 use common::AggregateId;
-use domain::{CustomerId, Money};
+use domain::{
+    AuthorizePayment, CapturePayment, CustomerId, DeclinePayment, DomainError, Money, Payment,
+    PaymentLedger, PaymentStatus, RefundPayment, RequestPayment, RequestRefund,
+};
+use event_store::EventStore;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
-use crate::error::SagaError;
+use crate::failure::FailureKind;
+
+/// Errors from the payment service.
+#[derive(Debug, Clone, PartialEq, Eq, Error, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PaymentError {
+    /// The provider refused the charge, with its decline code such as
+    /// `insufficient_funds`. Repeating the charge won't change the answer.
+    #[error("Payment declined: {code}")]
+    Declined { code: String },
+
+    /// The provider couldn't be reached or timed out; the call may succeed
+    /// if repeated.
+    #[error("Payment service unavailable: {reason}")]
+    Unavailable { reason: String },
+}
+
+impl PaymentError {
+    /// Classifies the error for customers.
+    pub fn failure_kind(&self) -> FailureKind {
+        match self {
+            PaymentError::Declined { .. } => FailureKind::PaymentDeclined,
+            PaymentError::Unavailable { .. } => FailureKind::Unknown,
+        }
+    }
+
+    /// Returns true if repeating the call may succeed.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, PaymentError::Unavailable { .. })
+    }
+}
 
 /// Result of a successful payment charge.
 #[derive(Debug, Clone)]
@@ -19,23 +55,54 @@ pub struct PaymentResult {
 /// Trait for payment processing operations.
 #[async_trait]
 pub trait PaymentService: Send + Sync {
-    /// Charges a customer for an order.
+    /// Charges a customer for an order. `amount` is in the order's
+    /// currency, and the charge is made in that currency.
+    ///
+    /// Fails with [`PaymentError::Declined`] when the provider refuses the
+    /// charge, so the customer is told why.
     async fn charge(
         &self,
         order_id: AggregateId,
         customer_id: CustomerId,
         amount: Money,
-    ) -> Result<PaymentResult, SagaError>;
+    ) -> Result<PaymentResult, PaymentError>;
+
+    /// Checks whether a charge of `amount` to the customer would go
+    /// through right now, without charging. Returns the error `charge`
+    /// would fail with.
+    async fn can_charge(&self, customer_id: CustomerId, amount: Money) -> Result<(), PaymentError>;
 
     /// Refunds a previously made payment.
-    async fn refund(&self, payment_id: &str) -> Result<(), SagaError>;
+    async fn refund(&self, payment_id: &str) -> Result<(), PaymentError>;
+
+    /// Charges like [`charge`](Self::charge), but a repeated call with the
+    /// same `key` returns the first charge instead of charging again.
+    /// Providers without idempotency keys charge every time.
+    async fn charge_with_key(
+        &self,
+        _key: &str,
+        order_id: AggregateId,
+        customer_id: CustomerId,
+        amount: Money,
+    ) -> Result<PaymentResult, PaymentError> {
+        self.charge(order_id, customer_id, amount).await
+    }
+
+    /// Refunds like [`refund`](Self::refund), with `key` identifying the
+    /// refund to providers that dedupe repeated requests.
+    async fn refund_with_key(&self, _key: &str, payment_id: &str) -> Result<(), PaymentError> {
+        self.refund(payment_id).await
+    }
 }
 
 #[derive(Debug, Default)]
 struct InMemoryPaymentState {
     payments: HashMap<String, (AggregateId, CustomerId, Money)>,
+    /// Payment IDs by the idempotency key they were charged under.
+    keys: HashMap<String, String>,
     next_id: u32,
     fail_on_charge: bool,
+    unavailable_charges: u32,
 }
 
 /// In-memory payment service for testing.
@@ -55,6 +122,12 @@ impl InMemoryPaymentService {
         self.state.write().unwrap().fail_on_charge = fail;
     }
 
+    /// Makes the next `calls` charges fail as if the provider were
+    /// unreachable.
+    pub fn set_unavailable_charges(&self, calls: u32) {
+        self.state.write().unwrap().unavailable_charges = calls;
+    }
+
     /// Returns the number of active payments.
     pub fn payment_count(&self) -> usize {
         self.state.read().unwrap().payments.len()
@@ -64,6 +137,42 @@ impl InMemoryPaymentService {
     pub fn has_payment(&self, payment_id: &str) -> bool {
         self.state.read().unwrap().payments.contains_key(payment_id)
     }
+
+    /// Returns the amount charged for an active payment.
+    pub fn charged_amount(&self, payment_id: &str) -> Option<Money> {
+        self.state
+            .read()
+            .unwrap()
+            .payments
+            .get(payment_id)
+            .map(|(_, _, amount)| *amount)
+    }
+}
+
+impl InMemoryPaymentState {
+    fn charge(
+        &mut self,
+        order_id: AggregateId,
+        customer_id: CustomerId,
+        amount: Money,
+    ) -> Result<PaymentResult, PaymentError> {
+        if self.unavailable_charges > 0 {
+            self.unavailable_charges -= 1;
+            return Err(PaymentError::Unavailable {
+                reason: "provider timed out".to_string(),
+            });
+        }
+        if self.fail_on_charge {
+            return Err(card_declined());
+        }
+
+        self.next_id += 1;
+        let payment_id = format!("PAY-{:04}", self.next_id);
+        self.payments
+            .insert(payment_id.clone(), (order_id, customer_id, amount));
+
+        Ok(PaymentResult { payment_id })
+    }
 }
 
 #[async_trait]
@@ -73,29 +182,262 @@ impl PaymentService for InMemoryPaymentService {
         order_id: AggregateId,
         customer_id: CustomerId,
         amount: Money,
-    ) -> Result<PaymentResult, SagaError> {
-        let mut state = self.state.write().unwrap();
+    ) -> Result<PaymentResult, PaymentError> {
+        self.state
+            .write()
+            .unwrap()
+            .charge(order_id, customer_id, amount)
+    }
 
-        if state.fail_on_charge {
-            return Err(SagaError::PaymentService("Payment declined".to_string()));
+    async fn charge_with_key(
+        &self,
+        key: &str,
+        order_id: AggregateId,
+        customer_id: CustomerId,
+        amount: Money,
+    ) -> Result<PaymentResult, PaymentError> {
+        let mut state = self.state.write().unwrap();
+        if let Some(payment_id) = state.keys.get(key) {
+            return Ok(PaymentResult {
+                payment_id: payment_id.clone(),
+            });
         }
-
-        state.next_id += 1;
-        let payment_id = format!("PAY-{:04}", state.next_id);
+        let result = state.charge(order_id, customer_id, amount)?;
         state
-            .payments
-            .insert(payment_id.clone(), (order_id, customer_id, amount));
+            .keys
+            .insert(key.to_string(), result.payment_id.clone());
+        Ok(result)
+    }
 
-        Ok(PaymentResult { payment_id })
+    async fn can_charge(
+        &self,
+        _customer_id: CustomerId,
+        _amount: Money,
+    ) -> Result<(), PaymentError> {
+        if self.state.read().unwrap().fail_on_charge {
+            return Err(card_declined());
+        }
+        Ok(())
     }
 
-    async fn refund(&self, payment_id: &str) -> Result<(), SagaError> {
+    async fn refund(&self, payment_id: &str) -> Result<(), PaymentError> {
         let mut state = self.state.write().unwrap();
         state.payments.remove(payment_id);
         Ok(())
     }
 }
 
+/// Payment service recording each charge as a `Payment` stream in the
+/// event store.
+///
+/// A charge is recorded as requested under an ID derived from the order
+/// before anything is sent, and that ID goes to the provider as the
+/// charge's idempotency key. An approved charge is then recorded as
+/// authorized and captured, and a decline with its code before the error
+/// is returned. A provider that can't be reached leaves the payment
+/// requested, so a retried charge resumes it under the same key rather
+/// than charging twice. Refunds are likewise requested before the provider
+/// is asked. Without a provider every charge is approved, as with the
+/// in-memory mock. Payment IDs are the payment stream IDs.
+pub struct EventSourcedPaymentService<S: EventStore> {
+    ledger: Arc<PaymentLedger<S>>,
+    provider: Option<Arc<dyn PaymentService>>,
+}
+
+impl<S: EventStore> EventSourcedPaymentService<S> {
+    /// Creates a service recording payments in `store`.
+    pub fn new(store: S) -> Self {
+        Self::with_ledger(PaymentLedger::new(store))
+    }
+
+    /// Creates a service recording payments through an already configured
+    /// ledger.
+    pub fn with_ledger(ledger: PaymentLedger<S>) -> Self {
+        Self {
+            ledger: Arc::new(ledger),
+            provider: None,
+        }
+    }
+
+    /// Sends charges and refunds to `provider` before recording them.
+    pub fn with_provider(mut self, provider: Arc<dyn PaymentService>) -> Self {
+        self.provider = Some(provider);
+        self
+    }
+
+    /// Returns the ledger payments are recorded in.
+    pub fn ledger(&self) -> &PaymentLedger<S> {
+        &self.ledger
+    }
+}
+
+/// Where an order's latest charge attempt stands.
+enum Open {
+    /// Requested and awaiting the provider's answer.
+    Requested(AggregateId),
+    /// Approved but not yet collected.
+    Authorized(AggregateId),
+    /// Already collected.
+    Captured(AggregateId),
+}
+
+impl<S: EventStore + 'static> EventSourcedPaymentService<S> {
+    /// Finds the order's open charge attempt, or requests the next one.
+    ///
+    /// Declined and refunded attempts are skipped, so an order can be
+    /// charged again once a previous charge is settled.
+    async fn open_payment(
+        &self,
+        order_id: AggregateId,
+        customer_id: CustomerId,
+        amount: Money,
+    ) -> Result<Open, PaymentError> {
+        for attempt in 0.. {
+            let payment_id = Payment::id_for_order(order_id, attempt);
+            let payment = self
+                .ledger
+                .get_payment(payment_id)
+                .await
+                .map_err(unavailable)?;
+            match payment.and_then(|p| p.status()) {
+                None => {
+                    let cmd = RequestPayment::new(payment_id, order_id, customer_id, amount);
+                    self.ledger.request(cmd).await.map_err(unavailable)?;
+                    return Ok(Open::Requested(payment_id));
+                }
+                Some(PaymentStatus::Pending) => return Ok(Open::Requested(payment_id)),
+                Some(PaymentStatus::Authorized) => return Ok(Open::Authorized(payment_id)),
+                Some(PaymentStatus::Captured) => return Ok(Open::Captured(payment_id)),
+                Some(
+                    PaymentStatus::Declined
+                    | PaymentStatus::RefundPending
+                    | PaymentStatus::Refunded,
+                ) => {}
+            }
+        }
+        unreachable!("an order has a free charge attempt")
+    }
+
+    async fn capture(&self, payment_id: AggregateId) -> Result<PaymentResult, PaymentError> {
+        self.ledger
+            .capture(CapturePayment::new(payment_id))
+            .await
+            .map_err(unavailable)?;
+        Ok(PaymentResult {
+            payment_id: payment_id.to_string(),
+        })
+    }
+}
+
+impl<S: EventStore> Clone for EventSourcedPaymentService<S> {
+    fn clone(&self) -> Self {
+        Self {
+            ledger: Arc::clone(&self.ledger),
+            provider: self.provider.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl<S: EventStore + 'static> PaymentService for EventSourcedPaymentService<S> {
+    async fn charge(
+        &self,
+        order_id: AggregateId,
+        customer_id: CustomerId,
+        amount: Money,
+    ) -> Result<PaymentResult, PaymentError> {
+        let payment_id = match self.open_payment(order_id, customer_id, amount).await? {
+            Open::Requested(payment_id) => payment_id,
+            Open::Authorized(payment_id) => return self.capture(payment_id).await,
+            Open::Captured(payment_id) => {
+                return Ok(PaymentResult {
+                    payment_id: payment_id.to_string(),
+                });
+            }
+        };
+
+        let mut cmd =
+            AuthorizePayment::new(order_id, customer_id, amount).with_payment_id(payment_id);
+        if let Some(provider) = &self.provider {
+            let key = payment_id.to_string();
+            match provider
+                .charge_with_key(&key, order_id, customer_id, amount)
+                .await
+            {
+                Ok(result) => cmd = cmd.with_provider_reference(result.payment_id),
+                Err(PaymentError::Declined { code }) => {
+                    let decline = DeclinePayment::new(order_id, customer_id, amount, code.clone())
+                        .with_payment_id(payment_id);
+                    self.ledger.decline(decline).await.map_err(unavailable)?;
+                    return Err(PaymentError::Declined { code });
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        self.ledger.authorize(cmd).await.map_err(unavailable)?;
+        self.capture(payment_id).await
+    }
+
+    async fn can_charge(&self, customer_id: CustomerId, amount: Money) -> Result<(), PaymentError> {
+        match &self.provider {
+            Some(provider) => provider.can_charge(customer_id, amount).await,
+            None => Ok(()),
+        }
+    }
+
+    async fn refund(&self, payment_id: &str) -> Result<(), PaymentError> {
+        // IDs that aren't payment streams were never charged here
+        let Ok(uuid) = payment_id.parse() else {
+            return Ok(());
+        };
+        let payment_id = AggregateId::from_uuid(uuid);
+        let Some(payment) = self
+            .ledger
+            .get_payment(payment_id)
+            .await
+            .map_err(unavailable)?
+        else {
+            return Ok(());
+        };
+        if !matches!(
+            payment.status(),
+            Some(
+                PaymentStatus::Authorized | PaymentStatus::Captured | PaymentStatus::RefundPending
+            )
+        ) {
+            return Ok(());
+        }
+
+        self.ledger
+            .request_refund(RequestRefund::new(payment_id))
+            .await
+            .map_err(unavailable)?;
+        if let (Some(provider), Some(reference)) = (&self.provider, payment.provider_reference()) {
+            let key = format!("{payment_id}:refund");
+            provider.refund_with_key(&key, reference).await?;
+        }
+        self.ledger
+            .refund(RefundPayment::new(payment_id))
+            .await
+            .map_err(unavailable)?;
+        Ok(())
+    }
+}
+
+/// Reports a failure to record a payment as the service being unavailable.
+fn unavailable(e: DomainError) -> PaymentError {
+    PaymentError::Unavailable {
+        reason: e.to_string(),
+    }
+}
+
+fn card_declined() -> PaymentError {
+    PaymentError::Declined {
+        code: "card_declined".to_string(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -126,10 +468,110 @@ mod tests {
         let amount = Money::from_cents(5000);
 
         let result = service.charge(order_id, customer_id, amount).await;
-        assert!(result.is_err());
+        assert!(matches!(result, Err(PaymentError::Declined { code }) if code == "card_declined"));
+        assert!(service.can_charge(customer_id, amount).await.is_err());
         assert_eq!(service.payment_count(), 0);
     }
 
+    #[tokio::test]
+    async fn test_unavailable_charges_are_retryable() {
+        let service = InMemoryPaymentService::new();
+        service.set_unavailable_charges(1);
+
+        let order_id = AggregateId::new();
+        let customer_id = CustomerId::new();
+        let amount = Money::from_cents(5000);
+
+        let error = service
+            .charge(order_id, customer_id, amount)
+            .await
+            .unwrap_err();
+        assert!(error.is_retryable());
+        assert!(!card_declined().is_retryable());
+        service.charge(order_id, customer_id, amount).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_event_sourced_payments_record_their_lifecycle() {
+        let provider = InMemoryPaymentService::new();
+        let service = EventSourcedPaymentService::new(event_store::InMemoryEventStore::new())
+            .with_provider(Arc::new(provider.clone()));
+        let order_id = AggregateId::new();
+        let customer_id = CustomerId::new();
+        let amount = Money::from_cents(5000);
+
+        let result = service.charge(order_id, customer_id, amount).await.unwrap();
+        let payment_id = AggregateId::from_uuid(result.payment_id.parse().unwrap());
+        let payment = service.ledger().get_payment(payment_id).await.unwrap();
+        let payment = payment.unwrap();
+        assert!(payment.is_captured());
+        assert_eq!(payment.provider_reference(), Some("PAY-0001"));
+
+        service.refund(&result.payment_id).await.unwrap();
+        service.refund(&result.payment_id).await.unwrap();
+        let payment = service.ledger().get_payment(payment_id).await.unwrap();
+        assert_eq!(payment.unwrap().status(), Some(PaymentStatus::Refunded));
+        assert_eq!(provider.payment_count(), 0);
+
+        // A settled charge leaves the next attempt to a new payment
+        provider.set_fail_on_charge(true);
+        let error = service
+            .charge(order_id, customer_id, amount)
+            .await
+            .unwrap_err();
+        assert_eq!(error, card_declined());
+        let declined = service
+            .ledger()
+            .get_payment(Payment::id_for_order(order_id, 1))
+            .await
+            .unwrap();
+        assert_eq!(declined.unwrap().status(), Some(PaymentStatus::Declined));
+        service.refund("PAY-0001").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_event_sourced_charge_is_requested_before_the_provider() {
+        let provider = InMemoryPaymentService::new();
+        let service = EventSourcedPaymentService::new(event_store::InMemoryEventStore::new())
+            .with_provider(Arc::new(provider.clone()));
+        let order_id = AggregateId::new();
+        let customer_id = CustomerId::new();
+        let amount = Money::from_cents(5000);
+        let payment_id = Payment::id_for_order(order_id, 0);
+
+        // An unreachable provider leaves the payment requested
+        provider.set_unavailable_charges(1);
+        let error = service
+            .charge(order_id, customer_id, amount)
+            .await
+            .unwrap_err();
+        assert!(error.is_retryable());
+        let payment = service.ledger().get_payment(payment_id).await.unwrap();
+        assert_eq!(payment.unwrap().status(), Some(PaymentStatus::Pending));
+
+        // The provider charged under the payment's key but the answer was
+        // lost; the retry reuses that charge instead of making another
+        let charged = provider
+            .charge_with_key(&payment_id.to_string(), order_id, customer_id, amount)
+            .await
+            .unwrap();
+        let result = service.charge(order_id, customer_id, amount).await.unwrap();
+        assert_eq!(result.payment_id, payment_id.to_string());
+        assert_eq!(provider.payment_count(), 1);
+        let payment = service.ledger().get_payment(payment_id).await.unwrap();
+        let payment = payment.unwrap();
+        assert!(payment.is_captured());
+        assert_eq!(
+            payment.provider_reference(),
+            Some(charged.payment_id.as_str())
+        );
+
+        // Charging a captured order again returns the same payment
+        let again = service.charge(order_id, customer_id, amount).await.unwrap();
+        assert_eq!(again.payment_id, result.payment_id);
+        assert_eq!(provider.payment_count(), 1);
+    }
+
     #[tokio::test]
     async fn test_sequential_payment_ids() {
         let service = InMemoryPaymentService::new();
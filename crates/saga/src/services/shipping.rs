@@ -1,35 +1,83 @@
 //! Shipping service trait and in-memory implementation.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, RwLock};
 
 use async_trait::async_trait;
 use common::AggregateId;
+use domain::{Address, ProductId, ShippedItem};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
-use crate::error::SagaError;
+use crate::failure::FailureKind;
+use crate::services::inventory::ReservationItem;
+
+/// Errors from the shipping service.
+#[derive(Debug, Clone, PartialEq, Eq, Error, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ShippingError {
+    /// No carrier accepted the shipment.
+    #[error("No carrier available: {reason}")]
+    NoCarrier { reason: String },
+
+    /// The service couldn't be reached or timed out; the call may succeed
+    /// if repeated.
+    #[error("Shipping service unavailable: {reason}")]
+    Unavailable { reason: String },
+}
+
+impl ShippingError {
+    /// Classifies the error for customers.
+    pub fn failure_kind(&self) -> FailureKind {
+        match self {
+            ShippingError::NoCarrier { .. } => FailureKind::CarrierUnavailable,
+            ShippingError::Unavailable { .. } => FailureKind::Unknown,
+        }
+    }
+
+    /// Returns true if repeating the call may succeed.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, ShippingError::Unavailable { .. })
+    }
+}
 
 /// Result of a successful shipment creation.
 #[derive(Debug, Clone)]
 pub struct ShipmentResult {
     /// The tracking number assigned by the shipping service.
     pub tracking_number: String,
+    /// Serial and lot numbers picked by the warehouse, for items that carry them.
+    pub shipped_items: Vec<ShippedItem>,
 }
 
 /// Trait for shipping operations.
 #[async_trait]
 pub trait ShippingService: Send + Sync {
-    /// Creates a shipment for an order.
-    async fn create_shipment(&self, order_id: AggregateId) -> Result<ShipmentResult, SagaError>;
+    /// Creates a shipment for the given order items, to the order's
+    /// shipping address. `address` is None for orders that were never
+    /// given one.
+    ///
+    /// Fails with [`ShippingError::NoCarrier`] when no carrier can take the
+    /// shipment, so the customer is told why.
+    async fn create_shipment(
+        &self,
+        order_id: AggregateId,
+        items: &[ReservationItem],
+        address: Option<&Address>,
+    ) -> Result<ShipmentResult, ShippingError>;
 
     /// Cancels a previously created shipment.
-    async fn cancel_shipment(&self, tracking_number: &str) -> Result<(), SagaError>;
+    async fn cancel_shipment(&self, tracking_number: &str) -> Result<(), ShippingError>;
 }
 
 #[derive(Debug, Default)]
 struct InMemoryShippingState {
     shipments: HashMap<String, AggregateId>,
+    addresses: HashMap<String, Address>,
     next_id: u32,
     fail_on_create: bool,
+    serialized: HashSet<ProductId>,
+    lot_numbers: HashMap<ProductId, String>,
 }
 
 /// In-memory shipping service for testing.
@@ -49,6 +97,24 @@ impl InMemoryShippingService {
         self.state.write().unwrap().fail_on_create = fail;
     }
 
+    /// Assigns a serial number to every unit of `product_id` shipped.
+    pub fn set_serialized(&self, product_id: impl Into<ProductId>) {
+        self.state
+            .write()
+            .unwrap()
+            .serialized
+            .insert(product_id.into());
+    }
+
+    /// Ships units of `product_id` from the given lot.
+    pub fn set_lot_number(&self, product_id: impl Into<ProductId>, lot_number: impl Into<String>) {
+        self.state
+            .write()
+            .unwrap()
+            .lot_numbers
+            .insert(product_id.into(), lot_number.into());
+    }
+
     /// Returns the number of active shipments.
     pub fn shipment_count(&self) -> usize {
         self.state.read().unwrap().shipments.len()
@@ -62,29 +128,73 @@ impl InMemoryShippingService {
             .shipments
             .contains_key(tracking_number)
     }
+
+    /// Returns the address an active shipment goes to.
+    pub fn shipment_address(&self, tracking_number: &str) -> Option<Address> {
+        self.state
+            .read()
+            .unwrap()
+            .addresses
+            .get(tracking_number)
+            .cloned()
+    }
 }
 
 #[async_trait]
 impl ShippingService for InMemoryShippingService {
-    async fn create_shipment(&self, order_id: AggregateId) -> Result<ShipmentResult, SagaError> {
+    async fn create_shipment(
+        &self,
+        order_id: AggregateId,
+        items: &[ReservationItem],
+        address: Option<&Address>,
+    ) -> Result<ShipmentResult, ShippingError> {
         let mut state = self.state.write().unwrap();
 
         if state.fail_on_create {
-            return Err(SagaError::ShippingService(
-                "Shipping unavailable".to_string(),
-            ));
+            return Err(ShippingError::NoCarrier {
+                reason: "no carrier accepted the shipment".to_string(),
+            });
         }
 
         state.next_id += 1;
         let tracking_number = format!("TRACK-{:04}", state.next_id);
         state.shipments.insert(tracking_number.clone(), order_id);
+        if let Some(address) = address {
+            state
+                .addresses
+                .insert(tracking_number.clone(), address.clone());
+        }
+
+        let shipped_items = items
+            .iter()
+            .filter_map(|item| {
+                let serialized = state.serialized.contains(&item.product_id);
+                let lot_number = state.lot_numbers.get(&item.product_id).cloned();
+                if !serialized && lot_number.is_none() {
+                    return None;
+                }
+                let mut shipped = ShippedItem::new(item.product_id.clone());
+                if serialized {
+                    shipped =
+                        shipped.with_serial_numbers((1..=item.quantity).map(|unit| {
+                            format!("SN-{tracking_number}-{}-{unit:03}", item.product_id)
+                        }));
+                }
+                shipped.lot_number = lot_number;
+                Some(shipped)
+            })
+            .collect();
 
-        Ok(ShipmentResult { tracking_number })
+        Ok(ShipmentResult {
+            tracking_number,
+            shipped_items,
+        })
     }
 
-    async fn cancel_shipment(&self, tracking_number: &str) -> Result<(), SagaError> {
+    async fn cancel_shipment(&self, tracking_number: &str) -> Result<(), ShippingError> {
         let mut state = self.state.write().unwrap();
         state.shipments.remove(tracking_number);
+        state.addresses.remove(tracking_number);
         Ok(())
     }
 }
@@ -98,7 +208,7 @@ mod tests {
         let service = InMemoryShippingService::new();
         let order_id = AggregateId::new();
 
-        let result = service.create_shipment(order_id).await.unwrap();
+        let result = service.create_shipment(order_id, &[], None).await.unwrap();
         assert!(result.tracking_number.starts_with("TRACK-"));
         assert_eq!(service.shipment_count(), 1);
         assert!(service.has_shipment(&result.tracking_number));
@@ -116,8 +226,8 @@ mod tests {
         service.set_fail_on_create(true);
 
         let order_id = AggregateId::new();
-        let result = service.create_shipment(order_id).await;
-        assert!(result.is_err());
+        let result = service.create_shipment(order_id, &[], None).await;
+        assert!(matches!(result, Err(ShippingError::NoCarrier { .. })));
         assert_eq!(service.shipment_count(), 0);
     }
 
@@ -126,10 +236,49 @@ mod tests {
         let service = InMemoryShippingService::new();
         let order_id = AggregateId::new();
 
-        let r1 = service.create_shipment(order_id).await.unwrap();
-        let r2 = service.create_shipment(order_id).await.unwrap();
+        let r1 = service.create_shipment(order_id, &[], None).await.unwrap();
+        let r2 = service.create_shipment(order_id, &[], None).await.unwrap();
 
         assert_eq!(r1.tracking_number, "TRACK-0001");
         assert_eq!(r2.tracking_number, "TRACK-0002");
     }
+
+    #[tokio::test]
+    async fn test_serial_and_lot_numbers() {
+        let service = InMemoryShippingService::new();
+        service.set_serialized("SKU-SERIAL");
+        service.set_lot_number("SKU-SERIAL", "LOT-A");
+        service.set_lot_number("SKU-LOT", "LOT-B");
+
+        let item = |product_id: &str, quantity| ReservationItem {
+            product_id: ProductId::new(product_id),
+            product_name: "Widget".to_string(),
+            quantity,
+        };
+        let result = service
+            .create_shipment(
+                AggregateId::new(),
+                &[
+                    item("SKU-SERIAL", 2),
+                    item("SKU-LOT", 5),
+                    item("SKU-PLAIN", 1),
+                ],
+                None,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            result.shipped_items,
+            vec![
+                ShippedItem::new("SKU-SERIAL")
+                    .with_serial_numbers([
+                        "SN-TRACK-0001-SKU-SERIAL-001",
+                        "SN-TRACK-0001-SKU-SERIAL-002"
+                    ])
+                    .with_lot_number("LOT-A"),
+                ShippedItem::new("SKU-LOT").with_lot_number("LOT-B"),
+            ]
+        );
+    }
 }
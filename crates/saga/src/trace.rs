@@ -0,0 +1,201 @@
+//! Trace IDs recorded on saga events.
+//!
+//! Each saga run belongs to one trace, identified as in W3C Trace Context: a
+//! caller can pass its own `traceparent` to continue its trace, otherwise a
+//! new one is started. The saga gets a span of its own, and so does each step
+//! attempt. The IDs are stored in saga event metadata, and the trace ID is
+//! recorded on the coordinator's log span, so a saga's trace can be found
+//! from its events and its log lines.
+//!
+//! Compensation and reconciliation events carry no span IDs.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::SagaEvent;
+
+/// Metadata key holding the trace a saga event belongs to.
+pub const TRACE_ID_KEY: &str = "trace_id";
+
+/// Metadata key holding the span of the saga or step attempt that recorded
+/// an event.
+pub const SPAN_ID_KEY: &str = "span_id";
+
+/// Metadata key holding the caller's span the saga was started from.
+pub const PARENT_SPAN_ID_KEY: &str = "parent_span_id";
+
+/// Trace and span a saga event is recorded under.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceContext {
+    /// 32 lowercase hex digits.
+    pub trace_id: String,
+    /// 16 lowercase hex digits.
+    pub span_id: String,
+    /// The caller's span, for a saga started from a `traceparent`.
+    pub parent_span_id: Option<String>,
+}
+
+impl TraceContext {
+    /// Starts a new trace.
+    pub fn new_root() -> Self {
+        Self {
+            trace_id: Uuid::new_v4().simple().to_string(),
+            span_id: new_span_id(),
+            parent_span_id: None,
+        }
+    }
+
+    /// Continues the trace of a W3C `traceparent` header value
+    /// (`00-<trace-id>-<parent-id>-<flags>`) in a new span. Returns None if
+    /// the value is malformed or its IDs are all zeros.
+    pub fn from_traceparent(traceparent: &str) -> Option<Self> {
+        let mut parts = traceparent.trim().split('-');
+        let (version, trace_id, parent_id, flags) =
+            (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+        let valid = |id: &str, len: usize| {
+            id.len() == len
+                && id.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+                && id.bytes().any(|b| b != b'0')
+        };
+        if version != "00"
+            || parts.next().is_some()
+            || !valid(trace_id, 32)
+            || !valid(parent_id, 16)
+            || flags.len() != 2
+        {
+            return None;
+        }
+        Some(Self {
+            trace_id: trace_id.to_string(),
+            span_id: new_span_id(),
+            parent_span_id: Some(parent_id.to_string()),
+        })
+    }
+
+    /// Returns a context for a new span in the same trace, under this one.
+    pub fn child(&self) -> Self {
+        Self {
+            trace_id: self.trace_id.clone(),
+            span_id: new_span_id(),
+            parent_span_id: Some(self.span_id.clone()),
+        }
+    }
+
+    /// Returns the metadata recorded on saga events.
+    pub fn to_metadata(&self) -> HashMap<String, serde_json::Value> {
+        let mut metadata = HashMap::from([
+            (TRACE_ID_KEY.to_string(), serde_json::json!(self.trace_id)),
+            (SPAN_ID_KEY.to_string(), serde_json::json!(self.span_id)),
+        ]);
+        if let Some(parent) = &self.parent_span_id {
+            metadata.insert(PARENT_SPAN_ID_KEY.to_string(), serde_json::json!(parent));
+        }
+        metadata
+    }
+
+    /// Reads the context back from event metadata, or None if the event
+    /// wasn't traced.
+    pub fn from_metadata(metadata: &HashMap<String, serde_json::Value>) -> Option<Self> {
+        let field = |key| metadata.get(key).and_then(|value| value.as_str());
+        Some(Self {
+            trace_id: field(TRACE_ID_KEY)?.to_string(),
+            span_id: field(SPAN_ID_KEY)?.to_string(),
+            parent_span_id: field(PARENT_SPAN_ID_KEY).map(String::from),
+        })
+    }
+}
+
+fn new_span_id() -> String {
+    Uuid::new_v4().simple().to_string()[..16].to_string()
+}
+
+/// The trace of one saga run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SagaTrace {
+    pub trace_id: String,
+    /// The saga's own span.
+    pub span_id: String,
+    /// The caller's span, if the saga continued its trace.
+    pub parent_span_id: Option<String>,
+    /// One span per step attempt, in the order they started.
+    pub steps: Vec<StepSpan>,
+}
+
+/// The span of one saga step attempt.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StepSpan {
+    pub step_name: String,
+    pub attempt: u32,
+    pub span_id: String,
+}
+
+impl SagaTrace {
+    /// Collects the trace from a saga's events and their metadata, in
+    /// order. Returns None if the saga wasn't traced.
+    pub fn from_events<'a>(
+        events: impl IntoIterator<Item = (&'a SagaEvent, &'a HashMap<String, serde_json::Value>)>,
+    ) -> Option<Self> {
+        let mut events = events.into_iter();
+        let (_, metadata) = events.next()?;
+        let root = TraceContext::from_metadata(metadata)?;
+        let steps = events
+            .filter_map(|(event, metadata)| match event {
+                SagaEvent::StepStarted(data) => Some(StepSpan {
+                    step_name: data.step_name.clone(),
+                    attempt: data.attempt,
+                    span_id: TraceContext::from_metadata(metadata)?.span_id,
+                }),
+                _ => None,
+            })
+            .collect();
+        Some(Self {
+            trace_id: root.trace_id,
+            span_id: root.span_id,
+            parent_span_id: root.parent_span_id,
+            steps,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_traceparent_is_continued() {
+        let context = TraceContext::from_traceparent(
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+        )
+        .unwrap();
+        assert_eq!(context.trace_id, "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(context.parent_span_id.as_deref(), Some("00f067aa0ba902b7"));
+        assert_eq!(context.span_id.len(), 16);
+
+        for invalid in [
+            "",
+            "01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+        ] {
+            assert_eq!(TraceContext::from_traceparent(invalid), None, "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_metadata_round_trip() {
+        let root = TraceContext::new_root();
+        assert_eq!(root.trace_id.len(), 32);
+        let child = root.child();
+        assert_eq!(child.trace_id, root.trace_id);
+        assert_eq!(child.parent_span_id.as_ref(), Some(&root.span_id));
+
+        assert_eq!(
+            TraceContext::from_metadata(&child.to_metadata()),
+            Some(child)
+        );
+        assert_eq!(TraceContext::from_metadata(&HashMap::new()), None);
+    }
+}
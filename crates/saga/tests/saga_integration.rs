@@ -1,13 +1,19 @@
 //! Integration tests for the saga pattern implementation.
 
-use common::AggregateId;
+use std::sync::Arc;
+
+use chrono::{FixedOffset, NaiveTime};
+use common::{AggregateId, VirtualClock};
 use domain::{
-    AddItem, Aggregate, CreateOrder, CustomerId, Money, OrderItem, OrderService, OrderState,
+    AddItem, Address, Aggregate, CreateOrder, Currency, CustomerId, Money, OrderItem, OrderService,
+    OrderState, PaymentStatus, ProductId, RestockItem, SetShippingAddress, UpdateShippingAddress,
 };
-use event_store::InMemoryEventStore;
+use event_store::{FaultyEventStore, InMemoryEventStore, StoreOperation};
+use saga::order_fulfillment::STEP_PROCESS_PAYMENT;
 use saga::{
-    InMemoryInventoryService, InMemoryPaymentService, InMemoryShippingService, SagaCoordinator,
-    SagaState,
+    BusinessHours, EventSourcedInventoryService, EventSourcedPaymentService, FailureKind,
+    InMemoryInventoryService, InMemoryPaymentService, InMemoryShippingService, InventoryService,
+    ReservationCheck, ReservationItem, SagaCoordinator, SagaState,
 };
 
 type TestCoordinator = SagaCoordinator<
@@ -96,7 +102,7 @@ async fn test_happy_path_full_order_fulfillment() {
     );
 
     // Verify context was accumulated
-    assert!(saga.reservation_id().is_some());
+    assert!(!saga.reservations().is_empty());
     assert!(saga.payment_id().is_some());
     assert!(saga.tracking_number().is_some());
 
@@ -104,12 +110,90 @@ async fn test_happy_path_full_order_fulfillment() {
     let order = h.order_service.get_order(order_id).await.unwrap().unwrap();
     assert_eq!(order.state(), OrderState::Completed);
 
-    // Verify external services have records
-    assert_eq!(h.inventory.reservation_count(), 1);
+    // Verify external services have records (one reservation per line item)
+    assert_eq!(h.inventory.reservation_count(), 2);
     assert_eq!(h.payment.payment_count(), 1);
     assert_eq!(h.shipping.shipment_count(), 1);
 }
 
+#[tokio::test]
+async fn test_payment_charged_in_order_currency() {
+    let h = TestHarness::new();
+    let cmd = CreateOrder::for_customer(CustomerId::new()).with_currency(Currency::EUR);
+    let order_id = cmd.order_id;
+    h.order_service.create_order(cmd).await.unwrap();
+    let price = Money::from_cents_in(1500, Currency::EUR);
+    h.order_service
+        .add_item(AddItem::new(
+            order_id,
+            OrderItem::new("SKU-001", "Widget", 2, price),
+        ))
+        .await
+        .unwrap();
+
+    // A price in another currency is refused
+    let err = h
+        .order_service
+        .add_item(AddItem::new(
+            order_id,
+            OrderItem::new("SKU-002", "Gadget", 1, Money::from_cents(1000)),
+        ))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("currency"), "{err}");
+
+    let saga_id = h.coordinator.execute_saga(order_id).await.unwrap();
+    let saga = h.coordinator.get_saga(saga_id).await.unwrap().unwrap();
+    assert_eq!(saga.state(), SagaState::Completed);
+    let payment_id = saga.payment_id().unwrap();
+    assert_eq!(
+        h.payment.charged_amount(payment_id),
+        Some(Money::from_cents_in(3000, Currency::EUR))
+    );
+}
+
+#[tokio::test]
+async fn test_shipment_sent_to_order_shipping_address() {
+    let h = TestHarness::new();
+    let order_id = h.create_order().await;
+    h.order_service
+        .set_shipping_address(SetShippingAddress::new(
+            order_id,
+            Address::new("1 Main St", "Springfield", "12345", "US"),
+        ))
+        .await
+        .unwrap();
+    let moved = Address::new("9 Elm St", "Springfield", "12345", "US").with_region("IL");
+    h.order_service
+        .update_shipping_address(UpdateShippingAddress::new(order_id, moved.clone()))
+        .await
+        .unwrap();
+
+    let saga_id = h.coordinator.execute_saga(order_id).await.unwrap();
+    let saga = h.coordinator.get_saga(saga_id).await.unwrap().unwrap();
+    let tracking_number = saga.tracking_number().unwrap();
+    assert_eq!(h.shipping.shipment_address(tracking_number), Some(moved));
+}
+
+#[tokio::test]
+async fn test_shipment_serial_and_lot_numbers_recorded_on_order() {
+    let h = TestHarness::new();
+    h.shipping.set_serialized("SKU-001");
+    h.shipping.set_lot_number("SKU-002", "LOT-42");
+    let order_id = h.create_order().await;
+
+    h.coordinator.execute_saga(order_id).await.unwrap();
+
+    let order = h.order_service.get_order(order_id).await.unwrap().unwrap();
+    let serialized = order.shipped_item(&ProductId::new("SKU-001")).unwrap();
+    assert_eq!(serialized.serial_numbers.len(), 2);
+    assert!(serialized.lot_number.is_none());
+
+    let lot = order.shipped_item(&ProductId::new("SKU-002")).unwrap();
+    assert!(lot.serial_numbers.is_empty());
+    assert_eq!(lot.lot_number.as_deref(), Some("LOT-42"));
+}
+
 #[tokio::test]
 async fn test_inventory_failure_no_compensation_needed() {
     let h = TestHarness::new();
@@ -147,7 +231,7 @@ async fn test_payment_failure_releases_inventory() {
     let saga = h.coordinator.get_saga(saga_id).await.unwrap().unwrap();
     assert_eq!(saga.state(), SagaState::Failed);
     assert_eq!(saga.completed_steps(), &["reserve_inventory"]);
-    assert!(saga.reservation_id().is_some());
+    assert!(!saga.reservations().is_empty());
     assert!(saga.payment_id().is_none());
 
     // Order should be cancelled
@@ -176,7 +260,7 @@ async fn test_shipping_failure_refunds_payment_releases_inventory() {
         saga.completed_steps(),
         &["reserve_inventory", "process_payment"]
     );
-    assert!(saga.reservation_id().is_some());
+    assert!(!saga.reservations().is_empty());
     assert!(saga.payment_id().is_some());
     assert!(saga.tracking_number().is_none());
 
@@ -205,7 +289,7 @@ async fn test_saga_event_sourced_can_reload_from_store() {
     assert_eq!(saga1.state(), saga2.state());
     assert_eq!(saga1.order_id(), saga2.order_id());
     assert_eq!(saga1.completed_steps(), saga2.completed_steps());
-    assert_eq!(saga1.reservation_id(), saga2.reservation_id());
+    assert_eq!(saga1.reservations(), saga2.reservations());
     assert_eq!(saga1.payment_id(), saga2.payment_id());
     assert_eq!(saga1.tracking_number(), saga2.tracking_number());
 }
@@ -232,8 +316,8 @@ async fn test_multiple_independent_sagas() {
     assert_eq!(saga1.order_id(), Some(order_id_1));
     assert_eq!(saga2.order_id(), Some(order_id_2));
 
-    // External services should have records for both
-    assert_eq!(h.inventory.reservation_count(), 2);
+    // External services should have records for both (two lines per order)
+    assert_eq!(h.inventory.reservation_count(), 4);
     assert_eq!(h.payment.payment_count(), 2);
     assert_eq!(h.shipping.shipment_count(), 2);
 }
@@ -275,8 +359,246 @@ async fn test_one_saga_fails_other_succeeds() {
     assert_eq!(order2.state(), OrderState::Cancelled);
 
     // First saga's services remain; second saga's are compensated
-    // (1 from saga1 + 0 from saga2 compensation = 1)
-    assert_eq!(h.inventory.reservation_count(), 1);
+    // (2 lines from saga1 + 0 from saga2 compensation = 2)
+    assert_eq!(h.inventory.reservation_count(), 2);
     assert_eq!(h.payment.payment_count(), 1);
     assert_eq!(h.shipping.shipment_count(), 1);
 }
+
+#[tokio::test]
+async fn test_store_outage_before_saga_starts_leaves_order_retryable() {
+    let store = FaultyEventStore::new(InMemoryEventStore::new());
+    let inventory = InMemoryInventoryService::new();
+    let payment = InMemoryPaymentService::new();
+    let coordinator = SagaCoordinator::new(
+        store.clone(),
+        inventory.clone(),
+        payment.clone(),
+        InMemoryShippingService::new(),
+    );
+    let order_service = OrderService::new(store.clone());
+    let cmd = CreateOrder::for_customer(CustomerId::new());
+    let order_id = cmd.order_id;
+    order_service.create_order(cmd).await.unwrap();
+    order_service
+        .add_item(AddItem::new(
+            order_id,
+            OrderItem::new("SKU-001", "Widget", 2, Money::from_cents(1000)),
+        ))
+        .await
+        .unwrap();
+
+    // The submit append fails, so no service is called
+    store.fail_next(StoreOperation::Append, 1);
+    assert!(coordinator.execute_saga(order_id).await.is_err());
+    assert_eq!(inventory.reservation_count(), 0);
+    assert_eq!(payment.payment_count(), 0);
+    let order = order_service.get_order(order_id).await.unwrap().unwrap();
+    assert_eq!(order.state(), OrderState::Draft);
+
+    let saga_id = coordinator.execute_saga(order_id).await.unwrap();
+    let saga = coordinator.get_saga(saga_id).await.unwrap().unwrap();
+    assert_eq!(saga.state(), SagaState::Completed);
+}
+
+#[tokio::test]
+async fn test_event_sourced_inventory_holds_and_releases_stock() {
+    let store = InMemoryEventStore::new();
+    let inventory = EventSourcedInventoryService::new(store.clone());
+    let payment = InMemoryPaymentService::new();
+    let coordinator = SagaCoordinator::new(
+        store.clone(),
+        inventory.clone(),
+        payment.clone(),
+        InMemoryShippingService::new(),
+    );
+    let order_service = OrderService::new(store);
+    for (sku, quantity) in [("SKU-001", 3), ("SKU-002", 1)] {
+        inventory
+            .items()
+            .restock(RestockItem::new(sku, quantity, None))
+            .await
+            .unwrap();
+    }
+    let available = |sku: &'static str| {
+        let inventory = inventory.clone();
+        async move {
+            let item = inventory.items().get_item(&ProductId::new(sku)).await;
+            item.unwrap().unwrap().available()
+        }
+    };
+    let order = || async {
+        let cmd = CreateOrder::for_customer(CustomerId::new());
+        let order_id = cmd.order_id;
+        order_service.create_order(cmd).await.unwrap();
+        for (sku, quantity) in [("SKU-001", 2), ("SKU-002", 1)] {
+            order_service
+                .add_item(AddItem::new(
+                    order_id,
+                    OrderItem::new(sku, "Widget", quantity, Money::from_cents(1000)),
+                ))
+                .await
+                .unwrap();
+        }
+        order_id
+    };
+
+    // A failed payment gives the stock back
+    payment.set_fail_on_charge(true);
+    let saga_id = coordinator.execute_saga(order().await).await.unwrap();
+    let saga = coordinator.get_saga(saga_id).await.unwrap().unwrap();
+    assert_eq!(saga.state(), SagaState::Failed);
+    assert_eq!(available("SKU-001").await, 3);
+    assert_eq!(available("SKU-002").await, 1);
+
+    payment.set_fail_on_charge(false);
+    let saga_id = coordinator.execute_saga(order().await).await.unwrap();
+    let saga = coordinator.get_saga(saga_id).await.unwrap().unwrap();
+    assert_eq!(saga.state(), SagaState::Completed);
+    assert_eq!(available("SKU-001").await, 1);
+    assert_eq!(available("SKU-002").await, 0);
+
+    // The next order finds the stock taken
+    let saga_id = coordinator.execute_saga(order().await).await.unwrap();
+    let saga = coordinator.get_saga(saga_id).await.unwrap().unwrap();
+    assert_eq!(saga.state(), SagaState::Failed);
+    assert_eq!(saga.failure_kind(), Some(FailureKind::InsufficientStock));
+    assert_eq!(available("SKU-001").await, 1);
+}
+
+#[tokio::test]
+async fn test_released_reservations_of_waiting_order_are_reconciled() {
+    let store = InMemoryEventStore::new();
+    let inventory = EventSourcedInventoryService::new(store.clone());
+    // Friday evening, so payment waits for Monday's business hours
+    let clock = Arc::new(VirtualClock::new("2026-10-16T20:00:00Z".parse().unwrap()));
+    let hours = BusinessHours::new(
+        FixedOffset::east_opt(0).unwrap(),
+        NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+        NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+    );
+    let coordinator = SagaCoordinator::new(
+        store.clone(),
+        inventory.clone(),
+        InMemoryPaymentService::new(),
+        InMemoryShippingService::new(),
+    )
+    .with_clock(clock)
+    .with_step_policy(STEP_PROCESS_PAYMENT, Arc::new(hours));
+    let order_service = OrderService::new(store);
+    for (sku, quantity) in [("SKU-001", 3), ("SKU-002", 1)] {
+        inventory
+            .items()
+            .restock(RestockItem::new(sku, quantity, None))
+            .await
+            .unwrap();
+    }
+    let cmd = CreateOrder::for_customer(CustomerId::new());
+    let order_id = cmd.order_id;
+    order_service.create_order(cmd).await.unwrap();
+    for (sku, quantity) in [("SKU-001", 2), ("SKU-002", 1)] {
+        order_service
+            .add_item(AddItem::new(
+                order_id,
+                OrderItem::new(sku, "Widget", quantity, Money::from_cents(1000)),
+            ))
+            .await
+            .unwrap();
+    }
+    let saga_id = coordinator.execute_saga(order_id).await.unwrap();
+    let order = order_service.get_order(order_id).await.unwrap().unwrap();
+    assert_eq!(order.state(), OrderState::Reserved);
+
+    // Inventory gave the gadget's units back while the order waited
+    let gadget = ProductId::new("SKU-002");
+    let released = order.reservation(&gadget).unwrap().to_string();
+    inventory.release(&released).await.unwrap();
+    let reports = coordinator.reconcile_all_reservations().await.unwrap();
+    assert_eq!(
+        reports[0].check,
+        ReservationCheck::Renewed {
+            products: vec![gadget.clone()]
+        }
+    );
+    let order = order_service.get_order(order_id).await.unwrap().unwrap();
+    assert_ne!(order.reservation(&gadget), Some(released.as_str()));
+
+    // This time another order takes the units first
+    let renewed = order.reservation(&gadget).unwrap();
+    inventory.release(renewed).await.unwrap();
+    let item = ReservationItem {
+        product_id: gadget.clone(),
+        product_name: "Gadget".to_string(),
+        quantity: 1,
+    };
+    inventory
+        .reserve(AggregateId::new(), vec![item])
+        .await
+        .unwrap();
+    let report = coordinator.reconcile_reservations(order_id).await.unwrap();
+    assert_eq!(
+        report.check,
+        ReservationCheck::Cancelled { product_id: gadget }
+    );
+    let saga = coordinator.get_saga(saga_id).await.unwrap().unwrap();
+    assert_eq!(saga.state(), SagaState::Failed);
+    let order = order_service.get_order(order_id).await.unwrap().unwrap();
+    assert_eq!(order.state(), OrderState::Cancelled);
+    let widget = inventory.items().get_item(&ProductId::new("SKU-001")).await;
+    assert_eq!(widget.unwrap().unwrap().available(), 3);
+}
+
+#[tokio::test]
+async fn test_event_sourced_payments_refunded_on_compensation() {
+    let store = InMemoryEventStore::new();
+    let payment = EventSourcedPaymentService::new(store.clone());
+    let shipping = InMemoryShippingService::new();
+    let coordinator = SagaCoordinator::new(
+        store.clone(),
+        InMemoryInventoryService::new(),
+        payment.clone(),
+        shipping.clone(),
+    );
+    let order_service = OrderService::new(store);
+    let order = || async {
+        let cmd = CreateOrder::for_customer(CustomerId::new());
+        let order_id = cmd.order_id;
+        order_service.create_order(cmd).await.unwrap();
+        order_service
+            .add_item(AddItem::new(
+                order_id,
+                OrderItem::new("SKU-001", "Widget", 2, Money::from_cents(1500)),
+            ))
+            .await
+            .unwrap();
+        order_id
+    };
+    let payment_status = |saga: &saga::SagaInstance| {
+        let payment = payment.clone();
+        let payment_id = saga.payment_id().unwrap().parse().unwrap();
+        async move {
+            let payment = payment
+                .ledger()
+                .get_payment(AggregateId::from_uuid(payment_id))
+                .await
+                .unwrap()
+                .unwrap();
+            (payment.status(), payment.amount())
+        }
+    };
+
+    let saga_id = coordinator.execute_saga(order().await).await.unwrap();
+    let saga = coordinator.get_saga(saga_id).await.unwrap().unwrap();
+    assert_eq!(saga.state(), SagaState::Completed);
+    assert_eq!(
+        payment_status(&saga).await,
+        (Some(PaymentStatus::Captured), Money::from_cents(3000))
+    );
+
+    // A shipment that can't be booked refunds the captured payment
+    shipping.set_fail_on_create(true);
+    let saga_id = coordinator.execute_saga(order().await).await.unwrap();
+    let saga = coordinator.get_saga(saga_id).await.unwrap().unwrap();
+    assert_eq!(saga.state(), SagaState::Failed);
+    assert_eq!(payment_status(&saga).await.0, Some(PaymentStatus::Refunded));
+}
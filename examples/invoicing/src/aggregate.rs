@@ -0,0 +1,280 @@
+//! Invoice aggregate implementation.
+
+use chrono::{DateTime, Utc};
+use common::AggregateId;
+use domain::{Aggregate, CustomerId, Money};
+use event_store::Version;
+use serde::{Deserialize, Serialize};
+
+use crate::events::{InvoiceEvent, InvoiceIssuedData};
+use crate::{InvoiceError, InvoiceStatus};
+
+/// Invoice aggregate root.
+///
+/// An invoice is issued for a fixed amount, paid down by one or more
+/// payments, and closes when paid in full or voided.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Invoice {
+    /// Unique invoice identifier.
+    id: Option<AggregateId>,
+
+    /// Current version for optimistic concurrency.
+    #[serde(default)]
+    version: Version,
+
+    /// The customer billed.
+    customer_id: Option<CustomerId>,
+
+    /// Current status.
+    status: InvoiceStatus,
+
+    /// Total amount due.
+    amount: Money,
+
+    /// Sum of payments received.
+    paid: Money,
+
+    /// When payment is due.
+    due_at: Option<DateTime<Utc>>,
+}
+
+impl Aggregate for Invoice {
+    type Event = InvoiceEvent;
+    type Error = InvoiceError;
+
+    fn aggregate_type() -> &'static str {
+        "Invoice"
+    }
+
+    fn id(&self) -> Option<AggregateId> {
+        self.id
+    }
+
+    fn version(&self) -> Version {
+        self.version
+    }
+
+    fn set_version(&mut self, version: Version) {
+        self.version = version;
+    }
+
+    fn apply(&mut self, event: Self::Event) {
+        match event {
+            InvoiceEvent::InvoiceIssued(data) => self.apply_issued(data),
+            InvoiceEvent::PaymentRecorded(data) => {
+                self.paid += data.amount;
+                if self.outstanding().is_zero() {
+                    self.status = InvoiceStatus::Paid;
+                }
+            }
+            InvoiceEvent::InvoiceVoided(_) => self.status = InvoiceStatus::Voided,
+        }
+    }
+}
+
+// Query methods
+impl Invoice {
+    /// Returns the customer billed.
+    pub fn customer_id(&self) -> Option<CustomerId> {
+        self.customer_id
+    }
+
+    /// Returns the current status.
+    pub fn status(&self) -> InvoiceStatus {
+        self.status
+    }
+
+    /// Returns the total amount due.
+    pub fn amount(&self) -> Money {
+        self.amount
+    }
+
+    /// Returns the sum of payments received.
+    pub fn paid(&self) -> Money {
+        self.paid
+    }
+
+    /// Returns the amount still owed.
+    pub fn outstanding(&self) -> Money {
+        self.amount - self.paid
+    }
+
+    /// Returns when payment is due.
+    pub fn due_at(&self) -> Option<DateTime<Utc>> {
+        self.due_at
+    }
+}
+
+// Command methods (return events)
+impl Invoice {
+    /// Issues the invoice.
+    pub fn issue(
+        &self,
+        invoice_id: AggregateId,
+        customer_id: CustomerId,
+        amount: Money,
+        due_at: DateTime<Utc>,
+    ) -> Result<Vec<InvoiceEvent>, InvoiceError> {
+        if self.id.is_some() {
+            return Err(InvoiceError::AlreadyIssued);
+        }
+        if !amount.is_positive() {
+            return Err(InvoiceError::InvalidAmount { amount });
+        }
+
+        Ok(vec![InvoiceEvent::invoice_issued(
+            invoice_id,
+            customer_id,
+            amount,
+            due_at,
+        )])
+    }
+
+    /// Records a payment against the invoice.
+    pub fn record_payment(
+        &self,
+        amount: Money,
+        reference: Option<String>,
+    ) -> Result<Vec<InvoiceEvent>, InvoiceError> {
+        self.ensure_open()?;
+        if !amount.is_positive() {
+            return Err(InvoiceError::InvalidAmount { amount });
+        }
+        let outstanding = self.outstanding();
+        if amount.currency() != outstanding.currency() {
+            return Err(InvoiceError::CurrencyMismatch {
+                expected: outstanding.currency(),
+                actual: amount.currency(),
+            });
+        }
+        if amount.cents() > outstanding.cents() {
+            return Err(InvoiceError::Overpayment {
+                amount,
+                outstanding,
+            });
+        }
+
+        Ok(vec![InvoiceEvent::payment_recorded(amount, reference)])
+    }
+
+    /// Voids the invoice.
+    pub fn void(&self, reason: String) -> Result<Vec<InvoiceEvent>, InvoiceError> {
+        self.ensure_open()?;
+        Ok(vec![InvoiceEvent::invoice_voided(reason)])
+    }
+
+    fn ensure_open(&self) -> Result<(), InvoiceError> {
+        if self.status != InvoiceStatus::Open {
+            return Err(InvoiceError::NotOpen {
+                status: self.status,
+            });
+        }
+        Ok(())
+    }
+}
+
+// Apply event helpers
+impl Invoice {
+    fn apply_issued(&mut self, data: InvoiceIssuedData) {
+        self.id = Some(data.invoice_id);
+        self.customer_id = Some(data.customer_id);
+        self.status = InvoiceStatus::Open;
+        self.amount = data.amount;
+        self.due_at = Some(data.due_at);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use domain::Currency;
+
+    fn issued(cents: i64) -> Invoice {
+        let mut invoice = Invoice::default();
+        let events = invoice
+            .issue(
+                AggregateId::new(),
+                CustomerId::new(),
+                Money::from_cents(cents),
+                Utc::now(),
+            )
+            .unwrap();
+        invoice.apply_events(events);
+        invoice
+    }
+
+    #[test]
+    fn test_issue_invoice() {
+        let invoice = issued(5000);
+        assert!(invoice.id().is_some());
+        assert_eq!(invoice.status(), InvoiceStatus::Open);
+        assert_eq!(invoice.outstanding(), Money::from_cents(5000));
+    }
+
+    #[test]
+    fn test_issue_validation() {
+        let invoice = Invoice::default();
+        let result = invoice.issue(
+            AggregateId::new(),
+            CustomerId::new(),
+            Money::zero(),
+            Utc::now(),
+        );
+        assert!(matches!(result, Err(InvoiceError::InvalidAmount { .. })));
+
+        let invoice = issued(5000);
+        let result = invoice.issue(
+            AggregateId::new(),
+            CustomerId::new(),
+            Money::from_cents(100),
+            Utc::now(),
+        );
+        assert!(matches!(result, Err(InvoiceError::AlreadyIssued)));
+    }
+
+    #[test]
+    fn test_payments_settle_invoice() {
+        let mut invoice = issued(5000);
+
+        let events = invoice
+            .record_payment(Money::from_cents(2000), Some("TX-1".into()))
+            .unwrap();
+        invoice.apply_events(events);
+        assert_eq!(invoice.status(), InvoiceStatus::Open);
+        assert_eq!(invoice.outstanding(), Money::from_cents(3000));
+
+        let result = invoice.record_payment(Money::from_cents(3500), None);
+        assert!(matches!(result, Err(InvoiceError::Overpayment { .. })));
+        let result = invoice.record_payment(Money::from_cents_in(100, Currency::EUR), None);
+        assert!(matches!(
+            result,
+            Err(InvoiceError::CurrencyMismatch {
+                expected: Currency::USD,
+                actual: Currency::EUR,
+            })
+        ));
+
+        let events = invoice
+            .record_payment(Money::from_cents(3000), None)
+            .unwrap();
+        invoice.apply_events(events);
+        assert_eq!(invoice.status(), InvoiceStatus::Paid);
+        assert!(matches!(
+            invoice.record_payment(Money::from_cents(1), None),
+            Err(InvoiceError::NotOpen {
+                status: InvoiceStatus::Paid
+            })
+        ));
+    }
+
+    #[test]
+    fn test_void_invoice() {
+        let mut invoice = issued(5000);
+        let events = invoice.void("duplicate".into()).unwrap();
+        invoice.apply_events(events);
+
+        assert_eq!(invoice.status(), InvoiceStatus::Voided);
+        assert!(invoice.void("again".into()).is_err());
+        assert!(Invoice::default().void("never issued".into()).is_err());
+    }
+}
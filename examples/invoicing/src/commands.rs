@@ -0,0 +1,103 @@
+//! Invoice commands.
+
+use chrono::{DateTime, Utc};
+use common::AggregateId;
+use domain::{Command, CustomerId, Money};
+
+use crate::Invoice;
+
+/// Command to issue a new invoice.
+#[derive(Debug, Clone)]
+pub struct IssueInvoice {
+    /// The invoice ID (generated if not provided).
+    pub invoice_id: AggregateId,
+
+    /// The customer billed.
+    pub customer_id: CustomerId,
+
+    /// Total amount due.
+    pub amount: Money,
+
+    /// When payment is due.
+    pub due_at: DateTime<Utc>,
+}
+
+impl IssueInvoice {
+    /// Creates a new IssueInvoice command with a generated ID.
+    pub fn new(customer_id: CustomerId, amount: Money, due_at: DateTime<Utc>) -> Self {
+        Self {
+            invoice_id: AggregateId::new(),
+            customer_id,
+            amount,
+            due_at,
+        }
+    }
+}
+
+impl Command for IssueInvoice {
+    type Aggregate = Invoice;
+
+    fn aggregate_id(&self) -> AggregateId {
+        self.invoice_id
+    }
+}
+
+/// Command to record a payment against an invoice.
+#[derive(Debug, Clone)]
+pub struct RecordPayment {
+    /// The invoice being paid.
+    pub invoice_id: AggregateId,
+
+    /// Amount received.
+    pub amount: Money,
+
+    /// External payment reference.
+    pub reference: Option<String>,
+}
+
+impl RecordPayment {
+    /// Creates a new RecordPayment command.
+    pub fn new(invoice_id: AggregateId, amount: Money, reference: Option<String>) -> Self {
+        Self {
+            invoice_id,
+            amount,
+            reference,
+        }
+    }
+}
+
+impl Command for RecordPayment {
+    type Aggregate = Invoice;
+
+    fn aggregate_id(&self) -> AggregateId {
+        self.invoice_id
+    }
+}
+
+/// Command to void an invoice.
+#[derive(Debug, Clone)]
+pub struct VoidInvoice {
+    /// The invoice to void.
+    pub invoice_id: AggregateId,
+
+    /// Why the invoice is voided.
+    pub reason: String,
+}
+
+impl VoidInvoice {
+    /// Creates a new VoidInvoice command.
+    pub fn new(invoice_id: AggregateId, reason: impl Into<String>) -> Self {
+        Self {
+            invoice_id,
+            reason: reason.into(),
+        }
+    }
+}
+
+impl Command for VoidInvoice {
+    type Aggregate = Invoice;
+
+    fn aggregate_id(&self) -> AggregateId {
+        self.invoice_id
+    }
+}
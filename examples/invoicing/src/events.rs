@@ -0,0 +1,108 @@
+//! Invoice domain events.
+
+use chrono::{DateTime, Utc};
+use common::AggregateId;
+use domain::{CustomerId, DomainEvent, Money};
+use serde::{Deserialize, Serialize};
+
+/// Events that can occur on an invoice.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data")]
+pub enum InvoiceEvent {
+    /// Invoice was issued to a customer.
+    InvoiceIssued(InvoiceIssuedData),
+
+    /// A payment was received against the invoice.
+    PaymentRecorded(PaymentRecordedData),
+
+    /// Invoice was voided.
+    InvoiceVoided(InvoiceVoidedData),
+}
+
+impl DomainEvent for InvoiceEvent {
+    fn event_type(&self) -> &'static str {
+        match self {
+            InvoiceEvent::InvoiceIssued(_) => "InvoiceIssued",
+            InvoiceEvent::PaymentRecorded(_) => "InvoicePaymentRecorded",
+            InvoiceEvent::InvoiceVoided(_) => "InvoiceVoided",
+        }
+    }
+}
+
+/// Data for InvoiceIssued event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvoiceIssuedData {
+    /// The invoice ID.
+    pub invoice_id: AggregateId,
+
+    /// The customer billed.
+    pub customer_id: CustomerId,
+
+    /// Total amount due.
+    pub amount: Money,
+
+    /// When payment is due.
+    pub due_at: DateTime<Utc>,
+
+    /// When the invoice was issued.
+    pub issued_at: DateTime<Utc>,
+}
+
+/// Data for PaymentRecorded event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentRecordedData {
+    /// Amount received.
+    pub amount: Money,
+
+    /// External payment reference, e.g. a bank transfer ID.
+    pub reference: Option<String>,
+
+    /// When the payment was recorded.
+    pub recorded_at: DateTime<Utc>,
+}
+
+/// Data for InvoiceVoided event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvoiceVoidedData {
+    /// Why the invoice was voided.
+    pub reason: String,
+
+    /// When the invoice was voided.
+    pub voided_at: DateTime<Utc>,
+}
+
+// Convenience constructors for events
+impl InvoiceEvent {
+    /// Creates an InvoiceIssued event.
+    pub fn invoice_issued(
+        invoice_id: AggregateId,
+        customer_id: CustomerId,
+        amount: Money,
+        due_at: DateTime<Utc>,
+    ) -> Self {
+        InvoiceEvent::InvoiceIssued(InvoiceIssuedData {
+            invoice_id,
+            customer_id,
+            amount,
+            due_at,
+            issued_at: Utc::now(),
+        })
+    }
+
+    /// Creates a PaymentRecorded event.
+    pub fn payment_recorded(amount: Money, reference: Option<String>) -> Self {
+        InvoiceEvent::PaymentRecorded(PaymentRecordedData {
+            amount,
+            reference,
+            recorded_at: Utc::now(),
+        })
+    }
+
+    /// Creates an InvoiceVoided event.
+    pub fn invoice_voided(reason: impl Into<String>) -> Self {
+        InvoiceEvent::InvoiceVoided(InvoiceVoidedData {
+            reason: reason.into(),
+            voided_at: Utc::now(),
+        })
+    }
+}
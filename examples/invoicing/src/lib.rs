@@ -0,0 +1,100 @@
+//! Invoicing, built outside the framework crates as a worked example.
+//!
+//! Orders are the framework's own aggregate; this crate adds an unrelated
+//! one end to end using only the public extension points:
+//! - [`Invoice`] implements [`domain::Aggregate`] with its own events
+//! - Commands implement [`domain::Command`] and run through
+//!   [`domain::CommandHandler`] in [`InvoiceService`]
+//! - [`OutstandingInvoicesView`] implements [`projections::Projection`] and
+//!   is fed by a [`projections::ProjectionProcessor`]
+//! - [`router`] exposes them over HTTP, reporting errors as
+//!   [`api::error::ApiError`]
+//!
+//! Rejections are surfaced with [`domain::DomainError::rejected`], so they
+//! carry invariant codes in decision records like the built-in aggregates.
+//! The crate is a workspace member, so CI keeps it compiling against the
+//! framework as it changes.
+
+mod aggregate;
+mod commands;
+mod events;
+mod projection;
+mod routes;
+mod service;
+
+pub use aggregate::Invoice;
+pub use commands::*;
+pub use events::{InvoiceEvent, InvoiceIssuedData, InvoiceVoidedData, PaymentRecordedData};
+pub use projection::{OutstandingInvoice, OutstandingInvoicesView};
+pub use routes::{InvoiceState, router};
+pub use service::InvoiceService;
+
+use domain::{Currency, Money};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Lifecycle of an invoice.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InvoiceStatus {
+    /// Not issued yet.
+    #[default]
+    Draft,
+    /// Issued and awaiting payment.
+    Open,
+    /// Paid in full.
+    Paid,
+    /// Cancelled; no further payments are accepted.
+    Voided,
+}
+
+impl std::fmt::Display for InvoiceStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            InvoiceStatus::Draft => "Draft",
+            InvoiceStatus::Open => "Open",
+            InvoiceStatus::Paid => "Paid",
+            InvoiceStatus::Voided => "Voided",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Errors that can occur during invoice operations.
+#[derive(Debug, Error)]
+pub enum InvoiceError {
+    /// Invoice was already issued.
+    #[error("Invoice already issued")]
+    AlreadyIssued,
+
+    /// Amounts must be positive.
+    #[error("Invalid amount: {amount}")]
+    InvalidAmount { amount: Money },
+
+    /// The invoice isn't open for the operation.
+    #[error("Invoice is {status}, not Open")]
+    NotOpen { status: InvoiceStatus },
+
+    /// Payment exceeds what is still owed.
+    #[error("Payment of {amount} exceeds outstanding {outstanding}")]
+    Overpayment { amount: Money, outstanding: Money },
+
+    /// Payment is in another currency than the invoice.
+    #[error("Payment in {actual} doesn't match invoice currency {expected}")]
+    CurrencyMismatch {
+        expected: Currency,
+        actual: Currency,
+    },
+}
+
+impl InvoiceError {
+    /// Returns a stable code naming the violated invariant.
+    pub fn code(&self) -> &'static str {
+        match self {
+            InvoiceError::AlreadyIssued => "invoice.already_issued",
+            InvoiceError::InvalidAmount { .. } => "invoice.invalid_amount",
+            InvoiceError::NotOpen { .. } => "invoice.not_open",
+            InvoiceError::Overpayment { .. } => "invoice.overpayment",
+            InvoiceError::CurrencyMismatch { .. } => "invoice.currency_mismatch",
+        }
+    }
+}
@@ -0,0 +1,246 @@
+//! Outstanding invoices read model — open invoices and what is still owed.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use common::AggregateId;
+use domain::{CustomerId, Money};
+use event_store::EventEnvelope;
+use projections::{Projection, ProjectionPosition, ReadModel, Result, read_state};
+use tokio::sync::RwLock;
+
+use crate::InvoiceEvent;
+
+/// An invoice that is issued and not yet paid or voided.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutstandingInvoice {
+    pub invoice_id: AggregateId,
+    pub customer_id: CustomerId,
+    pub amount: Money,
+    pub paid: Money,
+    pub due_at: DateTime<Utc>,
+}
+
+impl OutstandingInvoice {
+    /// Returns the amount still owed.
+    pub fn outstanding(&self) -> Money {
+        self.amount - self.paid
+    }
+}
+
+/// Internal state for the outstanding invoices view.
+struct OutstandingInvoicesState {
+    invoices: HashMap<AggregateId, OutstandingInvoice>,
+    position: ProjectionPosition,
+}
+
+/// Read model view for open invoices.
+///
+/// Invoices enter the view when issued and leave it once paid in full or
+/// voided.
+#[derive(Clone)]
+pub struct OutstandingInvoicesView {
+    state: Arc<RwLock<OutstandingInvoicesState>>,
+}
+
+impl OutstandingInvoicesView {
+    /// Creates a new empty outstanding invoices view.
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(RwLock::new(OutstandingInvoicesState {
+                invoices: HashMap::new(),
+                position: ProjectionPosition::zero(),
+            })),
+        }
+    }
+
+    /// Gets an open invoice by ID.
+    pub async fn get_invoice(&self, invoice_id: AggregateId) -> Option<OutstandingInvoice> {
+        self.state.read().await.invoices.get(&invoice_id).cloned()
+    }
+
+    /// Gets a customer's open invoices, earliest due first.
+    pub async fn get_for_customer(&self, customer_id: CustomerId) -> Vec<OutstandingInvoice> {
+        let state = self.state.read().await;
+        let mut invoices: Vec<_> = state
+            .invoices
+            .values()
+            .filter(|i| i.customer_id == customer_id)
+            .cloned()
+            .collect();
+        invoices.sort_by_key(|i| i.due_at);
+        invoices
+    }
+
+    fn handle_invoice_event(
+        state: &mut OutstandingInvoicesState,
+        invoice_id: AggregateId,
+        event: InvoiceEvent,
+    ) {
+        match event {
+            InvoiceEvent::InvoiceIssued(data) => {
+                state.invoices.insert(
+                    invoice_id,
+                    OutstandingInvoice {
+                        invoice_id,
+                        customer_id: data.customer_id,
+                        amount: data.amount,
+                        paid: Money::zero(),
+                        due_at: data.due_at,
+                    },
+                );
+            }
+            InvoiceEvent::PaymentRecorded(data) => {
+                let Some(invoice) = state.invoices.get_mut(&invoice_id) else {
+                    return;
+                };
+                invoice.paid += data.amount;
+                if !invoice.outstanding().is_positive() {
+                    state.invoices.remove(&invoice_id);
+                }
+            }
+            InvoiceEvent::InvoiceVoided(_) => {
+                state.invoices.remove(&invoice_id);
+            }
+        }
+    }
+}
+
+impl Default for OutstandingInvoicesView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Projection for OutstandingInvoicesView {
+    fn name(&self) -> &'static str {
+        "OutstandingInvoicesView"
+    }
+
+    async fn handle(&self, event: &EventEnvelope) -> Result<()> {
+        let mut state = self.state.write().await;
+
+        if event.aggregate_type == "Invoice" {
+            let invoice_event: InvoiceEvent = serde_json::from_value(event.payload.clone())?;
+            Self::handle_invoice_event(&mut state, event.aggregate_id, invoice_event);
+        }
+
+        state.position = state.position.advance_to(event);
+        Ok(())
+    }
+
+    async fn position(&self) -> ProjectionPosition {
+        self.state.read().await.position
+    }
+
+    async fn reset(&self) -> Result<()> {
+        let mut state = self.state.write().await;
+        state.invoices.clear();
+        state.position = ProjectionPosition::zero();
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ReadModel for OutstandingInvoicesView {
+    fn name(&self) -> &'static str {
+        "OutstandingInvoicesView"
+    }
+
+    async fn count(&self) -> Result<usize> {
+        let s = read_state(ReadModel::name(self), &self.state).await?;
+        Ok(s.invoices.len())
+    }
+
+    async fn memory_usage(&self) -> Result<usize> {
+        let s = read_state(ReadModel::name(self), &self.state).await?;
+        Ok(s.invoices.capacity() * (size_of::<AggregateId>() + size_of::<OutstandingInvoice>()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use domain::DomainEvent;
+
+    async fn handle(
+        view: &OutstandingInvoicesView,
+        id: AggregateId,
+        version: i64,
+        event: InvoiceEvent,
+    ) {
+        let envelope = EventEnvelope::builder()
+            .aggregate_id(id)
+            .aggregate_type("Invoice")
+            .event_type(event.event_type())
+            .version(event_store::Version::new(version))
+            .payload(&event)
+            .unwrap()
+            .build();
+        view.handle(&envelope).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_tracks_open_invoices_until_settled() {
+        let view = OutstandingInvoicesView::new();
+        let customer_id = CustomerId::new();
+        let paid_id = AggregateId::new();
+        let voided_id = AggregateId::new();
+
+        for id in [paid_id, voided_id] {
+            let event =
+                InvoiceEvent::invoice_issued(id, customer_id, Money::from_cents(1000), Utc::now());
+            handle(&view, id, 1, event).await;
+        }
+        handle(
+            &view,
+            paid_id,
+            2,
+            InvoiceEvent::payment_recorded(Money::from_cents(400), None),
+        )
+        .await;
+
+        let open = view.get_invoice(paid_id).await.unwrap();
+        assert_eq!(open.outstanding(), Money::from_cents(600));
+        assert_eq!(view.get_for_customer(customer_id).await.len(), 2);
+
+        handle(
+            &view,
+            paid_id,
+            3,
+            InvoiceEvent::payment_recorded(Money::from_cents(600), None),
+        )
+        .await;
+        handle(
+            &view,
+            voided_id,
+            2,
+            InvoiceEvent::invoice_voided("duplicate"),
+        )
+        .await;
+
+        assert!(view.get_for_customer(customer_id).await.is_empty());
+        assert_eq!(ReadModel::count(&view).await.unwrap(), 0);
+        assert_eq!(view.position().await.events_processed, 5);
+    }
+
+    #[tokio::test]
+    async fn test_ignores_other_aggregates() {
+        let view = OutstandingInvoicesView::new();
+        let envelope = EventEnvelope::builder()
+            .aggregate_id(AggregateId::new())
+            .aggregate_type("Order")
+            .event_type("OrderCreated")
+            .version(event_store::Version::first())
+            .payload_raw(serde_json::json!({}))
+            .build();
+
+        view.handle(&envelope).await.unwrap();
+
+        assert_eq!(ReadModel::count(&view).await.unwrap(), 0);
+        assert_eq!(view.position().await.events_processed, 1);
+    }
+}
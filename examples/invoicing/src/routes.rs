@@ -0,0 +1,254 @@
+//! Invoice endpoints.
+//!
+//! The router carries its own state, so it can be served alone or merged
+//! into the main API router.
+
+use std::sync::Arc;
+
+use api::error::ApiError;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use chrono::{DateTime, Duration, Utc};
+use common::AggregateId;
+use domain::{CustomerId, DecisionLog, Money};
+use event_store::EventStore;
+use projections::{Projection, ProjectionProcessor};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Invoice, InvoiceService, IssueInvoice, OutstandingInvoice, OutstandingInvoicesView,
+    RecordPayment, VoidInvoice,
+};
+
+/// Days until payment is due when a request doesn't say.
+const DEFAULT_PAYMENT_TERMS_DAYS: i64 = 30;
+
+/// Shared state for invoice handlers.
+pub struct InvoiceState<S: EventStore> {
+    pub service: InvoiceService<S>,
+    pub outstanding: OutstandingInvoicesView,
+    pub projection_processor: ProjectionProcessor<S>,
+}
+
+impl<S: EventStore + Clone + 'static> InvoiceState<S> {
+    /// Creates state reading and writing invoices in `store`.
+    pub fn new(store: S, decisions: DecisionLog) -> Self {
+        let outstanding = OutstandingInvoicesView::new();
+        let mut processor = ProjectionProcessor::new(store.clone());
+        processor.register(Box::new(outstanding.clone()) as Box<dyn Projection>);
+
+        Self {
+            service: InvoiceService::new(store).with_decision_log(decisions),
+            outstanding,
+            projection_processor: processor,
+        }
+    }
+}
+
+/// Builds the invoice routes.
+pub fn router<S: EventStore + Clone + 'static>(state: Arc<InvoiceState<S>>) -> Router {
+    Router::new()
+        .route("/invoices", post(issue::<S>))
+        .route("/invoices/{id}", get(get_invoice::<S>))
+        .route("/invoices/{id}/payments", post(record_payment::<S>))
+        .route("/invoices/{id}/void", post(void::<S>))
+        .route(
+            "/customers/{id}/invoices/outstanding",
+            get(outstanding::<S>),
+        )
+        .with_state(state)
+}
+
+// -- Request types --
+
+#[derive(Deserialize)]
+pub struct IssueInvoiceRequest {
+    pub customer_id: String,
+    pub amount_cents: i64,
+    pub due_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize)]
+pub struct RecordPaymentRequest {
+    pub amount_cents: i64,
+    pub reference: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct VoidInvoiceRequest {
+    pub reason: String,
+}
+
+// -- Response types --
+
+#[derive(Serialize)]
+pub struct InvoiceResponse {
+    pub id: String,
+    pub customer_id: Option<String>,
+    pub status: String,
+    pub amount_cents: i64,
+    pub paid_cents: i64,
+    pub outstanding_cents: i64,
+    pub due_at: Option<DateTime<Utc>>,
+    pub version: i64,
+}
+
+impl From<&Invoice> for InvoiceResponse {
+    fn from(invoice: &Invoice) -> Self {
+        use domain::Aggregate;
+
+        Self {
+            id: invoice.id().map(|id| id.to_string()).unwrap_or_default(),
+            customer_id: invoice.customer_id().map(|id| id.to_string()),
+            status: invoice.status().to_string(),
+            amount_cents: invoice.amount().cents(),
+            paid_cents: invoice.paid().cents(),
+            outstanding_cents: invoice.outstanding().cents(),
+            due_at: invoice.due_at(),
+            version: invoice.version().as_i64(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct OutstandingInvoiceResponse {
+    pub invoice_id: String,
+    pub outstanding_cents: i64,
+    pub due_at: DateTime<Utc>,
+}
+
+impl From<OutstandingInvoice> for OutstandingInvoiceResponse {
+    fn from(invoice: OutstandingInvoice) -> Self {
+        Self {
+            invoice_id: invoice.invoice_id.to_string(),
+            outstanding_cents: invoice.outstanding().cents(),
+            due_at: invoice.due_at,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct OutstandingResponse {
+    pub customer_id: String,
+    pub total_cents: i64,
+    pub invoices: Vec<OutstandingInvoiceResponse>,
+}
+
+// -- Handlers --
+
+/// POST /invoices — issue an invoice.
+#[tracing::instrument(skip(state, req))]
+pub async fn issue<S: EventStore + Clone + 'static>(
+    State(state): State<Arc<InvoiceState<S>>>,
+    Json(req): Json<IssueInvoiceRequest>,
+) -> Result<(StatusCode, Json<InvoiceResponse>), ApiError> {
+    let customer_id = parse_customer_id(&req.customer_id)?;
+    let due_at = req
+        .due_at
+        .unwrap_or_else(|| Utc::now() + Duration::days(DEFAULT_PAYMENT_TERMS_DAYS));
+
+    let result = state
+        .service
+        .issue(IssueInvoice::new(
+            customer_id,
+            Money::from_cents(req.amount_cents),
+            due_at,
+        ))
+        .await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(InvoiceResponse::from(&result.aggregate)),
+    ))
+}
+
+/// GET /invoices/:id — get an invoice.
+#[tracing::instrument(skip(state))]
+pub async fn get_invoice<S: EventStore + Clone + 'static>(
+    State(state): State<Arc<InvoiceState<S>>>,
+    Path(id): Path<String>,
+) -> Result<Json<InvoiceResponse>, ApiError> {
+    let invoice_id = parse_invoice_id(&id)?;
+    let invoice = state
+        .service
+        .get_invoice(invoice_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Invoice {id} not found")))?;
+
+    Ok(Json(InvoiceResponse::from(&invoice)))
+}
+
+/// POST /invoices/:id/payments — record a payment.
+#[tracing::instrument(skip(state, req))]
+pub async fn record_payment<S: EventStore + Clone + 'static>(
+    State(state): State<Arc<InvoiceState<S>>>,
+    Path(id): Path<String>,
+    Json(req): Json<RecordPaymentRequest>,
+) -> Result<Json<InvoiceResponse>, ApiError> {
+    let invoice_id = parse_invoice_id(&id)?;
+    let result = state
+        .service
+        .record_payment(RecordPayment::new(
+            invoice_id,
+            Money::from_cents(req.amount_cents),
+            req.reference,
+        ))
+        .await?;
+
+    Ok(Json(InvoiceResponse::from(&result.aggregate)))
+}
+
+/// POST /invoices/:id/void — void an invoice.
+#[tracing::instrument(skip(state, req))]
+pub async fn void<S: EventStore + Clone + 'static>(
+    State(state): State<Arc<InvoiceState<S>>>,
+    Path(id): Path<String>,
+    Json(req): Json<VoidInvoiceRequest>,
+) -> Result<Json<InvoiceResponse>, ApiError> {
+    let invoice_id = parse_invoice_id(&id)?;
+    let result = state
+        .service
+        .void(VoidInvoice::new(invoice_id, req.reason))
+        .await?;
+
+    Ok(Json(InvoiceResponse::from(&result.aggregate)))
+}
+
+/// GET /customers/:id/invoices/outstanding — list a customer's open invoices.
+#[tracing::instrument(skip(state))]
+pub async fn outstanding<S: EventStore + Clone + 'static>(
+    State(state): State<Arc<InvoiceState<S>>>,
+    Path(id): Path<String>,
+) -> Result<Json<OutstandingResponse>, ApiError> {
+    let customer_id = parse_customer_id(&id)?;
+
+    // Run catch-up to ensure the read model includes latest events
+    state
+        .projection_processor
+        .run_catch_up()
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+
+    let invoices = state.outstanding.get_for_customer(customer_id).await;
+    let total_cents = invoices.iter().map(|i| i.outstanding().cents()).sum();
+
+    Ok(Json(OutstandingResponse {
+        customer_id: customer_id.to_string(),
+        total_cents,
+        invoices: invoices.into_iter().map(Into::into).collect(),
+    }))
+}
+
+fn parse_invoice_id(id: &str) -> Result<AggregateId, ApiError> {
+    let uuid = uuid::Uuid::parse_str(id)
+        .map_err(|e| ApiError::BadRequest(format!("Invalid ID format: {e}")))?;
+    Ok(AggregateId::from(uuid))
+}
+
+fn parse_customer_id(id: &str) -> Result<CustomerId, ApiError> {
+    let uuid = uuid::Uuid::parse_str(id)
+        .map_err(|e| ApiError::BadRequest(format!("Invalid customer_id: {e}")))?;
+    Ok(CustomerId::from_uuid(uuid))
+}
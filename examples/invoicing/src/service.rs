@@ -0,0 +1,142 @@
+//! Invoice service providing a simplified API for invoice operations.
+
+use common::AggregateId;
+use domain::{Command, CommandHandler, CommandResult, DecisionLog, DomainError};
+use event_store::EventStore;
+
+use crate::{Invoice, InvoiceError, IssueInvoice, RecordPayment, VoidInvoice};
+
+impl From<InvoiceError> for DomainError {
+    fn from(e: InvoiceError) -> Self {
+        DomainError::rejected::<Invoice>(e.code(), e)
+    }
+}
+
+/// Service for managing invoices.
+pub struct InvoiceService<S: EventStore> {
+    handler: CommandHandler<S, Invoice>,
+}
+
+impl<S: EventStore> InvoiceService<S> {
+    /// Creates a new invoice service with the given event store.
+    pub fn new(store: S) -> Self {
+        Self {
+            handler: CommandHandler::new(store),
+        }
+    }
+
+    /// Records command decisions in `log`.
+    pub fn with_decision_log(mut self, log: DecisionLog) -> Self {
+        self.handler = self.handler.with_decision_log(log);
+        self
+    }
+
+    /// Issues a new invoice.
+    #[tracing::instrument(skip(self))]
+    pub async fn issue(&self, cmd: IssueInvoice) -> Result<CommandResult<Invoice>, DomainError> {
+        self.handler
+            .execute_named(cmd.name(), cmd.invoice_id, |invoice| {
+                invoice.issue(cmd.invoice_id, cmd.customer_id, cmd.amount, cmd.due_at)
+            })
+            .await
+    }
+
+    /// Records a payment against an invoice.
+    #[tracing::instrument(skip(self))]
+    pub async fn record_payment(
+        &self,
+        cmd: RecordPayment,
+    ) -> Result<CommandResult<Invoice>, DomainError> {
+        let command = cmd.name();
+        let RecordPayment {
+            invoice_id,
+            amount,
+            reference,
+        } = cmd;
+        self.handler
+            .execute_named(command, invoice_id, |invoice| {
+                invoice.record_payment(amount, reference)
+            })
+            .await
+    }
+
+    /// Voids an invoice.
+    #[tracing::instrument(skip(self))]
+    pub async fn void(&self, cmd: VoidInvoice) -> Result<CommandResult<Invoice>, DomainError> {
+        let command = cmd.name();
+        self.handler
+            .execute_named(command, cmd.invoice_id, |invoice| invoice.void(cmd.reason))
+            .await
+    }
+
+    /// Loads an invoice, returning None if it was never issued.
+    pub async fn get_invoice(
+        &self,
+        invoice_id: AggregateId,
+    ) -> Result<Option<Invoice>, DomainError> {
+        self.handler.load_existing(invoice_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use domain::{CustomerId, DecisionOutcome, Money};
+    use event_store::InMemoryEventStore;
+
+    use crate::InvoiceStatus;
+
+    #[tokio::test]
+    async fn test_issue_and_pay() {
+        let service = InvoiceService::new(InMemoryEventStore::new());
+        let cmd = IssueInvoice::new(CustomerId::new(), Money::from_cents(4000), Utc::now());
+        let invoice_id = cmd.invoice_id;
+        service.issue(cmd).await.unwrap();
+
+        service
+            .record_payment(RecordPayment::new(
+                invoice_id,
+                Money::from_cents(4000),
+                None,
+            ))
+            .await
+            .unwrap();
+
+        let invoice = service.get_invoice(invoice_id).await.unwrap().unwrap();
+        assert_eq!(invoice.status(), InvoiceStatus::Paid);
+        assert!(
+            service
+                .get_invoice(AggregateId::new())
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rejections_carry_invariant_codes() {
+        let log = DecisionLog::with_rejection_buffer(10);
+        let service = InvoiceService::new(InMemoryEventStore::new()).with_decision_log(log.clone());
+        let cmd = IssueInvoice::new(CustomerId::new(), Money::from_cents(1000), Utc::now());
+        let invoice_id = cmd.invoice_id;
+        service.issue(cmd).await.unwrap();
+
+        let err = service
+            .record_payment(RecordPayment::new(
+                invoice_id,
+                Money::from_cents(1500),
+                None,
+            ))
+            .await
+            .unwrap_err();
+        assert_eq!(err.invariant(), Some("invoice.overpayment"));
+
+        let rejections = log.recent_rejections(10);
+        assert_eq!(rejections.len(), 1);
+        assert_eq!(rejections[0].outcome, DecisionOutcome::Rejected);
+        assert_eq!(rejections[0].command, Some("RecordPayment"));
+        assert_eq!(rejections[0].aggregate_type, "Invoice");
+        assert_eq!(rejections[0].invariant, Some("invoice.overpayment"));
+    }
+}
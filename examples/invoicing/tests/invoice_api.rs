@@ -0,0 +1,190 @@
+//! HTTP tests for the invoice example, served alone and merged into the main API.
+
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use domain::DecisionLog;
+use event_store::InMemoryEventStore;
+use invoicing::InvoiceState;
+use tower::ServiceExt;
+
+fn setup() -> axum::Router {
+    let state = InvoiceState::new(InMemoryEventStore::new(), DecisionLog::new());
+    invoicing::router(Arc::new(state))
+}
+
+async fn send(
+    app: &axum::Router,
+    method: &str,
+    uri: &str,
+    body: Option<serde_json::Value>,
+) -> (StatusCode, serde_json::Value) {
+    // As the gateway in front of the API identifies an operator
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("x-principal-role", "operations");
+    let request = match body {
+        Some(body) => request
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string())),
+        None => request.body(Body::empty()),
+    }
+    .unwrap();
+
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json = serde_json::from_slice(&bytes).unwrap_or(serde_json::Value::Null);
+    (status, json)
+}
+
+#[tokio::test]
+async fn test_invoice_lifecycle() {
+    let app = setup();
+    let customer_id = uuid::Uuid::new_v4();
+
+    let (status, invoice) = send(
+        &app,
+        "POST",
+        "/invoices",
+        Some(serde_json::json!({ "customer_id": customer_id, "amount_cents": 5000 })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(invoice["status"], "Open");
+    let invoice_id = invoice["id"].as_str().unwrap().to_string();
+
+    let (status, invoice) = send(
+        &app,
+        "POST",
+        &format!("/invoices/{invoice_id}/payments"),
+        Some(serde_json::json!({ "amount_cents": 2000, "reference": "TX-1" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(invoice["outstanding_cents"], 3000);
+
+    let (status, outstanding) = send(
+        &app,
+        "GET",
+        &format!("/customers/{customer_id}/invoices/outstanding"),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(outstanding["total_cents"], 3000);
+    assert_eq!(outstanding["invoices"][0]["invoice_id"], invoice_id);
+
+    let (status, invoice) = send(
+        &app,
+        "POST",
+        &format!("/invoices/{invoice_id}/payments"),
+        Some(serde_json::json!({ "amount_cents": 3000 })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(invoice["status"], "Paid");
+    assert_eq!(invoice["version"], 3);
+
+    let (_, outstanding) = send(
+        &app,
+        "GET",
+        &format!("/customers/{customer_id}/invoices/outstanding"),
+        None,
+    )
+    .await;
+    assert_eq!(outstanding["total_cents"], 0);
+    assert!(outstanding["invoices"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_invoice_errors_map_to_http_statuses() {
+    let app = setup();
+
+    let (status, _) = send(
+        &app,
+        "GET",
+        &format!("/invoices/{}", uuid::Uuid::new_v4()),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/invoices",
+        Some(serde_json::json!({ "customer_id": "not-a-uuid", "amount_cents": 100 })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (_, invoice) = send(
+        &app,
+        "POST",
+        "/invoices",
+        Some(serde_json::json!({ "customer_id": uuid::Uuid::new_v4(), "amount_cents": 100 })),
+    )
+    .await;
+    let invoice_id = invoice["id"].as_str().unwrap();
+
+    let (status, body) = send(
+        &app,
+        "POST",
+        &format!("/invoices/{invoice_id}/void"),
+        Some(serde_json::json!({ "reason": "duplicate" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["status"], "Voided");
+
+    let (status, body) = send(
+        &app,
+        "POST",
+        &format!("/invoices/{invoice_id}/payments"),
+        Some(serde_json::json!({ "amount_cents": 100 })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["error"].as_str().unwrap().contains("Voided"));
+}
+
+#[tokio::test]
+async fn test_router_merges_into_main_api() {
+    let store = InMemoryEventStore::new();
+    let (state, processor, _) = api::create_default_state_with_options(
+        store.clone(),
+        api::StateOptions {
+            rejection_buffer: Some(10),
+            ..Default::default()
+        },
+    );
+    let metrics_handle = metrics_exporter_prometheus::PrometheusBuilder::new()
+        .build_recorder()
+        .handle();
+    let invoices = InvoiceState::new(store, state.decisions.clone());
+    let app = api::create_app(state, metrics_handle, processor)
+        .merge(invoicing::router(Arc::new(invoices)));
+
+    let (status, _) = send(&app, "GET", "/health", None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/invoices",
+        Some(serde_json::json!({ "customer_id": uuid::Uuid::new_v4(), "amount_cents": 0 })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Invoice rejections land in the shared decision log
+    let (status, rejections) = send(&app, "GET", "/admin/decisions/rejections", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(rejections[0]["aggregate_type"], "Invoice");
+    assert_eq!(rejections[0]["invariant"], "invoice.invalid_amount");
+}
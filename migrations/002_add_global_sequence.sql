@@ -0,0 +1,7 @@
+-- Global log position
+-- Gives every event a monotonically increasing position across all aggregates,
+-- used by projections to track exactly where they are in the log
+
+ALTER TABLE events ADD COLUMN sequence BIGSERIAL NOT NULL;
+
+CREATE UNIQUE INDEX idx_events_sequence ON events(sequence);
@@ -0,0 +1,9 @@
+-- Downstream consumer offsets
+-- Last global sequence each named external consumer has processed,
+-- compared against the head of the log to report consumer lag
+
+CREATE TABLE consumer_offsets (
+    consumer TEXT PRIMARY KEY,
+    sequence BIGINT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL
+);
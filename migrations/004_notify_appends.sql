@@ -0,0 +1,17 @@
+-- Append notifications
+-- Announces the highest global sequence of each insert on the
+-- events_appended channel, so readers can catch up without polling.
+-- Notifications are sent on commit; rolled-back appends send nothing
+
+CREATE FUNCTION notify_events_appended() RETURNS trigger AS $$
+BEGIN
+    PERFORM pg_notify('events_appended', (SELECT MAX(sequence) FROM new_events)::text);
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER events_appended
+    AFTER INSERT ON events
+    REFERENCING NEW TABLE AS new_events
+    FOR EACH STATEMENT
+    EXECUTE FUNCTION notify_events_appended();
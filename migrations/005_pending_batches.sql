@@ -0,0 +1,14 @@
+-- Pending event batches
+-- Appends staged for a later commit (e.g. awaiting external approval).
+-- They live outside the events table, so loads, queries, and projections
+-- never see them; committing moves the events into the log.
+
+CREATE TABLE pending_batches (
+    batch_id UUID PRIMARY KEY,
+    aggregate_id UUID NOT NULL,
+    expected_version BIGINT,
+    events JSONB NOT NULL,
+    staged_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX idx_pending_batches_staged_at ON pending_batches (staged_at);
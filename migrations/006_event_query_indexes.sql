@@ -0,0 +1,18 @@
+-- Composite indexes for common event query shapes
+-- Projections replay one event type over a time range, and audits read one
+-- aggregate type over a time range. The single-column indexes narrow only
+-- the first filter and leave the range to be checked row by row. Queries
+-- are ordered by (timestamp, version), so both indexes end with those.
+--
+-- Building these locks the events table against writes for the duration;
+-- on a large table, create them CONCURRENTLY by hand before migrating.
+
+CREATE INDEX IF NOT EXISTS idx_events_event_type_timestamp
+    ON events (event_type, timestamp, version);
+
+CREATE INDEX IF NOT EXISTS idx_events_aggregate_type_timestamp
+    ON events (aggregate_type, timestamp, version);
+
+-- Superseded by idx_events_event_type_timestamp, which leads with the same
+-- column
+DROP INDEX IF EXISTS idx_events_event_type;
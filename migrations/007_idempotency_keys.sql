@@ -0,0 +1,18 @@
+-- Idempotency keys
+-- Each key records the hash of the request that claimed it and, once that
+-- request finished, its response, so retries with the same key replay the
+-- response instead of running the command again. A NULL status means the
+-- request is still being handled. Expired keys are deleted by the
+-- idempotency_cleanup maintenance job.
+
+CREATE TABLE idempotency_keys (
+    key TEXT PRIMARY KEY,
+    request_hash TEXT NOT NULL,
+    status SMALLINT,
+    content_type TEXT,
+    body BYTEA,
+    created_at TIMESTAMPTZ NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX idx_idempotency_keys_expires_at ON idempotency_keys (expires_at);
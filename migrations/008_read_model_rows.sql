@@ -0,0 +1,20 @@
+-- Read model rows
+-- Rows of Postgres-backed views, stored as JSON per view and key, and the
+-- log position each view's rows reflect. A group commit writes both in one
+-- transaction, so a view resumes from exactly the events it last stored.
+
+CREATE TABLE read_model_rows (
+    view TEXT NOT NULL,
+    key TEXT NOT NULL,
+    row JSONB NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (view, key)
+);
+
+CREATE TABLE read_model_positions (
+    view TEXT PRIMARY KEY,
+    last_sequence BIGINT NOT NULL,
+    events_processed BIGINT NOT NULL,
+    last_event_id UUID,
+    updated_at TIMESTAMPTZ NOT NULL
+);
@@ -0,0 +1,13 @@
+-- Event annotations
+-- Notes operators attach to past events, e.g. while reviewing an incident.
+-- Kept beside the events table so the log itself is never modified.
+
+CREATE TABLE event_annotations (
+    annotation_id UUID PRIMARY KEY,
+    event_id UUID NOT NULL,
+    author TEXT NOT NULL,
+    note TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX idx_event_annotations_event_id ON event_annotations (event_id);
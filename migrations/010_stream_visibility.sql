@@ -0,0 +1,9 @@
+-- Stream visibility overrides
+-- Streams listed here are shown more or less widely in feeds and exports
+-- than their aggregate type's default. Streams without a row use the default.
+
+CREATE TABLE stream_visibility (
+    aggregate_id UUID PRIMARY KEY,
+    visibility TEXT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
@@ -0,0 +1,8 @@
+-- Idempotency key leases
+-- A key whose request is still running (NULL status) is held for a lease
+-- counted from claimed_at; once the lease runs out, e.g. because the process
+-- stopped mid-request, a retry may claim the key again instead of waiting
+-- for it to expire. Keys are scoped by the API to the caller that sent them,
+-- so the key column holds the caller's scope as well as their key.
+
+ALTER TABLE idempotency_keys RENAME COLUMN created_at TO claimed_at;
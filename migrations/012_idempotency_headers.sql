@@ -0,0 +1,13 @@
+-- Idempotency response headers
+-- Replayed responses carry every header the first response had, not just
+-- its content type, so a retried create still gets its Location and a
+-- read-your-writes client its projection position. Headers are kept as a
+-- JSON array of [name, value] pairs in the order they were sent.
+
+ALTER TABLE idempotency_keys ADD COLUMN headers JSONB;
+
+UPDATE idempotency_keys
+SET headers = jsonb_build_array(jsonb_build_array('content-type', content_type))
+WHERE content_type IS NOT NULL;
+
+ALTER TABLE idempotency_keys DROP COLUMN content_type;